@@ -1,6 +1,6 @@
 #!/bin/bash
 
-time ./target/release/main -iSpectralPath -o "" ./prism.ron
+./target/release/main bench ./scenes/prism.ron
 
 # run 1
 ### fma: off
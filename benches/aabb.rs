@@ -0,0 +1,50 @@
+//! Compares the iterator-based [Aabb] helpers against the join-based loops they replace.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use geometry::{Aabb, Float, Ray, Vector3};
+use std::time::Duration;
+
+fn cubes() -> Vec<Aabb> {
+    let random = || fastrand::f32() as Float;
+
+    (0..4096)
+        .map(|i| {
+            let min = Vector3::new(random(), random(), random()) * i as Float;
+            Aabb::new(min, min + Vector3::one())
+        })
+        .collect()
+}
+
+fn bench_union(c: &mut Criterion) {
+    let cubes = cubes();
+    let mut group = c.benchmark_group("Union of cubes");
+
+    group.warm_up_time(Duration::from_secs(5));
+
+    group.bench_function("Join fold", |b| {
+        b.iter(|| {
+            black_box(&cubes)
+                .iter()
+                .fold(Aabb::empty(), |acc, next| acc.join(next))
+        })
+    });
+
+    group.bench_function("Union all", |b| {
+        b.iter(|| Aabb::union_all(black_box(&cubes).iter().copied()))
+    });
+}
+
+fn bench_range(c: &mut Criterion) {
+    let cube = Aabb::default();
+    let ray = Ray::new_fast(Vector3::new(-2.0, 0.1, 0.2), Vector3::unit_x());
+    let mut group = c.benchmark_group("Ray range in cube");
+
+    group.warm_up_time(Duration::from_secs(5));
+
+    group.bench_function("Intersect range", |b| {
+        b.iter(|| black_box(&cube).intersect_range(black_box(&ray)))
+    });
+}
+
+criterion_group!(benches, bench_union, bench_range);
+criterion_main!(benches);
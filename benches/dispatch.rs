@@ -0,0 +1,80 @@
+//! Compares dynamic (`typetag`) and static (enum) dispatch of BxDFs and geometries.
+//!
+//! Run once with the default features and once with `--features static-dispatch`:
+//! ```sh
+//! cargo bench --bench dispatch
+//! cargo bench --bench dispatch --features static-dispatch
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use geometry::Ray;
+use rust_v::bxdf::Type;
+use rust_v::samplers::{Sample, Sampler};
+use rust_v::serialization::Serialization;
+use std::time::Duration;
+use ultraviolet::{Vec2, Vec3};
+
+fn bench_dispatch(c: &mut Criterion) {
+    let content = std::fs::read_to_string("./scenes/cornell.ron").expect("Could not read scene");
+    let serialization: Serialization = ron::from_str(&content).expect("Could not parse scene");
+
+    let mut scene = serialization.scene;
+    scene.init();
+
+    let sampler = Sampler::Random;
+    let rays: Vec<Ray> = (0..1024)
+        .map(|_| {
+            let d = sampler.get_3d() * 2.0 - Vec3::one();
+            Ray::new_fast(Vec3::new(0.0, 0.0, 4.0), (d - Vec3::unit_z()).normalized())
+        })
+        .collect();
+    let hits: Vec<_> = rays.iter().filter_map(|r| scene.intersect(r)).collect();
+
+    let mut group = c.benchmark_group("Dispatch");
+    group.warm_up_time(Duration::from_secs(5));
+
+    group.bench_function("Intersect", |b| {
+        b.iter(|| {
+            rays.iter()
+                .filter(|r| scene.intersect(black_box(r)).is_some())
+                .count()
+        })
+    });
+
+    group.bench_function("BSDF Sample", |b| {
+        let sample = Sample::new(0.5, Vec2::broadcast(0.5));
+
+        b.iter(|| {
+            hits.iter()
+                .filter_map(|h| {
+                    h.object.bsdf().sample(
+                        h.frame(),
+                        -h.ray.direction,
+                        Type::ALL,
+                        black_box(sample),
+                    )
+                })
+                .count()
+        })
+    });
+
+    group.bench_function("BSDF Evaluate", |b| {
+        b.iter(|| {
+            hits.iter()
+                .map(|h| {
+                    h.object.bsdf().evaluate(
+                        h.frame(),
+                        h.normal,
+                        -h.ray.direction,
+                        black_box(Type::ALL),
+                    )
+                })
+                .for_each(|spectrum| {
+                    black_box(spectrum);
+                })
+        })
+    });
+}
+
+criterion_group!(benches, bench_dispatch);
+criterion_main!(benches);
@@ -0,0 +1,89 @@
+//! Compares intersecting tiny meshes through their BVH against testing all of their faces, to
+//! pick the default [threshold](geometry::DEFAULT_BVH_THRESHOLD) between both.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use geometry::{Aabb, Face, Intersectable, Mesh, Ray, ShadingMode, Vertex};
+use std::time::Duration;
+use ultraviolet::{Vec2, Vec3};
+
+/// Creates a unit square in the `xy` plane, triangulated into `2 * n * n` faces.
+fn grid(n: u32, threshold: usize) -> Mesh {
+    let mut vertices = Vec::new();
+    for y in 0..=n {
+        for x in 0..=n {
+            vertices.push(Vertex {
+                position: Vec3::new(x as f32 / n as f32, y as f32 / n as f32, 0.0),
+                normal: Vec3::unit_z(),
+                uv: Vec2::zero(),
+            });
+        }
+    }
+
+    let mut faces = Vec::new();
+    for y in 0..n {
+        for x in 0..n {
+            let i = x + y * (n + 1);
+            faces.push(Face::new((i, i + 1, i + n + 2), Vec3::unit_z()));
+            faces.push(Face::new((i, i + n + 2, i + n + 1), Vec3::unit_z()));
+        }
+    }
+
+    let mut mesh = Mesh::new(vertices, faces, Aabb::empty(), ShadingMode::Flat, None);
+    geometry::with_bvh_threshold(threshold, || {
+        mesh.update_bounds().build_bvh();
+    });
+
+    mesh
+}
+
+fn rays() -> Vec<Ray> {
+    (0..1024)
+        .map(|_| {
+            let origin = Vec3::new(fastrand::f32(), fastrand::f32(), 1.0);
+            let target = Vec3::new(fastrand::f32(), fastrand::f32(), 0.0) * 1.5;
+            Ray::new_fast(origin, (target - origin).normalized())
+        })
+        .collect()
+}
+
+fn hits(mesh: &Mesh, rays: &[Ray]) -> usize {
+    rays.iter()
+        .filter(|r| mesh.intersect(black_box(r)).is_some())
+        .count()
+}
+
+fn bench_intersect(c: &mut Criterion) {
+    let rays = rays();
+    let mut group = c.benchmark_group("Tiny mesh intersection");
+
+    group.warm_up_time(Duration::from_secs(3));
+
+    for &n in &[1, 2, 3, 4, 6, 8] {
+        let faces = 2 * n * n;
+        let linear = grid(n, usize::MAX);
+        let tree = grid(n, 0);
+
+        group.bench_with_input(BenchmarkId::new("Linear", faces), &linear, |b, mesh| {
+            b.iter(|| hits(mesh, &rays))
+        });
+
+        group.bench_with_input(BenchmarkId::new("BVH", faces), &tree, |b, mesh| {
+            b.iter(|| hits(mesh, &rays))
+        });
+    }
+}
+
+fn bench_build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Tiny mesh construction");
+
+    for &n in &[1, 2, 3, 4, 6, 8] {
+        let faces = 2 * n * n;
+
+        group.bench_with_input(BenchmarkId::new("BVH", faces), &n, |b, &n| {
+            b.iter(|| grid(black_box(n), 0))
+        });
+    }
+}
+
+criterion_group!(benches, bench_intersect, bench_build);
+criterion_main!(benches);
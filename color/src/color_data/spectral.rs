@@ -913,3 +913,351 @@ pub static BLACK: [Float; 36] = [
     0.0323726333333333,
     0.0325031433333333,
 ];
+
+/// CIE standard illuminant D65 (average daylight), normalized to 1 at 560nm.
+pub static ILLUMINANT_D65: [Float; 36] = [
+    0.4997549999999999,
+    0.5464820000000000,
+    0.8275490000000001,
+    0.9148600000000000,
+    0.9343180000000000,
+    0.8668230000000000,
+    1.0486499999999999,
+    1.1700800000000000,
+    1.1781200000000001,
+    1.1486100000000001,
+    1.1592300000000000,
+    1.0881100000000001,
+    1.0935400000000000,
+    1.0780200000000000,
+    1.0479000000000001,
+    1.0768899999999999,
+    1.0440499999999999,
+    1.0404600000000002,
+    1.0000000000000000,
+    0.9633419999999999,
+    0.9578800000000000,
+    0.8868560000000000,
+    0.9000620000000000,
+    0.8959910000000001,
+    0.8769870000000001,
+    0.8328860000000000,
+    0.8369920000000001,
+    0.8002680000000000,
+    0.8021460000000000,
+    0.8227780000000000,
+    0.7828420000000000,
+    0.6972130000000000,
+    0.7160910000000000,
+    0.7434900000000000,
+    0.6160400000000000,
+    0.6988559999999999,
+];
+/// CIE standard illuminant D50 (horizon daylight), normalized to 1 at 560nm.
+pub static ILLUMINANT_D50: [Float; 36] = [
+    0.2448750000000000,
+    0.2987120000000000,
+    0.4930820000000000,
+    0.5651250000000000,
+    0.6003390000000001,
+    0.5781849999999999,
+    0.7482550000000000,
+    0.8724710000000000,
+    0.9061180000000000,
+    0.9136799999999999,
+    0.9510900000000000,
+    0.9196299999999999,
+    0.9572369999999999,
+    0.9661329999999999,
+    0.9712869999999999,
+    1.0209900000000001,
+    1.0075499999999999,
+    1.0231699999999999,
+    1.0000000000000000,
+    0.9773520000000001,
+    0.9891810000000000,
+    0.9349989999999999,
+    0.9768800000000000,
+    0.9926870000000000,
+    0.9904250000000000,
+    0.9572090000000000,
+    0.9885689999999999,
+    0.9566710000000000,
+    0.9818980000000000,
+    1.0300300000000000,
+    0.9913299999999999,
+    0.8738160000000000,
+    0.9160400000000000,
+    0.9289200000000000,
+    0.7685460000000001,
+    0.8651099999999999,
+];
+/// CIE standard illuminant A (incandescent, 2856K), normalized to 1 at 560nm.
+pub static ILLUMINANT_A: [Float; 36] = [
+    0.0979509960886738,
+    0.1208534536341114,
+    0.1470803844987550,
+    0.1767525215230304,
+    0.2099495729086599,
+    0.2467092268912794,
+    0.2870273044466300,
+    0.3308589297150251,
+    0.3781205668742749,
+    0.4286927624533730,
+    0.4824234315331340,
+    0.5391315328509929,
+    0.5986109895538909,
+    0.6606347274783062,
+    0.7249587198990483,
+    0.7913259454790965,
+    0.8594701837452934,
+    0.9291195891306120,
+    1.0000000000000000,
+    1.0718379528290081,
+    1.1443633836915748,
+    1.2173120094044467,
+    1.2904273891208506,
+    1.3634626739717155,
+    1.4361820576613078,
+    1.5083619448985841,
+    1.5797918573560303,
+    1.6502750986642087,
+    1.7196292009339431,
+    1.7876861755998510,
+    1.8542925911344599,
+    1.9193094995122590,
+    1.9826122323128703,
+    2.0440900861319715,
+    2.1036459155933298,
+    2.1611956507881058,
+];
+
+/// Spectrally flat neutral grey reflectances.
+pub static NEUTRAL_10: [Float; 36] = [
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+    0.1000000000000000,
+];
+pub static NEUTRAL_18: [Float; 36] = [
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+    0.1800000000000000,
+];
+pub static NEUTRAL_30: [Float; 36] = [
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+    0.3000000000000000,
+];
+pub static NEUTRAL_50: [Float; 36] = [
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+    0.5000000000000000,
+];
+pub static NEUTRAL_70: [Float; 36] = [
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+    0.7000000000000000,
+];
+pub static NEUTRAL_90: [Float; 36] = [
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+    0.9000000000000000,
+];
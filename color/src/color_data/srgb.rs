@@ -52,4 +52,24 @@ pub const GREY_3:        [Float; 3] = [0.470151683602212,  0.475953628435172, 0.
 pub const GREY_4:        [Float; 3] = [0.326433680189283,  0.331779293144700, 0.333384558671776];
 #[rustfmt::skip]
 pub const BLACK:         [Float; 3] = [0.195684876663901,  0.196313606429862, 0.198420076807872];
+// Standard illuminants, normalized to 1 at 560nm
+#[rustfmt::skip]
+pub const ILLUMINANT_D65: [Float; 3] = [0.982865249707586,  0.983044758772383, 0.982874686206887];
+#[rustfmt::skip]
+pub const ILLUMINANT_D50: [Float; 3] = [1.051455914944110,  0.970586716391249, 0.848847008267123];
+#[rustfmt::skip]
+pub const ILLUMINANT_A:   [Float; 3] = [1.291786441482491,  0.915142576425531, 0.515085522926916];
+// Neutral greys
+#[rustfmt::skip]
+pub const NEUTRAL_10:     [Float; 3] = [0.349190212628294,  0.349190212628294, 0.349190212628294];
+#[rustfmt::skip]
+pub const NEUTRAL_18:     [Float; 3] = [0.461356129500442,  0.461356129500442, 0.461356129500442];
+#[rustfmt::skip]
+pub const NEUTRAL_30:     [Float; 3] = [0.583831490060257,  0.583831490060257, 0.583831490060257];
+#[rustfmt::skip]
+pub const NEUTRAL_50:     [Float; 3] = [0.735356983052449,  0.735356983052449, 0.735356983052449];
+#[rustfmt::skip]
+pub const NEUTRAL_70:     [Float; 3] = [0.854305831544940,  0.854305831544940, 0.854305831544940];
+#[rustfmt::skip]
+pub const NEUTRAL_90:     [Float; 3] = [0.954687171885866,  0.954687171885866, 0.954687171885866];
 // @formatter:on
@@ -53,4 +53,24 @@ pub const GREY_3:        [Float; 3] = [0.180684867043266, 0.191321198763686, 0.2
 pub const GREY_4:        [Float; 3] = [0.084458522820453, 0.089407984961901, 0.098760848931306];
 #[rustfmt::skip]
 pub const BLACK:         [Float; 3] = [0.030422104614276, 0.031973719431692, 0.035418484065876];
+// Standard illuminants, normalized to 1 at 560nm
+#[rustfmt::skip]
+pub const ILLUMINANT_D65: [Float; 3] = [0.913987927777680, 0.961749654032902, 1.046936919403179];
+#[rustfmt::skip]
+pub const ILLUMINANT_D50: [Float; 3] = [0.921005291289159, 0.956451046951568, 0.788715353377299];
+#[rustfmt::skip]
+pub const ILLUMINANT_A:   [Float; 3] = [1.074703256107176, 0.983403241445458, 0.349131164450850];
+// Neutral greys
+#[rustfmt::skip]
+pub const NEUTRAL_10:     [Float; 3] = [0.095047000000000, 0.100000000000000, 0.108883000000000];
+#[rustfmt::skip]
+pub const NEUTRAL_18:     [Float; 3] = [0.171084600000000, 0.180000000000000, 0.195989400000000];
+#[rustfmt::skip]
+pub const NEUTRAL_30:     [Float; 3] = [0.285141000000000, 0.300000000000000, 0.326649000000000];
+#[rustfmt::skip]
+pub const NEUTRAL_50:     [Float; 3] = [0.475235000000000, 0.500000000000000, 0.544415000000000];
+#[rustfmt::skip]
+pub const NEUTRAL_70:     [Float; 3] = [0.665329000000000, 0.700000000000000, 0.762181000000000];
+#[rustfmt::skip]
+pub const NEUTRAL_90:     [Float; 3] = [0.855423000000000, 0.900000000000000, 0.979947000000000];
 // @formatter:on
@@ -3,7 +3,14 @@ use crate::*;
 use core::ops::{Index, IndexMut};
 use core::slice::SliceIndex;
 
-#[derive(Copy, Clone)]
+/// The integer value representing `1.0` in the fixed-point representation of an [IntSpectrum].
+pub const FIXED_POINT_ONE: u32 = 1 << 12;
+
+/// An integer spectrum.
+///
+/// It serves either as a plain counter (e.g. sample counts) or as a compact fixed-point accumulator
+/// with [FIXED_POINT_ONE] as unit. All additions saturate instead of overflowing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct IntSpectrum<const N: usize> {
     pub(crate) data: [u32; N],
 }
@@ -22,7 +29,102 @@ impl<const N: usize> IntSpectrum<N> {
     }
 
     pub fn increment(&mut self) {
-        self.data.iter_mut().for_each(|v| *v += 1);
+        self.data.iter_mut().for_each(|v| *v = v.saturating_add(1));
+    }
+
+    /// Adds the given spectrum component-wise, saturating at `u32::MAX`.
+    ///
+    /// # Arguments
+    /// * `rhs` - The spectrum to add
+    pub fn saturating_add(&mut self, rhs: Self) {
+        self.data
+            .iter_mut()
+            .zip(rhs.data.iter())
+            .for_each(|(a, b)| *a = a.saturating_add(*b));
+    }
+
+    /// Converts the given float into its fixed-point representation.
+    ///
+    /// # Constraints
+    /// * `value` - Negative or NaN values are treated as `0`.
+    ///
+    /// # Arguments
+    /// * `value` - The value to convert
+    ///
+    /// # Returns
+    /// * The saturated fixed-point value
+    #[inline]
+    pub fn to_fixed(value: Float) -> u32 {
+        // `as` casts saturate, NaN maps to 0
+        (value * FIXED_POINT_ONE as Float).round() as u32
+    }
+
+    /// Converts the given fixed-point value back into a float.
+    ///
+    /// # Arguments
+    /// * `value` - The fixed-point value
+    ///
+    /// # Returns
+    /// * The float value
+    #[inline]
+    pub fn from_fixed(value: u32) -> Float {
+        value as Float / FIXED_POINT_ONE as Float
+    }
+
+    /// Adds the given float to the fixed-point value at the given index, saturating at `u32::MAX`.
+    ///
+    /// # Arguments
+    /// * `index` - The index to add to
+    /// * `value` - The float value to add
+    pub fn add_fixed(&mut self, index: usize, value: Float) {
+        self.data[index] = self.data[index].saturating_add(Self::to_fixed(value));
+    }
+
+    /// Adds the given floats component-wise to the fixed-point values, saturating at `u32::MAX`.
+    ///
+    /// # Arguments
+    /// * `values` - The float values to add
+    pub fn add_fixed_all(&mut self, values: &[Float; N]) {
+        self.data
+            .iter_mut()
+            .zip(values.iter())
+            .for_each(|(a, v)| *a = a.saturating_add(Self::to_fixed(*v)));
+    }
+
+    /// Converts the fixed-point values back into floats.
+    ///
+    /// # Returns
+    /// * The float values
+    pub fn to_float(&self) -> [Float; N] {
+        let mut data = [0.0; N];
+        data.iter_mut()
+            .zip(self.data.iter())
+            .for_each(|(f, i)| *f = Self::from_fixed(*i));
+
+        data
+    }
+
+    /// Returns whether any component has reached `u32::MAX`.
+    ///
+    /// # Returns
+    /// * Whether the spectrum saturated
+    pub fn is_saturated(&self) -> bool {
+        self.data.contains(&u32::MAX)
+    }
+}
+
+impl<const N: usize> Add for IntSpectrum<N> {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self.saturating_add(rhs);
+        self
+    }
+}
+
+impl<const N: usize> AddAssign for IntSpectrum<N> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.saturating_add(rhs);
     }
 }
 
@@ -54,6 +156,30 @@ where
 
 macro_rules! impl_int_spectrum {
     ($name:ident, $size:expr) => {
+        impl $name {
+            /// Converts the given fixed-point spectrum into a float color.
+            ///
+            /// # Arguments
+            /// * `fixed` - The fixed-point spectrum
+            ///
+            /// # Returns
+            /// * Self
+            pub fn from_fixed_point(fixed: &IntSpectrum<$size>) -> Self {
+                Self::new(fixed.to_float())
+            }
+
+            /// Converts this color into a fixed-point spectrum.
+            ///
+            /// # Returns
+            /// * The saturated fixed-point spectrum
+            pub fn to_fixed_point(&self) -> IntSpectrum<$size> {
+                let mut fixed = IntSpectrum::default();
+                fixed.add_fixed_all(&self.data);
+
+                fixed
+            }
+        }
+
         impl Mul<IntSpectrum<$size>> for $name {
             type Output = Self;
 
@@ -97,3 +223,23 @@ macro_rules! impl_int_spectrum {
 impl_int_spectrum!(Srgb, 3);
 impl_int_spectrum!(Xyz, 3);
 impl_int_spectrum!(Spectrum, LAMBDA_NUM);
+
+#[test]
+fn saturates_at_limit() {
+    let mut counter = IntSpectrum::new([u32::MAX - 1, 0, 7]);
+    counter.increment();
+    counter.increment();
+    assert_eq!([u32::MAX, 2, 9], counter.data);
+    assert!(counter.is_saturated());
+
+    let sum = IntSpectrum::new([u32::MAX, 1, 2]) + IntSpectrum::new([1, u32::MAX, 3]);
+    assert_eq!([u32::MAX, u32::MAX, 5], sum.data);
+
+    // values beyond the fixed-point range, negative and NaN values
+    let mut fixed = IntSpectrum::<4>::default();
+    fixed.add_fixed_all(&[1.5, Float::MAX, -1.0, Float::NAN]);
+    fixed.add_fixed(1, 1.0);
+    assert_eq!([3 * FIXED_POINT_ONE / 2, u32::MAX, 0, 0], fixed.data);
+    assert_eq!(1.5, fixed.to_float()[0]);
+    assert!(!IntSpectrum::new([FIXED_POINT_ONE; 3]).is_saturated());
+}
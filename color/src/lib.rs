@@ -2,11 +2,13 @@ use core::convert::TryFrom;
 use core::fmt::Debug;
 use core::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Sub, SubAssign};
 use std::iter::Sum;
+use std::sync::OnceLock;
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_big_array::big_array;
 
-use color_data::{LAMBDA_END, LAMBDA_START};
+use cie::{xyz_of, CIE_Y_INTEGRAL};
+use color_data::{LAMBDA_END, LAMBDA_RANGE, LAMBDA_START};
 pub use int_spectrum::*;
 pub use spectrum::*;
 pub use srgb::*;
@@ -38,13 +40,120 @@ big_array! {
 pub enum SerdeColors {
     Srgb([Float; 3]),
     Xyz([Float; 3]),
-    #[serde(with = "SerdeBigArray")]
+    /// The samples of the wavelengths, or a map of named wavelengths (e.g. `"450nm"`) to values,
+    /// getting resampled
+    #[serde(
+        serialize_with = "SerdeBigArray::serialize",
+        deserialize_with = "deserialize_spectrum"
+    )]
     Spectrum([Float; 36]),
     Color(Colors),
     MulColor(Float, Colors),
     Constant(Float),
 }
 
+/// Parses a named wavelength in `nm`, e.g. `"450nm"` or `"450"`.
+fn parse_wavelength(name: &str) -> Option<Float> {
+    let name = name.trim();
+    let value = name.strip_suffix("nm").unwrap_or(name).trim();
+
+    value.parse::<Float>().ok().filter(|v| v.is_finite())
+}
+
+/// Resamples the given `(wavelength in nm, value)` pairs to the wavelengths of a
+/// [Spectrum], interpolating linearly and extending the outermost values.
+///
+/// # Constraints
+/// * `samples` - Should not be empty.
+///
+/// # Returns
+/// * The resampled values
+fn resample_named(mut samples: Vec<(Float, Float)>) -> [Float; LAMBDA_NUM] {
+    debug_assert!(!samples.is_empty());
+
+    samples.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let mut data = [0.0; LAMBDA_NUM];
+    for (i, value) in data.iter_mut().enumerate() {
+        let lambda = Spectrum::lambda_of_index(i) * 1000.0;
+        let next = samples.iter().position(|(l, _)| *l >= lambda);
+
+        *value = match next {
+            Some(0) => samples[0].1,
+            Some(j) => {
+                let (l0, v0) = samples[j - 1];
+                let (l1, v1) = samples[j];
+                v0.lerp(v1, (lambda - l0) / (l1 - l0))
+            }
+            None => samples[samples.len() - 1].1,
+        };
+    }
+
+    data
+}
+
+/// Deserializes the samples of a spectrum, either given as a sequence of all samples or as a map
+/// of named wavelengths to values.
+fn deserialize_spectrum<'de, D>(deserializer: D) -> Result<[Float; LAMBDA_NUM], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::{MapAccess, SeqAccess, Visitor};
+    use std::fmt;
+
+    struct SpectrumVisitor;
+
+    impl<'de> Visitor<'de> for SpectrumVisitor {
+        type Value = [Float; LAMBDA_NUM];
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                formatter,
+                "{} samples or a map of wavelengths (e.g. \"450nm\") to values",
+                LAMBDA_NUM
+            )
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut data = [0.0; LAMBDA_NUM];
+            for (i, value) in data.iter_mut().enumerate() {
+                *value = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+            }
+
+            if seq.next_element::<Float>()?.is_some() {
+                return Err(A::Error::invalid_length(LAMBDA_NUM + 1, &self));
+            }
+
+            Ok(data)
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut samples = Vec::new();
+            while let Some((name, value)) = map.next_entry::<String, Float>()? {
+                let lambda = parse_wavelength(&name)
+                    .ok_or_else(|| A::Error::custom(format!("Invalid wavelength: {}", name)))?;
+                samples.push((lambda, value));
+            }
+
+            if samples.is_empty() {
+                return Err(A::Error::custom("Empty map of wavelengths"));
+            }
+
+            Ok(resample_named(samples))
+        }
+    }
+
+    deserializer.deserialize_any(SpectrumVisitor)
+}
+
 #[macro_export]
 macro_rules! color {
     ($name:ident => $size:expr, $path:ident $(::$path2:ident)*) => {
@@ -97,23 +206,51 @@ macro_rules! color {
                 LAMBDA_START.lerp(LAMBDA_END, t)
             }
 
+            /// Returns the value at the given wavelength, linearly interpolated between the
+            /// neighbouring samples and clamped to the wavelength range.
+            ///
+            /// # Arguments
+            /// * `lambda` - The wavelength in `µm`
+            ///
+            /// # Returns
+            /// * The interpolated value
+            #[inline]
+            pub fn at_lambda(&self, lambda: Float) -> Float {
+                let t = ((lambda - LAMBDA_START) / (LAMBDA_END - LAMBDA_START)).max(0.0).min(1.0);
+                let x = t * ($size - 1) as Float;
+
+                let i = (x as usize).min($size - 2);
+                self[i].lerp(self[i + 1], x - i as Float)
+            }
+
+            /// Returns the light wave at the given index, without its CIE weights.
+            ///
+            /// The pdf corresponds to uniform sampling of the visible wavelength range.
             #[inline]
             pub fn as_light_wave(&self, light_wave_index: usize) -> LightWave {
                 let lambda = Self::lambda_of_index(light_wave_index);
                 let intensity = self[light_wave_index];
 
-                LightWave {
-                    lambda,
-                    intensity,
-                }
+                LightWave::new(lambda, intensity, 1.0 / LAMBDA_RANGE)
             }
 
+            /// Returns the light waves of all indices with their CIE weights, which get computed
+            /// only once per index.
             pub fn as_light_waves(&self) -> [LightWave; $size] {
-                let mut light_waves = [LightWave::default(); $size];
+                static CIE_WEIGHTS: OnceLock<[Xyz; $size]> = OnceLock::new();
 
+                let weights = CIE_WEIGHTS.get_or_init(|| {
+                    let mut weights = [Xyz::default(); $size];
+                    for i in 0..$size {
+                        weights[i] = xyz_of(Self::lambda_of_index(i));
+                    }
+
+                    weights
+                });
+
+                let mut light_waves = [LightWave::default(); $size];
                 for i in 0..$size {
-                    light_waves[i].lambda = Self::lambda_of_index(i);
-                    light_waves[i].intensity = self[i]
+                    light_waves[i] = self.as_light_wave(i).with_cie(weights[i]);
                 }
 
                 light_waves
@@ -156,6 +293,15 @@ macro_rules! color {
                     Colors::Grey3        => $path $(::$path2)* ::GREY_3,
                     Colors::Grey4        => $path $(::$path2)* ::GREY_4,
                     Colors::Black        => $path $(::$path2)* ::BLACK,
+                    Colors::IlluminantD65 => $path $(::$path2)* ::ILLUMINANT_D65,
+                    Colors::IlluminantD50 => $path $(::$path2)* ::ILLUMINANT_D50,
+                    Colors::IlluminantA  => $path $(::$path2)* ::ILLUMINANT_A,
+                    Colors::Neutral10    => $path $(::$path2)* ::NEUTRAL_10,
+                    Colors::Neutral18    => $path $(::$path2)* ::NEUTRAL_18,
+                    Colors::Neutral30    => $path $(::$path2)* ::NEUTRAL_30,
+                    Colors::Neutral50    => $path $(::$path2)* ::NEUTRAL_50,
+                    Colors::Neutral70    => $path $(::$path2)* ::NEUTRAL_70,
+                    Colors::Neutral90    => $path $(::$path2)* ::NEUTRAL_90,
                 };
                 // @formatter:on
 
@@ -234,6 +380,17 @@ macro_rules! color {
                 Self::new(data)
             }
 
+            fn gamma(&self, gamma: Float) -> Self {
+                let mut data = self.data;
+                data.iter_mut().for_each(|f| *f = f.powf(gamma));
+
+                Self::new(data)
+            }
+
+            fn luminance(&self) -> Float {
+                Xyz::from(*self)[1]
+            }
+
             fn lerp(&self, other: &Self, t: Float) -> Self {
                 let mut data = [Default::default(); $size];
 
@@ -434,12 +591,99 @@ macro_rules! color {
 }
 
 /// A light wave is described by a wavelength (lambda) in `μm` and an intensity (associated with amplitude).
+///
+/// It additionally carries the pdf it was sampled with and optionally the CIE color matching
+/// weights at its wavelength, such that it can be converted to `XYZ` without re-evaluating the
+/// CMFs.
 #[derive(Copy, Clone, Default)]
 pub struct LightWave {
     /// The wavelength in `μm`.
     pub lambda: Float,
     /// The intensity of the light wave.
     pub intensity: Float,
+    /// The pdf (per `μm`) with which the wavelength was sampled.
+    pub pdf: Float,
+    /// The CIE color matching weights at `lambda`, if precomputed.
+    pub cie: Option<Xyz>,
+}
+
+impl LightWave {
+    /// Creates a new light wave. Its CIE weights get evaluated when needed, unless given by
+    /// [with_cie](Self::with_cie).
+    ///
+    /// # Constraints
+    /// * `pdf` - Should be positive.
+    ///
+    /// # Arguments
+    /// * `lambda` - The wavelength in `μm`
+    /// * `intensity` - The intensity
+    /// * `pdf` - The pdf with which the wavelength was sampled
+    ///
+    /// # Returns
+    /// * Self
+    #[inline]
+    pub fn new(lambda: Float, intensity: Float, pdf: Float) -> Self {
+        Self {
+            lambda,
+            intensity,
+            pdf,
+            cie: None,
+        }
+    }
+
+    /// Sets the precomputed CIE weights at the wavelength of this light wave.
+    ///
+    /// # Arguments
+    /// * `cie` - The CIE color matching weights at `lambda`
+    ///
+    /// # Returns
+    /// * Self
+    #[inline]
+    pub fn with_cie(mut self, cie: Xyz) -> Self {
+        self.cie = Some(cie);
+        self
+    }
+
+    /// Returns the CIE color matching weights at the wavelength of this light wave, evaluating
+    /// them if not precomputed.
+    ///
+    /// # Returns
+    /// * The CIE weights
+    #[inline]
+    pub fn cie(&self) -> Xyz {
+        self.cie.unwrap_or_else(|| xyz_of(self.lambda))
+    }
+
+    /// Returns the `XYZ` contribution of this light wave as a single Monte Carlo sample.
+    ///
+    /// # Returns
+    /// * The `XYZ` contribution (zero if the pdf is not positive)
+    #[inline]
+    pub fn xyz(&self) -> Xyz {
+        if self.pdf <= 0.0 {
+            Xyz::default()
+        } else {
+            self.cie() * (self.intensity / (self.pdf * CIE_Y_INTEGRAL))
+        }
+    }
+
+    /// Converts a set of light wave samples into a single `XYZ` estimate by averaging their
+    /// contributions.
+    ///
+    /// # Arguments
+    /// * `light_waves` - The light wave samples
+    ///
+    /// # Returns
+    /// * The `XYZ` estimate
+    pub fn to_xyz(light_waves: &[LightWave]) -> Xyz {
+        if light_waves.is_empty() {
+            return Xyz::default();
+        }
+
+        let sum: Xyz = light_waves.iter().map(LightWave::xyz).sum();
+
+        sum / light_waves.len() as Float
+    }
 }
 
 /// A trait for colors. Allows arithmetic operations to be performed and gives utility functions
@@ -521,6 +765,27 @@ pub trait Color:
     /// * Square-rooted self
     fn sqrt(&self) -> Self;
 
+    /// Raises each color value to the power of `gamma`.
+    ///
+    /// # Constraints
+    /// * `gamma` - Should be finite.
+    ///
+    /// # Arguments
+    /// * `gamma` - The exponent
+    ///
+    /// # Returns
+    /// * Gamma-adjusted self
+    fn gamma(&self, gamma: Float) -> Self;
+
+    /// Returns the luminance (`Y` of the `XYZ` color space) of this color.
+    ///
+    /// Spectra are integrated against the CIE color matching functions, while sRGB colors
+    /// get linearized and weighted accordingly.
+    ///
+    /// # Returns
+    /// * The luminance
+    fn luminance(&self) -> Float;
+
     /// Linearly interpolates this color with the other one by parameter `t`.
     ///
     /// # Constraints
@@ -577,6 +842,49 @@ pub trait AsColor {
     fn pink() -> Self;
 }
 
+/// Every color derives its known colors from the same pre-defined [Colors], such that e.g.
+/// `Spectrum::red()` and `Srgb::red()` describe the same color.
+impl<C> AsColor for C
+where
+    C: Color,
+{
+    fn black() -> Self {
+        Self::broadcast(0.0)
+    }
+
+    fn grey() -> Self {
+        Self::from(Colors::Neutral50)
+    }
+
+    fn white() -> Self {
+        Self::from(Colors::White)
+    }
+
+    fn red() -> Self {
+        Self::from(Colors::Red)
+    }
+
+    fn yellow() -> Self {
+        Self::from(Colors::Yellow)
+    }
+
+    fn green() -> Self {
+        Self::from(Colors::Green)
+    }
+
+    fn cyan() -> Self {
+        Self::from(Colors::Cyan)
+    }
+
+    fn blue() -> Self {
+        Self::from(Colors::Blue)
+    }
+
+    fn pink() -> Self {
+        Self::from(Colors::Magenta)
+    }
+}
+
 /// Describes colors.
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Colors {
@@ -604,10 +912,19 @@ pub enum Colors {
     Grey3,
     Grey4,
     Black,
+    IlluminantD65,
+    IlluminantD50,
+    IlluminantA,
+    Neutral10,
+    Neutral18,
+    Neutral30,
+    Neutral50,
+    Neutral70,
+    Neutral90,
 }
 
 impl Colors {
-    pub const fn variants() -> [Self; 24] {
+    pub const fn variants() -> [Self; 33] {
         [
             Self::DarkSkin,
             Self::LightSkin,
@@ -633,6 +950,15 @@ impl Colors {
             Self::Grey3,
             Self::Grey4,
             Self::Black,
+            Self::IlluminantD65,
+            Self::IlluminantD50,
+            Self::IlluminantA,
+            Self::Neutral10,
+            Self::Neutral18,
+            Self::Neutral30,
+            Self::Neutral50,
+            Self::Neutral70,
+            Self::Neutral90,
         ]
     }
 }
@@ -666,9 +992,156 @@ impl TryFrom<&str> for Colors {
             "grey3" => Self::Grey3,
             "grey4" => Self::Grey4,
             "black" => Self::Black,
+            "illuminantd65" => Self::IlluminantD65,
+            "illuminantd50" => Self::IlluminantD50,
+            "illuminanta" => Self::IlluminantA,
+            "neutral10" => Self::Neutral10,
+            "neutral18" => Self::Neutral18,
+            "neutral30" => Self::Neutral30,
+            "neutral50" => Self::Neutral50,
+            "neutral70" => Self::Neutral70,
+            "neutral90" => Self::Neutral90,
             _ => return Err(format!("Unable to parse Color: {}", value)),
         };
 
         Ok(c)
     }
 }
+
+#[test]
+fn cached_cie_weights_match_evaluated_ones() {
+    let spectrum = Spectrum::new([0.5; LAMBDA_NUM]);
+
+    for (i, light_wave) in spectrum.as_light_waves().iter().enumerate() {
+        let evaluated = spectrum.as_light_wave(i);
+
+        assert!(evaluated.cie.is_none());
+        assert_eq!(evaluated.cie(), light_wave.cie.unwrap());
+        assert_eq!(evaluated.xyz(), light_wave.xyz());
+    }
+}
+
+#[test]
+fn illuminant_white_points() {
+    let chromaticity = |xyz: Xyz| {
+        let sum = xyz[0] + xyz[1] + xyz[2];
+        (xyz[0] / sum, xyz[1] / sum)
+    };
+
+    for &(color, x, y) in &[
+        (Colors::IlluminantD65, 0.3127, 0.3290),
+        (Colors::IlluminantD50, 0.3457, 0.3585),
+        (Colors::IlluminantA, 0.4476, 0.4074),
+    ] {
+        let spectral = chromaticity(Xyz::from(Spectrum::from(color)));
+        let tabulated = chromaticity(Xyz::from(color));
+
+        for (cx, cy) in [spectral, tabulated] {
+            assert!((cx - x).abs() < 5e-3, "{:?}: x = {}", color, cx);
+            assert!((cy - y).abs() < 5e-3, "{:?}: y = {}", color, cy);
+        }
+    }
+
+    for &(color, reflectance) in &[
+        (Colors::Neutral10, 0.1),
+        (Colors::Neutral18, 0.18),
+        (Colors::Neutral50, 0.5),
+        (Colors::Neutral90, 0.9),
+    ] {
+        let spectrum = Spectrum::from(color);
+        assert!(spectrum
+            .data
+            .iter()
+            .all(|&v| (v - reflectance).abs() < 1e-6));
+        assert!((Xyz::from(color)[1] - reflectance).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn known_colors_agree_across_types() {
+    fn check<C: AsColor>(to_srgb: impl Fn(C) -> Srgb) {
+        assert!(to_srgb(C::black()).is_black());
+
+        // spectra only approximate the achromatic colors
+        let white = to_srgb(C::white());
+        let grey = to_srgb(C::grey());
+        for c in [white, grey] {
+            assert!(c.component_max() - c.component_min() < 0.15, "{:?}", c);
+        }
+        assert!(white[1] > grey[1]);
+
+        for (color, channel) in [(C::red(), 0), (C::green(), 1), (C::blue(), 2)] {
+            let srgb = to_srgb(color);
+            let others = (0..3).filter(|&i| i != channel).map(|i| srgb[i]);
+
+            assert!(
+                others.fold(0.0, Float::max) < srgb[channel] - 0.2,
+                "{:?}",
+                srgb
+            );
+        }
+    }
+
+    check::<Srgb>(|c| c);
+    check::<Xyz>(Srgb::from);
+    check::<Spectrum>(Srgb::from);
+
+    let srgb = Srgb::red();
+    let xyz = Srgb::from(Xyz::red());
+    assert!((0..3).all(|i| (srgb[i] - xyz[i]).abs() < 1e-4));
+}
+
+#[test]
+fn luminance_and_gamma() {
+    assert!((Srgb::new([1.0, 1.0, 1.0]).luminance() - 1.0).abs() < 1e-3);
+    assert!((Srgb::new([1.0, 0.0, 0.0]).luminance() - 0.2126).abs() < 1e-3);
+    assert!((Srgb::new([0.0, 1.0, 0.0]).luminance() - 0.7152).abs() < 1e-3);
+    assert!((Srgb::new([0.0, 0.0, 1.0]).luminance() - 0.0722).abs() < 1e-3);
+    // sRGB values get linearized
+    assert!((Srgb::broadcast(0.5).luminance() - 0.2140).abs() < 1e-3);
+
+    assert_eq!(Xyz::new([0.3, 0.6, 0.9]).luminance(), 0.6);
+    // linear in the spectrum, integrated against the CIE `Y` curve
+    let flat = Spectrum::broadcast(1.0).luminance();
+    assert!((flat - 1.0).abs() < 0.05);
+    assert!((Spectrum::broadcast(0.5).luminance() - 0.5 * flat).abs() < 1e-6);
+    assert_eq!(
+        Spectrum::from(Colors::Red).luminance(),
+        Xyz::from(Spectrum::from(Colors::Red))[1]
+    );
+
+    let squared = Spectrum::broadcast(0.25).gamma(0.5);
+    assert!(squared.data.iter().all(|&v| (v - 0.5).abs() < 1e-6));
+    assert_eq!(Srgb::new([0.0, 1.0, 4.0]).gamma(2.0).data, [0.0, 1.0, 16.0]);
+}
+
+#[test]
+fn named_wavelengths_are_resampled() {
+    use serde::de::value::{Error, MapDeserializer};
+
+    assert_eq!(parse_wavelength("450nm"), Some(450.0));
+    assert_eq!(parse_wavelength(" 455 nm "), Some(455.0));
+    assert_eq!(parse_wavelength("blue"), None);
+
+    let named = |samples: Vec<(&str, Float)>| {
+        let map =
+            MapDeserializer::<_, Error>::new(samples.into_iter().map(|(n, v)| (n.to_string(), v)));
+        deserialize_spectrum(map)
+    };
+
+    // the first and last bands lie at 380nm and 730nm
+    let data = named(vec![("550nm", 0.8), ("380nm", 0.2), ("730nm", 0.4)]).unwrap();
+    assert!((data[0] - 0.2).abs() < 1e-6);
+    assert!((data[LAMBDA_NUM - 1] - 0.4).abs() < 1e-6);
+    assert!((data[17] - 0.8).abs() < 1e-6);
+    assert!((data[7] - (0.2 + 0.6 * 7.0 / 17.0)).abs() < 1e-6);
+
+    // constant beyond the outermost wavelengths
+    let data = named(vec![("450nm", 0.3), ("650nm", 0.5)]).unwrap();
+    assert!(data[..8].iter().all(|&v| (v - 0.3).abs() < 1e-6));
+    assert!(data[27..].iter().all(|&v| (v - 0.5).abs() < 1e-6));
+    assert!((data[17] - 0.4).abs() < 1e-6);
+
+    assert!(named(vec![]).is_err());
+    assert!(named(vec![("red", 0.5)]).is_err());
+}
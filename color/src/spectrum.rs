@@ -1,4 +1,3 @@
-use crate::cie::{xyz_of, CIE_Y_INTEGRAL};
 use crate::color_data::*;
 use crate::*;
 use image::Rgb;
@@ -31,16 +30,7 @@ impl From<Spectrum> for Srgb {
 
 impl From<Spectrum> for Xyz {
     fn from(spectrum: Spectrum) -> Self {
-        let xyz = spectrum
-            .as_light_waves()
-            .iter()
-            .fold(Xyz::broadcast(0.0), |acc, next| {
-                acc + xyz_of(next.lambda) * next.intensity
-            });
-
-        let scale = LAMBDA_RANGE / (CIE_Y_INTEGRAL * Spectrum::size() as Float);
-
-        xyz * scale
+        LightWave::to_xyz(&spectrum.as_light_waves())
     }
 }
 
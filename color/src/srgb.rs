@@ -22,9 +22,42 @@ impl TryFrom<SerdeColors> for Srgb {
     }
 }
 
+impl Srgb {
+    /// Converts this gamma-encoded sRGB color into linear RGB.
+    ///
+    /// # Returns
+    /// * The linear color
+    pub fn to_linear(&self) -> Self {
+        Self::new([uncompand(self[0]), uncompand(self[1]), uncompand(self[2])])
+    }
+
+    /// Converts this linear RGB color into gamma-encoded sRGB.
+    ///
+    /// # Returns
+    /// * The gamma-encoded color
+    pub fn to_srgb_encoded(&self) -> Self {
+        Self::new([compand(self[0]), compand(self[1]), compand(self[2])])
+    }
+
+    /// Quantizes this gamma-encoded color into the integer range `[0, max]`.
+    ///
+    /// Each channel gets clamped to `[0, 1]`, scaled by `max` and rounded to the nearest integer.
+    ///
+    /// # Arguments
+    /// * `max` - The maximum integer value (e.g. `255` for 8-bit)
+    ///
+    /// # Returns
+    /// * The quantized channels
+    pub fn quantize(&self, max: Float) -> [Float; 3] {
+        let q = |v: Float| (v.fast_clamp(0.0, 1.0) * max).round();
+
+        [q(self[0]), q(self[1]), q(self[2])]
+    }
+}
+
 impl From<Srgb> for Rgb<u8> {
     fn from(srgb: Srgb) -> Self {
-        let conv = srgb * Srgb::broadcast(2u32.pow(8) as Float);
+        let conv = srgb.quantize(u8::MAX as Float);
         let data = [conv[0] as u8, conv[1] as u8, conv[2] as u8];
 
         Self::from(data)
@@ -33,7 +66,7 @@ impl From<Srgb> for Rgb<u8> {
 
 impl From<Srgb> for Rgb<u16> {
     fn from(srgb: Srgb) -> Self {
-        let conv = srgb * Srgb::broadcast(2u32.pow(16) as Float);
+        let conv = srgb.quantize(u16::MAX as Float);
         let data = [conv[0] as u16, conv[1] as u16, conv[2] as u16];
 
         Self::from(data)
@@ -50,9 +83,10 @@ impl From<Srgb> for Xyz {
     #[allow(clippy::excessive_precision)]
     #[allow(clippy::many_single_char_names)]
     fn from(srgb: Srgb) -> Self {
-        let r = uncompand(srgb[0]);
-        let g = uncompand(srgb[1]);
-        let b = uncompand(srgb[2]);
+        let linear = srgb.to_linear();
+        let r = linear[0];
+        let g = linear[1];
+        let b = linear[2];
 
         let x = 0.4124564 * r + 0.3575761 * g + 0.1804375 * b;
         let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
@@ -62,6 +96,17 @@ impl From<Srgb> for Xyz {
     }
 }
 
+#[allow(clippy::excessive_precision)]
+#[inline]
+fn compand(val: Float) -> Float {
+    // https://entropymine.com/imageworsener/srgbformula/
+    if val <= 0.00313066844250063 {
+        val * 12.92
+    } else {
+        1.055 * val.powf(1.0 / 2.4) - 0.055
+    }
+}
+
 #[allow(clippy::excessive_precision)]
 #[inline]
 fn uncompand(val: Float) -> Float {
@@ -72,3 +117,22 @@ fn uncompand(val: Float) -> Float {
         ((val + 0.055) / 1.055).powf(2.4)
     }
 }
+
+#[test]
+fn gamma_round_trip_keeps_8_bit_levels() {
+    for level in 0..=u8::MAX {
+        let encoded = Srgb::broadcast(level as Float / u8::MAX as Float);
+        let round_trip = encoded.to_linear().to_srgb_encoded();
+
+        assert_eq!(Rgb([level; 3]), Rgb::<u8>::from(round_trip));
+    }
+
+    // middle grey, the linear segment and out of range values
+    assert!((Srgb::broadcast(0.5).to_srgb_encoded()[0] - 0.735_357).abs() < 1e-5);
+    assert!((Srgb::broadcast(0.001).to_srgb_encoded()[0] - 0.012_92).abs() < 1e-7);
+    assert_eq!(
+        [0.0, 255.0, 128.0],
+        Srgb::new([-0.5, 1.5, 0.5]).quantize(u8::MAX as Float)
+    );
+    assert_eq!(Rgb([u16::MAX; 3]), Rgb::<u16>::from(Srgb::broadcast(1.0)));
+}
@@ -53,17 +53,6 @@ impl From<Xyz> for Srgb {
         let g = -0.9692660 * x + 1.8760108 * y + 0.0415560 * z;
         let b =  0.0556434 * x - 0.2040259 * y + 1.0572252 * z;
 
-        Self::new([compand(r), compand(g), compand(b)])
-    }
-}
-
-#[allow(clippy::excessive_precision)]
-#[inline]
-fn compand(val: Float) -> Float {
-    // https://entropymine.com/imageworsener/srgbformula/
-    if val <= 0.00313066844250063 {
-        val * 12.92
-    } else {
-        1.055 * val.powf(1.0 / 2.4) - 0.055
+        Self::new([r, g, b]).to_srgb_encoded()
     }
 }
@@ -4,11 +4,11 @@ use crate::demo_scenes::{Demo, Float, Vector3, FOVY, SIGMA};
 use color::{Color, Colors};
 use geometry::{Aabb, BiconvexLens, Boundable, Bubble, Mesh, ShadingMode, Sphere};
 use rust_v::bxdf::{
-    BeckmannDistribution, FresnelDielectric, FresnelSpecular, FresnelType, MicrofacetReflection,
-    OrenNayar, BSDF,
+    bxdf_item, BeckmannDistribution, FresnelDielectric, FresnelSpecular, FresnelType,
+    MicrofacetReflection, OrenNayar, BSDF,
 };
 use rust_v::camera::{Camera, PerspectiveCamera};
-use rust_v::objects::{Emitter, Receiver, SceneObject};
+use rust_v::objects::{receiver_geometry, Emitter, Receiver, SceneObject};
 use rust_v::refractive_index::RefractiveType;
 use rust_v::samplers::camera::CameraSampler;
 
@@ -135,8 +135,8 @@ fn create_dragon() -> SceneObject {
         )),
     );
 
-    let bsdf = BSDF::new(vec![Box::new(specular), Box::new(microfacet)]);
-    let geometry = Box::new(dragon);
+    let bsdf = BSDF::new(vec![bxdf_item(specular), bxdf_item(microfacet)]);
+    let geometry = receiver_geometry(dragon);
 
     let receiver = Arc::new(Receiver::new(geometry, bsdf));
 
@@ -174,10 +174,10 @@ fn create_bunny() -> SceneObject {
         RefractiveType::Sapphire,
     );
 
-    let bxdf = Box::new(specular);
+    let bxdf = bxdf_item(specular);
 
     let bsdf = BSDF::new(vec![bxdf]);
-    let geometry = Box::new(bunny);
+    let geometry = receiver_geometry(bunny);
 
     let receiver = Arc::new(Receiver::new(geometry, bsdf));
 
@@ -195,10 +195,10 @@ fn create_sphere() -> SceneObject {
         RefractiveType::Glass,
     );
 
-    let bxdf = Box::new(specular);
+    let bxdf = bxdf_item(specular);
 
     let bsdf = BSDF::new(vec![bxdf]);
-    let geometry = Box::new(sphere);
+    let geometry = receiver_geometry(sphere);
 
     let receiver = Arc::new(Receiver::new(geometry, bsdf));
     SceneObject::Receiver(receiver)
@@ -216,10 +216,10 @@ fn create_bubble() -> SceneObject {
         RefractiveType::Glass,
     );
 
-    let bxdf = Box::new(specular);
+    let bxdf = bxdf_item(specular);
 
     let bsdf = BSDF::new(vec![bxdf]);
-    let geometry = Box::new(bubble);
+    let geometry = receiver_geometry(bubble);
 
     let receiver = Arc::new(Receiver::new(geometry, bsdf));
     SceneObject::Receiver(receiver)
@@ -241,10 +241,10 @@ fn create_biconvex_lens() -> SceneObject {
         RefractiveType::Glass,
     );
 
-    let bxdf = Box::new(specular);
+    let bxdf = bxdf_item(specular);
 
     let bsdf = BSDF::new(vec![bxdf]);
-    let geometry = Box::new(lens);
+    let geometry = receiver_geometry(lens);
 
     let receiver = Arc::new(Receiver::new(geometry, bsdf));
     SceneObject::Receiver(receiver)
@@ -276,10 +276,10 @@ fn create_wall(wall: &Wall) -> SceneObject {
     };
 
     let oren_nayar = OrenNayar::new(spectrum, SIGMA);
-    let bxdf = Box::new(oren_nayar);
+    let bxdf = bxdf_item(oren_nayar);
 
     let bsdf = BSDF::new(vec![bxdf]);
-    let geometry = Box::new(cube);
+    let geometry = receiver_geometry(cube);
 
     let receiver = Arc::new(Receiver::new(geometry, bsdf));
     SceneObject::Receiver(receiver)
@@ -5,10 +5,10 @@ use crate::demo_scenes::{Demo, Float, Vector3, FOVY};
 use color::Color;
 use geometry::{Aabb, Cylinder, Point, Sphere};
 use rust_v::bxdf::{
-    FresnelType, LambertianReflection, SpecularReflection, SpecularTransmission, BSDF,
+    bxdf_item, FresnelType, LambertianReflection, SpecularReflection, SpecularTransmission, BSDF,
 };
 use rust_v::camera::{Camera, PerspectiveCamera};
-use rust_v::objects::{Emitter, Receiver, SceneObject};
+use rust_v::objects::{receiver_geometry, Emitter, Receiver, SceneObject};
 use rust_v::refractive_index::RefractiveType;
 use rust_v::samplers::camera::CameraSampler;
 
@@ -52,11 +52,11 @@ fn ground() -> SceneObject {
     let cube = Aabb::new(min, max);
 
     let lambertian = LambertianReflection::new(Spectrum::broadcast(1.0));
-    let bxdf = Box::new(lambertian);
+    let bxdf = bxdf_item(lambertian);
 
     let bsdf = BSDF::new(vec![bxdf]);
 
-    let receiver = Arc::new(Receiver::new(Box::new(cube), bsdf));
+    let receiver = Arc::new(Receiver::new(receiver_geometry(cube), bsdf));
 
     SceneObject::Receiver(receiver)
 }
@@ -68,11 +68,11 @@ fn cylinder() -> SceneObject {
     let cylinder = Cylinder::new((bot, top), RADIUS);
 
     let specular = SpecularReflection::new(Spectrum::broadcast(1.0), FresnelType::NoOp);
-    let bxdf = Box::new(specular);
+    let bxdf = bxdf_item(specular);
 
     let bsdf = BSDF::new(vec![bxdf]);
 
-    let receiver = Arc::new(Receiver::new(Box::new(cylinder), bsdf));
+    let receiver = Arc::new(Receiver::new(receiver_geometry(cylinder), bsdf));
     SceneObject::Receiver(receiver)
 }
 
@@ -85,11 +85,11 @@ fn sphere() -> SceneObject {
         RefractiveType::Air,
         RefractiveType::Glass,
     );
-    let bxdf = Box::new(specular);
+    let bxdf = bxdf_item(specular);
 
     let bsdf = BSDF::new(vec![bxdf]);
 
-    let receiver = Arc::new(Receiver::new(Box::new(sphere), bsdf));
+    let receiver = Arc::new(Receiver::new(receiver_geometry(sphere), bsdf));
     SceneObject::Receiver(receiver)
 }
 
@@ -41,6 +41,17 @@ pub trait Demo {
             block_size: UVec2::broadcast(8),
             passes: 100,
             threads: None,
+            stack_size: None,
+            tile_range: None,
+            light_groups: Default::default(),
+            light_group_aovs: false,
+            light_paths: Vec::new(),
+            post_effects: Vec::new(),
+            exposure: None,
+            spectral_bands: Vec::new(),
+            energy_check: None,
+            geometry_aovs: false,
+            aovs: Vec::new(),
         };
 
         let integrator = Box::new(Whitted::new(8));
@@ -3,9 +3,9 @@ use crate::demo_scenes::{Demo, Vector3, FOVY, SIGMA};
 use crate::*;
 use color::{Color, Colors};
 use geometry::{Aabb, Boundable, Cylinder, Mesh, Point, ShadingMode, Sphere};
-use rust_v::bxdf::{FresnelSpecular, LambertianReflection, OrenNayar, BSDF};
+use rust_v::bxdf::{bxdf_item, FresnelSpecular, LambertianReflection, OrenNayar, BSDF};
 use rust_v::camera::{Camera, PerspectiveCamera};
-use rust_v::objects::{Emitter, Receiver, SceneObject};
+use rust_v::objects::{receiver_geometry, Emitter, Receiver, SceneObject};
 use rust_v::refractive_index::RefractiveType;
 use rust_v::samplers::camera::CameraSampler;
 
@@ -49,11 +49,11 @@ fn ground() -> SceneObject {
     let cube = Aabb::new(min, max);
 
     let oren_nayar = OrenNayar::new(Spectrum::from(Colors::White), SIGMA);
-    let bxdf = Box::new(oren_nayar);
+    let bxdf = bxdf_item(oren_nayar);
 
     let bsdf = BSDF::new(vec![bxdf]);
 
-    let receiver = Arc::new(Receiver::new(Box::new(cube), bsdf));
+    let receiver = Arc::new(Receiver::new(receiver_geometry(cube), bsdf));
 
     SceneObject::Receiver(receiver)
 }
@@ -83,11 +83,11 @@ fn prism() -> SceneObject {
         RefractiveType::Air,
         RefractiveType::Sapphire,
     );
-    let bxdf = Box::new(specular);
+    let bxdf = bxdf_item(specular);
 
     let bsdf = BSDF::new(vec![bxdf]);
 
-    let receiver = Arc::new(Receiver::new(Box::new(prism), bsdf));
+    let receiver = Arc::new(Receiver::new(receiver_geometry(prism), bsdf));
     SceneObject::Receiver(receiver)
 }
 
@@ -112,11 +112,11 @@ fn light_bulb_rectifier() -> SceneObject {
 
     let rectifier = Cylinder::new((left_end, right_end), 0.51);
     let lambertian = LambertianReflection::new(Spectrum::from(Colors::Grey2));
-    let bxdf = Box::new(lambertian);
+    let bxdf = bxdf_item(lambertian);
 
     let bsdf = BSDF::new(vec![bxdf]);
 
-    let receiver = Arc::new(Receiver::new(Box::new(rectifier), bsdf));
+    let receiver = Arc::new(Receiver::new(receiver_geometry(rectifier), bsdf));
     SceneObject::Receiver(receiver)
 }
 
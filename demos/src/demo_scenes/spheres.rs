@@ -5,10 +5,10 @@ use crate::demo_scenes::{Demo, Float, Vector3, FOVY};
 use color::{Color, Colors};
 use geometry::{Aabb, Point, Sphere};
 use rust_v::bxdf::{
-    FresnelType, LambertianReflection, SpecularReflection, SpecularTransmission, BSDF,
+    bxdf_item, FresnelType, LambertianReflection, SpecularReflection, SpecularTransmission, BSDF,
 };
 use rust_v::camera::{Camera, PerspectiveCamera};
-use rust_v::objects::{Emitter, Receiver, SceneObject};
+use rust_v::objects::{receiver_geometry, Emitter, Receiver, SceneObject};
 use rust_v::refractive_index::RefractiveType;
 use rust_v::samplers::camera::CameraSampler;
 use rust_v::scene::Scene;
@@ -49,11 +49,11 @@ fn ground() -> SceneObject {
     let cube = Aabb::new(min, max);
 
     let lambertian = LambertianReflection::new(Spectrum::from(Colors::White));
-    let bxdf = Box::new(lambertian);
+    let bxdf = bxdf_item(lambertian);
 
     let bsdf = BSDF::new(vec![bxdf]);
 
-    let receiver = Arc::new(Receiver::new(Box::new(cube), bsdf));
+    let receiver = Arc::new(Receiver::new(receiver_geometry(cube), bsdf));
 
     SceneObject::Receiver(receiver)
 }
@@ -63,11 +63,11 @@ fn sky() -> SceneObject {
     let sphere = Sphere::new(center, SKY_RADIUS);
 
     let lambertian = LambertianReflection::new(Spectrum::from(Colors::BlueSky));
-    let bxdf = Box::new(lambertian);
+    let bxdf = bxdf_item(lambertian);
 
     let bsdf = BSDF::new(vec![bxdf]);
 
-    let receiver = Arc::new(Receiver::new(Box::new(sphere), bsdf));
+    let receiver = Arc::new(Receiver::new(receiver_geometry(sphere), bsdf));
     SceneObject::Receiver(receiver)
 }
 
@@ -116,7 +116,7 @@ fn random_bsdf(color: Spectrum) -> (bool, BSDF) {
             BSDF::empty()
         } else if rand < 0.8 {
             let specular = SpecularReflection::new(Spectrum::broadcast(1.0), FresnelType::NoOp);
-            let bxdf = Box::new(specular);
+            let bxdf = bxdf_item(specular);
 
             BSDF::new(vec![bxdf])
         } else {
@@ -125,13 +125,13 @@ fn random_bsdf(color: Spectrum) -> (bool, BSDF) {
                 RefractiveType::Air,
                 RefractiveType::Glass,
             );
-            let bxdf = Box::new(specular);
+            let bxdf = bxdf_item(specular);
 
             BSDF::new(vec![bxdf])
         }
     } else {
         let lambertian = LambertianReflection::new(color);
-        let bxdf = Box::new(lambertian);
+        let bxdf = bxdf_item(lambertian);
 
         BSDF::new(vec![bxdf])
     };
@@ -165,7 +165,7 @@ fn create_scene() -> Scene {
                 let emitter = Arc::new(Emitter::new(Box::new(sphere), bsdf, color * 2.0));
                 SceneObject::Emitter(emitter)
             } else {
-                let receiver = Arc::new(Receiver::new(Box::new(sphere), bsdf));
+                let receiver = Arc::new(Receiver::new(receiver_geometry(sphere), bsdf));
                 SceneObject::Receiver(receiver)
             };
 
@@ -46,9 +46,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let srgb_conv = Srgb::from(spectrum);
 
         // convert float to u8
-        let scale = Srgb::broadcast(2u32.pow(8) as Float);
-        let srgb_bytes = srgb * scale;
-        let srgb_conv_bytes = srgb_conv * scale;
+        let max = u8::MAX as Float;
+        let srgb_bytes = srgb.quantize(max);
+        let srgb_conv_bytes = srgb_conv.quantize(max);
         let data = [
             srgb_bytes[0] as u8,
             srgb_bytes[1] as u8,
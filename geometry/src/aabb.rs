@@ -63,6 +63,18 @@ impl Aabb {
         size.x * size.y * size.z
     }
 
+    /// Returns the surface area of this cube.
+    ///
+    /// # Constraints
+    /// * The cube should not be [empty](Aabb::empty).
+    ///
+    /// # Returns
+    /// * The surface area
+    pub fn surface_area(&self) -> Float {
+        let size = self.size();
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
     /// Returns the center of this cube.
     ///
     /// # Returns
@@ -71,6 +83,17 @@ impl Aabb {
         (self.min + self.max) / 2.0
     }
 
+    /// Returns the squared distance from the given point to this cube.
+    ///
+    /// # Arguments
+    /// * `point` - The point
+    ///
+    /// # Returns
+    /// * The squared distance (`0` if the point is inside)
+    pub fn distance_squared(&self, point: Vector3) -> Float {
+        (point - point.clamped(self.min, self.max)).mag_sq()
+    }
+
     /// Joins this cube with another one, effectively creating a cube spanning both cubes.
     ///
     /// # Arguments
@@ -89,6 +112,68 @@ impl Aabb {
         let max = self.max.max_by_component(other);
         Self::new(min, max)
     }
+
+    /// Joins all given cubes, effectively creating a cube spanning all of them.
+    ///
+    /// # Arguments
+    /// * `cubes` - The cubes to join
+    ///
+    /// # Returns
+    /// * The outer join, or an [empty](Aabb::empty) cube if there are none
+    pub fn union_all<I: IntoIterator<Item = Aabb>>(cubes: I) -> Self {
+        let (min, max) = cubes.into_iter().fold(
+            (
+                Vector3::one() * Float::INFINITY,
+                Vector3::one() * Float::NEG_INFINITY,
+            ),
+            |(min, max), cube| {
+                (
+                    min.min_by_component(cube.min),
+                    max.max_by_component(cube.max),
+                )
+            },
+        );
+
+        Self { min, max }
+    }
+
+    /// Computes the range of the given ray inside this cube by the slab method, clipped to the
+    /// range of the ray.
+    ///
+    /// # Arguments
+    /// * `ray` - The ray
+    ///
+    /// # Returns
+    /// * The entering and exiting ray parameter (if the ray passes through the cube)
+    pub fn intersect_range(&self, ray: &Ray) -> Option<(Float, Float)> {
+        let t1 = (self.min - ray.origin) / ray.direction;
+        let t2 = (self.max - ray.origin) / ray.direction;
+
+        let t_min = t1.min_by_component(t2).component_max().max(ray.t_start);
+        let t_max = t1.max_by_component(t2).component_min().min(ray.t_end);
+
+        if t_min <= t_max {
+            Some((t_min, t_max))
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether this cube overlaps another one, including touching faces.
+    ///
+    /// # Arguments
+    /// * `other` - Another cube
+    ///
+    /// # Returns
+    /// * Whether both cubes overlap
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+            && self.min.z <= other.max.z
+            && other.min.z <= self.max.z
+    }
 }
 
 impl Container for Aabb {
@@ -204,3 +289,54 @@ fn intersect_inside() {
         assert!(intersection.normal.dot(ray.direction) > 0.0);
     }
 }
+
+#[test]
+fn distance_squared() {
+    let cube = Aabb::default();
+
+    assert_eq!(0.0, cube.distance_squared(Vector3::zero()));
+    assert_eq!(0.0, cube.distance_squared(Vector3::one()));
+
+    for v in UNIT_VECTORS.iter() {
+        assert_eq!(4.0, cube.distance_squared(*v * 3.0));
+    }
+
+    assert_eq!(3.0, cube.distance_squared(Vector3::one() * 2.0));
+}
+
+#[test]
+fn union_helpers() {
+    let cube = Aabb::default();
+    let other = Aabb::new(Vector3::zero(), Vector3::new(2.0, 3.0, 4.0));
+
+    // the empty cube is the identity of joins
+    assert_eq!(Aabb::empty(), Aabb::union_all(Vec::new()));
+    assert_eq!(cube, Aabb::empty().join(&cube));
+    assert_eq!(cube, cube.join(&Aabb::empty()));
+    assert!(!Aabb::empty().contains(&Vector3::zero()));
+
+    let joined = cube.join(&other);
+    assert_eq!(joined, Aabb::union_all(vec![cube, other]));
+    assert_eq!(joined, Aabb::union_all(vec![other, Aabb::empty(), cube]));
+    assert_eq!(Vector3::new(3.0, 4.0, 5.0), joined.size());
+    assert!(joined.contains(&Vector3::new(-1.0, 3.0, 0.5)));
+    assert!(!joined.contains(&Vector3::new(-1.5, 0.0, 0.0)));
+
+    assert_eq!(24.0, cube.surface_area());
+    assert_eq!(2.0 * (6.0 + 12.0 + 8.0), other.surface_area());
+}
+
+#[test]
+fn intersect_range() {
+    let cube = Aabb::default();
+
+    let ray = Ray::new_fast(Vector3::new(-3.0, 0.5, 0.0), Vector3::unit_x());
+    assert_eq!(Some((2.0, 4.0)), cube.intersect_range(&ray));
+
+    // clipped to the ray
+    let ray = Ray::new(Vector3::zero(), Vector3::unit_x(), 0.0, 0.5);
+    assert_eq!(Some((0.0, 0.5)), cube.intersect_range(&ray));
+
+    let ray = Ray::new_fast(Vector3::new(-3.0, 2.0, 0.0), Vector3::unit_x());
+    assert_eq!(None, cube.intersect_range(&ray));
+}
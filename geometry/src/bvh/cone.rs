@@ -0,0 +1,102 @@
+use crate::*;
+use utility::floats::FloatExt;
+
+/// A cone bounding a set of directions by an `axis` and the cosine of its half-angle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NormalCone {
+    pub axis: Vector3,
+    pub cos_theta: Float,
+}
+
+impl NormalCone {
+    /// Creates a degenerate cone containing only the given direction.
+    ///
+    /// # Constraints
+    /// * `direction` - Should be normalized.
+    ///
+    /// # Arguments
+    /// * `direction` - The direction
+    ///
+    /// # Returns
+    /// * Self, or the [entire](NormalCone::entire) cone if the direction is invalid
+    pub fn from_direction(direction: Vector3) -> Self {
+        if direction.x.is_finite()
+            && direction.y.is_finite()
+            && direction.z.is_finite()
+            && direction.mag_sq() > 0.0
+        {
+            Self {
+                axis: direction,
+                cos_theta: 1.0,
+            }
+        } else {
+            Self::entire()
+        }
+    }
+
+    /// Creates a cone containing all directions.
+    ///
+    /// # Returns
+    /// * Self
+    pub fn entire() -> Self {
+        Self {
+            axis: Vector3::unit_y(),
+            cos_theta: -1.0,
+        }
+    }
+
+    /// Returns whether every direction inside this cone points away from the given direction,
+    /// i.e. whether surfaces with these normals are all back-facing to a ray along `direction`.
+    ///
+    /// # Arguments
+    /// * `direction` - The (normalized) ray direction
+    ///
+    /// # Returns
+    /// * Whether all directions have a positive dot product with `direction`
+    #[inline]
+    pub fn is_back_facing(&self, direction: Vector3) -> bool {
+        // the angle between axis and direction plus the half-angle must stay below 90°
+        self.cos_theta > 0.0
+            && self.axis.dot(direction) > (1.0 - self.cos_theta * self.cos_theta).sqrt()
+    }
+
+    /// Creates the smallest cone bounding both cones.
+    ///
+    /// # Arguments
+    /// * `other` - Another cone
+    ///
+    /// # Returns
+    /// * The bounding cone
+    pub fn union(&self, other: &Self) -> Self {
+        let theta_a = self.cos_theta.fast_clamp(-1.0, 1.0).acos();
+        let theta_b = other.cos_theta.fast_clamp(-1.0, 1.0).acos();
+        let theta_d = self.axis.dot(other.axis).fast_clamp(-1.0, 1.0).acos();
+
+        if (theta_d + theta_b).min(PI) <= theta_a {
+            return *self;
+        }
+        if (theta_d + theta_a).min(PI) <= theta_b {
+            return *other;
+        }
+
+        let theta_o = (theta_a + theta_d + theta_b) / 2.0;
+        if theta_o >= PI {
+            return Self::entire();
+        }
+
+        // rotate our axis towards the other one
+        let rotation_axis = self.axis.cross(other.axis);
+        if rotation_axis.mag_sq() == 0.0 {
+            return Self::entire();
+        }
+        let rotation_axis = rotation_axis.normalized();
+
+        let theta_r = theta_o - theta_a;
+        let axis = self.axis * theta_r.cos() + rotation_axis.cross(self.axis) * theta_r.sin();
+
+        Self {
+            axis: axis.normalized(),
+            cos_theta: theta_o.cos(),
+        }
+    }
+}
@@ -1,20 +1,57 @@
 use crate::bvh::candidate::{Candidate, Candidates};
+use crate::bvh::cone::NormalCone;
 use crate::bvh::item::Item;
-use crate::bvh::node::Node;
+use crate::bvh::node::{CachedNode, Node};
 use crate::bvh::side::Side;
-use crate::{Aabb, Boundable, ContainerGeometry, Ray};
+use crate::{Aabb, Boundable, Container, ContainerGeometry, Ray, Vector3};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
 use std::sync::Arc;
 
 mod candidate;
+mod cone;
 mod item;
 mod node;
 mod plane;
 mod side;
 
+/// Statistics about the structure of a [Tree].
+#[derive(Clone, Debug, Default)]
+pub struct TreeStats {
+    /// The number of internal nodes
+    pub nodes: usize,
+    /// The number of leaves
+    pub leaves: usize,
+    /// The number of leaves at each depth
+    pub leaf_depths: Vec<usize>,
+    /// The number of leaves containing a given number of items
+    pub leaf_occupancy: Vec<usize>,
+}
+
+impl TreeStats {
+    /// Returns the maximum depth of the tree.
+    pub fn max_depth(&self) -> usize {
+        self.leaf_depths.len().saturating_sub(1)
+    }
+}
+
 pub struct Tree<T> {
     root: Node<T>,
     space: Aabb,
+    cone: NormalCone,
+    /// The id of the next inserted value
+    next_id: u32,
+}
+
+/// The structure of a [Tree] without its values, allowing to skip the expensive construction
+/// when the same values get loaded again.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TreeCache {
+    root: CachedNode,
+    space: Aabb,
+    len: u32,
 }
 
 impl<T> Tree<T>
@@ -39,7 +76,55 @@ where
         let mut sides = vec![Side::Both; n];
         let root = Node::new(space, candidates, n, &mut sides);
 
-        Self { root, space }
+        Self {
+            root,
+            space,
+            cone: NormalCone::entire(),
+            next_id: n as u32,
+        }
+    }
+
+    /// Inserts the given value into the tree without rebuilding it, by adding it to all leaves
+    /// overlapping its bounds and growing the tree's space if necessary.
+    ///
+    /// The tree keeps its structure, so many insertions degrade its quality compared to a new
+    /// tree. The normal cones along the way get reset.
+    ///
+    /// # Arguments
+    /// * `value` - The value to insert
+    /// * `bounds` - The bounds of the value
+    ///
+    /// # Returns
+    /// * The id of the value, identifying it for [remove](Tree::remove)
+    pub fn insert(&mut self, value: T, bounds: Aabb) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let space = self.space.join(&bounds);
+        if space != self.space {
+            self.root.grow(&self.space, &space);
+            self.space = space;
+        }
+
+        self.cone = NormalCone::entire();
+        self.root.insert(Arc::new(Item::new(value, id)), &bounds);
+
+        id
+    }
+
+    /// Removes the value with the given id from the tree without rebuilding it.
+    ///
+    /// Values of a tree built by [new](Tree::new) have the id of their index, inserted ones the id
+    /// returned by [insert](Tree::insert). The space of the tree does not shrink.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the value
+    /// * `bounds` - The bounds of the value
+    ///
+    /// # Returns
+    /// * Whether the tree contained the value
+    pub fn remove(&mut self, id: u32, bounds: &Aabb) -> bool {
+        self.space.overlaps(bounds) && self.root.remove(id, bounds)
     }
 
     pub fn intersect(&self, ray: &Ray) -> Vec<Arc<T>> {
@@ -52,6 +137,155 @@ where
             vec![]
         }
     }
+
+    /// Computes the normal cones of all nodes, allowing [intersect_occlusion](Tree::intersect_occlusion)
+    /// to skip subtrees entirely back-facing to a ray.
+    ///
+    /// # Arguments
+    /// * `f` - Returns the (geometric) normal of a value
+    pub fn build_normal_cones<F: Fn(&T) -> Vector3>(&mut self, f: F) {
+        self.cone = self.root.build_cones(&f);
+    }
+
+    /// Collects the values possibly occluding the given ray.
+    ///
+    /// Values facing away from the ray cannot occlude it when they belong to a closed surface the
+    /// ray starts outside of, as the ray has to enter it through a front-facing value first.
+    /// Subtrees whose normal cone is entirely back-facing therefore get skipped, if the ray starts
+    /// outside the bounds of this tree and therefore outside all surfaces. Rays starting inside
+    /// the bounds may leave a surface through back-facing values, and get tested against all
+    /// values like in [intersect](Tree::intersect). Without
+    /// [normal cones](Tree::build_normal_cones) this is always equal to [intersect](Tree::intersect).
+    ///
+    /// # Constraints
+    /// * The values should form closed surfaces.
+    ///
+    /// # Arguments
+    /// * `ray` - The occlusion ray
+    ///
+    /// # Returns
+    /// * The values possibly occluding the ray
+    pub fn intersect_occlusion(&self, ray: &Ray) -> Vec<Arc<T>> {
+        if self.space.contains(&ray.origin) {
+            return self.intersect(ray);
+        }
+
+        if !self.cone.is_back_facing(ray.direction) && self.space.contains_or_intersects(ray) {
+            let mut items = HashSet::new();
+            self.root.intersect_occlusion(ray, &mut items);
+
+            items.iter().map(|i| i.value.clone()).collect()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Collects the bounding boxes of all nodes up to the given depth, e.g. for visualization.
+    ///
+    /// # Arguments
+    /// * `max_depth` - The maximum depth (`0` only returns the bounds of the whole tree)
+    ///
+    /// # Returns
+    /// * The bounding boxes in depth-first order
+    pub fn node_bounds(&self, max_depth: u32) -> Vec<Aabb> {
+        let mut bounds = vec![self.space];
+        self.root.collect_bounds(1, max_depth, &mut bounds);
+
+        bounds
+    }
+
+    /// Collects statistics about the structure of this tree.
+    ///
+    /// # Returns
+    /// * The tree statistics
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats::default();
+        self.root.collect_stats(0, &mut stats);
+
+        stats
+    }
+
+    /// Creates the cached structure of this tree.
+    ///
+    /// # Arguments
+    /// * `len` - The number of values this tree was built with
+    ///
+    /// # Returns
+    /// * The tree cache
+    pub fn to_cache(&self, len: usize) -> TreeCache {
+        TreeCache {
+            root: self.root.to_cache(),
+            space: self.space,
+            len: len as u32,
+        }
+    }
+
+    /// Restores a tree from its cached structure.
+    ///
+    /// # Constraints
+    /// * `values` - Should be the same values (in the same order) the cached tree was built with.
+    ///
+    /// # Arguments
+    /// * `values` - The values
+    /// * `cache` - The cached structure
+    ///
+    /// # Returns
+    /// * Self, or `None` if the cache does not match the values
+    pub fn from_cache(values: Vec<T>, cache: &TreeCache) -> Option<Self> {
+        if values.len() != cache.len as usize {
+            return None;
+        }
+
+        let items: Vec<_> = values
+            .into_iter()
+            .enumerate()
+            .map(|(id, v)| Arc::new(Item::new(v, id as u32)))
+            .collect();
+
+        let root = Node::from_cache(&cache.root, &items)?;
+
+        Some(Self {
+            root,
+            space: cache.space,
+            cone: NormalCone::entire(),
+            next_id: cache.len,
+        })
+    }
+
+    /// Writes the cached structure of this tree to the given path in a compact binary format.
+    ///
+    /// # Arguments
+    /// * `len` - The number of values this tree was built with
+    /// * `path` - The file path
+    ///
+    /// # Returns
+    /// * Whether writing succeeded
+    pub fn save_cache(&self, len: usize, path: &str) -> bincode::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, &self.to_cache(len))
+    }
+
+    /// Loads a tree from the cached structure at the given path.
+    ///
+    /// # Constraints
+    /// * `values` - Should be the same values (in the same order) the cached tree was built with.
+    ///
+    /// # Arguments
+    /// * `values` - The values
+    /// * `path` - The file path
+    ///
+    /// # Returns
+    /// * Self, or an error if the file could not be read or does not match the values
+    pub fn load_cache(values: Vec<T>, path: &str) -> bincode::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let cache: TreeCache = bincode::deserialize_from(reader)?;
+
+        Self::from_cache(values, &cache).ok_or_else(|| {
+            Box::new(bincode::ErrorKind::Custom(
+                "BVH cache does not match the given values".to_string(),
+            ))
+        })
+    }
 }
 
 impl<T> Default for Tree<T> {
@@ -61,6 +295,8 @@ impl<T> Default for Tree<T> {
                 items: HashSet::new(),
             },
             space: Aabb::empty(),
+            cone: NormalCone::entire(),
+            next_id: 0,
         }
     }
 }
@@ -73,3 +309,75 @@ where
         self.space
     }
 }
+
+#[cfg(test)]
+fn row_of_boxes(n: usize) -> Vec<Aabb> {
+    use crate::Vector3;
+
+    (0..n)
+        .map(|i| {
+            let min = Vector3::new(2.0 * i as crate::Float, 0.0, 0.0);
+            Aabb::new(min, min + Vector3::one())
+        })
+        .collect()
+}
+
+#[test]
+fn cache_round_trip() {
+    use crate::Vector3;
+
+    let boxes = row_of_boxes(32);
+    let tree = Tree::new(boxes.clone(), |b| *b);
+    assert!(matches!(tree.root, Node::Node { .. }));
+
+    let path = std::env::temp_dir().join(format!("rust-v-bvh-{}.bin", std::process::id()));
+    let path = path.to_str().unwrap();
+    tree.save_cache(boxes.len(), path).unwrap();
+
+    let loaded = Tree::load_cache(boxes.clone(), path).unwrap();
+    let mismatch = Tree::load_cache(row_of_boxes(31), path);
+    std::fs::remove_file(path).unwrap();
+
+    assert!(mismatch.is_err());
+    assert_eq!(
+        bincode::serialize(&tree.to_cache(boxes.len())).unwrap(),
+        bincode::serialize(&loaded.to_cache(boxes.len())).unwrap()
+    );
+
+    for x in [0.5, 20.5, 62.5, 63.5] {
+        let ray = Ray::new_fast(Vector3::new(x, 0.5, -1.0), Vector3::unit_z());
+        let mut expected: Vec<_> = tree.intersect(&ray).iter().map(|b| b.min.x).collect();
+        let mut actual: Vec<_> = loaded.intersect(&ray).iter().map(|b| b.min.x).collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        actual.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        assert_eq!(expected, actual);
+    }
+}
+
+#[test]
+fn insert_and_remove_values() {
+    let boxes = row_of_boxes(8);
+    let mut tree = Tree::new(boxes.clone(), |b| *b);
+
+    // whether the tree finds the box starting at `x` as candidate of a ray through it
+    let finds = |tree: &Tree<Aabb>, x: crate::Float| {
+        let ray = Ray::new_fast(Vector3::new(x + 0.5, 0.5, -1.0), Vector3::unit_z());
+        tree.intersect(&ray).iter().any(|b| b.min.x == x)
+    };
+
+    // outside the space of the tree, which has to grow
+    let outside = Aabb::new(Vector3::new(20.0, 0.0, 0.0), Vector3::new(21.0, 1.0, 1.0));
+    let id = tree.insert(outside, outside);
+    assert_eq!(8, id);
+    assert!(finds(&tree, 20.0));
+    assert!(tree.bounds().contains(&Vector3::new(20.5, 0.5, 0.5)));
+
+    assert!(tree.remove(3, &boxes[3]));
+    assert!(!tree.remove(3, &boxes[3]));
+    assert!(!finds(&tree, 6.0));
+    assert!(finds(&tree, 4.0));
+
+    assert!(tree.remove(id, &outside));
+    assert!(!finds(&tree, 20.0));
+}
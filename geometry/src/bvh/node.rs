@@ -1,9 +1,12 @@
 use crate::bvh::candidate::{Candidate, Candidates};
+use crate::bvh::cone::NormalCone;
 use crate::bvh::item::Item;
 use crate::bvh::plane::Plane;
 use crate::bvh::side::Side;
+use crate::bvh::TreeStats;
 use crate::*;
 use crate::{Aabb, ContainerGeometry, Ray};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::sync::Arc;
 use utility::floats::FloatExt;
@@ -15,8 +18,10 @@ const K_I: Float = 20.0;
 pub struct InternalNode<T> {
     left_space: Aabb,
     left_node: Node<T>,
+    left_cone: NormalCone,
     right_space: Aabb,
     right_node: Node<T>,
+    right_cone: NormalCone,
 }
 
 impl<T> InternalNode<T>
@@ -27,12 +32,28 @@ where
         Self {
             left_space,
             left_node,
+            left_cone: NormalCone::entire(),
             right_space,
             right_node,
+            right_cone: NormalCone::entire(),
         }
     }
 }
 
+/// The structure of a node without its items, referencing them by id instead.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum CachedNode {
+    Leaf {
+        ids: Vec<u32>,
+    },
+    Node {
+        left_space: Aabb,
+        left_node: Box<CachedNode>,
+        right_space: Aabb,
+        right_node: Box<CachedNode>,
+    },
+}
+
 #[derive(Clone)]
 pub enum Node<T> {
     Leaf { items: HashSet<Arc<Item<T>>> },
@@ -78,6 +99,67 @@ where
         }
     }
 
+    /// Converts this node into its cached structure.
+    ///
+    /// # Returns
+    /// * The cached node
+    pub fn to_cache(&self) -> CachedNode {
+        match self {
+            Node::Leaf { items } => {
+                let mut ids: Vec<u32> = items.iter().map(|i| i.id).collect();
+                ids.sort_unstable();
+
+                CachedNode::Leaf { ids }
+            }
+            Node::Node { node } => CachedNode::Node {
+                left_space: node.left_space,
+                left_node: Box::new(node.left_node.to_cache()),
+                right_space: node.right_space,
+                right_node: Box::new(node.right_node.to_cache()),
+            },
+        }
+    }
+
+    /// Restores a node from its cached structure.
+    ///
+    /// # Arguments
+    /// * `cache` - The cached node
+    /// * `items` - The items indexed by their id
+    ///
+    /// # Returns
+    /// * Self, or `None` if the cache references unknown items
+    pub fn from_cache(cache: &CachedNode, items: &[Arc<Item<T>>]) -> Option<Self> {
+        let node = match cache {
+            CachedNode::Leaf { ids } => {
+                let items = ids
+                    .iter()
+                    .map(|id| items.get(*id as usize).cloned())
+                    .collect::<Option<_>>()?;
+
+                Self::Leaf { items }
+            }
+            CachedNode::Node {
+                left_space,
+                left_node,
+                right_space,
+                right_node,
+            } => {
+                let inner_node = InternalNode::new(
+                    *left_space,
+                    Self::from_cache(left_node, items)?,
+                    *right_space,
+                    Self::from_cache(right_node, items)?,
+                );
+
+                Self::Node {
+                    node: Box::new(inner_node),
+                }
+            }
+        };
+
+        Some(node)
+    }
+
     fn partition(
         n: usize,
         space: &Aabb,
@@ -123,26 +205,20 @@ where
     fn cost(p: &Plane, v: &Aabb, n_l: usize, n_r: usize) -> Float {
         let (left, right) = Self::split_space(v, p);
 
-        let volume_left = left.volume();
-        if volume_left == 0.0 {
+        // splits cutting off nothing are useless
+        if left.volume() == 0.0 || right.volume() == 0.0 {
             return Float::INFINITY;
         }
 
-        let volume_right = right.volume();
-        if volume_right == 0.0 {
-            return Float::INFINITY;
-        }
-
-        let total_volume = volume_left + volume_right;
+        // the probability of a ray through the parent also passing through a child
+        let area = v.surface_area();
+        let p_left = left.surface_area() / area;
+        let p_right = right.surface_area() / area;
 
         // Decrease cost if it cuts empty space
         let factor = if n_l == 0 || n_r == 0 { 0.8 } else { 1.0 };
 
-        factor
-            * (K_T
-                + K_I
-                    * (n_l as Float * volume_left / total_volume
-                        + n_r as Float * volume_right / total_volume))
+        factor * (K_T + K_I * (n_l as Float * p_left + n_r as Float * p_right))
     }
 
     fn split_space(space: &Aabb, plane: &Plane) -> (Aabb, Aabb) {
@@ -222,6 +298,167 @@ where
         (left_candidates, right_candidates)
     }
 
+    pub fn collect_stats(&self, depth: usize, stats: &mut TreeStats) {
+        match self {
+            Node::Leaf { items } => {
+                stats.leaves += 1;
+
+                if stats.leaf_depths.len() <= depth {
+                    stats.leaf_depths.resize(depth + 1, 0);
+                }
+                stats.leaf_depths[depth] += 1;
+
+                if stats.leaf_occupancy.len() <= items.len() {
+                    stats.leaf_occupancy.resize(items.len() + 1, 0);
+                }
+                stats.leaf_occupancy[items.len()] += 1;
+            }
+            Node::Node { node } => {
+                stats.nodes += 1;
+                node.left_node.collect_stats(depth + 1, stats);
+                node.right_node.collect_stats(depth + 1, stats);
+            }
+        }
+    }
+
+    pub fn collect_bounds(&self, depth: u32, max_depth: u32, bounds: &mut Vec<Aabb>) {
+        if depth > max_depth {
+            return;
+        }
+
+        if let Node::Node { node } = self {
+            bounds.push(node.left_space);
+            node.left_node.collect_bounds(depth + 1, max_depth, bounds);
+            bounds.push(node.right_space);
+            node.right_node.collect_bounds(depth + 1, max_depth, bounds);
+        }
+    }
+
+    /// Computes the normal cones of all nodes.
+    ///
+    /// # Arguments
+    /// * `f` - Returns the normal of an item
+    ///
+    /// # Returns
+    /// * The normal cone bounding all items of this node
+    pub fn build_cones<F: Fn(&T) -> Vector3>(&mut self, f: &F) -> NormalCone {
+        match self {
+            Node::Leaf { items } => {
+                let mut normals = items
+                    .iter()
+                    .map(|i| NormalCone::from_direction(f(&i.value)));
+
+                match normals.next() {
+                    Some(first) => normals.fold(first, |cone, n| cone.union(&n)),
+                    // an empty leaf never occludes
+                    None => NormalCone::entire(),
+                }
+            }
+            Node::Node { node } => {
+                node.left_cone = node.left_node.build_cones(f);
+                node.right_cone = node.right_node.build_cones(f);
+
+                node.left_cone.union(&node.right_cone)
+            }
+        }
+    }
+
+    /// Collects the items possibly occluding the ray, skipping subtrees entirely back-facing to
+    /// the ray.
+    ///
+    /// # Arguments
+    /// * `ray` - The occlusion ray
+    /// * `intersect_items` - The set to collect the items into
+    pub fn intersect_occlusion(&self, ray: &Ray, intersect_items: &mut HashSet<Arc<Item<T>>>) {
+        match self {
+            Node::Leaf { items } => intersect_items.extend(items.clone()),
+            Node::Node { node } => {
+                if !node.left_cone.is_back_facing(ray.direction)
+                    && node.left_space.contains_or_intersects(ray)
+                {
+                    node.left_node.intersect_occlusion(ray, intersect_items);
+                }
+                if !node.right_cone.is_back_facing(ray.direction)
+                    && node.right_space.contains_or_intersects(ray)
+                {
+                    node.right_node.intersect_occlusion(ray, intersect_items);
+                }
+            }
+        }
+    }
+
+    /// Inserts the item into all leaves whose space overlaps its bounds. The normal cones along
+    /// the way get reset, as they cannot be updated incrementally.
+    ///
+    /// # Arguments
+    /// * `item` - The item to insert
+    /// * `bounds` - The bounds of the item
+    pub fn insert(&mut self, item: Arc<Item<T>>, bounds: &Aabb) {
+        match self {
+            Node::Leaf { items } => {
+                items.insert(item);
+            }
+            Node::Node { node } => {
+                if node.left_space.overlaps(bounds) {
+                    node.left_cone = NormalCone::entire();
+                    node.left_node.insert(item.clone(), bounds);
+                }
+                if node.right_space.overlaps(bounds) {
+                    node.right_cone = NormalCone::entire();
+                    node.right_node.insert(item, bounds);
+                }
+            }
+        }
+    }
+
+    /// Removes the item with the given id from all leaves whose space overlaps its bounds.
+    ///
+    /// # Arguments
+    /// * `id` - The id of the item
+    /// * `bounds` - The bounds the item got inserted with
+    ///
+    /// # Returns
+    /// * Whether any leaf contained the item
+    pub fn remove(&mut self, id: u32, bounds: &Aabb) -> bool {
+        match self {
+            Node::Leaf { items } => {
+                let len = items.len();
+                items.retain(|i| i.id != id);
+
+                items.len() != len
+            }
+            Node::Node { node } => {
+                let left = node.left_space.overlaps(bounds) && node.left_node.remove(id, bounds);
+                let right = node.right_space.overlaps(bounds) && node.right_node.remove(id, bounds);
+
+                left || right
+            }
+        }
+    }
+
+    /// Grows the spaces of all nodes from the old to the new space of their parent. Faces of the
+    /// child spaces lying on the boundary of the old space get moved onto the new boundary, while
+    /// the splitting planes stay in place.
+    ///
+    /// # Constraints
+    /// * `new` - Should contain `old`.
+    ///
+    /// # Arguments
+    /// * `old` - The old space of this node
+    /// * `new` - The new space of this node
+    pub fn grow(&mut self, old: &Aabb, new: &Aabb) {
+        if let Node::Node { node } = self {
+            let left_space = grow_space(&node.left_space, old, new);
+            let right_space = grow_space(&node.right_space, old, new);
+
+            node.left_node.grow(&node.left_space, &left_space);
+            node.right_node.grow(&node.right_space, &right_space);
+
+            node.left_space = left_space;
+            node.right_space = right_space;
+        }
+    }
+
     pub fn intersect(&self, ray: &Ray, intersect_items: &mut HashSet<Arc<Item<T>>>) {
         match self {
             Node::Leaf { items } => intersect_items.extend(items.clone()),
@@ -236,3 +473,22 @@ where
         }
     }
 }
+
+/// Moves the faces of the given space lying on the boundary of the old space onto the boundary of
+/// the new space.
+fn grow_space(space: &Aabb, old: &Aabb, new: &Aabb) -> Aabb {
+    let pick = |s: Float, o: Float, n: Float| if s == o { n } else { s };
+
+    let min = Vector3::new(
+        pick(space.min.x, old.min.x, new.min.x),
+        pick(space.min.y, old.min.y, new.min.y),
+        pick(space.min.z, old.min.z, new.min.z),
+    );
+    let max = Vector3::new(
+        pick(space.max.x, old.max.x, new.max.x),
+        pick(space.max.y, old.max.y, new.max.y),
+        pick(space.max.z, old.max.z, new.max.z),
+    );
+
+    Aabb::new(min, max)
+}
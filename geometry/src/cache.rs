@@ -0,0 +1,168 @@
+//! A cache of preprocessed meshes, avoiding to parse obj files and build their BVHs again when
+//! rendering the same scene repeatedly, e.g. in parameter sweeps.
+//!
+//! While deserializing inside [with_cache_directory], each mesh loaded from an obj file gets
+//! stored in the cache directory, keyed by a hash of the obj file (path, size and modification
+//! time) and its load settings. Subsequent runs memory-map the entry instead, read-only.
+
+use crate::bvh::TreeCache;
+use crate::{Aabb, Face, Vertex};
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Invalidates all entries written by previous layouts of [CachedMesh] (or by previous ways of
+/// loading obj files, e.g. before splitting vertices at seams of the texture coordinates).
+const CACHE_VERSION: u32 = 3;
+
+thread_local! {
+    /// The directory of the mesh cache while deserializing.
+    static CACHE_DIRECTORY: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+    /// The failures to store meshes while deserializing.
+    static CACHE_ERRORS: RefCell<Vec<CacheError>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A mesh that could not be stored in the cache.
+#[derive(Debug)]
+pub struct CacheError {
+    /// The path of the cache entry
+    pub entry: PathBuf,
+    pub error: bincode::Error,
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Could not cache mesh {}: {}",
+            self.entry.display(),
+            self.error
+        )
+    }
+}
+
+impl Error for CacheError {}
+
+/// A preprocessed mesh: its final vertices and faces together with the structure of its BVH
+/// (`None` if its faces get tested linearly).
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CachedMesh {
+    pub vertices: Vec<Vertex>,
+    pub faces: Vec<Face>,
+    pub bounds: Aabb,
+    pub tree: Option<TreeCache>,
+}
+
+/// Runs the given function, caching the meshes of obj files deserialized inside in the given
+/// directory.
+///
+/// Failing to store a mesh does not fail the function, as caching is optional.
+///
+/// # Arguments
+/// * `directory` - The cache directory (no caching if `None`)
+/// * `f` - The function deserializing meshes
+///
+/// # Returns
+/// * The result of the function
+/// * The meshes that could not be stored
+pub fn with_cache_directory<T, F>(directory: Option<&Path>, f: F) -> (T, Vec<CacheError>)
+where
+    F: FnOnce() -> T,
+{
+    let previous = CACHE_DIRECTORY.with(|c| c.replace(directory.map(Path::to_path_buf)));
+    let previous_errors = CACHE_ERRORS.with(|e| e.take());
+    let result = f();
+    CACHE_DIRECTORY.with(|c| *c.borrow_mut() = previous);
+    let errors = CACHE_ERRORS.with(|e| e.replace(previous_errors));
+
+    (result, errors)
+}
+
+/// Returns the path of the cache entry of the given obj file and load settings, if caching.
+///
+/// # Arguments
+/// * `obj` - The resolved path of the obj file
+/// * `settings` - The settings modifying the loaded mesh
+///
+/// # Returns
+/// * The path of the cache entry (if any)
+pub(crate) fn entry<S: Hash>(obj: &Path, settings: S) -> Option<PathBuf> {
+    let directory = CACHE_DIRECTORY.with(|c| c.borrow().clone())?;
+    let metadata = fs::metadata(obj).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+    let mut hasher = DefaultHasher::new();
+    CACHE_VERSION.hash(&mut hasher);
+    cfg!(feature = "f64").hash(&mut hasher);
+    obj.canonicalize()
+        .unwrap_or_else(|_| obj.to_path_buf())
+        .hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    modified.hash(&mut hasher);
+    settings.hash(&mut hasher);
+
+    Some(directory.join(format!("{:016x}.mesh", hasher.finish())))
+}
+
+/// Memory-maps the given cache entry.
+///
+/// # Returns
+/// * The cached mesh, or `None` if missing or unreadable
+pub(crate) fn load(entry: &Path) -> Option<CachedMesh> {
+    let file = File::open(entry).ok()?;
+    // SAFETY: entries only get replaced atomically by renaming, never modified in place
+    let map = unsafe { Mmap::map(&file) }.ok()?;
+
+    bincode::deserialize(&map).ok()
+}
+
+/// Stores the given mesh in the cache entry. Failures get reported by [with_cache_directory].
+pub(crate) fn store(entry: &Path, mesh: &CachedMesh) {
+    let write = || -> bincode::Result<()> {
+        if let Some(directory) = entry.parent() {
+            fs::create_dir_all(directory)?;
+        }
+
+        // write to a temporary file first, such that concurrent runs never map partial entries
+        let temporary = entry.with_extension(format!("{}.tmp", std::process::id()));
+        bincode::serialize_into(BufWriter::new(File::create(&temporary)?), mesh)?;
+        fs::rename(&temporary, entry)?;
+
+        Ok(())
+    };
+
+    if let Err(error) = write() {
+        let entry = entry.to_path_buf();
+        CACHE_ERRORS.with(|e| e.borrow_mut().push(CacheError { entry, error }));
+    }
+}
+
+#[test]
+fn report_failed_stores() {
+    let file = std::env::temp_dir().join(format!("rust-v-cache-{}", std::process::id()));
+    fs::write(&file, []).unwrap();
+
+    // the directory of the entry cannot be created below a file
+    let entry = file.join("mesh.mesh");
+    let mesh = CachedMesh {
+        vertices: vec![],
+        faces: vec![],
+        bounds: Aabb::empty(),
+        tree: None,
+    };
+
+    let ((), errors) = with_cache_directory(None, || store(&entry, &mesh));
+    fs::remove_file(&file).unwrap();
+
+    assert_eq!(1, errors.len());
+    assert_eq!(entry, errors[0].entry);
+}
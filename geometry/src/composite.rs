@@ -28,9 +28,7 @@ where
     T: Deref<Target = dyn Boundable>,
 {
     fn bounds(&self) -> Aabb {
-        self.content
-            .iter()
-            .fold(Aabb::empty(), |acc, next| acc.join(&next.bounds()))
+        Aabb::union_all(self.content.iter().map(|c| c.bounds()))
     }
 }
 
@@ -56,6 +56,14 @@ impl Cylinder {
     pub fn height(&self) -> Float {
         (self.caps.1 - self.caps.0).mag()
     }
+
+    /// Returns the radius of this cylinder.
+    ///
+    /// # Returns
+    /// * The radius
+    pub fn radius(&self) -> Float {
+        self.radius
+    }
 }
 
 impl Boundable for Cylinder {
@@ -110,7 +118,10 @@ impl Intersectable for Cylinder {
             normal = -normal;
         }
 
-        Some(Intersection::new(point, normal, t, *ray))
+        // following the circumference around the axis
+        let tangent = axis.cross(normal);
+
+        Some(Intersection::new(point, normal, t, *ray).with_tangent(tangent))
     }
 
     fn intersects(&self, ray: &Ray) -> bool {
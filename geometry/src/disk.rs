@@ -50,7 +50,10 @@ impl Intersectable for Disk {
             return None;
         }
 
-        Some(Intersection::new(point, self.normal, t, *ray))
+        // following the circles around the center, e.g. for turned metal
+        let tangent = self.normal.cross(point - self.center);
+
+        Some(Intersection::new(point, self.normal, t, *ray).with_tangent(tangent))
     }
 
     fn intersects(&self, ray: &Ray) -> bool {
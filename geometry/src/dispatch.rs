@@ -0,0 +1,127 @@
+//! Enum-dispatched geometries, replacing `Box<dyn Geometry>` with the `static-dispatch` feature.
+//!
+//! The (de-)serialization format is the same as the one of the `typetag` trait objects, so scene
+//! files stay compatible.
+
+use crate::*;
+use serde::de::{self, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+macro_rules! geometries {
+    ($($variant:ident),+ $(,)?) => {
+        /// A closed set of all geometries, allowing static dispatch.
+        // Boxing the large variants would reintroduce the pointer chase this enum avoids.
+        #[allow(clippy::large_enum_variant)]
+        pub enum Geometries {
+            $($variant($variant)),+
+        }
+
+        $(
+            impl From<$variant> for Geometries {
+                fn from(geometry: $variant) -> Self {
+                    Self::$variant(geometry)
+                }
+            }
+        )+
+
+        impl Geometries {
+            /// Returns the number of triangles this geometry consists of.
+            #[inline]
+            pub fn triangle_count(&self) -> usize {
+                match self {
+                    $(Geometries::$variant(g) => g.triangle_count()),+
+                }
+            }
+        }
+
+        impl Boundable for Geometries {
+            #[inline]
+            fn bounds(&self) -> Aabb {
+                match self {
+                    $(Geometries::$variant(g) => g.bounds()),+
+                }
+            }
+        }
+
+        impl Intersectable for Geometries {
+            #[inline]
+            fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+                match self {
+                    $(Geometries::$variant(g) => g.intersect(ray)),+
+                }
+            }
+
+            #[inline]
+            fn intersects(&self, ray: &Ray) -> bool {
+                match self {
+                    $(Geometries::$variant(g) => g.intersects(ray)),+
+                }
+            }
+        }
+
+        impl Serialize for Geometries {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(Some(1))?;
+                match self {
+                    $(Geometries::$variant(g) => map.serialize_entry(stringify!($variant), g)?),+
+                }
+                map.end()
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Geometries {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                const VARIANTS: &[&str] = &[$(stringify!($variant)),+];
+
+                struct GeometriesVisitor;
+
+                impl<'de> Visitor<'de> for GeometriesVisitor {
+                    type Value = Geometries;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("a map containing a single geometry")
+                    }
+
+                    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+                    where
+                        A: MapAccess<'de>,
+                    {
+                        let name: String = map
+                            .next_key()?
+                            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+                        match name.as_str() {
+                            $(stringify!($variant) => Ok(Geometries::$variant(map.next_value()?)),)+
+                            _ => Err(de::Error::unknown_variant(&name, VARIANTS)),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_map(GeometriesVisitor)
+            }
+        }
+    };
+}
+
+geometries!(
+    Aabb,
+    BiconvexLens,
+    Bubble,
+    Cylinder,
+    Disk,
+    Instance,
+    Mesh,
+    Plane,
+    Point,
+    RoundedBox,
+    Sphere,
+    SphereFlake,
+);
@@ -0,0 +1,178 @@
+use crate::*;
+use crate::{Aabb, Boundable, Geometry, Intersectable, Intersection, Ray};
+use serde::{Deserialize, Serialize};
+use utility::floats::FloatExt;
+
+/// Any geometry placed by a similarity transform, i.e. a uniform scale, followed by a rotation and
+/// a translation, e.g. to instance a mesh multiple times.
+#[derive(Serialize, Deserialize)]
+#[serde(from = "InstanceSerde")]
+pub struct Instance {
+    geometry: Box<dyn Geometry>,
+    /// Optional uniform scaling (1st application)
+    scale: Option<Float>,
+    /// Optional rotation (2nd application)
+    /// - params: (axis, angle)
+    rotation: Option<(Vector3, Float)>,
+    /// Optional translation (3rd application)
+    translation: Option<Vector3>,
+    #[serde(skip)]
+    matrix: Matrix3,
+}
+
+/// The serialized form of an [`Instance`](Instance), without the rotation matrix.
+#[derive(Deserialize)]
+struct InstanceSerde {
+    geometry: Box<dyn Geometry>,
+    #[serde(default)]
+    scale: Option<Float>,
+    #[serde(default)]
+    rotation: Option<(Vector3, Float)>,
+    #[serde(default)]
+    translation: Option<Vector3>,
+}
+
+impl Instance {
+    /// Creates a new instance.
+    ///
+    /// # Constraints
+    /// * `scale` - Should be greater than `0`.
+    /// * `rotation` - The axis should be normalized.
+    ///
+    /// # Arguments
+    /// * `geometry` - The geometry in object space
+    /// * `scale` - The optional uniform scale
+    /// * `rotation` - The optional rotation `(axis, angle)`
+    /// * `translation` - The optional translation
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(
+        geometry: Box<dyn Geometry>,
+        scale: Option<Float>,
+        rotation: Option<(Vector3, Float)>,
+        translation: Option<Vector3>,
+    ) -> Self {
+        debug_assert!(scale.is_none_or(|s| s > 0.0));
+
+        let matrix = match rotation {
+            Some((axis, angle)) => Matrix3::from_rotation_around(axis, angle),
+            None => Matrix3::identity(),
+        };
+
+        Self {
+            geometry,
+            scale,
+            rotation,
+            translation,
+            matrix,
+        }
+    }
+
+    #[inline]
+    fn scale(&self) -> Float {
+        self.scale.unwrap_or(1.0)
+    }
+
+    #[inline]
+    fn translation(&self) -> Vector3 {
+        self.translation.unwrap_or_else(Vector3::zero)
+    }
+
+    #[inline]
+    fn point_to_world(&self, point: Vector3) -> Vector3 {
+        self.matrix * (point * self.scale()) + self.translation()
+    }
+
+    #[inline]
+    fn point_to_local(&self, point: Vector3) -> Vector3 {
+        self.matrix.transposed() * (point - self.translation()) / self.scale()
+    }
+
+    #[inline]
+    fn ray_to_local(&self, ray: &Ray) -> Ray {
+        let origin = self.point_to_local(ray.origin);
+        let direction = self.matrix.transposed() * ray.direction;
+        let scale = self.scale();
+
+        Ray::new(
+            origin,
+            direction.normalized(),
+            ray.t_start / scale,
+            ray.t_end / scale,
+        )
+    }
+}
+
+impl From<InstanceSerde> for Instance {
+    fn from(serde: InstanceSerde) -> Self {
+        Self::new(
+            serde.geometry,
+            serde.scale,
+            serde.rotation,
+            serde.translation,
+        )
+    }
+}
+
+impl Boundable for Instance {
+    fn bounds(&self) -> Aabb {
+        let local = self.geometry.bounds();
+
+        Aabb::union_all((0..8).map(|i| {
+            let corner = Vector3::new(
+                if i & 1 == 0 { local.min.x } else { local.max.x },
+                if i & 2 == 0 { local.min.y } else { local.max.y },
+                if i & 4 == 0 { local.min.z } else { local.max.z },
+            );
+            let corner = self.point_to_world(corner);
+
+            Aabb::new(corner, corner)
+        }))
+    }
+}
+
+impl Intersectable for Instance {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let i = self.geometry.intersect(&self.ray_to_local(ray))?;
+
+        let point = self.point_to_world(i.point);
+        let normal = (self.matrix * i.normal).normalized();
+        let t = (i.t * self.scale()).fast_clamp(ray.t_start, ray.t_end);
+
+        let mut intersection = Intersection::new(point, normal, t, *ray);
+        intersection.uv = i.uv;
+
+        match i.tangent {
+            Some(tangent) => Some(intersection.with_tangent(self.matrix * tangent)),
+            None => Some(intersection),
+        }
+    }
+
+    fn intersects(&self, ray: &Ray) -> bool {
+        self.geometry.intersects(&self.ray_to_local(ray))
+    }
+}
+
+#[typetag::serde]
+impl Geometry for Instance {
+    fn triangle_count(&self) -> usize {
+        self.geometry.triangle_count()
+    }
+}
+
+#[test]
+fn intersect_scaled_and_translated() {
+    let instance = Instance::new(
+        Box::new(Sphere::new(Vector3::zero(), 1.0)),
+        Some(2.0),
+        Some((Vector3::unit_y(), 1.0)),
+        Some(Vector3::new(0.0, 0.0, 5.0)),
+    );
+
+    let ray = Ray::new_fast(Vector3::zero(), Vector3::unit_z());
+    let i = instance.intersect(&ray).unwrap();
+
+    assert!((i.t - 3.0).abs() < 1e-4);
+    assert!((i.normal + Vector3::unit_z()).mag() < 1e-4);
+}
@@ -1,21 +1,31 @@
 mod aabb;
 mod bubble;
 pub mod bvh;
+mod cache;
 mod composite;
 mod cylinder;
 mod debug_util;
 mod disk;
+#[cfg(feature = "static-dispatch")]
+mod dispatch;
+mod instance;
 mod lenses;
 mod mesh;
+pub mod mtl_file;
 pub mod obj_file;
 mod plane;
 mod point;
 mod ray;
+mod rounded_box;
 mod sphere;
+mod sphere_flake;
+mod uv_mapping;
 
 #[cfg(not(feature = "f64"))]
 pub type Float = f32;
 #[cfg(not(feature = "f64"))]
+pub type Vector2 = ultraviolet::Vec2;
+#[cfg(not(feature = "f64"))]
 pub type Vector3 = ultraviolet::Vec3;
 #[cfg(not(feature = "f64"))]
 pub type Rotation3 = ultraviolet::Rotor3;
@@ -28,6 +38,8 @@ use std::f32::consts::{PI, TAU};
 #[cfg(feature = "f64")]
 pub type Float = f64;
 #[cfg(feature = "f64")]
+pub type Vector2 = ultraviolet::DVec2;
+#[cfg(feature = "f64")]
 pub type Vector3 = ultraviolet::DVec3;
 #[cfg(feature = "f64")]
 pub type Rotation3 = ultraviolet::DRotor3;
@@ -43,15 +55,22 @@ pub use plane::*;
 use crate::debug_util::{is_finite, is_normalized};
 pub use aabb::*;
 pub use bubble::*;
+pub use cache::{with_cache_directory, CacheError};
 pub use composite::*;
 pub use cylinder::*;
 pub use disk::*;
+#[cfg(feature = "static-dispatch")]
+pub use dispatch::*;
+pub use instance::*;
 pub use lenses::*;
 pub use mesh::*;
 pub use point::*;
 pub use ray::*;
+pub use rounded_box::*;
 pub use sphere::*;
+pub use sphere_flake::*;
 use utility::floats::FloatExt;
+pub use uv_mapping::*;
 
 /// The unit vectors in all directions.
 #[rustfmt::skip]
@@ -82,15 +101,44 @@ pub const UNIT_VECTORS: [Vector3; 6] = [
 /// # Returns
 /// * The offset point
 pub fn offset_point(point: Vector3, normal: Vector3, direction: Vector3) -> Vector3 {
+    offset_point_by(point, normal, direction, Float::big_epsilon())
+}
+
+/// Offsets a point by the given epsilon into the normal direction, depending on the angle to the
+/// given direction.
+///
+/// # Constraints
+/// * `point` - ALl values should be finite (neither infinite nor `NaN`).
+/// * `normal` - All values should be finite.
+///   Should be normalized.
+/// * `direction` - Should be finite.
+///   (Does not need to be normalized.)
+/// * `epsilon` - Should be positive.
+///
+/// # Arguments
+/// * `point` - The starting point
+/// * `normal` - The normal vector to offset towards
+/// * `direction` - The direction helper to decide whether to invert the normal
+/// * `epsilon` - The offset distance
+///
+/// # Returns
+/// * The offset point
+pub fn offset_point_by(
+    point: Vector3,
+    normal: Vector3,
+    direction: Vector3,
+    epsilon: Float,
+) -> Vector3 {
     debug_assert!(is_finite(&point));
     debug_assert!(is_finite(&normal));
     debug_assert!(is_normalized(&normal));
     debug_assert!(is_finite(&direction));
+    debug_assert!(epsilon > 0.0);
 
     let offset = if direction.dot(normal) >= 0.0 {
-        normal * Float::big_epsilon()
+        normal * epsilon
     } else {
-        normal * -Float::big_epsilon()
+        normal * -epsilon
     };
 
     point + offset
@@ -118,13 +166,41 @@ pub fn offset_point(point: Vector3, normal: Vector3, direction: Vector3) -> Vect
 /// # Returns
 /// * Ray from this intersection, offset by an epsilon
 pub fn offset_ray_towards(point: Vector3, normal: Vector3, direction: Vector3) -> Ray {
+    offset_ray_towards_by(point, normal, direction, Float::big_epsilon())
+}
+
+/// Offsets a point by the given epsilon into the normal direction, depending on the angle to the
+/// given direction and creates a ray from it.
+///
+/// # Constraints
+/// * `point` - ALl values should be finite (neither infinite nor `NaN`).
+/// * `normal` - All values should be finite.
+///   Should be normalized.
+/// * `direction` - Should be finite.
+///   Should be normalized.
+/// * `epsilon` - Should be positive.
+///
+/// # Arguments
+/// * `point` - The starting point
+/// * `normal` - The normal vector to offset towards
+/// * `direction` - The direction of the ray
+/// * `epsilon` - The offset distance
+///
+/// # Returns
+/// * Ray from this intersection, offset by the epsilon
+pub fn offset_ray_towards_by(
+    point: Vector3,
+    normal: Vector3,
+    direction: Vector3,
+    epsilon: Float,
+) -> Ray {
     debug_assert!(is_finite(&point));
     debug_assert!(is_finite(&normal));
     debug_assert!(is_normalized(&normal));
     debug_assert!(is_finite(&direction));
     debug_assert!(is_normalized(&direction));
 
-    let origin = offset_point(point, normal, direction);
+    let origin = offset_point_by(point, normal, direction, epsilon);
 
     Ray::new_fast(origin, direction)
 }
@@ -303,17 +379,22 @@ pub fn spherical_to_cartesian_trig(
     Vector3::new(x, y, z)
 }
 
-/// An intersection consists of the following 4 properties:
+/// An intersection consists of the following 5 properties:
 /// * `point` - The intersection point
 /// * `normal` - The surface normal (showing outside, even if intersection hits inside!)
 /// * `t` - The ray parameter at which it intersects
 /// * `ray` - The reference to the intersecting ray
+/// * `tangent` - The surface tangent following the parameterization (if any), orienting
+///   anisotropic materials
+/// * `uv` - The texture coordinates (if any)
 #[derive(Clone)]
 pub struct Intersection {
     pub point: Vector3,
     pub normal: Vector3,
     pub t: Float,
     pub ray: Ray,
+    pub tangent: Option<Vector3>,
+    pub uv: Option<Vector2>,
 }
 
 impl Intersection {
@@ -340,8 +421,40 @@ impl Intersection {
             normal,
             t,
             ray,
+            tangent: None,
+            uv: None,
         }
     }
+
+    /// Sets the surface tangent, e.g. the derivative of the point by the `u` texture coordinate.
+    /// Degenerate tangents (vanishing or parallel to the normal) get ignored.
+    ///
+    /// # Arguments
+    /// * `tangent` - The (unnormalized) tangent
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with_tangent(mut self, tangent: Vector3) -> Self {
+        let projected = tangent - self.normal * self.normal.dot(tangent);
+
+        if projected.mag_sq() > Float::big_epsilon() * tangent.mag_sq() && is_finite(&projected) {
+            self.tangent = Some(projected.normalized());
+        }
+
+        self
+    }
+
+    /// Sets the texture coordinates of the surface point.
+    ///
+    /// # Arguments
+    /// * `uv` - The texture coordinates
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with_uv(mut self, uv: Vector2) -> Self {
+        self.uv = Some(uv);
+        self
+    }
 }
 
 /// A coordinate system represents 3 (orthogonal) vectors in 3D space.
@@ -396,19 +509,11 @@ impl CoordinateSystem {
     ///
     /// # Returns
     /// * Self
-    // TODO: Make more efficient
+    #[inline]
     pub fn from_x(x_axis: Vector3) -> Self {
-        debug_assert!(is_finite(&x_axis));
-        debug_assert!(is_normalized(&x_axis));
+        let (y, z) = orthonormal_basis(x_axis);
 
-        if x_axis == Vector3::unit_x() {
-            Self::new(Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z())
-        } else {
-            let z = x_axis.cross(Vector3::unit_x()).normalized();
-            let y = z.cross(x_axis).normalized();
-
-            Self::new(x_axis, y, z)
-        }
+        Self::new(x_axis, y, z)
     }
 
     /// Creates a new coordinate system around the given `y` direction vector.
@@ -422,28 +527,11 @@ impl CoordinateSystem {
     ///
     /// # Returns
     /// * Self
-    // TODO: Make more efficient
+    #[inline]
     pub fn from_y(y_axis: Vector3) -> Self {
-        debug_assert!(is_finite(&y_axis));
-        debug_assert!(is_normalized(&y_axis));
-
-        let s = if y_axis.x.abs() > y_axis.y.abs() {
-            let l = (y_axis.x * y_axis.x + y_axis.z * y_axis.z).sqrt();
-            Vector3::new(-y_axis.z / l, 0.0, y_axis.x / l)
-        } else {
-            let l = (y_axis.y * y_axis.y + y_axis.z * y_axis.z).sqrt();
-            Vector3::new(0.0, y_axis.z / l, -y_axis.y / l)
-        };
-        Self::new(s, y_axis, y_axis.cross(s))
-
-        // if y_axis == Vector3::unit_y() {
-        //     Self::new(Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z())
-        // } else {
-        //     let x = y_axis.cross(Vector3::unit_y()).normalized();
-        //     let z = x.cross(y_axis).normalized();
-        //
-        //     Self::new(x, y_axis, z)
-        // }
+        let (z, x) = orthonormal_basis(y_axis);
+
+        Self::new(x, y_axis, z)
     }
 
     /// Creates a new coordinate system around the given `z` direction vector.
@@ -457,22 +545,65 @@ impl CoordinateSystem {
     ///
     /// # Returns
     /// * Self
-    // TODO: Make more efficient
+    #[inline]
     pub fn from_z(z_axis: Vector3) -> Self {
-        debug_assert!(is_finite(&z_axis));
-        debug_assert!(is_normalized(&z_axis));
+        let (x, y) = orthonormal_basis(z_axis);
 
-        if z_axis == Vector3::unit_z() {
-            Self::new(Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z())
-        } else {
-            let x = z_axis.cross(Vector3::unit_z()).normalized();
-            let y = z_axis.cross(x).normalized();
+        Self::new(x, y, z_axis)
+    }
 
-            Self::new(x, y, z_axis)
-        }
+    /// Transforms the given world vector into the local space of this coordinate system.
+    ///
+    /// # Arguments
+    /// * `v` - The world vector
+    ///
+    /// # Returns
+    /// * The local vector
+    #[inline]
+    pub fn to_local(&self, v: Vector3) -> Vector3 {
+        Vector3::new(v.dot(self.x_axis), v.dot(self.y_axis), v.dot(self.z_axis))
+    }
+
+    /// Transforms the given local vector of this coordinate system into world space.
+    ///
+    /// # Arguments
+    /// * `v` - The local vector
+    ///
+    /// # Returns
+    /// * The world vector
+    #[inline]
+    pub fn to_world(&self, v: Vector3) -> Vector3 {
+        self.x_axis * v.x + self.y_axis * v.y + self.z_axis * v.z
     }
 }
 
+/// Computes two vectors completing the given normal to a right-handed orthonormal basis, using
+/// the branchless construction of Duff et al. (2017), which stays stable near the poles.
+///
+/// # Constraints
+/// * `n` - All values must be finite (neither infinite nor `NaN`).
+///   Should be normalized.
+///
+/// # Arguments
+/// * `n` - The normal
+///
+/// # Returns
+/// * The vectors `(b1, b2)`, such that `b1 × b2 = n`
+#[inline]
+pub fn orthonormal_basis(n: Vector3) -> (Vector3, Vector3) {
+    debug_assert!(is_finite(&n));
+    debug_assert!(is_normalized(&n));
+
+    let sign = Float::copysign(1.0, n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+
+    let b1 = Vector3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+    let b2 = Vector3::new(b, sign + n.y * n.y * a, -n.y);
+
+    (b1, b2)
+}
+
 impl Default for CoordinateSystem {
     fn default() -> Self {
         Self::new(Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z())
@@ -550,7 +681,15 @@ pub trait Intersectable {
 
 /// A super-trait to combine `Boundable` and `Intersectable`, therefore giving a valid geometry.
 #[typetag::serde]
-pub trait Geometry: Boundable + Intersectable + Send + Sync {}
+pub trait Geometry: Boundable + Intersectable + Send + Sync {
+    /// Returns the number of triangles this geometry consists of.
+    ///
+    /// # Returns
+    /// * The number of triangles (`0` for analytic geometries)
+    fn triangle_count(&self) -> usize {
+        0
+    }
+}
 
 impl Boundable for Box<dyn Geometry> {
     fn bounds(&self) -> Aabb {
@@ -569,7 +708,11 @@ impl Intersectable for Box<dyn Geometry> {
 }
 
 #[typetag::serde]
-impl Geometry for Box<dyn Geometry> {}
+impl Geometry for Box<dyn Geometry> {
+    fn triangle_count(&self) -> usize {
+        self.as_ref().triangle_count()
+    }
+}
 
 pub trait ContainerGeometry: Container + Intersectable {
     fn contains_or_intersects(&self, ray: &Ray) -> bool {
@@ -578,3 +721,41 @@ pub trait ContainerGeometry: Container + Intersectable {
 }
 
 impl<T> ContainerGeometry for T where T: Container + Intersectable {}
+
+#[test]
+fn orthonormal_bases() {
+    let normals = [
+        Vector3::unit_z(),
+        -Vector3::unit_z(),
+        Vector3::unit_x(),
+        -Vector3::unit_y(),
+        // close to the poles, where the construction switches its sign
+        Vector3::new(1e-4, -2e-4, -1.0).normalized(),
+        Vector3::new(-3e-7, 1e-7, 1.0).normalized(),
+        Vector3::new(0.3, -0.8, 0.1).normalized(),
+    ];
+
+    for &n in &normals {
+        let (b1, b2) = orthonormal_basis(n);
+
+        assert!((b1.mag() - 1.0).abs() < 1e-5, "{:?} of {:?}", b1, n);
+        assert!((b2.mag() - 1.0).abs() < 1e-5, "{:?} of {:?}", b2, n);
+        assert!(b1.dot(b2).abs() < 1e-5);
+        assert!(b1.dot(n).abs() < 1e-5);
+        assert!(b2.dot(n).abs() < 1e-5);
+        // right-handed
+        assert!((b1.cross(b2) - n).mag() < 1e-5);
+
+        for system in &[
+            CoordinateSystem::from_x(n),
+            CoordinateSystem::from_y(n),
+            CoordinateSystem::from_z(n),
+        ] {
+            let x = system.x_axis.cross(system.y_axis);
+            assert!((x - system.z_axis).mag() < 1e-5);
+
+            let v = Vector3::new(0.2, -0.5, 0.7);
+            assert!((system.to_world(system.to_local(v)) - v).mag() < 1e-5);
+        }
+    }
+}
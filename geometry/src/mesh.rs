@@ -1,4 +1,6 @@
 use crate::bvh::Tree;
+use crate::cache;
+use crate::cache::CachedMesh;
 use crate::debug_util::is_finite;
 use crate::obj_file::ObjFile;
 #[allow(unused_imports)]
@@ -6,17 +8,19 @@ use crate::*;
 use serde::de::{Error, MapAccess, Visitor};
 use serde::ser::SerializeStruct;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::{Cell, RefCell};
 use std::fmt;
 use std::fmt::Debug;
 #[cfg(feature = "watertight-mesh")]
 use std::mem::swap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use utility::distribution::AliasTable;
 #[cfg(not(feature = "watertight-mesh"))]
 use utility::floats::FloatExt;
 
 /// The shading mode defines the shading of normals. In `Flat` mode, the surface of triangles will
 /// appear flat. In `Phong` however, they will be interpolated to create a smooth looking surface.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum ShadingMode {
     Flat,
     Phong,
@@ -42,6 +46,9 @@ pub struct Vertex {
     pub position: Vector3,
     #[serde(default)]
     pub normal: Vector3,
+    /// The texture coordinates
+    #[serde(default)]
+    pub uv: Vector2,
 }
 
 /// A triangle consists of vertex indices `(v0, v1, v2)`.
@@ -67,6 +74,57 @@ impl Face {
         )
     }
 
+    /// Returns the geometric normal of this triangle, following its winding order.
+    ///
+    /// # Arguments
+    /// * `vertices` - The vertices of the mesh
+    ///
+    /// # Returns
+    /// * The normalized geometric normal
+    pub fn geometric_normal(&self, vertices: &[Vertex]) -> Vector3 {
+        let (v0, v1, v2) = self.get_vertices(vertices);
+
+        (v1.position - v0.position)
+            .cross(v2.position - v0.position)
+            .normalized()
+    }
+
+    /// Returns the tangent of this triangle along the `u` texture coordinate.
+    ///
+    /// # Arguments
+    /// * `vertices` - The vertices of the mesh
+    ///
+    /// # Returns
+    /// * The unnormalized tangent, or `None` if the texture coordinates are degenerate
+    pub fn tangent(&self, vertices: &[Vertex]) -> Option<Vector3> {
+        let (v0, v1, v2) = self.get_vertices(vertices);
+
+        let (duv02, duv12) = (v0.uv - v2.uv, v1.uv - v2.uv);
+        let (dp02, dp12) = (v0.position - v2.position, v1.position - v2.position);
+
+        let det = duv02.x * duv12.y - duv02.y * duv12.x;
+        if det.is_approx_zero() {
+            return None;
+        }
+
+        Some((dp02 * duv12.y - dp12 * duv02.y) / det)
+    }
+
+    /// Returns the area of this triangle.
+    ///
+    /// # Arguments
+    /// * `vertices` - The vertices of the mesh
+    ///
+    /// # Returns
+    /// * The area
+    pub fn area(&self, vertices: &[Vertex]) -> Float {
+        let (v0, v1, v2) = self.get_vertices(vertices);
+
+        0.5 * (v1.position - v0.position)
+            .cross(v2.position - v0.position)
+            .mag()
+    }
+
     pub fn bounds(&self, vertices: &[Vertex]) -> Aabb {
         let (v0, v1, v2) = self.get_vertices(vertices);
         let (v0, v1, v2) = (v0.position, v1.position, v2.position);
@@ -126,8 +184,8 @@ impl Face {
         #[allow(unused_mut)]
         let mut w = bx * ay - by * ax;
 
-        // perform edge tests
-        if u < 0.0 || v < 0.0 || w < 0.0 {
+        // perform edge tests, hitting the triangle from both sides
+        if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
             return None;
         }
 
@@ -145,7 +203,7 @@ impl Face {
             }
 
             // perform edge tests
-            if u < 0.0 || v < 0.0 || w < 0.0 {
+            if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
                 return None;
             }
         }
@@ -183,7 +241,14 @@ impl Face {
         }
         .normalized();
 
-        Some(Intersection::new(point, normal, t, *ray))
+        // the scaled barycentric coordinates weight the vertices like the hit distance above
+        let uv = (u * v0.uv + v * v1.uv + w * v2.uv) * inv_det;
+
+        let intersection = Intersection::new(point, normal, t, *ray);
+        match self.tangent(&mesh.vertices) {
+            Some(tangent) => Some(intersection.with_tangent(tangent).with_uv(uv)),
+            None => Some(intersection),
+        }
     }
 
     #[cfg(not(feature = "watertight-mesh"))]
@@ -232,7 +297,14 @@ impl Face {
         }
         .normalized();
 
-        Some(Intersection::new(point, normal, t, *ray))
+        let uv = (1.0 - beta - gamma) * v0.uv + beta * v1.uv + gamma * v2.uv;
+
+        // degenerate texture coordinates mean the mesh has none
+        let intersection = Intersection::new(point, normal, t, *ray);
+        match self.tangent(&mesh.vertices) {
+            Some(tangent) => Some(intersection.with_tangent(tangent).with_uv(uv)),
+            None => Some(intersection),
+        }
     }
 
     #[cfg(feature = "watertight-mesh")]
@@ -284,8 +356,8 @@ impl Face {
         #[allow(unused_mut)]
         let mut w = bx * ay - by * ax;
 
-        // perform edge tests
-        if u < 0.0 || v < 0.0 || w < 0.0 {
+        // perform edge tests, hitting the triangle from both sides
+        if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
             return false;
         }
 
@@ -303,7 +375,7 @@ impl Face {
             }
 
             // perform edge tests
-            if u < 0.0 || v < 0.0 || w < 0.0 {
+            if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
                 return false;
             }
         }
@@ -375,6 +447,197 @@ pub struct FromObjFile {
     #[serde(default)]
     /// Optional translation (3rd application)
     translation: Option<Vector3>,
+    #[serde(default)]
+    /// Optional tolerance to weld vertices at, also removing faces without area
+    weld: Option<Float>,
+    #[serde(default)]
+    /// Optional group to select the faces of (`g` or `o`)
+    group: Option<String>,
+    #[serde(default)]
+    /// Optional material to select the faces of (`usemtl`)
+    material: Option<String>,
+}
+
+impl FromObjFile {
+    /// Creates new settings loading the whole obj file without transformations.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the obj file as written
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            scale: None,
+            rotation: None,
+            translation: None,
+            weld: None,
+            group: None,
+            material: None,
+        }
+    }
+
+    /// Returns the path of the obj file as written.
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Selects the faces of the given group and material only.
+    ///
+    /// # Arguments
+    /// * `group` - The group name (`None` for all groups)
+    /// * `material` - The material (`None` for all materials)
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with_part(mut self, group: Option<String>, material: Option<String>) -> Self {
+        self.group = group;
+        self.material = material;
+        self
+    }
+}
+
+/// The default number of faces below which meshes get intersected by testing all faces instead of
+/// building a BVH, as traversing the tree of a tiny mesh costs more than it saves
+/// (see `benches/mesh.rs`).
+pub const DEFAULT_BVH_THRESHOLD: usize = 16;
+
+thread_local! {
+    /// The number of faces below which meshes skip their BVH.
+    static BVH_THRESHOLD: Cell<usize> = const { Cell::new(DEFAULT_BVH_THRESHOLD) };
+    /// The directory to resolve relative obj file paths against while deserializing meshes.
+    static BASE_DIRECTORY: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+    /// The asset files loaded while [recording](record_assets).
+    static ASSETS: RefCell<Option<Vec<AssetReference>>> = const { RefCell::new(None) };
+}
+
+/// A reference to an asset file (e.g. an obj file) of a scene file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetReference {
+    /// The path as written
+    pub path: String,
+    /// The path resolved against the [base directory](with_base_directory)
+    pub resolved: PathBuf,
+}
+
+/// Runs the given function, resolving the relative paths of [obj files](FromObjFile) deserialized
+/// inside against the given directory (e.g. the one of the scene file) instead of the working
+/// directory.
+///
+/// # Arguments
+/// * `directory` - The directory to resolve against (the working directory if `None`)
+/// * `f` - The function deserializing meshes
+///
+/// # Returns
+/// * The result of the function
+pub fn with_base_directory<T, F>(directory: Option<&Path>, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let previous = BASE_DIRECTORY.with(|b| b.replace(directory.map(Path::to_path_buf)));
+    let result = f();
+    BASE_DIRECTORY.with(|b| *b.borrow_mut() = previous);
+
+    result
+}
+
+/// Runs the given function, intersecting meshes with fewer faces than the given threshold built
+/// inside by testing all faces instead of building a BVH.
+///
+/// # Arguments
+/// * `threshold` - The number of faces from which on meshes build a BVH (always if `0`)
+/// * `f` - The function building meshes
+///
+/// # Returns
+/// * The result of the function
+pub fn with_bvh_threshold<T, F>(threshold: usize, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let previous = BVH_THRESHOLD.with(|t| t.replace(threshold));
+    let result = f();
+    BVH_THRESHOLD.with(|t| t.set(previous));
+
+    result
+}
+
+/// Returns whether a mesh with the given number of faces builds a BVH under the current
+/// [threshold](with_bvh_threshold).
+fn uses_bvh(faces: usize) -> bool {
+    faces >= BVH_THRESHOLD.with(Cell::get)
+}
+
+/// Runs the given function, recording the asset files of all meshes (or other objects using
+/// [resolve_asset]) deserialized inside.
+///
+/// # Arguments
+/// * `f` - The function deserializing the assets
+///
+/// # Returns
+/// * The result of the function
+/// * The references to the loaded asset files in loading order
+pub fn record_assets<T, F>(f: F) -> (T, Vec<AssetReference>)
+where
+    F: FnOnce() -> T,
+{
+    let previous = ASSETS.with(|a| a.replace(Some(Vec::new())));
+    let result = f();
+    let references = ASSETS.with(|a| a.replace(previous)).unwrap_or_default();
+
+    (result, references)
+}
+
+/// Resolves the given asset file path against the [base directory](with_base_directory) and
+/// [records](record_assets) it.
+///
+/// This is meant to be called while deserializing objects loading external files.
+///
+/// # Arguments
+/// * `path` - The path as written
+///
+/// # Returns
+/// * The resolved path
+pub fn resolve_asset(path: &str) -> PathBuf {
+    let resolved = BASE_DIRECTORY.with(|b| resolve_path(path, b.borrow().as_deref()));
+
+    ASSETS.with(|a| {
+        if let Some(references) = a.borrow_mut().as_mut() {
+            references.push(AssetReference {
+                path: path.to_string(),
+                resolved: resolved.clone(),
+            });
+        }
+    });
+
+    resolved
+}
+
+/// Resolves the given file path against the [base directory](with_base_directory) without
+/// [recording](record_assets) it.
+///
+/// This is meant for files referenced by other assets instead of the scene file, e.g. the mtl
+/// file of an obj file, whose paths cannot be rewritten when packing the scene.
+///
+/// # Arguments
+/// * `path` - The path relative to the scene file
+///
+/// # Returns
+/// * The resolved path
+pub fn resolve_dependency(path: &str) -> PathBuf {
+    BASE_DIRECTORY.with(|b| resolve_path(path, b.borrow().as_deref()))
+}
+
+/// Resolves the given file path against the base directory, accepting both `/` and `\` as
+/// separators on all platforms.
+fn resolve_path(path: &str, base: Option<&Path>) -> PathBuf {
+    let path = PathBuf::from(path.replace('\\', "/"));
+
+    match base {
+        Some(base) if path.is_relative() => base.join(path),
+        _ => path,
+    }
 }
 
 /// A mesh consists of vertices and triangles, allowing queries for intersections.
@@ -384,10 +647,27 @@ pub struct Mesh {
     faces: Vec<Face>,
     bounds: Aabb,
     shading_mode: ShadingMode,
-    bvh: Tree<Face>,
+    /// Whether the mesh forms a closed surface, allowing occlusion tests to skip back faces
+    closed: bool,
+    /// The BVH over the faces, `None` if the faces get tested linearly
+    bvh: Option<Tree<Face>>,
+    /// The faces by their area, to sample the surface uniformly
+    areas: AliasTable,
+    surface_area: Float,
     obj: Option<FromObjFile>,
 }
 
+/// Creates the alias table of the areas of the given faces.
+///
+/// # Returns
+/// * The alias table
+/// * The total area
+fn face_areas(vertices: &[Vertex], faces: &[Face]) -> (AliasTable, Float) {
+    let areas: Vec<Float> = faces.iter().map(|f| f.area(vertices)).collect();
+
+    (AliasTable::new(&areas), areas.iter().sum())
+}
+
 impl Mesh {
     pub fn new(
         vertices: Vec<Vertex>,
@@ -396,12 +676,17 @@ impl Mesh {
         shading_mode: ShadingMode,
         obj: Option<FromObjFile>,
     ) -> Self {
+        let (areas, surface_area) = face_areas(&vertices, &faces);
+
         Self {
             vertices,
             faces,
             bounds,
             shading_mode,
-            bvh: Default::default(),
+            closed: false,
+            bvh: None,
+            areas,
+            surface_area,
             obj,
         }
     }
@@ -419,7 +704,59 @@ impl Mesh {
     where
         P: AsRef<Path> + Debug,
     {
-        let obj_file = ObjFile::from(&path);
+        Self::load_welded(path, shading_mode, None)
+    }
+
+    /// Loads the given obj file like [load](Mesh::load), optionally welding vertices closer than
+    /// the given tolerance and removing faces without area.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the obj file
+    /// * `shading_mode` - The shading mode
+    /// * `weld` - The welding tolerance (if any)
+    ///
+    /// # Returns
+    /// * Self
+    pub fn load_welded<P>(path: P, shading_mode: ShadingMode, weld: Option<Float>) -> Mesh
+    where
+        P: AsRef<Path> + Debug,
+    {
+        let mut obj = FromObjFile::new(path.as_ref().to_string_lossy().into());
+        obj.weld = weld;
+
+        Self::load_part(path, shading_mode, obj)
+    }
+
+    /// Loads the part of the given obj file selected by the group and material of the settings,
+    /// welding it if requested. The transformations of the settings do not get applied.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the obj file
+    /// * `shading_mode` - The shading mode
+    /// * `obj` - The settings to keep for serialization
+    ///
+    /// # Returns
+    /// * Self
+    fn load_part<P>(path: P, shading_mode: ShadingMode, obj: FromObjFile) -> Mesh
+    where
+        P: AsRef<Path> + Debug,
+    {
+        let mut obj_file = ObjFile::from(&path);
+
+        if obj.group.is_some() || obj.material.is_some() {
+            obj_file = obj_file
+                .select(obj.group.as_deref(), obj.material.as_deref())
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Obj file {:?} contains no faces of group {:?} and material {:?}",
+                        path, obj.group, obj.material
+                    )
+                });
+        }
+
+        if let Some(tolerance) = obj.weld {
+            obj_file.weld(tolerance);
+        }
 
         let mut bounds = Aabb::empty();
 
@@ -433,15 +770,65 @@ impl Mesh {
             obj_file.faces,
             bounds,
             shading_mode,
-            Some(FromObjFile {
-                path: path.as_ref().to_str().unwrap().into(),
-                scale: None,
-                translation: None,
-                rotation: None,
-            }),
+            Some(obj),
         )
     }
 
+    /// Loads a mesh from an obj file as described by the settings, resolving the path as an
+    /// [asset](resolve_asset) and using the [mesh cache](crate::with_cache_directory) if enabled.
+    ///
+    /// This is meant to be called while deserializing objects loading external files.
+    ///
+    /// # Arguments
+    /// * `obj` - The obj file settings
+    /// * `shading_mode` - The shading mode
+    /// * `closed` - Whether the mesh forms a closed surface
+    ///
+    /// # Returns
+    /// * Self
+    pub fn from_obj(obj: FromObjFile, shading_mode: ShadingMode, closed: bool) -> Mesh {
+        let path = resolve_asset(&obj.path);
+        let settings = (
+            obj.scale,
+            obj.rotation,
+            obj.translation,
+            obj.weld,
+            closed,
+            &obj.group,
+            &obj.material,
+        );
+        let settings = bincode::serialize(&settings).unwrap_or_default();
+        let entry = cache::entry(&path, settings);
+
+        if let Some(cached) = entry.as_deref().and_then(cache::load) {
+            if let Some(mesh) = Mesh::from_cache(cached, shading_mode, closed, &obj) {
+                return mesh;
+            }
+        }
+
+        let mut mesh = Mesh::load_part(path, shading_mode, obj.clone());
+
+        if let Some(scale) = obj.scale {
+            mesh.scale(scale);
+        }
+        if let Some((axis, angle)) = obj.rotation {
+            let rotation = Matrix3::from_rotation_around(axis, angle);
+            mesh.transform(rotation);
+        }
+        if let Some(translation) = obj.translation {
+            mesh.translate(translation);
+        }
+        mesh.set_closed(closed).update_bounds().build_bvh();
+        // keep the path as written, serializing independent of the working directory
+        mesh.obj = Some(obj);
+
+        if let Some(entry) = entry {
+            cache::store(&entry, &mesh.to_cache());
+        }
+
+        mesh
+    }
+
     /// Determines the weights by which to scale triangle (p0, p1, p2)'s normal when
     /// accumulating the vertex normal for vertices 0, 1, 2.
     ///
@@ -576,11 +963,139 @@ impl Mesh {
         self
     }
 
+    #[inline]
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    #[inline]
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    /// Marks this mesh as a closed surface (e.g. the dragon), letting occlusion tests skip
+    /// subtrees of back-facing triangles.
+    /// Rebuild the BVH afterwards with [build_bvh](Mesh::build_bvh).
+    ///
+    /// # Constraints
+    /// * The triangles should be consistently wound, with normals pointing outwards.
+    ///
+    /// # Arguments
+    /// * `closed` - Whether the mesh is closed
+    ///
+    /// # Returns
+    /// * Self for chained operations.
+    pub fn set_closed(&mut self, closed: bool) -> &mut Self {
+        self.closed = closed;
+
+        self
+    }
+
+    /// Builds the BVH over the faces, unless there are fewer faces than the
+    /// [threshold](with_bvh_threshold) and testing all of them is cheaper. The areas of the faces
+    /// get updated as well.
     pub fn build_bvh(&mut self) -> &mut Self {
-        self.bvh = Tree::new(self.faces.clone(), |f| f.bounds(&self.vertices));
+        self.bvh = if uses_bvh(self.faces.len()) {
+            Some(Tree::new(self.faces.clone(), |f| f.bounds(&self.vertices)))
+        } else {
+            None
+        };
+        self.build_normal_cones();
+
+        let (areas, surface_area) = face_areas(&self.vertices, &self.faces);
+        self.areas = areas;
+        self.surface_area = surface_area;
 
         self
     }
+
+    /// Returns the shading mode of the normals.
+    #[inline]
+    pub fn shading_mode(&self) -> ShadingMode {
+        self.shading_mode
+    }
+
+    /// Returns the total area of the faces.
+    #[inline]
+    pub fn surface_area(&self) -> Float {
+        self.surface_area
+    }
+
+    /// Samples a face proportionally to its area.
+    ///
+    /// # Constraints
+    /// * `u` - Should be within `[0, 1)`.
+    /// * `self` - Should have faces.
+    ///
+    /// # Arguments
+    /// * `u` - The uniform random number
+    ///
+    /// # Returns
+    /// * The sampled face
+    /// * The random number, rescaled to `[0, 1)` to be reused
+    pub fn sample_face(&self, u: Float) -> (&Face, Float) {
+        let (index, _, u) = self.areas.sample(u);
+
+        (&self.faces[index], u)
+    }
+
+    /// Returns whether the faces get intersected through a BVH instead of testing all of them.
+    pub fn has_bvh(&self) -> bool {
+        self.bvh.is_some()
+    }
+
+    fn build_normal_cones(&mut self) {
+        if let (true, Some(bvh)) = (self.closed, &mut self.bvh) {
+            let vertices = &self.vertices;
+            bvh.build_normal_cones(|f| f.geometric_normal(vertices));
+        }
+    }
+
+    /// Converts this mesh into its [cached](crate::with_cache_directory) form.
+    fn to_cache(&self) -> CachedMesh {
+        CachedMesh {
+            vertices: self.vertices.clone(),
+            faces: self.faces.clone(),
+            bounds: self.bounds,
+            tree: self.bvh.as_ref().map(|bvh| bvh.to_cache(self.faces.len())),
+        }
+    }
+
+    /// Restores a mesh from its [cached](crate::with_cache_directory) form, skipping the
+    /// construction of the BVH.
+    ///
+    /// # Returns
+    /// * Self, or `None` if the cached BVH does not match the faces or the
+    ///   [threshold](with_bvh_threshold)
+    fn from_cache(
+        cached: CachedMesh,
+        shading_mode: ShadingMode,
+        closed: bool,
+        obj: &FromObjFile,
+    ) -> Option<Self> {
+        let bvh = match (&cached.tree, uses_bvh(cached.faces.len())) {
+            (Some(tree), true) => Some(Tree::from_cache(cached.faces.clone(), tree)?),
+            (None, false) => None,
+            _ => return None,
+        };
+
+        let (areas, surface_area) = face_areas(&cached.vertices, &cached.faces);
+
+        let mut mesh = Self {
+            vertices: cached.vertices,
+            faces: cached.faces,
+            bounds: cached.bounds,
+            shading_mode,
+            closed,
+            bvh,
+            areas,
+            surface_area,
+            obj: Some(obj.clone()),
+        };
+        mesh.build_normal_cones();
+
+        Some(mesh)
+    }
 }
 
 impl Boundable for Mesh {
@@ -595,12 +1110,17 @@ impl Intersectable for Mesh {
         let mut new_ray = *ray;
         let mut intersection = None;
 
-        let hits = self.bvh.intersect(ray);
-        for hit in &hits {
-            if let Some(i) = hit.intersect(self, &new_ray) {
+        let mut test = |face: &Face| {
+            if let Some(i) = face.intersect(self, &new_ray) {
                 new_ray.t_end = i.t;
                 intersection = Some(i);
             }
+        };
+
+        match &self.bvh {
+            Some(bvh) => bvh.intersect(ray).iter().for_each(|hit| test(hit.as_ref())),
+            None if self.bounds.contains_or_intersects(ray) => self.faces.iter().for_each(test),
+            None => {}
         }
 
         if let Some(mut i) = intersection {
@@ -612,24 +1132,36 @@ impl Intersectable for Mesh {
     }
 
     fn intersects(&self, ray: &Ray) -> bool {
-        self.bvh
-            .intersect(ray)
-            .iter()
-            .any(|t| t.intersects(&self.vertices, ray))
+        // equal to a regular intersection unless the mesh is closed
+        match &self.bvh {
+            Some(bvh) => bvh
+                .intersect_occlusion(ray)
+                .iter()
+                .any(|t| t.intersects(&self.vertices, ray)),
+            None => {
+                self.bounds.contains_or_intersects(ray)
+                    && self.faces.iter().any(|t| t.intersects(&self.vertices, ray))
+            }
+        }
     }
 }
 
 #[typetag::serde]
-impl Geometry for Mesh {}
+impl Geometry for Mesh {
+    fn triangle_count(&self) -> usize {
+        self.faces.len()
+    }
+}
 
 impl Serialize for Mesh {
     fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("Mesh", 5)?;
+        let mut state = serializer.serialize_struct("Mesh", 6)?;
 
         state.serialize_field("shading_mode", &self.shading_mode)?;
+        state.serialize_field("closed", &self.closed)?;
 
         if let Some(obj) = &self.obj {
             state.serialize_field("obj_path", obj)?;
@@ -654,6 +1186,7 @@ impl<'de> Deserialize<'de> for Mesh {
             Faces,
             Bounds,
             ShadingMode,
+            Closed,
             Obj,
         }
 
@@ -668,8 +1201,9 @@ impl<'de> Deserialize<'de> for Mesh {
                     type Value = Field;
 
                     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                        formatter
-                            .write_str("`vertices`, `faces`, `bounds`, `shading_mode` or `obj`")
+                        formatter.write_str(
+                            "`vertices`, `faces`, `bounds`, `shading_mode`, `closed` or `obj`",
+                        )
                     }
 
                     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -681,6 +1215,7 @@ impl<'de> Deserialize<'de> for Mesh {
                             "faces" => Ok(Field::Faces),
                             "bounds" => Ok(Field::Bounds),
                             "shading_mode" => Ok(Field::ShadingMode),
+                            "closed" => Ok(Field::Closed),
                             "obj" => Ok(Field::Obj),
                             _ => Err(de::Error::unknown_field(v, FIELDS)),
                         }
@@ -708,6 +1243,7 @@ impl<'de> Deserialize<'de> for Mesh {
                 let mut faces = None;
                 let mut bounds = None;
                 let mut shading_mode = None;
+                let mut closed = None;
                 let mut obj: Option<FromObjFile> = None;
                 while let Some(key) = map.next_key()? {
                     match key {
@@ -745,6 +1281,13 @@ impl<'de> Deserialize<'de> for Mesh {
                                 shading_mode = Some(map.next_value()?);
                             }
                         }
+                        Field::Closed => {
+                            if closed.is_some() {
+                                return Err(de::Error::duplicate_field("closed"));
+                            } else {
+                                closed = Some(map.next_value()?);
+                            }
+                        }
                         Field::Obj => {
                             if obj.is_some() {
                                 return Err(de::Error::duplicate_field("obj"));
@@ -760,37 +1303,202 @@ impl<'de> Deserialize<'de> for Mesh {
                 let shading_mode =
                     shading_mode.ok_or_else(|| de::Error::invalid_length(0, &self))?;
 
-                if let Some(obj) = obj {
-                    let mut mesh = Mesh::load(obj.path, shading_mode);
-
-                    if let Some(scale) = obj.scale {
-                        mesh.scale(scale);
-                    }
-                    if let Some((axis, angle)) = obj.rotation {
-                        let rotation = Matrix3::from_rotation_around(axis, angle);
-                        mesh.transform(rotation);
-                    }
-                    if let Some(translation) = obj.translation {
-                        mesh.translate(translation);
-                    }
+                let closed = closed.unwrap_or(false);
 
-                    return Ok(mesh);
+                if let Some(obj) = obj {
+                    return Ok(Mesh::from_obj(obj, shading_mode, closed));
                 }
 
                 let vertices = vertices.ok_or_else(|| de::Error::invalid_length(0, &self))?;
                 let faces = faces.ok_or_else(|| de::Error::invalid_length(0, &self))?;
                 let bounds = bounds.ok_or_else(|| de::Error::invalid_length(0, &self))?;
 
-                Ok(Mesh::new(vertices, faces, bounds, shading_mode, None))
+                let mut mesh = Mesh::new(vertices, faces, bounds, shading_mode, None);
+                mesh.set_closed(closed).update_bounds().build_bvh();
+
+                Ok(mesh)
+            }
+        }
+
+        const FIELDS: &[&str] = &[
+            "vertices",
+            "faces",
+            "bounds",
+            "shading_mode",
+            "closed",
+            "obj",
+        ];
+        deserializer.deserialize_struct("Mesh", FIELDS, MeshVisitor)
+    }
+}
+
+#[test]
+fn closed_mesh_occludes_rays_from_inside() {
+    const N: u32 = 4;
+
+    let center = Vector3::broadcast(0.5);
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut faces = Vec::new();
+
+    // the sides of the unit cube as grids of `N²` quads, wound outwards
+    for axis in 0..3 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+        for side in 0..2 {
+            for i in 0..N {
+                for j in 0..N {
+                    let corner = |a: u32, b: u32| {
+                        let mut position = Vector3::zero();
+                        position[axis] = side as Float;
+                        position[u] = (i + a) as Float / N as Float;
+                        position[v] = (j + b) as Float / N as Float;
+
+                        Vertex {
+                            position,
+                            normal: Vector3::unit_z(),
+                            uv: Vector2::zero(),
+                        }
+                    };
+
+                    let first = vertices.len() as u32;
+                    vertices.extend_from_slice(&[
+                        corner(0, 0),
+                        corner(1, 0),
+                        corner(1, 1),
+                        corner(0, 1),
+                    ]);
+
+                    for &(a, b, c) in &[(0, 1, 2), (0, 2, 3)] {
+                        let mut face =
+                            Face::new((first + a, first + b, first + c), Vector3::zero());
+                        let centroid = (vertices[(first + a) as usize].position
+                            + vertices[(first + b) as usize].position
+                            + vertices[(first + c) as usize].position)
+                            / 3.0;
+
+                        if face.geometric_normal(&vertices).dot(centroid - center) < 0.0 {
+                            face.v = (first + a, first + c, first + b);
+                        }
+                        face.normal = face.geometric_normal(&vertices);
+                        faces.push(face);
+                    }
+                }
             }
         }
+    }
+
+    let mut mesh = Mesh::new(vertices, faces, Aabb::empty(), ShadingMode::Flat, None);
+    with_bvh_threshold(0, || {
+        mesh.set_closed(true).update_bounds().build_bvh();
+    });
+    assert!(mesh.has_bvh());
+
+    let outside = Vector3::new(0.3, 0.6, 2.0);
+    assert!(mesh.intersects(&Ray::new_fast(outside, -Vector3::unit_z())));
+    assert!(!mesh.intersects(&Ray::new_fast(outside, Vector3::unit_z())));
+
+    // the rays leave the cube through back faces
+    let directions = [
+        Vector3::unit_x(),
+        -Vector3::unit_x(),
+        Vector3::unit_y(),
+        -Vector3::unit_y(),
+        Vector3::unit_z(),
+        -Vector3::unit_z(),
+    ];
+    for &direction in &directions {
+        let ray = Ray::new_fast(Vector3::new(0.3, 0.6, 0.45), direction);
+
+        assert!(mesh.intersect(&ray).is_some());
+        assert!(mesh.intersects(&ray), "{:?} escaped the cube", direction);
+    }
+}
+
+#[test]
+fn resolve_obj_paths() {
+    let base = Path::new("scenes");
+    let expected = base.join("..").join("meshes").join("dragon.obj");
+
+    assert_eq!(expected, resolve_path("../meshes/dragon.obj", Some(base)));
+    assert_eq!(expected, resolve_path("..\\meshes\\dragon.obj", Some(base)));
+    assert_eq!(
+        Path::new("meshes").join("dragon.obj"),
+        resolve_path("meshes/dragon.obj", None)
+    );
+}
+
+#[test]
+fn face_tangents_follow_u() {
+    let vertex = |x: Float, y: Float, u: Float, v: Float| Vertex {
+        position: Vector3::new(x, y, 0.0),
+        normal: Vector3::unit_z(),
+        uv: Vector2::new(u, v),
+    };
+
+    // `u` runs along the y axis
+    let vertices = vec![
+        vertex(0.0, 0.0, 0.0, 0.0),
+        vertex(0.0, 2.0, 1.0, 0.0),
+        vertex(1.0, 0.0, 0.0, 1.0),
+    ];
+    let face = Face::new((0, 1, 2), Vector3::unit_z());
+    assert!((face.tangent(&vertices).unwrap() - 2.0 * Vector3::unit_y()).mag() < 1e-6);
+
+    let vertices = vec![
+        vertex(0.0, 0.0, 0.0, 0.0),
+        vertex(0.0, 2.0, 0.0, 0.0),
+        vertex(1.0, 0.0, 0.0, 0.0),
+    ];
+    assert!(face.tangent(&vertices).is_none());
+}
+
+#[test]
+fn linear_matches_bvh() {
+    let vertex = |x: Float, y: Float, z: Float| Vertex {
+        position: Vector3::new(x, y, z),
+        normal: Vector3::unit_z(),
+        uv: Vector2::zero(),
+    };
+    let vertices = vec![
+        vertex(0.0, 0.0, 0.0),
+        vertex(1.0, 0.0, 0.0),
+        vertex(1.0, 1.0, 0.0),
+        vertex(0.0, 1.0, 0.0),
+        vertex(0.0, 0.0, 0.5),
+        vertex(1.0, 0.0, 0.5),
+        vertex(1.0, 1.0, 0.5),
+        vertex(0.0, 1.0, 0.5),
+    ];
+    let faces = vec![
+        Face::new((0, 1, 2), Vector3::unit_z()),
+        Face::new((0, 2, 3), Vector3::unit_z()),
+        Face::new((4, 5, 6), Vector3::unit_z()),
+        Face::new((4, 6, 7), Vector3::unit_z()),
+    ];
+
+    let build = |threshold| {
+        let mut mesh = Mesh::new(
+            vertices.clone(),
+            faces.clone(),
+            Aabb::empty(),
+            ShadingMode::Flat,
+            None,
+        );
+        with_bvh_threshold(threshold, || {
+            mesh.update_bounds().build_bvh();
+        });
+
+        mesh
+    };
+    let (linear, tree) = (build(usize::MAX), build(0));
+    assert!(!linear.has_bvh());
+    assert!(tree.has_bvh());
+
+    for &(x, y) in &[(0.2, 0.7), (0.9, 0.1), (1.5, 0.5)] {
+        let ray = Ray::new_fast(Vector3::new(x, y, 1.0), -Vector3::unit_z());
 
-        const FIELDS: &[&str] = &["vertices", "faces", "bounds", "shading_mode", "obj"];
-        deserializer
-            .deserialize_struct("Mesh", FIELDS, MeshVisitor)
-            .map(|mut m| {
-                m.update_bounds().build_bvh();
-                m
-            })
+        let (a, b) = (linear.intersect(&ray), tree.intersect(&ray));
+        assert_eq!(a.map(|i| i.t), b.map(|i| i.t));
+        assert_eq!(linear.intersects(&ray), tree.intersects(&ray));
     }
 }
@@ -0,0 +1,191 @@
+use crate::*;
+use std::fs;
+use std::path::Path;
+use std::str::SplitWhitespace;
+
+/// A material of an mtl file, describing the surface of the faces of an [obj file](ObjFile)
+/// using it.
+///
+/// [ObjFile]: crate::obj_file::ObjFile
+#[derive(Clone, Debug, PartialEq)]
+pub struct MtlMaterial {
+    pub name: String,
+    /// The diffuse color (`Kd`)
+    pub diffuse: [Float; 3],
+    /// The specular color (`Ks`)
+    pub specular: [Float; 3],
+    /// The specular exponent (`Ns`)
+    pub shininess: Float,
+    /// The index of refraction (`Ni`), if given
+    pub ior: Option<Float>,
+    /// The opacity (`d`, or `1 - Tr`)
+    pub dissolve: Float,
+    /// The transmission filter (`Tf`), if given
+    pub transmission: Option<[Float; 3]>,
+    /// The illumination model (`illum`)
+    pub illumination: u32,
+    /// The diffuse color map (`map_Kd`), relative to the mtl file
+    pub diffuse_map: Option<String>,
+    /// The opacity map (`map_d`), relative to the mtl file
+    pub dissolve_map: Option<String>,
+}
+
+impl MtlMaterial {
+    /// Creates a new material with the defaults of the mtl format: a diffuse gray without
+    /// specular highlights.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the material
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            diffuse: [0.8; 3],
+            specular: [0.0; 3],
+            shininess: 0.0,
+            ior: None,
+            dissolve: 1.0,
+            transmission: None,
+            illumination: 2,
+            diffuse_map: None,
+            dissolve_map: None,
+        }
+    }
+
+    /// Returns whether the illumination model refracts light, e.g. of glass.
+    #[inline]
+    pub fn is_refractive(&self) -> bool {
+        matches!(self.illumination, 4 | 6 | 7 | 9)
+    }
+}
+
+/// The materials of an mtl file.
+#[derive(Clone, Debug, Default)]
+pub struct MtlFile {
+    pub materials: Vec<MtlMaterial>,
+}
+
+impl MtlFile {
+    /// Finds the material of the given name.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the material
+    ///
+    /// # Returns
+    /// * The material (if any)
+    pub fn get(&self, name: &str) -> Option<&MtlMaterial> {
+        self.materials.iter().find(|m| m.name == name)
+    }
+
+    /// Parses the content of an mtl file. Unsupported statements get skipped.
+    ///
+    /// # Arguments
+    /// * `content` - The content of the mtl file
+    ///
+    /// # Returns
+    /// * The parsed mtl file, or an error message
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut materials: Vec<MtlMaterial> = Vec::new();
+
+        for (line_number, line_content) in content.lines().enumerate() {
+            let line_content = line_content.trim();
+            if line_content.starts_with('#') || line_content.is_empty() {
+                continue;
+            }
+
+            let mut iter = line_content.split_whitespace();
+            let id = iter.next().unwrap_or_default();
+            let error = |e: String| format!("Invalid mtl statement at line {}: {}", line_number, e);
+
+            if id == "newmtl" {
+                let name = iter.collect::<Vec<_>>().join(" ");
+                materials.push(MtlMaterial::new(name));
+                continue;
+            }
+
+            let material = match materials.last_mut() {
+                Some(m) => m,
+                None => continue,
+            };
+
+            match id {
+                "Kd" => material.diffuse = parse_color(&mut iter).map_err(error)?,
+                "Ks" => material.specular = parse_color(&mut iter).map_err(error)?,
+                "Tf" => material.transmission = Some(parse_color(&mut iter).map_err(error)?),
+                "Ns" => material.shininess = parse_float(&mut iter).map_err(error)?,
+                "Ni" => material.ior = Some(parse_float(&mut iter).map_err(error)?),
+                "d" => material.dissolve = parse_float(&mut iter).map_err(error)?,
+                "Tr" => material.dissolve = 1.0 - parse_float(&mut iter).map_err(error)?,
+                "illum" => {
+                    material.illumination = iter
+                        .next()
+                        .and_then(|i| i.parse().ok())
+                        .ok_or_else(|| error("expected an integer".to_string()))?
+                }
+                // the file name is the last argument, following the options
+                "map_Kd" => material.diffuse_map = iter.last().map(String::from),
+                "map_d" => material.dissolve_map = iter.last().map(String::from),
+                _ => {}
+            }
+        }
+
+        Ok(Self { materials })
+    }
+
+    /// Loads the given mtl file.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the mtl file
+    ///
+    /// # Returns
+    /// * The loaded mtl file, or an error message
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        Self::parse(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+fn parse_float(iter: &mut SplitWhitespace) -> Result<Float, String> {
+    iter.next()
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(|| "expected a float".to_string())
+}
+
+/// Parses a color of the form `r [g b]`, a single value giving a gray.
+fn parse_color(iter: &mut SplitWhitespace) -> Result<[Float; 3], String> {
+    let r = parse_float(iter)?;
+    match iter.next() {
+        Some(g) => {
+            let g = g.parse().map_err(|_| "expected a float".to_string())?;
+            let b = parse_float(iter)?;
+
+            Ok([r, g, b])
+        }
+        None => Ok([r; 3]),
+    }
+}
+
+#[test]
+fn parse_materials() {
+    let content = "# exported\n\
+                   newmtl wood\nKd 0.5 0.3 0.1\nmap_Kd -s 2 2 1 textures/wood.png\n\
+                   newmtl glass\nKd 0\nKs 1 1 1\nNs 900\nNi 1.45\nillum 7\nTr 0.25\n";
+    let mtl_file = MtlFile::parse(content).unwrap();
+
+    let wood = mtl_file.get("wood").unwrap();
+    assert_eq!(wood.diffuse, [0.5, 0.3, 0.1]);
+    assert_eq!(wood.diffuse_map.as_deref(), Some("textures/wood.png"));
+    assert!(!wood.is_refractive());
+
+    let glass = mtl_file.get("glass").unwrap();
+    assert_eq!(glass.diffuse, [0.0; 3]);
+    assert_eq!(glass.ior, Some(1.45));
+    assert_eq!(glass.dissolve, 0.75);
+    assert!(glass.is_refractive());
+
+    assert!(MtlFile::parse("newmtl broken\nKd red\n").is_err());
+}
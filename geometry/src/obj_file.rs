@@ -1,19 +1,260 @@
 use crate::Face;
 use crate::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
+use std::ops::Range;
 use std::path::Path;
 use std::str::SplitWhitespace;
 
+/// The name of the faces before the first group statement of an obj file.
+pub const DEFAULT_GROUP: &str = "default";
+
+/// A consecutive range of faces sharing a group name (`g` or `o`) and a material (`usemtl`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObjGroup {
+    pub name: String,
+    /// The material of the faces (if any)
+    pub material: Option<String>,
+    pub faces: Range<usize>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ObjFile {
     pub vertices: Vec<Vertex>,
     pub faces: Vec<Face>,
+    /// The groups of the faces in file order
+    #[serde(default)]
+    pub groups: Vec<ObjGroup>,
+    /// The material libraries (`mtllib`), relative to the obj file
+    #[serde(default)]
+    pub material_libraries: Vec<String>,
 }
 
 impl ObjFile {
     pub fn new(vertices: Vec<Vertex>, faces: Vec<Face>) -> Self {
-        Self { vertices, faces }
+        Self {
+            vertices,
+            faces,
+            groups: Vec::new(),
+            material_libraries: Vec::new(),
+        }
+    }
+
+    /// Returns the distinct pairs of group name and material in file order, each one making up a
+    /// part of the mesh to render with its own material.
+    ///
+    /// # Returns
+    /// * The parts as `(group, material)`
+    pub fn parts(&self) -> Vec<(String, Option<String>)> {
+        let mut parts: Vec<(String, Option<String>)> = Vec::new();
+
+        for g in &self.groups {
+            if !parts.iter().any(|(n, m)| *n == g.name && *m == g.material) {
+                parts.push((g.name.clone(), g.material.clone()));
+            }
+        }
+
+        parts
+    }
+
+    /// Selects the faces of the given group and material, removing all other faces and the
+    /// vertices only referenced by them. The normals stay the ones of the whole mesh, keeping
+    /// smooth shading across the borders of groups.
+    ///
+    /// # Arguments
+    /// * `group` - The group name to select (`None` for all groups)
+    /// * `material` - The material to select (`None` for all materials)
+    ///
+    /// # Returns
+    /// * The selected part, or `None` if no face matches
+    pub fn select(&self, group: Option<&str>, material: Option<&str>) -> Option<Self> {
+        let mut faces = Vec::new();
+        let mut groups = Vec::new();
+
+        for g in &self.groups {
+            let selected = group.is_none_or(|name| g.name == name)
+                && material.is_none_or(|m| g.material.as_deref() == Some(m));
+
+            if selected && !g.faces.is_empty() {
+                let start = faces.len();
+                faces.extend_from_slice(&self.faces[g.faces.clone()]);
+                groups.push(ObjGroup {
+                    name: g.name.clone(),
+                    material: g.material.clone(),
+                    faces: start..faces.len(),
+                });
+            }
+        }
+
+        if faces.is_empty() {
+            return None;
+        }
+
+        let mut used = vec![u32::MAX; self.vertices.len()];
+        let mut vertices = Vec::new();
+        let mut compact = |i: u32| {
+            if used[i as usize] == u32::MAX {
+                used[i as usize] = vertices.len() as u32;
+                vertices.push(self.vertices[i as usize]);
+            }
+
+            used[i as usize]
+        };
+        for f in &mut faces {
+            f.v = (compact(f.v.0), compact(f.v.1), compact(f.v.2));
+        }
+
+        Some(Self {
+            vertices,
+            faces,
+            groups,
+            material_libraries: self.material_libraries.clone(),
+        })
+    }
+
+    /// Welds vertices closer than the given tolerance and removes faces without area, e.g. of
+    /// scanned meshes. The normals get recomputed afterwards.
+    ///
+    /// Only vertices with equal texture coordinates get welded, keeping the seams of the
+    /// texture coordinates.
+    ///
+    /// # Constraints
+    /// * `tolerance` - Should be non-negative.
+    ///
+    /// # Arguments
+    /// * `tolerance` - The maximum distance between welded vertices
+    pub fn weld(&mut self, tolerance: Float) {
+        debug_assert!(tolerance >= 0.0);
+
+        let cell_size = tolerance.max(Float::EPSILON);
+        let cell = |p: Vector3| {
+            (
+                (p.x / cell_size).floor() as i64,
+                (p.y / cell_size).floor() as i64,
+                (p.z / cell_size).floor() as i64,
+            )
+        };
+
+        let mut grid: HashMap<(i64, i64, i64), Vec<u32>> = HashMap::new();
+        let mut vertices: Vec<Vertex> = Vec::with_capacity(self.vertices.len());
+        let mut remap = Vec::with_capacity(self.vertices.len());
+
+        for v in &self.vertices {
+            let (x, y, z) = cell(v.position);
+
+            let mut existing = None;
+            'search: for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        if let Some(candidates) = grid.get(&(x + dx, y + dy, z + dz)) {
+                            existing = candidates.iter().copied().find(|&i| {
+                                let other = &vertices[i as usize];
+
+                                other.uv == v.uv && (other.position - v.position).mag() <= tolerance
+                            });
+
+                            if existing.is_some() {
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+
+            let index = existing.unwrap_or_else(|| {
+                let index = vertices.len() as u32;
+                vertices.push(*v);
+                grid.entry((x, y, z)).or_default().push(index);
+
+                index
+            });
+            remap.push(index);
+        }
+
+        // remove collapsed and zero-area faces
+        let mut kept = Vec::with_capacity(self.faces.len());
+        let mut faces: Vec<Face> = self
+            .faces
+            .iter()
+            .map(|f| {
+                let v = (
+                    remap[f.v.0 as usize],
+                    remap[f.v.1 as usize],
+                    remap[f.v.2 as usize],
+                );
+
+                Face::new(v, f.normal)
+            })
+            .filter(|f| {
+                let (v0, v1, v2) = f.get_vertices(&vertices);
+                let area = (v1.position - v0.position).cross(v2.position - v0.position);
+
+                // also catches NaN
+                let keep = area.mag_sq() > 0.0;
+                kept.push(keep);
+
+                keep
+            })
+            .collect();
+
+        // shrink the groups by their removed faces
+        let mut start = 0;
+        for g in &mut self.groups {
+            let len = kept[g.faces.clone()].iter().filter(|k| **k).count();
+            g.faces = start..start + len;
+            start += len;
+        }
+
+        // remove vertices only referenced by removed faces
+        let mut used = vec![u32::MAX; vertices.len()];
+        let mut compacted = Vec::with_capacity(vertices.len());
+        let mut compact = |i: u32| {
+            if used[i as usize] == u32::MAX {
+                used[i as usize] = compacted.len() as u32;
+                compacted.push(vertices[i as usize]);
+            }
+
+            used[i as usize]
+        };
+        for f in &mut faces {
+            f.v = (compact(f.v.0), compact(f.v.1), compact(f.v.2));
+        }
+
+        self.vertices = compacted;
+        self.faces = faces;
+        self.compute_normals();
+    }
+
+    /// Computes the face normals and the angle-weighted vertex normals.
+    fn compute_normals(&mut self) {
+        let vertices = &mut self.vertices;
+        let faces = &mut self.faces;
+
+        // initialize face normals
+        faces.iter_mut().for_each(|f| {
+            let (v0, v1, v2) = f.get_vertices(vertices);
+
+            f.normal = (v1.position - v0.position)
+                .cross(v2.position - v0.position)
+                .normalized()
+        });
+
+        vertices.iter_mut().for_each(|v| v.normal = Vector3::zero());
+
+        // compute face normals and add them to vertices
+        for f in faces.iter() {
+            let (v0, v1, v2) = f.get_vertices(vertices);
+            let (w0, w1, w2) = Mesh::angle_weights(v0.position, v1.position, v2.position);
+
+            // scatter face normals to vertex normals
+            vertices[f.v.0 as usize].normal += w0 * f.normal;
+            vertices[f.v.1 as usize].normal += w1 * f.normal;
+            vertices[f.v.2 as usize].normal += w2 * f.normal;
+        }
+
+        // normalize vertex normals
+        vertices.iter_mut().for_each(|v| v.normal.normalize());
     }
 }
 
@@ -25,9 +266,10 @@ impl From<ObjFile> for String {
             .map(|v| {
                 let p = v.position;
                 let n = v.normal;
+                let uv = v.uv;
                 format!(
-                    "v {0} {1} {2}\nvn {3} {4} {5}",
-                    p.x, p.y, p.z, n.x, n.y, n.z
+                    "v {0} {1} {2}\nvt {3} {4}\nvn {5} {6} {7}",
+                    p.x, p.y, p.z, uv.x, uv.y, n.x, n.y, n.z
                 )
             })
             .collect();
@@ -36,7 +278,10 @@ impl From<ObjFile> for String {
         let mut f: Vec<String> = obj_file
             .faces
             .iter()
-            .map(|f| format!("f {0} {1} {2}", f.v.0 + 1, f.v.1 + 1, f.v.2 + 1))
+            .map(|f| {
+                let (v0, v1, v2) = (f.v.0 + 1, f.v.1 + 1, f.v.2 + 1);
+                format!("f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", v0, v1, v2)
+            })
             .collect();
 
         let mut out = v;
@@ -45,15 +290,27 @@ impl From<ObjFile> for String {
     }
 }
 
-impl<P> From<P> for ObjFile
-where
-    P: AsRef<Path>,
-{
-    fn from(path: P) -> Self {
-        let content = fs::read_to_string(path).expect("Could not load path");
-
+impl ObjFile {
+    /// Parses the content of an obj file.
+    ///
+    /// Vertices referenced with different texture coordinates get split, such that each one
+    /// carries its own texture coordinates (e.g. along the seams of an unwrapped mesh). The split
+    /// vertices share the normal of the original one, keeping smooth shading across seams.
+    ///
+    /// # Arguments
+    /// * `content` - The content of the obj file
+    ///
+    /// # Returns
+    /// * The parsed obj file
+    pub fn parse(content: &str) -> Self {
         let mut vertices = Vec::new();
         let mut faces = Vec::new();
+        let mut uvs = Vec::new();
+        let mut face_uvs = Vec::new();
+        let mut groups: Vec<ObjGroup> = Vec::new();
+        let mut material_libraries = Vec::new();
+        let mut group = DEFAULT_GROUP.to_string();
+        let mut material = None;
 
         for (line_number, line_content) in content.lines().enumerate() {
             if line_content.starts_with('#') || line_content.is_empty() {
@@ -72,41 +329,124 @@ where
                     let vertex = Vertex {
                         position,
                         normal: Vector3::zero(),
+                        uv: Vector2::zero(),
                     };
                     vertices.push(vertex);
                 }
+                "vt" => {
+                    let u = iter.next().unwrap().parse().unwrap();
+                    let v = iter.next().map_or(0.0, |v| v.parse().unwrap());
+                    uvs.push(Vector2::new(u, v));
+                }
                 "f" => {
-                    let face = parse_face(&mut iter);
+                    let (face, uv) = parse_face(&mut iter);
                     faces.push(face);
+                    face_uvs.push(uv);
+
+                    match groups.last_mut() {
+                        Some(g) if g.name == group && g.material == material => {
+                            g.faces.end = faces.len();
+                        }
+                        _ => {
+                            let start = faces.len() - 1;
+                            groups.push(ObjGroup {
+                                name: group.clone(),
+                                material: material.clone(),
+                                faces: start..faces.len(),
+                            });
+                        }
+                    }
+                }
+                "g" | "o" => {
+                    let name = iter.collect::<Vec<_>>().join(" ");
+                    group = if name.is_empty() {
+                        DEFAULT_GROUP.to_string()
+                    } else {
+                        name
+                    };
                 }
+                "usemtl" => material = Some(iter.collect::<Vec<_>>().join(" ")),
+                "mtllib" => material_libraries.extend(iter.map(String::from)),
                 _ => {} //eprintln!("Unsupported (skipping): {}", id),
             }
         }
 
-        // initialize face normals
-        faces.iter_mut().for_each(|f| {
-            let (v0, v1, v2) = f.get_vertices(&vertices);
+        // the normals get computed before splitting, to stay smooth across seams
+        let mut obj_file = Self::new(vertices, faces);
+        obj_file.groups = groups;
+        obj_file.material_libraries = material_libraries;
+        obj_file.compute_normals();
+        obj_file.split_uvs(&uvs, &face_uvs);
 
-            f.normal = (v1.position - v0.position)
-                .cross(v2.position - v0.position)
-                .normalized()
-        });
+        obj_file
+    }
 
-        // compute face normals and add them to vertices
-        for f in &faces {
-            let (v0, v1, v2) = f.get_vertices(&mut vertices);
-            let (w0, w1, w2) = Mesh::angle_weights(v0.position, v1.position, v2.position);
+    /// Assigns the texture coordinates to the vertices, splitting the ones referenced with
+    /// different texture coordinates.
+    ///
+    /// # Arguments
+    /// * `uvs` - The texture coordinates of the obj file
+    /// * `face_uvs` - The texture coordinate indices of each face (if any)
+    fn split_uvs(&mut self, uvs: &[Vector2], face_uvs: &[Option<(u32, u32, u32)>]) {
+        let mut split: HashMap<(u32, u32), u32> = HashMap::new();
+        let vertices = &mut self.vertices;
 
-            // scatter face normals to vertex normals
-            vertices[f.v.0 as usize].normal += w0 * f.normal;
-            vertices[f.v.1 as usize].normal += w1 * f.normal;
-            vertices[f.v.2 as usize].normal += w2 * f.normal;
+        let mut assign = |v: u32, vt: u32| -> u32 {
+            *split.entry((v, vt)).or_insert_with(|| {
+                let mut vertex = vertices[v as usize];
+                vertex.uv = uvs[vt as usize];
+
+                vertices.push(vertex);
+                (vertices.len() - 1) as u32
+            })
+        };
+
+        for (f, uv) in self.faces.iter_mut().zip(face_uvs) {
+            if let Some((t0, t1, t2)) = *uv {
+                f.v = (assign(f.v.0, t0), assign(f.v.1, t1), assign(f.v.2, t2));
+            }
         }
 
-        // normalize vertex normals
-        vertices.iter_mut().for_each(|v| v.normal.normalize());
+        // remove the original vertices only referenced with texture coordinates, keeping the ones
+        // without any face (e.g. of points and lines)
+        let mut keep = vec![true; self.vertices.len()];
+        split.keys().for_each(|&(v, _)| keep[v as usize] = false);
+        for f in &self.faces {
+            keep[f.v.0 as usize] = true;
+            keep[f.v.1 as usize] = true;
+            keep[f.v.2 as usize] = true;
+        }
 
-        Self::new(vertices, faces)
+        let indices: Vec<u32> = keep
+            .iter()
+            .scan(0, |next, &k| {
+                let index = *next;
+                *next += k as u32;
+                Some(index)
+            })
+            .collect();
+        let mut kept = keep.iter();
+        self.vertices.retain(|_| *kept.next().unwrap());
+        for f in &mut self.faces {
+            f.v = (
+                indices[f.v.0 as usize],
+                indices[f.v.1 as usize],
+                indices[f.v.2 as usize],
+            );
+        }
+    }
+}
+
+impl<P> From<P> for ObjFile
+where
+    P: AsRef<Path>,
+{
+    fn from(path: P) -> Self {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("Could not load obj file {:?}: {}", path, err));
+
+        Self::parse(&content)
     }
 }
 
@@ -118,15 +458,147 @@ fn parse_vector3(iter: &mut SplitWhitespace) -> Vector3 {
     Vector3::new(x, y, z)
 }
 
-fn parse_face(iter: &mut SplitWhitespace) -> Face {
-    let p = |s: &str| -> u32 { s.splitn(2, '/').next().unwrap().parse().unwrap() };
+/// Parses a face of the form `v[/vt[/vn]] v[/vt[/vn]] v[/vt[/vn]]`.
+///
+/// # Returns
+/// * The face
+/// * The texture coordinate indices, if given
+fn parse_face(iter: &mut SplitWhitespace) -> (Face, Option<(u32, u32, u32)>) {
+    // offset by one because indexing starts at 1 in obj files
+    let p = |s: &str| -> (u32, Option<u32>) {
+        let mut split = s.split('/');
+        let v = split.next().unwrap().parse::<u32>().unwrap() - 1;
+        let vt = split
+            .next()
+            .filter(|vt| !vt.is_empty())
+            .map(|vt| vt.parse::<u32>().unwrap() - 1);
 
-    let v0 = p(iter.next().unwrap());
-    let v1 = p(iter.next().unwrap());
-    let v2 = p(iter.next().unwrap());
+        (v, vt)
+    };
 
-    // offset by one because indexing starts at 1 in obj files
-    let vertices = (v0 - 1, v1 - 1, v2 - 1);
+    let (v0, vt0) = p(iter.next().unwrap());
+    let (v1, vt1) = p(iter.next().unwrap());
+    let (v2, vt2) = p(iter.next().unwrap());
+
+    let uv = match (vt0, vt1, vt2) {
+        (Some(t0), Some(t1), Some(t2)) => Some((t0, t1, t2)),
+        _ => None,
+    };
+
+    (Face::new((v0, v1, v2), Vector3::zero()), uv)
+}
+
+#[test]
+fn weld_within_tolerance() {
+    let vertex = |x, y| Vertex {
+        position: Vector3::new(x, y, 0.0),
+        normal: Vector3::zero(),
+        uv: Vector2::zero(),
+    };
+    let vertices = vec![
+        vertex(0.0, 0.0),
+        vertex(1.0, 0.0),
+        vertex(0.0, 1.0),
+        // duplicates of the vertices above
+        vertex(1.0 + 1e-4, 0.0),
+        vertex(0.0, 1.0 - 1e-4),
+        vertex(1.0, 1.0),
+        // just beyond the tolerance
+        vertex(1.0, 1.0 + 2e-3),
+    ];
+    let faces = vec![
+        Face::new((0, 1, 2), Vector3::zero()),
+        Face::new((3, 5, 4), Vector3::zero()),
+        Face::new((5, 6, 2), Vector3::zero()),
+        // collapses into an edge
+        Face::new((0, 1, 3), Vector3::zero()),
+    ];
+
+    let mut obj_file = ObjFile::new(vertices, faces);
+    obj_file.weld(1e-3);
+
+    assert_eq!(obj_file.vertices.len(), 5);
+    assert_eq!(obj_file.faces.len(), 3);
+
+    let (_, a1, a2) = obj_file.faces[0].get_vertices(&obj_file.vertices);
+    let (b1, _, b2) = obj_file.faces[1].get_vertices(&obj_file.vertices);
+    assert_eq!(a1.position, Vector3::unit_x());
+    assert_eq!(b1.position, a1.position);
+    assert_eq!(b2.position, a2.position);
+
+    let (c0, c1, _) = obj_file.faces[2].get_vertices(&obj_file.vertices);
+    assert_ne!(c0.position, c1.position);
+
+    for f in &obj_file.faces {
+        assert!((f.normal - Vector3::unit_z()).mag() < 1e-4);
+    }
+}
+
+#[test]
+fn split_vertices_at_uv_seams() {
+    let content = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\n\
+                   vt 0 0\nvt 1 0\nvt 0 1\nvt 1 1\nvt 0.5 0.5\n\
+                   f 1/1 2/2 3/3\nf 2/5 4/4 3/3\n";
+    let obj_file = ObjFile::parse(content);
+
+    // vertex 2 is referenced with two texture coordinates
+    assert_eq!(obj_file.vertices.len(), 5);
+    assert_eq!(obj_file.faces.len(), 2);
+
+    let (a, _, c) = obj_file.faces[0].get_vertices(&obj_file.vertices);
+    let (b, _, d) = obj_file.faces[1].get_vertices(&obj_file.vertices);
+    assert_eq!(a.uv, Vector2::zero());
+    assert_eq!(b.uv, Vector2::new(0.5, 0.5));
+    assert_eq!(b.position, Vector3::unit_x());
+    assert_eq!(c.uv, d.uv);
+}
+
+#[test]
+fn keep_vertices_without_faces() {
+    let content = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 5 5 5\nv 6 6 6\n\
+                   vt 0 0\nvt 1 0\nvt 0 1\n\
+                   f 1/1 2/2 3/3\nl 4 5\n";
+    let obj_file = ObjFile::parse(content);
+
+    // the split vertices get appended after the remaining original ones
+    assert_eq!(obj_file.vertices.len(), 5);
+    assert_eq!(obj_file.vertices[0].position, Vector3::new(5.0, 5.0, 5.0));
+    assert_eq!(obj_file.vertices[1].position, Vector3::new(6.0, 6.0, 6.0));
+
+    let (a, b, c) = obj_file.faces[0].get_vertices(&obj_file.vertices);
+    assert_eq!(a.uv, Vector2::zero());
+    assert_eq!(b.uv, Vector2::unit_x());
+    assert_eq!(c.uv, Vector2::unit_y());
+}
+
+#[test]
+fn select_groups_by_material() {
+    let content = "mtllib scene.mtl\n\
+                   v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nv 2 1 0\n\
+                   f 1 2 3\n\
+                   g floor\nusemtl wood\nf 2 4 3\nusemtl stone\nf 2 5 4\n\
+                   g wall\nusemtl wood\nf 3 4 5\n";
+    let obj_file = ObjFile::parse(content);
+
+    assert_eq!(obj_file.material_libraries, vec!["scene.mtl".to_string()]);
+    assert_eq!(obj_file.groups.len(), 4);
+    assert_eq!(
+        obj_file.parts(),
+        vec![
+            (DEFAULT_GROUP.to_string(), None),
+            ("floor".to_string(), Some("wood".to_string())),
+            ("floor".to_string(), Some("stone".to_string())),
+            ("wall".to_string(), Some("wood".to_string())),
+        ]
+    );
+
+    let wood = obj_file.select(None, Some("wood")).unwrap();
+    assert_eq!(wood.faces.len(), 2);
+    assert_eq!(wood.vertices.len(), 4);
+
+    let floor = obj_file.select(Some("floor"), None).unwrap();
+    assert_eq!(floor.faces.len(), 2);
+    assert_eq!(floor.groups[1].faces, 1..2);
 
-    Face::new(vertices, Vector3::zero())
+    assert!(obj_file.select(Some("roof"), None).is_none());
 }
@@ -11,6 +11,8 @@ pub struct Ray {
     pub direction: Vector3,
     pub t_start: Float,
     pub t_end: Float,
+    /// The time at which the ray travels, e.g. within the shutter interval of a camera
+    pub time: Float,
 }
 
 impl Ray {
@@ -41,9 +43,50 @@ impl Ray {
             direction,
             t_start,
             t_end,
+            time: 0.0,
         }
     }
 
+    /// Sets the time at which this ray travels.
+    ///
+    /// # Arguments
+    /// * `time` - The time
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with_time(mut self, time: Float) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Creates a new empty ray that does not contain any ray parameter and therefore never
+    /// intersects anything.
+    ///
+    /// # Arguments
+    /// * `origin` - The origin
+    /// * `direction` - The direction
+    ///
+    /// # Returns
+    /// * Self
+    pub fn empty(origin: Vector3, direction: Vector3) -> Self {
+        Self {
+            origin,
+            direction,
+            t_start: 0.0,
+            t_end: -1.0,
+            time: 0.0,
+        }
+    }
+
+    /// Checks whether this ray does not contain any ray parameter.
+    ///
+    /// # Returns
+    /// * Whether this ray is [empty](Ray::empty)
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.t_end < self.t_start
+    }
+
     /// Creates a new ray with the `direction` constraints being from `0` to `infinity`.
     ///
     /// # Constraints
@@ -0,0 +1,188 @@
+use crate::debug_util::is_finite;
+#[cfg(test)]
+use crate::UNIT_VECTORS;
+use crate::*;
+use crate::{Aabb, Boundable, Container, Geometry, Intersectable, Intersection, Ray};
+use serde::{Deserialize, Serialize};
+use utility::floats::FloatExt;
+
+/// The maximum number of sphere tracing steps before giving up.
+const MAX_STEPS: usize = 256;
+
+/// An axis-aligned box with rounded edges and corners, e.g. for soap bars or devices.
+///
+/// In contrast to a mesh, the rounding stays smooth at any magnification. It gets intersected by
+/// sphere tracing its exact signed distance function.
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundedBox {
+    center: Vector3,
+    /// The half extent along each axis, including the rounding
+    half_size: Vector3,
+    /// The radius of the rounded edges and corners
+    radius: Float,
+}
+
+impl RoundedBox {
+    /// Creates a new rounded box.
+    ///
+    /// # Constraints
+    /// * `center` - All values should be finite (neither infinite nor `NaN`).
+    /// * `half_size` - All values should be finite.
+    ///   All values should be greater than `0`.
+    /// * `radius` - Should be in range `[0, half_size.component_min()]`.
+    ///
+    /// # Arguments
+    /// * `center` - The center
+    /// * `half_size` - The half extent along each axis, including the rounding
+    /// * `radius` - The radius of the rounded edges and corners
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(center: Vector3, half_size: Vector3, radius: Float) -> Self {
+        debug_assert!(is_finite(&center));
+        debug_assert!(is_finite(&half_size));
+        debug_assert!(half_size.component_min() > 0.0);
+        debug_assert!((0.0..=half_size.component_min()).contains(&radius));
+
+        Self {
+            center,
+            half_size,
+            radius,
+        }
+    }
+
+    /// Returns the offset of the given point (relative to the center) to the inner box, whose
+    /// rounded surface forms this box.
+    #[inline]
+    fn offset(&self, local: Vector3) -> Vector3 {
+        local.abs() - self.half_size + Vector3::one() * self.radius
+    }
+
+    /// Returns the signed distance of the given point (relative to the center) to the surface,
+    /// being negative inside.
+    fn distance(&self, local: Vector3) -> Float {
+        let q = self.offset(local);
+
+        q.max_by_component(Vector3::zero()).mag() + q.component_max().min(0.0) - self.radius
+    }
+
+    /// Returns the outward normal at the given point (relative to the center) on the surface.
+    fn normal(&self, local: Vector3) -> Vector3 {
+        let q = self.offset(local);
+        let outside = q.max_by_component(Vector3::zero());
+
+        let normal = if outside != Vector3::zero() {
+            outside
+        } else if q.x >= q.y && q.x >= q.z {
+            Vector3::unit_x()
+        } else if q.y >= q.z {
+            Vector3::unit_y()
+        } else {
+            Vector3::unit_z()
+        };
+
+        let sign = Vector3::new(local.x.signum(), local.y.signum(), local.z.signum());
+
+        (normal * sign).normalized()
+    }
+
+    /// Sphere traces the given ray, stepping by the distance to the surface from the side the ray
+    /// starts on.
+    ///
+    /// # Returns
+    /// * The ray parameter and the point of the first crossing of the surface (if any)
+    fn trace(&self, ray: &Ray) -> Option<(Float, Vector3)> {
+        let (mut t, t_max) = self.bounds().intersect_range(ray)?;
+        let epsilon = Float::big_epsilon() * self.half_size.component_max();
+
+        let origin = ray.origin - self.center;
+        let distance = self.distance(origin + ray.direction * t);
+
+        // close to the surface, the ray starts on the side it heads into
+        let side = if distance.abs() < epsilon {
+            -self
+                .normal(origin + ray.direction * t)
+                .dot(ray.direction)
+                .signum()
+        } else {
+            distance.signum()
+        };
+
+        for _ in 0..MAX_STEPS {
+            let local = origin + ray.direction * t;
+            let distance = side * self.distance(local);
+
+            // only crossings towards the other side count, ignoring the surface the ray leaves
+            if distance < epsilon && side * self.normal(local).dot(ray.direction) < 0.0 {
+                return Some((t, local + self.center));
+            }
+
+            t += distance.max(epsilon);
+            if t > t_max {
+                return None;
+            }
+        }
+
+        None
+    }
+}
+
+impl Boundable for RoundedBox {
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.center - self.half_size, self.center + self.half_size)
+    }
+}
+
+impl Container for RoundedBox {
+    fn contains(&self, point: &Vector3) -> bool {
+        self.distance(*point - self.center) <= 0.0
+    }
+}
+
+impl Intersectable for RoundedBox {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let (t, point) = self.trace(ray)?;
+        let normal = self.normal(point - self.center);
+
+        Some(Intersection::new(point, normal, t, *ray))
+    }
+
+    fn intersects(&self, ray: &Ray) -> bool {
+        self.trace(ray).is_some()
+    }
+}
+
+#[typetag::serde]
+impl Geometry for RoundedBox {}
+
+#[test]
+fn intersect_faces() {
+    let rounded_box = RoundedBox::new(Vector3::zero(), Vector3::one(), 0.25);
+
+    for v in &UNIT_VECTORS {
+        let ray = Ray::new_fast(*v * 2.0, -*v);
+
+        let intersection = rounded_box.intersect(&ray).unwrap();
+
+        assert!((intersection.point - *v).mag() < 1e-3);
+        assert!((intersection.normal - *v).mag() < 1e-3);
+    }
+}
+
+#[test]
+fn intersect_rounded_corner() {
+    let rounded_box = RoundedBox::new(Vector3::zero(), Vector3::one(), 0.25);
+    let diagonal = Vector3::one().normalized();
+
+    // from the outside
+    let ray = Ray::new_fast(diagonal * 3.0, -diagonal);
+    let intersection = rounded_box.intersect(&ray).unwrap();
+    let corner = Vector3::one() * 0.75 + diagonal * 0.25;
+    assert!((intersection.point - corner).mag() < 1e-3);
+    assert!((intersection.normal - diagonal).mag() < 1e-3);
+
+    // from the inside
+    let ray = Ray::new_fast(Vector3::zero(), diagonal);
+    let intersection = rounded_box.intersect(&ray).unwrap();
+    assert!((intersection.point - corner).mag() < 1e-3);
+}
@@ -7,11 +7,48 @@ use crate::{Aabb, Boundable, Container, Geometry, Intersectable, Intersection};
 use serde::{Deserialize, Serialize};
 use utility::math::solve_quadratic;
 
+/// A clipping of a sphere, keeping only the part within a height range and up to an azimuth.
+///
+/// The azimuth `phi` gets measured around the `y` axis, from the `z` axis towards the `x` axis.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SphereClip {
+    /// The minimum height relative to the center
+    pub y_min: Float,
+    /// The maximum height relative to the center
+    pub y_max: Float,
+    /// The maximum azimuth (in radians)
+    pub phi_max: Float,
+}
+
+impl SphereClip {
+    /// Returns the azimuth of the given point relative to the center, within `[0, 2π)`.
+    #[inline]
+    pub fn phi(local: Vector3) -> Float {
+        let phi = local.x.atan2(local.z);
+
+        if phi < 0.0 {
+            phi + TAU
+        } else {
+            phi
+        }
+    }
+
+    /// Returns whether the given point relative to the center lies within the clipping.
+    #[inline]
+    pub fn keeps(&self, local: Vector3) -> bool {
+        local.y >= self.y_min && local.y <= self.y_max && Self::phi(local) <= self.phi_max
+    }
+}
+
 /// A sphere consists of a center and a radius.
+///
+/// It may optionally be [clipped](SphereClip) to model domes, bowls or lens caps.
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Sphere {
     pub center: Vector3,
     pub radius: Float,
+    #[serde(default)]
+    pub clip: Option<SphereClip>,
 }
 
 impl Sphere {
@@ -30,7 +67,43 @@ impl Sphere {
         debug_assert!(is_finite(&center));
         debug_assert!(radius > 0.0);
 
-        Self { center, radius }
+        Self {
+            center,
+            radius,
+            clip: None,
+        }
+    }
+
+    /// Clips the sphere to the given height range and azimuth.
+    ///
+    /// # Constraints
+    /// * `y_min` - Should be less than `y_max`.
+    /// * `phi_max` - Should be within `(0, 2π]`.
+    ///
+    /// # Arguments
+    /// * `y_min` - The minimum height relative to the center
+    /// * `y_max` - The maximum height relative to the center
+    /// * `phi_max` - The maximum azimuth (in radians)
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with_clip(mut self, y_min: Float, y_max: Float, phi_max: Float) -> Self {
+        debug_assert!(y_min < y_max);
+        debug_assert!(phi_max > 0.0 && phi_max <= TAU);
+
+        self.clip = Some(SphereClip {
+            y_min: y_min.max(-self.radius),
+            y_max: y_max.min(self.radius),
+            phi_max,
+        });
+
+        self
+    }
+
+    /// Returns whether the given point on the surface is kept by the clipping.
+    #[inline]
+    pub fn keeps(&self, point: Vector3) -> bool {
+        self.clip.is_none_or(|clip| clip.keeps(point - self.center))
     }
 }
 
@@ -43,8 +116,13 @@ impl Container for Sphere {
 impl Boundable for Sphere {
     fn bounds(&self) -> Aabb {
         let diff = Vector3::one() * self.radius;
-        let min = self.center - diff;
-        let max = self.center + diff;
+        let mut min = self.center - diff;
+        let mut max = self.center + diff;
+
+        if let Some(clip) = self.clip {
+            min.y = self.center.y + clip.y_min;
+            max.y = self.center.y + clip.y_max;
+        }
 
         Aabb::new(min, max)
     }
@@ -61,21 +139,24 @@ impl Intersectable for Sphere {
 
         let (t_min, t_max) = solve_quadratic(a, b, c)?;
 
-        let t = if ray.contains(t_min) {
-            t_min
-        } else if ray.contains(t_max) {
-            t_max
-        } else {
-            return None;
-        };
+        let (t, point) = [t_min, t_max]
+            .iter()
+            .filter(|t| ray.contains(**t))
+            .map(|t| (*t, ray.at(*t)))
+            .find(|(_, point)| self.keeps(*point))?;
 
-        let point = ray.at(t);
         let normal = (point - self.center).normalized();
+        // following the azimuth around the y axis
+        let tangent = Vector3::unit_y().cross(normal);
 
-        Some(Intersection::new(point, normal, t, *ray))
+        Some(Intersection::new(point, normal, t, *ray).with_tangent(tangent))
     }
 
     fn intersects(&self, ray: &Ray) -> bool {
+        if self.clip.is_some() {
+            return self.intersect(ray).is_some();
+        }
+
         let dir = ray.direction;
         let oc = ray.origin - self.center;
 
@@ -146,3 +227,33 @@ fn intersect_inside() {
         assert!(intersection.normal.dot(ray.direction) > 0.0);
     }
 }
+
+#[test]
+fn intersect_clipped() {
+    let dome = Sphere::default().with_clip(0.0, 1.0, TAU);
+
+    // hits the inside of the dome from below
+    let ray = Ray::new_fast(-2.0 * Vector3::unit_y(), Vector3::unit_y());
+    let intersection = dome.intersect(&ray).unwrap();
+    assert_eq!(Vector3::unit_y(), intersection.normal);
+
+    // misses the clipped-away lower half
+    let ray = Ray::new_fast(Vector3::new(2.0, -0.5, 0.0), -Vector3::unit_x());
+    assert!(!dome.intersects(&ray));
+
+    assert_eq!(0.0, dome.bounds().min.y);
+}
+
+#[test]
+fn intersect_tangent() {
+    let sphere = Sphere::default();
+
+    // the tangent follows the azimuth around the y axis
+    let ray = Ray::new_fast(2.0 * Vector3::unit_z(), -Vector3::unit_z());
+    let intersection = sphere.intersect(&ray).unwrap();
+    assert!((intersection.tangent.unwrap() - Vector3::unit_x()).mag() < 1e-6);
+
+    // the tangent is undefined at the poles
+    let ray = Ray::new_fast(2.0 * Vector3::unit_y(), -Vector3::unit_y());
+    assert!(sphere.intersect(&ray).unwrap().tangent.is_none());
+}
@@ -0,0 +1,260 @@
+use crate::debug_util::is_finite;
+use crate::ray::Ray;
+#[cfg(test)]
+use crate::UNIT_VECTORS;
+use crate::*;
+use crate::{Aabb, Boundable, Geometry, Intersectable, Intersection, Sphere};
+use serde::{Deserialize, Serialize};
+use utility::math::solve_quadratic;
+
+fn one_third() -> Float {
+    1.0 / 3.0
+}
+
+/// The number of children surrounding each sphere.
+const CHILDREN: usize = 9;
+
+/// Returns the directions of the children in the frame of their parent, whose own direction
+/// points along `y`. Six children lie on the equator, three more in the upper hemisphere.
+fn child_directions() -> [Vector3; CHILDREN] {
+    let mut directions = [Vector3::zero(); CHILDREN];
+
+    for (i, d) in directions.iter_mut().enumerate() {
+        let (azimuth, elevation) = if i < 6 {
+            (i as Float * TAU / 6.0, 0.0)
+        } else {
+            ((i - 6) as Float * TAU / 3.0 + TAU / 12.0, PI / 3.0)
+        };
+
+        let (sin_a, cos_a) = azimuth.sin_cos();
+        let (sin_e, cos_e) = elevation.sin_cos();
+        *d = Vector3::new(cos_e * cos_a, sin_e, cos_e * sin_a);
+    }
+
+    directions
+}
+
+/// Returns whether the ray overlaps the given sphere anywhere inside its range, including rays
+/// starting inside the sphere.
+#[inline]
+fn overlaps(ray: &Ray, center: Vector3, radius: Float) -> bool {
+    let oc = ray.origin - center;
+
+    let a = ray.direction.dot(ray.direction);
+    let b = 2.0 * ray.direction.dot(oc);
+    let c = radius.mul_add(-radius, oc.dot(oc));
+
+    match solve_quadratic(a, b, c) {
+        Some((t_min, t_max)) => t_min <= ray.t_end && ray.t_start <= t_max,
+        None => false,
+    }
+}
+
+/// A sphere flake is a procedural aggregate of spheres. Each sphere is recursively surrounded by
+/// 9 smaller spheres, up to the given depth.
+///
+/// The spheres are never stored. Instead, each level is an implicit instance of its parent,
+/// scaled by `ratio` and rotated towards its direction, allowing huge sphere counts at no memory
+/// cost. Whole subtrees get culled by their bounding spheres while traversing.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SphereFlake {
+    pub center: Vector3,
+    pub radius: Float,
+    /// The number of recursive levels of child spheres
+    pub depth: u32,
+    /// The radius of each child relative to its parent
+    #[serde(default = "one_third")]
+    pub ratio: Float,
+}
+
+impl SphereFlake {
+    /// Creates a new sphere flake.
+    ///
+    /// # Constraints
+    /// * `radius` - Should be greater than `0.0`.
+    /// * `ratio` - Should be within `(0, 1)`.
+    ///
+    /// # Arguments
+    /// * `center` - The center of the root sphere
+    /// * `radius` - The radius of the root sphere
+    /// * `depth` - The number of recursive levels of child spheres
+    /// * `ratio` - The radius of each child relative to its parent
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(center: Vector3, radius: Float, depth: u32, ratio: Float) -> Self {
+        debug_assert!(is_finite(&center));
+        debug_assert!(radius > 0.0);
+        debug_assert!(ratio > 0.0 && ratio < 1.0);
+
+        Self {
+            center,
+            radius,
+            depth,
+            ratio,
+        }
+    }
+
+    /// Returns the number of spheres in this flake.
+    ///
+    /// # Returns
+    /// * The sphere count
+    pub fn sphere_count(&self) -> usize {
+        (0..=self.depth).map(|level| CHILDREN.pow(level)).sum()
+    }
+
+    /// Returns the radius of a sphere bounding the subtree of a sphere with the given radius.
+    ///
+    /// The children of a sphere of radius `r` lie at distance `r + ratio * r` and are bounded
+    /// recursively, such that the bound `b` satisfies `b = r * (1 + ratio) + ratio * b`.
+    #[inline]
+    fn bound(&self, radius: Float) -> Float {
+        radius * (1.0 + self.ratio) / (1.0 - self.ratio)
+    }
+
+    /// Visits the spheres of the subtree in depth-first order, skipping subtrees whose bounds do
+    /// not overlap the ray. Stops as soon as `f` returns `true`.
+    #[allow(clippy::too_many_arguments)]
+    fn traverse<F>(
+        &self,
+        ray: &mut Ray,
+        directions: &[Vector3; CHILDREN],
+        center: Vector3,
+        radius: Float,
+        axis: Vector3,
+        depth: u32,
+        f: &mut F,
+    ) -> bool
+    where
+        F: FnMut(&mut Ray, Sphere) -> bool,
+    {
+        if !overlaps(ray, center, self.bound(radius)) {
+            return false;
+        }
+
+        if f(ray, Sphere::new(center, radius)) {
+            return true;
+        }
+
+        if depth == 0 {
+            return false;
+        }
+
+        let frame = CoordinateSystem::from_y(axis);
+        let child_radius = radius * self.ratio;
+        let distance = radius + child_radius;
+
+        directions.iter().any(|d| {
+            let direction = frame.x_axis * d.x + frame.y_axis * d.y + frame.z_axis * d.z;
+            let child = center + direction * distance;
+
+            self.traverse(
+                ray,
+                directions,
+                child,
+                child_radius,
+                direction,
+                depth - 1,
+                f,
+            )
+        })
+    }
+}
+
+impl Boundable for SphereFlake {
+    fn bounds(&self) -> Aabb {
+        let diff = Vector3::one() * self.bound(self.radius);
+
+        Aabb::new(self.center - diff, self.center + diff)
+    }
+}
+
+impl Intersectable for SphereFlake {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let directions = child_directions();
+        let mut new_ray = *ray;
+        let mut intersection = None;
+
+        self.traverse(
+            &mut new_ray,
+            &directions,
+            self.center,
+            self.radius,
+            Vector3::unit_y(),
+            self.depth,
+            &mut |r, sphere| {
+                if let Some(i) = sphere.intersect(r) {
+                    r.t_end = i.t;
+                    intersection = Some(i);
+                }
+
+                false
+            },
+        );
+
+        if let Some(mut i) = intersection {
+            i.ray = *ray;
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    fn intersects(&self, ray: &Ray) -> bool {
+        let directions = child_directions();
+        let mut new_ray = *ray;
+
+        self.traverse(
+            &mut new_ray,
+            &directions,
+            self.center,
+            self.radius,
+            Vector3::unit_y(),
+            self.depth,
+            &mut |r, sphere| sphere.intersects(r),
+        )
+    }
+}
+
+#[typetag::serde]
+impl Geometry for SphereFlake {}
+
+impl Default for SphereFlake {
+    /// Constructs the default sphere flake of radius `1.0` around the zero point with 3 levels.
+    ///
+    /// # Returns
+    /// * Self
+    fn default() -> Self {
+        Self::new(Vector3::zero(), 1.0, 3, one_third())
+    }
+}
+
+#[test]
+fn intersect_outside() {
+    let flake = SphereFlake::default();
+    let bounds = flake.bounds();
+
+    for v in UNIT_VECTORS.iter() {
+        let ray = Ray::new_fast(*v * 10.0, -*v);
+
+        let intersection = flake.intersect(&ray).unwrap();
+
+        assert!(flake.intersects(&ray));
+        assert!(bounds.contains(&intersection.point));
+        assert!(intersection.normal.dot(ray.direction) < 0.0);
+
+        let away = Ray::new_fast(*v * 10.0, *v);
+
+        assert!(flake.intersect(&away).is_none());
+        assert!(!flake.intersects(&away));
+    }
+}
+
+#[test]
+fn sphere_count() {
+    let flake = SphereFlake::new(Vector3::zero(), 1.0, 0, 0.5);
+    assert_eq!(1, flake.sphere_count());
+
+    let flake = SphereFlake::new(Vector3::zero(), 1.0, 2, 0.5);
+    assert_eq!(1 + 9 + 81, flake.sphere_count());
+}
@@ -0,0 +1,222 @@
+#[cfg(test)]
+use crate::UNIT_VECTORS;
+use crate::*;
+use serde::{Deserialize, Serialize};
+use utility::floats::FloatExt;
+
+/// Generates texture coordinates for primitives without explicit ones by projecting surface
+/// points relative to the bounds of the primitive.
+///
+/// All mappings produce coordinates within `[0, 1]` for points inside the bounds.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum UvMapping {
+    /// Projects along the `y` axis onto the `xz` plane
+    Planar,
+    /// Wraps `u` around the `y` axis, while `v` goes up along it
+    Cylindrical,
+    /// Maps longitude to `u` and latitude to `v`, with `v = 0` at the top
+    Spherical,
+    /// Projects along the dominant axis of the normal, like the faces of a cube
+    Box,
+}
+
+impl UvMapping {
+    /// Computes the texture coordinates of the given surface point.
+    ///
+    /// # Constraints
+    /// * `point` - All values should be finite (neither infinite nor `NaN`).
+    /// * `normal` - All values should be finite.
+    ///   Should be normalized.
+    ///
+    /// # Arguments
+    /// * `point` - The surface point
+    /// * `normal` - The surface normal
+    /// * `bounds` - The bounds of the primitive
+    ///
+    /// # Returns
+    /// * The texture coordinates
+    pub fn map(&self, point: Vector3, normal: Vector3, bounds: &Aabb) -> Vector2 {
+        let half = bounds.size() / 2.0;
+        let offset = point - bounds.center();
+
+        // the position inside the bounds, within [-1, 1] (degenerate axes collapse to 0)
+        let local = Vector3::new(
+            Self::relative(offset.x, half.x),
+            Self::relative(offset.y, half.y),
+            Self::relative(offset.z, half.z),
+        );
+
+        match self {
+            UvMapping::Planar => Self::to_unit(local.x, local.z),
+            UvMapping::Cylindrical => Vector2::new(Self::azimuth(local), (local.y + 1.0) / 2.0),
+            UvMapping::Spherical => {
+                let v = if local.mag_sq() == 0.0 {
+                    0.5
+                } else {
+                    local.normalized().y.fast_clamp(-1.0, 1.0).acos() / PI
+                };
+
+                Vector2::new(Self::azimuth(local), v)
+            }
+            UvMapping::Box => {
+                let n = normal.abs();
+
+                if n.x >= n.y && n.x >= n.z {
+                    Self::to_unit(local.z, local.y)
+                } else if n.y >= n.z {
+                    Self::to_unit(local.x, local.z)
+                } else {
+                    Self::to_unit(local.x, local.y)
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn relative(offset: Float, half: Float) -> Float {
+        if half > 0.0 {
+            offset / half
+        } else {
+            0.0
+        }
+    }
+
+    #[inline]
+    fn to_unit(u: Float, v: Float) -> Vector2 {
+        Vector2::new((u + 1.0) / 2.0, (v + 1.0) / 2.0)
+    }
+
+    /// The angle around the `y` axis within `[0, 1]`.
+    #[inline]
+    fn azimuth(local: Vector3) -> Float {
+        (local.z.atan2(local.x) + PI) / TAU
+    }
+}
+
+/// A triplanar projection blends three planar projections along the world axes by the
+/// orientation of the normal. It works on any geometry, e.g. meshes without texture coordinates,
+/// as it only depends on the world position and normal.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Triplanar {
+    /// The world-space size of one texture tile
+    pub scale: Float,
+    /// The exponent sharpening the transition between the projections
+    pub sharpness: Float,
+}
+
+impl Triplanar {
+    /// Creates a new triplanar projection.
+    ///
+    /// # Constraints
+    /// * `scale` - Should be greater than `0`.
+    /// * `sharpness` - Should be greater than `0`.
+    ///
+    /// # Arguments
+    /// * `scale` - The world-space size of one texture tile
+    /// * `sharpness` - The exponent sharpening the transition between the projections
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(scale: Float, sharpness: Float) -> Self {
+        debug_assert!(scale > 0.0);
+        debug_assert!(sharpness > 0.0);
+
+        Self { scale, sharpness }
+    }
+
+    /// Projects the given surface point along the `x`, `y` and `z` axes.
+    ///
+    /// A texture lookup blends the values at the 3 texture coordinates by their weights.
+    ///
+    /// # Constraints
+    /// * `point` - All values should be finite (neither infinite nor `NaN`).
+    /// * `normal` - All values should be finite.
+    ///   Should be normalized.
+    ///
+    /// # Arguments
+    /// * `point` - The world-space surface point
+    /// * `normal` - The world-space surface normal
+    ///
+    /// # Returns
+    /// * The texture coordinates (repeating within `[0, 1)`) and weights (summing up to `1`)
+    pub fn project(&self, point: Vector3, normal: Vector3) -> [(Vector2, Float); 3] {
+        let p = point / self.scale;
+        let tile = |u: Float, v: Float| Vector2::new(u - u.floor(), v - v.floor());
+
+        let n = normal.abs();
+        let weights = Vector3::new(
+            n.x.powf(self.sharpness),
+            n.y.powf(self.sharpness),
+            n.z.powf(self.sharpness),
+        );
+        let weights = weights / (weights.x + weights.y + weights.z);
+
+        [
+            (tile(p.z, p.y), weights.x),
+            (tile(p.x, p.z), weights.y),
+            (tile(p.x, p.y), weights.z),
+        ]
+    }
+}
+
+impl Default for Triplanar {
+    /// Constructs a triplanar projection with tiles of size `1` and a sharpness of `4`.
+    ///
+    /// # Returns
+    /// * Self
+    fn default() -> Self {
+        Self::new(1.0, 4.0)
+    }
+}
+
+#[test]
+fn map_inside_unit_range() {
+    let bounds = Aabb::default();
+
+    for mapping in &[
+        UvMapping::Planar,
+        UvMapping::Cylindrical,
+        UvMapping::Spherical,
+        UvMapping::Box,
+    ] {
+        for v in UNIT_VECTORS.iter() {
+            let uv = mapping.map(*v, *v, &bounds);
+
+            assert!(0.0 <= uv.x && uv.x <= 1.0);
+            assert!(0.0 <= uv.y && uv.y <= 1.0);
+        }
+    }
+}
+
+#[test]
+fn map_spherical_poles() {
+    let bounds = Aabb::default();
+
+    let top = UvMapping::Spherical.map(Vector3::unit_y(), Vector3::unit_y(), &bounds);
+    let bottom = UvMapping::Spherical.map(-Vector3::unit_y(), -Vector3::unit_y(), &bounds);
+
+    assert_eq!(0.0, top.y);
+    assert_eq!(1.0, bottom.y);
+}
+
+#[test]
+fn triplanar_weights() {
+    let triplanar = Triplanar::default();
+
+    for v in UNIT_VECTORS.iter() {
+        let projections = triplanar.project(*v * 0.5, *v);
+        let sum: Float = projections.iter().map(|(_, w)| w).sum();
+
+        assert!((sum - 1.0).abs() < 1e-5);
+        assert_eq!(1, projections.iter().filter(|(_, w)| *w == 1.0).count());
+    }
+
+    let normal = Vector3::one().normalized();
+    let projections = triplanar.project(Vector3::new(1.25, -0.5, 3.75), normal);
+
+    for (uv, w) in projections.iter() {
+        assert!((w - 1.0 / 3.0).abs() < 1e-5);
+        assert!(0.0 <= uv.x && uv.x < 1.0);
+        assert!(0.0 <= uv.y && uv.y < 1.0);
+    }
+}
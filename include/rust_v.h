@@ -0,0 +1,62 @@
+/*
+ * The C API of Rust-V, built with the `ffi` feature as a shared library:
+ *
+ *   cargo rustc --release --lib --features ffi --crate-type cdylib
+ *
+ * Functions failing return NULL or -1, describing the error in rust_v_last_error().
+ */
+
+#ifndef RUST_V_H
+#define RUST_V_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A renderer with its (optional) running render job. */
+typedef struct RustVRenderer RustVRenderer;
+
+/*
+ * Returns the message of the last error on the calling thread, or NULL if none occurred.
+ * The message stays valid until the next failing call on the same thread.
+ */
+const char *rust_v_last_error(void);
+
+/*
+ * Creates a renderer of the given scene file content (RON, nul-terminated UTF-8). Relative paths
+ * get resolved against the working directory. Returns NULL on errors.
+ */
+RustVRenderer *rust_v_renderer_new(const char *scene);
+
+/* Frees the given renderer (may be NULL), stopping its render job. */
+void rust_v_renderer_free(RustVRenderer *renderer);
+
+/* Starts rendering in background threads, or resumes it after stopping. */
+int rust_v_renderer_start(RustVRenderer *renderer);
+
+/* Stops rendering, waiting for the render threads to finish their current tile. */
+int rust_v_renderer_stop(RustVRenderer *renderer);
+
+/* Waits until rendering finished. */
+int rust_v_renderer_wait(RustVRenderer *renderer);
+
+/* Returns the fraction of rendered tiles in [0, 1], or -1 on errors. */
+double rust_v_renderer_progress(const RustVRenderer *renderer);
+
+/* Writes the resolution of the rendered image into width and height. */
+int rust_v_renderer_resolution(const RustVRenderer *renderer, uint32_t *width, uint32_t *height);
+
+/*
+ * Copies the current image as 8-bit sRGB with (linear) alpha, row by row, into the given buffer
+ * of at least 4 * width * height bytes. While rendering, the image shows the progress so far.
+ */
+int rust_v_renderer_copy_rgba(const RustVRenderer *renderer, uint8_t *buffer, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RUST_V_H */
@@ -0,0 +1,358 @@
+//! A planetary atmosphere scattering sunlight spectrally, such that the color of the sky emerges
+//! from first principles: blue at noon by Rayleigh scattering, reddened at sunset along long paths
+//! and tinted by the absorption of ozone.
+//!
+//! The atmosphere is a spherical shell around a planet, consisting of
+//! * air molecules scattering by Rayleigh (`~ λ⁻⁴`) with an exponentially decreasing density,
+//! * aerosols scattering (and absorbing) by Mie with an exponentially decreasing density,
+//! * an ozone layer absorbing by the Chappuis band, with a tent-shaped density.
+//!
+//! All parameters are given in kilometers, [scaled](Atmosphere::scale) to the scene units. The
+//! defaults describe the earth (Bruneton and Neyret 2008, Bruneton 2017).
+//!
+//! Since the extinction varies spectrally, collisions get sampled by spectral tracking (Kutz et
+//! al. 2017) with the maximum extinction of all wavelengths as majorant, weighting the throughput
+//! of each wavelength.
+
+use crate::mc::sample_exponential;
+use crate::phase::{
+    henyey_greenstein, rayleigh, sample_henyey_greenstein, sample_rayleigh, scatter,
+};
+use crate::refractive_index::interpolate;
+use crate::samplers::Sampler;
+use crate::{Float, Spectrum, Vector2, Vector3};
+use color::Color;
+use geometry::Ray;
+use serde::{Deserialize, Serialize};
+
+/// The Rayleigh scattering coefficient of air at sea level at `0.44 µm` (per km).
+const RAYLEIGH_440: Float = 33.1e-3;
+
+/// The wavelengths of the ozone absorption in **µm**
+static OZONE_INDEX: [Float; 9] = [0.4, 0.45, 0.5, 0.55, 0.6, 0.65, 0.7, 0.75, 0.8];
+/// The absorption coefficient of ozone at the peak density of the layer (per km), following the
+/// Chappuis band.
+static OZONE_ABSORPTION: [Float; 9] = [
+    0.006e-3, 0.17e-3, 0.86e-3, 1.881e-3, 2.9e-3, 1.5e-3, 0.5e-3, 0.26e-3, 0.11e-3,
+];
+
+/// Returns the mean of all wavelengths.
+fn mean(spectrum: &Spectrum) -> Float {
+    (0..Spectrum::size()).map(|i| spectrum[i]).sum::<Float>() / Spectrum::size() as Float
+}
+
+/// The scattering and extinction coefficients at a point (per scene unit).
+struct Coefficients {
+    rayleigh: Spectrum,
+    scattering: Spectrum,
+    extinction: Spectrum,
+}
+
+/// A planetary atmosphere.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Atmosphere {
+    /// The center of the planet (in scene units)
+    pub center: Vector3,
+    /// The length of a kilometer in scene units
+    pub scale: Float,
+    /// The radius of the planet
+    pub planet_radius: Float,
+    /// The height of the atmosphere above the ground
+    pub height: Float,
+    /// The altitude at which the density of air molecules dropped to `1/e`
+    pub rayleigh_height: Float,
+    /// A factor of the air density, e.g. to exaggerate the scattering
+    pub rayleigh_density: Float,
+    /// The altitude at which the density of aerosols dropped to `1/e`
+    pub mie_height: Float,
+    /// The scattering coefficient of aerosols at the ground (per km), increasing with haze
+    pub mie_scattering: Float,
+    /// The absorption coefficient of aerosols at the ground (per km)
+    pub mie_absorption: Float,
+    /// The asymmetry of the Henyey-Greenstein phase function of aerosols
+    pub mie_g: Float,
+    /// The altitude of the peak density of ozone
+    pub ozone_altitude: Float,
+    /// The distance from the peak at which the density of ozone vanishes
+    pub ozone_width: Float,
+    /// A factor of the ozone density
+    pub ozone_density: Float,
+}
+
+impl Default for Atmosphere {
+    /// Returns the atmosphere of the earth in kilometers, the ground touching the origin.
+    fn default() -> Self {
+        Self {
+            center: Vector3::new(0.0, -6360.0, 0.0),
+            scale: 1.0,
+            planet_radius: 6360.0,
+            height: 60.0,
+            rayleigh_height: 8.0,
+            rayleigh_density: 1.0,
+            mie_height: 1.2,
+            mie_scattering: 3.996e-3,
+            mie_absorption: 0.444e-3,
+            mie_g: 0.8,
+            ozone_altitude: 25.0,
+            ozone_width: 15.0,
+            ozone_density: 1.0,
+        }
+    }
+}
+
+impl Atmosphere {
+    /// Returns the altitude (in km) of the given point above the ground, being `0` below.
+    fn altitude(&self, point: Vector3) -> Float {
+        ((point - self.center).mag() / self.scale - self.planet_radius).max(0.0)
+    }
+
+    /// Returns the scattering and extinction coefficients at the given point.
+    fn coefficients(&self, point: Vector3) -> Coefficients {
+        let altitude = self.altitude(point);
+
+        let rayleigh_density = self.rayleigh_density * (-altitude / self.rayleigh_height).exp();
+        let mie_density = (-altitude / self.mie_height).exp();
+        let ozone_density = self.ozone_density
+            * (1.0 - (altitude - self.ozone_altitude).abs() / self.ozone_width).max(0.0);
+
+        let mie_scattering = self.mie_scattering * mie_density;
+        let mie_absorption = self.mie_absorption * mie_density;
+
+        let mut rayleigh = Spectrum::broadcast(0.0);
+        let mut ozone = Spectrum::broadcast(0.0);
+        for i in 0..Spectrum::size() {
+            let lambda = Spectrum::lambda_of_index(i);
+
+            rayleigh[i] = rayleigh_density * Self::rayleigh(lambda);
+            ozone[i] = ozone_density * interpolate(&OZONE_INDEX, &OZONE_ABSORPTION, lambda);
+        }
+
+        let scattering = rayleigh + Spectrum::broadcast(mie_scattering);
+        let extinction = scattering + ozone + Spectrum::broadcast(mie_absorption);
+
+        Coefficients {
+            rayleigh: rayleigh / self.scale,
+            scattering: scattering / self.scale,
+            extinction: extinction / self.scale,
+        }
+    }
+
+    /// Returns the Rayleigh scattering coefficient of air at sea level (per km).
+    ///
+    /// # Arguments
+    /// * `lambda` - The wavelength in **µm**
+    fn rayleigh(lambda: Float) -> Float {
+        let ratio = 0.44 / lambda;
+
+        RAYLEIGH_440 * ratio * ratio * ratio * ratio
+    }
+
+    /// Returns the majorant of the extinction of all wavelengths, bounding it everywhere inside
+    /// the atmosphere.
+    fn majorant(&self) -> Float {
+        let max = (0..Spectrum::size())
+            .map(Spectrum::lambda_of_index)
+            .map(|lambda| {
+                self.rayleigh_density * Self::rayleigh(lambda)
+                    + self.ozone_density * interpolate(&OZONE_INDEX, &OZONE_ABSORPTION, lambda)
+            })
+            .fold(0.0, Float::max);
+
+        (max + self.mie_scattering + self.mie_absorption) / self.scale
+    }
+
+    /// Clips the given ray segment to the shell of the atmosphere.
+    fn clip(&self, ray: &Ray, t_max: Float) -> Option<(Float, Float)> {
+        let radius = (self.planet_radius + self.height) * self.scale;
+        let offset = ray.origin - self.center;
+
+        // the direction is normalized
+        let b = offset.dot(ray.direction);
+        let c = offset.mag_sq() - radius * radius;
+        let discriminant = b * b - c;
+        if discriminant <= 0.0 {
+            return None;
+        }
+
+        let root = discriminant.sqrt();
+        let t_min = (-b - root).max(ray.t_start);
+        let t_max = (-b + root).min(t_max);
+
+        if t_min < t_max {
+            Some((t_min, t_max))
+        } else {
+            None
+        }
+    }
+
+    /// Samples the distance to the next real collision along the ray by spectral tracking.
+    ///
+    /// # Constraints
+    /// * `ray` - Should have a normalized direction.
+    ///
+    /// # Arguments
+    /// * `ray` - The ray
+    /// * `t_max` - The end of the ray segment, e.g. the next surface intersection
+    /// * `sampler` - The sampler
+    ///
+    /// # Returns
+    /// * The ray parameter of the collision and the scattering there, or `None` if passing
+    ///   through
+    /// * The weight of each wavelength to multiply the throughput with, being `0` if the light
+    ///   got absorbed
+    pub fn sample_collision(
+        &self,
+        ray: &Ray,
+        t_max: Float,
+        sampler: Sampler,
+    ) -> (Option<(Float, AtmosphereScattering)>, Spectrum) {
+        let mut weight = Spectrum::broadcast(1.0);
+
+        let (mut t, t_max) = match self.clip(ray, t_max) {
+            Some(segment) => segment,
+            None => return (None, weight),
+        };
+
+        let majorant = self.majorant();
+        loop {
+            t += sample_exponential(sampler.get_1d(), majorant);
+            if t >= t_max {
+                return (None, weight);
+            }
+
+            let coefficients = self.coefficients(ray.at(t));
+            let null = Spectrum::broadcast(majorant) - coefficients.extinction;
+
+            // the probabilities of the events are averaged over all wavelengths
+            let scattering_mean = mean(&coefficients.scattering);
+            let null_mean = mean(&null);
+            let u = sampler.get_1d() * majorant;
+
+            if u < scattering_mean {
+                weight *= coefficients.scattering / scattering_mean;
+
+                let scattering = AtmosphereScattering {
+                    rayleigh: coefficients.rayleigh / coefficients.scattering,
+                    g: self.mie_g,
+                };
+                return (Some((t, scattering)), weight);
+            } else if u < scattering_mean + null_mean {
+                weight *= null / null_mean;
+            } else {
+                return (None, Spectrum::broadcast(0.0));
+            }
+        }
+    }
+
+    /// Estimates the transmittance of each wavelength along the ray by ratio tracking.
+    ///
+    /// # Constraints
+    /// * `ray` - Should have a normalized direction.
+    ///
+    /// # Arguments
+    /// * `ray` - The ray
+    /// * `t_max` - The end of the ray segment
+    /// * `sampler` - The sampler
+    ///
+    /// # Returns
+    /// * An unbiased estimate of the transmittance in `[0, 1]`
+    pub fn transmittance(&self, ray: &Ray, t_max: Float, sampler: Sampler) -> Spectrum {
+        let mut transmittance = Spectrum::broadcast(1.0);
+
+        let (mut t, t_max) = match self.clip(ray, t_max) {
+            Some(segment) => segment,
+            None => return transmittance,
+        };
+
+        let majorant = self.majorant();
+        loop {
+            t += sample_exponential(sampler.get_1d(), majorant);
+            if t >= t_max {
+                return transmittance;
+            }
+
+            let extinction = self.coefficients(ray.at(t)).extinction;
+            transmittance *= Spectrum::broadcast(1.0) - extinction / majorant;
+        }
+    }
+}
+
+/// The scattering at a collision inside an [atmosphere](Atmosphere), blending the Rayleigh and
+/// Mie phase functions by their share of the scattering of each wavelength.
+#[derive(Copy, Clone, Debug)]
+pub struct AtmosphereScattering {
+    /// The share of Rayleigh scattering of each wavelength
+    rayleigh: Spectrum,
+    /// The asymmetry of Mie scattering
+    g: Float,
+}
+
+impl AtmosphereScattering {
+    /// Evaluates the blended phase function.
+    ///
+    /// # Arguments
+    /// * `direction` - The direction of travel before scattering
+    /// * `incident` - The direction of travel after scattering
+    ///
+    /// # Returns
+    /// * The phase function value of each wavelength
+    pub fn phase(&self, direction: Vector3, incident: Vector3) -> Spectrum {
+        let cos_theta = direction.dot(incident);
+        let rayleigh = rayleigh(cos_theta);
+        let mie = henyey_greenstein(cos_theta, self.g);
+
+        self.rayleigh * rayleigh + (Spectrum::broadcast(1.0) - self.rayleigh) * mie
+    }
+
+    /// Samples the blended phase function, choosing a lobe by its mean share.
+    ///
+    /// # Constraints
+    /// * `sample` - All values should be within `[0, 1)`.
+    ///
+    /// # Arguments
+    /// * `direction` - The direction of travel before scattering
+    /// * `sample` - A random sample
+    ///
+    /// # Returns
+    /// * The direction of travel after scattering
+    /// * The phase function divided by the pdf
+    pub fn sample_phase(&self, direction: Vector3, sample: Vector2) -> (Vector3, Spectrum) {
+        let share = mean(&self.rayleigh);
+
+        let cos_theta = if sample.x < share {
+            sample_rayleigh(sample.x / share)
+        } else {
+            sample_henyey_greenstein(self.g, (sample.x - share) / (1.0 - share))
+        };
+        let incident = scatter(direction, cos_theta, sample.y);
+
+        let pdf =
+            share * rayleigh(cos_theta) + (1.0 - share) * henyey_greenstein(cos_theta, self.g);
+        let weight = if pdf > 0.0 {
+            self.phase(direction, incident) / pdf
+        } else {
+            Spectrum::broadcast(0.0)
+        };
+
+        (incident, weight)
+    }
+}
+
+#[test]
+fn zenith_transmittance_reddens() {
+    let atmosphere = Atmosphere::default();
+    let ray = Ray::new_fast(Vector3::zero(), Vector3::unit_y());
+
+    let n = 10_000;
+    let transmittance = (0..n)
+        .map(|_| atmosphere.transmittance(&ray, Float::INFINITY, Sampler::Random))
+        .sum::<Spectrum>()
+        / n as Float;
+
+    let blue = transmittance[0];
+    let red = transmittance[Spectrum::size() - 1];
+
+    assert!(blue < red);
+    assert!(red <= 1.0);
+}
@@ -1,113 +1,625 @@
-#[macro_use]
-extern crate clap;
+use clap::{ArgEnum, Args, IntoApp, Parser, Subcommand};
+use clap_complete::Shell;
+use image::DynamicImage;
 
-use clap::App;
-
-use ron::from_str;
+use rust_v::camera::EquirectangularCamera;
+use rust_v::dataset::DatasetSpec;
+use rust_v::merge::merge_exr;
+use rust_v::metadata::{fnv1a, Metadata};
+use rust_v::pack::pack;
 use rust_v::renderer::Renderer;
+use rust_v::samplers::camera::CameraSampler;
 use rust_v::serialization::Serialization;
 #[cfg(feature = "show-image")]
 use rust_v::RenderWindow;
-use std::convert::TryInto;
 use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use ultraviolet::UVec2;
+
+#[cfg(not(feature = "f64"))]
+type Float = f32;
+#[cfg(not(feature = "f64"))]
+type Vector3 = ultraviolet::Vec3;
+#[cfg(feature = "f64")]
+type Float = f64;
+#[cfg(feature = "f64")]
+type Vector3 = ultraviolet::DVec3;
 
-const LIVE: &str = "LIVE_WINDOW";
-const VERBOSE: &str = "VERBOSE";
-const INPUT: &str = "INPUT";
-const FORMAT: &str = "FORMAT";
-const OUTPUT: &str = "OUTPUT";
-const PASSES: &str = "PASSES";
-const THREADS: &str = "THREADS";
+/// The maximum depth of the BVH nodes exported with `--export-obj`.
+const EXPORT_BVH_DEPTH: u32 = 8;
+
+/// The interval to check whether a render with `--checkpoint` is done or due to save.
+const CHECKPOINT_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 #[cfg(not(feature = "show-image"))]
 fn main() -> Result<(), Box<dyn Error>> {
-    create_config().run()
+    Cli::parse().run()
 }
 
 #[show_image::main]
 #[cfg(feature = "show-image")]
 fn main() -> Result<(), Box<dyn Error>> {
-    create_config().run()?;
+    Cli::parse().run()?;
 
     show_image::exit(0);
 }
 
-fn create_config() -> CmdInput {
-    #[cfg(not(feature = "show-image"))]
-    let yaml = load_yaml!("cli.yml");
-    #[cfg(feature = "show-image")]
-    let yaml = load_yaml!("cli-live.yml");
-
-    let matches = App::from(yaml).get_matches();
-
-    let verbose = matches.is_present(VERBOSE);
-    let live = cfg!(feature = "show-image") && matches.is_present(LIVE);
-    let input = matches.value_of(INPUT).expect("No scene file given!");
-    let pixel_type: PixelType = match matches.value_of(FORMAT).unwrap_or("u8").try_into() {
-        Ok(format) => format,
-        Err(err) => panic!("Cannot parse pixel format: {}", err),
-    };
-    let output = matches.value_of(OUTPUT).map(|s| s.to_string());
-    let passes = matches
-        .value_of(PASSES)
-        .map(|string| match string.parse::<u32>() {
-            Ok(p) => p,
-            Err(err) => panic!("Cannot parse passes override: {}", err),
-        });
-    let threads = matches
-        .value_of(THREADS)
-        .map(|string| match string.parse::<u32>() {
-            Ok(t) => t,
-            Err(err) => panic!("Cannot parse threads override: {}", err),
+/// A rust ray tracer supporting rgb and spectral ray tracing. Currently WIP.
+#[derive(Debug, Parser)]
+#[clap(name = "rust_v", version, author)]
+struct Cli {
+    /// Print verbose messages.
+    #[clap(short, long, global = true)]
+    verbose: bool,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Renders the given scene file.
+    Render {
+        /// The scene file to render
+        input: String,
+        #[clap(flatten)]
+        options: RenderOptions,
+    },
+    /// Parses and sets up the given scene files without rendering them.
+    Validate {
+        /// The scene files to validate
+        #[clap(required = true)]
+        inputs: Vec<String>,
+        /// Print statistics about the scenes and their estimated memory usage.
+        #[clap(long)]
+        stats: bool,
+    },
+    /// Renders the given scene file repeatedly without saving it, reporting the render times.
+    Bench {
+        /// The scene file to render
+        input: String,
+        /// The number of renders to time.
+        #[clap(short, long, default_value = "3")]
+        runs: u32,
+        /// Override the number of passes of the scene file.
+        #[clap(short, long)]
+        passes: Option<u32>,
+        /// Override the number of threads of the scene file.
+        #[clap(short, long)]
+        threads: Option<u32>,
+    },
+    /// Merges the partial sensor dumps of renders with disjoint tile ranges into one image, or
+    /// averages independent .exr renders weighted by their passes.
+    Merge {
+        /// The scene file the parts were rendered from, or the output .exr file when averaging
+        /// .exr renders
+        input: String,
+        /// The partial sensor dumps or .exr renders to merge
+        #[clap(required = true)]
+        parts: Vec<String>,
+        /// Override the output file of the scene file.
+        #[clap(short, long)]
+        output: Option<String>,
+        /// The output pixel format.
+        #[clap(short, long, arg_enum, default_value = "u8")]
+        format: PixelType,
+    },
+    /// Copies the scene file and all assets it references into a directory or .zip archive,
+    /// rewriting the paths to the copies.
+    Pack {
+        /// The scene file to pack
+        input: String,
+        /// The output directory, or .zip archive
+        output: String,
+    },
+    /// Renders the frames of a synthetic dataset, placing objects and lights at random into a
+    /// base scene. Each frame gets saved with its AOVs and an annotation of the placements.
+    Dataset {
+        /// The dataset specification
+        spec: String,
+        /// The output directory
+        #[clap(short, long, default_value = "dataset")]
+        output: PathBuf,
+        /// The output pixel format.
+        #[clap(short, long, arg_enum, default_value = "u8")]
+        format: PixelType,
+        /// Override the number of passes of the base scene file.
+        #[clap(short, long)]
+        passes: Option<u32>,
+        /// Override the number of threads of the base scene file.
+        #[clap(short, long)]
+        threads: Option<u32>,
+        /// Only generate the frames with indices in [a, b), e.g. "0..100".
+        #[clap(long, parse(try_from_str = parse_range))]
+        frames: Option<(usize, usize)>,
+    },
+    /// Renders one of the bundled demo scenes.
+    Demo {
+        /// The demo scene to render
+        #[clap(arg_enum)]
+        scene: DemoScene,
+        #[clap(flatten)]
+        options: RenderOptions,
+    },
+    /// Prints the completions of the given shell to stdout.
+    Completions {
+        /// The shell to generate completions for
+        #[clap(arg_enum)]
+        shell: Shell,
+    },
+}
+
+/// The options of a render overriding the scene file.
+#[derive(Debug, Clone, Args)]
+struct RenderOptions {
+    /// Use a live window to progressively show rendering. The window allows saving with
+    /// [Ctrl + S] and adjusting light groups with [Tab], [Up] and [Down].
+    #[clap(short, long, hide = cfg!(not(feature = "show-image")))]
+    live: bool,
+    /// Print statistics about the scene and the estimated memory usage before rendering.
+    #[clap(long)]
+    stats: bool,
+    /// The output pixel format.
+    #[clap(short, long, arg_enum, default_value = "u8")]
+    format: PixelType,
+    /// Override the output file of the scene file.
+    #[clap(short, long)]
+    output: Option<String>,
+    /// Override the number of passes of the scene file.
+    #[clap(short, long)]
+    passes: Option<u32>,
+    /// Override the number of threads of the scene file.
+    #[clap(short, long)]
+    threads: Option<u32>,
+    /// Override the stack size of each render thread in MiB.
+    #[clap(long)]
+    stack_size: Option<usize>,
+    /// Override the intensity multiplier of a light group, e.g. "sun=0.5". May be given multiple
+    /// times.
+    #[clap(short = 'g', long, parse(try_from_str = parse_light_group))]
+    light_group: Vec<(String, Float)>,
+    /// Render an equirectangular environment probe at the given position, e.g. "0,1,0". Use an
+    /// .exr output for HDR.
+    #[clap(long, parse(try_from_str = parse_probe))]
+    probe: Option<Vector3>,
+    /// Export the camera frustum, light samples and BVH boxes to the given .obj file instead of
+    /// rendering.
+    #[clap(long)]
+    export_obj: Option<String>,
+    /// Only render the tiles with indices in [a, b), e.g. "0..64", saving a partial sensor dump
+    /// (.bin) next to the output file. Combine the parts with the merge subcommand.
+    #[clap(long, parse(try_from_str = parse_range))]
+    tile_range: Option<(usize, usize)>,
+    /// Resume the render from the checkpoint (.bin) at the given path if it exists, and save
+    /// checkpoints to it while rendering. Ignored by the live window.
+    #[clap(long)]
+    checkpoint: Option<String>,
+    /// The interval in seconds to save checkpoints at.
+    #[clap(long, default_value = "300")]
+    checkpoint_interval: u64,
+    /// Cache the parsed meshes and their BVHs in the given directory, memory-mapping them on
+    /// subsequent runs of the same scene.
+    #[clap(long)]
+    cache: Option<PathBuf>,
+    /// Intersect meshes with fewer triangles than this by testing all of them instead of
+    /// building a BVH.
+    #[clap(long, default_value_t = geometry::DEFAULT_BVH_THRESHOLD)]
+    bvh_threshold: usize,
+    /// Render the scene again and check that it reproduces the image of the given manifest
+    /// (.json) byte-for-byte, instead of saving it. Only renders with the NoOp samplers are
+    /// deterministic.
+    #[clap(long)]
+    verify: Option<PathBuf>,
+}
+
+impl RenderOptions {
+    /// The options of merging partial renders, which only save the image.
+    fn merge(output: Option<String>, format: PixelType) -> Self {
+        Self {
+            live: false,
+            stats: false,
+            format,
+            output,
+            passes: None,
+            threads: None,
+            stack_size: None,
+            light_group: Vec::new(),
+            probe: None,
+            export_obj: None,
+            tile_range: None,
+            checkpoint: None,
+            checkpoint_interval: 300,
+            cache: None,
+            bvh_threshold: geometry::DEFAULT_BVH_THRESHOLD,
+            verify: None,
+        }
+    }
+}
+
+/// The example scenes bundled into the binary.
+#[derive(Debug, Copy, Clone, ArgEnum)]
+enum DemoScene {
+    Cornell,
+    GlassSphere,
+    Prism,
+    SphereFlake,
+}
+
+impl DemoScene {
+    fn name(&self) -> &'static str {
+        match self {
+            DemoScene::Cornell => "cornell",
+            DemoScene::GlassSphere => "glass-sphere",
+            DemoScene::Prism => "prism",
+            DemoScene::SphereFlake => "sphere-flake",
+        }
+    }
+
+    fn content(&self) -> &'static str {
+        match self {
+            DemoScene::Cornell => include_str!("../../scenes/cornell.ron"),
+            DemoScene::GlassSphere => include_str!("../../scenes/glass-sphere.ron"),
+            DemoScene::Prism => include_str!("../../scenes/prism.ron"),
+            DemoScene::SphereFlake => include_str!("../../scenes/sphere-flake.ron"),
+        }
+    }
+}
+
+fn parse_light_group(value: &str) -> Result<(String, Float), String> {
+    let mut split = value.splitn(2, '=');
+    let name = split.next().unwrap_or_default();
+    let multiplier = split.next().map(|m| m.parse::<Float>());
+
+    match multiplier {
+        Some(Ok(m)) if !name.is_empty() => Ok((name.to_string(), m)),
+        _ => Err(format!("Cannot parse light group override: {}", value)),
+    }
+}
+
+fn parse_range(value: &str) -> Result<(usize, usize), String> {
+    let mut split = value.splitn(2, "..");
+    let start = split.next().map(|s| s.trim().parse::<usize>());
+    let end = split.next().map(|e| e.trim().parse::<usize>());
+
+    match (start, end) {
+        (Some(Ok(start)), Some(Ok(end))) if start < end => Ok((start, end)),
+        _ => Err(format!("Cannot parse range: {}", value)),
+    }
+}
+
+fn parse_probe(value: &str) -> Result<Vector3, String> {
+    let coords = value
+        .split(',')
+        .map(|c| c.trim().parse::<Float>())
+        .collect::<Result<Vec<Float>, _>>()
+        .map_err(|err| format!("Cannot parse probe position: {}", err))?;
+
+    match coords.as_slice() {
+        &[x, y, z] => Ok(Vector3::new(x, y, z)),
+        _ => Err(format!("Cannot parse probe position: {}", value)),
+    }
+}
+
+/// Reads the given scene file.
+fn read_scene(path: &str) -> Result<String, Box<dyn Error>> {
+    std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read scene file {}: {}", path, err).into())
+}
+
+impl Cli {
+    fn run(self) -> Result<(), Box<dyn Error>> {
+        let verbose = self.verbose;
+
+        match self.command {
+            Command::Render { input, options } => {
+                CmdInput::from_file(verbose, input, options)?.run()
+            }
+            Command::Validate { inputs, stats } => validate(verbose, &inputs, stats),
+            Command::Bench {
+                input,
+                runs,
+                passes,
+                threads,
+            } => bench(verbose, &input, runs, passes, threads),
+            Command::Merge {
+                input,
+                parts,
+                output,
+                format,
+            } => {
+                // averaging independent renders does not need the scene
+                if is_exr(&input) {
+                    let passes = merge_exr(input.clone(), &parts)?;
+                    println!(
+                        "Merged {} images with {} passes into {}",
+                        parts.len(),
+                        passes,
+                        input
+                    );
+
+                    return Ok(());
+                }
+
+                CmdInput::from_file(verbose, input, RenderOptions::merge(output, format))?
+                    .merge(&parts)
+            }
+            Command::Pack { input, output } => {
+                let assets = pack(&input, &output)?;
+                println!("Packed {} with {} assets into {}", input, assets, output);
+
+                Ok(())
+            }
+            Command::Dataset {
+                spec,
+                output,
+                format,
+                passes,
+                threads,
+                frames,
+            } => dataset(verbose, &spec, &output, format, passes, threads, frames),
+            Command::Demo { scene, mut options } => {
+                if options.output.is_none() {
+                    options.output = Some(format!("{}.png", scene.name()));
+                }
+
+                CmdInput {
+                    verbose,
+                    input: format!("demo:{}", scene.name()),
+                    path: None,
+                    content: scene.content().to_string(),
+                    options,
+                }
+                .run()
+            }
+            Command::Completions { shell } => {
+                let mut app = Cli::into_app();
+                let name = app.get_name().to_string();
+                clap_complete::generate(shell, &mut app, name, &mut std::io::stdout());
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Parses and sets up each scene file, reporting all invalid ones.
+fn validate(verbose: bool, inputs: &[String], stats: bool) -> Result<(), Box<dyn Error>> {
+    let mut invalid = 0;
+
+    for input in inputs {
+        let result = read_scene(input).and_then(|content| {
+            let serialization = Serialization::parse(&content, Some(Path::new(input)))?;
+            if verbose {
+                println!("{:#?}", serialization.config);
+            }
+
+            Ok(Renderer::new(serialization))
         });
 
-    CmdInput {
-        verbose,
-        live,
-        input: input.to_owned(),
-        pixel_type,
-        output,
-        passes,
-        threads,
+        match result {
+            Ok(renderer) => {
+                println!("{}: ok", input);
+
+                if stats {
+                    println!("{}", renderer.stats());
+                }
+            }
+            Err(err) => {
+                eprintln!("{}: {}", input, err);
+                invalid += 1;
+            }
+        }
+    }
+
+    if invalid > 0 {
+        return Err(format!("{} of {} scene files are invalid", invalid, inputs.len()).into());
     }
+
+    Ok(())
 }
 
-#[derive(Debug, Clone)]
-struct CmdInput {
+/// Renders the scene file `runs` times without saving it, printing the time of each run.
+fn bench(
     verbose: bool,
-    live: bool,
-    input: String,
-    pixel_type: PixelType,
-    output: Option<String>,
+    input: &str,
+    runs: u32,
     passes: Option<u32>,
     threads: Option<u32>,
+) -> Result<(), Box<dyn Error>> {
+    let content = read_scene(input)?;
+    let mut times = Vec::with_capacity(runs as usize);
+
+    for run in 0..runs {
+        let mut serialization = Serialization::parse(&content, Some(Path::new(input)))?;
+        if let Some(p) = passes {
+            serialization.config.passes = p;
+        }
+        if threads.is_some() {
+            serialization.config.threads = threads;
+        }
+
+        if verbose && run == 0 {
+            println!("{:#?}", serialization.config);
+        }
+
+        let mut renderer = Renderer::new(serialization);
+
+        let start = Instant::now();
+        renderer
+            .render()
+            .join()
+            .expect("Could not join render threads");
+        let time = start.elapsed();
+
+        println!("Run {}: {:.3}s", run + 1, time.as_secs_f64());
+        times.push(time);
+    }
+
+    if let (Some(min), Some(max)) = (times.iter().min(), times.iter().max()) {
+        let mean = times.iter().sum::<Duration>() / times.len() as u32;
+        println!(
+            "min: {:.3}s, mean: {:.3}s, max: {:.3}s",
+            min.as_secs_f64(),
+            mean.as_secs_f64(),
+            max.as_secs_f64()
+        );
+    }
+
+    Ok(())
+}
+
+/// Renders and saves the frames of the given dataset specification into the output directory,
+/// e.g. `00042.png` with the AOVs `00042_normal.exr` etc. and the annotation `00042.ron`.
+fn dataset(
+    verbose: bool,
+    spec_path: &str,
+    output: &Path,
+    format: PixelType,
+    passes: Option<u32>,
+    threads: Option<u32>,
+    frames: Option<(usize, usize)>,
+) -> Result<(), Box<dyn Error>> {
+    let spec = DatasetSpec::load(spec_path)?;
+    std::fs::create_dir_all(output)?;
+
+    let (start, end) = frames.unwrap_or((0, spec.frames as usize));
+    let end = end.min(spec.frames as usize);
+
+    for frame in start..end {
+        let (mut serialization, annotation) = spec.frame(frame as u32)?;
+
+        let image_path = output.join(format!("{:05}.png", frame));
+        serialization.config.filename = Some(image_path.to_string_lossy().to_string());
+        if let Some(p) = passes {
+            serialization.config.passes = p;
+        }
+        if threads.is_some() {
+            serialization.config.threads = threads;
+        }
+
+        println!("Frame {} of {}", frame + 1, spec.frames);
+
+        let mut renderer = Renderer::new(serialization);
+        renderer
+            .render()
+            .join()
+            .expect("Could not join render threads");
+
+        // the metadata refers to the base scene file
+        let scene_path = spec.scene_path();
+        let input = CmdInput::from_file(
+            verbose,
+            scene_path.to_string_lossy().to_string(),
+            RenderOptions::merge(None, format),
+        )?;
+        input.save_image(&renderer)?;
+
+        let annotation_path = image_path.with_extension("ron");
+        let pretty = ron::ser::PrettyConfig::default();
+        std::fs::write(
+            &annotation_path,
+            ron::ser::to_string_pretty(&annotation, pretty)?,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[derive(Clone)]
+struct CmdInput {
+    verbose: bool,
+    /// The scene file, or the name of a demo scene
+    input: String,
+    /// The scene file to resolve relative paths against (`None` for demo scenes)
+    path: Option<PathBuf>,
+    /// The content of the scene file
+    content: String,
+    options: RenderOptions,
 }
 
 impl CmdInput {
-    fn deserialize_renderer(&self) -> Renderer {
-        let content =
-            std::fs::read_to_string(&self.input).expect("Could not read serialization file");
-        let mut serialization: Serialization =
-            from_str(content.as_str()).expect("Could not parse serialization file");
+    fn from_file(
+        verbose: bool,
+        input: String,
+        options: RenderOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            verbose,
+            path: Some(PathBuf::from(&input)),
+            content: read_scene(&input)?,
+            input,
+            options,
+        })
+    }
 
-        if let Some(o) = &self.output {
+    fn is_live(&self) -> bool {
+        cfg!(feature = "show-image") && self.options.live
+    }
+
+    fn deserialize_renderer(&self) -> Result<Renderer, Box<dyn Error>> {
+        let options = &self.options;
+        let (serialization, cache_errors) =
+            geometry::with_bvh_threshold(options.bvh_threshold, || {
+                geometry::with_cache_directory(options.cache.as_deref(), || {
+                    Serialization::parse(&self.content, self.path.as_deref())
+                })
+            });
+        for err in cache_errors {
+            eprintln!("{}", err);
+        }
+        let mut serialization = serialization
+            .map_err(|err| format!("Could not parse scene file {}: {}", self.input, err))?;
+
+        if let Some(o) = &options.output {
             if !o.is_empty() {
-                serialization.config.filename = self.output.clone()
+                serialization.config.filename = options.output.clone()
             }
         }
 
-        if let Some(p) = self.passes {
+        if let Some(p) = options.passes {
             serialization.config.passes = p;
         }
-        if self.threads.is_some() {
-            serialization.config.threads = self.threads
+        if options.threads.is_some() {
+            serialization.config.threads = options.threads
+        }
+        if options.stack_size.is_some() {
+            serialization.config.stack_size = options.stack_size
+        }
+        if options.tile_range.is_some() {
+            serialization.config.tile_range = options.tile_range
+        }
+        for (name, multiplier) in &options.light_group {
+            serialization
+                .config
+                .light_groups
+                .insert(name.clone(), *multiplier);
+        }
+
+        if let Some(position) = options.probe {
+            // capture the whole sphere with the vertical resolution of the scene camera
+            let height = serialization.camera.resolution().y;
+            serialization.camera = Box::new(EquirectangularCamera::new(
+                CameraSampler::Random,
+                Default::default(),
+                position,
+                UVec2::new(2 * height, height),
+            ));
         }
 
         if self.verbose {
             println!("{:#?}", serialization.config);
         }
 
-        Renderer::new(serialization)
+        Ok(Renderer::new(serialization))
+    }
+
+    /// Collects the [metadata](Renderer::metadata) of the render, including the hash of the scene
+    /// file.
+    fn metadata(&self, renderer: &Renderer) -> Metadata {
+        renderer.metadata().with("Scene", &self.input).with(
+            "Scene hash",
+            format!("{:016x}", fnv1a(self.content.as_bytes())),
+        )
     }
 
     fn save_image(&self, renderer: &Renderer) -> Result<(), Box<dyn Error>> {
@@ -118,10 +630,110 @@ impl CmdInput {
                 println!("Saving image...");
             }
 
-            match self.pixel_type {
-                PixelType::U8 => renderer.get_image_u8().save(path)?,
-                PixelType::U16 => renderer.get_image_u16().save(path)?,
-            };
+            let metadata = self.metadata(renderer);
+            let pixel_type = self.options.format;
+
+            if is_exr(path) {
+                renderer.save_exr(path, &metadata)?;
+            } else if is_png(path) {
+                let image = match pixel_type {
+                    PixelType::U8 => DynamicImage::ImageRgb8(renderer.get_image_u8()),
+                    PixelType::U16 => DynamicImage::ImageRgb16(renderer.get_image_u16()),
+                };
+                metadata.save_png(&image, path)?;
+            } else {
+                match pixel_type {
+                    PixelType::U8 => renderer.get_image_u8().save(path)?,
+                    PixelType::U16 => renderer.get_image_u16().save(path)?,
+                };
+            }
+
+            let manifest_path = suffixed_path(path, "manifest").with_extension("json");
+            if self.verbose {
+                println!("Saving manifest to {:?}", manifest_path);
+            }
+
+            let manifest = metadata
+                .with("Image", path)
+                .with("Image hash", format!("{:016x}", renderer.image_hash()));
+            std::fs::write(manifest_path, manifest.to_json())?;
+
+            for (i, group) in renderer.light_group_names().iter().enumerate() {
+                let group_path = suffixed_path(path, group);
+
+                if self.verbose {
+                    println!("Saving light group {} to {:?}", group, group_path);
+                }
+
+                match pixel_type {
+                    PixelType::U8 => renderer.get_light_group_image_u8(i).save(group_path)?,
+                    PixelType::U16 => renderer.get_light_group_image_u16(i).save(group_path)?,
+                };
+            }
+
+            for (i, name) in renderer.light_path_names().iter().enumerate() {
+                let light_path = suffixed_path(path, name);
+
+                if self.verbose {
+                    println!("Saving light path {} to {:?}", name, light_path);
+                }
+
+                match pixel_type {
+                    PixelType::U8 => renderer.get_light_path_image_u8(i).save(light_path)?,
+                    PixelType::U16 => renderer.get_light_path_image_u16(i).save(light_path)?,
+                };
+            }
+
+            for (i, name) in renderer.aov_names().iter().enumerate() {
+                let aov_path = suffixed_path(path, name).with_extension("exr");
+
+                if self.verbose {
+                    println!("Saving AOV {} to {:?}", name, aov_path);
+                }
+
+                renderer.save_aov_exr(i, aov_path)?;
+            }
+
+            // the .exr output already contains the alpha channel
+            if renderer.has_holdouts() && !is_exr(path) {
+                let alpha_path = suffixed_path(path, "alpha");
+
+                if self.verbose {
+                    println!("Saving alpha to {:?}", alpha_path);
+                }
+
+                match pixel_type {
+                    PixelType::U8 => renderer.get_alpha_image_u8().save(alpha_path)?,
+                    PixelType::U16 => renderer.get_alpha_image_u16().save(alpha_path)?,
+                };
+            }
+
+            if renderer.geometry_aovs() {
+                let position_path = suffixed_path(path, "position").with_extension("exr");
+                let normal_path = suffixed_path(path, "normal").with_extension("exr");
+
+                if self.verbose {
+                    println!(
+                        "Saving geometry AOVs to {:?} and {:?}",
+                        position_path, normal_path
+                    );
+                }
+
+                renderer.save_geometry_exr(position_path, normal_path)?;
+            }
+
+            for (i, band) in renderer.spectral_band_names().iter().enumerate() {
+                let band_path = suffixed_path(path, band);
+
+                if self.verbose {
+                    println!("Saving spectral band {} to {:?}", band, band_path);
+                }
+
+                match pixel_type {
+                    PixelType::U8 => renderer.get_spectral_band_image_u8(i).save(band_path)?,
+                    PixelType::U16 => renderer.get_spectral_band_image_u16(i).save(band_path)?,
+                };
+            }
 
             if self.verbose {
                 println!("Successfully saved image");
@@ -131,15 +743,49 @@ impl CmdInput {
         Ok(())
     }
 
+    /// Merges the partial sensor dumps into the sensor of the scene and saves the image.
+    fn merge(&self, parts: &[String]) -> Result<(), Box<dyn Error>> {
+        let renderer = self.deserialize_renderer()?;
+
+        for part in parts {
+            if self.verbose {
+                println!("Merging {}", part);
+            }
+
+            renderer.load_partial(part)?;
+        }
+
+        self.save_image(&renderer)
+    }
+
     pub fn run(&self) -> Result<(), Box<dyn Error>> {
         if self.verbose {
-            println!("{:#?}", self);
+            println!("{:#?}", self.options);
         }
 
-        let mut renderer = self.deserialize_renderer();
+        let mut renderer = self.deserialize_renderer()?;
+
+        let manifest = match &self.options.verify {
+            Some(path) => Some(read_manifest(path, &renderer)?),
+            None => None,
+        };
+
+        if self.options.stats {
+            println!("{}", renderer.stats());
+        }
+
+        if let Some(path) = &self.options.export_obj {
+            renderer.export_obj(path, EXPORT_BVH_DEPTH)?;
+
+            if self.verbose {
+                println!("Exported scene setup to {}", path);
+            }
+
+            return Ok(());
+        }
 
         #[cfg(feature = "show-image")]
-        if self.live {
+        if self.is_live() {
             let mut window = RenderWindow::new("Rust-V".to_string(), &mut renderer)?;
             window.render()?;
 
@@ -148,30 +794,256 @@ impl CmdInput {
             }
         }
 
-        if !self.live {
-            let job = renderer.render();
-            job.join().expect("Could not join render threads");
+        if !self.is_live() {
+            match &self.options.checkpoint {
+                Some(path) => self.render_with_checkpoints(&mut renderer, path)?,
+                None => {
+                    let job = renderer.render();
+                    job.join().expect("Could not join render threads");
+                }
+            }
+        }
+
+        if renderer.energy_warnings() > 0 {
+            eprintln!(
+                "{} tiles failed the energy check",
+                renderer.energy_warnings()
+            );
+        }
+
+        let tile_errors = renderer.tile_errors();
+        if !tile_errors.is_empty() {
+            eprintln!("{} tile passes panicked:", tile_errors.len());
+            for error in &tile_errors {
+                let outcome = if error.recovered {
+                    "recovered"
+                } else {
+                    "skipped"
+                };
+
+                eprintln!(
+                    "  tile {} in pass {}: {} after {} panic(s), last: {}",
+                    error.tile, error.pass, outcome, error.panics, error.message
+                );
+            }
+        }
+
+        #[cfg(feature = "sanitize")]
+        {
+            let discarded = renderer.discarded();
+            if discarded.non_finite > 0 || discarded.negative > 0 {
+                eprintln!(
+                    "Discarded {} non-finite and {} negative contributions",
+                    discarded.non_finite, discarded.negative
+                );
+            }
+        }
+
+        if let Some(manifest) = manifest {
+            return verify(&manifest, &renderer);
+        }
+
+        if renderer.is_partial() {
+            return self.save_partial(&renderer);
         }
 
         self.save_image(&renderer)
     }
+
+    /// Renders until done, resuming from the checkpoint at the given path if it exists. The render
+    /// job gets stopped at each checkpoint interval to save a consistent checkpoint.
+    fn render_with_checkpoints(
+        &self,
+        renderer: &mut Renderer,
+        path: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        if Path::new(path).exists() {
+            renderer
+                .load_checkpoint(path)
+                .map_err(|err| format!("Could not resume from checkpoint {}: {}", path, err))?;
+
+            if self.verbose {
+                println!(
+                    "Resuming from {} at {:.1}%",
+                    path,
+                    100.0 * renderer.progress_fraction()
+                );
+            }
+        }
+
+        let interval = Duration::from_secs(self.options.checkpoint_interval.max(1));
+        while !renderer.is_done() {
+            let job = renderer.render();
+            let start = Instant::now();
+            while !renderer.is_done() && start.elapsed() < interval {
+                std::thread::sleep(CHECKPOINT_POLL_INTERVAL);
+            }
+
+            job.stop().expect("Could not join render threads");
+            renderer.save_checkpoint(path)?;
+
+            if self.verbose {
+                println!("Saved checkpoint {}", path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Saves the sensor dump of a render restricted to a tile range, e.g. `out.png` rendering the
+    /// tiles `0..64` becomes `out_tiles_0_64.bin`.
+    fn save_partial(&self, renderer: &Renderer) -> Result<(), Box<dyn Error>> {
+        let path = match renderer.filename() {
+            Some(path) => path,
+            None => return Err("Partial renders need an output file".into()),
+        };
+
+        let range = renderer.tile_range();
+        let suffix = format!("tiles_{}_{}", range.start, range.end);
+        let dump_path = suffixed_path(path, &suffix).with_extension("bin");
+
+        println!("Saving partial render to {:?}", dump_path);
+        renderer.save_partial(&dump_path.to_string_lossy())?;
+
+        Ok(())
+    }
+}
+
+/// Reads the given manifest, checking that it belongs to the scene of the renderer.
+fn read_manifest(path: &Path, renderer: &Renderer) -> Result<Metadata, Box<dyn Error>> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read manifest {}: {}", path.display(), err))?;
+    let manifest = Metadata::from_json(&json)
+        .map_err(|err| format!("Could not parse manifest {}: {}", path.display(), err))?;
+
+    let content_hash = format!("{:016x}", renderer.content_hash());
+    match manifest.get("Content hash") {
+        Some(hash) if hash == content_hash => Ok(manifest),
+        Some(hash) => Err(format!(
+            "The scene (content hash {}) differs from the one of the manifest ({})",
+            content_hash, hash
+        )
+        .into()),
+        None => Err(format!("The manifest {} has no content hash", path.display()).into()),
+    }
+}
+
+/// Checks that the render reproduces the image of the manifest byte-for-byte.
+fn verify(manifest: &Metadata, renderer: &Renderer) -> Result<(), Box<dyn Error>> {
+    let image = manifest.get("Image").unwrap_or("the image");
+    let image_hash = format!("{:016x}", renderer.image_hash());
+
+    match manifest.get("Image hash") {
+        Some(hash) if hash == image_hash => {
+            println!("Reproduced {} byte-for-byte", image);
+
+            Ok(())
+        }
+        Some(hash) => Err(format!(
+            "The render (image hash {}) differs from {} ({})",
+            image_hash, image, hash
+        )
+        .into()),
+        None => Err("The manifest has no image hash".into()),
+    }
+}
+
+/// Whether the given path points to an OpenEXR file, which gets saved in linear HDR.
+fn is_exr(path: &str) -> bool {
+    has_extension(path, "exr")
+}
+
+/// Whether the given path points to a PNG file, which gets saved with metadata.
+fn is_png(path: &str) -> bool {
+    has_extension(path, "png")
+}
+
+fn has_extension(path: &str, extension: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+}
+
+/// Appends the suffix to the file stem of the given path, e.g. `out.png` becomes `out_sun.png`
+/// for the suffix `sun`.
+fn suffixed_path(path: &str, suffix: &str) -> PathBuf {
+    let path = Path::new(path);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+
+    let mut file_name = format!("{}_{}", stem, suffix);
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        file_name = format!("{}.{}", file_name, extension);
+    }
+
+    path.with_file_name(file_name)
 }
 
 /// Represents the pixel type to save.
-#[derive(Debug, Clone)]
+#[derive(Debug, Copy, Clone, ArgEnum)]
 pub enum PixelType {
     U8,
     U16,
 }
 
-impl TryInto<PixelType> for &str {
-    type Error = String;
+#[test]
+fn cli_parses_subcommands() {
+    Cli::into_app().debug_assert();
 
-    fn try_into(self) -> Result<PixelType, Self::Error> {
-        match self.to_lowercase().as_str() {
-            "u8" => Ok(PixelType::U8),
-            "u16" => Ok(PixelType::U16),
-            _ => Err(self.to_string()),
+    let cli = Cli::try_parse_from([
+        "rust_v",
+        "render",
+        "scene.ron",
+        "-v",
+        "-f",
+        "u16",
+        "-p",
+        "4",
+        "-g",
+        "sun=0.5",
+        "-g",
+        "fill=2",
+        "--probe",
+        "0, 1,2",
+    ])
+    .unwrap();
+    assert!(cli.verbose);
+    match cli.command {
+        Command::Render { input, options } => {
+            assert_eq!(input, "scene.ron");
+            assert!(matches!(options.format, PixelType::U16));
+            assert_eq!(options.passes, Some(4));
+            assert_eq!(options.threads, None);
+            assert_eq!(
+                options.light_group,
+                vec![("sun".to_string(), 0.5), ("fill".to_string(), 2.0)]
+            );
+            assert_eq!(options.probe, Some(Vector3::new(0.0, 1.0, 2.0)));
         }
+        command => panic!("parsed {:?}", command),
     }
+
+    let cli = Cli::try_parse_from(["rust_v", "demo", "glass-sphere", "--stats"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Command::Demo {
+            scene: DemoScene::GlassSphere,
+            options: RenderOptions { stats: true, .. },
+        }
+    ));
+
+    let cli = Cli::try_parse_from(["rust_v", "completions", "zsh"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Command::Completions { shell: Shell::Zsh }
+    ));
+
+    // malformed values and missing subcommands get rejected
+    assert!(Cli::try_parse_from(["rust_v", "render", "scene.ron", "-g", "sun"]).is_err());
+    assert!(Cli::try_parse_from(["rust_v", "render", "scene.ron", "--probe", "1,2"]).is_err());
+    assert!(Cli::try_parse_from(["rust_v", "validate"]).is_err());
+    assert!(Cli::try_parse_from(["rust_v"]).is_err());
 }
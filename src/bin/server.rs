@@ -0,0 +1,403 @@
+//! A headless render service, e.g. to back a web front-end or render checks in CI.
+//!
+//! The service offers the following endpoints:
+//! - `POST /render`: Starts rendering the scene file in the request body, responding with the id
+//!   of the render job.
+//! - `GET /render/<id>`: Streams the progress of the job as server-sent events, i.e. a
+//!   `data: <fraction>` event whenever it advances, and a final `done` (or `error`) event.
+//! - `GET /render/<id>/exr`: Responds with the finished image as OpenEXR, `202 Accepted` while
+//!   still rendering.
+//! - `DELETE /render/<id>`: Stops and forgets the job.
+
+use clap::Parser;
+use indicatif::ProgressDrawTarget;
+use parking_lot::Mutex;
+use rust_v::metadata::fnv1a;
+use rust_v::renderer::Renderer;
+use rust_v::serialization::Serialization;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response, ResponseBox, Server, StatusCode};
+
+/// The maximum size of a scene file in bytes.
+const MAX_SCENE_SIZE: usize = 16 * 1024 * 1024;
+
+/// The interval to poll the progress of a render job at.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A headless render service accepting scene files via HTTP.
+#[derive(Debug, Parser)]
+#[clap(name = "rust_v_server", version, author)]
+struct Cli {
+    /// The address to listen at. Scene files may reference any file readable by the service, so
+    /// only expose it to trusted clients.
+    #[clap(short, long, default_value = "127.0.0.1:8080")]
+    address: String,
+    /// Override the number of threads of each render job.
+    #[clap(short, long)]
+    threads: Option<u32>,
+}
+
+/// The state of a render job.
+enum State {
+    Rendering,
+    /// The finished image as OpenEXR
+    Done(Vec<u8>),
+    Failed(String),
+}
+
+struct Job {
+    /// A handle sharing the progress of the rendering renderer
+    renderer: Renderer,
+    state: Mutex<State>,
+    should_stop: AtomicBool,
+}
+
+type Jobs = Arc<Mutex<HashMap<u64, Arc<Job>>>>;
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let cli = Cli::parse();
+    let server = Server::http(&cli.address)?;
+    let jobs: Jobs = Arc::new(Mutex::new(HashMap::new()));
+    let next_id = Arc::new(AtomicU64::new(0));
+
+    println!("Listening at {}", cli.address);
+
+    for request in server.incoming_requests() {
+        let jobs = jobs.clone();
+        let next_id = next_id.clone();
+        let threads = cli.threads;
+
+        // progress streams block, so each request gets its own thread
+        thread::spawn(move || {
+            if let Err(err) = handle(request, &jobs, &next_id, threads) {
+                eprintln!("Could not respond: {}", err);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+fn text_response(status: u16, text: &str) -> Response<io::Cursor<Vec<u8>>> {
+    Response::from_string(text).with_status_code(StatusCode(status))
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("Invalid header")
+}
+
+/// Responds to the request at its endpoint.
+fn handle(
+    mut request: Request,
+    jobs: &Jobs,
+    next_id: &AtomicU64,
+    threads: Option<u32>,
+) -> io::Result<()> {
+    let response = route(&mut request, jobs, next_id, threads)?;
+    request.respond(response)
+}
+
+/// Dispatches the request to its endpoint.
+///
+/// # Returns
+/// * The response to the request, or an error reading its body
+fn route(
+    request: &mut Request,
+    jobs: &Jobs,
+    next_id: &AtomicU64,
+    threads: Option<u32>,
+) -> io::Result<ResponseBox> {
+    let segments: Vec<String> = request
+        .url()
+        .trim_matches('/')
+        .split('/')
+        .map(str::to_string)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let method = request.method().clone();
+
+    let response = match (method, segments.as_slice()) {
+        (Method::Post, ["render"]) => {
+            let mut content = String::new();
+            let size = request
+                .as_reader()
+                .take(MAX_SCENE_SIZE as u64 + 1)
+                .read_to_string(&mut content)?;
+
+            if size > MAX_SCENE_SIZE {
+                return Ok(text_response(413, "Scene file too large").boxed());
+            }
+
+            match start(&content, jobs, next_id, threads) {
+                Ok(id) => text_response(201, &id.to_string()).boxed(),
+                Err(err) => text_response(400, &err).boxed(),
+            }
+        }
+        (method, ["render", id, rest @ ..]) => {
+            let job = id
+                .parse::<u64>()
+                .ok()
+                .and_then(|id| jobs.lock().get(&id).cloned().map(|job| (id, job)));
+            let (id, job) = match job {
+                Some(job) => job,
+                None => return Ok(text_response(404, "Unknown render job").boxed()),
+            };
+
+            match (method, rest) {
+                (Method::Get, []) => {
+                    let headers = vec![
+                        header("Content-Type", "text/event-stream"),
+                        header("Cache-Control", "no-cache"),
+                    ];
+                    let stream = ProgressStream::new(job);
+
+                    Response::new(StatusCode(200), headers, stream, None, None).boxed()
+                }
+                (Method::Get, ["exr"]) => match &*job.state.lock() {
+                    State::Rendering => text_response(202, "Still rendering").boxed(),
+                    State::Done(exr) => Response::from_data(exr.clone())
+                        .with_header(header("Content-Type", "image/x-exr"))
+                        .boxed(),
+                    State::Failed(err) => text_response(500, err).boxed(),
+                },
+                (Method::Delete, []) => {
+                    job.should_stop.store(true, Ordering::Relaxed);
+                    jobs.lock().remove(&id);
+
+                    text_response(204, "").boxed()
+                }
+                _ => text_response(405, "Method not allowed").boxed(),
+            }
+        }
+        _ => text_response(404, "Not found").boxed(),
+    };
+
+    Ok(response)
+}
+
+/// Parses the given scene file and starts rendering it in the background.
+///
+/// # Returns
+/// * The id of the render job, or the parsing error
+fn start(
+    content: &str,
+    jobs: &Jobs,
+    next_id: &AtomicU64,
+    threads: Option<u32>,
+) -> Result<u64, String> {
+    // relative paths get resolved against the working directory of the service
+    let mut serialization = Serialization::parse(content, None)
+        .map_err(|err| format!("Could not parse scene file: {}", err))?;
+    if threads.is_some() {
+        serialization.config.threads = threads;
+    }
+
+    let mut renderer = Renderer::new(serialization);
+    renderer
+        .progress_bar
+        .lock()
+        .set_draw_target(ProgressDrawTarget::hidden());
+
+    let id = next_id.fetch_add(1, Ordering::Relaxed);
+    let job = Arc::new(Job {
+        renderer: renderer.clone(),
+        state: Mutex::new(State::Rendering),
+        should_stop: AtomicBool::new(false),
+    });
+    jobs.lock().insert(id, job.clone());
+
+    let hash = format!("{:016x}", fnv1a(content.as_bytes()));
+    thread::spawn(move || {
+        let render = renderer.render();
+        while !renderer.is_done() {
+            if job.should_stop.load(Ordering::Relaxed) {
+                let _ = render.stop();
+                return;
+            }
+
+            thread::sleep(PROGRESS_INTERVAL);
+        }
+
+        let state = match render.join() {
+            Ok(()) => encode_exr(&renderer, id, &hash),
+            Err(_) => Err("Render thread panicked".to_string()),
+        };
+
+        *job.state.lock() = match state {
+            Ok(exr) => State::Done(exr),
+            Err(err) => State::Failed(err),
+        };
+    });
+
+    Ok(id)
+}
+
+/// Saves the finished render as OpenEXR into a temporary file and reads it back.
+fn encode_exr(renderer: &Renderer, id: u64, hash: &str) -> Result<Vec<u8>, String> {
+    let name = format!("rust_v_server_{}_{}.exr", std::process::id(), id);
+    let path = std::env::temp_dir().join(name);
+    let metadata = renderer.metadata().with("Scene hash", hash);
+
+    let result = renderer
+        .save_exr(&path, &metadata)
+        .map_err(|err| err.to_string())
+        .and_then(|_| std::fs::read(&path).map_err(|err| err.to_string()));
+    let _ = std::fs::remove_file(&path);
+
+    result
+}
+
+/// Streams the progress of a render job as server-sent events until it finishes.
+struct ProgressStream {
+    job: Arc<Job>,
+    /// The last sent progress
+    last: Option<f64>,
+    /// The bytes of the current event not yet read
+    pending: Vec<u8>,
+    finished: bool,
+}
+
+impl ProgressStream {
+    fn new(job: Arc<Job>) -> Self {
+        Self {
+            job,
+            last: None,
+            pending: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Returns the next event, waiting until there is one.
+    fn next_event(&mut self) -> String {
+        loop {
+            match &*self.job.state.lock() {
+                State::Done(_) => {
+                    self.finished = true;
+                    return "event: done\ndata: 1\n\n".to_string();
+                }
+                State::Failed(err) => {
+                    self.finished = true;
+                    return format!("event: error\ndata: {}\n\n", err.replace('\n', " "));
+                }
+                State::Rendering => {}
+            }
+
+            if self.job.should_stop.load(Ordering::Relaxed) {
+                self.finished = true;
+                return "event: error\ndata: Render job stopped\n\n".to_string();
+            }
+
+            let progress = self.job.renderer.progress_fraction();
+            if self.last != Some(progress) {
+                self.last = Some(progress);
+                return format!("data: {:.4}\n\n", progress);
+            }
+
+            thread::sleep(PROGRESS_INTERVAL);
+        }
+    }
+}
+
+impl Read for ProgressStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            if self.finished {
+                return Ok(0);
+            }
+
+            self.pending = self.next_event().into_bytes();
+        }
+
+        let n = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+
+        Ok(n)
+    }
+}
+
+#[test]
+fn routes_render_jobs() {
+    use tiny_http::TestRequest;
+
+    let jobs: Jobs = Arc::new(Mutex::new(HashMap::new()));
+    let next_id = AtomicU64::new(0);
+    let mut send = |method: Method, path: &str, body: &'static str| {
+        let mut request = TestRequest::new()
+            .with_method(method)
+            .with_path(path)
+            .with_body(body)
+            .into();
+        let response = route(&mut request, &jobs, &next_id, Some(1)).unwrap();
+        let status = response.status_code().0;
+        let mut data = Vec::new();
+        response.into_reader().read_to_end(&mut data).unwrap();
+
+        (status, data)
+    };
+
+    assert_eq!(send(Method::Get, "/", "").0, 404);
+    assert_eq!(send(Method::Get, "/render/0", "").0, 404);
+    assert_eq!(send(Method::Get, "/render/abc/exr", "").0, 404);
+    assert_eq!(send(Method::Post, "/render", "(").0, 400);
+
+    let scene = r#"(
+        config: (
+            filename: None,
+            bounds: None,
+            block_size: (x: 4, y: 4),
+            passes: 1,
+            threads: None,
+        ),
+        camera: {
+            "PerspectiveCamera": (
+                Sampler: Random,
+                Position: (x: 0, y: 0, z: 4),
+                Target: (x: 0, y: 0, z: 0),
+                Up: (x: 0, y: 1, z: 0),
+                FovY: 40,
+                Resolution: (x: 4, y: 4),
+            ),
+        },
+        integrator: {"Path": (max_depth: 2)},
+        sampler: Random,
+        scene: (
+            bounding_box: (
+                min: (x: -1, y: -1, z: -1),
+                max: (x: 1, y: 1, z: 1),
+            ),
+            objects: [],
+        ),
+    )"#;
+    let (status, id) = send(Method::Post, "/render", scene);
+    assert_eq!(status, 201);
+    let path = format!("/render/{}", String::from_utf8(id).unwrap());
+    assert_eq!(send(Method::Put, &path, "").0, 405);
+
+    let mut exr = send(Method::Get, &format!("{}/exr", path), "");
+    for _ in 0..200 {
+        if exr.0 != 202 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+        exr = send(Method::Get, &format!("{}/exr", path), "");
+    }
+    assert_eq!(exr.0, 200);
+    // the magic number of OpenEXR files
+    assert_eq!(exr.1[..4], [0x76, 0x2f, 0x31, 0x01]);
+
+    let (status, events) = send(Method::Get, &path, "");
+    assert_eq!(status, 200);
+    assert!(String::from_utf8(events)
+        .unwrap()
+        .ends_with("event: done\ndata: 1\n\n"));
+
+    assert_eq!(send(Method::Delete, &path, "").0, 204);
+    assert_eq!(send(Method::Get, &path, "").0, 404);
+}
@@ -1,13 +1,142 @@
-use crate::bxdf::{same_hemisphere, world_to_bxdf, BxDF, BxDFSample, BxDFSampleResult, Type};
+use crate::bxdf::{same_hemisphere, BxDF, BxDFSample, BxDFSampleResult, Frame, Type};
 use crate::debug_utils::is_normalized;
 use crate::samplers::Sample;
+use crate::texture::TexturePoint;
 use crate::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Default)]
-pub struct BSDF {
+/// The BxDF stored in a [BSDF], dispatched dynamically by default or statically with the
+/// `static-dispatch` feature.
+#[cfg(not(feature = "static-dispatch"))]
+pub type BxDFItem = Box<dyn BxDF>;
+/// The BxDF stored in a [BSDF], dispatched dynamically by default or statically with the
+/// `static-dispatch` feature.
+#[cfg(feature = "static-dispatch")]
+pub type BxDFItem = crate::bxdf::BxDFs;
+
+/// Wraps the given BxDF into a [BxDFItem], independently of the dispatch.
+///
+/// # Arguments
+/// * `bxdf` - The BxDF
+///
+/// # Returns
+/// * The BxDF item
+#[cfg(not(feature = "static-dispatch"))]
+pub fn bxdf_item<B: BxDF + 'static>(bxdf: B) -> BxDFItem {
+    Box::new(bxdf)
+}
+
+/// Wraps the given BxDF into a [BxDFItem], independently of the dispatch.
+///
+/// # Arguments
+/// * `bxdf` - The BxDF
+///
+/// # Returns
+/// * The BxDF item
+#[cfg(feature = "static-dispatch")]
+pub fn bxdf_item<B: Into<BxDFItem>>(bxdf: B) -> BxDFItem {
+    bxdf.into()
+}
+
+/// The number of distinct type masks, i.e. all combinations of the [Type] bits.
+const TYPE_MASKS: usize = 1 << 6;
+
+/// A BxDF of a [BSDF] with its [textures](BxDF::textured) evaluated at a surface point.
+enum Shaded<'a> {
+    Item(&'a BxDFItem),
+    Textured(Box<dyn BxDF>),
+}
+
+impl<'a> Shaded<'a> {
+    #[inline]
+    fn new(bxdf: &'a BxDFItem, at: TexturePoint) -> Self {
+        match bxdf.textured(at) {
+            Some(textured) => Shaded::Textured(textured),
+            None => Shaded::Item(bxdf),
+        }
+    }
+
+    #[inline]
+    fn evaluate(&self, incident: Vector3, outgoing: Vector3) -> Spectrum {
+        match self {
+            Shaded::Item(b) => b.evaluate(incident, outgoing),
+            Shaded::Textured(b) => b.evaluate(incident, outgoing),
+        }
+    }
+
+    #[inline]
+    fn evaluate_wavelength(&self, incident: Vector3, outgoing: Vector3, index: usize) -> Float {
+        match self {
+            Shaded::Item(b) => b.evaluate_wavelength(incident, outgoing, index),
+            Shaded::Textured(b) => b.evaluate_wavelength(incident, outgoing, index),
+        }
+    }
+
+    #[inline]
+    fn sample(&self, outgoing: Vector3, sample: Vector2) -> Option<BxDFSample<Spectrum>> {
+        match self {
+            Shaded::Item(b) => b.sample(outgoing, sample),
+            Shaded::Textured(b) => b.sample(outgoing, sample),
+        }
+    }
+
+    #[inline]
+    fn sample_buf(
+        &self,
+        outgoing: Vector3,
+        sample: Vector2,
+        indices: &[usize],
+    ) -> Option<BxDFSampleResult> {
+        match self {
+            Shaded::Item(b) => b.sample_buf(outgoing, sample, indices),
+            Shaded::Textured(b) => b.sample_buf(outgoing, sample, indices),
+        }
+    }
+
+    #[inline]
+    fn sample_wavelength(
+        &self,
+        outgoing: Vector3,
+        sample: Vector2,
+        index: usize,
+    ) -> Option<BxDFSample<Float>> {
+        match self {
+            Shaded::Item(b) => b.sample_wavelength(outgoing, sample, index),
+            Shaded::Textured(b) => b.sample_wavelength(outgoing, sample, index),
+        }
+    }
+
+    #[inline]
+    fn pdf(&self, incident: Vector3, outgoing: Vector3) -> Float {
+        match self {
+            Shaded::Item(b) => b.pdf(incident, outgoing),
+            Shaded::Textured(b) => b.pdf(incident, outgoing),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SerdeBSDF {
     #[serde(default)]
-    bxdfs: Vec<Box<dyn BxDF>>,
+    bxdfs: Vec<BxDFItem>,
+}
+
+impl From<SerdeBSDF> for BSDF {
+    fn from(serde: SerdeBSDF) -> Self {
+        Self::new(serde.bxdfs)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(from = "SerdeBSDF")]
+pub struct BSDF {
+    bxdfs: Vec<BxDFItem>,
+    /// The indices of the BxDFs matching each type mask, grouped by mask
+    #[serde(skip)]
+    matching: Vec<u32>,
+    /// The ranges of each type mask inside `matching`
+    #[serde(skip)]
+    offsets: Vec<u32>,
 }
 
 impl BSDF {
@@ -15,8 +144,48 @@ impl BSDF {
         Self::new(vec![])
     }
 
-    pub fn new(bxdfs: Vec<Box<dyn BxDF>>) -> Self {
-        Self { bxdfs }
+    pub fn new(bxdfs: Vec<BxDFItem>) -> Self {
+        let mut matching = Vec::new();
+        let mut offsets = Vec::with_capacity(TYPE_MASKS + 1);
+        offsets.push(0);
+
+        // precompute the matching BxDFs of each type mask for O(1) selection
+        for mask in 0..TYPE_MASKS {
+            let t = Type::from_bits_truncate(mask as u8);
+
+            matching.extend(
+                bxdfs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, bxdf)| bxdf.is_type(t))
+                    .map(|(i, _)| i as u32),
+            );
+            offsets.push(matching.len() as u32);
+        }
+
+        Self {
+            bxdfs,
+            matching,
+            offsets,
+        }
+    }
+
+    /// Returns the indices of the BxDFs matching the given type.
+    #[inline]
+    fn matching_indices(&self, t: Type) -> &[u32] {
+        let mask = t.bits() as usize & (TYPE_MASKS - 1);
+        let start = self.offsets[mask] as usize;
+        let end = self.offsets[mask + 1] as usize;
+
+        &self.matching[start..end]
+    }
+
+    /// Returns the BxDFs matching the given type.
+    #[inline]
+    fn matching(&self, t: Type) -> impl Iterator<Item = &BxDFItem> {
+        self.matching_indices(t)
+            .iter()
+            .map(move |&i| &self.bxdfs[i as usize])
     }
 
     pub fn size(&self) -> usize {
@@ -27,40 +196,39 @@ impl BSDF {
         self.size() == 0
     }
 
+    /// Returns the union of the types of all BxDFs.
+    ///
+    /// # Returns
+    /// * The types
+    pub fn types(&self) -> Type {
+        self.bxdfs
+            .iter()
+            .fold(Type::empty(), |types, bxdf| types | bxdf.get_type())
+    }
+
     pub fn num_types(&self, t: Type) -> usize {
-        self.bxdfs.iter().filter(|bxdf| bxdf.is_type(t)).count()
+        self.matching_indices(t).len()
     }
 
-    pub fn random_matching_bxdf(&self, t: Type, rand: Float) -> Option<&dyn BxDF> {
-        let count = self.num_types(t);
-        if count == 0 {
+    pub fn random_matching_bxdf(&self, t: Type, rand: Float) -> Option<&BxDFItem> {
+        let indices = self.matching_indices(t);
+        if indices.is_empty() {
             return None;
         }
 
-        let index = (rand * count as Float) as usize;
-        self.bxdfs
-            .iter()
-            .filter_map(|bxdf| {
-                if bxdf.is_type(t) {
-                    Some(bxdf.as_ref())
-                } else {
-                    None
-                }
-            })
-            .nth(index)
+        let index = ((rand * indices.len() as Float) as usize).min(indices.len() - 1);
+        Some(&self.bxdfs[indices[index] as usize])
     }
 
     pub fn evaluate(
         &self,
-        normal: Vector3,
+        frame: Frame,
         incident_world: Vector3,
         outgoing_world: Vector3,
         mut types: Type,
     ) -> Spectrum {
-        let rotation = world_to_bxdf(normal);
-
-        let incident = rotation * incident_world;
-        let outgoing = rotation * outgoing_world;
+        let incident = frame.to_local(incident_world);
+        let outgoing = frame.to_local(outgoing_world);
 
         // transmission or reflection
         if same_hemisphere(incident, outgoing) {
@@ -69,22 +237,15 @@ impl BSDF {
             types &= !Type::REFLECTION;
         }
 
-        self.bxdfs
-            .iter()
-            .filter_map(|bxdf| {
-                if bxdf.is_type(types) {
-                    Some(bxdf.evaluate(incident, outgoing))
-                } else {
-                    None
-                }
-            })
+        self.matching(types)
+            .map(|bxdf| Shaded::new(bxdf, frame.texture_point()).evaluate(incident, outgoing))
             .sum()
     }
 
     /// Evaluates a random BxDF.
     ///
     /// # Arguments
-    /// * `normal` - The surface normal. Used to rotate into the local BxDF space.
+    /// * `frame` - The shading frame of the surface. Used to rotate into the local BxDF space.
     /// * `incident_world` - The incoming incident vector in world space.
     /// * `outgoing_world` - The outgoing incident vector in world space.
     /// * `types` - The types to match a BxDF randomly.
@@ -93,15 +254,14 @@ impl BSDF {
     ///               dependencies.
     pub fn evaluate_buf(
         &self,
-        normal: Vector3,
+        frame: Frame,
         incident_world: Vector3,
         outgoing_world: Vector3,
         mut types: Type,
         indices: &[usize],
     ) -> Vec<Float> {
-        let rotation = world_to_bxdf(normal);
-        let incident = rotation * incident_world;
-        let outgoing = rotation * outgoing_world;
+        let incident = frame.to_local(incident_world);
+        let outgoing = frame.to_local(outgoing_world);
 
         // transmission or reflection
         if same_hemisphere(incident, outgoing) {
@@ -111,29 +271,26 @@ impl BSDF {
         }
 
         let mut buf = vec![0.0; indices.len()];
-        self.bxdfs
-            .iter()
-            .filter(|bxdf| bxdf.is_type(types))
-            .for_each(|bxdf| {
-                for i in 0..indices.len() {
-                    buf[i] += bxdf.evaluate_wavelength(incident, outgoing, indices[i]);
-                }
-            });
+        self.matching(types).for_each(|bxdf| {
+            let bxdf = Shaded::new(bxdf, frame.texture_point());
+            for i in 0..indices.len() {
+                buf[i] += bxdf.evaluate_wavelength(incident, outgoing, indices[i]);
+            }
+        });
 
         buf
     }
 
     pub fn evaluate_wavelength(
         &self,
-        normal: Vector3,
+        frame: Frame,
         incident_world: Vector3,
         outgoing_world: Vector3,
         mut types: Type,
         light_wave_index: usize,
     ) -> Float {
-        let rotation = world_to_bxdf(normal);
-        let incident = rotation * incident_world;
-        let outgoing = rotation * outgoing_world;
+        let incident = frame.to_local(incident_world);
+        let outgoing = frame.to_local(outgoing_world);
 
         // transmission or reflection
         if same_hemisphere(incident, outgoing) {
@@ -142,49 +299,49 @@ impl BSDF {
             types &= !Type::REFLECTION;
         }
 
-        self.bxdfs
-            .iter()
-            .filter_map(|bxdf| {
-                if bxdf.is_type(types) {
-                    Some(bxdf.evaluate_wavelength(incident, outgoing, light_wave_index))
-                } else {
-                    None
-                }
+        self.matching(types)
+            .map(|bxdf| {
+                Shaded::new(bxdf, frame.texture_point()).evaluate_wavelength(
+                    incident,
+                    outgoing,
+                    light_wave_index,
+                )
             })
             .sum()
     }
 
     pub fn evaluate_bxdf_light_wave(
         bxdf: &dyn BxDF,
-        normal: Vector3,
+        frame: Frame,
         incident_world: Vector3,
         outgoing_world: Vector3,
         light_wave_index: usize,
     ) -> Float {
-        let rotation = world_to_bxdf(normal);
-        let incident = rotation * incident_world;
-        let outgoing = rotation * outgoing_world;
+        let incident = frame.to_local(incident_world);
+        let outgoing = frame.to_local(outgoing_world);
 
         bxdf.evaluate_wavelength(incident, outgoing, light_wave_index)
     }
 
     pub fn sample(
         &self,
-        normal: Vector3,
+        frame: Frame,
         outgoing_world: Vector3,
         types: Type,
         sample: Sample,
     ) -> Option<BxDFSample<Spectrum>> {
-        debug_assert!(is_normalized(normal));
+        debug_assert!(is_normalized(frame.n));
         debug_assert!(is_normalized(outgoing_world));
 
-        let rotation = world_to_bxdf(normal);
-        let outgoing = rotation * outgoing_world;
+        let outgoing = frame.to_local(outgoing_world);
 
-        let bxdf = self.random_matching_bxdf(types, sample.one_d)?;
+        let bxdf = Shaded::new(
+            self.random_matching_bxdf(types, sample.one_d)?,
+            frame.texture_point(),
+        );
 
         bxdf.sample(outgoing, sample.two_d).map(|mut s| {
-            s.incident = rotation.reversed() * s.incident;
+            s.incident = frame.to_world(s.incident);
             s
         })
     }
@@ -192,7 +349,7 @@ impl BSDF {
     /// Samples a random BxDF.
     ///
     /// # Arguments
-    /// * `normal` - The surface normal. Used to rotate into the local BxDF space.
+    /// * `frame` - The shading frame of the surface. Used to rotate into the local BxDF space.
     /// * `outgoing_world` - The outgoing incident vector in world space.
     /// * `types` - The types to match a BxDF randomly.
     /// * `sample` - The random sample for decisions.
@@ -201,29 +358,29 @@ impl BSDF {
     ///               dependencies.
     pub fn sample_buf(
         &self,
-        normal: Vector3,
+        frame: Frame,
         outgoing_world: Vector3,
         types: Type,
         sample: Sample,
         indices: &[usize],
     ) -> Option<BxDFSampleResult> {
-        debug_assert!(is_normalized(normal));
+        debug_assert!(is_normalized(frame.n));
         debug_assert!(is_normalized(outgoing_world));
 
-        let rotation = world_to_bxdf(normal);
-        let outgoing = rotation * outgoing_world;
+        let outgoing = frame.to_local(outgoing_world);
 
-        let bxdf = self.random_matching_bxdf(types, sample.one_d)?;
+        let bxdf = Shaded::new(
+            self.random_matching_bxdf(types, sample.one_d)?,
+            frame.texture_point(),
+        );
 
         bxdf.sample_buf(outgoing, sample.two_d, indices)
             .map(|mut sample| {
-                let reverse = rotation.reversed();
-
                 match sample {
-                    BxDFSampleResult::Bundle(ref mut s) => s.incident = reverse * s.incident,
-                    BxDFSampleResult::ScatteredBundle(ref mut s) => {
-                        s.iter_mut().for_each(|s| s.incident = reverse * s.incident)
-                    }
+                    BxDFSampleResult::Bundle(ref mut s) => s.incident = frame.to_world(s.incident),
+                    BxDFSampleResult::ScatteredBundle(ref mut s) => s
+                        .iter_mut()
+                        .for_each(|s| s.incident = frame.to_world(s.incident)),
                 }
 
                 sample
@@ -232,48 +389,42 @@ impl BSDF {
 
     pub fn sample_light_wave(
         &self,
-        normal: Vector3,
+        frame: Frame,
         outgoing_world: Vector3,
         types: Type,
         sample: Sample,
         light_wave_index: usize,
     ) -> Option<BxDFSample<Float>> {
-        debug_assert!(is_normalized(normal));
+        debug_assert!(is_normalized(frame.n));
         debug_assert!(is_normalized(outgoing_world));
 
-        let rotation = world_to_bxdf(normal);
-        let outgoing = rotation * outgoing_world;
+        let outgoing = frame.to_local(outgoing_world);
 
-        let bxdf = self.random_matching_bxdf(types, sample.one_d)?;
+        let bxdf = Shaded::new(
+            self.random_matching_bxdf(types, sample.one_d)?,
+            frame.texture_point(),
+        );
 
         bxdf.sample_wavelength(outgoing, sample.two_d, light_wave_index)
             .map(|mut s| {
-                s.incident = rotation.reversed() * s.incident;
+                s.incident = frame.to_world(s.incident);
                 s
             })
     }
 
     pub fn pdf(
         &self,
-        normal: Vector3,
+        frame: Frame,
         incident_world: Vector3,
         outgoing_world: Vector3,
         types: Type,
     ) -> Float {
-        let rotation = world_to_bxdf(normal);
-        let incident = rotation * incident_world;
-        let outgoing = rotation * outgoing_world;
+        let incident = frame.to_local(incident_world);
+        let outgoing = frame.to_local(outgoing_world);
 
         let (pdf, num) = self
-            .bxdfs
-            .iter()
-            .filter_map(|bxdf| {
-                if bxdf.is_type(types) {
-                    Some(bxdf.pdf(incident, outgoing))
-                } else {
-                    None
-                }
-            })
+            .matching(types)
+            .map(|bxdf| Shaded::new(bxdf, frame.texture_point()).pdf(incident, outgoing))
             .fold((0.0, 0usize), |(a, num), b| (a + b, num + 1));
 
         if num > 0 {
@@ -283,3 +434,65 @@ impl BSDF {
         }
     }
 }
+
+impl Default for BSDF {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+#[test]
+fn select_matching_bxdfs_uniformly() {
+    use crate::bxdf::{LambertianReflection, LambertianTransmission};
+    use color::{Color, Spectrum};
+
+    let bsdf = BSDF::new(vec![
+        bxdf_item(LambertianReflection::new(Spectrum::broadcast(0.5))),
+        bxdf_item(LambertianTransmission::new(Spectrum::broadcast(0.5))),
+        bxdf_item(LambertianReflection::new(Spectrum::broadcast(0.2))),
+    ]);
+
+    let n = 1200;
+    for &t in &[
+        Type::ALL,
+        Type::REFLECTION | Type::DIFFUSE,
+        Type::TRANSMISSION | Type::DIFFUSE,
+        // nothing matches
+        Type::REFLECTION | Type::SPECULAR,
+    ] {
+        let candidates: Vec<usize> = (0..bsdf.size())
+            .filter(|&i| bsdf.bxdfs[i].is_type(t))
+            .collect();
+        assert_eq!(bsdf.num_types(t), candidates.len());
+
+        let mut counts = vec![0; bsdf.size()];
+        for s in 0..n {
+            let rand = (s as Float + 0.5) / n as Float;
+
+            match bsdf.random_matching_bxdf(t, rand) {
+                Some(bxdf) => {
+                    let i = (0..bsdf.size())
+                        .find(|&i| std::ptr::eq(bxdf, &bsdf.bxdfs[i]))
+                        .unwrap();
+                    counts[i] += 1;
+                }
+                None => assert!(candidates.is_empty()),
+            }
+        }
+
+        // every match is selected with the probability 1 / num_types, as assumed by the pdf
+        for (i, &count) in counts.iter().enumerate() {
+            let expected = if candidates.contains(&i) {
+                n / candidates.len()
+            } else {
+                0
+            };
+            assert_eq!(count, expected, "{:?} selected {} {} times", t, i, count);
+        }
+
+        let last = bsdf.random_matching_bxdf(t, 1.0 - Float::EPSILON);
+        assert_eq!(last.is_some(), !candidates.is_empty());
+    }
+
+    assert!(BSDF::empty().random_matching_bxdf(Type::ALL, 0.5).is_none());
+}
@@ -0,0 +1,450 @@
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::PI;
+#[cfg(feature = "f64")]
+use std::f64::consts::PI;
+
+use crate::bxdf::*;
+use crate::mc::sample_unit_hemisphere;
+use crate::*;
+use color::Color;
+use serde::{Deserialize, Serialize};
+use utility::floats::FloatExt;
+
+/// The minimal microfacet roughness of the coat, avoiding a degenerate distribution.
+const MIN_ROUGHNESS: Float = 1e-3;
+
+/// The number of strata per dimension to estimate the albedo of the base with.
+const ALBEDO_STRATA: usize = 16;
+
+/// The number of steps to integrate the hemispherical Fresnel reflectance with.
+const FRESNEL_STEPS: usize = 128;
+
+fn one() -> Float {
+    1.0
+}
+
+/// A dielectric coat (e.g. lacquer or clearcoat) on top of an arbitrary base BSDF, like the
+/// layers of car paint or varnished wood.
+///
+/// Following the statistical layering of Weidlich and Wilkie, light either reflects off the rough
+/// surface of the coat, weighted by the Fresnel reflectance, or refracts into the coat and scatters
+/// at the base, which gets evaluated at the refracted directions. Light passing through the coat
+/// gets weighted by the Fresnel transmittance and attenuated by the absorption along its path
+/// through the coat on the way in and out.
+///
+/// Light reflected back at the base by the inside of the coat is accounted for by the geometric
+/// series of the interreflections between the base's albedo and the hemispherical internal Fresnel
+/// reflectance, such that a white diffuse base keeps its energy.
+#[derive(Serialize, Deserialize)]
+#[serde(from = "CoatedSerde")]
+pub struct Coated {
+    base: BSDF,
+    /// The index of refraction of the coat
+    ior: Float,
+    /// The roughness of the coat's surface
+    roughness: Float,
+    /// The thickness of the coat
+    #[serde(default = "one")]
+    thickness: Float,
+    /// The absorption coefficient of the coat per unit thickness
+    #[serde(default)]
+    absorption: Spectrum,
+    /// The hemispherical reflectance of the inside of the coat
+    #[serde(skip)]
+    internal_reflectance: Float,
+    /// The estimated albedo of the base
+    #[serde(skip)]
+    base_albedo: Spectrum,
+}
+
+/// The serialized form of a [`Coated`](Coated), without the precomputed reflectances.
+#[derive(Deserialize)]
+struct CoatedSerde {
+    base: BSDF,
+    ior: Float,
+    roughness: Float,
+    #[serde(default = "one")]
+    thickness: Float,
+    #[serde(default)]
+    absorption: Spectrum,
+}
+
+impl From<CoatedSerde> for Coated {
+    fn from(serde: CoatedSerde) -> Self {
+        Self::new(
+            serde.base,
+            serde.ior,
+            serde.roughness,
+            serde.thickness,
+            serde.absorption,
+        )
+    }
+}
+
+impl Coated {
+    /// Creates a new coated BSDF.
+    ///
+    /// # Constraints
+    /// * `ior` - Should be greater than `0`.
+    /// * `roughness` - Should be within `[0, 1]`.
+    /// * `thickness` - Should be non-negative.
+    ///
+    /// # Arguments
+    /// * `base` - The BSDF below the coat
+    /// * `ior` - The index of refraction of the coat
+    /// * `roughness` - The roughness of the coat's surface
+    /// * `thickness` - The thickness of the coat
+    /// * `absorption` - The absorption coefficient of the coat per unit thickness
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(
+        base: BSDF,
+        ior: Float,
+        roughness: Float,
+        thickness: Float,
+        absorption: Spectrum,
+    ) -> Self {
+        debug_assert!(ior > 0.0);
+        debug_assert!(thickness >= 0.0);
+
+        let mut coated = Self {
+            base,
+            ior,
+            roughness,
+            thickness,
+            absorption,
+            internal_reflectance: 0.0,
+            base_albedo: Spectrum::broadcast(0.0),
+        };
+        coated.internal_reflectance = coated.internal_reflectance();
+        coated.base_albedo = coated.base_albedo();
+
+        coated
+    }
+
+    /// Integrates the hemispherical reflectance of the inside of the coat for diffuse light. By
+    /// reciprocity, the transmitted part relates to the one of the outside by `1 / ior²`, the rest
+    /// getting reflected (mostly by total internal reflection).
+    fn internal_reflectance(&self) -> Float {
+        let external = (0..FRESNEL_STEPS)
+            .map(|i| {
+                let cos = (i as Float + 0.5) / FRESNEL_STEPS as Float;
+                self.fresnel(cos) * 2.0 * cos
+            })
+            .sum::<Float>()
+            / FRESNEL_STEPS as Float;
+
+        1.0 - (1.0 - external) / (self.ior * self.ior)
+    }
+
+    /// Estimates the albedo of the base at normal incidence by stratified cosine sampling.
+    fn base_albedo(&self) -> Spectrum {
+        let outgoing = Vector3::unit_y();
+        let mut albedo = Spectrum::broadcast(0.0);
+
+        for x in 0..ALBEDO_STRATA {
+            for y in 0..ALBEDO_STRATA {
+                let sample = Vector2::new(x as Float + 0.5, y as Float + 0.5);
+                let incident = sample_unit_hemisphere(sample / ALBEDO_STRATA as Float);
+
+                albedo += self
+                    .base
+                    .evaluate(bxdf_frame(), incident, outgoing, Type::ALL);
+            }
+        }
+
+        // the pdf of cosine sampling is cos / π
+        let albedo = albedo * PI / (ALBEDO_STRATA * ALBEDO_STRATA) as Float;
+
+        albedo.clamp(0.0, 1.0)
+    }
+
+    fn distribution(&self) -> TrowbridgeReitzDistribution {
+        let alpha = self.roughness.fast_max(MIN_ROUGHNESS);
+
+        TrowbridgeReitzDistribution::new(alpha, alpha, true)
+    }
+
+    fn fresnel(&self, cos_i: Float) -> Float {
+        fresnel_dielectric(cos_i, 1.0, self.ior)
+    }
+
+    /// Refracts the given direction into the coat, mirrored into the upper hemisphere to
+    /// evaluate the base with. Directions below the surface do not pass through the coat.
+    fn refract_in(&self, w: Vector3) -> Vector3 {
+        let cos = cos_theta(w);
+        if cos <= 0.0 {
+            return w;
+        }
+
+        let sin2_t = sin2_theta(w) / (self.ior * self.ior);
+        let cos_t = (1.0 - sin2_t).fast_max(0.0).sqrt();
+
+        Vector3::new(w.x / self.ior, cos_t, w.z / self.ior)
+    }
+
+    /// Refracts the given direction inside the coat out of it, the inverse of
+    /// [refract_in](Coated::refract_in).
+    ///
+    /// # Returns
+    /// * The refracted direction, or `None` on total internal reflection
+    fn refract_out(&self, w: Vector3) -> Option<Vector3> {
+        if cos_theta(w) <= 0.0 {
+            return Some(w);
+        }
+
+        let (x, z) = (w.x * self.ior, w.z * self.ior);
+        let sin2 = x * x + z * z;
+        if sin2 >= 1.0 {
+            return None;
+        }
+
+        Some(Vector3::new(x, (1.0 - sin2).sqrt(), z))
+    }
+
+    /// Returns the Fresnel transmittance into the coat and the distance travelled inside it
+    /// relative to its thickness for the given direction. Directions below the surface do not
+    /// pass through the coat.
+    fn transmission(&self, w: Vector3) -> (Float, Float) {
+        let cos = cos_theta(w);
+        if cos <= 0.0 {
+            return (1.0, 0.0);
+        }
+
+        let cos_t = cos_theta(self.refract_in(w));
+
+        (
+            1.0 - self.fresnel(cos),
+            1.0 / cos_t.fast_max(Float::big_epsilon()),
+        )
+    }
+
+    /// Returns the achromatic transmittance of the interfaces, including the compression of the
+    /// radiance leaving the coat by `1 / ior²`, and the relative path length through the coat
+    /// towards the base.
+    fn attenuation(&self, incident: Vector3, outgoing: Vector3) -> (Float, Float) {
+        let (t_i, d_i) = self.transmission(incident);
+        let (t_o, d_o) = self.transmission(outgoing);
+        let compression = if cos_theta(outgoing) > 0.0 {
+            1.0 / (self.ior * self.ior)
+        } else {
+            1.0
+        };
+
+        (t_i * t_o * compression, (d_i + d_o) * self.thickness)
+    }
+
+    /// Returns the change of the solid angle of the given direction by refracting it into the
+    /// coat, i.e. `dω' / dω`.
+    fn solid_angle_ratio(&self, w: Vector3) -> Float {
+        let cos = cos_theta(w);
+        if cos <= 0.0 {
+            return 1.0;
+        }
+
+        let cos_t = cos_theta(self.refract_in(w)).fast_max(Float::big_epsilon());
+
+        cos / (self.ior * self.ior * cos_t)
+    }
+
+    /// Returns the transmittance through the coat at the given wavelength index, including the
+    /// interreflections between the base and the inside of the coat. Diffusely interreflected
+    /// light travels twice the thickness on average per traversal.
+    fn layer_transmittance(&self, distance: Float, index: usize) -> Float {
+        let absorption = (-self.absorption[index] * distance).exp();
+        let interreflected = self.internal_reflectance
+            * self.base_albedo[index]
+            * (-self.absorption[index] * 4.0 * self.thickness).exp();
+
+        absorption / (1.0 - interreflected)
+    }
+
+    fn layer_spectrum(&self, distance: Float) -> Spectrum {
+        let mut transmittance = Spectrum::broadcast(1.0);
+        for i in 0..Spectrum::size() {
+            transmittance[i] = self.layer_transmittance(distance, i);
+        }
+
+        transmittance
+    }
+
+    /// Evaluates the microfacet reflection off the coat's surface.
+    fn evaluate_coat(&self, incident: Vector3, outgoing: Vector3) -> Float {
+        let cos_i = cos_theta(incident);
+        let cos_o = cos_theta(outgoing);
+        if cos_i <= 0.0 || cos_o <= 0.0 {
+            return 0.0;
+        }
+
+        let wh = (incident + outgoing).normalized();
+        let distribution = self.distribution();
+
+        self.fresnel(incident.dot(wh)) * distribution.d(wh) * distribution.g(incident, outgoing)
+            / (4.0 * cos_i * cos_o)
+    }
+
+    fn pdf_coat(&self, incident: Vector3, outgoing: Vector3) -> Float {
+        if cos_theta(incident) <= 0.0 || cos_theta(outgoing) <= 0.0 {
+            return 0.0;
+        }
+
+        let wh = (incident + outgoing).normalized();
+
+        self.distribution().pdf(outgoing, wh) / (4.0 * outgoing.dot(wh))
+    }
+
+    /// Returns the probability of sampling the coat instead of the base, following the Fresnel
+    /// reflectance.
+    fn coat_probability(&self, outgoing: Vector3) -> Float {
+        if self.base.is_empty() {
+            1.0
+        } else if cos_theta(outgoing) > 0.0 {
+            self.fresnel(cos_theta(outgoing))
+        } else {
+            0.0
+        }
+    }
+}
+
+#[typetag::serde]
+impl BxDF for Coated {
+    fn get_type(&self) -> Type {
+        Type::REFLECTION | Type::GLOSSY | self.base.types()
+    }
+
+    fn evaluate(&self, incident: Vector3, outgoing: Vector3) -> Spectrum {
+        let (transmittance, distance) = self.attenuation(incident, outgoing);
+        let (incident_t, outgoing_t) = (self.refract_in(incident), self.refract_in(outgoing));
+        let base = self
+            .base
+            .evaluate(bxdf_frame(), incident_t, outgoing_t, Type::ALL);
+
+        base * self.layer_spectrum(distance) * transmittance
+            + Spectrum::broadcast(self.evaluate_coat(incident, outgoing))
+    }
+
+    fn evaluate_wavelength(&self, incident: Vector3, outgoing: Vector3, index: usize) -> Float {
+        let (transmittance, distance) = self.attenuation(incident, outgoing);
+        let (incident_t, outgoing_t) = (self.refract_in(incident), self.refract_in(outgoing));
+        let base =
+            self.base
+                .evaluate_wavelength(bxdf_frame(), incident_t, outgoing_t, Type::ALL, index);
+
+        base * self.layer_transmittance(distance, index) * transmittance
+            + self.evaluate_coat(incident, outgoing)
+    }
+
+    fn sample(&self, outgoing: Vector3, sample: Vector2) -> Option<BxDFSample<Spectrum>> {
+        debug_assert!(is_normalized(outgoing));
+        debug_assert!(within_01(sample));
+
+        let p = self.coat_probability(outgoing);
+
+        if sample.x < p {
+            // reuse the sample for the coat, remapped to [0, 1)
+            let sample = Vector2::new((sample.x / p).fast_min(1.0 - Float::EPSILON), sample.y);
+            let wh = self.distribution().sample_wh(outgoing, sample);
+            let incident = 2.0 * outgoing.dot(wh) * wh - outgoing;
+
+            if cos_theta(incident) <= 0.0 {
+                return None;
+            }
+
+            let spectrum = self.evaluate(incident, outgoing);
+            let pdf = self.pdf(incident, outgoing);
+
+            return Some(BxDFSample::new(
+                spectrum,
+                incident,
+                pdf,
+                Type::REFLECTION | Type::GLOSSY,
+            ));
+        }
+
+        // select a BxDF of the base and sample it inside the coat, remapping the sample each time
+        let u = ((sample.x - p) / (1.0 - p)).fast_min(1.0 - Float::EPSILON);
+        let n = self.base.size() as Float;
+        let bxdf = self.base.random_matching_bxdf(Type::ALL, u)?;
+        let u = (u * n).fract();
+
+        let base_sample = bxdf.sample(self.refract_in(outgoing), Vector2::new(u, sample.y))?;
+        let incident = self.refract_out(base_sample.incident)?;
+
+        if base_sample.typ.is_specular() {
+            // delta distributions cannot be combined with the other lobes
+            let (transmittance, distance) = self.attenuation(incident, outgoing);
+            let spectrum = base_sample.spectrum * self.layer_spectrum(distance) * transmittance;
+            let pdf = base_sample.pdf * self.solid_angle_ratio(incident) * (1.0 - p) / n;
+
+            return Some(BxDFSample::new(spectrum, incident, pdf, base_sample.typ));
+        }
+
+        let spectrum = self.evaluate(incident, outgoing);
+        let pdf = self.pdf(incident, outgoing);
+
+        Some(BxDFSample::new(spectrum, incident, pdf, base_sample.typ))
+    }
+
+    fn pdf(&self, incident: Vector3, outgoing: Vector3) -> Float {
+        let p = self.coat_probability(outgoing);
+        let base = if p < 1.0 {
+            let (incident_t, outgoing_t) = (self.refract_in(incident), self.refract_in(outgoing));
+
+            self.base
+                .pdf(bxdf_frame(), incident_t, outgoing_t, Type::ALL)
+                * self.solid_angle_ratio(incident)
+        } else {
+            0.0
+        };
+
+        p * self.pdf_coat(incident, outgoing) + (1.0 - p) * base
+    }
+}
+
+#[test]
+fn coat_conserves_energy() {
+    let base: BSDF =
+        ron::from_str(r#"(bxdfs: [{"LambertianReflection": (r: Constant(1))}])"#).unwrap();
+    let coated = Coated::new(base, 1.5, 0.2, 1.0, Spectrum::broadcast(0.0));
+    let outgoing = Vector3::new(0.3, 0.8, 0.1).normalized();
+
+    // cosine-weighted hemisphere sampling, the pdf being cos / π
+    let n = 256;
+    let mut albedo = 0.0;
+    for x in 0..n {
+        for y in 0..n {
+            let sample = Vector2::new(x as Float + 0.5, y as Float + 0.5) / n as Float;
+            let incident = sample_unit_hemisphere(sample);
+
+            albedo += coated.evaluate(incident, outgoing)[0] * PI;
+        }
+    }
+    albedo /= (n * n) as Float;
+
+    assert!(albedo > 0.5);
+    assert!(albedo <= 1.0);
+}
+
+#[test]
+fn interreflections_keep_white_base_energy() {
+    let base: BSDF =
+        ron::from_str(r#"(bxdfs: [{"LambertianReflection": (r: Constant(1))}])"#).unwrap();
+    let coated = Coated::new(base, 1.5, 0.2, 1.0, Spectrum::broadcast(0.0));
+    let outgoing = Vector3::new(-0.5, 0.6, 0.2).normalized();
+
+    let n = 256;
+    let mut albedo = 0.0;
+    for x in 0..n {
+        for y in 0..n {
+            let sample = Vector2::new(x as Float + 0.5, y as Float + 0.5) / n as Float;
+            let incident = sample_unit_hemisphere(sample);
+
+            albedo += coated.evaluate(incident, outgoing)[0] * PI;
+        }
+    }
+    albedo /= (n * n) as Float;
+
+    assert!(albedo > 0.95, "albedo {}", albedo);
+    assert!(albedo <= 1.0, "albedo {}", albedo);
+}
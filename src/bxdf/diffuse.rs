@@ -1,5 +1,6 @@
 use crate::bxdf::{bxdf_normal, BxDF, BxDFSample, BxDFSampleResult, Type};
 use crate::mc::sample_unit_sphere;
+use crate::texture::{Texture, TexturePoint};
 use crate::*;
 use serde::{Deserialize, Serialize};
 #[cfg(not(feature = "f64"))]
@@ -11,7 +12,7 @@ use std::f64::consts::FRAC_1_PI;
 /// It is therefore not equally distributed like the lambertian reflection.
 #[derive(Serialize, Deserialize)]
 pub struct DiffuseReflection {
-    r: Spectrum,
+    r: Texture<Spectrum>,
 }
 
 #[typetag::serde]
@@ -21,11 +22,21 @@ impl BxDF for DiffuseReflection {
     }
 
     fn evaluate(&self, _: Vector3, _: Vector3) -> Spectrum {
-        self.r * FRAC_1_PI
+        self.r.evaluate(TexturePoint::default()) * FRAC_1_PI
     }
 
     fn evaluate_wavelength(&self, _: Vector3, _: Vector3, index: usize) -> Float {
-        self.r[index] * FRAC_1_PI
+        self.r.evaluate(TexturePoint::default())[index] * FRAC_1_PI
+    }
+
+    fn textured(&self, at: TexturePoint) -> Option<Box<dyn BxDF>> {
+        if self.r.is_constant() {
+            return None;
+        }
+
+        Some(Box::new(Self {
+            r: Texture::Constant(self.r.evaluate(at)),
+        }))
     }
 
     fn sample(&self, outgoing: Vector3, sample: Vector2) -> Option<BxDFSample<Spectrum>> {
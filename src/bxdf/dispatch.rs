@@ -0,0 +1,215 @@
+//! Enum-dispatched BxDFs, replacing the `Box<dyn BxDF>` of a [BSDF](crate::bxdf::BSDF) with the
+//! `static-dispatch` feature.
+//!
+//! The (de-)serialization format is the same as the one of the `typetag` trait objects, so scene
+//! files stay compatible.
+
+use crate::bxdf::*;
+use crate::texture::TexturePoint;
+use crate::{Float, Spectrum, Vector2, Vector3};
+use serde::de::{self, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+macro_rules! bxdfs {
+    ($($variant:ident),+ $(,)?) => {
+        /// A closed set of all BxDFs, allowing static dispatch.
+        pub enum BxDFs {
+            $($variant($variant)),+
+        }
+
+        $(
+            impl From<$variant> for BxDFs {
+                fn from(bxdf: $variant) -> Self {
+                    Self::$variant(bxdf)
+                }
+            }
+        )+
+
+        impl BxDFs {
+            #[inline]
+            pub fn get_type(&self) -> Type {
+                match self {
+                    $(BxDFs::$variant(b) => b.get_type()),+
+                }
+            }
+
+            #[inline]
+            pub fn is_type(&self, t: Type) -> bool {
+                match self {
+                    $(BxDFs::$variant(b) => b.is_type(t)),+
+                }
+            }
+
+            #[inline]
+            pub fn evaluate(&self, incident: Vector3, outgoing: Vector3) -> Spectrum {
+                match self {
+                    $(BxDFs::$variant(b) => b.evaluate(incident, outgoing)),+
+                }
+            }
+
+            #[inline]
+            pub fn evaluate_buf(
+                &self,
+                incident: Vector3,
+                outgoing: Vector3,
+                indices: &[usize],
+            ) -> Vec<Float> {
+                match self {
+                    $(BxDFs::$variant(b) => b.evaluate_buf(incident, outgoing, indices)),+
+                }
+            }
+
+            #[inline]
+            pub fn evaluate_wavelength(
+                &self,
+                incident: Vector3,
+                outgoing: Vector3,
+                index: usize,
+            ) -> Float {
+                match self {
+                    $(BxDFs::$variant(b) => b.evaluate_wavelength(incident, outgoing, index)),+
+                }
+            }
+
+            #[inline]
+            pub fn sample(
+                &self,
+                outgoing: Vector3,
+                sample: Vector2,
+            ) -> Option<BxDFSample<Spectrum>> {
+                match self {
+                    $(BxDFs::$variant(b) => b.sample(outgoing, sample)),+
+                }
+            }
+
+            #[inline]
+            pub fn sample_buf(
+                &self,
+                outgoing: Vector3,
+                sample: Vector2,
+                indices: &[usize],
+            ) -> Option<BxDFSampleResult> {
+                match self {
+                    $(BxDFs::$variant(b) => b.sample_buf(outgoing, sample, indices)),+
+                }
+            }
+
+            #[inline]
+            pub fn sample_wavelength(
+                &self,
+                outgoing: Vector3,
+                sample: Vector2,
+                light_wave_index: usize,
+            ) -> Option<BxDFSample<Float>> {
+                match self {
+                    $(BxDFs::$variant(b) => {
+                        b.sample_wavelength(outgoing, sample, light_wave_index)
+                    }),+
+                }
+            }
+
+            #[inline]
+            pub fn pdf(&self, incident: Vector3, outgoing: Vector3) -> Float {
+                match self {
+                    $(BxDFs::$variant(b) => b.pdf(incident, outgoing)),+
+                }
+            }
+
+            #[inline]
+            pub fn textured(&self, at: TexturePoint) -> Option<Box<dyn BxDF>> {
+                match self {
+                    $(BxDFs::$variant(b) => b.textured(at)),+
+                }
+            }
+        }
+
+        impl Serialize for BxDFs {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(Some(1))?;
+                match self {
+                    $(BxDFs::$variant(b) => map.serialize_entry(stringify!($variant), b)?),+
+                }
+                map.end()
+            }
+        }
+
+        impl<'de> Deserialize<'de> for BxDFs {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                const VARIANTS: &[&str] = &[$(stringify!($variant)),+];
+
+                struct BxDFsVisitor;
+
+                impl<'de> Visitor<'de> for BxDFsVisitor {
+                    type Value = BxDFs;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("a map containing a single BxDF")
+                    }
+
+                    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+                    where
+                        A: MapAccess<'de>,
+                    {
+                        let name: String = map
+                            .next_key()?
+                            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+                        match name.as_str() {
+                            $(stringify!($variant) => Ok(BxDFs::$variant(map.next_value()?)),)+
+                            _ => Err(de::Error::unknown_variant(&name, VARIANTS)),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_map(BxDFsVisitor)
+            }
+        }
+    };
+}
+
+bxdfs!(
+    DiffuseReflection,
+    LambertianReflection,
+    LambertianTransmission,
+    OrenNayar,
+    SpecularReflection,
+    SpecularTransmission,
+    FresnelSpecular,
+    MicrofacetReflection,
+    MicrofacetTransmission,
+    MicrofacetConductor,
+    MeasuredBxDF,
+    ScaledBxDF,
+    Coated,
+);
+
+#[test]
+fn dispatches_like_trait_objects() {
+    let content = r#"{"OrenNayar": (r: Constant(0.8), a: 0.9, b: 0.2)}"#;
+    let boxed: Box<dyn BxDF> = ron::from_str(content).unwrap();
+    let bxdf: BxDFs = ron::from_str(content).unwrap();
+
+    assert_eq!(boxed.get_type(), bxdf.get_type());
+    assert_eq!(
+        ron::to_string(&boxed).unwrap(),
+        ron::to_string(&bxdf).unwrap()
+    );
+
+    let incident = Vector3::new(0.2, 0.9, -0.3).normalized();
+    let outgoing = Vector3::new(-0.5, 0.6, 0.2).normalized();
+    assert_eq!(
+        boxed.evaluate(incident, outgoing),
+        bxdf.evaluate(incident, outgoing)
+    );
+
+    let unknown = r#"{"Unknown": ()}"#;
+    assert!(ron::from_str::<BxDFs>(unknown).is_err());
+}
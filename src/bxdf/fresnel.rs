@@ -7,13 +7,23 @@ use serde::{Deserialize, Serialize};
 use std::mem::swap;
 use utility::floats::FloatExt;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum FresnelType {
     /// A `Fresnel` implementation for dielectric materials.
     Dielectric(FresnelDielectric),
+    /// A `Fresnel` implementation for conductors (metals), absorbing the transmitted light.
+    Conductor(FresnelConductor),
     /// A no-operation `Fresnel` implementation that returns 100% reflection for all incoming directions.
     /// Although this is physically implausible, it is a convenient capability to have available.
     NoOp,
+    /// Schlick's approximation, describing metals and plastics by their reflectance at normal
+    /// incidence instead of physical indices of refraction.
+    Schlick { f0: Spectrum },
+    /// Artistically tints the reflectance of another `Fresnel` implementation.
+    Tinted {
+        fresnel: Box<FresnelType>,
+        tint: Spectrum,
+    },
 }
 
 impl Fresnel for FresnelType {
@@ -21,7 +31,13 @@ impl Fresnel for FresnelType {
     fn evaluate(&self, cos_i: Float) -> Spectrum {
         match self {
             FresnelType::Dielectric(t) => t.evaluate(cos_i),
+            FresnelType::Conductor(c) => c.evaluate(cos_i),
             FresnelType::NoOp => Spectrum::broadcast(1.0),
+            FresnelType::Schlick { f0 } => {
+                let weight = schlick_weight(cos_i);
+                *f0 + (Spectrum::broadcast(1.0) - *f0) * weight
+            }
+            FresnelType::Tinted { fresnel, tint } => fresnel.evaluate(cos_i) * *tint,
         }
     }
 
@@ -29,11 +45,34 @@ impl Fresnel for FresnelType {
     fn evaluate_lambda(&self, cos_i: Float, lambda: Float) -> Float {
         match self {
             FresnelType::Dielectric(f) => f.evaluate_lambda(cos_i, lambda),
+            FresnelType::Conductor(c) => c.evaluate_lambda(cos_i, lambda),
             FresnelType::NoOp => 1.0,
+            FresnelType::Schlick { f0 } => {
+                let f0 = f0.at_lambda(lambda);
+                (1.0 - f0).mul_add(schlick_weight(cos_i), f0)
+            }
+            FresnelType::Tinted { fresnel, tint } => {
+                fresnel.evaluate_lambda(cos_i, lambda) * tint.at_lambda(lambda)
+            }
         }
     }
 }
 
+/// Computes the weight `(1 - cos)^5` of Schlick's Fresnel approximation.
+///
+/// # Arguments
+/// * `cos_i` - The cosine of the angle between normal and incident
+///
+/// # Returns
+/// * The weight of the grazing reflectance
+#[inline]
+pub fn schlick_weight(cos_i: Float) -> Float {
+    let m = (1.0 - cos_i.abs()).fast_clamp(0.0, 1.0);
+    let m2 = m * m;
+
+    m2 * m2 * m
+}
+
 /// Computes the fraction of reflected light for parallel polarized light.
 ///
 /// # Arguments
@@ -103,6 +142,40 @@ pub fn fresnel_dielectric(mut cos_i: Float, mut eta_i: Float, mut eta_t: Float)
     0.5 * (r_par * r_par + r_perp * r_perp)
 }
 
+/// Computes the Fresnel reflection for conductors and unpolarized light.
+///
+/// # Arguments
+/// * `cos_i` - The cosine of the angle between normal and incident
+/// * `eta_i` - The index of refraction for the incident medium
+/// * `eta_t` - The index of refraction of the conductor
+/// * `k` - The extinction coefficient of the conductor
+///
+/// # Returns
+/// * The Fresnel reflectance
+pub fn fresnel_conductor(cos_i: Float, eta_i: Float, eta_t: Float, k: Float) -> Float {
+    let cos_i = cos_i.abs().fast_min(1.0);
+    let eta = eta_t / eta_i;
+    let eta_k = k / eta_i;
+
+    let cos2 = cos_i * cos_i;
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let eta_k2 = eta_k * eta_k;
+
+    let t0 = eta2 - eta_k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * eta_k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).fast_max(0.0).sqrt();
+    let t2 = 2.0 * cos_i * a;
+    let r_perp = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let r_par = r_perp * (t3 - t4) / (t3 + t4);
+
+    0.5 * (r_par + r_perp)
+}
+
 /// Provides an interface for computing Fresnel reflection coefficients.
 pub trait Fresnel: Send + Sync {
     /// Evaluates the amount of light reflected by the surface.
@@ -118,7 +191,7 @@ pub trait Fresnel: Send + Sync {
 }
 
 /// An implementation of `Fresnel` for dielectric materials.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct FresnelDielectric {
     pub eta_i: RefractiveType,
     pub eta_t: RefractiveType,
@@ -150,3 +223,74 @@ impl Fresnel for FresnelDielectric {
         fresnel_dielectric(cos_i, self.eta_i.n(lambda), self.eta_t.n(lambda))
     }
 }
+
+/// An implementation of `Fresnel` for conductors, using the spectral extinction coefficient of
+/// the conductor.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FresnelConductor {
+    pub eta_i: RefractiveType,
+    pub eta_t: RefractiveType,
+}
+
+impl FresnelConductor {
+    /// Creates a new conductor.
+    ///
+    /// # Arguments
+    /// * `eta_i` - The index of refraction for the incident medium
+    /// * `eta_t` - The index of refraction of the conductor, e.g. [gold](RefractiveType::Gold)
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(eta_i: RefractiveType, eta_t: RefractiveType) -> Self {
+        Self { eta_i, eta_t }
+    }
+}
+
+impl Fresnel for FresnelConductor {
+    fn evaluate(&self, cos_i: Float) -> Spectrum {
+        let mut spectrum = Spectrum::broadcast(0.0);
+        for i in 0..Spectrum::size() {
+            spectrum[i] = self.evaluate_lambda(cos_i, Spectrum::lambda_of_index(i));
+        }
+
+        spectrum
+    }
+
+    #[inline]
+    fn evaluate_lambda(&self, cos_i: Float, lambda: Float) -> Float {
+        let k = self.eta_t.k(lambda).unwrap_or(0.0);
+
+        fresnel_conductor(cos_i, self.eta_i.n(lambda), self.eta_t.n(lambda), k)
+    }
+}
+
+#[test]
+fn schlick_approximates_dielectrics() {
+    let (eta_i, eta_t): (Float, Float) = (1.0, 1.5);
+    let f0 = ((eta_t - eta_i) / (eta_t + eta_i)).powi(2);
+    let schlick = FresnelType::Schlick {
+        f0: Spectrum::broadcast(f0),
+    };
+
+    // exact at normal incidence, converging towards total reflection at grazing incidence
+    for &(cos_i, tolerance) in &[(1.0, 1e-6), (0.5, 0.025), (1e-3, 1e-2), (0.0, 0.0)] {
+        let exact = fresnel_dielectric(cos_i, eta_i, eta_t);
+        let approx = schlick.evaluate_lambda(cos_i, 0.55);
+
+        assert!(
+            (approx - exact).abs() <= tolerance,
+            "{}: {} != {}",
+            cos_i,
+            approx,
+            exact
+        );
+        assert!((schlick.evaluate(cos_i)[10] - approx).abs() < 1e-6);
+    }
+    assert_eq!(schlick.evaluate_lambda(0.0, 0.55), 1.0);
+
+    let tinted = FresnelType::Tinted {
+        fresnel: Box::new(schlick),
+        tint: Spectrum::broadcast(0.5),
+    };
+    assert!((tinted.evaluate_lambda(1.0, 0.55) - 0.5 * f0).abs() < 1e-6);
+}
@@ -1,4 +1,5 @@
 use crate::bxdf::{BxDF, Type};
+use crate::texture::{Texture, TexturePoint};
 use crate::*;
 use serde::{Deserialize, Serialize};
 #[cfg(not(feature = "f64"))]
@@ -9,7 +10,7 @@ use std::f64::consts::FRAC_1_PI;
 /// The lambertian reflection reflects equally into all directions of the hemisphere.
 #[derive(Serialize, Deserialize)]
 pub struct LambertianReflection {
-    r: Spectrum,
+    r: Texture<Spectrum>,
 }
 
 impl LambertianReflection {
@@ -17,12 +18,12 @@ impl LambertianReflection {
     /// Creates a new lambertian reflection.
     ///
     /// # Arguments
-    /// * `r` - The reflection spectrum
+    /// * `r` - The (textured) reflection spectrum
     ///
     /// # Returns
     /// * Self
-    pub fn new(r: Spectrum) -> Self {
-        Self { r }
+    pub fn new<T: Into<Texture<Spectrum>>>(r: T) -> Self {
+        Self { r: r.into() }
     }
 }
 
@@ -33,31 +34,39 @@ impl BxDF for LambertianReflection {
     }
 
     fn evaluate(&self, _: Vector3, _: Vector3) -> Spectrum {
-        self.r * FRAC_1_PI
+        self.r.evaluate(TexturePoint::default()) * FRAC_1_PI
     }
 
     #[inline]
     fn evaluate_wavelength(&self, _: Vector3, _: Vector3, light_wave_index: usize) -> Float {
-        self.r[light_wave_index] * FRAC_1_PI
+        self.r.evaluate(TexturePoint::default())[light_wave_index] * FRAC_1_PI
+    }
+
+    fn textured(&self, at: TexturePoint) -> Option<Box<dyn BxDF>> {
+        if self.r.is_constant() {
+            return None;
+        }
+
+        Some(Box::new(Self::new(self.r.evaluate(at))))
     }
 }
 
 /// The lambertian transmission transmits equally into all directions of the hemisphere.
 #[derive(Serialize, Deserialize)]
 pub struct LambertianTransmission {
-    t: Spectrum,
+    t: Texture<Spectrum>,
 }
 
 impl LambertianTransmission {
     /// Creates a new lambertian transmission.
     ///
     /// # Arguments
-    /// * `t` - The transmission spectrum
+    /// * `t` - The (textured) transmission spectrum
     ///
     /// # Returns
     /// * Self
-    pub fn new(t: Spectrum) -> Self {
-        Self { t }
+    pub fn new<T: Into<Texture<Spectrum>>>(t: T) -> Self {
+        Self { t: t.into() }
     }
 }
 
@@ -68,11 +77,19 @@ impl BxDF for LambertianTransmission {
     }
 
     fn evaluate(&self, _: Vector3, _: Vector3) -> Spectrum {
-        self.t * FRAC_1_PI
+        self.t.evaluate(TexturePoint::default()) * FRAC_1_PI
     }
 
     #[inline]
     fn evaluate_wavelength(&self, _: Vector3, _: Vector3, light_wave_index: usize) -> Float {
-        self.t[light_wave_index] * FRAC_1_PI
+        self.t.evaluate(TexturePoint::default())[light_wave_index] * FRAC_1_PI
+    }
+
+    fn textured(&self, at: TexturePoint) -> Option<Box<dyn BxDF>> {
+        if self.t.is_constant() {
+            return None;
+        }
+
+        Some(Box::new(Self::new(self.t.evaluate(at))))
     }
 }
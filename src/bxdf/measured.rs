@@ -0,0 +1,383 @@
+//! Measured isotropic BRDFs of the MERL database (Matusik et al. 2003), e.g. to validate the
+//! analytical models against real materials.
+//!
+//! A MERL file tabulates the RGB reflectance over the half-difference angles of Rusinkiewicz: the
+//! elevation of the half vector (non-linearly), and the elevation and azimuth of the difference
+//! vector (exploiting reciprocity).
+
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+#[cfg(feature = "f64")]
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::bxdf::*;
+use crate::texture::{rgb_weights, TextureValue};
+use crate::{Float, Spectrum, Vector2, Vector3};
+use color::Color;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+const RES_THETA_H: usize = 90;
+const RES_THETA_D: usize = 90;
+/// Half of the azimuth resolution, the other half being given by reciprocity.
+const RES_PHI_D: usize = 180;
+const SIZE: usize = RES_THETA_H * RES_THETA_D * RES_PHI_D;
+
+/// The scales of the red, green and blue channels of the raw data.
+const SCALES: [Float; 3] = [1.0 / 1500.0, 1.15 / 1500.0, 1.66 / 1500.0];
+
+/// Converts the given direction of the local BxDF space (`y` up) into the `z` up frame of the
+/// half-difference parameterization.
+#[inline]
+fn z_up(v: Vector3) -> Vector3 {
+    Vector3::new(v.x, -v.z, v.y)
+}
+
+/// Returns the half-difference angles `(theta_h, theta_d, phi_d)` of the given directions.
+fn half_difference(incident: Vector3, outgoing: Vector3) -> (Float, Float, Float) {
+    let (incident, outgoing) = (z_up(incident), z_up(outgoing));
+    let half = (incident + outgoing).normalized();
+
+    let theta_h = half.z.clamp(-1.0, 1.0).acos();
+    let phi_h = half.y.atan2(half.x);
+
+    // rotate the incident direction by -phi_h around the normal and by -theta_h around the
+    // binormal
+    let (sin_phi, cos_phi) = phi_h.sin_cos();
+    let (sin_theta, cos_theta) = theta_h.sin_cos();
+    let x = incident.x * cos_phi + incident.y * sin_phi;
+    let y = -incident.x * sin_phi + incident.y * cos_phi;
+    let z = incident.z;
+    let diff = Vector3::new(
+        x * cos_theta - z * sin_theta,
+        y,
+        x * sin_theta + z * cos_theta,
+    );
+
+    (
+        theta_h,
+        diff.z.clamp(-1.0, 1.0).acos(),
+        diff.y.atan2(diff.x),
+    )
+}
+
+/// Returns the index of the table entry of the given half-difference angles.
+fn table_index(theta_h: Float, theta_d: Float, phi_d: Float) -> usize {
+    let theta_h_index = if theta_h <= 0.0 {
+        0
+    } else {
+        ((theta_h / FRAC_PI_2).sqrt() * RES_THETA_H as Float) as usize
+    };
+    let theta_d_index = (theta_d / FRAC_PI_2 * RES_THETA_D as Float) as usize;
+    // by reciprocity
+    let phi_d = if phi_d < 0.0 { phi_d + PI } else { phi_d };
+    let phi_d_index = (phi_d / PI * RES_PHI_D as Float) as usize;
+
+    phi_d_index.min(RES_PHI_D - 1)
+        + theta_d_index.min(RES_THETA_D - 1) * RES_PHI_D
+        + theta_h_index.min(RES_THETA_H - 1) * RES_PHI_D * RES_THETA_D
+}
+
+/// The textual representation of a measured BRDF in scene files.
+#[derive(Clone, Serialize, Deserialize)]
+struct MeasuredSource {
+    /// The MERL file, relative to the scene file
+    file: String,
+}
+
+/// A measured isotropic BRDF of the MERL database.
+///
+/// The RGB reflectance gets upsampled to spectra by smooth bands. Incident directions get
+/// importance sampled by reflecting around half vectors, whose elevation follows a tabulated cdf
+/// of the mean reflectance.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "MeasuredSource", into = "MeasuredSource")]
+pub struct MeasuredBxDF {
+    path: String,
+    /// The scaled red, green and blue reflectance, one channel after another
+    data: Vec<Float>,
+    /// The cdf of sampling each bin of the half vector elevation
+    cdf: Vec<Float>,
+}
+
+impl MeasuredBxDF {
+    /// Loads a binary MERL BRDF.
+    ///
+    /// The format consists of the resolution `theta_h, theta_d, phi_d` as little-endian `i32`,
+    /// followed by the red, green and blue tables as little-endian `f64`.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the MERL file
+    ///
+    /// # Returns
+    /// * The measured BRDF, or an error message
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let data = Self::parse(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let cdf = Self::theta_h_cdf(&data);
+
+        Ok(Self {
+            path: path.display().to_string(),
+            data,
+            cdf,
+        })
+    }
+
+    /// Parses the scaled tables of a binary MERL BRDF.
+    fn parse(bytes: &[u8]) -> Result<Vec<Float>, String> {
+        if bytes.len() < 12 {
+            return Err("MERL file too short".to_string());
+        }
+
+        let i32_at =
+            |i: usize| i32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let size = i32_at(0) as i64 * i32_at(4) as i64 * i32_at(8) as i64;
+        if size != SIZE as i64 {
+            return Err(format!("MERL file has {} entries, expected {}", size, SIZE));
+        }
+
+        if bytes.len() != 12 + 3 * SIZE * 8 {
+            return Err("MERL file has an unexpected length".to_string());
+        }
+
+        let data = bytes[12..]
+            .chunks_exact(8)
+            .enumerate()
+            .map(|(i, b)| {
+                let value = f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+                // negative values mark missing measurements
+                (value as Float * SCALES[i / SIZE]).max(0.0)
+            })
+            .collect();
+
+        Ok(data)
+    }
+
+    /// Tabulates the cdf of the half vector elevation bins, proportional to the mean reflectance
+    /// integrated over the solid angle of each bin.
+    fn theta_h_cdf(data: &[Float]) -> Vec<Float> {
+        let bin_size = RES_THETA_D * RES_PHI_D;
+
+        let weights: Vec<Float> = (0..RES_THETA_H)
+            .map(|i| {
+                let start = i * bin_size;
+                let mean = (0..3)
+                    .map(|c| {
+                        let offset = c * SIZE + start;
+                        data[offset..offset + bin_size].iter().sum::<Float>()
+                    })
+                    .sum::<Float>()
+                    / (3 * bin_size) as Float;
+
+                let u = (i as Float + 0.5) / RES_THETA_H as Float;
+                let theta = u * u * FRAC_PI_2;
+
+                mean * theta.sin() * theta.cos() * u
+            })
+            .collect();
+
+        // every bin keeps a small probability, avoiding a vanishing pdf of measured reflectance
+        let floor = 1e-3 * weights.iter().copied().fold(0.0, Float::max);
+
+        let mut cdf = Vec::with_capacity(RES_THETA_H + 1);
+        cdf.push(0.0);
+        for w in weights {
+            let last = cdf[cdf.len() - 1];
+            cdf.push(last + w.max(floor).max(Float::MIN_POSITIVE));
+        }
+
+        let total = cdf[RES_THETA_H];
+        cdf.iter_mut().for_each(|c| *c /= total);
+
+        cdf
+    }
+
+    /// Returns the RGB reflectance of the given directions in the upper hemisphere.
+    fn rgb(&self, incident: Vector3, outgoing: Vector3) -> Option<[Float; 3]> {
+        if !same_hemisphere(incident, outgoing) {
+            return None;
+        }
+
+        // isotropic, such that mirroring both directions to the upper hemisphere is valid
+        let (incident, outgoing) = if outgoing.y < 0.0 {
+            (flip_if_neg(incident), flip_if_neg(outgoing))
+        } else {
+            (incident, outgoing)
+        };
+
+        let (theta_h, theta_d, phi_d) = half_difference(incident, outgoing);
+        let index = table_index(theta_h, theta_d, phi_d);
+
+        Some([
+            self.data[index],
+            self.data[index + SIZE],
+            self.data[index + 2 * SIZE],
+        ])
+    }
+
+    /// Returns the pdf of sampling the given half vector elevation (in solid angle).
+    fn half_pdf(&self, theta_h: Float) -> Float {
+        let u = (theta_h / FRAC_PI_2).sqrt();
+        let sin = theta_h.sin();
+        if u <= 0.0 || sin <= 0.0 {
+            return 0.0;
+        }
+
+        let bin = ((u * RES_THETA_H as Float) as usize).min(RES_THETA_H - 1);
+        let probability = self.cdf[bin + 1] - self.cdf[bin];
+
+        // uniform within the bin of u, with theta = u^2 * pi / 2 and a uniform azimuth
+        probability * RES_THETA_H as Float / (u * PI * TAU * sin)
+    }
+
+    /// Samples an incident direction by reflecting around a sampled half vector.
+    ///
+    /// # Returns
+    /// * The incident direction and its pdf
+    fn sample_incident(&self, outgoing: Vector3, sample: Vector2) -> Option<(Vector3, Float)> {
+        if bxdf_is_parallel(outgoing) {
+            return None;
+        }
+
+        let bin = match self
+            .cdf
+            .binary_search_by(|c| c.partial_cmp(&sample.x).unwrap())
+        {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        }
+        .min(RES_THETA_H - 1);
+        let probability = self.cdf[bin + 1] - self.cdf[bin];
+        let offset = ((sample.x - self.cdf[bin]) / probability).clamp(0.0, 1.0);
+
+        let u = (bin as Float + offset) / RES_THETA_H as Float;
+        let theta_h = u * u * FRAC_PI_2;
+        let phi_h = sample.y * TAU;
+
+        let (sin_theta, cos_theta) = theta_h.sin_cos();
+        let (sin_phi, cos_phi) = phi_h.sin_cos();
+        let mut half = Vector3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+        if outgoing.y < 0.0 {
+            half.y = -half.y;
+        }
+
+        let cos_o = outgoing.dot(half);
+        if cos_o <= 0.0 {
+            return None;
+        }
+
+        let incident = 2.0 * cos_o * half - outgoing;
+        if !same_hemisphere(incident, outgoing) {
+            return None;
+        }
+
+        let pdf = self.half_pdf(theta_h) / (4.0 * cos_o);
+
+        Some((incident, pdf))
+    }
+}
+
+impl TryFrom<MeasuredSource> for MeasuredBxDF {
+    type Error = String;
+
+    fn try_from(source: MeasuredSource) -> Result<Self, Self::Error> {
+        let mut bxdf = Self::load(geometry::resolve_asset(&source.file))?;
+        bxdf.path = source.file;
+
+        Ok(bxdf)
+    }
+}
+
+impl From<MeasuredBxDF> for MeasuredSource {
+    fn from(bxdf: MeasuredBxDF) -> Self {
+        Self { file: bxdf.path }
+    }
+}
+
+#[typetag::serde]
+impl BxDF for MeasuredBxDF {
+    fn get_type(&self) -> Type {
+        Type::REFLECTION | Type::GLOSSY
+    }
+
+    fn evaluate(&self, incident: Vector3, outgoing: Vector3) -> Spectrum {
+        let rgb = match self.rgb(incident, outgoing) {
+            Some(rgb) => rgb,
+            None => return Spectrum::broadcast(0.0),
+        };
+
+        Spectrum::from_rgb(rgb)
+    }
+
+    fn evaluate_wavelength(&self, incident: Vector3, outgoing: Vector3, index: usize) -> Float {
+        match self.rgb(incident, outgoing) {
+            Some(rgb) => {
+                let [r, g, b] = rgb_weights(Spectrum::lambda_of_index(index));
+                r * rgb[0] + g * rgb[1] + b * rgb[2]
+            }
+            None => 0.0,
+        }
+    }
+
+    fn sample(&self, outgoing: Vector3, sample: Vector2) -> Option<BxDFSample<Spectrum>> {
+        let (incident, pdf) = self.sample_incident(outgoing, sample)?;
+        let spectrum = self.evaluate(incident, outgoing);
+
+        Some(BxDFSample::new(spectrum, incident, pdf, self.get_type()))
+    }
+
+    fn sample_buf(
+        &self,
+        outgoing: Vector3,
+        sample: Vector2,
+        indices: &[usize],
+    ) -> Option<BxDFSampleResult> {
+        let (incident, pdf) = self.sample_incident(outgoing, sample)?;
+        let spectrum = self.evaluate_buf(incident, outgoing, indices);
+
+        Some(BxDFSampleResult::Bundle(BxDFSample::new(
+            spectrum,
+            incident,
+            pdf,
+            self.get_type(),
+        )))
+    }
+
+    fn sample_wavelength(
+        &self,
+        outgoing: Vector3,
+        sample: Vector2,
+        index: usize,
+    ) -> Option<BxDFSample<Float>> {
+        let (incident, pdf) = self.sample_incident(outgoing, sample)?;
+        let intensity = self.evaluate_wavelength(incident, outgoing, index);
+
+        Some(BxDFSample::new(intensity, incident, pdf, self.get_type()))
+    }
+
+    fn pdf(&self, incident: Vector3, outgoing: Vector3) -> Float {
+        if !same_hemisphere(incident, outgoing) {
+            return 0.0;
+        }
+
+        let half = (incident + outgoing).normalized();
+        let theta_h = half.y.abs().clamp(-1.0, 1.0).acos();
+
+        self.half_pdf(theta_h) / (4.0 * outgoing.dot(half).abs())
+    }
+}
+
+#[test]
+fn half_difference_of_mirror_directions() {
+    let incident = Vector3::new(1.0, 1.0, 0.0).normalized();
+    let outgoing = Vector3::new(-1.0, 1.0, 0.0).normalized();
+
+    let (theta_h, theta_d, _) = half_difference(incident, outgoing);
+
+    assert!(theta_h.abs() < 1e-3);
+    assert!((theta_d - PI / 4.0).abs() < 1e-3);
+}
@@ -8,9 +8,10 @@ use crate::{Float, Spectrum, Vector2, Vector3};
 use color::Color;
 use utility::{floats, math};
 
-use crate::bxdf::fresnel::Fresnel;
+use crate::bxdf::fresnel::{fresnel_dielectric, Fresnel, FresnelConductor};
 use crate::bxdf::*;
-use geometry::spherical_to_cartesian1;
+use crate::refractive_index::RefractiveType;
+use crate::texture::{Texture, TexturePoint};
 use serde::{Deserialize, Serialize};
 
 #[allow(dead_code)]
@@ -47,6 +48,18 @@ pub trait MicrofacetDistribution: Send + Sync {
     }
 
     fn is_sample_visible_area(&self) -> bool;
+
+    /// Evaluates the roughness textures of this distribution at a surface point, like
+    /// [BxDF::textured].
+    ///
+    /// # Arguments
+    /// * `at` - The surface point
+    ///
+    /// # Results
+    /// * The distribution with constant roughness, or `None` if it is not textured
+    fn textured(&self, _at: TexturePoint) -> Option<Box<dyn MicrofacetDistribution>> {
+        None
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -182,12 +195,11 @@ impl MicrofacetDistribution for BeckmannDistribution {
             let cos2_theta = cos2_theta(wh);
             let cos4_theta = cos2_theta * cos2_theta;
 
-            let alpha_x2 = self.alpha_x * self.alpha_x;
-
-            let cos2 = cos2_phi(wh) / alpha_x2;
-            let sin2 = sin2_phi(wh) / self.alpha_y * self.alpha_y;
+            let cos2 = cos2_phi(wh) / (self.alpha_x * self.alpha_x);
+            let sin2 = sin2_phi(wh) / (self.alpha_y * self.alpha_y);
 
-            Float::exp(-tan2_theta * (cos2 + sin2)) / (PI * alpha_x2 * cos4_theta)
+            Float::exp(-tan2_theta * (cos2 + sin2))
+                / (PI * self.alpha_x * self.alpha_y * cos4_theta)
         }
     }
 
@@ -203,7 +215,7 @@ impl MicrofacetDistribution for BeckmannDistribution {
 
         let alpha = Float::sqrt(cos2 + sin2);
         let a = 1.0 / (alpha * tan_theta.abs());
-        if a >= Float::big_epsilon() {
+        if a >= 1.6 {
             0.0
         } else {
             (1.0 - 1.259 * a + 0.396 * a * a) / (3.535 * a + 2.181 * a * a)
@@ -232,13 +244,13 @@ impl MicrofacetDistribution for BeckmannDistribution {
 
         let tan2_theta: Float;
         let phi: Float;
-        if sample.x.is_approx_eq(sample.y) {
+        if self.alpha_x == self.alpha_y {
             tan2_theta = -alpha_x2 * log_sample;
             phi = TAU * sample.y;
         } else {
             // Compute _tan2Theta_ and _phi_ for anisotropic Beckmann
             // distribution
-            let tan = Float::tan(TAU * sample.y * FRAC_PI_2);
+            let tan = Float::tan(TAU * sample.y + FRAC_PI_2);
             let mut phi_new = Float::atan(self.alpha_y * tan / self.alpha_x);
             if sample.y > 0.5 {
                 phi_new += PI;
@@ -253,10 +265,11 @@ impl MicrofacetDistribution for BeckmannDistribution {
         }
 
         // Map sampled Beckmann angles to normal direction _wh_
-        let cos_theta = 1.0 / Float::sqrt(1.0 - tan2_theta);
+        let cos_theta = 1.0 / Float::sqrt(1.0 + tan2_theta);
         let sin_theta = Float::fast_max(0.0, 1.0 - cos_theta * cos_theta).sqrt();
+        let (sin_phi, cos_phi) = phi.sin_cos();
 
-        let mut wh = spherical_to_cartesian1(sin_theta, cos_theta, phi);
+        let mut wh = Vector3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
         if !same_hemisphere(wo, wh) {
             wh = -wh;
         }
@@ -269,24 +282,91 @@ impl MicrofacetDistribution for BeckmannDistribution {
     }
 }
 
-/*pub struct TrowbridgeReitzDistribution {
+/// The Trowbridge-Reitz (GGX) distribution, whose longer tails than the Beckmann distribution
+/// match measured glossy surfaces better.
+#[derive(Deserialize, Serialize)]
+pub struct TrowbridgeReitzDistribution {
     alpha_x: Float,
     alpha_y: Float,
-    sample_visible_area: bool
+    sample_visible_area: bool,
+    /// An isotropic roughness varying over the surface, replacing `alpha_x` and `alpha_y`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alpha: Option<Texture<Float>>,
 }
 
 impl TrowbridgeReitzDistribution {
+    /// Creates a new Trowbridge-Reitz distribution.
+    ///
+    /// # Constraints
+    /// * `alpha_x` - Should be greater than `0`.
+    /// * `alpha_y` - Should be greater than `0`.
+    ///
+    /// # Arguments
+    /// * `alpha_x` - The roughness along the x-axis
+    /// * `alpha_y` - The roughness along the z-axis
+    /// * `sample_visible_area` - Whether to only sample normals visible from the outgoing direction
+    ///
+    /// # Returns
+    /// * Self
     pub fn new(alpha_x: Float, alpha_y: Float, sample_visible_area: bool) -> Self {
-        Self { alpha_x, alpha_y, sample_visible_area }
+        debug_assert!(alpha_x > 0.0);
+        debug_assert!(alpha_y > 0.0);
+
+        Self {
+            alpha_x,
+            alpha_y,
+            sample_visible_area,
+            alpha: None,
+        }
+    }
+
+    /// Samples a normal visible from the given direction in the upper hemisphere (Heitz 2018).
+    fn sample_visible(&self, wo: Vector3, sample: Vector2) -> Vector3 {
+        // stretch the view direction onto the hemisphere configuration
+        let vh = Vector3::new(self.alpha_x * wo.x, wo.y, self.alpha_y * wo.z).normalized();
+
+        // orthonormal basis around it
+        let len_sq = vh.x * vh.x + vh.z * vh.z;
+        let t1 = if len_sq > 0.0 {
+            Vector3::new(-vh.z, 0.0, vh.x) / len_sq.sqrt()
+        } else {
+            Vector3::unit_x()
+        };
+        let t2 = t1.cross(vh);
+
+        // sample the projected area of the visible half of the disk
+        let r = sample.x.sqrt();
+        let (sin_phi, cos_phi) = (TAU * sample.y).sin_cos();
+        let p1 = r * cos_phi;
+        let s = 0.5 * (1.0 + vh.y);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).fast_max(0.0).sqrt() + s * r * sin_phi;
+
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).fast_max(0.0).sqrt() * vh;
+
+        // unstretch
+        Vector3::new(self.alpha_x * nh.x, nh.y.fast_max(0.0), self.alpha_y * nh.z).normalized()
     }
 }
 
+#[typetag::serde]
 impl MicrofacetDistribution for TrowbridgeReitzDistribution {
-    fn d(&self, wh: &Vector3) -> Float {
-        unimplemented!()
+    fn d(&self, wh: Vector3) -> Float {
+        let tan2_theta = tan2_theta(wh);
+        if tan2_theta.is_infinite() {
+            return 0.0;
+        }
+
+        let cos2_theta = cos2_theta(wh);
+        let cos4_theta = cos2_theta * cos2_theta;
+
+        let e = (cos2_phi(wh) / (self.alpha_x * self.alpha_x)
+            + sin2_phi(wh) / (self.alpha_y * self.alpha_y))
+            * tan2_theta;
+
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos4_theta * (1.0 + e) * (1.0 + e))
     }
 
-    fn lambda(&self, w: &Vector3) -> Float {
+    fn lambda(&self, w: Vector3) -> Float {
         let tan_theta = tan_theta(w);
         if tan_theta.is_infinite() {
             return 0.0;
@@ -296,20 +376,130 @@ impl MicrofacetDistribution for TrowbridgeReitzDistribution {
         let cos2 = cos2_phi(w) * self.alpha_x * self.alpha_x;
         let sin2 = sin2_phi(w) * self.alpha_y * self.alpha_y;
 
-        let alpha = Float::sqrt(cos2 + sin2);
-        let alpha2_tan2 = tan2 * alpha * alpha;
+        let alpha2_tan2 = tan2 * (cos2 + sin2);
 
         (-1.0 + Float::sqrt(1.0 + alpha2_tan2)) / 2.0
     }
 
-    fn sample_wh(&self, wo: &Vector3, sample: &Vector2) -> Vector3 {
-        unimplemented!()
+    fn sample_wh(&self, wo: Vector3, sample: Vector2) -> Vector3 {
+        if self.sample_visible_area {
+            let is_neg = is_neg(wo);
+            let wh = self.sample_visible(flip_if_neg(wo), sample);
+
+            return if is_neg { -wh } else { wh };
+        }
+
+        let (phi, alpha2) = if self.alpha_x == self.alpha_y {
+            (TAU * sample.y, self.alpha_x * self.alpha_x)
+        } else {
+            let mut phi =
+                Float::atan(self.alpha_y / self.alpha_x * Float::tan(TAU * sample.y + FRAC_PI_2));
+            if sample.y > 0.5 {
+                phi += PI;
+            }
+
+            let (sin_phi, cos_phi) = phi.sin_cos();
+            let alpha2 = 1.0
+                / (cos_phi * cos_phi / (self.alpha_x * self.alpha_x)
+                    + sin_phi * sin_phi / (self.alpha_y * self.alpha_y));
+
+            (phi, alpha2)
+        };
+
+        let tan2_theta = alpha2 * sample.x / (1.0 - sample.x);
+        let cos_theta = 1.0 / Float::sqrt(1.0 + tan2_theta);
+        let sin_theta = Float::fast_max(0.0, 1.0 - cos_theta * cos_theta).sqrt();
+        let (sin_phi, cos_phi) = phi.sin_cos();
+
+        let wh = Vector3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+        if same_hemisphere(wo, wh) {
+            wh
+        } else {
+            -wh
+        }
+    }
+
+    fn is_sample_visible_area(&self) -> bool {
+        self.sample_visible_area
+    }
+
+    fn textured(&self, at: TexturePoint) -> Option<Box<dyn MicrofacetDistribution>> {
+        let alpha = Float::big_epsilon().fast_max(self.alpha.as_ref()?.evaluate(at));
+
+        Some(Box::new(Self::new(alpha, alpha, self.sample_visible_area)))
     }
+}
 
-    fn pdf(&self, wo: &Vector3, wh: &Vector3) -> Float {
-        unimplemented!()
+/// Samples a microfacet orientation and reflects the outgoing direction about it, if possible.
+///
+/// # Returns
+/// `(incident, wh, cos_o)`
+fn sample_reflected(
+    distribution: &dyn MicrofacetDistribution,
+    outgoing: Vector3,
+    sample: Vector2,
+) -> Option<(Vector3, Vector3, Float)> {
+    // Sample microfacet orientation $\wh$ and reflected direction $\wi$
+    if bxdf_is_parallel(outgoing) {
+        return None;
     }
-}*/
+
+    let wh = distribution.sample_wh(outgoing, sample);
+    let cos_o = outgoing.dot(wh);
+    // Should be rare
+    if cos_o < 0.0 {
+        return None;
+    }
+
+    let incident = outgoing.reflected(wh);
+    if !same_hemisphere(incident, outgoing) {
+        return None;
+    }
+
+    Some((incident, wh, cos_o))
+}
+
+/// Computes the half vector and the Torrance-Sparrow factor `D * G / (4 cos_i cos_o)`, excluding
+/// the Fresnel term.
+///
+/// # Returns
+/// `(wh, factor)` if the directions are not parallel to the surface
+fn reflection_factor(
+    distribution: &dyn MicrofacetDistribution,
+    incident: Vector3,
+    outgoing: Vector3,
+) -> Option<(Vector3, Float)> {
+    let cos_theta_i = cos_theta(incident).abs();
+    let cos_theta_o = cos_theta(outgoing).abs();
+    if cos_theta_i == 0.0 || cos_theta_o == 0.0 {
+        return None;
+    }
+
+    let wh = incident + outgoing;
+    if wh == Vector3::zero() {
+        return None;
+    }
+
+    let wh = wh.normalized();
+    let mul = distribution.d(wh) * distribution.g(incident, outgoing);
+
+    Some((wh, mul / (4.0 * cos_theta_i * cos_theta_o)))
+}
+
+/// Returns the pdf of [sampling](sample_reflected) the given incident direction.
+fn reflection_pdf(
+    distribution: &dyn MicrofacetDistribution,
+    incident: Vector3,
+    outgoing: Vector3,
+) -> Float {
+    if !same_hemisphere(incident, outgoing) {
+        0.0
+    } else {
+        let wh = (incident + outgoing).normalized();
+
+        distribution.pdf(outgoing, wh) / (4.0 * outgoing.dot(wh))
+    }
+}
 
 #[derive(Deserialize, Serialize)]
 pub struct MicrofacetReflection {
@@ -330,35 +520,6 @@ impl MicrofacetReflection {
             fresnel,
         }
     }
-
-    /// Samples for a incident and wh if possible.
-    ///
-    /// # Returns
-    /// `(incident, wh)`
-    fn sample_incident_wh_cos_o(
-        &self,
-        outgoing: Vector3,
-        sample: Vector2,
-    ) -> Option<(Vector3, Vector3, Float)> {
-        // Sample microfacet orientation $\wh$ and reflected direction $\wi$
-        if bxdf_is_parallel(outgoing) {
-            return None;
-        }
-
-        let wh = self.distribution.sample_wh(outgoing, sample);
-        let cos_o = outgoing.dot(wh);
-        // Should be rare
-        if cos_o < 0.0 {
-            return None;
-        }
-
-        let incident = outgoing.reflected(wh);
-        if !same_hemisphere(incident, outgoing) {
-            return None;
-        }
-
-        Some((incident, wh, cos_o))
-    }
 }
 
 #[typetag::serde]
@@ -368,41 +529,157 @@ impl BxDF for MicrofacetReflection {
     }
 
     fn evaluate(&self, incident: Vector3, outgoing: Vector3) -> Spectrum {
-        let cos_theta_i = cos_theta(incident).abs();
-        let cos_theta_o = cos_theta(outgoing).abs();
-        if cos_theta_i == 0.0 || cos_theta_o == 0.0 {
-            return Spectrum::broadcast(0.0);
+        match reflection_factor(self.distribution.as_ref(), incident, outgoing) {
+            Some((wh, mul)) => self.r * self.fresnel.evaluate(incident.dot(wh)) * mul,
+            None => Spectrum::broadcast(0.0),
         }
+    }
 
-        let wh = incident + outgoing;
-        if wh == Vector3::zero() {
-            return Spectrum::broadcast(0.0);
+    fn evaluate_buf(&self, incident: Vector3, outgoing: Vector3, indices: &[usize]) -> Vec<Float> {
+        let (wh, mul) = match reflection_factor(self.distribution.as_ref(), incident, outgoing) {
+            Some(factor) => factor,
+            None => return vec![0.0; indices.len()],
+        };
+        let cos_i = incident.dot(wh);
+
+        indices
+            .iter()
+            .map(|&i| (Spectrum::lambda_of_index(i), i))
+            .map(|(lambda, i)| self.fresnel.evaluate_lambda(cos_i, lambda) * self.r[i] * mul)
+            .collect()
+    }
+
+    fn evaluate_wavelength(
+        &self,
+        incident: Vector3,
+        outgoing: Vector3,
+        light_wave_index: usize,
+    ) -> Float {
+        match reflection_factor(self.distribution.as_ref(), incident, outgoing) {
+            Some((wh, mul)) => {
+                let lambda = Spectrum::lambda_of_index(light_wave_index);
+                let f = self.fresnel.evaluate_lambda(incident.dot(wh), lambda);
+
+                self.r[light_wave_index] * f * mul
+            }
+            None => 0.0,
         }
+    }
 
-        let wh = wh.normalized();
+    fn sample(&self, outgoing: Vector3, sample: Vector2) -> Option<BxDFSample<Spectrum>> {
+        let (incident, wh, cos_o) = sample_reflected(self.distribution.as_ref(), outgoing, sample)?;
 
-        let f = self.fresnel.evaluate(incident.dot(wh));
-        let mul = self.distribution.d(wh) * self.distribution.g(incident, outgoing);
+        let spectrum = self.evaluate(incident, outgoing);
+        let pdf = self.distribution.pdf(outgoing, wh) / (4.0 * cos_o);
 
-        self.r * f * (mul / (4.0 * cos_theta_i * cos_theta_o))
+        Some(BxDFSample::new(spectrum, incident, pdf, self.get_type()))
     }
 
-    fn evaluate_buf(&self, incident: Vector3, outgoing: Vector3, indices: &[usize]) -> Vec<Float> {
-        let cos_theta_i = cos_theta(incident).abs();
-        let cos_theta_o = cos_theta(outgoing).abs();
-        if cos_theta_i == 0.0 || cos_theta_o == 0.0 {
-            return vec![0.0; indices.len()];
+    fn sample_buf(
+        &self,
+        outgoing: Vector3,
+        sample: Vector2,
+        indices: &[usize],
+    ) -> Option<BxDFSampleResult> {
+        let (incident, wh, cos_o) = sample_reflected(self.distribution.as_ref(), outgoing, sample)?;
+
+        let spectrum = self.evaluate_buf(incident, outgoing, indices);
+        let pdf = self.distribution.pdf(outgoing, wh) / (4.0 * cos_o);
+
+        Some(BxDFSampleResult::Bundle(BxDFSample::new(
+            spectrum,
+            incident,
+            pdf,
+            self.get_type(),
+        )))
+    }
+
+    fn sample_wavelength(
+        &self,
+        outgoing: Vector3,
+        sample: Vector2,
+        index: usize,
+    ) -> Option<BxDFSample<Float>> {
+        let (incident, wh, cos_o) = sample_reflected(self.distribution.as_ref(), outgoing, sample)?;
+
+        let spectrum = self.evaluate_wavelength(incident, outgoing, index);
+        let pdf = self.distribution.pdf(outgoing, wh) / (4.0 * cos_o);
+
+        Some(BxDFSample::new(spectrum, incident, pdf, self.get_type()))
+    }
+
+    fn pdf(&self, incident: Vector3, outgoing: Vector3) -> Float {
+        reflection_pdf(self.distribution.as_ref(), incident, outgoing)
+    }
+
+    fn textured(&self, at: TexturePoint) -> Option<Box<dyn BxDF>> {
+        Some(Box::new(Self {
+            r: self.r,
+            distribution: self.distribution.textured(at)?,
+            fresnel: self.fresnel.clone(),
+        }))
+    }
+}
+
+/// A glossy reflection off a rough metal like brushed gold, using the measured spectral indices
+/// of refraction of the metal.
+#[derive(Deserialize, Serialize)]
+pub struct MicrofacetConductor {
+    /// A tint on top of the reflectance given by the metal
+    #[serde(default = "white")]
+    r: Spectrum,
+    distribution: Box<dyn MicrofacetDistribution>,
+    fresnel: FresnelConductor,
+}
+
+fn white() -> Spectrum {
+    Spectrum::broadcast(1.0)
+}
+
+impl MicrofacetConductor {
+    /// Creates a new microfacet conductor.
+    ///
+    /// # Arguments
+    /// * `r` - A tint on top of the reflectance given by the metal
+    /// * `distribution` - The microfacet distribution
+    /// * `eta_i` - The index of refraction for the incident medium
+    /// * `eta_t` - The index of refraction of the metal
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(
+        r: Spectrum,
+        distribution: Box<dyn MicrofacetDistribution>,
+        eta_i: RefractiveType,
+        eta_t: RefractiveType,
+    ) -> Self {
+        Self {
+            r,
+            distribution,
+            fresnel: FresnelConductor::new(eta_i, eta_t),
         }
+    }
+}
 
-        let wh = incident + outgoing;
-        if wh == Vector3::zero() {
-            return vec![0.0; indices.len()];
+#[typetag::serde]
+impl BxDF for MicrofacetConductor {
+    fn get_type(&self) -> Type {
+        Type::REFLECTION | Type::GLOSSY
+    }
+
+    fn evaluate(&self, incident: Vector3, outgoing: Vector3) -> Spectrum {
+        match reflection_factor(self.distribution.as_ref(), incident, outgoing) {
+            Some((wh, mul)) => self.r * self.fresnel.evaluate(incident.dot(wh)) * mul,
+            None => Spectrum::broadcast(0.0),
         }
+    }
 
-        let wh = wh.normalized();
+    fn evaluate_buf(&self, incident: Vector3, outgoing: Vector3, indices: &[usize]) -> Vec<Float> {
+        let (wh, mul) = match reflection_factor(self.distribution.as_ref(), incident, outgoing) {
+            Some(factor) => factor,
+            None => return vec![0.0; indices.len()],
+        };
         let cos_i = incident.dot(wh);
-        let mul = self.distribution.d(wh) * self.distribution.g(incident, outgoing)
-            / (4.0 * cos_theta_i * cos_theta_o);
 
         indices
             .iter()
@@ -417,29 +694,19 @@ impl BxDF for MicrofacetReflection {
         outgoing: Vector3,
         light_wave_index: usize,
     ) -> Float {
-        let cos_theta_i = cos_theta(incident).abs();
-        let cos_theta_o = cos_theta(outgoing).abs();
-        if cos_theta_i == 0.0 || cos_theta_o == 0.0 {
-            return 0.0;
-        }
+        match reflection_factor(self.distribution.as_ref(), incident, outgoing) {
+            Some((wh, mul)) => {
+                let lambda = Spectrum::lambda_of_index(light_wave_index);
+                let f = self.fresnel.evaluate_lambda(incident.dot(wh), lambda);
 
-        let wh = incident + outgoing;
-        if wh == Vector3::zero() {
-            return 0.0;
+                self.r[light_wave_index] * f * mul
+            }
+            None => 0.0,
         }
-
-        let wh = wh.normalized();
-        let cos_i = incident.dot(wh);
-        let lambda = Spectrum::lambda_of_index(light_wave_index);
-
-        let f = self.fresnel.evaluate_lambda(cos_i, lambda);
-        let mul = self.distribution.d(wh) * self.distribution.g(incident, outgoing);
-
-        self.r[light_wave_index] * f * (mul / (4.0 * cos_theta_i * cos_theta_o))
     }
 
     fn sample(&self, outgoing: Vector3, sample: Vector2) -> Option<BxDFSample<Spectrum>> {
-        let (incident, wh, cos_o) = self.sample_incident_wh_cos_o(outgoing, sample)?;
+        let (incident, wh, cos_o) = sample_reflected(self.distribution.as_ref(), outgoing, sample)?;
 
         let spectrum = self.evaluate(incident, outgoing);
         let pdf = self.distribution.pdf(outgoing, wh) / (4.0 * cos_o);
@@ -453,7 +720,7 @@ impl BxDF for MicrofacetReflection {
         sample: Vector2,
         indices: &[usize],
     ) -> Option<BxDFSampleResult> {
-        let (incident, wh, cos_o) = self.sample_incident_wh_cos_o(outgoing, sample)?;
+        let (incident, wh, cos_o) = sample_reflected(self.distribution.as_ref(), outgoing, sample)?;
 
         let spectrum = self.evaluate_buf(incident, outgoing, indices);
         let pdf = self.distribution.pdf(outgoing, wh) / (4.0 * cos_o);
@@ -472,7 +739,7 @@ impl BxDF for MicrofacetReflection {
         sample: Vector2,
         index: usize,
     ) -> Option<BxDFSample<Float>> {
-        let (incident, wh, cos_o) = self.sample_incident_wh_cos_o(outgoing, sample)?;
+        let (incident, wh, cos_o) = sample_reflected(self.distribution.as_ref(), outgoing, sample)?;
 
         let spectrum = self.evaluate_wavelength(incident, outgoing, index);
         let pdf = self.distribution.pdf(outgoing, wh) / (4.0 * cos_o);
@@ -481,130 +748,341 @@ impl BxDF for MicrofacetReflection {
     }
 
     fn pdf(&self, incident: Vector3, outgoing: Vector3) -> Float {
-        if !same_hemisphere(incident, outgoing) {
-            0.0
+        reflection_pdf(self.distribution.as_ref(), incident, outgoing)
+    }
+
+    fn textured(&self, at: TexturePoint) -> Option<Box<dyn BxDF>> {
+        Some(Box::new(Self {
+            r: self.r,
+            distribution: self.distribution.textured(at)?,
+            fresnel: self.fresnel.clone(),
+        }))
+    }
+}
+
+/// A glossy transmission through a rough dielectric surface like frosted glass, modeled by a
+/// distribution of tiny refracting facets.
+#[derive(Deserialize, Serialize)]
+pub struct MicrofacetTransmission {
+    t: Spectrum,
+    distribution: Box<dyn MicrofacetDistribution>,
+    fresnel: FresnelDielectric,
+}
+
+impl MicrofacetTransmission {
+    /// Creates a new microfacet transmission.
+    ///
+    /// # Arguments
+    /// * `t` - The transmission
+    /// * `distribution` - The distribution of the microfacet normals
+    /// * `eta_i` - The index of refraction above the surface
+    /// * `eta_t` - The index of refraction below the surface
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(
+        t: Spectrum,
+        distribution: Box<dyn MicrofacetDistribution>,
+        eta_i: RefractiveType,
+        eta_t: RefractiveType,
+    ) -> Self {
+        let fresnel = FresnelDielectric::new(eta_i, eta_t);
+
+        Self {
+            t,
+            distribution,
+            fresnel,
+        }
+    }
+
+    /// Returns the indices of refraction above and below the surface at the given wavelength, or
+    /// their uniform value without one.
+    fn etas(&self, lambda: Option<Float>) -> (Float, Float) {
+        match lambda {
+            Some(lambda) => (self.fresnel.eta_i.n(lambda), self.fresnel.eta_t.n(lambda)),
+            None => (
+                self.fresnel.eta_i.n_uniform(),
+                self.fresnel.eta_t.n_uniform(),
+            ),
+        }
+    }
+
+    /// Returns the ratio of the index of refraction on the incident side to the outgoing side.
+    fn eta(outgoing: Vector3, (eta_a, eta_b): (Float, Float)) -> Float {
+        if cos_theta(outgoing) > 0.0 {
+            eta_b / eta_a
         } else {
-            let wh = (incident + outgoing).normalized();
+            eta_a / eta_b
+        }
+    }
 
-            self.distribution.pdf(outgoing, wh) / (4.0 * outgoing.dot(wh))
+    /// Returns the generalized half vector of a refraction, facing upwards.
+    fn half_vector(incident: Vector3, outgoing: Vector3, eta: Float) -> Option<Vector3> {
+        let wh = outgoing + incident * eta;
+        if wh == Vector3::zero() {
+            return None;
+        }
+
+        let wh = wh.normalized();
+        if cos_theta(wh) < 0.0 {
+            Some(-wh)
+        } else {
+            Some(wh)
         }
     }
+
+    /// Evaluates the transmission without the spectral scaling `t`.
+    fn transmittance(&self, incident: Vector3, outgoing: Vector3, etas: (Float, Float)) -> Float {
+        if same_hemisphere(incident, outgoing) {
+            return 0.0;
+        }
+
+        let cos_theta_i = cos_theta(incident);
+        let cos_theta_o = cos_theta(outgoing);
+        if cos_theta_i == 0.0 || cos_theta_o == 0.0 {
+            return 0.0;
+        }
+
+        let eta = Self::eta(outgoing, etas);
+        let wh = match Self::half_vector(incident, outgoing, eta) {
+            Some(wh) => wh,
+            None => return 0.0,
+        };
+
+        // both directions must lie on opposite sides of the microfacet
+        let cos_o = outgoing.dot(wh);
+        let cos_i = incident.dot(wh);
+        if cos_o * cos_i > 0.0 {
+            return 0.0;
+        }
+
+        let f = fresnel_dielectric(cos_o, etas.0, etas.1);
+
+        let sqrt_denom = cos_o + eta * cos_i;
+        let dist = self.distribution.d(wh) * self.distribution.g(incident, outgoing);
+        let factor = eta * eta * cos_i.abs() * cos_o.abs()
+            / (cos_theta_i * cos_theta_o * sqrt_denom * sqrt_denom);
+
+        (1.0 - f) * (dist * factor).abs()
+    }
+
+    fn pdf_etas(&self, incident: Vector3, outgoing: Vector3, etas: (Float, Float)) -> Float {
+        if same_hemisphere(incident, outgoing) {
+            return 0.0;
+        }
+
+        let eta = Self::eta(outgoing, etas);
+        let wh = match Self::half_vector(incident, outgoing, eta) {
+            Some(wh) => wh,
+            None => return 0.0,
+        };
+
+        let cos_o = outgoing.dot(wh);
+        let cos_i = incident.dot(wh);
+        if cos_o * cos_i > 0.0 {
+            return 0.0;
+        }
+
+        // change of variables from the half vector to the incident direction
+        let sqrt_denom = cos_o + eta * cos_i;
+        let dwh_dwi = (eta * eta * cos_i / (sqrt_denom * sqrt_denom)).abs();
+
+        self.distribution.pdf(outgoing, wh) * dwh_dwi
+    }
+
+    /// Samples a microfacet normal facing the outgoing direction.
+    fn sample_wh(&self, outgoing: Vector3, sample: Vector2) -> Option<Vector3> {
+        if bxdf_is_parallel(outgoing) {
+            return None;
+        }
+
+        let wh = self.distribution.sample_wh(outgoing, sample);
+        // Should be rare
+        if outgoing.dot(wh) < 0.0 {
+            return None;
+        }
+
+        Some(wh)
+    }
+
+    /// Refracts the outgoing direction through the given microfacet normal.
+    fn incident(outgoing: Vector3, wh: Vector3, etas: (Float, Float)) -> Option<Vector3> {
+        let eta = 1.0 / Self::eta(outgoing, etas);
+
+        refract(outgoing, wh, eta).map(|v| v.normalized())
+    }
+}
+
+#[typetag::serde]
+impl BxDF for MicrofacetTransmission {
+    fn get_type(&self) -> Type {
+        Type::TRANSMISSION | Type::GLOSSY
+    }
+
+    fn evaluate(&self, incident: Vector3, outgoing: Vector3) -> Spectrum {
+        self.t * self.transmittance(incident, outgoing, self.etas(None))
+    }
+
+    fn evaluate_wavelength(&self, incident: Vector3, outgoing: Vector3, index: usize) -> Float {
+        let etas = self.etas(Some(Spectrum::lambda_of_index(index)));
+
+        self.t[index] * self.transmittance(incident, outgoing, etas)
+    }
+
+    fn sample(&self, outgoing: Vector3, sample: Vector2) -> Option<BxDFSample<Spectrum>> {
+        let wh = self.sample_wh(outgoing, sample)?;
+        let etas = self.etas(None);
+        let incident = Self::incident(outgoing, wh, etas)?;
+
+        let spectrum = self.t * self.transmittance(incident, outgoing, etas);
+        let pdf = self.pdf_etas(incident, outgoing, etas);
+
+        Some(BxDFSample::new(spectrum, incident, pdf, self.get_type()))
+    }
+
+    fn sample_buf(
+        &self,
+        outgoing: Vector3,
+        sample: Vector2,
+        indices: &[usize],
+    ) -> Option<BxDFSampleResult> {
+        let wh = self.sample_wh(outgoing, sample)?;
+        let typ = self.get_type();
+
+        // each wavelength refracts differently through the same microfacet
+        let bundle = indices
+            .iter()
+            .filter_map(|&index| {
+                let etas = self.etas(Some(Spectrum::lambda_of_index(index)));
+                let incident = Self::incident(outgoing, wh, etas)?;
+
+                let intensity = self.t[index] * self.transmittance(incident, outgoing, etas);
+                let pdf = self.pdf_etas(incident, outgoing, etas);
+
+                Some(BxDFSampleIndex::new(intensity, incident, pdf, typ, index))
+            })
+            .collect();
+
+        Some(BxDFSampleResult::ScatteredBundle(bundle))
+    }
+
+    fn sample_wavelength(
+        &self,
+        outgoing: Vector3,
+        sample: Vector2,
+        index: usize,
+    ) -> Option<BxDFSample<Float>> {
+        let wh = self.sample_wh(outgoing, sample)?;
+        let etas = self.etas(Some(Spectrum::lambda_of_index(index)));
+        let incident = Self::incident(outgoing, wh, etas)?;
+
+        let spectrum = self.t[index] * self.transmittance(incident, outgoing, etas);
+        let pdf = self.pdf_etas(incident, outgoing, etas);
+
+        Some(BxDFSample::new(spectrum, incident, pdf, self.get_type()))
+    }
+
+    fn pdf(&self, incident: Vector3, outgoing: Vector3) -> Float {
+        self.pdf_etas(incident, outgoing, self.etas(None))
+    }
+
+    fn textured(&self, at: TexturePoint) -> Option<Box<dyn BxDF>> {
+        Some(Box::new(Self {
+            t: self.t,
+            distribution: self.distribution.textured(at)?,
+            fresnel: self.fresnel.clone(),
+        }))
+    }
 }
 
-// #[derive(Debug)]
-// pub struct MicrofacetTransmission {
-//     t: Spectrum,
-//     distribution: Box<dyn MicrofacetDistribution>,
-//     fresnel: Dielectric,
-// }
-//
-// impl MicrofacetTransmission {
-//     pub fn new(
-//         t: Spectrum,
-//         distribution: Box<dyn MicrofacetDistribution>,
-//         fresnel: Dielectric,
-//     ) -> Self {
-//         Self {
-//             t,
-//             distribution,
-//             fresnel,
-//         }
-//     }
-// }
-//
-// impl BxDF for MicrofacetTransmission {
-//     fn get_type(&self) -> BxDFType {
-//         BxDFType::TRANSMISSION | BxDFType::GLOSSY
-//     }
-//
-//     fn evaluate(&self, incident: &Vector3, outgoing: &Vector3) -> Spectrum {
-//         if same_hemisphere(incident, outgoing) {
-//             return Spectrum::black();
-//         }
-//
-//         let cos_theta_i = cos_theta(incident);
-//         let cos_theta_o = cos_theta(outgoing);
-//         if cos_theta_i == 0.0 || cos_theta_o == 0.0 {
-//             return Spectrum::black();
-//         }
-//
-//         // Compute $\wh$ from $\wo$ and $\wi$ for microfacet transmission
-//         let (eta, wh) = {
-//             let eta = if cos_theta_o > 0.0 {
-//                 self.fresnel.eta_t / self.fresnel.eta_i
-//             } else {
-//                 self.fresnel.eta_i / self.fresnel.eta_t
-//             };
-//             let wh = flip_if_neg(*outgoing + *incident * eta);
-//             (eta, wh)
-//         };
-//
-//         // Same side?
-//         let cos_i = outgoing.dot(wh);
-//         let cos_t = incident.dot(wh);
-//         if cos_i * cos_t > 0.0 {
-//             return Spectrum::black();
-//         }
-//
-//         let f = self.fresnel.evaluate(cos_i);
-//
-//         let sqrt_denom = cos_i + eta * cos_t;
-//
-//         let t = (Spectrum::new_const(1.0) - f) * self.t;
-//         let dist = self.distribution.d(&wh) * self.distribution.g(incident, outgoing);
-//         let factor =
-//             cos_i.abs() * cos_t.abs() / (cos_theta_i * cos_theta_i * sqrt_denom * sqrt_denom);
-//
-//         t * (dist * factor).abs()
-//     }
-//
-//     fn sample(&self, outgoing: &Vector3, sample: &Vector2) -> BxDFSample {
-//         if bxdf_is_parallel(outgoing) {
-//             return BxDFSample::black_nan_0();
-//         }
-//
-//         let wh = self.distribution.sample_wh(outgoing, sample);
-//         // Should be rare
-//         if outgoing.dot(wh) < 0.0 {
-//             return BxDFSample::black_nan_0();
-//         }
-//
-//         let eta = if cos_theta(outgoing) > 0.0 {
-//             self.fresnel.eta_i / self.fresnel.eta_t
-//         } else {
-//             self.fresnel.eta_t / self.fresnel.eta_i
-//         };
-//
-//         let incident = outgoing.refracted(wh, eta);
-//         let spectrum = self.evaluate(&incident, outgoing);
-//         let pdf = self.pdf(&incident, outgoing);
-//
-//         BxDFSample::new(spectrum, incident, pdf, self.get_type())
-//     }
-//
-//     fn pdf(&self, incident: &Vector3, outgoing: &Vector3) -> Float {
-//         if same_hemisphere(incident, outgoing) {
-//             return 0.0;
-//         }
-//
-//         // Compute $\wh$ from $\wo$ and $\wi$ for microfacet transmission
-//         let eta = if cos_theta(outgoing) > 0.0 {
-//             self.fresnel.eta_i / self.fresnel.eta_t
-//         } else {
-//             self.fresnel.eta_t / self.fresnel.eta_i
-//         };
-//
-//         let wh = (*outgoing + *incident * eta).normalized();
-//
-//         let cos_i = incident.dot(wh);
-//         let cos_o = outgoing.dot(wh);
-//         if cos_i * cos_o > 0.0 {
-//             return 0.0;
-//         }
-//
-//         let sqrt_denom = cos_o + eta * cos_i;
-//         let dwh_dwi = eta * eta * cos_i.abs() / (sqrt_denom * sqrt_denom);
-//
-//         self.distribution.pdf(outgoing, &wh) * dwh_dwi
-//     }
-// }
+#[test]
+fn trowbridge_reitz_pdf_normalized() {
+    use crate::mc::sample_unit_hemisphere;
+
+    let outgoing = Vector3::new(0.3, 0.9, 0.1).normalized();
+
+    for &visible in &[false, true] {
+        let distribution = TrowbridgeReitzDistribution::new(0.3, 0.4, visible);
+
+        // cosine-weighted hemisphere sampling, the pdf being cos / π
+        let n = 128;
+        let mut integral = 0.0;
+        for x in 0..n {
+            for y in 0..n {
+                let sample = Vector2::new(x as Float + 0.5, y as Float + 0.5) / n as Float;
+                let wh = sample_unit_hemisphere(sample);
+
+                integral += distribution.pdf(outgoing, wh) * PI / cos_theta(wh);
+            }
+        }
+        integral /= (n * n) as Float;
+
+        assert!((integral - 1.0).abs() < 0.05);
+    }
+}
+
+#[test]
+fn microfacet_transmission_refracts() {
+    let distribution = Box::new(TrowbridgeReitzDistribution::new(0.2, 0.2, true));
+    let bxdf = MicrofacetTransmission::new(
+        Spectrum::broadcast(1.0),
+        distribution,
+        RefractiveType::Air,
+        RefractiveType::Glass,
+    );
+
+    for &outgoing in &[Vector3::new(0.3, 0.9, 0.1), Vector3::new(-0.1, -0.95, 0.2)] {
+        let outgoing = outgoing.normalized();
+        let sample = bxdf.sample(outgoing, Vector2::new(0.3, 0.6)).unwrap();
+
+        assert!(!same_hemisphere(sample.incident, outgoing));
+        assert!(sample.pdf > 0.0);
+        assert!((sample.pdf - bxdf.pdf(sample.incident, outgoing)).abs() < 1e-3 * sample.pdf);
+    }
+}
+
+#[test]
+fn microfacet_transmission_conserves_energy() {
+    let bxdf = MicrofacetTransmission::new(
+        Spectrum::broadcast(1.0),
+        Box::new(TrowbridgeReitzDistribution::new(0.3, 0.3, true)),
+        RefractiveType::Air,
+        RefractiveType::Glass,
+    );
+
+    // entering the glass loses only the Fresnel reflection, leaving it also total internal ones
+    for &(outgoing, min) in &[
+        (Vector3::new(0.0, 1.0, 0.0), 0.9),
+        (Vector3::new(0.5, 0.8, 0.0), 0.9),
+        (Vector3::new(0.0, -1.0, 0.0), 0.7),
+    ] {
+        let outgoing = outgoing.normalized();
+
+        let n = 128;
+        let mut energy = 0.0;
+        for x in 0..n {
+            for y in 0..n {
+                let sample = Vector2::new(x as Float + 0.5, y as Float + 0.5) / n as Float;
+                if let Some(sample) = bxdf.sample(outgoing, sample) {
+                    let f = bxdf.evaluate(sample.incident, outgoing);
+                    assert!((f[0] - sample.spectrum[0]).abs() <= 1e-4 * f[0]);
+
+                    if sample.pdf > 0.0 {
+                        energy += f[0] * cos_theta(sample.incident).abs() / sample.pdf;
+                    }
+                }
+            }
+        }
+        energy /= (n * n) as Float;
+
+        assert!(energy > min && energy <= 1.0);
+    }
+}
+
+#[test]
+fn gold_reflects_red_more_than_blue() {
+    let fresnel = FresnelConductor::new(RefractiveType::Air, RefractiveType::Gold);
+
+    assert!(fresnel.evaluate_lambda(1.0, 0.7) > 0.9);
+    assert!(fresnel.evaluate_lambda(1.0, 0.45) < 0.5);
+}
@@ -1,67 +1,152 @@
 #![allow(clippy::upper_case_acronyms)]
 
 mod bsdf;
+mod coated;
 mod diffuse;
+#[cfg(feature = "static-dispatch")]
+mod dispatch;
 mod fresnel;
 mod lambertian;
+mod measured;
 mod microfacet;
 mod oren_nayar;
 mod specular;
 
-pub use bsdf::BSDF;
+pub use bsdf::{bxdf_item, BxDFItem, BSDF};
+#[cfg(feature = "static-dispatch")]
+pub use dispatch::BxDFs;
 
+pub use coated::*;
 pub use diffuse::*;
 pub use fresnel::*;
 pub use lambertian::*;
+pub use measured::*;
 pub use microfacet::*;
 pub use oren_nayar::*;
 pub use specular::*;
 
 use crate::debug_utils::{is_finite, is_normalized, within_01};
 use crate::mc::sample_unit_hemisphere;
+use crate::texture::TexturePoint;
 use crate::Spectrum;
 use crate::*;
+use geometry::orthonormal_basis;
 use serde::{Deserialize, Serialize};
 
 #[cfg(not(feature = "f64"))]
-use std::f32::consts::{FRAC_1_PI, PI};
+use std::f32::consts::FRAC_1_PI;
 #[cfg(feature = "f64")]
-use std::f64::consts::{FRAC_1_PI, PI};
-use std::ops::Mul;
+use std::f64::consts::FRAC_1_PI;
 use utility::floats::FloatExt;
 
-/// A rotation is either
-/// - not happening
-/// - flipping direction
-/// - some rotation
+/// The local shading frame of a surface point. The normal `n` maps onto the
+/// [BxDF normal](bxdf_normal), the tangents `t` and `b` onto the `x` and `z` axis respectively.
 #[derive(Copy, Clone, Debug)]
-pub enum Rotation {
-    None,
-    Flip,
-    Some(Rotation3),
-}
+pub struct Frame {
+    pub t: Vector3,
+    pub b: Vector3,
+    pub n: Vector3,
+    /// The texture coordinates of the surface point (if any), evaluating textured BxDFs
+    pub uv: Option<Vector2>,
+    /// The surface point in world space (if any), evaluating procedural textures
+    pub point: Option<Vector3>,
+}
+
+impl Frame {
+    /// Creates a new shading frame around the given normal.
+    ///
+    /// # Constraints
+    /// * `n` - All values must be finite (neither infinite nor `NaN`).
+    ///   Should be normalized.
+    ///
+    /// # Arguments
+    /// * `n` - The normal in world space
+    ///
+    /// # Returns
+    /// * Self
+    #[inline]
+    pub fn new(n: Vector3) -> Self {
+        let (b, t) = orthonormal_basis(n);
 
-impl Rotation {
-    /// Reversed the rotation (if any).
+        Self {
+            t,
+            b,
+            n,
+            uv: None,
+            point: None,
+        }
+    }
+
+    /// Creates a new shading frame around the given normal, orienting the `x` axis of the local
+    /// BxDF space along the given tangent, e.g. for the direction of brushed metal.
+    ///
+    /// # Constraints
+    /// * `n` - All values must be finite (neither infinite nor `NaN`).
+    ///   Should be normalized.
+    /// * `t` - Should be normalized and perpendicular to `n`.
+    ///
+    /// # Arguments
+    /// * `n` - The normal in world space
+    /// * `t` - The tangent in world space
+    ///
+    /// # Returns
+    /// * Self
     #[inline]
-    pub fn reversed(&self) -> Self {
-        match self {
-            Rotation::Some(r) => Self::Some(r.reversed()),
-            _ => *self,
+    pub fn from_tangent(n: Vector3, t: Vector3) -> Self {
+        debug_assert!(is_normalized(t));
+        debug_assert!(n.dot(t).abs() < Float::big_epsilon());
+
+        Self {
+            t,
+            b: t.cross(n),
+            n,
+            uv: None,
+            point: None,
         }
     }
-}
 
-impl Mul<Vector3> for Rotation {
-    type Output = Vector3;
+    /// Sets the texture coordinates of the surface point.
+    ///
+    /// # Arguments
+    /// * `uv` - The texture coordinates (if any)
+    ///
+    /// # Returns
+    /// * Self
+    #[inline]
+    pub fn with_uv(mut self, uv: Option<Vector2>) -> Self {
+        self.uv = uv;
+        self
+    }
 
+    /// Sets the surface point in world space.
+    ///
+    /// # Arguments
+    /// * `point` - The surface point (if any)
+    ///
+    /// # Returns
+    /// * Self
     #[inline]
-    fn mul(self, rhs: Vector3) -> Self::Output {
-        match self {
-            Rotation::None => rhs,
-            Rotation::Flip => flip(rhs),
-            Rotation::Some(r) => r * rhs,
-        }
+    pub fn with_point(mut self, point: Option<Vector3>) -> Self {
+        self.point = point;
+        self
+    }
+
+    /// Returns the surface point to evaluate textures at.
+    #[inline]
+    pub fn texture_point(&self) -> TexturePoint {
+        TexturePoint::new(self.uv, self.point)
+    }
+
+    /// Transforms the given world vector into the local BxDF space.
+    #[inline]
+    pub fn to_local(&self, v: Vector3) -> Vector3 {
+        Vector3::new(v.dot(self.t), v.dot(self.n), v.dot(self.b))
+    }
+
+    /// Transforms the given vector of the local BxDF space into world space.
+    #[inline]
+    pub fn to_world(&self, v: Vector3) -> Vector3 {
+        self.t * v.x + self.n * v.y + self.b * v.z
     }
 }
 
@@ -84,6 +169,15 @@ pub fn bxdf_normal() -> Vector3 {
     Vector3::unit_y()
 }
 
+/// The BxDF frame coincides with the axes of the local BxDF space.
+///
+/// # Returns
+/// * The global BxDF frame
+#[inline(always)]
+pub fn bxdf_frame() -> Frame {
+    Frame::from_tangent(bxdf_normal(), Vector3::unit_x())
+}
+
 #[inline(always)]
 pub fn bxdf_incident_to(v: Vector3) -> Vector3 {
     debug_assert!(is_finite(v));
@@ -248,30 +342,23 @@ pub fn same_hemisphere(a: Vector3, b: Vector3) -> bool {
     a.y * b.y > 0.0
 }
 
+/// Returns the shading frame transforming between world space and the local BxDF space around
+/// the given normal.
+///
+/// # Constraints
+/// * `v` - All values must be finite (neither infinite nor `NaN`).
+///   Should be normalized.
+///
+/// # Arguments
+/// * `v` - The normal in world space
+///
+/// # Returns
+/// * The shading frame
 #[inline(always)]
-pub fn world_to_bxdf(v: Vector3) -> Rotation {
-    debug_assert!(is_finite(v));
-
-    if v == Vector3::unit_y() {
-        Rotation::None
-    } else if v == -Vector3::unit_y() {
-        Rotation::Flip
-    } else {
-        Rotation::Some(Rotation3::from_rotation_between(v, bxdf_normal()))
-    }
-}
-
-#[inline]
-pub fn bxdf_to_world(v: Vector3) -> Rotation3 {
+pub fn world_to_bxdf(v: Vector3) -> Frame {
     debug_assert!(is_finite(v));
 
-    if v == Vector3::unit_y() {
-        Rotation3::default()
-    } else if v == -Vector3::unit_y() {
-        Rotation3::from_rotation_xy(-PI)
-    } else {
-        Rotation3::from_rotation_between(bxdf_normal(), v)
-    }
+    Frame::new(v)
 }
 
 bitflags! {
@@ -539,6 +626,20 @@ pub trait BxDF: Send + Sync {
             0.0
         }
     }
+
+    /// Evaluates the [textures](crate::texture::Texture) of this BxDF at a surface point.
+    ///
+    /// Untextured BxDFs (the default) return `None` and get used as they are. Textured ones get
+    /// evaluated by their average values without a resolved point.
+    ///
+    /// # Arguments
+    /// * `at` - The surface point
+    ///
+    /// # Results
+    /// * The BxDF with constant textures, or `None` if this BxDF is not textured
+    fn textured(&self, _at: TexturePoint) -> Option<Box<dyn BxDF>> {
+        None
+    }
 }
 
 /// This special BxDF scales all spectrum outputs of another one, effectively wrapping around
@@ -596,4 +697,63 @@ impl BxDF for ScaledBxDF {
     fn pdf(&self, incident: Vector3, outgoing: Vector3) -> Float {
         self.bxdf.pdf(incident, outgoing)
     }
+
+    fn textured(&self, at: TexturePoint) -> Option<Box<dyn BxDF>> {
+        let bxdf = self.bxdf.textured(at)?;
+
+        Some(Box::new(Self::new(bxdf, self.scale)))
+    }
+}
+
+#[test]
+fn frames_are_orthonormal() {
+    let normals = [
+        Vector3::unit_y(),
+        -Vector3::unit_z(),
+        Vector3::new(1e-4, 0.0, -1.0).normalized(),
+        Vector3::new(0.3, -0.8, 0.1).normalized(),
+    ];
+    let v = Vector3::new(0.2, -0.5, 0.7);
+
+    for &n in &normals {
+        let frame = Frame::new(n);
+
+        assert!(is_normalized(frame.t));
+        assert!(is_normalized(frame.b));
+        assert!(frame.t.dot(frame.n).abs() < 1e-5);
+        assert!(frame.b.dot(frame.n).abs() < 1e-5);
+        assert!(frame.t.dot(frame.b).abs() < 1e-5);
+        // right-handed in the local BxDF space
+        assert!((frame.t.cross(frame.n) - frame.b).mag() < 1e-5);
+
+        assert!((frame.to_local(n) - bxdf_normal()).mag() < 1e-5);
+        assert!((frame.to_world(bxdf_normal()) - n).mag() < 1e-5);
+        assert!((frame.to_world(frame.to_local(v)) - v).mag() < 1e-5);
+        assert!((frame.to_local(frame.to_world(v)) - v).mag() < 1e-5);
+    }
+}
+
+#[test]
+fn tangent_frames_align_with_the_tangent() {
+    let n = Vector3::new(0.3, -0.8, 0.1).normalized();
+    let t = Vector3::new(1.0, 0.0, 0.0);
+    let t = (t - n * n.dot(t)).normalized();
+    let frame = Frame::from_tangent(n, t);
+
+    assert!(frame.t.dot(frame.n).abs() < 1e-5);
+    assert!(frame.b.dot(frame.n).abs() < 1e-5);
+    assert!(frame.t.dot(frame.b).abs() < 1e-5);
+    assert!(is_normalized(frame.b));
+
+    // the tangent is the x axis of the local BxDF space
+    assert!((frame.to_local(t) - Vector3::unit_x()).mag() < 1e-5);
+    assert!((frame.to_local(n) - bxdf_normal()).mag() < 1e-5);
+    assert!((frame.to_world(Vector3::unit_x()) - t).mag() < 1e-5);
+
+    // right-handed like the frames without a tangent
+    assert!((frame.t.cross(frame.n) - frame.b).mag() < 1e-5);
+
+    let frame = bxdf_frame();
+    let v = Vector3::new(0.2, -0.5, 0.7);
+    assert!((frame.to_local(v) - v).mag() < 1e-6);
 }
@@ -1,5 +1,6 @@
 use crate::bxdf::{cos_phi, cos_theta, sin_phi, sin_theta, BxDF, Type};
 use crate::debug_utils::is_normalized;
+use crate::texture::{Texture, TexturePoint};
 use crate::*;
 use serde::{Deserialize, Serialize};
 #[cfg(not(feature = "f64"))]
@@ -11,7 +12,7 @@ use utility::floats::FloatExt;
 /// The Oren-Nayar reflectance model describes rough opaque diffuse surfaces where each facet is lambertian (diffuse).
 #[derive(Serialize, Deserialize)]
 pub struct OrenNayar {
-    r: Spectrum,
+    r: Texture<Spectrum>,
     a: Float,
     b: Float,
 }
@@ -23,12 +24,12 @@ impl OrenNayar {
     /// * `sigma` - Should be in range `[0, inf)`.
     ///
     /// # Arguments
-    /// * `r` - The reflection
+    /// * `r` - The (textured) reflection
     /// * `sigma` - The roughness (gradient of the surface elevation) in degrees
     ///
     /// # Returns
     /// * Self
-    pub fn new(r: Spectrum, sigma: Float) -> Self {
+    pub fn new<T: Into<Texture<Spectrum>>>(r: T, sigma: Float) -> Self {
         debug_assert!(sigma.in_range_incl_left(0.0, Float::INFINITY));
 
         let sigma = sigma.to_radians();
@@ -36,7 +37,7 @@ impl OrenNayar {
         let a = 1.0 - (sigma2 / (2.0 * (sigma2 + 0.33)));
         let b = 0.45 * sigma2 / (sigma2 + 0.09);
 
-        Self { r, a, b }
+        Self { r: r.into(), a, b }
     }
 
     /// Calculates the Oren Nayar scaling parameter.
@@ -98,13 +99,15 @@ impl BxDF for OrenNayar {
     fn evaluate(&self, incident: Vector3, outgoing: Vector3) -> Spectrum {
         let oren_nayar = self.calc_param(incident, outgoing);
 
-        self.r * oren_nayar
+        self.r.evaluate(TexturePoint::default()) * oren_nayar
     }
 
     fn evaluate_buf(&self, incident: Vector3, outgoing: Vector3, indices: &[usize]) -> Vec<Float> {
         let oren_nayar = self.calc_param(incident, outgoing);
 
-        indices.iter().map(|&i| self.r[i] * oren_nayar).collect()
+        let r = self.r.evaluate(TexturePoint::default());
+
+        indices.iter().map(|&i| r[i] * oren_nayar).collect()
     }
 
     fn evaluate_wavelength(
@@ -115,6 +118,18 @@ impl BxDF for OrenNayar {
     ) -> Float {
         let oren_nayar = self.calc_param(incident, outgoing);
 
-        self.r[light_wave_index] * oren_nayar
+        self.r.evaluate(TexturePoint::default())[light_wave_index] * oren_nayar
+    }
+
+    fn textured(&self, at: TexturePoint) -> Option<Box<dyn BxDF>> {
+        if self.r.is_constant() {
+            return None;
+        }
+
+        Some(Box::new(Self {
+            r: Texture::Constant(self.r.evaluate(at)),
+            a: self.a,
+            b: self.b,
+        }))
     }
 }
@@ -0,0 +1,205 @@
+use crate::camera::Camera;
+use crate::filters::Filter;
+use crate::samplers::Sampler;
+use crate::{Bivector3, Float, Rotation3, Vector3};
+use geometry::Ray;
+use serde::{Deserialize, Serialize};
+use ultraviolet::{Slerp, UVec2};
+use utility::floats::FloatExt;
+
+/// A rigid transform of a camera at a point in time.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    pub time: Float,
+    /// The translation of the camera
+    #[serde(default = "Vector3::zero")]
+    pub translation: Vector3,
+    /// The optional rotation `(axis, angle)` of the camera around the pivot
+    #[serde(default)]
+    pub rotation: Option<(Vector3, Float)>,
+}
+
+impl CameraKeyframe {
+    fn rotor(&self) -> Rotation3 {
+        match self.rotation {
+            Some((axis, angle)) => {
+                Rotation3::from_angle_plane(angle, Bivector3::from_normalized_axis(axis))
+            }
+            None => Rotation3::identity(),
+        }
+    }
+}
+
+/// A camera moving over time, distributing the primary rays of another camera over the interval
+/// the shutter is open, producing motion blur.
+///
+/// The camera gets transformed by interpolating its keyframes: the translations linearly, the
+/// rotations spherically. Before the first and after the last keyframe, the camera stays at rest.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AnimatedCamera {
+    camera: Box<dyn Camera>,
+    /// The time the shutter opens and closes
+    shutter: (Float, Float),
+    /// The point the camera rotates around, usually its position
+    #[serde(default = "Vector3::zero")]
+    pivot: Vector3,
+    /// The keyframes, sorted by time
+    keyframes: Vec<CameraKeyframe>,
+}
+
+impl AnimatedCamera {
+    /// Creates a new animated camera.
+    ///
+    /// # Constraints
+    /// * `shutter` - The opening time should be less-or-equal to the closing time.
+    /// * `keyframes` - Should be sorted by time. The rotation axes should be normalized.
+    ///
+    /// # Arguments
+    /// * `camera` - The camera at rest
+    /// * `shutter` - The time the shutter opens and closes
+    /// * `pivot` - The point the camera rotates around
+    /// * `keyframes` - The keyframes
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(
+        camera: Box<dyn Camera>,
+        shutter: (Float, Float),
+        pivot: Vector3,
+        keyframes: Vec<CameraKeyframe>,
+    ) -> Self {
+        debug_assert!(shutter.0 <= shutter.1);
+        debug_assert!(keyframes.windows(2).all(|w| w[0].time <= w[1].time));
+
+        Self {
+            camera,
+            shutter,
+            pivot,
+            keyframes,
+        }
+    }
+
+    /// Interpolates the keyframes at the given time.
+    ///
+    /// # Returns
+    /// * The translation
+    /// * The rotation
+    fn transform(&self, time: Float) -> (Vector3, Rotation3) {
+        let next = self.keyframes.iter().position(|k| k.time > time);
+
+        match next {
+            None => match self.keyframes.last() {
+                Some(last) => (last.translation, last.rotor()),
+                None => (Vector3::zero(), Rotation3::identity()),
+            },
+            Some(0) => (self.keyframes[0].translation, self.keyframes[0].rotor()),
+            Some(i) => {
+                let (a, b) = (&self.keyframes[i - 1], &self.keyframes[i]);
+                let t = (time - a.time) / (b.time - a.time);
+
+                let translation = a.translation + (b.translation - a.translation) * t;
+                let rotation = a.rotor().slerp(b.rotor(), t).normalized();
+
+                (translation, rotation)
+            }
+        }
+    }
+
+    /// Moves the given ray of the camera at rest to the pose at a random time within the shutter
+    /// interval.
+    fn animate(&self, mut ray: Ray) -> Ray {
+        let time = self
+            .shutter
+            .0
+            .lerp(self.shutter.1, Sampler::Random.get_1d());
+        let (translation, rotation) = self.transform(time);
+
+        ray.origin = rotation * (ray.origin - self.pivot) + self.pivot + translation;
+        ray.direction = (rotation * ray.direction).normalized();
+        ray.time = time;
+
+        ray
+    }
+}
+
+#[typetag::serde]
+impl Camera for AnimatedCamera {
+    fn resolution(&self) -> UVec2 {
+        self.camera.resolution()
+    }
+
+    fn get_filter(&self) -> &dyn Filter {
+        self.camera.get_filter()
+    }
+
+    fn primary_ray(&self, pixel: UVec2) -> Ray {
+        self.animate(self.camera.primary_ray(pixel))
+    }
+
+    fn primary_ray_wavelength(&self, pixel: UVec2, lambda: Float) -> Ray {
+        self.animate(self.camera.primary_ray_wavelength(pixel, lambda))
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.camera.is_dispersive()
+    }
+}
+
+#[test]
+fn rays_follow_the_keyframes() {
+    #[cfg(not(feature = "f64"))]
+    use std::f32::consts::FRAC_PI_2;
+    #[cfg(feature = "f64")]
+    use std::f64::consts::FRAC_PI_2;
+
+    let camera = |shutter: Float| -> Box<dyn Camera> {
+        ron::from_str(&format!(
+            r#"{{
+                "AnimatedCamera": (
+                    camera: {{
+                        "PerspectiveCamera": (
+                            Sampler: NoOp,
+                            Position: (x: 0, y: 0, z: 4),
+                            Target: (x: 0, y: 0, z: 0),
+                            Up: (x: 0, y: 1, z: 0),
+                            FovY: 40,
+                            Resolution: (x: 9, y: 9),
+                        ),
+                    }},
+                    shutter: ({0}, {0}),
+                    pivot: (x: 0, y: 0, z: 4),
+                    keyframes: [
+                        (time: 1, rotation: Some(((x: 0, y: 1, z: 0), 0))),
+                        (
+                            time: 3,
+                            translation: (x: 2, y: 0, z: 0),
+                            rotation: Some(((x: 0, y: 1, z: 0), {1})),
+                        ),
+                    ],
+                ),
+            }}"#,
+            shutter, FRAC_PI_2
+        ))
+        .unwrap()
+    };
+
+    let center = UVec2::new(4, 4);
+    let at_rest = camera(0.0).primary_ray(center);
+    assert_eq!(at_rest.time, 0.0);
+    assert!((at_rest.direction - -Vector3::unit_z()).mag() < 1e-4);
+
+    // halfway, the camera moved by half the translation and turned by 45 degrees
+    let halfway = camera(2.0).primary_ray(center);
+    assert_eq!(halfway.time, 2.0);
+    assert!((halfway.origin - Vector3::new(1.0, 0.0, 4.0)).mag() < 1e-4);
+    assert!((halfway.direction.dot(at_rest.direction) - (FRAC_PI_2 / 2.0).cos()).abs() < 1e-3);
+    assert!(halfway.direction.y.abs() < 1e-4);
+
+    // after the last keyframe the camera rests again
+    let end = camera(3.0).primary_ray(center);
+    let after = camera(5.0).primary_ray(center);
+    assert!((end.origin - Vector3::new(2.0, 0.0, 4.0)).mag() < 1e-4);
+    assert!(end.direction.dot(at_rest.direction).abs() < 1e-4);
+    assert!((after.origin - end.origin).mag() < 1e-4);
+    assert!((after.direction - end.direction).mag() < 1e-4);
+}
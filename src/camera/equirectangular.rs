@@ -0,0 +1,118 @@
+use crate::camera::Camera;
+use crate::debug_utils::is_finite;
+use crate::filters::Filter;
+use crate::samplers::camera::CameraSampler;
+use crate::{Float, Vector3};
+use geometry::{spherical_to_cartesian, Ray};
+use serde::{Deserialize, Serialize};
+use ultraviolet::UVec2;
+
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::{PI, TAU};
+#[cfg(feature = "f64")]
+use std::f64::consts::{PI, TAU};
+
+/// A camera capturing the full sphere of directions around its position in an equirectangular
+/// (latitude-longitude) mapping. Useful to bake environment maps / radiance probes of a scene.
+///
+/// The `x` axis of the image maps to the azimuth around the `y` axis (starting at `+z`), the `y`
+/// axis of the image maps to the polar angle from `+y` (top) to `-y` (bottom).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EquirectangularCamera {
+    sampler: CameraSampler,
+    #[serde(default)]
+    filter: Box<dyn Filter>,
+    position: Vector3,
+    resolution: UVec2,
+}
+
+impl EquirectangularCamera {
+    /// Creates a new equirectangular camera.
+    ///
+    /// # Constraints
+    /// * `position` - All values should be finite (neither infinite nor `NaN`).
+    /// * `resolution` - Should usually have an aspect ratio of `2:1`.
+    ///
+    /// # Arguments
+    /// * `sampler` - The pixel sampler
+    /// * `filter` - The pixel filter
+    /// * `position` - The position to capture the sphere from
+    /// * `resolution` - The resolution of the image
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(
+        sampler: CameraSampler,
+        filter: Box<dyn Filter>,
+        position: Vector3,
+        resolution: UVec2,
+    ) -> Self {
+        debug_assert!(is_finite(position));
+
+        Self {
+            sampler,
+            filter,
+            position,
+            resolution,
+        }
+    }
+}
+
+#[typetag::serde]
+impl Camera for EquirectangularCamera {
+    fn resolution(&self) -> UVec2 {
+        self.resolution
+    }
+
+    fn get_filter(&self) -> &dyn Filter {
+        self.filter.as_ref()
+    }
+
+    fn primary_ray(&self, pixel: UVec2) -> Ray {
+        debug_assert!(pixel == pixel.min_by_component(self.resolution));
+
+        let sample = self.sampler.sample();
+
+        let u = ((pixel.x as Float + sample.x) / self.resolution.x as Float).clamp(0.0, 1.0);
+        let v = ((pixel.y as Float + sample.y) / self.resolution.y as Float).clamp(0.0, 1.0);
+
+        let direction = spherical_to_cartesian(u * TAU, v * PI);
+
+        Ray::new_fast(self.position, direction.normalized())
+    }
+}
+
+#[test]
+fn rays_cover_the_sphere() {
+    let position = Vector3::new(1.0, 2.0, 3.0);
+    let camera = EquirectangularCamera::new(
+        CameraSampler::NoOp,
+        Box::default(),
+        position,
+        UVec2::new(4, 3),
+    );
+
+    let direction = |x, y| {
+        let ray = camera.primary_ray(UVec2::new(x, y));
+        assert_eq!(ray.origin, position);
+
+        ray.direction
+    };
+    let h = (0.5 as Float).sqrt();
+    let c = (3.0 as Float).sqrt() / 2.0;
+
+    // the middle row lies on the horizon, going around the y axis starting at +z
+    for &(x, expected) in &[
+        (0, Vector3::new(h, 0.0, h)),
+        (1, Vector3::new(h, 0.0, -h)),
+        (2, Vector3::new(-h, 0.0, -h)),
+        (3, Vector3::new(-h, 0.0, h)),
+    ] {
+        let d = direction(x, 1);
+        assert!((d - expected).mag() < 1e-5, "{}: {:?}", x, d);
+    }
+
+    // the top and bottom rows look up and down by 60 degrees
+    assert!((direction(0, 0).y - c).abs() < 1e-5);
+    assert!((direction(0, 2).y + c).abs() < 1e-5);
+}
@@ -0,0 +1,146 @@
+use crate::camera::Camera;
+use crate::debug_utils::{is_finite, is_normalized};
+use crate::filters::Filter;
+use crate::samplers::camera::CameraSampler;
+use crate::{Float, Vector3};
+use geometry::Ray;
+use serde::{Deserialize, Serialize};
+use ultraviolet::UVec2;
+use utility::floats::FloatExt;
+
+/// The projection of a fisheye lens, mapping the angle to the view direction onto the distance
+/// to the image center.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum FisheyeProjection {
+    /// The distance is proportional to the angle, keeping angles between directions.
+    Equidistant,
+    /// The distance is proportional to `sin(angle / 2)`, keeping solid angles (areas).
+    Equisolid,
+}
+
+/// A fisheye camera somewhere in space, looking at a target.
+///
+/// The field of view spans the image diagonal, such that the whole image gets covered
+/// ("full-frame" fisheye). Fields of view up to `360` degrees are possible.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FisheyeCamera {
+    sampler: CameraSampler,
+    #[serde(default)]
+    filter: Box<dyn Filter>,
+    position: Vector3,
+    target: Vector3,
+    up: Vector3,
+    /// The diagonal field of view (in degrees)
+    fov: Float,
+    projection: FisheyeProjection,
+    resolution: UVec2,
+}
+
+impl FisheyeCamera {
+    /// Creates a new fisheye camera.
+    ///
+    /// # Constraints
+    /// * `position` - All values should be finite (neither infinite nor `NaN`).
+    /// * `target` - All values should be finite.
+    /// * `up` - All values should be finite.
+    ///   Should be normalized.
+    /// * `fov` - Should be within `(0, 360]`.
+    ///
+    /// # Arguments
+    /// * `sampler` - The pixel sampler
+    /// * `filter` - The pixel filter
+    /// * `position` - The eye / position of the camera
+    /// * `target` - Where the camera looks at
+    /// * `up` - The up vector of the camera
+    /// * `fov` - The diagonal field of view (in degrees)
+    /// * `projection` - The projection of the lens
+    /// * `resolution` - The resolution of the camera
+    ///
+    /// # Returns
+    /// * Self
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        sampler: CameraSampler,
+        filter: Box<dyn Filter>,
+        position: Vector3,
+        target: Vector3,
+        up: Vector3,
+        fov: Float,
+        projection: FisheyeProjection,
+        resolution: UVec2,
+    ) -> Self {
+        debug_assert!(is_finite(position));
+        debug_assert!(is_finite(target));
+        debug_assert!(is_finite(up));
+        debug_assert!(is_normalized(up));
+        debug_assert!(fov > 0.0 && fov <= 360.0);
+
+        Self {
+            sampler,
+            filter,
+            position,
+            target,
+            up,
+            fov,
+            projection,
+            resolution,
+        }
+    }
+
+    /// Returns the angle to the view direction of the given normalized distance to the image
+    /// center.
+    fn angle(&self, radius: Float) -> Float {
+        let max_angle = (0.5 * self.fov).to_radians();
+
+        match self.projection {
+            FisheyeProjection::Equidistant => radius * max_angle,
+            FisheyeProjection::Equisolid => {
+                2.0 * (radius * (0.5 * max_angle).sin())
+                    .fast_clamp(-1.0, 1.0)
+                    .asin()
+            }
+        }
+    }
+}
+
+#[typetag::serde]
+impl Camera for FisheyeCamera {
+    fn resolution(&self) -> UVec2 {
+        self.resolution
+    }
+
+    fn get_filter(&self) -> &dyn Filter {
+        self.filter.as_ref()
+    }
+
+    fn primary_ray(&self, pixel: UVec2) -> Ray {
+        debug_assert!(pixel == pixel.min_by_component(self.resolution));
+
+        let sample = self.sampler.sample();
+
+        let w = self.resolution.x as Float;
+        let h = self.resolution.y as Float;
+        let half_diagonal = 0.5 * (w * w + h * h).sqrt();
+
+        // offset to the image center, normalized by the half diagonal
+        let x = (pixel.x as Float + sample.x - 0.5 * w) / half_diagonal;
+        let y = (pixel.y as Float + sample.y - 0.5 * h) / half_diagonal;
+        let radius = (x * x + y * y).sqrt().min(1.0);
+
+        let view = (self.target - self.position).normalized();
+        let axis_right = view.cross(self.up).normalized();
+        let axis_up = axis_right.cross(view);
+
+        let angle = self.angle(radius);
+        let sideways = if radius > 0.0 {
+            // the image y axis points downwards
+            (axis_right * x - axis_up * y) / (x * x + y * y).sqrt()
+        } else {
+            Vector3::zero()
+        };
+
+        let direction = view * angle.cos() + sideways * angle.sin();
+
+        Ray::new_fast(self.position, direction.normalized())
+    }
+}
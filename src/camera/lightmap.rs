@@ -0,0 +1,222 @@
+use crate::camera::Camera;
+use crate::filters::Filter;
+use crate::samplers::camera::CameraSampler;
+use crate::{Float, Vector2, Vector3};
+use geometry::{offset_ray_towards, Mesh, Ray};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ultraviolet::UVec2;
+
+/// The serialized form of a [`LightmapCamera`](LightmapCamera), without the texel lookup.
+#[derive(Clone, Serialize, Deserialize)]
+struct LightmapCameraSerde {
+    sampler: CameraSampler,
+    #[serde(default)]
+    filter: Box<dyn Filter>,
+    mesh: Arc<Mesh>,
+    resolution: UVec2,
+}
+
+/// A camera that does not look at the scene, but maps each pixel into the texture space of a mesh
+/// with texture coordinates. Each primary ray starts on the surface point of the mesh and points
+/// into the direction of the surface normal, to be used with the [`Lightmap`](crate::integrator::Lightmap)
+/// integrator.
+///
+/// Pixels not covered by any triangle in texture space generate [empty](Ray::empty) rays.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "LightmapCameraSerde", into = "LightmapCameraSerde")]
+pub struct LightmapCamera {
+    sampler: CameraSampler,
+    filter: Box<dyn Filter>,
+    mesh: Arc<Mesh>,
+    resolution: UVec2,
+    /// The indices of the faces overlapping each texel in texture space
+    texels: Arc<Vec<Vec<u32>>>,
+}
+
+impl LightmapCamera {
+    /// Creates a new lightmap camera.
+    ///
+    /// # Constraints
+    /// * `mesh` - Should contain texture coordinates within `[0, 1]`.
+    ///
+    /// # Arguments
+    /// * `sampler` - The pixel sampler
+    /// * `filter` - The pixel filter
+    /// * `mesh` - The mesh to bake the lightmap of
+    /// * `resolution` - The resolution of the lightmap
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(
+        sampler: CameraSampler,
+        filter: Box<dyn Filter>,
+        mesh: Arc<Mesh>,
+        resolution: UVec2,
+    ) -> Self {
+        let (width, height) = (resolution.x as Float, resolution.y as Float);
+        let mut texels = vec![Vec::new(); (resolution.x * resolution.y) as usize];
+
+        for (i, face) in mesh.faces().iter().enumerate() {
+            let (v0, v1, v2) = face.get_vertices(mesh.vertices());
+            let min = v0.uv.min_by_component(v1.uv.min_by_component(v2.uv));
+            let max = v0.uv.max_by_component(v1.uv.max_by_component(v2.uv));
+
+            // the v axis points upwards, whereas the pixels count downwards
+            let x_min = (min.x * width).floor().max(0.0) as u32;
+            let x_max = ((max.x * width).ceil() as u32).min(resolution.x);
+            let y_min = ((1.0 - max.y) * height).floor().max(0.0) as u32;
+            let y_max = (((1.0 - min.y) * height).ceil() as u32).min(resolution.y);
+
+            for y in y_min..y_max {
+                for x in x_min..x_max {
+                    texels[(x + y * resolution.x) as usize].push(i as u32);
+                }
+            }
+        }
+
+        Self {
+            sampler,
+            filter,
+            mesh,
+            resolution,
+            texels: Arc::new(texels),
+        }
+    }
+
+    /// Computes the barycentric coordinates of the given texture coordinate inside the face.
+    ///
+    /// # Returns
+    /// * The barycentric coordinates (possibly negative if outside), or `None` if degenerate
+    fn barycentrics(&self, face: u32, uv: Vector2) -> Option<Vector3> {
+        let face = &self.mesh.faces()[face as usize];
+        let (v0, v1, v2) = face.get_vertices(self.mesh.vertices());
+
+        let cross = |a: Vector2, b: Vector2| a.x * b.y - a.y * b.x;
+
+        let e1 = v1.uv - v0.uv;
+        let e2 = v2.uv - v0.uv;
+        let area = cross(e1, e2);
+        if area == 0.0 {
+            return None;
+        }
+
+        let p = uv - v0.uv;
+        let b1 = cross(p, e2) / area;
+        let b2 = cross(e1, p) / area;
+
+        Some(Vector3::new(1.0 - b1 - b2, b1, b2))
+    }
+}
+
+impl From<LightmapCameraSerde> for LightmapCamera {
+    fn from(serde: LightmapCameraSerde) -> Self {
+        Self::new(serde.sampler, serde.filter, serde.mesh, serde.resolution)
+    }
+}
+
+impl From<LightmapCamera> for LightmapCameraSerde {
+    fn from(camera: LightmapCamera) -> Self {
+        Self {
+            sampler: camera.sampler,
+            filter: camera.filter,
+            mesh: camera.mesh,
+            resolution: camera.resolution,
+        }
+    }
+}
+
+#[typetag::serde]
+impl Camera for LightmapCamera {
+    fn resolution(&self) -> UVec2 {
+        self.resolution
+    }
+
+    fn get_filter(&self) -> &dyn Filter {
+        self.filter.as_ref()
+    }
+
+    fn primary_ray(&self, pixel: UVec2) -> Ray {
+        debug_assert!(pixel == pixel.min_by_component(self.resolution));
+
+        let sample = self.sampler.sample();
+        let uv = Vector2::new(
+            (pixel.x as Float + sample.x) / self.resolution.x as Float,
+            1.0 - (pixel.y as Float + sample.y) / self.resolution.y as Float,
+        );
+
+        // take the containing face, or else the closest one to cover the texels at the borders
+        let mut best: Option<(u32, Vector3)> = None;
+        for &face in &self.texels[(pixel.x + pixel.y * self.resolution.x) as usize] {
+            if let Some(b) = self.barycentrics(face, uv) {
+                let inside = b.x.min(b.y.min(b.z));
+
+                if best.is_none_or(|(_, best_b)| inside > best_b.x.min(best_b.y.min(best_b.z))) {
+                    best = Some((face, b));
+                }
+
+                if inside >= 0.0 {
+                    break;
+                }
+            }
+        }
+
+        match best {
+            Some((face, b)) => {
+                let face = &self.mesh.faces()[face as usize];
+                let (v0, v1, v2) = face.get_vertices(self.mesh.vertices());
+
+                let b = b.max_by_component(Vector3::zero());
+                let b = b / (b.x + b.y + b.z);
+
+                let point = v0.position * b.x + v1.position * b.y + v2.position * b.z;
+                let normal = v0.normal * b.x + v1.normal * b.y + v2.normal * b.z;
+                let normal = if normal.mag_sq() > 0.0 {
+                    normal.normalized()
+                } else {
+                    face.normal
+                };
+
+                offset_ray_towards(point, normal, normal)
+            }
+            None => Ray::empty(Vector3::zero(), Vector3::unit_y()),
+        }
+    }
+}
+
+#[test]
+fn rays_start_on_the_texels() {
+    use geometry::{Aabb, Face, ShadingMode, Vertex};
+
+    // a triangle on the xz plane, covering the lower left quarter of the texture space
+    let vertex = |x: Float, z: Float| Vertex {
+        position: Vector3::new(x, 0.0, z),
+        normal: Vector3::unit_y(),
+        uv: Vector2::new(x, z) / 2.0,
+    };
+    let vertices = vec![vertex(0.0, 0.0), vertex(1.0, 0.0), vertex(0.0, 1.0)];
+    let faces = vec![Face::new((0, 1, 2), Vector3::unit_y())];
+    let mesh = Mesh::new(vertices, faces, Aabb::empty(), ShadingMode::Phong, None);
+
+    let camera = LightmapCamera::new(
+        CameraSampler::NoOp,
+        Box::default(),
+        Arc::new(mesh),
+        UVec2::new(4, 4),
+    );
+
+    // uv (0.125, 0.125) at the bottom left
+    let ray = camera.primary_ray(UVec2::new(0, 3));
+    assert!(!ray.is_empty());
+    assert!((ray.origin - Vector3::new(0.25, 0.0, 0.25)).mag() < 1e-3);
+    assert!(ray.origin.y > 0.0);
+    assert_eq!(ray.direction, Vector3::unit_y());
+
+    // the texel overlapping the hypotenuse starts on its closest point
+    let ray = camera.primary_ray(UVec2::new(1, 2));
+    assert!((ray.origin - Vector3::new(0.5, 0.0, 0.5)).mag() < 1e-3);
+
+    // outside of the triangle's texels
+    assert!(camera.primary_ray(UVec2::new(3, 0)).is_empty());
+    assert!(camera.primary_ray(UVec2::new(2, 3)).is_empty());
+}
@@ -1,9 +1,20 @@
+mod animated;
+mod equirectangular;
+mod fisheye;
+mod lightmap;
 mod perspective;
+mod realistic;
 //pub mod perspective_simone;
 
+pub use animated::{AnimatedCamera, CameraKeyframe};
+pub use equirectangular::EquirectangularCamera;
+pub use fisheye::{FisheyeCamera, FisheyeProjection};
+pub use lightmap::LightmapCamera;
 pub use perspective::PerspectiveCamera;
+pub use realistic::{LensElement, RealisticCamera};
 
 use crate::filters::Filter;
+use crate::{Float, Vector2, Vector3};
 use dyn_clone::DynClone;
 use geometry::Ray;
 use ultraviolet::UVec2;
@@ -16,6 +27,9 @@ pub trait Camera: DynClone + Send + Sync {
 
     /// Creates a new primary ray of the given pixel.
     ///
+    /// Samples that do not leave the camera (e.g. blocked by a lens system) generate
+    /// [empty](Ray::empty) rays.
+    ///
     /// # Constraints
     /// * `pixel` - Should be within the camera's resolution.
     ///
@@ -25,4 +39,69 @@ pub trait Camera: DynClone + Send + Sync {
     /// # Returns
     /// * A ray
     fn primary_ray(&self, pixel: UVec2) -> Ray;
+
+    /// Creates a new primary ray of the given pixel at the given wavelength, for cameras whose
+    /// optics [disperse](Camera::is_dispersive) light.
+    ///
+    /// # Constraints
+    /// * `pixel` - Should be within the camera's resolution.
+    ///
+    /// # Arguments
+    /// * `pixel` - The pixel to generate the ray from
+    /// * `lambda` - The wavelength in **µm**
+    ///
+    /// # Returns
+    /// * A ray
+    fn primary_ray_wavelength(&self, pixel: UVec2, _lambda: Float) -> Ray {
+        self.primary_ray(pixel)
+    }
+
+    /// Returns whether the primary rays of this camera depend on the wavelength.
+    fn is_dispersive(&self) -> bool {
+        false
+    }
+
+    /// Connects the given point to the camera, projecting it onto the sensor.
+    ///
+    /// Cameras that cannot be connected to (e.g. not being a pinhole) return `None`.
+    ///
+    /// # Constraints
+    /// * `point` - All values should be finite (neither infinite nor `NaN`).
+    ///
+    /// # Arguments
+    /// * `point` - The point to connect
+    ///
+    /// # Returns
+    /// * The connection, or `None` if the point does not project onto the sensor
+    fn connect(&self, _point: Vector3) -> Option<CameraConnection> {
+        None
+    }
+
+    /// Returns a copy of this camera translated in its own frame, e.g. to navigate in the live
+    /// window. The directions of the primary rays stay the same.
+    ///
+    /// Cameras that cannot be moved return `None`.
+    ///
+    /// # Arguments
+    /// * `offset` - The translation along the right, up and viewing direction of the camera
+    ///
+    /// # Returns
+    /// * The translated camera, or `None` if not supported
+    fn translated(&self, _offset: Vector3) -> Option<Box<dyn Camera>> {
+        None
+    }
+}
+
+dyn_clone::clone_trait_object!(Camera);
+
+/// A connection of a point in the scene to the camera, as used by light tracing.
+#[derive(Copy, Clone, Debug)]
+pub struct CameraConnection {
+    /// The position on the sensor in raster space (pixel centers at `+0.5`)
+    pub raster: Vector2,
+    /// The position of the camera
+    pub eye: Vector3,
+    /// The importance of the pixel towards the point, converted to the area measure at the point
+    /// (excluding the cosine at the point)
+    pub importance: Float,
 }
@@ -1,8 +1,8 @@
-use crate::camera::Camera;
+use crate::camera::{Camera, CameraConnection};
 use crate::debug_utils::{is_finite, is_normalized};
 use crate::filters::Filter;
 use crate::samplers::camera::CameraSampler;
-use crate::{Float, Vector3};
+use crate::{Float, Vector2, Vector3};
 use geometry::Ray;
 use serde::de::{Error, MapAccess, Visitor};
 use serde::ser::SerializeStruct;
@@ -12,9 +12,10 @@ use ultraviolet::UVec2;
 use utility::floats::FloatExt;
 
 /// A perspective camera with a fov somewhere in space, looking at a target.
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct PerspectiveCamera {
     sampler: CameraSampler,
+    filter: Box<dyn Filter>,
     position: Vector3,
     target: Vector3,
     up: Vector3,
@@ -79,6 +80,7 @@ impl PerspectiveCamera {
 
         Self {
             sampler,
+            filter: Box::default(),
             position,
             target,
             up: axis_up,
@@ -89,6 +91,18 @@ impl PerspectiveCamera {
             lower_left,
         }
     }
+
+    /// Replaces the pixel filter, a box filter covering a single pixel by default.
+    ///
+    /// # Arguments
+    /// * `filter` - The pixel filter
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with_filter(mut self, filter: Box<dyn Filter>) -> Self {
+        self.filter = filter;
+        self
+    }
 }
 
 #[typetag::serde]
@@ -98,7 +112,7 @@ impl Camera for PerspectiveCamera {
     }
 
     fn get_filter(&self) -> &dyn Filter {
-        todo!()
+        self.filter.as_ref()
     }
 
     #[inline]
@@ -114,6 +128,69 @@ impl Camera for PerspectiveCamera {
 
         Ray::new_fast(self.position, direction.normalized())
     }
+
+    fn connect(&self, point: Vector3) -> Option<CameraConnection> {
+        debug_assert!(is_finite(point));
+
+        let view = self.target - self.position;
+        let distance = view.mag();
+        let view = view / distance;
+
+        let direction = point - self.position;
+        let distance_sq = direction.mag_sq();
+        let cos = direction.dot(view);
+        if cos <= 0.0 || distance_sq == 0.0 {
+            return None;
+        }
+
+        // intersect the image plane through the target
+        let on_plane = self.position + direction * (distance / cos) - self.lower_left;
+        let raster = Vector2::new(
+            on_plane.dot(self.x_dir) / self.x_dir.mag_sq(),
+            on_plane.dot(self.y_dir) / self.y_dir.mag_sq(),
+        );
+
+        if raster.x < 0.0
+            || raster.y < 0.0
+            || raster.x >= self.resolution.x as Float
+            || raster.y >= self.resolution.y as Float
+        {
+            return None;
+        }
+
+        // The pixels are uniformly sampled on the image plane. Converting the area of a pixel to
+        // the solid angle at the eye gives `d² / cos³`, and further to the area at the point
+        // `cos_point / r²`, leaving out the cosine at the point.
+        let cos = cos / distance_sq.sqrt();
+        let pixel_area = self.x_dir.mag() * self.y_dir.mag();
+        let importance = distance * distance / (pixel_area * cos * cos * cos * distance_sq);
+
+        Some(CameraConnection {
+            raster,
+            eye: self.position,
+            importance,
+        })
+    }
+
+    fn translated(&self, offset: Vector3) -> Option<Box<dyn Camera>> {
+        debug_assert!(is_finite(offset));
+
+        let view = (self.target - self.position).normalized();
+        let right = view.cross(self.up);
+        let offset = offset.x * right + offset.y * self.up + offset.z * view;
+
+        Some(Box::new(
+            PerspectiveCamera::new(
+                self.sampler,
+                self.position + offset,
+                self.target + offset,
+                self.up,
+                self.fovy,
+                self.resolution,
+            )
+            .with_filter(self.filter.clone()),
+        ))
+    }
 }
 
 impl Serialize for PerspectiveCamera {
@@ -121,8 +198,9 @@ impl Serialize for PerspectiveCamera {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("PerspectiveCamera", 6)?;
+        let mut state = serializer.serialize_struct("PerspectiveCamera", 7)?;
         state.serialize_field("Sampler", &self.sampler)?;
+        state.serialize_field("Filter", &self.filter)?;
         state.serialize_field("Position", &self.position)?;
         state.serialize_field("Target", &self.target)?;
         state.serialize_field("Up", &self.up)?;
@@ -140,6 +218,7 @@ impl<'de> Deserialize<'de> for PerspectiveCamera {
     {
         enum Field {
             Sampler,
+            Filter,
             Position,
             Target,
             Up,
@@ -159,7 +238,7 @@ impl<'de> Deserialize<'de> for PerspectiveCamera {
 
                     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
                         formatter.write_str(
-                            "`Sampler`, `Position`, `Target`, `Up`, `FovY` or `Resolution`",
+                            "`Sampler`, `Filter`, `Position`, `Target`, `Up`, `FovY` or `Resolution`",
                         )
                     }
 
@@ -169,6 +248,7 @@ impl<'de> Deserialize<'de> for PerspectiveCamera {
                     {
                         match v {
                             "Sampler" => Ok(Field::Sampler),
+                            "Filter" => Ok(Field::Filter),
                             "Position" => Ok(Field::Position),
                             "Target" => Ok(Field::Target),
                             "Up" => Ok(Field::Up),
@@ -197,6 +277,7 @@ impl<'de> Deserialize<'de> for PerspectiveCamera {
                 A: MapAccess<'de>,
             {
                 let mut sampler = None;
+                let mut filter = None;
                 let mut position = None;
                 let mut target = None;
                 let mut up = None;
@@ -211,6 +292,13 @@ impl<'de> Deserialize<'de> for PerspectiveCamera {
                                 sampler = Some(map.next_value()?)
                             }
                         }
+                        Field::Filter => {
+                            if filter.is_some() {
+                                return Err(de::Error::duplicate_field("Filter"));
+                            } else {
+                                filter = Some(map.next_value()?)
+                            }
+                        }
                         Field::Position => {
                             if position.is_some() {
                                 return Err(de::Error::duplicate_field("Position"));
@@ -256,13 +344,22 @@ impl<'de> Deserialize<'de> for PerspectiveCamera {
                 let fovy = fovy.ok_or_else(|| de::Error::invalid_length(0, &self))?;
                 let resolution = resolution.ok_or_else(|| de::Error::invalid_length(0, &self))?;
 
-                Ok(PerspectiveCamera::new(
-                    sampler, position, target, up, fovy, resolution,
-                ))
+                Ok(
+                    PerspectiveCamera::new(sampler, position, target, up, fovy, resolution)
+                        .with_filter(filter.unwrap_or_default()),
+                )
             }
         }
 
-        const FIELDS: &[&str] = &["Sampler", "Position", "Target", "Up", "FovY", "Resolution"];
+        const FIELDS: &[&str] = &[
+            "Sampler",
+            "Filter",
+            "Position",
+            "Target",
+            "Up",
+            "FovY",
+            "Resolution",
+        ];
         deserializer.deserialize_struct("PerspectiveCamera", FIELDS, CameraVisitor)
     }
 }
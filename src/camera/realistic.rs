@@ -0,0 +1,369 @@
+use crate::bxdf::refract;
+use crate::camera::Camera;
+use crate::debug_utils::{is_finite, is_normalized};
+use crate::filters::Filter;
+use crate::mc::sample_unit_disk_concentric;
+use crate::refractive_index::RefractiveType;
+use crate::samplers::camera::CameraSampler;
+use crate::samplers::Sampler;
+use crate::{Float, Vector3};
+use geometry::Ray;
+use serde::{Deserialize, Serialize};
+use ultraviolet::UVec2;
+use utility::math::solve_quadratic;
+
+/// The wavelength (in µm) of the Fraunhofer d-line, at which rays without a wavelength get traced
+/// and the lens system gets focused.
+const DESIGN_WAVELENGTH: Float = 0.5876;
+
+fn default_medium() -> RefractiveType {
+    RefractiveType::Air
+}
+
+fn default_unit() -> Float {
+    0.001
+}
+
+/// A spherical element of a lens system, as listed in lens prescriptions (in millimeters).
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct LensElement {
+    /// The radius of curvature, positive if the surface bulges towards the scene.
+    /// A radius of `0` describes the planar aperture stop.
+    pub curvature_radius: Float,
+    /// The distance to the next element towards the film
+    pub thickness: Float,
+    /// The diameter of the element
+    pub aperture: Float,
+    /// The medium between this and the next element towards the film
+    #[serde(default = "default_medium")]
+    pub medium: RefractiveType,
+}
+
+/// A camera tracing its primary rays through a system of spherical lens elements, producing
+/// physically based depth of field, vignetting and (chromatic) aberrations.
+///
+/// The elements are listed from the scene towards the film. The distance of the rear element to
+/// the film replaces its thickness and gets computed by focusing at the given distance.
+/// Rays blocked by the lens system are [empty](Ray::empty).
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "RealisticCameraSerde")]
+pub struct RealisticCamera {
+    sampler: CameraSampler,
+    filter: Box<dyn Filter>,
+    /// The center of the film
+    position: Vector3,
+    target: Vector3,
+    up: Vector3,
+    lenses: Vec<LensElement>,
+    /// The distance to focus at (in scene units), measured from the film
+    focus_distance: Float,
+    /// The diagonal of the film (in millimeters)
+    film_diagonal: Float,
+    /// The size of a millimeter in scene units
+    unit: Float,
+    resolution: UVec2,
+    #[serde(skip)]
+    film_distance: Float,
+    #[serde(skip)]
+    axes: (Vector3, Vector3, Vector3),
+}
+
+/// The serialized form of a [`RealisticCamera`](RealisticCamera), without the focused film
+/// distance.
+#[derive(Deserialize)]
+struct RealisticCameraSerde {
+    sampler: CameraSampler,
+    #[serde(default)]
+    filter: Box<dyn Filter>,
+    position: Vector3,
+    target: Vector3,
+    up: Vector3,
+    lenses: Vec<LensElement>,
+    focus_distance: Float,
+    film_diagonal: Float,
+    #[serde(default = "default_unit")]
+    unit: Float,
+    resolution: UVec2,
+}
+
+impl RealisticCamera {
+    /// Creates a new realistic camera, focusing the lens system at the given distance.
+    ///
+    /// # Constraints
+    /// * `position` - All values should be finite (neither infinite nor `NaN`).
+    /// * `target` - All values should be finite.
+    /// * `up` - All values should be finite.
+    ///   Should be normalized.
+    /// * `lenses` - Should not be empty.
+    /// * `focus_distance` - Should be greater than about `4` focal lengths of the lens system.
+    /// * `film_diagonal` - Should be greater than `0`.
+    /// * `unit` - Should be greater than `0`.
+    ///
+    /// # Arguments
+    /// * `sampler` - The pixel sampler
+    /// * `filter` - The pixel filter
+    /// * `position` - The center of the film
+    /// * `target` - Where the camera looks at
+    /// * `up` - The up vector of the camera
+    /// * `lenses` - The lens elements from the scene towards the film (in millimeters)
+    /// * `focus_distance` - The distance to focus at (in scene units)
+    /// * `film_diagonal` - The diagonal of the film (in millimeters)
+    /// * `unit` - The size of a millimeter in scene units
+    /// * `resolution` - The resolution of the camera
+    ///
+    /// # Returns
+    /// * Self
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        sampler: CameraSampler,
+        filter: Box<dyn Filter>,
+        position: Vector3,
+        target: Vector3,
+        up: Vector3,
+        lenses: Vec<LensElement>,
+        focus_distance: Float,
+        film_diagonal: Float,
+        unit: Float,
+        resolution: UVec2,
+    ) -> Self {
+        debug_assert!(is_finite(position));
+        debug_assert!(is_finite(target));
+        debug_assert!(is_finite(up));
+        debug_assert!(is_normalized(up));
+        debug_assert!(!lenses.is_empty());
+        debug_assert!(film_diagonal > 0.0);
+        debug_assert!(unit > 0.0);
+
+        let view = (target - position).normalized();
+        let axis_right = view.cross(up).normalized();
+        let axis_up = axis_right.cross(view);
+
+        let mut camera = Self {
+            sampler,
+            filter,
+            position,
+            target,
+            up,
+            lenses,
+            focus_distance,
+            film_diagonal,
+            unit,
+            resolution,
+            film_distance: 0.0,
+            axes: (axis_right, axis_up, view),
+        };
+        camera.film_distance = camera.focus(focus_distance / unit);
+
+        camera
+    }
+
+    /// Returns the position of the vertex of each element along the optical axis, with the film
+    /// at `0` and the scene towards positive values.
+    fn vertices(&self, film_distance: Float) -> Vec<Float> {
+        let mut vertices = vec![film_distance; self.lenses.len()];
+        for i in (0..self.lenses.len() - 1).rev() {
+            vertices[i] = vertices[i + 1] + self.lenses[i].thickness;
+        }
+
+        vertices
+    }
+
+    /// Traces the given ray (in millimeters) through the lens system.
+    ///
+    /// # Arguments
+    /// * `origin` - The origin of the ray
+    /// * `direction` - The normalized direction of the ray
+    /// * `vertices` - The vertices of the elements
+    /// * `lambda` - The wavelength (in µm)
+    ///
+    /// # Returns
+    /// * The origin and direction of the exiting ray, or `None` if it got blocked
+    fn trace(
+        &self,
+        mut origin: Vector3,
+        mut direction: Vector3,
+        vertices: &[Float],
+        lambda: Float,
+    ) -> Option<(Vector3, Vector3)> {
+        let from_film = direction.z > 0.0;
+        let n = self.lenses.len();
+
+        for step in 0..n {
+            let i = if from_film { n - 1 - step } else { step };
+            let element = &self.lenses[i];
+            let radius = element.curvature_radius;
+
+            let (t, normal) = if radius == 0.0 {
+                let t = (vertices[i] - origin.z) / direction.z;
+                (t, Vector3::unit_z())
+            } else {
+                let center = Vector3::new(0.0, 0.0, vertices[i] - radius);
+                let oc = origin - center;
+                let b = 2.0 * oc.dot(direction);
+                let c = oc.mag_sq() - radius * radius;
+                let (t0, t1) = solve_quadratic(1.0, b, c)?;
+
+                // the surface near the vertex is the first hit if it bulges away from the ray
+                let t = if (direction.z > 0.0) == (radius < 0.0) {
+                    t0
+                } else {
+                    t1
+                };
+
+                (t, (origin + direction * t - center).normalized())
+            };
+
+            if t <= 0.0 {
+                return None;
+            }
+
+            origin += direction * t;
+
+            let half_aperture = 0.5 * element.aperture;
+            if origin.x * origin.x + origin.y * origin.y > half_aperture * half_aperture {
+                return None;
+            }
+
+            if radius != 0.0 {
+                let eta_film = element.medium.n(lambda);
+                let eta_scene = if i == 0 {
+                    default_medium().n(lambda)
+                } else {
+                    self.lenses[i - 1].medium.n(lambda)
+                };
+                let eta = if from_film {
+                    eta_film / eta_scene
+                } else {
+                    eta_scene / eta_film
+                };
+
+                let normal = if normal.dot(direction) > 0.0 {
+                    -normal
+                } else {
+                    normal
+                };
+                direction = refract(-direction, normal, eta)?.normalized();
+            }
+        }
+
+        Some((origin, direction))
+    }
+
+    /// Computes the distance of the rear element to the film focusing at the given distance, by
+    /// approximating the lens system as a thick lens.
+    ///
+    /// The principal planes and focal points get found by tracing rays parallel to the optical
+    /// axis through the system from both sides.
+    fn focus(&self, distance: Float) -> Float {
+        let fallback = self.lenses[self.lenses.len() - 1].thickness;
+        let vertices = self.vertices(0.0);
+        let height = 0.05 * self.lenses[0].aperture;
+
+        // returns the focal point and principal plane of a parallel ray at the given height
+        let cardinal = |(origin, direction): (Vector3, Vector3)| {
+            let focal = origin.z - origin.x / direction.x * direction.z;
+            let principal = origin.z + (height - origin.x) / direction.x * direction.z;
+            (focal, principal)
+        };
+
+        let from_scene = Vector3::new(height, 0.0, vertices[0] + 1.0);
+        let from_film = Vector3::new(height, 0.0, -1.0);
+
+        let rear = self.trace(from_scene, -Vector3::unit_z(), &vertices, DESIGN_WAVELENGTH);
+        let front = self.trace(from_film, Vector3::unit_z(), &vertices, DESIGN_WAVELENGTH);
+
+        let ((rear_focal, rear_principal), (_, front_principal)) = match (rear, front) {
+            (Some(rear), Some(front)) if rear.1.x != 0.0 && front.1.x != 0.0 => {
+                (cardinal(rear), cardinal(front))
+            }
+            _ => return fallback,
+        };
+
+        let focal_length = rear_principal - rear_focal;
+
+        // Solve the thick lens equation `1 / s + 1 / s' = 1 / f` for the image distance `s'`
+        // behind the rear principal plane, with `s + s' = k`.
+        let k = distance - front_principal + rear_principal;
+        let discriminant = (k * k - 4.0 * k * focal_length).max(0.0);
+        let image_distance = 0.5 * (k - discriminant.sqrt());
+
+        let film_distance = image_distance - rear_principal;
+        if film_distance.is_finite() && film_distance > 0.0 {
+            film_distance
+        } else {
+            fallback
+        }
+    }
+}
+
+impl From<RealisticCameraSerde> for RealisticCamera {
+    fn from(serde: RealisticCameraSerde) -> Self {
+        Self::new(
+            serde.sampler,
+            serde.filter,
+            serde.position,
+            serde.target,
+            serde.up,
+            serde.lenses,
+            serde.focus_distance,
+            serde.film_diagonal,
+            serde.unit,
+            serde.resolution,
+        )
+    }
+}
+
+#[typetag::serde]
+impl Camera for RealisticCamera {
+    fn resolution(&self) -> UVec2 {
+        self.resolution
+    }
+
+    fn get_filter(&self) -> &dyn Filter {
+        self.filter.as_ref()
+    }
+
+    fn primary_ray(&self, pixel: UVec2) -> Ray {
+        self.primary_ray_wavelength(pixel, DESIGN_WAVELENGTH)
+    }
+
+    fn primary_ray_wavelength(&self, pixel: UVec2, lambda: Float) -> Ray {
+        debug_assert!(pixel == pixel.min_by_component(self.resolution));
+
+        let (axis_right, axis_up, view) = self.axes;
+        let sample = self.sampler.sample();
+
+        let w = self.resolution.x as Float;
+        let h = self.resolution.y as Float;
+        let pixel_size = self.film_diagonal / (w * w + h * h).sqrt();
+
+        // the lens system flips the image
+        let film = Vector3::new(
+            -(pixel.x as Float + sample.x - 0.5 * w) * pixel_size,
+            (pixel.y as Float + sample.y - 0.5 * h) * pixel_size,
+            0.0,
+        );
+
+        // aim at a uniformly sampled point on the rear element
+        let rear = &self.lenses[self.lenses.len() - 1];
+        let pupil = sample_unit_disk_concentric(Sampler::Random.get_2d()) * 0.5 * rear.aperture;
+        let pupil = Vector3::new(pupil.x, pupil.y, self.film_distance);
+
+        let vertices = self.vertices(self.film_distance);
+        match self.trace(film, (pupil - film).normalized(), &vertices, lambda) {
+            Some((origin, direction)) => {
+                let to_world = |v: Vector3| axis_right * v.x + axis_up * v.y + view * v.z;
+
+                Ray::new_fast(
+                    self.position + to_world(origin) * self.unit,
+                    to_world(direction).normalized(),
+                )
+            }
+            None => Ray::empty(self.position, view),
+        }
+    }
+
+    fn is_dispersive(&self) -> bool {
+        true
+    }
+}
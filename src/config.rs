@@ -1,5 +1,11 @@
+use crate::integrator::LightPathExpression;
+use crate::postprocessing::{Exposure, PostEffect};
+use crate::sensor::aov::Aov;
+use crate::sensor::band::SpectralBand;
 use crate::sensor::bounds::Bounds2;
+use crate::Float;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use ultraviolet::UVec2;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -9,4 +15,38 @@ pub struct Config {
     pub block_size: UVec2,
     pub passes: u32,
     pub threads: Option<u32>,
+    /// The stack size of each render thread in MiB, defaulting to 32 MiB.
+    #[serde(default)]
+    pub stack_size: Option<usize>,
+    /// Only renders the tiles with indices in `[start, end)`, allowing to split a render into
+    /// disjoint parts, e.g. across the processes of a render farm.
+    #[serde(default)]
+    pub tile_range: Option<(usize, usize)>,
+    /// Intensity multipliers of named light groups.
+    #[serde(default)]
+    pub light_groups: BTreeMap<String, Float>,
+    /// Whether to accumulate a separate buffer per light group.
+    #[serde(default)]
+    pub light_group_aovs: bool,
+    /// Named light path expressions to accumulate a separate buffer of matching contributions.
+    #[serde(default)]
+    pub light_paths: Vec<(String, LightPathExpression)>,
+    /// Post-processing effects applied in order to the HDR image.
+    #[serde(default)]
+    pub post_effects: Vec<PostEffect>,
+    /// The exposure of the LDR images, leaving the HDR image unscaled if not given.
+    #[serde(default)]
+    pub exposure: Option<Exposure>,
+    /// Narrow-band filters to additionally save one (linear, greyscale) image each.
+    #[serde(default)]
+    pub spectral_bands: Vec<SpectralBand>,
+    /// The relative tolerance to warn at if a tile senses more radiance than any light emits.
+    #[serde(default)]
+    pub energy_check: Option<Float>,
+    /// Whether to additionally save the world-space positions and normals as float images.
+    #[serde(default)]
+    pub geometry_aovs: bool,
+    /// Auxiliary outputs written by the integrators, each saved as a separate float image.
+    #[serde(default)]
+    pub aovs: Vec<Aov>,
 }
@@ -0,0 +1,344 @@
+//! Generates synthetic datasets, e.g. to train machine learning models on labeled images.
+//!
+//! A [dataset specification](DatasetSpec) extends a base scene file by objects and lights placed
+//! at random per frame. Each frame gets rendered together with its normal, depth and segmentation
+//! [AOVs](Aov), and an [annotation](Annotation) of the placements.
+
+use crate::bxdf::BSDF;
+use crate::mc::sample_unit_sphere;
+use crate::objects::{Emitter, Receiver, ReceiverGeometry, SceneObject};
+use crate::pack::invalid_data;
+use crate::sensor::aov::Aov;
+use crate::serialization::Serialization;
+use crate::{Float, Spectrum, Vector2, Vector3};
+use fastrand::Rng;
+use geometry::{Aabb, Boundable, Geometry, Instance, Sphere};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::TAU;
+#[cfg(feature = "f64")]
+use std::f64::consts::TAU;
+
+/// The number of attempts to place an object without overlapping the previously placed ones,
+/// before skipping it.
+const MAX_PLACEMENT_ATTEMPTS: u32 = 32;
+
+fn default_aovs() -> Vec<Aov> {
+    vec![Aov::Normal, Aov::Depth, Aov::Segmentation]
+}
+
+/// The specification of a synthetic dataset.
+#[derive(Deserialize)]
+pub struct DatasetSpec {
+    /// The base scene file (relative to the specification), providing the camera, integrator,
+    /// config and static objects
+    pub scene: String,
+    /// The number of frames to generate
+    pub frames: u32,
+    /// The seed of the randomization, the same seed generating the same frames
+    #[serde(default)]
+    pub seed: u64,
+    /// The region to place the centers of the objects in
+    pub region: Aabb,
+    /// The kinds of objects to place
+    pub objects: Vec<ObjectSpec>,
+    /// The lights to place in addition to the ones of the base scene
+    #[serde(default)]
+    pub lights: Option<LightSpec>,
+    /// The AOVs to save in addition to the ones of the base scene
+    #[serde(default = "default_aovs")]
+    pub aovs: Vec<Aov>,
+    #[serde(skip)]
+    directory: Option<PathBuf>,
+}
+
+/// A kind of object, placed with a random scale, rotation and material.
+#[derive(Deserialize)]
+pub struct ObjectSpec {
+    /// The geometry in object space, centered around the origin
+    pub geometry: Box<dyn Geometry>,
+    /// The segmentation label of all objects of this kind
+    pub label: u32,
+    /// The inclusive range of the number of objects per frame
+    pub count: (u32, u32),
+    /// The range of the uniform scale
+    #[serde(default = "unit_range")]
+    pub scale: (Float, Float),
+    /// Whether to only rotate around the `y` axis, e.g. for objects standing on the ground
+    #[serde(default)]
+    pub upright: bool,
+    /// The materials to choose from
+    pub materials: Vec<BSDF>,
+}
+
+fn unit_range() -> (Float, Float) {
+    (1.0, 1.0)
+}
+
+/// Spherical lights, placed with a random radius and emission.
+#[derive(Deserialize)]
+pub struct LightSpec {
+    /// The inclusive range of the number of lights per frame
+    pub count: (u32, u32),
+    /// The region to place the centers of the lights in
+    pub region: Aabb,
+    /// The range of the radius
+    pub radius: (Float, Float),
+    /// The emissions to choose from
+    pub emissions: Vec<Spectrum>,
+    /// The range of the intensity scaling the emission
+    #[serde(default = "unit_range")]
+    pub intensity: (Float, Float),
+}
+
+/// The placement of an object in a frame.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Placement {
+    /// The segmentation label
+    pub label: u32,
+    /// The index into the [objects](DatasetSpec::objects) of the specification
+    pub kind: usize,
+    /// The index into the [materials](ObjectSpec::materials) of the kind
+    pub material: usize,
+    pub center: Vector3,
+    pub scale: Float,
+    /// The rotation `(axis, angle)`
+    pub rotation: (Vector3, Float),
+    /// The world-space bounds
+    pub bounds: Aabb,
+}
+
+/// The placement of a light in a frame.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LightPlacement {
+    pub center: Vector3,
+    pub radius: Float,
+    /// The index into the [emissions](LightSpec::emissions) of the specification
+    pub emission: usize,
+    pub intensity: Float,
+}
+
+/// The ground truth of a generated frame, besides its AOVs.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Annotation {
+    pub frame: u32,
+    pub objects: Vec<Placement>,
+    pub lights: Vec<LightPlacement>,
+}
+
+/// Returns a uniform random value in the given range.
+#[inline]
+fn uniform(rng: &Rng, range: (Float, Float)) -> Float {
+    range.0 + (range.1 - range.0) * rng.f64() as Float
+}
+
+/// Returns a uniform random point inside the given bounds.
+#[inline]
+fn uniform_point(rng: &Rng, bounds: &Aabb) -> Vector3 {
+    let sample = Vector3::new(rng.f64() as Float, rng.f64() as Float, rng.f64() as Float);
+
+    bounds.min + bounds.size() * sample
+}
+
+#[cfg(not(feature = "static-dispatch"))]
+fn receiver_geometry(instance: Instance) -> ReceiverGeometry {
+    Box::new(instance)
+}
+
+#[cfg(feature = "static-dispatch")]
+fn receiver_geometry(instance: Instance) -> ReceiverGeometry {
+    instance.into()
+}
+
+impl DatasetSpec {
+    /// Loads the dataset specification of the given file.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the specification
+    ///
+    /// # Returns
+    /// * The specification
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        let directory = path.parent().map(Path::to_path_buf);
+
+        let mut spec: Self =
+            geometry::with_base_directory(directory.as_deref(), || ron::from_str(&content))
+                .map_err(|err| invalid_data(format!("{}: {}", path.display(), err)))?;
+        spec.directory = directory;
+
+        spec.validate()
+            .map_err(|err| invalid_data(format!("{}: {}", path.display(), err)))?;
+
+        Ok(spec)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (i, object) in self.objects.iter().enumerate() {
+            if object.count.0 > object.count.1 {
+                return Err(format!("object {} has an empty count range", i));
+            }
+            if object.materials.is_empty() {
+                return Err(format!("object {} has no materials", i));
+            }
+            if object.label == 0 {
+                return Err(format!("object {} uses the background label 0", i));
+            }
+        }
+
+        if let Some(lights) = &self.lights {
+            if lights.count.0 > lights.count.1 {
+                return Err("lights have an empty count range".to_string());
+            }
+            if lights.emissions.is_empty() {
+                return Err("lights have no emissions".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the path of the base scene file.
+    pub fn scene_path(&self) -> PathBuf {
+        match &self.directory {
+            Some(directory) => directory.join(&self.scene),
+            None => PathBuf::from(&self.scene),
+        }
+    }
+
+    /// Duplicates the given (trait) object of the specification by serializing it.
+    fn duplicate<T: Serialize + DeserializeOwned>(&self, value: &T) -> io::Result<T> {
+        let content = ron::to_string(value).map_err(invalid_data)?;
+
+        geometry::with_base_directory(self.directory.as_deref(), || ron::from_str(&content))
+            .map_err(invalid_data)
+    }
+
+    /// Generates the given frame by placing random objects and lights into the base scene.
+    ///
+    /// # Arguments
+    /// * `frame` - The index of the frame, seeding its randomization
+    ///
+    /// # Returns
+    /// * The scene of the frame, with the AOVs of the specification enabled
+    /// * The annotation of the placements
+    pub fn frame(&self, frame: u32) -> io::Result<(Serialization, Annotation)> {
+        let path = self.scene_path();
+        let content = fs::read_to_string(&path)?;
+        let mut serialization = Serialization::parse(&content, Some(&path))
+            .map_err(|err| invalid_data(format!("{}: {}", path.display(), err)))?;
+
+        for aov in &self.aovs {
+            if !serialization.config.aovs.contains(aov) {
+                serialization.config.aovs.push(*aov);
+            }
+        }
+
+        let rng = Rng::with_seed(self.seed.wrapping_add(frame as u64));
+        let mut annotation = Annotation {
+            frame,
+            ..Default::default()
+        };
+
+        for (kind, object) in self.objects.iter().enumerate() {
+            for _ in 0..rng.u32(object.count.0..=object.count.1) {
+                let placed = self.place(&rng, kind, object, &annotation.objects)?;
+
+                if let Some((receiver, placement)) = placed {
+                    serialization
+                        .scene
+                        .add(SceneObject::Receiver(Arc::new(receiver)));
+                    annotation.objects.push(placement);
+                }
+            }
+        }
+
+        if let Some(lights) = &self.lights {
+            for _ in 0..rng.u32(lights.count.0..=lights.count.1) {
+                let light = LightPlacement {
+                    center: uniform_point(&rng, &lights.region),
+                    radius: uniform(&rng, lights.radius),
+                    emission: rng.usize(..lights.emissions.len()),
+                    intensity: uniform(&rng, lights.intensity),
+                };
+
+                let emitter = Emitter::new(
+                    Box::new(Sphere::new(light.center, light.radius)),
+                    BSDF::default(),
+                    lights.emissions[light.emission] * light.intensity,
+                );
+
+                serialization
+                    .scene
+                    .add(SceneObject::Emitter(Arc::new(emitter)));
+                annotation.lights.push(light);
+            }
+        }
+
+        Ok((serialization, annotation))
+    }
+
+    /// Places an object of the given kind at random, without overlapping the already placed
+    /// ones.
+    ///
+    /// # Returns
+    /// * The receiver and its placement, or `None` if no free place got found
+    fn place(
+        &self,
+        rng: &Rng,
+        kind: usize,
+        object: &ObjectSpec,
+        placed: &[Placement],
+    ) -> io::Result<Option<(Receiver, Placement)>> {
+        for _ in 0..MAX_PLACEMENT_ATTEMPTS {
+            let scale = uniform(rng, object.scale);
+            let axis = if object.upright {
+                Vector3::unit_y()
+            } else {
+                sample_unit_sphere(Vector2::new(rng.f64() as Float, rng.f64() as Float))
+            };
+            let angle = rng.f64() as Float * TAU;
+            let center = uniform_point(rng, &self.region);
+
+            let instance = Instance::new(
+                self.duplicate(&object.geometry)?,
+                Some(scale),
+                Some((axis, angle)),
+                Some(center),
+            );
+            let bounds = instance.bounds();
+
+            if placed.iter().any(|p| p.bounds.overlaps(&bounds)) {
+                continue;
+            }
+
+            let material = rng.usize(..object.materials.len());
+            let mut receiver = Receiver::new(
+                receiver_geometry(instance),
+                self.duplicate(&object.materials[material])?,
+            );
+            receiver.label = object.label;
+
+            let placement = Placement {
+                label: object.label,
+                kind,
+                material,
+                center,
+                scale,
+                rotation: (axis, angle),
+                bounds,
+            };
+
+            return Ok(Some((receiver, placement)));
+        }
+
+        Ok(None)
+    }
+}
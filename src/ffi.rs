@@ -0,0 +1,343 @@
+//! A minimal C API to embed the renderer into non-Rust applications, enabled by the `ffi` feature.
+//!
+//! The declarations are in `include/rust_v.h`. Functions failing return `NULL` or a negative
+//! value, describing the error in [rust_v_last_error].
+
+use crate::renderer::{RenderJob, Renderer};
+use crate::serialization::Serialization;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_double, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(err: String) {
+    let err = CString::new(err.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(err));
+}
+
+/// Runs the given function, turning errors and panics into the given failure value.
+fn guard<T, F>(failure: T, f: F) -> T
+where
+    F: FnOnce() -> Result<T, String>,
+{
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(err)) => {
+            set_last_error(err);
+            failure
+        }
+        Err(_) => {
+            set_last_error("Rust-V panicked".to_string());
+            failure
+        }
+    }
+}
+
+/// A renderer with its (optional) running render job.
+pub struct RustVRenderer {
+    renderer: Renderer,
+    job: Option<RenderJob<()>>,
+}
+
+/// Returns the message of the last error on the calling thread, or `NULL` if none occurred.
+///
+/// The message stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn rust_v_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Creates a renderer of the given scene file content.
+///
+/// # Safety
+/// `scene` must be a valid, nul-terminated UTF-8 string.
+///
+/// # Arguments
+/// * `scene` - The content of the scene file (RON), relative paths getting resolved against the
+///             working directory
+///
+/// # Returns
+/// * The renderer to free with [rust_v_renderer_free], or `NULL` on errors
+#[no_mangle]
+pub unsafe extern "C" fn rust_v_renderer_new(scene: *const c_char) -> *mut RustVRenderer {
+    guard(ptr::null_mut(), || {
+        if scene.is_null() {
+            return Err("The scene is NULL".to_string());
+        }
+
+        let content = CStr::from_ptr(scene)
+            .to_str()
+            .map_err(|err| format!("The scene is not UTF-8: {}", err))?;
+        let serialization = Serialization::parse(content, None)
+            .map_err(|err| format!("Could not parse scene file: {}", err))?;
+
+        let renderer = RustVRenderer {
+            renderer: Renderer::new(serialization),
+            job: None,
+        };
+
+        Ok(Box::into_raw(Box::new(renderer)))
+    })
+}
+
+/// Frees the given renderer, stopping its render job.
+///
+/// # Safety
+/// `renderer` must be `NULL` or returned by [rust_v_renderer_new], and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rust_v_renderer_free(renderer: *mut RustVRenderer) {
+    if renderer.is_null() {
+        return;
+    }
+
+    let renderer = Box::from_raw(renderer);
+    if let Some(job) = renderer.job {
+        let _ = job.stop();
+    }
+}
+
+/// Starts rendering in background threads, or resumes it after [stopping](rust_v_renderer_stop).
+///
+/// # Safety
+/// `renderer` must be returned by [rust_v_renderer_new].
+///
+/// # Returns
+/// * `0` on success, `-1` on errors
+#[no_mangle]
+pub unsafe extern "C" fn rust_v_renderer_start(renderer: *mut RustVRenderer) -> c_int {
+    guard(-1, || {
+        let renderer = renderer.as_mut().ok_or("The renderer is NULL")?;
+        if renderer.job.is_some() || renderer.renderer.is_done() {
+            return Err("The renderer is already rendering or done".to_string());
+        }
+
+        renderer.job = Some(renderer.renderer.render());
+
+        Ok(0)
+    })
+}
+
+/// Stops rendering, waiting for the render threads to finish their current tile.
+///
+/// # Safety
+/// `renderer` must be returned by [rust_v_renderer_new].
+///
+/// # Returns
+/// * `0` on success, `-1` on errors
+#[no_mangle]
+pub unsafe extern "C" fn rust_v_renderer_stop(renderer: *mut RustVRenderer) -> c_int {
+    guard(-1, || {
+        let renderer = renderer.as_mut().ok_or("The renderer is NULL")?;
+
+        if let Some(job) = renderer.job.take() {
+            job.stop().map_err(|_| "A render thread panicked")?;
+        }
+
+        Ok(0)
+    })
+}
+
+/// Waits until rendering finished.
+///
+/// # Safety
+/// `renderer` must be returned by [rust_v_renderer_new].
+///
+/// # Returns
+/// * `0` on success, `-1` on errors
+#[no_mangle]
+pub unsafe extern "C" fn rust_v_renderer_wait(renderer: *mut RustVRenderer) -> c_int {
+    guard(-1, || {
+        let renderer = renderer.as_mut().ok_or("The renderer is NULL")?;
+
+        if let Some(job) = renderer.job.take() {
+            job.join().map_err(|_| "A render thread panicked")?;
+        }
+
+        Ok(0)
+    })
+}
+
+/// Returns the progress of rendering.
+///
+/// # Safety
+/// `renderer` must be returned by [rust_v_renderer_new].
+///
+/// # Returns
+/// * The fraction of rendered tiles in `[0, 1]`, or `-1` on errors
+#[no_mangle]
+pub unsafe extern "C" fn rust_v_renderer_progress(renderer: *const RustVRenderer) -> c_double {
+    guard(-1.0, || {
+        let renderer = renderer.as_ref().ok_or("The renderer is NULL")?;
+
+        Ok(renderer.renderer.progress_fraction())
+    })
+}
+
+/// Writes the resolution of the rendered image into `width` and `height`.
+///
+/// # Safety
+/// `renderer` must be returned by [rust_v_renderer_new]. `width` and `height` must be valid
+/// pointers.
+///
+/// # Returns
+/// * `0` on success, `-1` on errors
+#[no_mangle]
+pub unsafe extern "C" fn rust_v_renderer_resolution(
+    renderer: *const RustVRenderer,
+    width: *mut u32,
+    height: *mut u32,
+) -> c_int {
+    guard(-1, || {
+        let renderer = renderer.as_ref().ok_or("The renderer is NULL")?;
+        if width.is_null() || height.is_null() {
+            return Err("The resolution pointers are NULL".to_string());
+        }
+
+        let resolution = renderer.renderer.image_resolution();
+        *width = resolution.x;
+        *height = resolution.y;
+
+        Ok(0)
+    })
+}
+
+/// Copies the current image as 8-bit sRGB with (linear) alpha, row by row, into the given buffer.
+/// While rendering, the image shows the progress so far.
+///
+/// # Safety
+/// `renderer` must be returned by [rust_v_renderer_new]. `buffer` must be valid for writing `len`
+/// bytes.
+///
+/// # Arguments
+/// * `renderer` - The renderer
+/// * `buffer` - The buffer to copy into
+/// * `len` - The length of the buffer, at least `4 * width * height`
+///
+/// # Returns
+/// * `0` on success, `-1` on errors
+#[no_mangle]
+pub unsafe extern "C" fn rust_v_renderer_copy_rgba(
+    renderer: *const RustVRenderer,
+    buffer: *mut u8,
+    len: usize,
+) -> c_int {
+    guard(-1, || {
+        let renderer = &renderer.as_ref().ok_or("The renderer is NULL")?.renderer;
+        if buffer.is_null() {
+            return Err("The buffer is NULL".to_string());
+        }
+
+        let image = renderer.get_image_u8();
+        let pixels = (image.width() * image.height()) as usize;
+        if len < 4 * pixels {
+            return Err(format!("The buffer needs at least {} bytes", 4 * pixels));
+        }
+
+        let alpha = if renderer.has_holdouts() {
+            Some(renderer.get_alpha_image_u8())
+        } else {
+            None
+        };
+
+        let buffer = std::slice::from_raw_parts_mut(buffer, 4 * pixels);
+        for (i, (rgba, rgb)) in buffer.chunks_exact_mut(4).zip(image.pixels()).enumerate() {
+            rgba[..3].copy_from_slice(&rgb.0);
+            rgba[3] = alpha.as_ref().map_or(u8::MAX, |a| a.as_raw()[i]);
+        }
+
+        Ok(0)
+    })
+}
+
+#[test]
+fn renders_through_the_c_api() {
+    let last_error = || unsafe { CStr::from_ptr(rust_v_last_error()).to_str().unwrap() };
+
+    unsafe {
+        assert!(rust_v_renderer_new(ptr::null()).is_null());
+        assert_eq!("The scene is NULL", last_error());
+        assert_eq!(-1, rust_v_renderer_start(ptr::null_mut()));
+        assert_eq!("The renderer is NULL", last_error());
+        assert_eq!(-1.0, rust_v_renderer_progress(ptr::null()));
+        rust_v_renderer_free(ptr::null_mut());
+
+        let invalid = CString::new("(").unwrap();
+        assert!(rust_v_renderer_new(invalid.as_ptr()).is_null());
+        assert!(last_error().starts_with("Could not parse scene file"));
+    }
+
+    let scene = CString::new(
+        r#"(
+            config: (
+                filename: None,
+                bounds: None,
+                block_size: (x: 4, y: 4),
+                passes: 1,
+                threads: Some(1),
+            ),
+            camera: {
+                "PerspectiveCamera": (
+                    Sampler: Random,
+                    Position: (x: 0, y: 0, z: 4),
+                    Target: (x: 0, y: 0, z: 0),
+                    Up: (x: 0, y: 1, z: 0),
+                    FovY: 40,
+                    Resolution: (x: 4, y: 2),
+                ),
+            },
+            integrator: {"Path": (max_depth: 2)},
+            sampler: Random,
+            scene: (
+                bounding_box: (
+                    min: (x: -1, y: -1, z: -1),
+                    max: (x: 1, y: 1, z: 1),
+                ),
+                objects: [],
+            ),
+        )"#,
+    )
+    .unwrap();
+
+    unsafe {
+        let renderer = rust_v_renderer_new(scene.as_ptr());
+        assert!(!renderer.is_null());
+
+        let (mut width, mut height) = (0, 0);
+        assert_eq!(
+            0,
+            rust_v_renderer_resolution(renderer, &mut width, &mut height)
+        );
+        assert_eq!((4, 2), (width, height));
+        assert_eq!(
+            -1,
+            rust_v_renderer_resolution(renderer, ptr::null_mut(), &mut height)
+        );
+
+        assert_eq!(0, rust_v_renderer_start(renderer));
+        assert_eq!(-1, rust_v_renderer_start(renderer));
+        assert_eq!(0, rust_v_renderer_wait(renderer));
+        assert_eq!(1.0, rust_v_renderer_progress(renderer));
+
+        let mut buffer = [0u8; 4 * 4 * 2];
+        assert_eq!(
+            -1,
+            rust_v_renderer_copy_rgba(renderer, buffer.as_mut_ptr(), 4)
+        );
+        assert_eq!("The buffer needs at least 32 bytes", last_error());
+        assert_eq!(
+            0,
+            rust_v_renderer_copy_rgba(renderer, buffer.as_mut_ptr(), buffer.len())
+        );
+        // the empty scene stays black and opaque
+        assert!(buffer
+            .chunks_exact(4)
+            .all(|rgba| rgba == [0, 0, 0, u8::MAX]));
+
+        rust_v_renderer_free(renderer);
+    }
+}
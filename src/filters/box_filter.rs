@@ -26,4 +26,9 @@ impl Filter for BoxFilter {
             1.0
         }
     }
+
+    #[inline]
+    fn radius(&self) -> Vector2 {
+        self.radius
+    }
 }
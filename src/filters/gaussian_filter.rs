@@ -29,4 +29,9 @@ impl Filter for GaussianFilter {
     fn evaluate(&self, point: Vector2) -> Float {
         self.gaussian(point.x, self.exp.x) * self.gaussian(point.y, self.exp.y)
     }
+
+    #[inline]
+    fn radius(&self) -> Vector2 {
+        self.radius
+    }
 }
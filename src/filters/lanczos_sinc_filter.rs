@@ -37,4 +37,9 @@ impl Filter for LanczosSincFilter {
     fn evaluate(&self, point: Vector2) -> Float {
         self.windowed_sinc(point.x, self.radius.x) * self.windowed_sinc(point.y, self.radius.y)
     }
+
+    #[inline]
+    fn radius(&self) -> Vector2 {
+        self.radius
+    }
 }
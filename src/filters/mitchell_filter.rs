@@ -48,4 +48,9 @@ impl Filter for MitchellFilter {
         let var = point * self.inv_radius;
         self.mitchell_1d(var.x) * self.mitchell_1d(var.y)
     }
+
+    #[inline]
+    fn radius(&self) -> Vector2 {
+        Vector2::one() / self.inv_radius
+    }
 }
@@ -4,6 +4,7 @@ mod lanczos_sinc_filter;
 mod mitchell_filter;
 mod triangle_filter;
 use crate::*;
+use dyn_clone::DynClone;
 
 pub use box_filter::*;
 pub use gaussian_filter::*;
@@ -12,7 +13,7 @@ pub use mitchell_filter::*;
 pub use triangle_filter::*;
 
 #[typetag::serde]
-pub trait Filter {
+pub trait Filter: DynClone + Send + Sync {
     /// Evaluates a relative sample point to filter center position.
     ///
     /// # Constraints
@@ -25,4 +26,19 @@ pub trait Filter {
     /// # Returns
     /// * The evaluated value
     fn evaluate(&self, point: Vector2) -> Float;
+
+    /// Returns the radius (extent in each direction) of this filter.
+    ///
+    /// # Returns
+    /// * The radius
+    fn radius(&self) -> Vector2;
+}
+
+dyn_clone::clone_trait_object!(Filter);
+
+impl Default for Box<dyn Filter> {
+    /// A box filter covering a single pixel.
+    fn default() -> Self {
+        Box::new(BoxFilter::new(Vector2::broadcast(0.5)))
+    }
 }
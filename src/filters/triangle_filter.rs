@@ -23,4 +23,9 @@ impl Filter for TriangleFilter {
         Float::fast_max(0.0, self.radius.x - point.x.abs())
             * Float::fast_max(0.0, self.radius.y - point.y.abs())
     }
+
+    #[inline]
+    fn radius(&self) -> Vector2 {
+        self.radius
+    }
 }
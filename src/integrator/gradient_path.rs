@@ -0,0 +1,285 @@
+use crate::bxdf::Type;
+use crate::camera::Camera;
+use crate::integrator::{clamp_indirect, Integrator};
+use crate::objects::SceneObject;
+use crate::postprocessing::Frame;
+use crate::samplers::Sampler;
+use crate::scene::Scene;
+use crate::sensor::pixel::Pixel;
+use crate::sensor::Sensor;
+use crate::{Float, Spectrum};
+use color::Color;
+use geometry::Ray;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ultraviolet::UVec2;
+
+fn default_alpha() -> Float {
+    0.2
+}
+
+fn default_iterations() -> u32 {
+    50
+}
+
+/// A gradient-domain path tracer (Kettunen et al. 2015), additionally estimating the finite
+/// differences towards the next pixel in `x` and `y` and reconstructing the image from them by a
+/// screened Poisson solve.
+///
+/// The differences get estimated by the random replay shift: the paths of the neighbouring
+/// pixels are traced with the same random numbers as the base path, such that smooth regions
+/// yield small, low-variance gradients. Without media or MIS, the base paths are the ones of a
+/// path tracer sampling all lights at each bounce.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GradientPath {
+    max_depth: u32,
+    /// The weight of the pixel averages against the gradients in the reconstruction
+    #[serde(default = "default_alpha")]
+    alpha: Float,
+    /// The number of Gauss-Seidel iterations of the reconstruction
+    #[serde(default = "default_iterations")]
+    iterations: u32,
+    /// The maximal value of contributions of indirect bounces, suppressing fireflies.
+    #[serde(default)]
+    indirect_clamp: Option<Float>,
+    #[serde(skip)]
+    camera: Option<Arc<dyn Camera>>,
+}
+
+impl GradientPath {
+    /// Creates a new gradient-domain path tracer.
+    ///
+    /// # Constraints
+    /// * `alpha` - Should be greater than `0`.
+    ///
+    /// # Arguments
+    /// * `max_depth` - The depth of light bounces
+    /// * `alpha` - The weight of the pixel averages against the gradients
+    /// * `iterations` - The number of iterations of the reconstruction
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(max_depth: u32, alpha: Float, iterations: u32) -> Self {
+        debug_assert!(alpha > 0.0);
+
+        Self {
+            max_depth,
+            alpha,
+            iterations,
+            indirect_clamp: None,
+            camera: None,
+        }
+    }
+
+    /// Clamps the contributions of indirect bounces to the given maximal value, trading bias for
+    /// the suppression of fireflies.
+    ///
+    /// # Constraints
+    /// * `indirect_clamp` - Should be greater than `0`.
+    ///
+    /// # Arguments
+    /// * `indirect_clamp` - The maximal value of indirect contributions
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with_indirect_clamp(mut self, indirect_clamp: Float) -> Self {
+        debug_assert!(indirect_clamp > 0.0);
+
+        self.indirect_clamp = Some(indirect_clamp);
+        self
+    }
+
+    /// Clamps the contribution if it is indirect.
+    fn clamp(&self, indirect: bool, contribution: Spectrum) -> Spectrum {
+        if indirect {
+            clamp_indirect(contribution, self.indirect_clamp)
+        } else {
+            contribution
+        }
+    }
+
+    /// Traces a path sampling all lights at each bounce.
+    fn radiance(&self, scene: &Scene, primary_ray: &Ray, sampler: Sampler) -> Spectrum {
+        let mut illumination = Spectrum::broadcast(0.0);
+        let mut throughput = Spectrum::broadcast(1.0);
+        let mut ray = *primary_ray;
+        // emitters hit after non-specular bounces are covered by light sampling
+        let mut specular = true;
+
+        for depth in 0..self.max_depth {
+            let hit = match scene.intersect(&ray) {
+                Some(hit) => hit,
+                None => {
+                    illumination += self.clamp(depth >= 2, throughput * scene.ambient());
+                    break;
+                }
+            };
+
+            if let SceneObject::Emitter(e) = &hit.object {
+                if specular {
+                    illumination += self.clamp(depth >= 2, throughput * e.emission());
+                }
+            }
+
+            let bsdf = hit.object.bsdf();
+            if bsdf.is_empty() {
+                break;
+            }
+
+            let outgoing = -ray.direction;
+
+            for light in &scene.emitters {
+                if !light.is_linked(&hit.object) {
+                    continue;
+                }
+
+                let sample = light.sample(hit.point, sampler.get_2d());
+                if sample.pdf <= 0.0
+                    || sample.radiance.is_black()
+                    || !sample.occlusion_tester.unoccluded(scene)
+                {
+                    continue;
+                }
+
+                let bsdf_spectrum =
+                    bsdf.evaluate(hit.frame(), sample.incident, outgoing, Type::ALL);
+                let cos = sample.incident.dot(hit.normal).abs();
+
+                let contribution =
+                    throughput * bsdf_spectrum * sample.radiance * (cos / sample.pdf);
+                illumination += self.clamp(depth >= 1, contribution);
+            }
+
+            let bxdf_sample =
+                match bsdf.sample(hit.frame(), outgoing, Type::ALL, sampler.get_sample()) {
+                    Some(sample) if sample.pdf > 0.0 && !sample.spectrum.is_black() => sample,
+                    _ => break,
+                };
+
+            specular = bxdf_sample.typ.is_specular();
+            let cos = if specular {
+                // division of cosine omitted in specular bxdfs
+                1.0
+            } else {
+                bxdf_sample.incident.dot(hit.normal).abs()
+            };
+
+            throughput *= bxdf_sample.spectrum * (cos / bxdf_sample.pdf);
+            ray = scene.offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
+        }
+
+        illumination
+    }
+}
+
+#[typetag::serde]
+impl Integrator for GradientPath {
+    fn integrate(&self, pixel: &mut Pixel, scene: &Scene, primary_ray: &Ray, sampler: Sampler) {
+        let camera = match &self.camera {
+            Some(camera) => camera,
+            None => {
+                pixel.add(self.radiance(scene, primary_ray, sampler));
+                return;
+            }
+        };
+
+        // Replays the random numbers of the camera and the sampler for the shifted paths. The
+        // primary ray gets generated again, such that its pixel offset gets replayed as well.
+        let seed = fastrand::u64(..);
+        let next_seed = fastrand::u64(..);
+        let position = pixel.position;
+        let resolution = camera.resolution();
+
+        fastrand::seed(seed);
+        let base = self.radiance(scene, &camera.primary_ray(position), sampler);
+
+        let shift = |neighbour: UVec2| {
+            fastrand::seed(seed);
+            self.radiance(scene, &camera.primary_ray(neighbour), sampler) - base
+        };
+
+        let dx = if position.x + 1 < resolution.x {
+            shift(UVec2::new(position.x + 1, position.y))
+        } else {
+            Spectrum::broadcast(0.0)
+        };
+        let dy = if position.y + 1 < resolution.y {
+            shift(UVec2::new(position.x, position.y + 1))
+        } else {
+            Spectrum::broadcast(0.0)
+        };
+
+        fastrand::seed(next_seed);
+
+        pixel.add(base);
+        pixel.add_gradients(dx, dy);
+    }
+
+    fn init(&mut self, camera: &(dyn Camera + 'static)) {
+        self.camera = Some(Arc::from(dyn_clone::clone_box(camera)));
+    }
+
+    /// Solves the screened Poisson equation by Gauss-Seidel iterations, starting at the pixel
+    /// averages. Gradients towards pixels outside the sensor bounds get ignored.
+    fn reconstruct(&self, frame: &mut Frame, sensor: &Sensor) {
+        let (width, height) = (frame.width as usize, frame.height as usize);
+        let bounds = sensor.bounds;
+
+        let mut gradients = vec![None; width * height];
+        for tile in &sensor.tiles {
+            for px in &tile.lock().pixels {
+                if let [dx, dy] = px.gradients[..] {
+                    let x = (px.position.x - bounds.min.x) as usize;
+                    let y = (px.position.y - bounds.min.y) as usize;
+                    gradients[x + y * width] = Some((dx, dy));
+                }
+            }
+        }
+
+        if gradients.iter().all(Option::is_none) {
+            return;
+        }
+
+        let alpha_sq = self.alpha * self.alpha;
+        let primal = frame.pixels.clone();
+        let image = &mut frame.pixels;
+
+        for _ in 0..self.iterations {
+            for y in 0..height {
+                for x in 0..width {
+                    let i = x + y * width;
+                    let mut sum = primal[i] * alpha_sq;
+                    let mut weight = alpha_sq;
+
+                    // each gradient estimates the next pixel minus the current one
+                    if x + 1 < width {
+                        if let Some((dx, _)) = gradients[i] {
+                            sum += image[i + 1] - dx;
+                            weight += 1.0;
+                        }
+                    }
+                    if x > 0 {
+                        if let Some((dx, _)) = gradients[i - 1] {
+                            sum += image[i - 1] + dx;
+                            weight += 1.0;
+                        }
+                    }
+                    if y + 1 < height {
+                        if let Some((_, dy)) = gradients[i] {
+                            sum += image[i + width] - dy;
+                            weight += 1.0;
+                        }
+                    }
+                    if y > 0 {
+                        if let Some((_, dy)) = gradients[i - width] {
+                            sum += image[i - width] + dy;
+                            weight += 1.0;
+                        }
+                    }
+
+                    image[i] = sum / weight;
+                }
+            }
+        }
+    }
+}
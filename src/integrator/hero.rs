@@ -1,30 +1,36 @@
 use crate::bxdf::{BxDFSampleResult, Type};
 use crate::integrator::{
-    direct_illumination_buf, direct_illumination_wavelength, DirectLightStrategy, Integrator,
-    SpectralPathSingle,
+    default_ray_budget, direct_illumination_buf, trace_scattered_bundle, DirectLightStrategy,
+    Integrator, PathState, RayBudget,
 };
-use crate::objects::SceneObject;
 use crate::samplers::spectral_samplers::SpectralSampler;
 use crate::samplers::Sampler;
 use crate::scene::{Scene, SceneIntersection};
 use crate::sensor::pixel::Pixel;
 use crate::Float;
-use geometry::{offset_ray_towards, Ray};
+use geometry::Ray;
 use serde::{Deserialize, Serialize};
 
+/// A spectral path tracer following a bundle of wavelengths spread around a hero wavelength.
+///
+/// When dispersion scatters the bundle apart, each wavelength continues on its own sub-path,
+/// bounded by the ray budget of the trace.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Hero {
     max_depth: u32,
     light_wave_samples: u32,
     direct_light_strategy: DirectLightStrategy,
+    /// The maximum number of rays a path spends on following wavelengths on their own, after
+    /// dispersion scattered them apart
+    #[serde(default = "default_ray_budget")]
+    ray_budget: u32,
 }
 
 impl Hero {
-    #[allow(clippy::needless_range_loop)] // clippy is stupid here
     fn trace(
         &self,
         scene: &Scene,
-        mut hit: SceneIntersection,
+        hit: SceneIntersection,
         sampler: Sampler,
         indices: &[usize],
         illumination: &mut [Float],
@@ -34,98 +40,66 @@ impl Hero {
         assert_eq!(buf_size, illumination.len());
         assert_eq!(buf_size, throughput.len());
 
-        let mut specular = false;
-        for bounce in 0..self.max_depth {
-            let outgoing = -hit.ray.direction;
-            let normal = hit.normal;
+        let mut state = PathState::new(hit, throughput);
+        let mut budget = RayBudget::new(self.ray_budget);
+
+        while state.bounce < self.max_depth {
+            let hit = &state.hit;
             let bsdf = hit.object.bsdf();
 
-            // immediately hitting emitter?
-            if bounce == 0 || specular {
-                if let SceneObject::Emitter(e) = &hit.object {
-                    for i in 0..buf_size {
-                        illumination[i] = throughput[i] * e.emission[i];
-                    }
-                    break;
-                }
-            }
+            state.add_emission(indices, illumination);
 
             // add direction illumination
             direct_illumination_buf(
                 scene,
                 sampler,
                 self.direct_light_strategy,
-                &hit,
+                hit,
                 bsdf,
                 indices,
                 illumination,
-                throughput,
+                state.throughput,
             );
 
-            if let Some(spectral_sample) =
-                bsdf.sample_buf(normal, outgoing, Type::ALL, sampler.get_sample(), indices)
-            {
-                match spectral_sample {
-                    BxDFSampleResult::Bundle(bxdf_sample) => {
-                        if bxdf_sample.pdf == 0.0 || bxdf_sample.spectrum.iter().all(|&s| s == 0.0)
-                        {
-                            break;
-                        }
-
-                        specular = bxdf_sample.typ.is_specular();
-                        let cos_abs = if specular {
-                            // division of cosine omitted in specular bxdfs
-                            1.0
-                        } else {
-                            bxdf_sample.incident.dot(normal).abs()
-                        };
-
-                        for i in 0..buf_size {
-                            throughput[i] *= bxdf_sample.spectrum[i] * cos_abs / bxdf_sample.pdf;
-                        }
-
-                        let ray = offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
-                        match scene.intersect(&ray) {
-                            Some(i) => hit = i,
-                            None => break,
-                        }
+            let spectral_sample = match bsdf.sample_buf(
+                hit.frame(),
+                -hit.ray.direction,
+                Type::ALL,
+                sampler.get_sample(),
+                indices,
+            ) {
+                Some(s) => s,
+                None => break,
+            };
+
+            match spectral_sample {
+                BxDFSampleResult::Bundle(bxdf_sample) => {
+                    if !state.attenuate(&bxdf_sample) {
+                        break;
                     }
-                    BxDFSampleResult::ScatteredBundle(bundle) => {
-                        let hero = if !bundle.is_empty() {
-                            &bundle[0]
-                        } else {
-                            return;
-                        };
-
-
-                        for sample in &bundle {
-                            if sample.pdf == 0.0 || sample.intensity == 0.0 {
-                                return;
-                            }
-
-                            let specular = sample.typ.is_specular();
-                            let cos_abs = if specular {
-                                // division of cosine omitted in specular bxdfs
-                                1.0
-                            } else {
-                                sample.incident.dot(normal).abs()
-                            };
-
-                            //let modifier = crate::Spectrum::size() as Float / buf_size as Float;
-                            //let modifier = 1.0 / crate::Spectrum::size() as Float;
-
-                            throughput[sample.index] *= sample.intensity * cos_abs / sample.pdf;
-                        }
-
-                        let ray = offset_ray_towards(hit.point, hit.normal, hero.incident);
-                        match scene.intersect(&ray) {
-                            Some(i) => hit = i,
-                            None => break,
-                        }
+
+                    let strategy = self.direct_light_strategy;
+                    if !state.continue_ray(scene, strategy, &bxdf_sample) {
+                        let weight = state.escape_weight(scene, strategy, &bxdf_sample);
+                        state.add_scaled(&scene.ambient(), weight, indices, illumination);
+                        break;
                     }
                 }
-            } else {
-                break;
+                BxDFSampleResult::ScatteredBundle(bundle) => {
+                    trace_scattered_bundle(
+                        scene,
+                        hit,
+                        sampler,
+                        self.direct_light_strategy,
+                        &bundle,
+                        state.bounce..self.max_depth,
+                        illumination,
+                        state.throughput,
+                        &mut budget,
+                    );
+
+                    break;
+                }
             }
         }
     }
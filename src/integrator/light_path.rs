@@ -0,0 +1,360 @@
+use crate::bxdf::{Type, BSDF};
+use crate::scene::Scene;
+use crate::Spectrum;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// An event along a light path, in the [notation](crate::integrator) of the integrators.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// `E` - The eye
+    Eye,
+    /// `L` - The light (emitters and the ambient light)
+    Light,
+    /// `D` - Diffuse reflection or transmission
+    Diffuse,
+    /// `G` - Glossy reflection or transmission
+    Glossy,
+    /// `S` - Specular reflection or refraction
+    Specular,
+}
+
+impl Event {
+    /// Classifies a sampled scattering event.
+    ///
+    /// # Arguments
+    /// * `typ` - The type of the sampled BxDF
+    ///
+    /// # Returns
+    /// * The event
+    pub fn from_type(typ: Type) -> Self {
+        if typ.is_specular() {
+            Event::Specular
+        } else if typ.contains(Type::GLOSSY) {
+            Event::Glossy
+        } else {
+            Event::Diffuse
+        }
+    }
+
+    /// Classifies the scattering event of light sampled directly at a surface.
+    ///
+    /// As specular BxDFs cannot be evaluated, the event is glossy if the BSDF contains a glossy
+    /// BxDF and diffuse otherwise.
+    ///
+    /// # Arguments
+    /// * `bsdf` - The BSDF of the surface
+    ///
+    /// # Returns
+    /// * The event
+    pub fn from_bsdf(bsdf: &BSDF) -> Self {
+        if bsdf.num_types(Type::REFLECTION | Type::TRANSMISSION | Type::GLOSSY) > 0 {
+            Event::Glossy
+        } else {
+            Event::Diffuse
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    /// Matches the given event
+    Event(Event),
+    /// Matches any single event (`.`)
+    Any,
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    /// Matches zero or more repetitions (`*`)
+    Star(Box<Node>),
+    /// Matches one or more repetitions (`+`)
+    Plus(Box<Node>),
+    /// Matches zero or one occurrence (`?` or `[...]`)
+    Optional(Box<Node>),
+}
+
+impl Node {
+    /// Collects the end positions of all matches of this node starting at `start`.
+    fn ends(&self, path: &[Event], start: usize, out: &mut Vec<usize>) {
+        match self {
+            Node::Event(e) => {
+                if path.get(start) == Some(e) {
+                    Self::push(out, start + 1);
+                }
+            }
+            Node::Any => {
+                if start < path.len() {
+                    Self::push(out, start + 1);
+                }
+            }
+            Node::Concat(nodes) => {
+                let mut current = vec![start];
+                for node in nodes {
+                    let mut next = Vec::new();
+                    for &position in &current {
+                        node.ends(path, position, &mut next);
+                    }
+                    current = next;
+                }
+                current.into_iter().for_each(|end| Self::push(out, end));
+            }
+            Node::Alternation(nodes) => nodes.iter().for_each(|n| n.ends(path, start, out)),
+            Node::Star(node) => {
+                Self::push(out, start);
+                Self::repeat(node, path, start, out);
+            }
+            Node::Plus(node) => Self::repeat(node, path, start, out),
+            Node::Optional(node) => {
+                Self::push(out, start);
+                node.ends(path, start, out);
+            }
+        }
+    }
+
+    /// Collects the end positions of one or more repetitions of the given node.
+    fn repeat(node: &Node, path: &[Event], start: usize, out: &mut Vec<usize>) {
+        let mut visited = Vec::new();
+        let mut pending = vec![start];
+
+        while let Some(position) = pending.pop() {
+            let mut ends = Vec::new();
+            node.ends(path, position, &mut ends);
+
+            for end in ends {
+                if !visited.contains(&end) {
+                    visited.push(end);
+                    pending.push(end);
+                    Self::push(out, end);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn push(out: &mut Vec<usize>, end: usize) {
+        if !out.contains(&end) {
+            out.push(end);
+        }
+    }
+}
+
+/// A light path expression (LPE) is a regular expression over the [events](Event) of a light
+/// path, e.g. `E D S+ L` for caustics or `E (D|G) L` for direct lighting only.
+///
+/// The expression supports
+/// * the events `E`, `L`, `D`, `G` and `S`, or `.` for any event
+/// * alternations `(a|b)` and groups `(...)`
+/// * the repetitions `*`, `+` and `?`
+/// * optional groups `[...]`
+///
+/// Whitespace is ignored.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LightPathExpression {
+    source: String,
+    root: Node,
+}
+
+impl LightPathExpression {
+    /// Returns whether the given path matches this expression entirely.
+    ///
+    /// # Arguments
+    /// * `path` - The events of the path, starting at the eye
+    ///
+    /// # Returns
+    /// * Whether it matches
+    pub fn matches(&self, path: &[Event]) -> bool {
+        let mut ends = Vec::new();
+        self.root.ends(path, 0, &mut ends);
+
+        ends.contains(&path.len())
+    }
+}
+
+impl TryFrom<&str> for LightPathExpression {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let tokens: Vec<char> = value.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+
+        let root = parser.alternation()?;
+        if let Some(c) = parser.peek() {
+            return Err(format!(
+                "Unexpected '{}' in light path expression \"{}\"",
+                c, value
+            ));
+        }
+
+        Ok(Self {
+            source: value.to_string(),
+            root,
+        })
+    }
+}
+
+impl TryFrom<String> for LightPathExpression {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
+impl From<LightPathExpression> for String {
+    fn from(expression: LightPathExpression) -> Self {
+        expression.source
+    }
+}
+
+/// A recursive descent parser of light path expressions.
+struct Parser {
+    tokens: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected '{}' in light path expression", c))
+        }
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut nodes = vec![self.concat()?];
+        while self.peek() == Some('|') {
+            self.position += 1;
+            nodes.push(self.concat()?);
+        }
+
+        Ok(if nodes.len() == 1 {
+            nodes.pop().unwrap()
+        } else {
+            Node::Alternation(nodes)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' || c == ']' {
+                break;
+            }
+            nodes.push(self.repeat()?);
+        }
+
+        Ok(if nodes.len() == 1 {
+            nodes.pop().unwrap()
+        } else {
+            Node::Concat(nodes)
+        })
+    }
+
+    fn repeat(&mut self) -> Result<Node, String> {
+        let mut node = self.atom()?;
+        while let Some(c) = self.peek() {
+            node = match c {
+                '*' => Node::Star(Box::new(node)),
+                '+' => Node::Plus(Box::new(node)),
+                '?' => Node::Optional(Box::new(node)),
+                _ => break,
+            };
+            self.position += 1;
+        }
+
+        Ok(node)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let c = self
+            .peek()
+            .ok_or_else(|| "Unexpected end of light path expression".to_string())?;
+        self.position += 1;
+
+        let node = match c {
+            'E' => Node::Event(Event::Eye),
+            'L' => Node::Event(Event::Light),
+            'D' => Node::Event(Event::Diffuse),
+            'G' => Node::Event(Event::Glossy),
+            'S' => Node::Event(Event::Specular),
+            '.' => Node::Any,
+            '(' => {
+                let node = self.alternation()?;
+                self.expect(')')?;
+                node
+            }
+            '[' => {
+                let node = self.alternation()?;
+                self.expect(']')?;
+                Node::Optional(Box::new(node))
+            }
+            c => return Err(format!("Unknown event '{}' in light path expression", c)),
+        };
+
+        Ok(node)
+    }
+}
+
+/// Adds the contribution of a path ending at a light to the buffer of each matching
+/// [light path expression](Scene::light_paths).
+///
+/// # Arguments
+/// * `scene` - The scene containing the expressions
+/// * `path` - The events of the path, without the final `L`
+/// * `contribution` - The contribution of the path
+/// * `light_paths` - The buffers of the expressions (empty if disabled)
+pub fn add_light_path(
+    scene: &Scene,
+    path: &mut Vec<Event>,
+    contribution: Spectrum,
+    light_paths: &mut [Spectrum],
+) {
+    if light_paths.is_empty() {
+        return;
+    }
+
+    path.push(Event::Light);
+    for (expression, buffer) in scene.light_paths().iter().zip(light_paths.iter_mut()) {
+        if expression.matches(path) {
+            *buffer += contribution;
+        }
+    }
+    path.pop();
+}
+
+#[test]
+fn parse_and_match() {
+    use Event::*;
+
+    let caustics = LightPathExpression::try_from("E D S+ L").unwrap();
+    assert!(caustics.matches(&[Eye, Diffuse, Specular, Light]));
+    assert!(caustics.matches(&[Eye, Diffuse, Specular, Specular, Light]));
+    assert!(!caustics.matches(&[Eye, Diffuse, Light]));
+    assert!(!caustics.matches(&[Eye, Specular, Diffuse, Light]));
+
+    let direct = LightPathExpression::try_from("E(D|G)L").unwrap();
+    assert!(direct.matches(&[Eye, Glossy, Light]));
+    assert!(!direct.matches(&[Eye, Diffuse, Diffuse, Light]));
+
+    let kajiya = LightPathExpression::try_from("E[(D|G|S)+(D|G)]L").unwrap();
+    assert!(kajiya.matches(&[Eye, Light]));
+    assert!(kajiya.matches(&[Eye, Specular, Specular, Diffuse, Light]));
+    assert!(!kajiya.matches(&[Eye, Diffuse, Specular, Light]));
+
+    let any = LightPathExpression::try_from("E .* L").unwrap();
+    assert!(any.matches(&[Eye, Light]));
+    assert!(any.matches(&[Eye, Glossy, Specular, Diffuse, Light]));
+
+    assert!(LightPathExpression::try_from("E (D L").is_err());
+    assert!(LightPathExpression::try_from("E X L").is_err());
+    assert!(LightPathExpression::try_from("E D) L").is_err());
+}
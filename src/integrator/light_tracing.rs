@@ -0,0 +1,213 @@
+use crate::bxdf::Type;
+use crate::camera::{Camera, CameraConnection};
+use crate::integrator::Integrator;
+use crate::objects::OcclusionTester;
+use crate::samplers::Sampler;
+use crate::scene::Scene;
+use crate::sensor::pixel::Pixel;
+use crate::{Float, Vector3};
+use color::Color;
+use geometry::Ray;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// A light tracer (or particle tracer), tracing paths from the emitters and connecting each of
+/// their vertices to the camera.
+///
+/// Each sample traces a single light path and [splats](Pixel::splat) its contributions onto
+/// whichever pixels they project to, independent of the pixel being integrated. The splats get
+/// normalized by the number of samples taken over all pixels.
+///
+/// Paths hitting purely specular surfaces cannot be connected, so specular surfaces seen directly
+/// by the camera stay black, while caustics on diffuse surfaces converge quickly. Like in the
+/// other integrators, point lights do not fall off with the squared distance. Neither the ambient
+/// illumination nor media get traced, and the camera needs to support
+/// [connections](Camera::connect).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LightTracing {
+    max_depth: u32,
+    #[serde(skip)]
+    camera: Option<Arc<dyn Camera>>,
+}
+
+impl LightTracing {
+    /// Creates a new light tracer.
+    ///
+    /// # Arguments
+    /// * `max_depth` - The depth of light bounces
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(max_depth: u32) -> Self {
+        Self {
+            max_depth,
+            camera: None,
+        }
+    }
+
+    /// Connects the given point to the camera, if visible.
+    ///
+    /// # Returns
+    /// * The connection
+    /// * The direction towards the camera
+    fn connect(&self, scene: &Scene, point: Vector3) -> Option<(CameraConnection, Vector3)> {
+        let connection = self.camera.as_ref()?.connect(point)?;
+
+        if !OcclusionTester::between(point, connection.eye).unoccluded(scene) {
+            return None;
+        }
+
+        Some((connection, (connection.eye - point).normalized()))
+    }
+}
+
+#[typetag::serde]
+impl Integrator for LightTracing {
+    fn integrate(&self, pixel: &mut Pixel, scene: &Scene, _: &Ray, sampler: Sampler) {
+        // the contributions get splatted, the pixel only counts the sample
+        pixel.add_black();
+
+        let emitters = &scene.emitters;
+        if emitters.is_empty() {
+            return;
+        }
+
+        let index = (sampler.get_1d() * emitters.len() as Float) as usize;
+        let emitter = &emitters[index.min(emitters.len() - 1)];
+        // the emitter got selected with a probability of `1 / n`
+        let selection = emitters.len() as Float;
+
+        let (surface, direction, flux) = emitter.sample_ray(sampler.get_2d(), sampler.get_2d());
+
+        // emitters seen directly by the camera
+        if let Some((connection, towards)) = self.connect(scene, surface.point) {
+            let radiance = if emitter.is_delta() {
+                emitter.emission()
+            } else {
+                emitter.radiance(towards, surface.normal) * towards.dot(surface.normal)
+            };
+
+            let weight = selection * connection.importance / surface.pdf;
+            pixel.splat(connection.raster, radiance * weight);
+        }
+
+        let mut throughput = flux * selection;
+        let mut ray = scene.offset_ray_towards(surface.point, surface.normal, direction);
+
+        for depth in 0..self.max_depth {
+            let hit = match scene.intersect(&ray) {
+                Some(hit) => hit,
+                None => break,
+            };
+
+            if depth == 0 && emitter.is_delta() {
+                // the other integrators light surfaces by point lights without a falloff
+                let distance = (hit.point - surface.point).mag();
+                throughput *= distance * distance;
+            }
+
+            let bsdf = hit.object.bsdf();
+            let incident = -ray.direction;
+
+            // delta distributions cannot be connected to
+            if bsdf.num_types(Type::ALL & !Type::SPECULAR) > 0 {
+                if let Some((connection, outgoing)) = self.connect(scene, hit.point) {
+                    let bsdf_spectrum = bsdf.evaluate(hit.frame(), incident, outgoing, Type::ALL);
+                    let cos = outgoing.dot(hit.normal).abs();
+
+                    let contribution = throughput * bsdf_spectrum * (cos * connection.importance);
+                    pixel.splat(connection.raster, contribution);
+                }
+            }
+
+            let bxdf_sample =
+                match bsdf.sample(hit.frame(), incident, Type::ALL, sampler.get_sample()) {
+                    Some(sample) if sample.pdf > 0.0 && !sample.spectrum.is_black() => sample,
+                    _ => break,
+                };
+
+            let cos = if bxdf_sample.typ.is_specular() {
+                // division of cosine omitted in specular bxdfs
+                1.0
+            } else {
+                bxdf_sample.incident.dot(hit.normal).abs()
+            };
+
+            throughput *= bxdf_sample.spectrum * (cos / bxdf_sample.pdf);
+            ray = scene.offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
+        }
+    }
+
+    fn init(&mut self, camera: &(dyn Camera + 'static)) {
+        self.camera = Some(Arc::from(dyn_clone::clone_box(camera)));
+    }
+}
+
+#[test]
+fn light_tracing_converges_to_direct_illumination() {
+    use crate::renderer::Renderer;
+
+    let render = |integrator: &str, sampler: &str, passes: u32, light: &str| {
+        let source = format!(
+            r#"(
+                config: (
+                    filename: None,
+                    bounds: None,
+                    block_size: (x: 8, y: 8),
+                    passes: {},
+                    threads: Some(2),
+                ),
+                camera: {{
+                    "PerspectiveCamera": (
+                        Sampler: NoOp,
+                        Position: (x: 0, y: 0, z: 4),
+                        Target: (x: 0, y: 0, z: 0),
+                        Up: (x: 0, y: 1, z: 0),
+                        FovY: 40,
+                        Resolution: (x: 16, y: 16),
+                    ),
+                }},
+                integrator: {{{}}},
+                sampler: {},
+                scene: (
+                    bounding_box: (
+                        min: (x: -1, y: -1, z: -1),
+                        max: (x: 1, y: 3, z: 3),
+                    ),
+                    objects: [
+                        Receiver((
+                            geometry: {{"Sphere": (center: (x: 0, y: 0, z: 0), radius: 1)}},
+                            bsdf: (bxdfs: [{{"LambertianReflection": (r: Constant(0.8))}}]),
+                        )),
+                        Emitter(({})),
+                    ],
+                ),
+            )"#,
+            passes, integrator, sampler, light
+        );
+
+        let mut renderer = Renderer::new(ron::from_str(&source).unwrap());
+        renderer.render().join().unwrap();
+        let frame = renderer.get_frame();
+
+        // the total energy arriving at the sensor
+        // the total energy arriving at the sensor
+        frame.pixels.iter().map(|px| px[0]).sum::<Float>()
+    };
+
+    let sphere = r#"geometry: {"Sphere": (center: (x: 0, y: 3, z: 3), radius: 0.25)}, emission: Constant(50)"#;
+    let point = r#"geometry: {"Point": ((x: 0, y: 3, z: 3))}, emission: Constant(0.5)"#;
+
+    for light in &[sphere, point] {
+        let direct = render(r#""Whitted": (max_depth: 1)"#, "NoOp", 1, light);
+        let traced = render(r#""LightTracing": (max_depth: 1)"#, "Random", 1024, light);
+
+        assert!(direct > 0.0);
+        assert!(
+            (traced - direct).abs() < 0.1 * direct,
+            "{} != {}",
+            traced,
+            direct
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use crate::bxdf::Type;
+use crate::integrator::{direct_illumination, Integrator};
+use crate::mc::sample_unit_hemisphere;
+use crate::objects::SceneObject;
+use crate::samplers::Sampler;
+use crate::scene::Scene;
+use crate::sensor::pixel::Pixel;
+use crate::{Float, Spectrum};
+use color::Color;
+use geometry::{CoordinateSystem, Ray};
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::PI;
+#[cfg(feature = "f64")]
+use std::f64::consts::PI;
+
+/// Bakes the irradiance arriving at a surface point into a lightmap.
+///
+/// The primary ray is expected to start at the surface point and point into the direction of the
+/// surface normal, as generated by the [`LightmapCamera`](crate::camera::LightmapCamera).
+/// Direct irradiance is gathered by sampling each emitter, indirect irradiance by path tracing a
+/// cosine-weighted direction over the hemisphere.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Lightmap {
+    max_depth: u32,
+}
+
+impl Lightmap {
+    pub fn new(max_depth: u32) -> Self {
+        Self { max_depth }
+    }
+
+    /// Computes the irradiance at the origin of the ray, with the ray direction as normal.
+    fn irradiance(&self, scene: &Scene, ray: &Ray, sampler: Sampler) -> Spectrum {
+        let point = ray.origin;
+        let normal = ray.direction;
+
+        let mut irradiance = Spectrum::broadcast(0.0);
+
+        for light in &scene.emitters {
+            let emitter_sample = light.sample(point, sampler.get_2d());
+            let cos = emitter_sample.incident.dot(normal);
+
+            if cos > 0.0
+                && emitter_sample.pdf > 0.0
+                && !emitter_sample.radiance.is_black()
+                && emitter_sample.occlusion_tester.unoccluded(scene)
+            {
+                irradiance += emitter_sample.radiance * (cos / emitter_sample.pdf);
+            }
+        }
+
+        if self.max_depth == 0 {
+            return irradiance;
+        }
+
+        // cosine-weighted sampling cancels with the cosine term up to a factor of π
+        let frame = CoordinateSystem::from_y(normal);
+        let local = sample_unit_hemisphere(sampler.get_2d());
+        let incident =
+            (frame.x_axis * local.x + frame.y_axis * local.y + frame.z_axis * local.z).normalized();
+
+        irradiance + self.incident_radiance(scene, &Ray::new_fast(point, incident), sampler) * PI
+    }
+
+    /// Path traces the indirect radiance arriving along the ray. Emission of directly hit emitters
+    /// is omitted after non-specular bounces, as it is already gathered by sampling the emitters.
+    fn incident_radiance(&self, scene: &Scene, ray: &Ray, sampler: Sampler) -> Spectrum {
+        let mut radiance = Spectrum::broadcast(0.0);
+        let mut throughput = Spectrum::broadcast(1.0);
+        let mut specular = false;
+
+        let mut hit = match scene.intersect(ray) {
+            Some(i) => i,
+            None => return scene.ambient(),
+        };
+
+        for _ in 1..self.max_depth {
+            let outgoing = -hit.ray.direction;
+            let normal = hit.normal;
+            let frame = hit.frame();
+            let bsdf = hit.object.bsdf();
+
+            if let SceneObject::Emitter(e) = &hit.object {
+                if specular {
+                    radiance += throughput * e.emission();
+                }
+            }
+            radiance += throughput * direct_illumination(scene, sampler, &hit, bsdf);
+
+            let bxdf_sample = match bsdf.sample(frame, outgoing, Type::ALL, sampler.get_sample()) {
+                Some(s) if s.pdf != 0.0 && !s.spectrum.is_black() => s,
+                _ => break,
+            };
+
+            specular = bxdf_sample.typ.is_specular();
+            let cos_abs: Float = if specular {
+                // division of cosine omitted in specular bxdfs
+                1.0
+            } else {
+                bxdf_sample.incident.dot(normal).abs()
+            };
+
+            throughput *= bxdf_sample.spectrum * (cos_abs / bxdf_sample.pdf);
+
+            let ray = scene.offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
+            match scene.intersect(&ray) {
+                Some(i) => hit = i,
+                None => {
+                    radiance += throughput * scene.ambient();
+                    break;
+                }
+            }
+        }
+
+        radiance
+    }
+}
+
+#[typetag::serde]
+impl Integrator for Lightmap {
+    fn integrate(&self, pixel: &mut Pixel, scene: &Scene, primary_ray: &Ray, sampler: Sampler) {
+        if primary_ray.is_empty() {
+            pixel.add_black();
+        } else {
+            pixel.add(self.irradiance(scene, primary_ray, sampler));
+        }
+    }
+}
@@ -17,29 +17,52 @@
 //! * **Goral** radiosity: `ED*L`
 
 mod debug_normals;
+mod gradient_path;
+mod hero;
+mod light_path;
+mod light_tracing;
+mod lightmap;
 mod path;
+mod path_state;
+mod pssmlt;
 mod spectral_path;
 mod spectral_path_single;
+mod sppm;
+mod volumetric_path;
 mod whitted;
-// mod hero;
 
 pub use debug_normals::*;
+pub use gradient_path::*;
+pub use hero::*;
+pub use light_path::*;
+pub use light_tracing::*;
+pub use lightmap::*;
 pub use path::*;
+pub use path_state::*;
+pub use pssmlt::*;
 pub use spectral_path::*;
 pub use spectral_path_single::*;
+pub use sppm::*;
+pub use volumetric_path::*;
 pub use whitted::*;
-// pub use hero::*;
 
-use crate::bxdf::{Type, BSDF};
-use crate::objects::Emitter;
+use crate::bxdf::{BxDFSampleIndex, Type, BSDF};
+use crate::camera::Camera;
+use crate::mc::{power_heuristic, sample_unit_sphere};
+use crate::objects::{Emitter, OcclusionTester, SceneObject};
+use crate::postprocessing::Frame;
 use crate::samplers::Sampler;
 use crate::scene::{Scene, SceneIntersection};
 use crate::sensor::pixel::Pixel;
-use crate::Float;
+use crate::sensor::Sensor;
 use crate::Spectrum;
+use crate::{Float, Vector3};
 use color::Color;
-use core::slice::Iter;
 use geometry::Ray;
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::PI;
+#[cfg(feature = "f64")]
+use std::f64::consts::PI;
 use std::sync::Arc;
 
 /// An integrator to calculate the color of a pixel / ray.
@@ -56,28 +79,394 @@ pub trait Integrator: DynClone + Send + Sync {
     /// # Returns
     /// * The color spectrum of the given ray
     fn integrate(&self, pixel: &mut Pixel, scene: &Scene, primary_ray: &Ray, sampler: Sampler);
+
+    /// Integrates all pixels of a tile for one pass, allowing to trace rays of different pixels
+    /// together. By default, each pixel gets [integrated](Integrator::integrate) on its own, while
+    /// [empty](Ray::empty) primary rays count as black.
+    ///
+    /// # Arguments
+    /// * `pixels` - The pixels of the tile
+    /// * `scene` - The scene to integrate
+    /// * `camera` - The camera generating the primary rays
+    /// * `sampler` - A sampler to generate values
+    fn integrate_tile(
+        &self,
+        pixels: &mut [Pixel],
+        scene: &Scene,
+        camera: &dyn Camera,
+        sampler: Sampler,
+    ) {
+        for px in pixels {
+            let primary_ray = camera.primary_ray(px.position);
+
+            // samples blocked inside the camera
+            if primary_ray.is_empty() {
+                px.add_black();
+            } else {
+                self.integrate(px, scene, &primary_ray, sampler);
+            }
+        }
+    }
+
+    /// Initializes the integrator with the camera of the render, before integrating any pixel.
+    ///
+    /// # Arguments
+    /// * `camera` - The camera
+    fn init(&mut self, _camera: &(dyn Camera + 'static)) {}
+
+    /// Prepares acceleration structures depending on the scene (e.g. photon maps), once before
+    /// any tile gets scheduled, instead of lazily while integrating.
+    ///
+    /// # Arguments
+    /// * `scene` - The initialized scene to integrate
+    /// * `sensor` - The sensor to integrate
+    fn preprocess(&mut self, _scene: &Scene, _sensor: &Sensor) {}
+
+    /// Reconstructs the frame of the pixel averages from additionally estimated data, before any
+    /// post-processing.
+    ///
+    /// # Arguments
+    /// * `frame` - The frame of the sensor bounds
+    /// * `sensor` - The sensor
+    fn reconstruct(&self, _frame: &mut Frame, _sensor: &Sensor) {}
+
+    /// Returns whether the integrator accumulates the contributions of each light group into
+    /// separate [buffers](Pixel::light_groups).
+    ///
+    /// # Returns
+    /// * Whether light group buffers are supported
+    fn supports_light_groups(&self) -> bool {
+        false
+    }
 }
 
 use dyn_clone::DynClone;
 use serde::{Deserialize, Serialize};
-use std::ops::Index;
+use std::ops::{Index, Range};
+
+/// The pdf of sampling a direction towards the ambient light, uniformly over the sphere.
+const ENVIRONMENT_PDF: Float = 1.0 / (4.0 * PI);
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum DirectLightStrategy {
     All,
     Random,
+    /// All lights whose influence at the shading point reaches the given threshold
+    Influential(Float),
+    /// A single light per shading point, choosing between the ambient light and the emitters
+    /// proportionally to their estimated contribution. The emitters get selected by descending
+    /// the [light tree](crate::objects::LightTree) of the scene, at a cost logarithmic in their
+    /// number.
+    Mixture,
 }
 
 impl DirectLightStrategy {
-    pub fn get_emitters<'a>(&self, scene: &'a Scene, sample: Float) -> Iter<'a, Arc<Emitter>> {
-        match self {
-            DirectLightStrategy::All => scene.emitters.iter(),
+    pub fn get_emitters<'a>(
+        &self,
+        scene: &'a Scene,
+        point: Vector3,
+        sample: Float,
+    ) -> impl Iterator<Item = &'a Arc<Emitter>> {
+        let (emitters, threshold) = match *self {
+            DirectLightStrategy::All => (scene.emitters.as_slice(), None),
             DirectLightStrategy::Random => {
                 let i = (scene.emitters.len() as Float * sample) as usize;
-                core::slice::from_ref(scene.emitters.index(i)).iter()
+                (core::slice::from_ref(scene.emitters.index(i)), None)
+            }
+            DirectLightStrategy::Influential(threshold) => {
+                (scene.emitters.as_slice(), Some(threshold))
+            }
+            DirectLightStrategy::Mixture => match select_mixture(scene, point, sample) {
+                Some(i) => (core::slice::from_ref(scene.emitters.index(i)), None),
+                None => (&[][..], None),
+            },
+        };
+
+        // same query as `Scene::emitters_in_bounds`, but keeping a single iterator type
+        emitters
+            .iter()
+            .filter(move |e| threshold.is_none_or(|t| e.influence(point) >= t))
+    }
+
+    /// Returns whether the ambient light gets sampled for direct illumination at the given
+    /// point, deciding with the same sample as [get_emitters](Self::get_emitters).
+    ///
+    /// # Arguments
+    /// * `scene` - The scene
+    /// * `point` - The shading point
+    /// * `sample` - The sample selecting the lights
+    ///
+    /// # Returns
+    /// * Whether to sample the ambient light
+    pub fn samples_environment(&self, scene: &Scene, point: Vector3, sample: Float) -> bool {
+        match *self {
+            DirectLightStrategy::Mixture => sample < environment_probability(scene, point),
+            _ => false,
+        }
+    }
+
+    /// Returns the probability that the given emitter gets selected for direct illumination at
+    /// the given point.
+    ///
+    /// # Arguments
+    /// * `scene` - The scene
+    /// * `point` - The shading point
+    /// * `emitter` - The emitter
+    ///
+    /// # Returns
+    /// * The selection probability
+    pub fn selection_pdf(&self, scene: &Scene, point: Vector3, emitter: &Emitter) -> Float {
+        match *self {
+            DirectLightStrategy::All => 1.0,
+            DirectLightStrategy::Random => 1.0 / scene.emitters.len() as Float,
+            DirectLightStrategy::Influential(threshold) => {
+                if emitter.influence(point) >= threshold {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            DirectLightStrategy::Mixture => {
+                let emitters = 1.0 - environment_probability(scene, point);
+                emitters * scene.light_tree().pdf(point, emitter)
             }
         }
     }
+
+    /// Returns the probability that the ambient light gets selected for direct illumination at
+    /// the given point.
+    ///
+    /// # Arguments
+    /// * `scene` - The scene
+    /// * `point` - The shading point
+    ///
+    /// # Returns
+    /// * The selection probability (`0` if the strategy does not sample the ambient light)
+    pub fn environment_selection_pdf(&self, scene: &Scene, point: Vector3) -> Float {
+        match *self {
+            DirectLightStrategy::Mixture => environment_probability(scene, point),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Returns the estimated contribution of the ambient light, i.e. the irradiance it causes on an
+/// unoccluded surface.
+#[inline]
+fn environment_estimate(scene: &Scene) -> Float {
+    PI * scene.ambient().component_max()
+}
+
+/// Returns the probability that the mixture strategy selects the ambient light instead of an
+/// emitter, comparing its estimated contribution to the one of the emitters' light tree.
+fn environment_probability(scene: &Scene, point: Vector3) -> Float {
+    let environment = environment_estimate(scene);
+    let total = environment + scene.light_tree().importance(point);
+
+    if total > 0.0 {
+        environment / total
+    } else {
+        0.0
+    }
+}
+
+/// Selects a light proportionally to its estimated contribution at the given point.
+///
+/// # Arguments
+/// * `scene` - The scene
+/// * `point` - The shading point
+/// * `sample` - A random sample
+///
+/// # Returns
+/// * The index of the selected emitter, or `None` for the ambient light
+fn select_mixture(scene: &Scene, point: Vector3, sample: Float) -> Option<usize> {
+    let environment = environment_probability(scene, point);
+
+    if sample < environment {
+        return None;
+    }
+
+    let sample = ((sample - environment) / (1.0 - environment)).min(1.0 - Float::EPSILON);
+    scene.light_tree().sample(point, sample)
+}
+
+/// Returns the multiple importance sampling weight of the emission of an emitter, which got hit
+/// by sampling the BSDF at the previous intersection. Direct illumination sampled the same paths
+/// with the complementary weight.
+///
+/// Emitters hit by the primary ray or after a specular bounce get weighted by `1`, as direct
+/// illumination cannot sample these paths.
+///
+/// # Arguments
+/// * `scene` - The scene
+/// * `strategy` - The direct light strategy at the previous intersection
+/// * `previous` - The previous intersection
+/// * `emitter` - The hit emitter
+/// * `incident` - The sampled direction towards the emitter
+/// * `bsdf_pdf` - The pdf of sampling the direction
+///
+/// # Returns
+/// * The weight of the emission
+fn emitter_hit_weight(
+    scene: &Scene,
+    strategy: DirectLightStrategy,
+    previous: &SceneIntersection,
+    emitter: &Emitter,
+    incident: Vector3,
+    bsdf_pdf: Float,
+) -> Float {
+    if !emitter.is_linked(&previous.object) || previous.object.bsdf().is_empty() {
+        return 1.0;
+    }
+
+    let light_pdf = strategy.selection_pdf(scene, previous.point, emitter)
+        * emitter.pdf(previous.point, incident);
+
+    power_heuristic(bsdf_pdf, light_pdf)
+}
+
+/// Returns the multiple importance sampling weight of the emission of the given hit.
+///
+/// # Arguments
+/// * `scene` - The scene
+/// * `strategy` - The direct light strategy at the previous intersection
+/// * `previous` - The previous intersection
+/// * `hit` - The new intersection
+/// * `bsdf_pdf` - The pdf of sampling the new intersection
+/// * `specular` - Whether the sampled BxDF is specular
+///
+/// # Returns
+/// * The weight of the emission (`1` if the hit is no emitter)
+fn emission_weight(
+    scene: &Scene,
+    strategy: DirectLightStrategy,
+    previous: &SceneIntersection,
+    hit: &SceneIntersection,
+    bsdf_pdf: Float,
+    specular: bool,
+) -> Float {
+    match &hit.object {
+        SceneObject::Emitter(e) if !specular => {
+            emitter_hit_weight(scene, strategy, previous, e, hit.ray.direction, bsdf_pdf)
+        }
+        _ => 1.0,
+    }
+}
+
+/// Returns the multiple importance sampling weight of the ambient light picked up by a ray
+/// escaping the scene. If the strategy samples the ambient light, direct illumination sampled
+/// the same paths with the complementary weight.
+///
+/// # Arguments
+/// * `scene` - The scene
+/// * `strategy` - The direct light strategy at the previous intersection
+/// * `previous` - The previous intersection
+/// * `bsdf_pdf` - The pdf of sampling the escaping direction
+/// * `specular` - Whether the sampled BxDF is specular
+///
+/// # Returns
+/// * The weight of the ambient light
+fn escape_weight(
+    scene: &Scene,
+    strategy: DirectLightStrategy,
+    previous: &SceneIntersection,
+    bsdf_pdf: Float,
+    specular: bool,
+) -> Float {
+    if specular || previous.object.bsdf().is_empty() {
+        return 1.0;
+    }
+
+    let light_pdf = strategy.environment_selection_pdf(scene, previous.point) * ENVIRONMENT_PDF;
+
+    power_heuristic(bsdf_pdf, light_pdf)
+}
+
+/// A direct illumination sample of the ambient light.
+struct EnvironmentSample {
+    incident: Vector3,
+    /// The ambient light, attenuated by the transmittance towards it
+    radiance: Spectrum,
+    /// The pdf of the direction, including the selection of the ambient light
+    pdf: Float,
+    /// The multiple importance sampling weight against sampling the BSDF
+    weight: Float,
+}
+
+/// Samples a direction towards the ambient light uniformly, if the strategy selects it with the
+/// given sample.
+///
+/// # Arguments
+/// * `scene` - The scene
+/// * `sampler` - The sampler
+/// * `strategy` - The direct light strategy
+/// * `hit` - The intersection to illuminate
+/// * `bsdf` - The BSDF at the intersection
+/// * `selection` - The sample selecting the lights
+///
+/// # Returns
+/// * The sample, or `None` if the ambient light is not selected or occluded
+fn sample_environment(
+    scene: &Scene,
+    sampler: Sampler,
+    strategy: DirectLightStrategy,
+    hit: &SceneIntersection,
+    bsdf: &BSDF,
+    selection: Float,
+) -> Option<EnvironmentSample> {
+    if !strategy.samples_environment(scene, hit.point, selection) {
+        return None;
+    }
+
+    let incident = sample_unit_sphere(sampler.get_2d());
+    let target = hit.point + incident * scene.escape_distance(hit.point);
+    let transmittance = OcclusionTester::between(hit.point, target).transmittance(scene, sampler);
+
+    if transmittance.is_black() {
+        return None;
+    }
+
+    let pdf = strategy.environment_selection_pdf(scene, hit.point) * ENVIRONMENT_PDF;
+    let bsdf_pdf = bsdf.pdf(hit.frame(), incident, -hit.ray.direction, Type::ALL);
+
+    Some(EnvironmentSample {
+        incident,
+        radiance: scene.ambient() * transmittance,
+        pdf,
+        weight: power_heuristic(pdf, bsdf_pdf),
+    })
+}
+
+/// Returns the multiple importance sampling weight of a direct illumination sample.
+///
+/// # Arguments
+/// * `emitter` - The sampled emitter
+/// * `light_pdf` - The pdf of sampling the emitter, including its selection
+/// * `bsdf` - The BSDF at the intersection
+/// * `intersection` - The intersection
+/// * `incident` - The direction towards the emitter
+///
+/// # Returns
+/// * The weight of the sample
+fn direct_light_weight(
+    emitter: &Emitter,
+    light_pdf: Float,
+    bsdf: &BSDF,
+    intersection: &SceneIntersection,
+    incident: Vector3,
+) -> Float {
+    if emitter.is_delta() {
+        return 1.0;
+    }
+
+    let bsdf_pdf = bsdf.pdf(
+        intersection.frame(),
+        incident,
+        -intersection.ray.direction,
+        Type::ALL,
+    );
+
+    power_heuristic(light_pdf, bsdf_pdf)
 }
 
 fn direct_illumination(
@@ -85,6 +474,77 @@ fn direct_illumination(
     sampler: Sampler,
     intersection: &SceneIntersection,
     bsdf: &BSDF,
+) -> Spectrum {
+    direct_illumination_light_groups(
+        scene,
+        sampler,
+        intersection,
+        bsdf,
+        Spectrum::broadcast(1.0),
+        &mut [],
+        false,
+    )
+}
+
+/// Samples each linked light once like [direct_illumination], but without testing for occlusion,
+/// e.g. to [batch](Scene::occluded_batch) the shadow rays.
+///
+/// # Returns
+/// * The contribution of each light sample if unoccluded, and its shadow ray
+fn unshadowed_light_samples(
+    scene: &Scene,
+    sampler: Sampler,
+    intersection: &SceneIntersection,
+    bsdf: &BSDF,
+) -> Vec<(Spectrum, Ray)> {
+    let mut samples = Vec::new();
+
+    if bsdf.is_empty() {
+        return samples;
+    }
+
+    let outgoing = -intersection.ray.direction;
+
+    for light in &scene.emitters {
+        if !light.is_linked(&intersection.object) {
+            continue;
+        }
+
+        let emitter_sample = light.sample(intersection.point, sampler.get_2d());
+        if emitter_sample.pdf <= 0.0 || emitter_sample.radiance.is_black() {
+            continue;
+        }
+
+        let bsdf_spectrum = bsdf.evaluate(
+            intersection.frame(),
+            emitter_sample.incident,
+            outgoing,
+            Type::ALL,
+        );
+        let cos = emitter_sample.incident.dot(intersection.normal).abs();
+
+        if !bsdf_spectrum.is_black() && cos != 0.0 {
+            let contribution = bsdf_spectrum * emitter_sample.radiance * (cos / emitter_sample.pdf);
+            samples.push((contribution, emitter_sample.occlusion_tester.ray_in(scene)));
+        }
+    }
+
+    samples
+}
+
+/// Computes the direct illumination and additionally adds the contribution of each light group,
+/// weighted by `throughput`, to `light_groups`.
+///
+/// With `mis`, the samples get weighted against sampling the BSDF, which has to add the
+/// [complementary weighted](emitter_hit_weight) emission of hit emitters.
+fn direct_illumination_light_groups(
+    scene: &Scene,
+    sampler: Sampler,
+    intersection: &SceneIntersection,
+    bsdf: &BSDF,
+    throughput: Spectrum,
+    light_groups: &mut [Spectrum],
+    mis: bool,
 ) -> Spectrum {
     let mut illumination = Spectrum::broadcast(0.0);
 
@@ -95,14 +555,21 @@ fn direct_illumination(
     let outgoing_world = -intersection.ray.direction;
 
     for light in &scene.emitters {
+        if !light.is_linked(&intersection.object) {
+            continue;
+        }
+
         let emitter_sample = light.sample(intersection.point, sampler.get_2d());
+        if emitter_sample.pdf <= 0.0 || emitter_sample.radiance.is_black() {
+            continue;
+        }
 
-        if emitter_sample.pdf > 0.0
-            && !emitter_sample.radiance.is_black()
-            && emitter_sample.occlusion_tester.unoccluded(scene)
-        {
+        let transmittance = emitter_sample
+            .occlusion_tester
+            .transmittance(scene, sampler);
+        if !transmittance.is_black() {
             let bsdf_spectrum = bsdf.evaluate(
-                intersection.normal,
+                intersection.frame(),
                 emitter_sample.incident,
                 outgoing_world,
                 Type::ALL,
@@ -112,8 +579,28 @@ fn direct_illumination(
                 let cos = emitter_sample.incident.dot(intersection.normal);
 
                 if cos != 0.0 {
-                    illumination +=
-                        bsdf_spectrum * emitter_sample.radiance * (cos.abs() / emitter_sample.pdf)
+                    let weight = if mis {
+                        direct_light_weight(
+                            light,
+                            emitter_sample.pdf,
+                            bsdf,
+                            intersection,
+                            emitter_sample.incident,
+                        )
+                    } else {
+                        1.0
+                    };
+                    let contribution = bsdf_spectrum
+                        * emitter_sample.radiance
+                        * transmittance
+                        * (weight * cos.abs() / emitter_sample.pdf);
+                    illumination += contribution;
+
+                    if !light_groups.is_empty() {
+                        if let Some(i) = scene.light_group_index(light) {
+                            light_groups[i] += throughput * contribution;
+                        }
+                    }
                 }
             }
         }
@@ -138,28 +625,63 @@ fn direct_illumination_buf(
     }
 
     let outgoing_world = -hit.ray.direction;
-    for light in strategy.get_emitters(scene, sampler.get_1d()) {
-        let sample = light.sample_buf(hit.point, sampler.get_2d(), indices);
+    let mut radiance = vec![0.0; indices.len()];
+    let selection = sampler.get_1d();
 
-        if sample.pdf > 0.0 && sample.occlusion_tester.unoccluded(scene) {
+    for light in strategy.get_emitters(scene, hit.point, selection) {
+        if !light.is_linked(&hit.object) {
+            continue;
+        }
+
+        let sample = light.sample_buf(hit.point, sampler.get_2d(), indices, &mut radiance);
+        let light_pdf = sample.pdf * strategy.selection_pdf(scene, hit.point, light);
+
+        if light_pdf <= 0.0 {
+            continue;
+        }
+
+        let transmittance = sample.occlusion_tester.transmittance(scene, sampler);
+        if !transmittance.is_black() {
             let intensities = bsdf.evaluate_buf(
-                hit.normal,
+                hit.frame(),
                 sample.incident,
                 outgoing_world,
                 Type::ALL,
                 indices,
             );
 
+            let weight = direct_light_weight(light, light_pdf, bsdf, hit, sample.incident);
+            let cos_abs = sample.incident.dot(hit.normal).abs();
+
             for i in 0..indices.len() {
                 if intensities[i] != 0.0 && sample.radiance[i] != 0.0 {
-                    let cos_abs = sample.incident.dot(hit.normal).abs();
-
-                    illumination[i] +=
-                        throughput[i] * intensities[i] * sample.radiance[i] * cos_abs / sample.pdf;
+                    illumination[i] += throughput[i]
+                        * intensities[i]
+                        * sample.radiance[i]
+                        * transmittance[indices[i]]
+                        * cos_abs
+                        / light_pdf
+                        * weight;
                 }
             }
         }
     }
+
+    if let Some(sample) = sample_environment(scene, sampler, strategy, hit, bsdf, selection) {
+        let intensities = bsdf.evaluate_buf(
+            hit.frame(),
+            sample.incident,
+            outgoing_world,
+            Type::ALL,
+            indices,
+        );
+        let factor = sample.incident.dot(hit.normal).abs() / sample.pdf * sample.weight;
+
+        for i in 0..indices.len() {
+            illumination[i] +=
+                throughput[i] * intensities[i] * sample.radiance[indices[i]] * factor;
+        }
+    }
 }
 
 #[inline]
@@ -178,16 +700,27 @@ fn direct_illumination_wavelength(
     }
 
     let outgoing_world = -intersection.ray.direction;
+    let selection = sampler.get_1d();
+
+    for light in strategy.get_emitters(scene, intersection.point, selection) {
+        if !light.is_linked(&intersection.object) {
+            continue;
+        }
 
-    for light in strategy.get_emitters(scene, sampler.get_1d()) {
         let emitter_sample = light.sample_wavelength(intersection.point, sampler.get_2d(), index);
+        let light_pdf =
+            emitter_sample.pdf * strategy.selection_pdf(scene, intersection.point, light);
 
-        if emitter_sample.pdf != 0.0
-            && emitter_sample.radiance != 0.0
-            && emitter_sample.occlusion_tester.unoccluded(scene)
-        {
+        if light_pdf == 0.0 || emitter_sample.radiance == 0.0 {
+            continue;
+        }
+
+        let transmittance = emitter_sample
+            .occlusion_tester
+            .transmittance(scene, sampler)[index];
+        if transmittance != 0.0 {
             let bsdf_spectrum = bsdf.evaluate_wavelength(
-                intersection.normal,
+                intersection.frame(),
                 emitter_sample.incident,
                 outgoing_world,
                 Type::ALL,
@@ -196,12 +729,531 @@ fn direct_illumination_wavelength(
 
             if bsdf_spectrum != 0.0 {
                 let cos = emitter_sample.incident.dot(intersection.normal);
+                let weight = direct_light_weight(
+                    light,
+                    light_pdf,
+                    bsdf,
+                    intersection,
+                    emitter_sample.incident,
+                );
 
-                illumination +=
-                    bsdf_spectrum * emitter_sample.radiance * (cos.abs() / emitter_sample.pdf)
+                illumination += bsdf_spectrum
+                    * emitter_sample.radiance
+                    * transmittance
+                    * (weight * cos.abs() / light_pdf)
             }
         }
     }
 
+    if let Some(sample) =
+        sample_environment(scene, sampler, strategy, intersection, bsdf, selection)
+    {
+        let bsdf_spectrum = bsdf.evaluate_wavelength(
+            intersection.frame(),
+            sample.incident,
+            outgoing_world,
+            Type::ALL,
+            index,
+        );
+        let cos_abs = sample.incident.dot(intersection.normal).abs();
+
+        illumination +=
+            bsdf_spectrum * sample.radiance[index] * (sample.weight * cos_abs / sample.pdf);
+    }
+
     illumination
 }
+
+fn default_ray_budget() -> u32 {
+    256
+}
+
+/// Bounds the number of rays a single trace spends on following wavelengths on their own, after
+/// a [scattered bundle](crate::bxdf::BxDFSampleResult::ScatteredBundle) split them apart.
+struct RayBudget {
+    remaining: u32,
+}
+
+impl RayBudget {
+    fn new(rays: u32) -> Self {
+        Self { remaining: rays }
+    }
+
+    /// Takes a ray of the budget.
+    ///
+    /// # Returns
+    /// * Whether a ray was left
+    fn take(&mut self) -> bool {
+        if self.remaining == 0 {
+            false
+        } else {
+            self.remaining -= 1;
+            true
+        }
+    }
+
+    /// Splits off an equal share of the remaining rays for one of `parts` sub-paths, such that
+    /// early sub-paths cannot starve the later ones. Unused rays get
+    /// [returned](RayBudget::give_back).
+    fn share(&mut self, parts: usize) -> RayBudget {
+        let rays = self.remaining / parts.max(1) as u32;
+        self.remaining -= rays;
+
+        RayBudget::new(rays)
+    }
+
+    /// Returns the unused rays of a [share](RayBudget::share).
+    fn give_back(&mut self, share: RayBudget) {
+        self.remaining += share.remaining;
+    }
+}
+
+/// Follows a single wavelength from the given intersection on, spending a ray of the budget per
+/// bounce.
+///
+/// # Arguments
+/// * `hit` - The intersection to continue from
+/// * `bounces` - The remaining bounces
+/// * `index` - The index of the wavelength
+/// * `illumination` - The illumination of the wavelength to add to
+/// * `throughput` - The throughput of the wavelength up to the intersection
+/// * `weight` - The weight of the emission at the intersection
+/// * `budget` - The ray budget
+#[allow(clippy::too_many_arguments)]
+fn trace_wavelength(
+    scene: &Scene,
+    mut hit: SceneIntersection,
+    sampler: Sampler,
+    strategy: DirectLightStrategy,
+    bounces: Range<u32>,
+    index: usize,
+    illumination: &mut Float,
+    throughput: &mut Float,
+    mut weight: Float,
+    budget: &mut RayBudget,
+) {
+    for _ in bounces {
+        let outgoing = -hit.ray.direction;
+        let normal = hit.normal;
+        let frame = hit.frame();
+        let bsdf = hit.object.bsdf();
+
+        if let SceneObject::Emitter(e) = &hit.object {
+            *illumination += *throughput * e.emission_wavelength(index) * weight;
+        }
+        *illumination += *throughput
+            * direct_illumination_wavelength(scene, sampler, strategy, &hit, bsdf, index);
+
+        let bxdf_sample =
+            match bsdf.sample_light_wave(frame, outgoing, Type::ALL, sampler.get_sample(), index) {
+                Some(sample) => sample,
+                None => break,
+            };
+
+        if bxdf_sample.pdf == 0.0 || bxdf_sample.spectrum == 0.0 || !budget.take() {
+            break;
+        }
+
+        let specular = bxdf_sample.typ.is_specular();
+        let cos_abs = if specular {
+            // division of cosine omitted in specular bxdfs
+            1.0
+        } else {
+            bxdf_sample.incident.dot(normal).abs()
+        };
+
+        *throughput *= bxdf_sample.spectrum * cos_abs / bxdf_sample.pdf;
+
+        let ray = scene.offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
+        match scene.intersect(&ray) {
+            Some(i) => {
+                weight = emission_weight(scene, strategy, &hit, &i, bxdf_sample.pdf, specular);
+                hit = i;
+            }
+            None => {
+                let weight = escape_weight(scene, strategy, &hit, bxdf_sample.pdf, specular);
+                *illumination += *throughput * scene.ambient()[index] * weight;
+                break;
+            }
+        }
+    }
+}
+
+/// Continues each sample of a scattered bundle with a [sub-path](trace_wavelength) of its own
+/// wavelength, each getting an equal share of the remaining ray budget. Wavelengths running out
+/// of rays get terminated.
+///
+/// # Arguments
+/// * `hit` - The intersection the bundle scattered at
+/// * `bundle` - The scattered samples, one per buffer entry
+/// * `bounces` - The remaining bounces of the sub-paths
+/// * `illumination` - The illumination per buffer entry to add to
+/// * `throughput` - The throughput per buffer entry up to the intersection
+/// * `budget` - The ray budget
+#[allow(clippy::too_many_arguments)]
+fn trace_scattered_bundle(
+    scene: &Scene,
+    hit: &SceneIntersection,
+    sampler: Sampler,
+    strategy: DirectLightStrategy,
+    bundle: &[BxDFSampleIndex],
+    bounces: Range<u32>,
+    illumination: &mut [Float],
+    throughput: &mut [Float],
+    budget: &mut RayBudget,
+) {
+    for (i, sample) in bundle.iter().enumerate() {
+        if sample.pdf == 0.0 || sample.intensity == 0.0 {
+            continue;
+        }
+
+        let mut share = budget.share(bundle.len() - i);
+        if !share.take() {
+            budget.give_back(share);
+            continue;
+        }
+
+        let specular = sample.typ.is_specular();
+        let cos_abs = if specular {
+            // division of cosine omitted in specular bxdfs
+            1.0
+        } else {
+            sample.incident.dot(hit.normal).abs()
+        };
+
+        throughput[i] *= sample.intensity * cos_abs / sample.pdf;
+
+        let ray = scene.offset_ray_towards(hit.point, hit.normal, sample.incident);
+        match scene.intersect(&ray) {
+            Some(new_hit) => {
+                let weight = emission_weight(scene, strategy, hit, &new_hit, sample.pdf, specular);
+
+                trace_wavelength(
+                    scene,
+                    new_hit,
+                    sampler,
+                    strategy,
+                    bounces.clone(),
+                    sample.index,
+                    &mut illumination[i],
+                    &mut throughput[i],
+                    weight,
+                    &mut share,
+                );
+            }
+            None => {
+                let weight = escape_weight(scene, strategy, hit, sample.pdf, specular);
+                illumination[i] += throughput[i] * scene.ambient()[sample.index] * weight;
+            }
+        }
+
+        budget.give_back(share);
+    }
+}
+
+/// Scales the given contribution down such that none of its values exceeds the threshold (if
+/// any), keeping its hue. Clamping indirect contributions suppresses fireflies at the cost of
+/// energy.
+fn clamp_indirect(contribution: Spectrum, threshold: Option<Float>) -> Spectrum {
+    match threshold {
+        Some(threshold) => {
+            let max = contribution.component_max();
+
+            if max > threshold {
+                contribution * (threshold / max)
+            } else {
+                contribution
+            }
+        }
+        None => contribution,
+    }
+}
+
+/// Estimates the directional albedo of the BSDF at the given intersection by sampling it once,
+/// e.g. for the albedo [AOV](crate::sensor::aov::Aov) averaged over many samples.
+fn estimate_albedo(sampler: Sampler, intersection: &SceneIntersection) -> Spectrum {
+    let normal = intersection.normal;
+    let outgoing = -intersection.ray.direction;
+
+    match intersection.object.bsdf().sample(
+        intersection.frame(),
+        outgoing,
+        Type::ALL,
+        sampler.get_sample(),
+    ) {
+        Some(sample) if sample.pdf > 0.0 => {
+            let cos = if sample.typ.is_specular() {
+                // division of cosine omitted in specular bxdfs
+                1.0
+            } else {
+                sample.incident.dot(normal).abs()
+            };
+
+            sample.spectrum * (cos / sample.pdf)
+        }
+        _ => Spectrum::broadcast(0.0),
+    }
+}
+
+/// A diffuse unit sphere lit by a spherical emitter above it.
+///
+/// Indirect light cannot reach the receiver again, such that the exitant radiance at its top is
+/// exactly `ρ * L * (r / d)²`.
+#[cfg(test)]
+const ANALYTIC_SCENE: &str = r#"(
+    bounding_box: (
+        min: (x: -1, y: -1, z: -1),
+        max: (x: 1, y: 3.5, z: 1),
+    ),
+    objects: [
+        Receiver((
+            geometry: {
+                "Sphere": (
+                    center: (x: 0, y: 0, z: 0),
+                    radius: 1,
+                ),
+            },
+            bsdf: (
+                bxdfs: [
+                    {
+                        "LambertianReflection": (
+                            r: Constant(0.5),
+                        ),
+                    },
+                ],
+            ),
+        )),
+        Emitter((
+            geometry: {
+                "Sphere": (
+                    center: (x: 0, y: 3, z: 0),
+                    radius: 0.5,
+                ),
+            },
+            bsdf: (
+                bxdfs: [],
+            ),
+            emission: Constant(1),
+        )),
+    ],
+)"#;
+
+#[test]
+fn integrators_agree_on_analytic_scene() {
+    use ultraviolet::UVec2;
+
+    const SAMPLES: usize = 20000;
+    const EXPECTED: Float = 0.5 * (0.5 / 2.0) * (0.5 / 2.0);
+
+    let mut scene: Scene = ron::from_str(ANALYTIC_SCENE).unwrap();
+    scene.init();
+
+    // hits the top of the receiver at (0, 1, 0)
+    let origin = Vector3::new(1.0, 2.0, 0.0);
+    let primary_ray = Ray::new_fast(origin, Vector3::new(-1.0, -1.0, 0.0).normalized());
+
+    // the light tracer splats onto the sensor, and the lightmap integrator and the normal
+    // debugger do not estimate the radiance along the primary ray
+    let integrators = [
+        r#"{"Path": (max_depth: 4)}"#,
+        r#"{"Pssmlt": (max_depth: 4, bootstrap_samples: 4, mutations: 4)}"#,
+        r#"{"SpectralPath": (
+            max_depth: 4,
+            light_wave_samples: 6,
+            direct_light_strategy: All,
+            spectral_sampler: Random,
+        )}"#,
+        r#"{"SpectralPathSingle": (
+            max_depth: 4,
+            light_wave_samples: 6,
+            direct_light_strategy: All,
+            spectral_sampler: Random,
+        )}"#,
+        r#"{"VolumetricPath": (max_depth: 4)}"#,
+        r#"{"Hero": (
+            max_depth: 4,
+            light_wave_samples: 6,
+            direct_light_strategy: All,
+        )}"#,
+        r#"{"Whitted": (max_depth: 4)}"#,
+        r#"{"GradientPath": (max_depth: 4)}"#,
+        // the convex scene has no indirect illumination, leaving the photon maps empty
+        r#"{"Sppm": (max_depth: 4, photons: 16, initial_radius: 0.1)}"#,
+    ];
+
+    for source in &integrators {
+        let integrator: Box<dyn Integrator> = ron::from_str(source).unwrap();
+        let mut pixel = Pixel::new(UVec2::zero(), 0, 0);
+
+        for _ in 0..SAMPLES {
+            integrator.integrate(&mut pixel, &scene, &primary_ray, Sampler::Random);
+        }
+
+        let size = Spectrum::size();
+        let average = (0..size).map(|i| pixel.average[i]).sum::<Float>() / size as Float;
+
+        assert!(
+            (average - EXPECTED).abs() < 0.05 * EXPECTED,
+            "{} estimated {} instead of {}",
+            source,
+            average,
+            EXPECTED
+        );
+    }
+}
+
+#[test]
+fn ray_budget_bounds_scattered_sub_paths() {
+    use ultraviolet::UVec2;
+
+    let mut budget = RayBudget::new(10);
+    let mut share = budget.share(3);
+    assert_eq!((3, 7), (share.remaining, budget.remaining));
+    assert!(share.take());
+    budget.give_back(share);
+    assert_eq!(9, budget.remaining);
+
+    // looking through a dispersive glass sphere at a light, taking 2 rays per wavelength
+    let mut scene: Scene = ron::from_str(
+        r#"(
+            bounding_box: (
+                min: (x: -1, y: -1, z: -4),
+                max: (x: 1, y: 1, z: 1),
+            ),
+            objects: [
+                Receiver((
+                    geometry: {"Sphere": (center: (x: 0, y: 0, z: 0), radius: 1)},
+                    bsdf: (bxdfs: [{"SpecularTransmission": (
+                        t: Constant(1),
+                        fresnel: (eta_i: Air, eta_t: Glass),
+                    )}]),
+                )),
+                Emitter((
+                    geometry: {"Sphere": (center: (x: 0, y: 0, z: -3), radius: 0.5)},
+                    bsdf: (bxdfs: []),
+                    emission: Constant(1),
+                )),
+            ],
+        )"#,
+    )
+    .unwrap();
+    scene.init();
+
+    // following every wavelength
+    let size = Spectrum::size();
+    let primary_ray = Ray::new_fast(Vector3::new(0.0, 0.0, 3.0), -Vector3::unit_z());
+    let render = |integrator: &str, ray_budget: u32| {
+        let source = integrator
+            .replace(
+                "light_wave_samples",
+                &format!("light_wave_samples: {}", size),
+            )
+            .replace("ray_budget", &format!("ray_budget: {}", ray_budget));
+        let integrator: Box<dyn Integrator> = ron::from_str(&source).unwrap();
+        let mut pixel = Pixel::new(UVec2::zero(), 0, 0);
+        integrator.integrate(&mut pixel, &scene, &primary_ray, Sampler::NoOp);
+
+        (0..size).map(|i| pixel.average[i]).sum::<Float>()
+    };
+
+    let integrators = [
+        r#"{"SpectralPath": (
+            max_depth: 4,
+            light_wave_samples,
+            direct_light_strategy: All,
+            spectral_sampler: Random,
+            ray_budget,
+        )}"#,
+        r#"{"Hero": (
+            max_depth: 4,
+            light_wave_samples,
+            direct_light_strategy: All,
+            ray_budget,
+        )}"#,
+    ];
+
+    for integrator in &integrators {
+        let unbounded = render(integrator, 1000);
+
+        assert!(unbounded > 0.0, "{}", integrator);
+        assert_eq!(
+            unbounded,
+            render(integrator, 2 * size as u32),
+            "{}",
+            integrator
+        );
+        // the last wavelength runs out of rays
+        assert!(
+            render(integrator, 2 * size as u32 - 1) < unbounded,
+            "{}",
+            integrator
+        );
+        assert_eq!(0.0, render(integrator, 0), "{}", integrator);
+    }
+}
+
+#[test]
+fn mixture_strategy_samples_ambient_light() {
+    use ultraviolet::UVec2;
+
+    const SAMPLES: usize = 20000;
+    // the emitter covers the ambient light within the cone of `sin² = 1/16`
+    const EXPECTED: Float = 0.5 * (0.5 / 2.0) * (0.5 / 2.0) + 0.5 * 0.1 * (15.0 / 16.0);
+
+    let source = ANALYTIC_SCENE.replacen(
+        "objects:",
+        "ambient_light: AmbientLight(Constant(0.1)),\n    objects:",
+        1,
+    );
+    let mut scene: Scene = ron::from_str(&source).unwrap();
+    scene.init();
+
+    let origin = Vector3::new(1.0, 2.0, 0.0);
+    let primary_ray = Ray::new_fast(origin, Vector3::new(-1.0, -1.0, 0.0).normalized());
+
+    let integrators = [
+        r#"{"SpectralPath": (
+            max_depth: 4,
+            light_wave_samples: 6,
+            direct_light_strategy: All,
+            spectral_sampler: Random,
+        )}"#,
+        r#"{"SpectralPath": (
+            max_depth: 4,
+            light_wave_samples: 6,
+            direct_light_strategy: Mixture,
+            spectral_sampler: Random,
+        )}"#,
+        r#"{"SpectralPathSingle": (
+            max_depth: 4,
+            light_wave_samples: 6,
+            direct_light_strategy: Mixture,
+            spectral_sampler: Random,
+        )}"#,
+        r#"{"Hero": (
+            max_depth: 4,
+            light_wave_samples: 6,
+            direct_light_strategy: Mixture,
+        )}"#,
+    ];
+
+    for source in &integrators {
+        let integrator: Box<dyn Integrator> = ron::from_str(source).unwrap();
+        let mut pixel = Pixel::new(UVec2::zero(), 0, 0);
+
+        for _ in 0..SAMPLES {
+            integrator.integrate(&mut pixel, &scene, &primary_ray, Sampler::Random);
+        }
+
+        let size = Spectrum::size();
+        let average = (0..size).map(|i| pixel.average[i]).sum::<Float>() / size as Float;
+
+        assert!(
+            (average - EXPECTED).abs() < 0.05 * EXPECTED,
+            "{} estimated {} instead of {}",
+            source,
+            average,
+            EXPECTED
+        );
+    }
+}
@@ -1,22 +1,250 @@
 use crate::bxdf::Type;
-use crate::integrator::{direct_illumination, Integrator};
-use crate::objects::SceneObject;
+use crate::integrator::{
+    add_light_path, clamp_indirect, direct_illumination_light_groups, estimate_albedo,
+    DirectLightStrategy, Event, Integrator, PathState,
+};
 use crate::samplers::Sampler;
 use crate::scene::Scene;
+use crate::sensor::aov::AovSample;
 use crate::sensor::pixel::Pixel;
-use crate::Spectrum;
+use crate::{Float, Spectrum};
 use color::Color;
-use geometry::{offset_ray_towards, Ray};
+use geometry::Ray;
 use serde::{Deserialize, Serialize};
 
+fn one() -> u32 {
+    1
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Path {
     max_depth: u32,
+    /// The number of BSDF samples drawn at the first bounce.
+    #[serde(default = "one")]
+    bsdf_samples: u32,
+    /// The number of light samples drawn at the first bounce.
+    #[serde(default = "one")]
+    light_samples: u32,
+    /// The maximal value of contributions of indirect bounces, suppressing fireflies.
+    #[serde(default)]
+    indirect_clamp: Option<Float>,
 }
 
 impl Path {
     pub fn new(max_depth: u32) -> Self {
-        Self { max_depth }
+        Self {
+            max_depth,
+            bsdf_samples: 1,
+            light_samples: 1,
+            indirect_clamp: None,
+        }
+    }
+
+    /// Splits the first bounce into multiple BSDF and light samples.
+    /// Deeper bounces continue with a single sample each, weighted accordingly.
+    ///
+    /// # Constraints
+    /// * `bsdf_samples` - Should be greater than `0`.
+    /// * `light_samples` - Should be greater than `0`.
+    ///
+    /// # Arguments
+    /// * `bsdf_samples` - The number of BSDF samples at the first bounce
+    /// * `light_samples` - The number of light samples at the first bounce
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with_splitting(mut self, bsdf_samples: u32, light_samples: u32) -> Self {
+        debug_assert!(bsdf_samples > 0);
+        debug_assert!(light_samples > 0);
+
+        self.bsdf_samples = bsdf_samples;
+        self.light_samples = light_samples;
+        self
+    }
+
+    /// Clamps the contributions of indirect bounces to the given maximal value, trading bias for
+    /// the suppression of fireflies, e.g. of rare specular-diffuse-specular paths.
+    ///
+    /// # Constraints
+    /// * `indirect_clamp` - Should be greater than `0`.
+    ///
+    /// # Arguments
+    /// * `indirect_clamp` - The maximal value of indirect contributions
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with_indirect_clamp(mut self, indirect_clamp: Float) -> Self {
+        debug_assert!(indirect_clamp > 0.0);
+
+        self.indirect_clamp = Some(indirect_clamp);
+        self
+    }
+
+    /// Clamps the contribution if its path bounced more than once.
+    fn clamp(&self, path: &[Event], contribution: Spectrum) -> Spectrum {
+        if path.len() > 2 {
+            clamp_indirect(contribution, self.indirect_clamp)
+        } else {
+            contribution
+        }
+    }
+
+    /// Traces a path starting at the hit of the given state until `max_depth` is reached.
+    ///
+    /// The `path` holds the [events](Event) leading to the hit, classifying the contributions
+    /// into the `light_paths` buffers and the `direct` illumination.
+    #[allow(clippy::too_many_arguments)]
+    fn trace(
+        &self,
+        scene: &Scene,
+        sampler: Sampler,
+        mut state: PathState<Spectrum>,
+        path: &mut Vec<Event>,
+        illumination: &mut Spectrum,
+        direct: &mut Spectrum,
+        light_groups: &mut [Spectrum],
+        light_paths: &mut [Spectrum],
+    ) {
+        while state.bounce < self.max_depth {
+            let depth = state.bounce;
+            let bsdf = state.hit.object.bsdf();
+
+            if let Some((e, emission)) = state.emission() {
+                let emission = self.clamp(path, emission);
+                *illumination += emission;
+                add_direct(path, emission, direct);
+                add_light_path(scene, path, emission, light_paths);
+
+                if !light_groups.is_empty() {
+                    if let Some(i) = scene.light_group_index(e) {
+                        light_groups[i] += emission;
+                    }
+                }
+            }
+
+            let light_samples = if depth == 0 { self.light_samples } else { 1 };
+            let weight = state.throughput / light_samples as Float;
+
+            path.push(Event::from_bsdf(bsdf));
+            for _ in 0..light_samples {
+                let light = weight
+                    * direct_illumination_light_groups(
+                        scene,
+                        sampler,
+                        &state.hit,
+                        bsdf,
+                        weight,
+                        light_groups,
+                        true,
+                    );
+                let light = self.clamp(path, light);
+                *illumination += light;
+                add_direct(path, light, direct);
+                add_light_path(scene, path, light, light_paths);
+            }
+            path.pop();
+
+            let bsdf_samples = if depth == 0 { self.bsdf_samples } else { 1 };
+            if bsdf_samples > 1 {
+                let weight = state.throughput / bsdf_samples as Float;
+                let len = path.len();
+
+                for _ in 0..bsdf_samples {
+                    let mut split = PathState::new(state.hit.clone(), weight).at_bounce(depth);
+
+                    if self.scatter(
+                        scene,
+                        sampler,
+                        &mut split,
+                        path,
+                        illumination,
+                        direct,
+                        light_paths,
+                    ) {
+                        self.trace(
+                            scene,
+                            sampler,
+                            split,
+                            path,
+                            illumination,
+                            direct,
+                            light_groups,
+                            light_paths,
+                        );
+                    }
+
+                    path.truncate(len);
+                }
+
+                return;
+            }
+
+            if !self.scatter(
+                scene,
+                sampler,
+                &mut state,
+                path,
+                illumination,
+                direct,
+                light_paths,
+            ) {
+                return;
+            }
+        }
+    }
+
+    /// Samples the BSDF at the hit of the given state and follows the sampled direction.
+    /// Rays escaping the scene pick up the ambient light.
+    ///
+    /// The sampled [event](Event) gets appended to the `path`.
+    ///
+    /// # Returns
+    /// * Whether the state continues at a next hit, otherwise the path terminates
+    #[allow(clippy::too_many_arguments)]
+    fn scatter(
+        &self,
+        scene: &Scene,
+        sampler: Sampler,
+        state: &mut PathState<Spectrum>,
+        path: &mut Vec<Event>,
+        illumination: &mut Spectrum,
+        direct: &mut Spectrum,
+        light_paths: &mut [Spectrum],
+    ) -> bool {
+        let hit = &state.hit;
+        let bxdf_sample = match hit.object.bsdf().sample(
+            hit.frame(),
+            -hit.ray.direction,
+            Type::ALL,
+            sampler.get_sample(),
+        ) {
+            Some(s) => s,
+            None => return false,
+        };
+
+        if !state.attenuate(&bxdf_sample) {
+            return false;
+        }
+        path.push(Event::from_type(bxdf_sample.typ));
+
+        if state.continue_ray(scene, DirectLightStrategy::All, &bxdf_sample) {
+            return true;
+        }
+
+        let ambient = self.clamp(path, state.throughput * scene.ambient());
+        *illumination += ambient;
+        add_direct(path, ambient, direct);
+        add_light_path(scene, path, ambient, light_paths);
+
+        false
+    }
+}
+
+/// Adds the contribution to the `direct` illumination if its path bounced at most once, i.e.
+/// consists of the eye and at most one event.
+fn add_direct(path: &[Event], contribution: Spectrum, direct: &mut Spectrum) {
+    if path.len() <= 2 {
+        *direct += contribution;
     }
 }
 
@@ -25,49 +253,158 @@ impl Integrator for Path {
     fn integrate(&self, pixel: &mut Pixel, scene: &Scene, primary_ray: &Ray, sampler: Sampler) {
         if let Some(intersection) = scene.intersect(primary_ray) {
             let mut illumination = Spectrum::broadcast(0.0);
-            let mut throughput = Spectrum::broadcast(1.0);
+            let mut light_groups = vec![Spectrum::broadcast(0.0); pixel.light_groups.len()];
+            let mut light_paths = vec![Spectrum::broadcast(0.0); pixel.light_paths.len()];
+            let mut direct = Spectrum::broadcast(0.0);
+            let mut path = Vec::with_capacity(self.max_depth as usize + 2);
+            path.push(Event::Eye);
 
-            let mut hit = intersection;
+            let aovs = scene.aovs();
+            let aov_sample = if aovs.is_empty() {
+                None
+            } else {
+                Some(AovSample {
+                    normal: intersection.normal,
+                    depth: intersection.t,
+                    albedo: estimate_albedo(sampler, &intersection),
+                    label: intersection.object.label(),
+                    ..Default::default()
+                })
+            };
 
-            for _ in 0..self.max_depth {
-                let outgoing = -hit.ray.direction;
-                let normal = hit.normal;
-                let bsdf = hit.object.bsdf();
+            self.trace(
+                scene,
+                sampler,
+                PathState::new(intersection, Spectrum::broadcast(1.0)),
+                &mut path,
+                &mut illumination,
+                &mut direct,
+                &mut light_groups,
+                &mut light_paths,
+            );
 
-                if let SceneObject::Emitter(e) = &hit.object {
-                    illumination += throughput * e.emission;
-                } else {
-                    illumination += throughput * direct_illumination(scene, sampler, &hit, bsdf);
-                }
+            pixel.add_with_aovs(illumination, &light_groups, &light_paths);
 
-                let sample = sampler.get_sample();
-                if let Some(bxdf_sample) = bsdf.sample(normal, outgoing, Type::ALL, sample) {
-                    if bxdf_sample.pdf == 0.0 || bxdf_sample.spectrum.is_black() {
-                        break;
-                    }
+            if let Some(mut aov_sample) = aov_sample {
+                aov_sample.direct = direct;
+                aov_sample.indirect = illumination - direct;
+                pixel.add_aovs(aovs, &aov_sample);
+            }
+        } else if scene.ambient_light.is_black() {
+            pixel.add_black();
 
-                    let cos_abs = if bxdf_sample.typ.is_specular() {
-                        // division of cosine omitted in specular bxdfs
-                        1.0
-                    } else {
-                        bxdf_sample.incident.dot(normal).abs()
-                    };
+            if !scene.aovs().is_empty() {
+                pixel.add_aovs(scene.aovs(), &AovSample::default());
+            }
+        } else {
+            let ambient = scene.ambient();
+            let mut light_paths = vec![Spectrum::broadcast(0.0); pixel.light_paths.len()];
+            add_light_path(scene, &mut vec![Event::Eye], ambient, &mut light_paths);
 
-                    throughput *= bxdf_sample.spectrum * (cos_abs / bxdf_sample.pdf);
+            pixel.add_with_aovs(ambient, &[], &light_paths);
 
-                    let ray = offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
-                    match scene.intersect(&ray) {
-                        Some(i) => hit = i,
-                        None => break,
-                    }
-                } else {
-                    break;
-                }
+            if !scene.aovs().is_empty() {
+                let aov_sample = AovSample {
+                    direct: ambient,
+                    ..Default::default()
+                };
+                pixel.add_aovs(scene.aovs(), &aov_sample);
             }
-
-            pixel.add(illumination);
-        } else {
-            pixel.add_black()
         }
     }
+
+    fn supports_light_groups(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn split_samples_average_to_a_single_one() {
+    use crate::renderer::Renderer;
+
+    // without randomness every split sample equals the single one
+    let render = |splitting: &str| {
+        let source = format!(
+            r#"(
+                config: (
+                    filename: None,
+                    bounds: None,
+                    block_size: (x: 4, y: 4),
+                    passes: 1,
+                    threads: Some(2),
+                ),
+                camera: {{
+                    "PerspectiveCamera": (
+                        Sampler: NoOp,
+                        Position: (x: 0, y: 0, z: 4),
+                        Target: (x: 0, y: 0, z: 0),
+                        Up: (x: 0, y: 1, z: 0),
+                        FovY: 40,
+                        Resolution: (x: 8, y: 8),
+                    ),
+                }},
+                integrator: {{"Path": (max_depth: 3, {})}},
+                sampler: NoOp,
+                scene: (
+                    bounding_box: (
+                        min: (x: -1, y: -1, z: -1),
+                        max: (x: 1, y: 3, z: 2),
+                    ),
+                    ambient_light: (Constant(0.25)),
+                    objects: [
+                        Receiver((
+                            geometry: {{"Sphere": (center: (x: 0, y: 0, z: 0), radius: 1)}},
+                            bsdf: (bxdfs: [{{"LambertianReflection": (r: Constant(0.8))}}]),
+                        )),
+                        Emitter((
+                            geometry: {{"Point": ((x: 0, y: 3, z: 2))}},
+                            emission: Constant(10),
+                        )),
+                    ],
+                ),
+            )"#,
+            splitting
+        );
+
+        let mut renderer = Renderer::new(ron::from_str(&source).unwrap());
+        renderer.render().join().unwrap();
+        let frame = renderer.get_frame();
+
+        (0..8)
+            .flat_map(|y| (0..8).map(move |x| (x, y)))
+            .map(|(x, y)| frame.get(x, y)[0])
+            .collect::<Vec<_>>()
+    };
+
+    let single = render("");
+    let split = render("bsdf_samples: 4, light_samples: 3");
+
+    assert!(single.iter().any(|&v| v > 0.25));
+    for (a, b) in single.iter().zip(&split) {
+        assert!((a - b).abs() <= 1e-4 * a.max(1.0), "{} != {}", a, b);
+    }
+}
+
+#[test]
+fn clamp_indirect_contributions() {
+    use Event::*;
+
+    let mut bright = Spectrum::broadcast(0.5);
+    bright[3] = 4.0;
+
+    let clamped = Path::new(3).with_indirect_clamp(1.0);
+    // the events of the path so far
+    let direct = [Eye, Diffuse];
+    let indirect = [Eye, Diffuse, Specular];
+
+    assert_eq!(clamped.clamp(&direct, bright).data, bright.data);
+    assert_eq!(Path::new(3).clamp(&indirect, bright).data, bright.data);
+
+    // scaled down to the threshold, keeping the hue
+    let scaled = clamped.clamp(&indirect, bright);
+    assert_eq!(scaled[3], 1.0);
+    assert_eq!(scaled[0], 0.125);
+
+    let dim = Spectrum::broadcast(0.5);
+    assert_eq!(clamped.clamp(&indirect, dim).data, dim.data);
 }
@@ -0,0 +1,224 @@
+use crate::bxdf::BxDFSample;
+use crate::integrator::{emission_weight, escape_weight, DirectLightStrategy};
+use crate::objects::{Emitter, SceneObject};
+use crate::scene::{Scene, SceneIntersection};
+use crate::{Float, Spectrum};
+use color::Color;
+
+/// The state of a path while it gets traced: the current hit, the throughput leading to it and
+/// the weight of its emission.
+///
+/// The throughput is either a [Spectrum] of all wavelengths, or a buffer of the values of a
+/// sampled set of wavelengths.
+pub struct PathState<T> {
+    pub hit: SceneIntersection,
+    pub throughput: T,
+    /// The weight of the emission at the hit, e.g. of multiple importance sampling
+    pub emission_weight: Float,
+    /// The number of bounces leading to the hit
+    pub bounce: u32,
+    /// Whether the last bounce was specular
+    pub specular: bool,
+}
+
+impl<T> PathState<T> {
+    /// Creates a new path state at a primary hit, seeing emitters at full weight.
+    ///
+    /// # Arguments
+    /// * `hit` - The primary hit
+    /// * `throughput` - The initial throughput
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(hit: SceneIntersection, throughput: T) -> Self {
+        Self {
+            hit,
+            throughput,
+            emission_weight: 1.0,
+            bounce: 0,
+            specular: false,
+        }
+    }
+
+    /// Starts the path state at the given bounce, e.g. when splitting a path.
+    ///
+    /// # Arguments
+    /// * `bounce` - The number of bounces leading to the hit
+    ///
+    /// # Returns
+    /// * Self
+    pub fn at_bounce(mut self, bounce: u32) -> Self {
+        self.bounce = bounce;
+        self
+    }
+
+    /// Returns the factor a sample of the BSDF at the hit contributes to the throughput.
+    ///
+    /// # Arguments
+    /// * `sample` - The BSDF sample
+    ///
+    /// # Returns
+    /// * The absolute cosine of the incident direction divided by the pdf
+    fn factor<S>(&self, sample: &BxDFSample<S>) -> Float {
+        let cos_abs = if sample.typ.is_specular() {
+            // division of cosine omitted in specular bxdfs
+            1.0
+        } else {
+            sample.incident.dot(self.hit.normal).abs()
+        };
+
+        cos_abs / sample.pdf
+    }
+
+    /// Follows the direction of a BSDF sample to the next hit, weighting its emission by
+    /// multiple importance sampling with the given direct light strategy.
+    ///
+    /// # Arguments
+    /// * `scene` - The scene
+    /// * `strategy` - The direct light strategy at the hit
+    /// * `sample` - The BSDF sample, [attenuating](Self::attenuate) the throughput before
+    ///
+    /// # Returns
+    /// * Whether a next hit got found, otherwise the path escaped the scene
+    pub fn continue_ray<S>(
+        &mut self,
+        scene: &Scene,
+        strategy: DirectLightStrategy,
+        sample: &BxDFSample<S>,
+    ) -> bool {
+        let ray = scene.offset_ray_towards(self.hit.point, self.hit.normal, sample.incident);
+
+        match scene.intersect(&ray) {
+            Some(next) => {
+                self.emission_weight =
+                    emission_weight(scene, strategy, &self.hit, &next, sample.pdf, self.specular);
+                self.hit = next;
+                self.bounce += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the weight of the ambient light picked up by a ray, whose
+    /// [continuation](Self::continue_ray) escaped the scene.
+    ///
+    /// # Arguments
+    /// * `scene` - The scene
+    /// * `strategy` - The direct light strategy at the hit
+    /// * `sample` - The BSDF sample of the escaping ray
+    ///
+    /// # Returns
+    /// * The weight of the ambient light
+    pub fn escape_weight<S>(
+        &self,
+        scene: &Scene,
+        strategy: DirectLightStrategy,
+        sample: &BxDFSample<S>,
+    ) -> Float {
+        escape_weight(scene, strategy, &self.hit, sample.pdf, self.specular)
+    }
+}
+
+impl PathState<Spectrum> {
+    /// Attenuates the throughput by a BSDF sample at the hit.
+    ///
+    /// # Arguments
+    /// * `sample` - The BSDF sample
+    ///
+    /// # Returns
+    /// * Whether the sample carries any throughput, otherwise the path terminates
+    pub fn attenuate(&mut self, sample: &BxDFSample<Spectrum>) -> bool {
+        if sample.pdf == 0.0 || sample.spectrum.is_black() {
+            return false;
+        }
+
+        self.throughput *= sample.spectrum * self.factor(sample);
+        self.specular = sample.typ.is_specular();
+
+        true
+    }
+
+    /// Returns the hit emitter (if any) with its weighted emission, attenuated by the throughput.
+    pub fn emission(&self) -> Option<(&Emitter, Spectrum)> {
+        match &self.hit.object {
+            SceneObject::Emitter(e) => Some((
+                e.as_ref(),
+                self.throughput * e.emission() * self.emission_weight,
+            )),
+            SceneObject::Receiver(_) => None,
+        }
+    }
+
+    /// Adds the weighted emission of the hit (if an emitter) to the illumination.
+    ///
+    /// # Arguments
+    /// * `illumination` - The illumination of the path
+    pub fn add_emission(&self, illumination: &mut Spectrum) {
+        if let Some((_, emission)) = self.emission() {
+            *illumination += emission;
+        }
+    }
+}
+
+impl PathState<&mut [Float]> {
+    /// Attenuates the throughput buffer by a BSDF sample of a bundle of wavelengths at the hit.
+    ///
+    /// # Arguments
+    /// * `sample` - The BSDF sample, holding a value per wavelength of the buffer
+    ///
+    /// # Returns
+    /// * Whether the sample carries any throughput, otherwise the path terminates
+    pub fn attenuate(&mut self, sample: &BxDFSample<Vec<Float>>) -> bool {
+        debug_assert_eq!(self.throughput.len(), sample.spectrum.len());
+
+        if sample.pdf == 0.0 || sample.spectrum.iter().all(|&s| s == 0.0) {
+            return false;
+        }
+
+        let factor = self.factor(sample);
+        for (t, s) in self.throughput.iter_mut().zip(&sample.spectrum) {
+            *t *= s * factor;
+        }
+        self.specular = sample.typ.is_specular();
+
+        true
+    }
+
+    /// Adds the weighted emission of the hit (if an emitter) to the illumination buffer.
+    ///
+    /// # Arguments
+    /// * `indices` - The wavelength indices of the buffers
+    /// * `illumination` - The illumination buffer of the path
+    pub fn add_emission(&self, indices: &[usize], illumination: &mut [Float]) {
+        if let SceneObject::Emitter(e) = &self.hit.object {
+            self.add_scaled(&e.emission(), self.emission_weight, indices, illumination);
+        }
+    }
+
+    /// Adds the given spectrum, attenuated by the throughput and scaled by a weight, to the
+    /// illumination buffer, e.g. the ambient light of an escaped path.
+    ///
+    /// # Arguments
+    /// * `spectrum` - The spectrum of all wavelengths
+    /// * `weight` - The weight of the spectrum
+    /// * `indices` - The wavelength indices of the buffers
+    /// * `illumination` - The illumination buffer of the path
+    pub fn add_scaled(
+        &self,
+        spectrum: &Spectrum,
+        weight: Float,
+        indices: &[usize],
+        illumination: &mut [Float],
+    ) {
+        debug_assert_eq!(indices.len(), illumination.len());
+
+        for ((l, t), &index) in illumination
+            .iter_mut()
+            .zip(self.throughput.iter())
+            .zip(indices)
+        {
+            *l += t * spectrum[index] * weight;
+        }
+    }
+}
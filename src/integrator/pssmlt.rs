@@ -0,0 +1,303 @@
+use crate::bxdf::Type;
+use crate::integrator::Integrator;
+use crate::objects::SceneObject;
+use crate::samplers::{Sample, Sampler};
+use crate::scene::Scene;
+use crate::sensor::pixel::Pixel;
+use crate::{Float, Spectrum, Vector2};
+use color::Color;
+use geometry::Ray;
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::TAU;
+#[cfg(feature = "f64")]
+use std::f64::consts::TAU;
+
+fn default_bootstrap_samples() -> u32 {
+    16
+}
+
+fn default_mutations() -> u32 {
+    64
+}
+
+fn default_large_step_probability() -> Float {
+    0.3
+}
+
+fn default_sigma() -> Float {
+    0.01
+}
+
+/// The number of primary samples consumed by each bounce: one to select a light, two to sample
+/// it and three to sample the BSDF. A fixed number keeps the samples of the same bounce aligned
+/// between mutations.
+const SAMPLES_PER_BOUNCE: usize = 6;
+
+/// A path in primary sample space, i.e. the random numbers used to construct it.
+/// Missing samples get drawn lazily.
+#[derive(Clone)]
+struct PrimarySamples {
+    values: Vec<Float>,
+    index: usize,
+    sampler: Sampler,
+}
+
+impl PrimarySamples {
+    fn new(sampler: Sampler, max_depth: u32) -> Self {
+        Self {
+            values: Vec::with_capacity(max_depth as usize * SAMPLES_PER_BOUNCE),
+            index: 0,
+            sampler,
+        }
+    }
+
+    fn next(&mut self) -> Float {
+        if self.index == self.values.len() {
+            self.values.push(self.sampler.get_1d());
+        }
+
+        let value = self.values[self.index];
+        self.index += 1;
+        value
+    }
+
+    fn next_2d(&mut self) -> Vector2 {
+        Vector2::new(self.next(), self.next())
+    }
+
+    /// Proposes a new path by either drawing all samples anew (large step) or perturbing each
+    /// sample by a gaussian of the given standard deviation (small step), wrapping around inside
+    /// `[0, 1)`.
+    fn mutate(&self, large_step: bool, sigma: Float) -> Self {
+        let values = self
+            .values
+            .iter()
+            .map(|&v| {
+                if large_step {
+                    self.sampler.get_1d()
+                } else {
+                    let v = v + sigma * self.gaussian();
+                    let v = v - v.floor();
+                    // rounding may push tiny negative values onto 1
+                    if v < 1.0 {
+                        v
+                    } else {
+                        0.0
+                    }
+                }
+            })
+            .collect();
+
+        Self {
+            values,
+            index: 0,
+            sampler: self.sampler,
+        }
+    }
+
+    /// Draws a standard normal value by the Box-Muller transform.
+    fn gaussian(&self) -> Float {
+        let u1 = 1.0 - self.sampler.get_1d();
+        let u2 = self.sampler.get_1d();
+
+        (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+    }
+}
+
+/// A state of the Markov chain: the path in primary sample space with its radiance.
+#[derive(Clone)]
+struct State {
+    samples: PrimarySamples,
+    radiance: Spectrum,
+    /// The scalar contribution the chain is distributed proportionally to
+    luminance: Float,
+}
+
+/// Primary sample space Metropolis light transport (PSSMLT) after Kelemen et al.
+///
+/// Instead of sampling each path independently, it mutates the random numbers of a path
+/// tracer, exploring paths of high contribution (e.g. caustics) locally once found.
+///
+/// Each pixel runs its own Markov chain per pass: `bootstrap_samples` independent paths estimate
+/// the normalization and select the initial state proportionally to their luminance, followed by
+/// `mutations` mutations of which a fraction of `large_step_probability` draws a fresh path.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Pssmlt {
+    max_depth: u32,
+    #[serde(default = "default_bootstrap_samples")]
+    bootstrap_samples: u32,
+    #[serde(default = "default_mutations")]
+    mutations: u32,
+    #[serde(default = "default_large_step_probability")]
+    large_step_probability: Float,
+    /// The standard deviation of small step perturbations
+    #[serde(default = "default_sigma")]
+    sigma: Float,
+}
+
+impl Pssmlt {
+    /// Creates a new PSSMLT integrator with the default chain settings.
+    ///
+    /// # Arguments
+    /// * `max_depth` - The depth of light bounces
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(max_depth: u32) -> Self {
+        Self {
+            max_depth,
+            bootstrap_samples: default_bootstrap_samples(),
+            mutations: default_mutations(),
+            large_step_probability: default_large_step_probability(),
+            sigma: default_sigma(),
+        }
+    }
+
+    /// Traces a path with next event estimation, drawing all random numbers from the given
+    /// primary samples.
+    ///
+    /// # Returns
+    /// * The radiance arriving along the primary ray
+    fn radiance(&self, scene: &Scene, primary_ray: &Ray, samples: &mut PrimarySamples) -> Spectrum {
+        let mut illumination = Spectrum::broadcast(0.0);
+        let mut throughput = Spectrum::broadcast(1.0);
+        let mut ray = *primary_ray;
+        // emitters hit after non-specular bounces are covered by next event estimation
+        let mut specular = true;
+
+        for _ in 0..self.max_depth {
+            let light_sample = samples.next();
+            let emitter_sample = samples.next_2d();
+            let bsdf_sample = Sample::new(samples.next(), samples.next_2d());
+
+            let hit = match scene.intersect(&ray) {
+                Some(hit) => hit,
+                None => {
+                    illumination += throughput * scene.ambient();
+                    break;
+                }
+            };
+
+            if let SceneObject::Emitter(e) = &hit.object {
+                if specular {
+                    illumination += throughput * e.emission();
+                }
+            }
+
+            let bsdf = hit.object.bsdf();
+            let outgoing = -ray.direction;
+
+            let emitters = &scene.emitters;
+            if !bsdf.is_empty() && !emitters.is_empty() {
+                let index = (light_sample * emitters.len() as Float) as usize;
+                let light = &emitters[index.min(emitters.len() - 1)];
+
+                if light.is_linked(&hit.object) {
+                    let sample = light.sample(hit.point, emitter_sample);
+
+                    if sample.pdf > 0.0
+                        && !sample.radiance.is_black()
+                        && sample.occlusion_tester.unoccluded(scene)
+                    {
+                        let bsdf_spectrum =
+                            bsdf.evaluate(hit.frame(), sample.incident, outgoing, Type::ALL);
+                        let cos = sample.incident.dot(hit.normal).abs();
+
+                        // the light got selected with a probability of `1 / n`
+                        illumination += throughput
+                            * bsdf_spectrum
+                            * sample.radiance
+                            * (cos * emitters.len() as Float / sample.pdf);
+                    }
+                }
+            }
+
+            let bxdf_sample = match bsdf.sample(hit.frame(), outgoing, Type::ALL, bsdf_sample) {
+                Some(sample) if sample.pdf > 0.0 && !sample.spectrum.is_black() => sample,
+                _ => break,
+            };
+
+            specular = bxdf_sample.typ.is_specular();
+            let cos = if specular {
+                // division of cosine omitted in specular bxdfs
+                1.0
+            } else {
+                bxdf_sample.incident.dot(hit.normal).abs()
+            };
+
+            throughput *= bxdf_sample.spectrum * (cos / bxdf_sample.pdf);
+            ray = scene.offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
+        }
+
+        illumination
+    }
+
+    fn state(&self, scene: &Scene, primary_ray: &Ray, mut samples: PrimarySamples) -> State {
+        let radiance = self.radiance(scene, primary_ray, &mut samples);
+        let luminance = radiance.luminance().max(0.0);
+
+        State {
+            samples,
+            radiance,
+            luminance,
+        }
+    }
+}
+
+#[typetag::serde]
+impl Integrator for Pssmlt {
+    fn integrate(&self, pixel: &mut Pixel, scene: &Scene, primary_ray: &Ray, sampler: Sampler) {
+        // bootstrap: estimate the normalization and select the initial state proportionally to
+        // its luminance by reservoir sampling
+        let mut normalization = 0.0;
+        let mut current = None;
+
+        for _ in 0..self.bootstrap_samples.max(1) {
+            let state = self.state(
+                scene,
+                primary_ray,
+                PrimarySamples::new(sampler, self.max_depth),
+            );
+            normalization += state.luminance;
+
+            if state.luminance > 0.0 && sampler.get_1d() * normalization < state.luminance {
+                current = Some(state);
+            }
+        }
+        normalization /= self.bootstrap_samples.max(1) as Float;
+
+        let mut current = match current {
+            Some(state) => state,
+            None => {
+                pixel.add_black();
+                return;
+            }
+        };
+
+        // accumulates the expected values of both the current and the proposed state
+        let mut accumulated = Spectrum::broadcast(0.0);
+
+        for _ in 0..self.mutations {
+            let large_step = sampler.get_1d() < self.large_step_probability;
+            let proposal = self.state(
+                scene,
+                primary_ray,
+                current.samples.mutate(large_step, self.sigma),
+            );
+
+            let acceptance = (proposal.luminance / current.luminance).min(1.0);
+
+            if proposal.luminance > 0.0 {
+                accumulated += proposal.radiance * (acceptance / proposal.luminance);
+            }
+            accumulated += current.radiance * ((1.0 - acceptance) / current.luminance);
+
+            if sampler.get_1d() < acceptance {
+                current = proposal;
+            }
+        }
+
+        pixel.add(accumulated * (normalization / self.mutations.max(1) as Float));
+    }
+}
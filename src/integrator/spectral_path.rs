@@ -1,14 +1,15 @@
 use crate::bxdf::{BxDFSampleResult, Type};
 use crate::integrator::{
-    direct_illumination_buf, direct_illumination_wavelength, DirectLightStrategy, Integrator,
+    default_ray_budget, direct_illumination_buf, trace_scattered_bundle, DirectLightStrategy,
+    Integrator, PathState, RayBudget,
 };
 use crate::objects::SceneObject;
 use crate::samplers::spectral_samplers::SpectralSampler;
 use crate::samplers::Sampler;
 use crate::scene::{Scene, SceneIntersection};
 use crate::sensor::pixel::Pixel;
-use crate::Float;
-use geometry::{offset_ray_towards, Ray};
+use crate::{Float, Spectrum};
+use geometry::Ray;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -17,167 +18,123 @@ pub struct SpectralPath {
     light_wave_samples: u32,
     direct_light_strategy: DirectLightStrategy,
     spectral_sampler: SpectralSampler,
+    /// Whether the first bounce evaluates all wavelengths, while deeper bounces only follow the
+    /// sampled set of `light_wave_samples`.
+    #[serde(default)]
+    full_first_bounce: bool,
+    /// The maximum number of rays a path spends on following wavelengths on their own, after
+    /// dispersion scattered them apart
+    #[serde(default = "default_ray_budget")]
+    ray_budget: u32,
 }
 
 impl SpectralPath {
-    #[allow(clippy::too_many_arguments)]
-    fn trace_single(
-        &self,
-        scene: &Scene,
-        mut hit: SceneIntersection,
-        sampler: Sampler,
-        index: usize,
-        illumination: &mut Float,
-        throughput: &mut Float,
-        current_bounce: u32,
-    ) {
-        for _ in current_bounce..self.max_depth {
-            let outgoing = -hit.ray.direction;
-            let normal = hit.normal;
-            let bsdf = hit.object.bsdf();
+    /// Computes the emission and direct illumination of all wavelengths at the first bounce.
+    fn first_bounce(&self, scene: &Scene, hit: &SceneIntersection, sampler: Sampler) -> Vec<Float> {
+        let size = Spectrum::size();
+        let mut illumination = vec![0.0; size];
 
-            if let SceneObject::Emitter(e) = &hit.object {
-                *illumination += *throughput * e.emission[index];
-            } else {
-                *illumination += *throughput
-                    * direct_illumination_wavelength(
-                        scene,
-                        sampler,
-                        self.direct_light_strategy,
-                        &hit,
-                        bsdf,
-                        index,
-                    );
+        if let SceneObject::Emitter(e) = &hit.object {
+            for (i, l) in illumination.iter_mut().enumerate() {
+                *l = e.emission_wavelength(i);
             }
+        }
 
-            if let Some(bxdf_sample) =
-                bsdf.sample_light_wave(normal, outgoing, Type::ALL, sampler.get_sample(), index)
-            {
-                if bxdf_sample.pdf == 0.0 || bxdf_sample.spectrum == 0.0 {
-                    break;
-                }
-
-                let cos_abs = if bxdf_sample.typ.is_specular() {
-                    // division of cosine omitted in specular bxdfs
-                    1.0
-                } else {
-                    bxdf_sample.incident.dot(normal).abs()
-                };
+        let indices: Vec<usize> = (0..size).collect();
+        let throughput = vec![1.0; size];
 
-                *throughput *= bxdf_sample.spectrum * cos_abs / bxdf_sample.pdf;
+        direct_illumination_buf(
+            scene,
+            sampler,
+            self.direct_light_strategy,
+            hit,
+            hit.object.bsdf(),
+            &indices,
+            &mut illumination,
+            &throughput,
+        );
 
-                let ray = offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
-                match scene.intersect(&ray) {
-                    Some(i) => hit = i,
-                    None => break,
-                }
-            } else {
-                break;
-            }
-        }
+        illumination
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn trace(
         &self,
         scene: &Scene,
-        mut hit: SceneIntersection,
+        hit: SceneIntersection,
         sampler: Sampler,
         indices: &[usize],
         illumination: &mut [Float],
         throughput: &mut [Float],
+        direct_from: u32,
     ) {
         let buf_size = indices.len();
         assert_eq!(buf_size, illumination.len());
         assert_eq!(buf_size, throughput.len());
 
-        for bounce in 0..self.max_depth {
-            let outgoing = -hit.ray.direction;
-            let normal = hit.normal;
+        let mut state = PathState::new(hit, throughput);
+        let mut budget = RayBudget::new(self.ray_budget);
+
+        while state.bounce < self.max_depth {
+            let hit = &state.hit;
             let bsdf = hit.object.bsdf();
 
-            if let SceneObject::Emitter(e) = &hit.object {
-                for i in 0..buf_size {
-                    illumination[i] = throughput[i] * e.emission[i];
-                }
-            } else {
-                // add direction illumination
+            // bounces before `direct_from` are already accounted for by the caller
+            if state.bounce >= direct_from {
+                state.add_emission(indices, illumination);
+
+                // add direct illumination
                 direct_illumination_buf(
                     scene,
                     sampler,
                     self.direct_light_strategy,
-                    &hit,
+                    hit,
                     bsdf,
                     indices,
                     illumination,
-                    throughput,
+                    state.throughput,
                 );
             }
 
-            if let Some(spectral_sample) =
-                bsdf.sample_buf(normal, outgoing, Type::ALL, sampler.get_sample(), indices)
-            {
-                match spectral_sample {
-                    BxDFSampleResult::Bundle(bxdf_sample) => {
-                        if bxdf_sample.pdf == 0.0 || bxdf_sample.spectrum.iter().all(|&s| s == 0.0)
-                        {
-                            break;
-                        }
-
-                        let cos_abs = if bxdf_sample.typ.is_specular() {
-                            // division of cosine omitted in specular bxdfs
-                            1.0
-                        } else {
-                            bxdf_sample.incident.dot(normal).abs()
-                        };
-
-                        let mul = cos_abs / bxdf_sample.pdf;
-                        for (t, s) in throughput.iter_mut().zip(bxdf_sample.spectrum) {
-                            *t *= s * mul;
-                        }
-
-                        let ray = offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
-                        match scene.intersect(&ray) {
-                            Some(i) => hit = i,
-                            None => break,
-                        }
+            let spectral_sample = match bsdf.sample_buf(
+                hit.frame(),
+                -hit.ray.direction,
+                Type::ALL,
+                sampler.get_sample(),
+                indices,
+            ) {
+                Some(s) => s,
+                None => break,
+            };
+
+            match spectral_sample {
+                BxDFSampleResult::Bundle(bxdf_sample) => {
+                    if !state.attenuate(&bxdf_sample) {
+                        break;
                     }
-                    BxDFSampleResult::ScatteredBundle(bundle) => {
-                        for (index, sample) in bundle.iter().enumerate() {
-                            if sample.pdf == 0.0 || sample.intensity == 0.0 {
-                                continue;
-                            }
-
-                            let cos_abs = if sample.typ.is_specular() {
-                                // division of cosine omitted in specular bxdfs
-                                1.0
-                            } else {
-                                sample.incident.dot(normal).abs()
-                            };
-
-                            throughput[index] *= sample.intensity * cos_abs / sample.pdf;
-
-                            let ray = offset_ray_towards(hit.point, hit.normal, sample.incident);
-                            match scene.intersect(&ray) {
-                                Some(new_hit) => {
-                                    self.trace_single(
-                                        scene,
-                                        new_hit,
-                                        sampler,
-                                        sample.index,
-                                        &mut illumination[index],
-                                        &mut throughput[index],
-                                        bounce,
-                                    );
-                                }
-                                None => continue,
-                            }
-                        }
 
+                    let strategy = self.direct_light_strategy;
+                    if !state.continue_ray(scene, strategy, &bxdf_sample) {
+                        let weight = state.escape_weight(scene, strategy, &bxdf_sample);
+                        state.add_scaled(&scene.ambient(), weight, indices, illumination);
                         break;
                     }
                 }
-            } else {
-                break;
+                BxDFSampleResult::ScatteredBundle(bundle) => {
+                    trace_scattered_bundle(
+                        scene,
+                        hit,
+                        sampler,
+                        self.direct_light_strategy,
+                        &bundle,
+                        state.bounce..self.max_depth,
+                        illumination,
+                        state.throughput,
+                        &mut budget,
+                    );
+
+                    break;
+                }
             }
         }
     }
@@ -195,6 +152,32 @@ impl Integrator for SpectralPath {
 
             self.spectral_sampler.fill_samples(&mut indices);
 
+            if self.full_first_bounce && len < Spectrum::size() {
+                let mut first = self.first_bounce(scene, &hit, sampler);
+
+                self.trace(
+                    scene,
+                    hit,
+                    sampler,
+                    &indices,
+                    &mut illumination,
+                    &mut throughput,
+                    1,
+                );
+
+                // each wavelength is expected to be sampled `len / size` times
+                let scale = Spectrum::size() as Float / len as Float;
+                for i in 0..len {
+                    first[indices[i]] += illumination[i] * scale;
+                }
+
+                for (index, lambda) in first.into_iter().enumerate() {
+                    pixel.add_light_wave(lambda, index);
+                }
+
+                return;
+            }
+
             self.trace(
                 scene,
                 hit,
@@ -202,6 +185,7 @@ impl Integrator for SpectralPath {
                 &indices,
                 &mut illumination,
                 &mut throughput,
+                0,
             );
 
             for i in 0..len {
@@ -210,8 +194,91 @@ impl Integrator for SpectralPath {
 
                 pixel.add_light_wave(lambda, index);
             }
-        } else {
+        } else if scene.ambient_light.is_black() {
             pixel.add_black();
+        } else {
+            let mut indices = vec![0; self.light_wave_samples as usize];
+
+            self.spectral_sampler.fill_samples(&mut indices);
+
+            for index in indices {
+                pixel.add_light_wave(scene.ambient()[index], index);
+            }
         }
     }
 }
+
+#[test]
+fn full_first_bounce_evaluates_all_wavelengths() {
+    use crate::renderer::Renderer;
+
+    let render = |light_wave_samples: usize, full_first_bounce: bool| {
+        let source = format!(
+            r#"(
+                config: (
+                    filename: None,
+                    bounds: None,
+                    block_size: (x: 4, y: 4),
+                    passes: 1,
+                    threads: Some(2),
+                ),
+                camera: {{
+                    "PerspectiveCamera": (
+                        Sampler: NoOp,
+                        Position: (x: 0, y: 0, z: 4),
+                        Target: (x: 0, y: 0, z: 0),
+                        Up: (x: 0, y: 1, z: 0),
+                        FovY: 40,
+                        Resolution: (x: 8, y: 8),
+                    ),
+                }},
+                integrator: {{
+                    "SpectralPath": (
+                        max_depth: 1,
+                        light_wave_samples: {},
+                        direct_light_strategy: All,
+                        spectral_sampler: Random,
+                        full_first_bounce: {},
+                    ),
+                }},
+                sampler: NoOp,
+                scene: (
+                    bounding_box: (
+                        min: (x: -1, y: -1, z: -1),
+                        max: (x: 1, y: 3, z: 2),
+                    ),
+                    objects: [
+                        Receiver((
+                            geometry: {{"Sphere": (center: (x: 0, y: 0, z: 0), radius: 1)}},
+                            bsdf: (bxdfs: [{{"LambertianReflection": (r: Constant(0.8))}}]),
+                        )),
+                        Emitter((
+                            geometry: {{"Point": ((x: 0, y: 3, z: 2))}},
+                            emission: Constant(10),
+                        )),
+                    ],
+                ),
+            )"#,
+            light_wave_samples, full_first_bounce
+        );
+
+        let mut renderer = Renderer::new(ron::from_str(&source).unwrap());
+        renderer.render().join().unwrap();
+        let frame = renderer.get_frame();
+
+        (0..8)
+            .flat_map(|y| (0..8).map(move |x| (x, y)))
+            .flat_map(|(x, y)| frame.get(x, y).data.to_vec())
+            .collect::<Vec<_>>()
+    };
+
+    // a single bounce only consists of the first one, which sees every wavelength
+    let full = render(Spectrum::size(), false);
+    let first = render(1, true);
+
+    assert!(full.iter().any(|&v| v > 0.0));
+    assert_ne!(render(1, false), full);
+    for (a, b) in full.iter().zip(&first) {
+        assert!((a - b).abs() <= 1e-4 * a.max(1.0), "{} != {}", a, b);
+    }
+}
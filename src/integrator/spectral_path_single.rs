@@ -1,20 +1,30 @@
 use crate::bxdf::Type;
-use crate::integrator::{direct_illumination_wavelength, DirectLightStrategy, Integrator};
+use crate::camera::Camera;
+use crate::integrator::{
+    direct_illumination_wavelength, emission_weight, escape_weight, DirectLightStrategy, Integrator,
+};
 use crate::objects::SceneObject;
 use crate::samplers::spectral_samplers::SpectralSampler;
 use crate::samplers::Sampler;
 use crate::scene::{Scene, SceneIntersection};
 use crate::sensor::pixel::Pixel;
-use crate::Float;
-use geometry::{offset_ray_towards, Ray};
+use crate::{Float, Spectrum};
+use geometry::Ray;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// A spectral path tracer following each sampled wavelength on its own.
+///
+/// With a [dispersive](Camera::is_dispersive) camera, each wavelength gets its own primary ray,
+/// such that chromatic aberrations of the camera show.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SpectralPathSingle {
     max_depth: u32,
     light_wave_samples: u32,
     direct_light_strategy: DirectLightStrategy,
     spectral_sampler: SpectralSampler,
+    #[serde(skip)]
+    camera: Option<Arc<dyn Camera>>,
 }
 
 impl SpectralPathSingle {
@@ -27,34 +37,37 @@ impl SpectralPathSingle {
     ) -> Float {
         let mut illumination = 0.0;
         let mut throughput = 1.0;
+        // primary hits see emitters at full weight
+        let mut weight = 1.0;
 
         for _ in 0..self.max_depth {
             let outgoing = -hit.ray.direction;
             let normal = hit.normal;
+            let frame = hit.frame();
             let bsdf = hit.object.bsdf();
 
             if let SceneObject::Emitter(e) = &hit.object {
-                illumination += throughput * e.emission[index];
-            } else {
-                illumination += throughput
-                    * direct_illumination_wavelength(
-                        scene,
-                        sampler,
-                        self.direct_light_strategy,
-                        &hit,
-                        bsdf,
-                        index,
-                    );
+                illumination += throughput * e.emission_wavelength(index) * weight;
             }
+            illumination += throughput
+                * direct_illumination_wavelength(
+                    scene,
+                    sampler,
+                    self.direct_light_strategy,
+                    &hit,
+                    bsdf,
+                    index,
+                );
 
             if let Some(bxdf_sample) =
-                bsdf.sample_light_wave(normal, outgoing, Type::ALL, sampler.get_sample(), index)
+                bsdf.sample_light_wave(frame, outgoing, Type::ALL, sampler.get_sample(), index)
             {
                 if bxdf_sample.pdf == 0.0 || bxdf_sample.spectrum == 0.0 {
                     break;
                 }
 
-                let cos_abs = if bxdf_sample.typ.is_specular() {
+                let specular = bxdf_sample.typ.is_specular();
+                let cos_abs = if specular {
                     // division of cosine omitted in specular bxdfs
                     1.0
                 } else {
@@ -63,10 +76,30 @@ impl SpectralPathSingle {
 
                 throughput *= bxdf_sample.spectrum * cos_abs / bxdf_sample.pdf;
 
-                let ray = offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
+                let ray = scene.offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
                 match scene.intersect(&ray) {
-                    Some(i) => hit = i,
-                    None => break,
+                    Some(i) => {
+                        weight = emission_weight(
+                            scene,
+                            self.direct_light_strategy,
+                            &hit,
+                            &i,
+                            bxdf_sample.pdf,
+                            specular,
+                        );
+                        hit = i;
+                    }
+                    None => {
+                        let weight = escape_weight(
+                            scene,
+                            self.direct_light_strategy,
+                            &hit,
+                            bxdf_sample.pdf,
+                            specular,
+                        );
+                        illumination += throughput * scene.ambient()[index] * weight;
+                        break;
+                    }
                 }
             } else {
                 break;
@@ -75,11 +108,44 @@ impl SpectralPathSingle {
 
         illumination
     }
+
+    /// Integrates each sampled wavelength along its own primary ray of the given camera.
+    fn integrate_dispersive(
+        &self,
+        pixel: &mut Pixel,
+        scene: &Scene,
+        camera: &dyn Camera,
+        sampler: Sampler,
+    ) {
+        let mut indices = vec![0; self.light_wave_samples as usize];
+
+        self.spectral_sampler.fill_samples(&mut indices);
+
+        for index in indices {
+            let lambda = Spectrum::lambda_of_index(index);
+            let ray = camera.primary_ray_wavelength(pixel.position, lambda);
+
+            let illumination = if ray.is_empty() {
+                0.0
+            } else if let Some(hit) = scene.intersect(&ray) {
+                self.trace_single(scene, hit, sampler, index)
+            } else {
+                scene.ambient()[index]
+            };
+
+            pixel.add_light_wave(illumination, index);
+        }
+    }
 }
 
 #[typetag::serde]
 impl Integrator for SpectralPathSingle {
     fn integrate(&self, pixel: &mut Pixel, scene: &Scene, primary_ray: &Ray, sampler: Sampler) {
+        if let Some(camera) = self.camera.as_ref().filter(|c| c.is_dispersive()) {
+            self.integrate_dispersive(pixel, scene, camera.as_ref(), sampler);
+            return;
+        }
+
         if let Some(hit) = scene.intersect(primary_ray) {
             let mut indices = vec![0; self.light_wave_samples as usize];
 
@@ -89,8 +155,20 @@ impl Integrator for SpectralPathSingle {
                 let lambda = self.trace_single(scene, hit.clone(), sampler, index);
                 pixel.add_light_wave(lambda, index);
             }
-        } else {
+        } else if scene.ambient_light.is_black() {
             pixel.add_black();
+        } else {
+            let mut indices = vec![0; self.light_wave_samples as usize];
+
+            self.spectral_sampler.fill_samples(&mut indices);
+
+            for index in indices {
+                pixel.add_light_wave(scene.ambient()[index], index);
+            }
         }
     }
+
+    fn init(&mut self, camera: &(dyn Camera + 'static)) {
+        self.camera = Some(Arc::from(dyn_clone::clone_box(camera)));
+    }
 }
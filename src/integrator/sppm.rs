@@ -0,0 +1,371 @@
+use crate::bxdf::{Type, BSDF};
+use crate::integrator::{direct_illumination, Integrator};
+use crate::objects::SceneObject;
+use crate::samplers::Sampler;
+use crate::scene::{Scene, SceneIntersection};
+use crate::sensor::pixel::Pixel;
+use crate::sensor::Sensor;
+use crate::{Float, Spectrum, Vector3};
+use color::Color;
+use geometry::Ray;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::PI;
+#[cfg(feature = "f64")]
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+fn default_alpha() -> Float {
+    2.0 / 3.0
+}
+
+/// The number of photon maps kept for passes still being rendered by other threads.
+const CACHED_PHOTON_MAPS: usize = 4;
+
+/// The photon maps of the most recent passes, identified by their pass.
+type PhotonMaps = Vec<(u32, Arc<PhotonMap>)>;
+
+/// All BxDF types except specular ones, which cannot receive photons.
+fn non_specular() -> Type {
+    Type::ALL & !Type::SPECULAR
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Photon {
+    position: Vector3,
+    /// The direction the photon arrived from
+    incident: Vector3,
+    flux: Spectrum,
+}
+
+/// The photons of a pass, stored in a uniform grid with cells of the size of the gather radius.
+#[derive(Debug)]
+struct PhotonMap {
+    radius: Float,
+    /// The number of photons emitted, normalizing the flux of the stored ones
+    emitted: u32,
+    cells: HashMap<(i32, i32, i32), Vec<Photon>>,
+}
+
+impl PhotonMap {
+    fn new(radius: Float, emitted: u32) -> Self {
+        Self {
+            radius,
+            emitted,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, position: Vector3) -> (i32, i32, i32) {
+        let cell = position / self.radius;
+
+        (
+            cell.x.floor() as i32,
+            cell.y.floor() as i32,
+            cell.z.floor() as i32,
+        )
+    }
+
+    fn insert(&mut self, photon: Photon) {
+        let cell = self.cell(photon.position);
+        self.cells.entry(cell).or_default().push(photon);
+    }
+
+    /// Estimates the radiance leaving the given hit towards `outgoing` from the photons within
+    /// the gather radius.
+    fn estimate(&self, hit: &SceneIntersection, bsdf: &BSDF, outgoing: Vector3) -> Spectrum {
+        let mut flux = Spectrum::broadcast(0.0);
+        let radius_sq = self.radius * self.radius;
+        let (x, y, z) = self.cell(hit.point);
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let photons = match self.cells.get(&(x + dx, y + dy, z + dz)) {
+                        Some(photons) => photons,
+                        None => continue,
+                    };
+
+                    for photon in photons {
+                        if (photon.position - hit.point).mag_sq() <= radius_sq {
+                            let bsdf_spectrum =
+                                bsdf.evaluate(hit.frame(), photon.incident, outgoing, Type::ALL);
+                            flux += bsdf_spectrum * photon.flux;
+                        }
+                    }
+                }
+            }
+        }
+
+        flux / (PI * radius_sq * self.emitted as Float)
+    }
+}
+
+/// Stochastic progressive photon mapping (SPPM) in the probabilistic formulation of Knaus and
+/// Zwicker.
+///
+/// Each pass shoots `photons` photons into the scene and estimates the indirect illumination at
+/// the first non-specular hit of the camera path from the photons within the gather radius.
+/// The radius shrinks with every pass, such that the average of the passes converges to the
+/// correct image. Direct illumination is sampled at the camera path instead.
+///
+/// The passes are the ones of the renderer, identified by the number of samples of a pixel.
+/// The photon map of the first pass gets built while [preprocessing](Integrator::preprocess),
+/// the ones of later passes by the first thread needing them and shared afterwards.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sppm {
+    max_depth: u32,
+    /// The number of photons emitted per pass
+    photons: u32,
+    /// The gather radius of the first pass
+    initial_radius: Float,
+    /// The fraction of photons kept per pass, controlling how fast the radius shrinks
+    #[serde(default = "default_alpha")]
+    alpha: Float,
+    #[serde(skip)]
+    photon_maps: Arc<Mutex<PhotonMaps>>,
+}
+
+impl Sppm {
+    /// Creates a new SPPM integrator.
+    ///
+    /// # Constraints
+    /// * `initial_radius` - Should be greater than `0`.
+    ///
+    /// # Arguments
+    /// * `max_depth` - The depth of light bounces of both photons and camera paths
+    /// * `photons` - The number of photons emitted per pass
+    /// * `initial_radius` - The gather radius of the first pass
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(max_depth: u32, photons: u32, initial_radius: Float) -> Self {
+        debug_assert!(initial_radius > 0.0);
+
+        Self {
+            max_depth,
+            photons,
+            initial_radius,
+            alpha: default_alpha(),
+            photon_maps: Arc::default(),
+        }
+    }
+
+    /// Returns the gather radius of the given pass, shrinking the squared radius by
+    /// `(i + alpha) / (i + 1)` after each pass `i`.
+    ///
+    /// # Arguments
+    /// * `pass` - The pass, starting at `0`
+    ///
+    /// # Returns
+    /// * The gather radius
+    pub fn radius(&self, pass: u32) -> Float {
+        let radius_sq = (1..=pass).fold(self.initial_radius * self.initial_radius, |r, i| {
+            r * (i as Float + self.alpha) / (i as Float + 1.0)
+        });
+
+        radius_sq.sqrt()
+    }
+
+    /// Returns the photon map of the given pass, building it if necessary.
+    fn photon_map(&self, scene: &Scene, pass: u32, sampler: Sampler) -> Arc<PhotonMap> {
+        let mut maps = self.photon_maps.lock();
+
+        if let Some((_, map)) = maps.iter().find(|(p, _)| *p == pass) {
+            return map.clone();
+        }
+
+        let map = Arc::new(self.shoot_photons(scene, pass, sampler));
+        if maps.len() == CACHED_PHOTON_MAPS {
+            // evict the oldest pass
+            let oldest = (0..maps.len()).min_by_key(|&i| maps[i].0).unwrap();
+            maps.swap_remove(oldest);
+        }
+        maps.push((pass, map.clone()));
+
+        map
+    }
+
+    /// Traces the photons of a pass, storing them at each non-specular hit after the first
+    /// bounce, as direct illumination gets sampled separately.
+    fn shoot_photons(&self, scene: &Scene, pass: u32, sampler: Sampler) -> PhotonMap {
+        let mut map = PhotonMap::new(self.radius(pass), self.photons);
+        let emitters = &scene.emitters;
+
+        if emitters.is_empty() {
+            return map;
+        }
+
+        for _ in 0..self.photons {
+            let index = (sampler.get_1d() * emitters.len() as Float) as usize;
+            let emitter = &emitters[index.min(emitters.len() - 1)];
+
+            let (surface, direction, flux) = emitter.sample_ray(sampler.get_2d(), sampler.get_2d());
+            // the emitter got selected with a probability of `1 / n`
+            let mut flux = flux * emitters.len() as Float;
+            let mut ray = scene.offset_ray_towards(surface.point, surface.normal, direction);
+
+            for depth in 0..self.max_depth {
+                let hit = match scene.intersect(&ray) {
+                    Some(hit) => hit,
+                    None => break,
+                };
+
+                let bsdf = hit.object.bsdf();
+                let outgoing = -ray.direction;
+
+                if depth > 0 && bsdf.num_types(non_specular()) > 0 {
+                    map.insert(Photon {
+                        position: hit.point,
+                        incident: outgoing,
+                        flux,
+                    });
+                }
+
+                let bxdf_sample =
+                    match bsdf.sample(hit.frame(), outgoing, Type::ALL, sampler.get_sample()) {
+                        Some(sample) if sample.pdf > 0.0 && !sample.spectrum.is_black() => sample,
+                        _ => break,
+                    };
+
+                let cos = if bxdf_sample.typ.is_specular() {
+                    // division of cosine omitted in specular bxdfs
+                    1.0
+                } else {
+                    bxdf_sample.incident.dot(hit.normal).abs()
+                };
+
+                flux *= bxdf_sample.spectrum * (cos / bxdf_sample.pdf);
+                ray = scene.offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
+            }
+        }
+
+        map
+    }
+}
+
+#[typetag::serde]
+impl Integrator for Sppm {
+    fn preprocess(&mut self, scene: &Scene, _sensor: &Sensor) {
+        let map = Arc::new(self.shoot_photons(scene, 0, Sampler::Random));
+
+        let mut maps = self.photon_maps.lock();
+        maps.clear();
+        maps.push((0, map));
+    }
+
+    fn integrate(&self, pixel: &mut Pixel, scene: &Scene, primary_ray: &Ray, sampler: Sampler) {
+        let pass = pixel.samples()[0];
+        let map = self.photon_map(scene, pass, sampler);
+
+        let mut illumination = Spectrum::broadcast(0.0);
+        let mut throughput = Spectrum::broadcast(1.0);
+        let mut ray = *primary_ray;
+
+        // follows specular bounces until the first non-specular hit gathers the photons
+        for _ in 0..self.max_depth {
+            let hit = match scene.intersect(&ray) {
+                Some(hit) => hit,
+                None => {
+                    illumination += throughput * scene.ambient();
+                    break;
+                }
+            };
+
+            if let SceneObject::Emitter(e) = &hit.object {
+                illumination += throughput * e.emission();
+            }
+
+            let bsdf = hit.object.bsdf();
+            let outgoing = -ray.direction;
+
+            if bsdf.num_types(non_specular()) > 0 {
+                illumination += throughput * direct_illumination(scene, sampler, &hit, bsdf);
+                illumination += throughput * map.estimate(&hit, bsdf, outgoing);
+                break;
+            }
+
+            let bxdf_sample =
+                match bsdf.sample(hit.frame(), outgoing, Type::ALL, sampler.get_sample()) {
+                    Some(sample) if sample.pdf > 0.0 && !sample.spectrum.is_black() => sample,
+                    _ => break,
+                };
+
+            // only specular bxdfs remain, omitting the division of the cosine
+            throughput *= bxdf_sample.spectrum / bxdf_sample.pdf;
+            ray = scene.offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
+        }
+
+        pixel.add(illumination);
+    }
+}
+
+#[test]
+fn radius_shrinks() {
+    let sppm = Sppm::new(4, 1000, 0.5);
+
+    assert_eq!(0.5, sppm.radius(0));
+    // (1 + 2/3) / 2 = 5/6
+    assert!((sppm.radius(1) - 0.5 * (5.0 as Float / 6.0).sqrt()).abs() < 1e-6);
+    assert!(sppm.radius(100) < sppm.radius(10));
+}
+
+#[test]
+fn preprocess_builds_the_first_photon_map() {
+    use crate::sensor::bounds::UBounds2;
+    use crate::Vector2;
+    use ultraviolet::UVec2;
+
+    // a light between a diffuse floor and ceiling, storing photons from the second bounce on
+    let mut scene: Scene = ron::from_str(
+        r#"(
+            bounding_box: (
+                min: (x: -2, y: -1, z: -2),
+                max: (x: 2, y: 3, z: 2),
+            ),
+            objects: [
+                Receiver((
+                    geometry: {"Sphere": (center: (x: 0, y: -101, z: 0), radius: 100)},
+                    bsdf: (bxdfs: [{"LambertianReflection": (r: Constant(0.5))}]),
+                )),
+                Receiver((
+                    geometry: {"Sphere": (center: (x: 0, y: 103, z: 0), radius: 100)},
+                    bsdf: (bxdfs: [{"LambertianReflection": (r: Constant(0.5))}]),
+                )),
+                Emitter((
+                    geometry: {"Sphere": (center: (x: 0, y: 1, z: 0), radius: 0.25)},
+                    emission: Constant(1),
+                )),
+            ],
+        )"#,
+    )
+    .unwrap();
+    scene.init();
+
+    let resolution = UVec2::new(4, 4);
+    let bounds = UBounds2::new(UVec2::zero(), resolution);
+    let sensor = Sensor::new(resolution, None, bounds, resolution, 0, 0, Vector2::zero());
+
+    let mut sppm = Sppm::new(4, 100, 0.5);
+    sppm.preprocess(&scene, &sensor);
+
+    let map = sppm.photon_maps.lock()[0].clone();
+    assert_eq!(0, map.0);
+    assert!(!map.1.cells.is_empty());
+
+    // integrating the first pass reuses the map instead of shooting the photons again
+    assert!(Arc::ptr_eq(
+        &map.1,
+        &sppm.photon_map(&scene, 0, Sampler::NoOp)
+    ));
+    assert_eq!(1, sppm.photon_maps.lock().len());
+
+    // preprocessing again (e.g. after changing the emission) replaces the cached maps
+    sppm.photon_map(&scene, 1, Sampler::NoOp);
+    sppm.preprocess(&scene, &sensor);
+    let maps = sppm.photon_maps.lock();
+    assert_eq!(1, maps.len());
+    assert!(!Arc::ptr_eq(&map.1, &maps[0].1));
+}
@@ -0,0 +1,191 @@
+use crate::bxdf::Type;
+use crate::integrator::{clamp_indirect, Integrator};
+use crate::objects::{Emitter, SceneObject};
+use crate::samplers::Sampler;
+use crate::scene::Scene;
+use crate::sensor::pixel::Pixel;
+use crate::{Float, Spectrum, Vector3};
+use color::Color;
+use geometry::Ray;
+use serde::{Deserialize, Serialize};
+
+/// A path tracer additionally scattering inside the [media](crate::medium::GridMedium) and the
+/// [atmosphere](crate::atmosphere::Atmosphere) of the scene.
+///
+/// Collisions with media get sampled by delta tracking before each surface intersection, while
+/// light samples get attenuated by the transmittance estimated by ratio tracking. Without media,
+/// it traces the same paths as a path tracer sampling all lights at each bounce.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VolumetricPath {
+    max_depth: u32,
+    /// The maximal value of contributions of indirect bounces, suppressing fireflies.
+    #[serde(default)]
+    indirect_clamp: Option<Float>,
+}
+
+impl VolumetricPath {
+    /// Creates a new volumetric path tracer.
+    ///
+    /// # Arguments
+    /// * `max_depth` - The depth of light bounces, including scattering inside media
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(max_depth: u32) -> Self {
+        Self {
+            max_depth,
+            indirect_clamp: None,
+        }
+    }
+
+    /// Clamps the contributions of indirect bounces to the given maximal value, trading bias for
+    /// the suppression of fireflies.
+    ///
+    /// # Constraints
+    /// * `indirect_clamp` - Should be greater than `0`.
+    ///
+    /// # Arguments
+    /// * `indirect_clamp` - The maximal value of indirect contributions
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with_indirect_clamp(mut self, indirect_clamp: Float) -> Self {
+        debug_assert!(indirect_clamp > 0.0);
+
+        self.indirect_clamp = Some(indirect_clamp);
+        self
+    }
+
+    /// Clamps the contribution if it is indirect.
+    fn clamp(&self, indirect: bool, contribution: Spectrum) -> Spectrum {
+        if indirect {
+            clamp_indirect(contribution, self.indirect_clamp)
+        } else {
+            contribution
+        }
+    }
+
+    /// Samples the given light from a point, attenuated by the transmittance towards it.
+    ///
+    /// # Returns
+    /// * The incident direction
+    /// * The attenuated radiance divided by the pdf
+    fn sample_light(
+        scene: &Scene,
+        sampler: Sampler,
+        light: &Emitter,
+        point: Vector3,
+    ) -> Option<(Vector3, Spectrum)> {
+        let sample = light.sample(point, sampler.get_2d());
+
+        if sample.pdf <= 0.0 || sample.radiance.is_black() {
+            return None;
+        }
+
+        let transmittance = sample.occlusion_tester.transmittance(scene, sampler);
+        if transmittance.is_black() {
+            return None;
+        }
+
+        Some((
+            sample.incident,
+            sample.radiance * transmittance / sample.pdf,
+        ))
+    }
+}
+
+#[typetag::serde]
+impl Integrator for VolumetricPath {
+    fn integrate(&self, pixel: &mut Pixel, scene: &Scene, primary_ray: &Ray, sampler: Sampler) {
+        let mut illumination = Spectrum::broadcast(0.0);
+        let mut throughput = Spectrum::broadcast(1.0);
+        let mut ray = *primary_ray;
+        // emitters hit after non-specular bounces are covered by light sampling
+        let mut specular = true;
+
+        for depth in 0..self.max_depth {
+            let hit = scene.intersect(&ray);
+            let t_max = hit.as_ref().map_or(ray.t_end, |h| h.t);
+
+            let (collision, weight) = scene.sample_medium(&ray, t_max, sampler);
+            throughput *= weight;
+            if throughput.is_black() {
+                break;
+            }
+
+            if let Some((scatterer, t)) = collision {
+                let point = ray.at(t);
+
+                for light in &scene.emitters {
+                    if let Some((incident, radiance)) =
+                        Self::sample_light(scene, sampler, light, point)
+                    {
+                        let phase = scatterer.phase(ray.direction, incident);
+                        illumination += self.clamp(depth >= 1, throughput * radiance * phase);
+                    }
+                }
+
+                let (incident, weight) = scatterer.sample_phase(ray.direction, sampler.get_2d());
+                throughput *= weight;
+                ray = Ray::new_fast(point, incident);
+                specular = false;
+                continue;
+            }
+
+            let hit = match hit {
+                Some(hit) => hit,
+                None => {
+                    illumination += self.clamp(depth >= 2, throughput * scene.ambient());
+                    break;
+                }
+            };
+
+            if let SceneObject::Emitter(e) = &hit.object {
+                if specular {
+                    illumination += self.clamp(depth >= 2, throughput * e.emission());
+                }
+            }
+
+            let bsdf = hit.object.bsdf();
+            let outgoing = -ray.direction;
+
+            if !bsdf.is_empty() {
+                for light in &scene.emitters {
+                    if !light.is_linked(&hit.object) {
+                        continue;
+                    }
+
+                    if let Some((incident, radiance)) =
+                        Self::sample_light(scene, sampler, light, hit.point)
+                    {
+                        let bsdf_spectrum =
+                            bsdf.evaluate(hit.frame(), incident, outgoing, Type::ALL);
+                        let cos = incident.dot(hit.normal).abs();
+
+                        let contribution = throughput * bsdf_spectrum * radiance * cos;
+                        illumination += self.clamp(depth >= 1, contribution);
+                    }
+                }
+            }
+
+            let bxdf_sample =
+                match bsdf.sample(hit.frame(), outgoing, Type::ALL, sampler.get_sample()) {
+                    Some(sample) if sample.pdf > 0.0 && !sample.spectrum.is_black() => sample,
+                    _ => break,
+                };
+
+            specular = bxdf_sample.typ.is_specular();
+            let cos = if specular {
+                // division of cosine omitted in specular bxdfs
+                1.0
+            } else {
+                bxdf_sample.incident.dot(hit.normal).abs()
+            };
+
+            throughput *= bxdf_sample.spectrum * (cos / bxdf_sample.pdf);
+            ray = scene.offset_ray_towards(hit.point, hit.normal, bxdf_sample.incident);
+        }
+
+        pixel.add(illumination);
+    }
+}
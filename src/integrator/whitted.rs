@@ -1,18 +1,24 @@
 use crate::bxdf::Type;
-use crate::integrator::{direct_illumination, Integrator};
+use crate::camera::Camera;
+use crate::integrator::{
+    direct_illumination, unshadowed_light_samples, DirectLightStrategy, Integrator, PathState,
+};
 use crate::objects::SceneObject;
 use crate::samplers::Sampler;
 use crate::scene::{Scene, SceneIntersection};
 use crate::sensor::pixel::Pixel;
 use crate::Spectrum;
 use color::Color;
-use geometry::{offset_ray_towards, Ray};
+use geometry::Ray;
 use serde::{Deserialize, Serialize};
 
 /// The Whitted integrator is a common integrator following specular reflection/transmission recursively.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Whitted {
     max_depth: u32,
+    /// Whether to trace the shadow rays of the primary hits of a tile together
+    #[serde(default)]
+    batch_shadow_rays: bool,
 }
 
 impl Whitted {
@@ -24,7 +30,22 @@ impl Whitted {
     /// # Returns
     /// * Self
     pub fn new(max_depth: u32) -> Self {
-        Self { max_depth }
+        Self {
+            max_depth,
+            batch_shadow_rays: false,
+        }
+    }
+
+    /// Traces the shadow rays of the primary hits of each tile together, sorted to be coherent.
+    ///
+    /// # Arguments
+    /// * `batch_shadow_rays` - Whether to batch the shadow rays
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with_batched_shadow_rays(mut self, batch_shadow_rays: bool) -> Self {
+        self.batch_shadow_rays = batch_shadow_rays;
+        self
     }
 
     fn illumination(
@@ -34,17 +55,27 @@ impl Whitted {
         sampler: Sampler,
         depth: u32,
     ) -> Spectrum {
-        let object = &intersection.object;
-        let bsdf = object.bsdf();
+        let bsdf = intersection.object.bsdf();
+
+        self.emission_and_specular(scene, intersection, sampler, depth)
+            + direct_illumination(scene, sampler, intersection, bsdf)
+    }
 
+    /// Computes the illumination at the given intersection except for the direct illumination,
+    /// i.e. the emission and the recursively followed specular reflection/transmission.
+    fn emission_and_specular(
+        &self,
+        scene: &Scene,
+        intersection: &SceneIntersection,
+        sampler: Sampler,
+        depth: u32,
+    ) -> Spectrum {
         let mut illumination = Spectrum::broadcast(0.0);
 
-        if let SceneObject::Emitter(e) = object {
-            illumination += e.emission; //e.radiance(&outgoing, &normal);
+        if let SceneObject::Emitter(e) = &intersection.object {
+            illumination += e.emission(); //e.radiance(&outgoing, &normal);
         }
 
-        illumination += direct_illumination(scene, sampler, intersection, bsdf);
-
         let new_depth = depth + 1;
         if new_depth < self.max_depth {
             let reflection = Type::SPECULAR | Type::REFLECTION;
@@ -84,35 +115,23 @@ impl Whitted {
         let outgoing = -intersection.ray.direction;
 
         let bsdf = intersection.object.bsdf();
-        let normal = intersection.normal;
         let sample = sampler.get_sample();
 
-        let bxdf_sample_option = bsdf.sample(normal, outgoing, typ, sample);
+        let bxdf_sample_option = bsdf.sample(intersection.frame(), outgoing, typ, sample);
 
         let mut reflection = Spectrum::broadcast(0.0);
 
         if let Some(bxdf_sample) = bxdf_sample_option {
-            if bxdf_sample.pdf > 0.0 && !bxdf_sample.spectrum.is_black() {
-                let cos_abs = if bxdf_sample.typ.is_specular() {
-                    // division of cosine omitted in specular bxdfs
-                    1.0
-                } else {
-                    bxdf_sample.incident.dot(normal).abs()
-                };
-
-                if cos_abs != 0.0 {
-                    let refl_ray = offset_ray_towards(
-                        intersection.point,
-                        intersection.normal,
-                        bxdf_sample.incident,
-                    );
-
-                    if let Some(si) = scene.intersect(&refl_ray) {
-                        let illumination = self.illumination(scene, &si, sampler, depth);
-                        reflection +=
-                            illumination * bxdf_sample.spectrum * (cos_abs / bxdf_sample.pdf);
-                    }
-                }
+            let mut state = PathState::new(intersection.clone(), Spectrum::broadcast(1.0));
+
+            if state.attenuate(&bxdf_sample) && !state.throughput.is_black() {
+                let illumination =
+                    if state.continue_ray(scene, DirectLightStrategy::All, &bxdf_sample) {
+                        self.illumination(scene, &state.hit, sampler, depth)
+                    } else {
+                        scene.ambient()
+                    };
+                reflection += illumination * state.throughput;
             }
         }
 
@@ -127,8 +146,186 @@ impl Integrator for Whitted {
             let illumination = self.illumination(scene, &i, sampler, 0);
 
             pixel.add(illumination);
-        } else {
+        } else if scene.ambient_light.is_black() {
             pixel.add_black();
+        } else {
+            pixel.add(scene.ambient());
+        }
+    }
+
+    fn integrate_tile(
+        &self,
+        pixels: &mut [Pixel],
+        scene: &Scene,
+        camera: &dyn Camera,
+        sampler: Sampler,
+    ) {
+        if !self.batch_shadow_rays {
+            for px in pixels {
+                let primary_ray = camera.primary_ray(px.position);
+                self.integrate(px, scene, &primary_ray, sampler);
+            }
+            return;
+        }
+
+        let mut illuminations = vec![None; pixels.len()];
+        // the pixel index and contribution of each shadow ray
+        let mut contributions = Vec::new();
+        let mut shadow_rays = Vec::new();
+
+        for (i, px) in pixels.iter().enumerate() {
+            let primary_ray = camera.primary_ray(px.position);
+
+            if let Some(hit) = scene.intersect(&primary_ray) {
+                let bsdf = hit.object.bsdf();
+                illuminations[i] = Some(self.emission_and_specular(scene, &hit, sampler, 0));
+
+                for (contribution, ray) in unshadowed_light_samples(scene, sampler, &hit, bsdf) {
+                    contributions.push((i, contribution));
+                    shadow_rays.push(ray);
+                }
+            }
+        }
+
+        let occluded = scene.occluded_batch(&shadow_rays);
+        for ((i, contribution), occluded) in contributions.into_iter().zip(occluded) {
+            if !occluded {
+                if let Some(illumination) = &mut illuminations[i] {
+                    *illumination += contribution;
+                }
+            }
+        }
+
+        for (px, illumination) in pixels.iter_mut().zip(illuminations) {
+            match illumination {
+                Some(illumination) => px.add(illumination),
+                None if scene.ambient_light.is_black() => px.add_black(),
+                None => px.add(scene.ambient()),
+            }
         }
     }
 }
+
+#[test]
+fn escaping_rays_see_the_ambient_light() {
+    use crate::renderer::Renderer;
+
+    let render = |ambient: &str| {
+        let source = format!(
+            r#"(
+                config: (
+                    filename: None,
+                    bounds: None,
+                    block_size: (x: 4, y: 4),
+                    passes: 1,
+                    threads: Some(2),
+                ),
+                camera: {{
+                    "PerspectiveCamera": (
+                        Sampler: NoOp,
+                        Position: (x: 0, y: 0, z: 4),
+                        Target: (x: 0, y: 0, z: 0),
+                        Up: (x: 0, y: 1, z: 0),
+                        FovY: 40,
+                        Resolution: (x: 8, y: 8),
+                    ),
+                }},
+                integrator: {{"Whitted": (max_depth: 2)}},
+                sampler: NoOp,
+                scene: (
+                    bounding_box: (
+                        min: (x: -1, y: -1, z: -1),
+                        max: (x: 1, y: 1, z: 1),
+                    ),
+                    {}
+                    objects: [
+                        Receiver((
+                            geometry: {{"Sphere": (center: (x: 0, y: 0, z: 0), radius: 0.5)}},
+                            bsdf: (bxdfs: [{{"LambertianReflection": (r: Constant(0.8))}}]),
+                        )),
+                    ],
+                ),
+            )"#,
+            ambient
+        );
+
+        let mut renderer = Renderer::new(ron::from_str(&source).unwrap());
+        renderer.render().join().unwrap();
+        let frame = renderer.get_frame();
+
+        // a corner escaping the scene and the unlit sphere in the center
+        (frame.get(0, 0)[0], frame.get(4, 4)[0])
+    };
+
+    assert_eq!(render(""), (0.0, 0.0));
+    assert_eq!(render("ambient_light: (Constant(0.5)),"), (0.5, 0.0));
+}
+
+#[test]
+fn batched_shadow_rays_match_single_ones() {
+    use crate::renderer::Renderer;
+    use crate::serialization::Serialization;
+
+    let render = |batch: bool| {
+        let source = format!(
+            r#"(
+                config: (
+                    filename: None,
+                    bounds: None,
+                    block_size: (x: 4, y: 4),
+                    passes: 1,
+                    threads: Some(2),
+                ),
+                camera: {{
+                    "PerspectiveCamera": (
+                        Sampler: NoOp,
+                        Position: (x: 0, y: 0, z: 4),
+                        Target: (x: 0, y: 0, z: 0),
+                        Up: (x: 0, y: 1, z: 0),
+                        FovY: 40,
+                        Resolution: (x: 12, y: 12),
+                    ),
+                }},
+                integrator: {{"Whitted": (max_depth: 2, batch_shadow_rays: {})}},
+                sampler: NoOp,
+                scene: (
+                    bounding_box: (
+                        min: (x: -1, y: -1, z: -1),
+                        max: (x: 1, y: 4, z: 3),
+                    ),
+                    objects: [
+                        Receiver((
+                            geometry: {{"Sphere": (center: (x: 0, y: 0, z: 0), radius: 1)}},
+                            bsdf: (bxdfs: [{{"LambertianReflection": (r: Constant(0.8))}}]),
+                        )),
+                        // casts a shadow onto the upper half of the sphere
+                        Receiver((
+                            geometry: {{"Sphere": (center: (x: 0.3, y: 1.6, z: 1.2), radius: 0.3)}},
+                            bsdf: (bxdfs: []),
+                        )),
+                        Emitter((
+                            geometry: {{"Point": ((x: 0, y: 3, z: 2))}},
+                            emission: Constant(10),
+                        )),
+                    ],
+                ),
+            )"#,
+            batch
+        );
+
+        let mut renderer = Renderer::new(Serialization::parse(&source, None).unwrap());
+        renderer.render().join().unwrap();
+        let frame = renderer.get_frame();
+
+        (0..12)
+            .flat_map(|y| (0..12).map(move |x| (x, y)))
+            .map(|(x, y)| frame.get(x, y)[0])
+            .collect::<Vec<_>>()
+    };
+
+    let single = render(false);
+    let batched = render(true);
+
+    assert_eq!(single, batched);
+    assert!(batched.iter().any(|&v| v > 0.0));
+}
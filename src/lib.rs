@@ -4,23 +4,38 @@ extern crate bitflags;
 #[cfg(feature = "show-image")]
 pub use window::RenderWindow;
 
+pub mod atmosphere;
 pub mod bxdf;
 
 pub mod camera;
+pub mod dataset;
 mod debug_utils;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod integrator;
 pub mod mc;
+pub mod medium;
+pub mod merge;
+pub mod metadata;
+mod nanovdb;
+pub mod obj_export;
 pub mod objects;
+pub mod pack;
+pub mod periodic;
+pub mod phase;
 pub mod samplers;
 pub mod scene;
 
 pub mod filters;
+pub mod postprocessing;
 pub mod refractive_index;
 
 pub mod config;
 pub mod renderer;
 pub mod sensor;
 pub mod serialization;
+pub mod stats;
+pub mod texture;
 #[cfg(feature = "show-image")]
 mod window;
 
@@ -34,6 +49,10 @@ type Vector2 = ultraviolet::Vec2;
 type Vector3 = ultraviolet::Vec3;
 #[cfg(not(feature = "f64"))]
 type Rotation3 = ultraviolet::Rotor3;
+#[cfg(not(feature = "f64"))]
+type Bivector3 = ultraviolet::Bivec3;
+#[cfg(not(feature = "f64"))]
+type Matrix3 = ultraviolet::Mat3;
 #[cfg(feature = "f64")]
 type Float = f64;
 #[cfg(feature = "f64")]
@@ -42,3 +61,7 @@ type Vector2 = ultraviolet::DVec2;
 type Vector3 = ultraviolet::DVec3;
 #[cfg(feature = "f64")]
 type Rotation3 = ultraviolet::DRotor3;
+#[cfg(feature = "f64")]
+type Bivector3 = ultraviolet::DBivec3;
+#[cfg(feature = "f64")]
+type Matrix3 = ultraviolet::DMat3;
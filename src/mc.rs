@@ -189,3 +189,229 @@ pub fn uniform_sample_cone_frame(
 pub fn uniform_cone_pdf(cos_theta: Float) -> Float {
     1.0 / (TAU as Float * (1.0 - cos_theta))
 }
+
+/// Samples a hemisphere with a cosine power distribution (`cos^exponent`) described by the
+/// sample, e.g. for Phong lobes.
+///
+/// # Constraints
+/// * `sample` - All values should be within `[0, 1]`.
+/// * `exponent` - Should be greater or equal to `0`.
+///
+/// # Arguments
+/// * `sample` - A random sample
+/// * `exponent` - The exponent of the cosine, `1` being a cosine distribution
+///
+/// # Results
+/// * A direction on the unit hemisphere around the `(0, 1, 0)` axis
+#[inline]
+pub fn sample_unit_hemisphere_cosine_power(sample: Vector2, exponent: Float) -> Vector3 {
+    debug_assert!(within_01(sample));
+    debug_assert!(exponent >= 0.0);
+
+    let cos_theta = sample.x.powf(1.0 / (exponent + 1.0));
+    let sin_theta = cos_theta.mul_add(-cos_theta, 1.0).fast_max(0.0).sqrt();
+    let (sin_phi, cos_phi) = Float::sin_cos(sample.y * TAU as Float);
+
+    spherical_to_cartesian_trig(sin_theta, cos_theta, sin_phi, cos_phi)
+}
+
+/// Computes the pdf for sampling a [cosine power](sample_unit_hemisphere_cosine_power)
+/// distributed direction.
+///
+/// # Arguments
+/// * `cos_theta` - The cosine of the direction to the `(0, 1, 0)` axis
+/// * `exponent` - The exponent of the cosine
+///
+/// # Results
+/// * `Float` - The pdf
+#[inline]
+pub fn cosine_power_hemisphere_pdf(cos_theta: Float, exponent: Float) -> Float {
+    if cos_theta <= 0.0 {
+        0.0
+    } else {
+        (exponent + 1.0) * cos_theta.powf(exponent) / TAU as Float
+    }
+}
+
+/// Samples a distance with an exponential distribution, e.g. the free flight distance in a
+/// homogeneous medium.
+///
+/// # Constraints
+/// * `sample` - Should be within `[0, 1)`.
+/// * `sigma` - Should be greater than `0`.
+///
+/// # Arguments
+/// * `sample` - A random sample
+/// * `sigma` - The rate (e.g. extinction coefficient)
+///
+/// # Results
+/// * `Float` - The distance
+#[inline]
+pub fn sample_exponential(sample: Float, sigma: Float) -> Float {
+    debug_assert!((0.0..1.0).contains(&sample));
+    debug_assert!(sigma > 0.0);
+
+    -(1.0 - sample).ln() / sigma
+}
+
+/// Computes the pdf for sampling an [exponentially](sample_exponential) distributed distance.
+///
+/// # Arguments
+/// * `distance` - The distance
+/// * `sigma` - The rate
+///
+/// # Results
+/// * `Float` - The pdf
+#[inline]
+pub fn exponential_pdf(distance: Float, sigma: Float) -> Float {
+    sigma * (-sigma * distance).exp()
+}
+
+/// Samples a distance along a ray proportional to the inverse squared distance to a point,
+/// e.g. a point light inside a medium (Kulla and Fajardo 2012).
+///
+/// # Constraints
+/// * `sample` - Should be within `[0, 1]`.
+/// * `direction` - Should be normalized.
+/// * `t_min` - Should be less than `t_max`.
+/// * `t_max` - Should be finite.
+///
+/// # Arguments
+/// * `sample` - A random sample
+/// * `origin` - The origin of the ray
+/// * `direction` - The direction of the ray
+/// * `(t_min, t_max)` - The segment of the ray to sample
+/// * `point` - The point to sample towards
+///
+/// # Results
+/// * The ray parameter of the sampled distance
+/// * The pdf
+pub fn sample_equiangular(
+    sample: Float,
+    origin: Vector3,
+    direction: Vector3,
+    (t_min, t_max): (Float, Float),
+    point: Vector3,
+) -> (Float, Float) {
+    debug_assert!(t_min < t_max);
+    debug_assert!(t_max.is_finite());
+
+    let (delta, distance, theta_a, theta_b) =
+        equiangular_angles(origin, direction, (t_min, t_max), point);
+
+    let t = distance * theta_a.lerp(theta_b, sample).tan();
+    let pdf = distance / ((theta_b - theta_a) * (distance * distance + t * t));
+
+    ((delta + t).fast_clamp(t_min, t_max), pdf)
+}
+
+/// Computes the pdf for [equiangular](sample_equiangular) sampling of the given ray parameter.
+///
+/// # Arguments
+/// * `t` - The ray parameter within `[t_min, t_max]`
+/// * `origin` - The origin of the ray
+/// * `direction` - The direction of the ray
+/// * `(t_min, t_max)` - The segment of the ray
+/// * `point` - The point sampled towards
+///
+/// # Results
+/// * `Float` - The pdf
+pub fn equiangular_pdf(
+    t: Float,
+    origin: Vector3,
+    direction: Vector3,
+    (t_min, t_max): (Float, Float),
+    point: Vector3,
+) -> Float {
+    if t < t_min || t > t_max {
+        return 0.0;
+    }
+
+    let (delta, distance, theta_a, theta_b) =
+        equiangular_angles(origin, direction, (t_min, t_max), point);
+    let t = t - delta;
+
+    distance / ((theta_b - theta_a) * (distance * distance + t * t))
+}
+
+/// Returns the ray parameter closest to the point, the distance of the point to the ray and the
+/// angles of the segment ends as seen from the point.
+fn equiangular_angles(
+    origin: Vector3,
+    direction: Vector3,
+    (t_min, t_max): (Float, Float),
+    point: Vector3,
+) -> (Float, Float, Float, Float) {
+    let delta = (point - origin).dot(direction);
+    // points on the ray would cause a singular pdf
+    let distance = (origin + direction * delta - point)
+        .mag()
+        .fast_max(Float::big_epsilon());
+
+    let theta_a = (t_min - delta).atan2(distance);
+    let theta_b = (t_max - delta).atan2(distance);
+
+    (delta, distance, theta_a, theta_b)
+}
+
+/// Weights a sampling technique against another one by the balance heuristic of multiple
+/// importance sampling.
+///
+/// # Arguments
+/// * `pdf` - The pdf of the weighted technique
+/// * `other` - The pdf of the other technique
+///
+/// # Returns
+/// * The weight in `[0, 1]`
+#[inline]
+pub fn balance_heuristic(pdf: Float, other: Float) -> Float {
+    let sum = pdf + other;
+
+    if sum == 0.0 {
+        0.0
+    } else {
+        pdf / sum
+    }
+}
+
+/// Weights a sampling technique against another one by the power heuristic (`β = 2`) of
+/// multiple importance sampling.
+///
+/// # Arguments
+/// * `pdf` - The pdf of the weighted technique
+/// * `other` - The pdf of the other technique
+///
+/// # Returns
+/// * The weight in `[0, 1]`
+#[inline]
+pub fn power_heuristic(pdf: Float, other: Float) -> Float {
+    let pdf2 = pdf * pdf;
+    let sum = pdf2 + other * other;
+
+    if sum == 0.0 {
+        0.0
+    } else {
+        pdf2 / sum
+    }
+}
+
+#[test]
+fn equiangular_pdf_normalized() {
+    let origin = Vector3::new(-2.0, 0.0, 0.0);
+    let direction = Vector3::unit_x();
+    let point = Vector3::new(0.5, 0.3, 0.0);
+    let segment = (0.5, 4.0);
+
+    let (t, pdf) = sample_equiangular(0.3, origin, direction, segment, point);
+    assert!((pdf - equiangular_pdf(t, origin, direction, segment, point)).abs() < 1e-3 * pdf);
+
+    // midpoint rule over the segment
+    let n = 10_000;
+    let dt = (segment.1 - segment.0) / n as Float;
+    let integral: Float = (0..n)
+        .map(|i| segment.0 + (i as Float + 0.5) * dt)
+        .map(|t| equiangular_pdf(t, origin, direction, segment, point) * dt)
+        .sum();
+
+    assert!((integral - 1.0).abs() < 1e-2);
+}
@@ -0,0 +1,503 @@
+//! Participating media scattering and absorbing light inside a volume instead of on surfaces,
+//! e.g. smoke or clouds.
+//!
+//! A [grid medium](GridMedium) spans an axis-aligned box, its density given by a
+//! [voxel grid](DensityGrid). Since the density varies, distances and transmittances cannot be
+//! computed analytically, but get estimated by delta tracking and ratio tracking respectively
+//! (Novák et al.), using the maximum density as majorant.
+
+use crate::atmosphere::AtmosphereScattering;
+use crate::mc::sample_exponential;
+use crate::nanovdb;
+use crate::phase::PhaseFunction;
+use crate::samplers::Sampler;
+use crate::{Float, Spectrum, Vector2, Vector3};
+#[cfg(test)]
+use color::Color;
+use geometry::{Aabb, Ray};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+#[cfg(test)]
+use std::f64::consts::TAU;
+use std::fs;
+use std::path::Path;
+
+/// The size of the header of a binary density grid: the resolution as three `u32`.
+const HEADER_SIZE: usize = 12;
+
+/// The textual representation of a density grid in scene files.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum GridSource {
+    /// A binary grid file
+    File(String),
+    /// A NanoVDB file, of which the first float grid gets loaded
+    NanoVdb(String),
+    /// The densities inside the scene file
+    Inline {
+        resolution: (usize, usize, usize),
+        densities: Vec<Float>,
+    },
+}
+
+/// A voxel grid of densities, either loaded from a binary or NanoVDB file, or given inline.
+///
+/// The binary format consists of the resolution `x, y, z` as little-endian `u32`, followed by the
+/// `x * y * z` densities as little-endian `f32`, with `x` varying the fastest.
+/// Sparse NanoVDB grids get rasterized into the box of their active voxels.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "GridSource", into = "GridSource")]
+pub struct DensityGrid {
+    /// The grid file as written (if any)
+    file: Option<GridSource>,
+    resolution: [usize; 3],
+    densities: Vec<Float>,
+    max_density: Float,
+}
+
+impl DensityGrid {
+    /// Creates a new density grid.
+    ///
+    /// # Constraints
+    /// * `resolution` - All values should be greater than `0`.
+    /// * `densities` - Should contain `x * y * z` non-negative values, `x` varying the fastest.
+    ///
+    /// # Arguments
+    /// * `resolution` - The number of voxels along each axis
+    /// * `densities` - The densities of the voxels
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(resolution: [usize; 3], densities: Vec<Float>) -> Self {
+        Self::try_new(resolution, densities).unwrap()
+    }
+
+    fn try_new(resolution: [usize; 3], densities: Vec<Float>) -> Result<Self, String> {
+        let [x, y, z] = resolution;
+
+        if x == 0 || y == 0 || z == 0 {
+            return Err(format!("Invalid grid resolution {:?}", resolution));
+        }
+        if densities.len() != x * y * z {
+            return Err(format!(
+                "Grid of resolution {:?} expects {} densities, got {}",
+                resolution,
+                x * y * z,
+                densities.len()
+            ));
+        }
+        if densities.iter().any(|d| !d.is_finite() || *d < 0.0) {
+            return Err("Grid densities must be finite and non-negative".to_string());
+        }
+
+        let max_density = densities.iter().copied().fold(0.0, Float::max);
+
+        Ok(Self {
+            file: None,
+            resolution,
+            densities,
+            max_density,
+        })
+    }
+
+    /// Loads a binary density grid.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the grid file
+    ///
+    /// # Returns
+    /// * The density grid, or an error message
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        Self::from_bytes(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Loads the first float grid of an uncompressed NanoVDB file.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the NanoVDB file
+    ///
+    /// # Returns
+    /// * The density grid, or an error message
+    pub fn load_nanovdb<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        nanovdb::load_float_grid(&bytes)
+            .and_then(|(resolution, densities)| Self::try_new(resolution, densities))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Parses a binary density grid.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_SIZE {
+            return Err("Grid file too short".to_string());
+        }
+
+        if !(bytes.len() - HEADER_SIZE).is_multiple_of(4) {
+            return Err("Grid file has trailing bytes".to_string());
+        }
+
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let resolution = [u32_at(0) as usize, u32_at(4) as usize, u32_at(8) as usize];
+
+        let densities: Vec<Float> = bytes[HEADER_SIZE..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as Float)
+            .collect();
+
+        Self::try_new(resolution, densities)
+    }
+
+    /// Returns the maximum density of all voxels.
+    #[inline]
+    pub fn max_density(&self) -> Float {
+        self.max_density
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> Float {
+        let [rx, ry, _] = self.resolution;
+        self.densities[x + rx * (y + ry * z)]
+    }
+
+    /// Returns the trilinearly interpolated density at the given point, with the voxel centers
+    /// lying at `(i + 0.5) / resolution`.
+    ///
+    /// # Constraints
+    /// * `point` - All values should be within `[0, 1]`.
+    ///
+    /// # Arguments
+    /// * `point` - The point inside the unit cube spanned by the grid
+    ///
+    /// # Returns
+    /// * The density
+    pub fn density(&self, point: Vector3) -> Float {
+        let mut index = [0; 3];
+        let mut fraction = [0.0; 3];
+
+        for (axis, p) in [point.x, point.y, point.z].iter().enumerate() {
+            let max = self.resolution[axis] - 1;
+            let p = (p * self.resolution[axis] as Float - 0.5).max(0.0);
+            let i = (p as usize).min(max);
+
+            index[axis] = i;
+            fraction[axis] = if i == max { 0.0 } else { p - i as Float };
+        }
+
+        let [x, y, z] = index;
+        let [fx, fy, fz] = fraction;
+        let [rx, ry, rz] = self.resolution;
+        let (x1, y1, z1) = (
+            (x + 1).min(rx - 1),
+            (y + 1).min(ry - 1),
+            (z + 1).min(rz - 1),
+        );
+
+        let lerp = |a: Float, b: Float, t: Float| a + (b - a) * t;
+
+        let d00 = lerp(self.voxel(x, y, z), self.voxel(x1, y, z), fx);
+        let d10 = lerp(self.voxel(x, y1, z), self.voxel(x1, y1, z), fx);
+        let d01 = lerp(self.voxel(x, y, z1), self.voxel(x1, y, z1), fx);
+        let d11 = lerp(self.voxel(x, y1, z1), self.voxel(x1, y1, z1), fx);
+
+        lerp(lerp(d00, d10, fy), lerp(d01, d11, fy), fz)
+    }
+}
+
+impl TryFrom<GridSource> for DensityGrid {
+    type Error = String;
+
+    fn try_from(source: GridSource) -> Result<Self, Self::Error> {
+        match source {
+            GridSource::File(path) => {
+                let mut grid = Self::load(geometry::resolve_asset(&path))?;
+                grid.file = Some(GridSource::File(path));
+                Ok(grid)
+            }
+            GridSource::NanoVdb(path) => {
+                let mut grid = Self::load_nanovdb(geometry::resolve_asset(&path))?;
+                grid.file = Some(GridSource::NanoVdb(path));
+                Ok(grid)
+            }
+            GridSource::Inline {
+                resolution,
+                densities,
+            } => Self::try_new([resolution.0, resolution.1, resolution.2], densities),
+        }
+    }
+}
+
+impl From<DensityGrid> for GridSource {
+    fn from(grid: DensityGrid) -> Self {
+        match grid.file {
+            Some(file) => file,
+            None => {
+                let [x, y, z] = grid.resolution;
+                GridSource::Inline {
+                    resolution: (x, y, z),
+                    densities: grid.densities,
+                }
+            }
+        }
+    }
+}
+
+/// A heterogeneous medium whose density is given by a voxel grid spanning the bounds.
+///
+/// The extinction at a point is `sigma_t * density`, of which the fraction `albedo` gets
+/// scattered according to the [phase function](PhaseFunction).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GridMedium {
+    bounds: Aabb,
+    density: DensityGrid,
+    /// The extinction coefficient at a density of `1`
+    sigma_t: Float,
+    /// The scattering albedo
+    albedo: Spectrum,
+    #[serde(default)]
+    phase: PhaseFunction,
+}
+
+impl GridMedium {
+    /// Creates a new grid medium.
+    ///
+    /// # Constraints
+    /// * `sigma_t` - Should be non-negative.
+    ///
+    /// # Arguments
+    /// * `bounds` - The box spanned by the grid
+    /// * `density` - The density grid
+    /// * `sigma_t` - The extinction coefficient at a density of `1`
+    /// * `albedo` - The scattering albedo
+    /// * `phase` - The phase function
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(
+        bounds: Aabb,
+        density: DensityGrid,
+        sigma_t: Float,
+        albedo: Spectrum,
+        phase: PhaseFunction,
+    ) -> Self {
+        debug_assert!(sigma_t >= 0.0);
+
+        Self {
+            bounds,
+            density,
+            sigma_t,
+            albedo,
+            phase,
+        }
+    }
+
+    /// Returns the scattering albedo.
+    #[inline]
+    pub fn albedo(&self) -> Spectrum {
+        self.albedo
+    }
+
+    /// Returns the bounds of the medium.
+    #[inline]
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    /// Returns the majorant of the extinction, bounding it everywhere inside the medium.
+    #[inline]
+    fn majorant(&self) -> Float {
+        self.sigma_t * self.density.max_density()
+    }
+
+    /// Returns the extinction at the given world point.
+    fn sigma_t(&self, point: Vector3) -> Float {
+        let local = (point - self.bounds.min) / self.bounds.size();
+        let local = local.clamped(Vector3::zero(), Vector3::one());
+
+        self.sigma_t * self.density.density(local)
+    }
+
+    /// Clips the given ray segment to the bounds.
+    fn clip(&self, ray: &Ray, t_max: Float) -> Option<(Float, Float)> {
+        let (t_min, t_exit) = self.bounds.intersect_range(ray)?;
+        let t_max = t_exit.min(t_max);
+
+        if t_min < t_max {
+            Some((t_min, t_max))
+        } else {
+            None
+        }
+    }
+
+    /// Samples the distance to the next real collision along the ray by delta tracking.
+    ///
+    /// # Constraints
+    /// * `ray` - Should have a normalized direction.
+    ///
+    /// # Arguments
+    /// * `ray` - The ray
+    /// * `t_max` - The end of the ray segment, e.g. the next surface intersection
+    /// * `sampler` - The sampler
+    ///
+    /// # Returns
+    /// * The ray parameter of the collision, or `None` if passing through
+    pub fn sample_collision(&self, ray: &Ray, t_max: Float, sampler: Sampler) -> Option<Float> {
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return None;
+        }
+
+        let (mut t, t_max) = self.clip(ray, t_max)?;
+
+        loop {
+            t += sample_exponential(sampler.get_1d(), majorant);
+            if t >= t_max {
+                return None;
+            }
+
+            if sampler.get_1d() * majorant < self.sigma_t(ray.at(t)) {
+                return Some(t);
+            }
+        }
+    }
+
+    /// Estimates the transmittance along the ray by ratio tracking.
+    ///
+    /// # Constraints
+    /// * `ray` - Should have a normalized direction.
+    ///
+    /// # Arguments
+    /// * `ray` - The ray
+    /// * `t_max` - The end of the ray segment
+    /// * `sampler` - The sampler
+    ///
+    /// # Returns
+    /// * An unbiased estimate of the transmittance in `[0, 1]`
+    pub fn transmittance(&self, ray: &Ray, t_max: Float, sampler: Sampler) -> Float {
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return 1.0;
+        }
+
+        let (mut t, t_max) = match self.clip(ray, t_max) {
+            Some(segment) => segment,
+            None => return 1.0,
+        };
+
+        let mut transmittance = 1.0;
+        loop {
+            t += sample_exponential(sampler.get_1d(), majorant);
+            if t >= t_max {
+                return transmittance;
+            }
+
+            transmittance *= 1.0 - self.sigma_t(ray.at(t)) / majorant;
+        }
+    }
+
+    /// Evaluates the phase function.
+    ///
+    /// # Arguments
+    /// * `direction` - The direction of travel before scattering
+    /// * `incident` - The direction of travel after scattering
+    ///
+    /// # Returns
+    /// * The phase function value of each wavelength
+    pub fn phase(&self, direction: Vector3, incident: Vector3) -> Spectrum {
+        self.phase.evaluate(direction, incident)
+    }
+
+    /// Samples the phase function.
+    ///
+    /// # Constraints
+    /// * `sample` - All values should be within `[0, 1)`.
+    ///
+    /// # Arguments
+    /// * `direction` - The direction of travel before scattering
+    /// * `sample` - A random sample
+    ///
+    /// # Returns
+    /// * The direction of travel after scattering
+    /// * The phase function divided by the pdf
+    pub fn sample_phase(&self, direction: Vector3, sample: Vector2) -> (Vector3, Spectrum) {
+        self.phase.sample(direction, sample)
+    }
+}
+
+/// The scattering at a real collision inside a medium.
+pub enum Scatterer<'a> {
+    /// A collision inside a [grid medium](GridMedium)
+    Grid(&'a GridMedium),
+    /// A collision inside the [atmosphere](crate::atmosphere::Atmosphere)
+    Atmosphere(AtmosphereScattering),
+}
+
+impl Scatterer<'_> {
+    /// Evaluates the phase function.
+    ///
+    /// # Arguments
+    /// * `direction` - The direction of travel before scattering
+    /// * `incident` - The direction of travel after scattering
+    ///
+    /// # Returns
+    /// * The phase function value of each wavelength
+    pub fn phase(&self, direction: Vector3, incident: Vector3) -> Spectrum {
+        match self {
+            Scatterer::Grid(medium) => medium.phase(direction, incident),
+            Scatterer::Atmosphere(scattering) => scattering.phase(direction, incident),
+        }
+    }
+
+    /// Samples the phase function.
+    ///
+    /// # Constraints
+    /// * `sample` - All values should be within `[0, 1)`.
+    ///
+    /// # Arguments
+    /// * `direction` - The direction of travel before scattering
+    /// * `sample` - A random sample
+    ///
+    /// # Returns
+    /// * The direction of travel after scattering
+    /// * The phase function divided by the pdf
+    pub fn sample_phase(&self, direction: Vector3, sample: Vector2) -> (Vector3, Spectrum) {
+        match self {
+            Scatterer::Grid(medium) => medium.sample_phase(direction, sample),
+            Scatterer::Atmosphere(scattering) => scattering.sample_phase(direction, sample),
+        }
+    }
+}
+
+#[test]
+fn grid_from_bytes() {
+    let mut bytes = Vec::new();
+    for r in &[2u32, 1, 1] {
+        bytes.extend_from_slice(&r.to_le_bytes());
+    }
+    for d in &[0.5f32, 1.5] {
+        bytes.extend_from_slice(&d.to_le_bytes());
+    }
+
+    let grid = DensityGrid::from_bytes(&bytes).unwrap();
+    assert_eq!(1.5, grid.max_density());
+    assert_eq!(0.5, grid.density(Vector3::new(0.0, 0.5, 0.5)));
+    assert_eq!(1.0, grid.density(Vector3::new(0.5, 0.5, 0.5)));
+
+    assert!(DensityGrid::from_bytes(&bytes[..16]).is_err());
+}
+
+#[test]
+fn isotropic_phase_integrates_to_one() {
+    let medium = GridMedium::new(
+        Aabb::new(Vector3::zero(), Vector3::one()),
+        DensityGrid::new([1, 1, 1], vec![1.0]),
+        1.0,
+        Spectrum::broadcast(1.0),
+        PhaseFunction::Isotropic,
+    );
+
+    let phase = medium.phase(Vector3::unit_y(), Vector3::unit_x())[0];
+    assert!((phase - 1.0 / (2.0 * TAU as Float)).abs() < 1e-6);
+}
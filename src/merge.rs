@@ -0,0 +1,172 @@
+//! Merges independently seeded renders of the same scene by averaging their OpenEXR images,
+//! weighted by the number of passes stored in their headers.
+
+use exr::error::{Error, Result};
+use exr::prelude::*;
+use std::borrow::Cow;
+use std::path::Path;
+
+type Pixels = (Vec2<usize>, Vec<(f32, f32, f32, f32)>);
+
+/// The name of the OpenEXR header attribute storing the number of passes of a render.
+pub const PASSES_ATTRIBUTE: &str = "passes";
+
+/// Creates the OpenEXR layer attributes storing the given number of passes.
+///
+/// # Arguments
+/// * `passes` - The number of passes of the render
+///
+/// # Returns
+/// * The layer attributes
+pub fn layer_attributes(passes: u32) -> LayerAttributes {
+    let mut attributes = LayerAttributes::default();
+    attributes.other.insert(
+        Text::from(PASSES_ATTRIBUTE),
+        AttributeValue::I32(passes as i32),
+    );
+
+    attributes
+}
+
+/// Reads the number of passes stored in the given layer attributes.
+///
+/// # Arguments
+/// * `attributes` - The layer attributes
+///
+/// # Returns
+/// * The number of passes, or `None` if missing
+pub fn passes(attributes: &LayerAttributes) -> Option<u32> {
+    match attributes.other.get(&Text::from(PASSES_ATTRIBUTE))? {
+        AttributeValue::I32(passes) if *passes > 0 => Some(*passes as u32),
+        _ => None,
+    }
+}
+
+/// Averages the given OpenEXR images into the output image. Each image gets weighted by its
+/// number of [passes](PASSES_ATTRIBUTE), counting as a single pass if missing.
+///
+/// # Constraints
+/// * `inputs` - Should not be empty.
+///   All images should have the same resolution.
+///
+/// # Arguments
+/// * `output` - The path of the merged image
+/// * `inputs` - The paths of the images to merge
+///
+/// # Returns
+/// * The total number of passes of the merged image
+pub fn merge_exr<P: AsRef<Path>>(output: P, inputs: &[P]) -> Result<u32> {
+    let mut size = None;
+    let mut sum: Vec<(f32, f32, f32, f32)> = Vec::new();
+    let mut total = 0;
+
+    for input in inputs {
+        let image = read_first_rgba_layer_from_file(
+            input,
+            |resolution, _| (resolution, vec![(0.0, 0.0, 0.0, 0.0); resolution.area()]),
+            |(resolution, pixels): &mut Pixels,
+             position: Vec2<usize>,
+             (r, g, b, a): (f32, f32, f32, f32)| {
+                pixels[position.x() + position.y() * resolution.width()] = (r, g, b, a);
+            },
+        )?;
+
+        let layer = image.layer_data;
+        let (resolution, pixels) = layer.channel_data.pixels;
+
+        match size {
+            None => {
+                size = Some(resolution);
+                sum = vec![(0.0, 0.0, 0.0, 0.0); pixels.len()];
+            }
+            Some(size) if size != resolution => {
+                return Err(Error::Invalid(Cow::from("images of different resolutions")));
+            }
+            _ => {}
+        }
+
+        let passes = passes(&layer.attributes).unwrap_or(1);
+        let weight = passes as f32;
+        total += passes;
+
+        for (s, p) in sum.iter_mut().zip(pixels) {
+            s.0 += p.0 * weight;
+            s.1 += p.1 * weight;
+            s.2 += p.2 * weight;
+            s.3 += p.3 * weight;
+        }
+    }
+
+    let size = size.ok_or_else(|| Error::Invalid(Cow::from("no images to merge")))?;
+    let norm = 1.0 / total as f32;
+
+    let layer = Layer::new(
+        size,
+        layer_attributes(total),
+        Encoding::FAST_LOSSLESS,
+        SpecificChannels::rgba(|position: Vec2<usize>| {
+            let s = sum[position.x() + position.y() * size.width()];
+            (s.0 * norm, s.1 * norm, s.2 * norm, s.3 * norm)
+        }),
+    );
+
+    Image::from_layer(layer).write().to_file(output)?;
+
+    Ok(total)
+}
+
+#[test]
+fn merge_weighted_by_passes() {
+    let dir = std::env::temp_dir();
+    let path = |name: &str| dir.join(format!("rust-v-merge-{}-{}.exr", name, std::process::id()));
+
+    let write = |name: &str, width: usize, value: f32, passes: Option<u32>| {
+        let attributes = passes.map_or_else(LayerAttributes::default, layer_attributes);
+        let layer = Layer::new(
+            Vec2(width, 2),
+            attributes,
+            Encoding::FAST_LOSSLESS,
+            SpecificChannels::rgba(|_: Vec2<usize>| (value, value * 2.0, value, 1.0_f32)),
+        );
+        Image::from_layer(layer)
+            .write()
+            .to_file(path(name))
+            .unwrap();
+
+        path(name)
+    };
+
+    let inputs = vec![
+        write("a", 3, 1.0, Some(1)),
+        write("b", 3, 5.0, Some(3)),
+        // counts as a single pass
+        write("c", 3, 2.0, None),
+    ];
+    let output = path("out");
+
+    assert_eq!(merge_exr(output.clone(), &inputs).unwrap(), 5);
+
+    let merged = read_first_rgba_layer_from_file(
+        &output,
+        |resolution, _| (resolution, vec![(0.0, 0.0, 0.0, 0.0); resolution.area()]),
+        |(resolution, pixels): &mut Pixels,
+         position: Vec2<usize>,
+         (r, g, b, a): (f32, f32, f32, f32)| {
+            pixels[position.x() + position.y() * resolution.width()] = (r, g, b, a);
+        },
+    )
+    .unwrap();
+    assert_eq!(passes(&merged.layer_data.attributes), Some(5));
+    for &(r, g, b, a) in &merged.layer_data.channel_data.pixels.1 {
+        assert!((r - 3.6).abs() < 1e-6 && (g - 7.2).abs() < 1e-5 && (b - 3.6).abs() < 1e-6);
+        assert!((a - 1.0).abs() < 1e-6);
+    }
+
+    // differing resolutions cannot be merged
+    let small = write("small", 2, 1.0, Some(1));
+    assert!(merge_exr(output.clone(), &[inputs[0].clone(), small.clone()]).is_err());
+
+    for p in inputs.iter().chain(&[output, small]) {
+        std::fs::remove_file(p).unwrap();
+    }
+}
@@ -0,0 +1,282 @@
+//! Embeds render metadata into the saved images, allowing to reproduce and audit them later.
+//!
+//! PNG images get a `tEXt` chunk per entry, while OpenEXR images get a text attribute in their
+//! header. Additionally, the entries get written into a JSON manifest next to the image, which
+//! allows to [verify](Metadata::from_json) that a scene still reproduces the image.
+
+use image::{DynamicImage, ImageOutputFormat, ImageResult};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// An ordered list of key-value pairs describing a render.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    entries: Vec<(String, String)>,
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the given entry, replacing any previous one of the same key.
+    ///
+    /// # Constraints
+    /// * `key` - Should consist of 1 to 79 printable ASCII characters.
+    ///
+    /// # Arguments
+    /// * `key` - The key
+    /// * `value` - The value
+    ///
+    /// # Returns
+    /// * Self
+    pub fn with<V: ToString>(mut self, key: &str, value: V) -> Self {
+        debug_assert!(!key.is_empty() && key.len() < 80);
+
+        self.entries.retain(|(k, _)| k != key);
+        self.entries.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns the entries in insertion order.
+    ///
+    /// # Returns
+    /// * The key-value pairs
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+
+    /// Returns the value of the given key.
+    ///
+    /// # Arguments
+    /// * `key` - The key
+    ///
+    /// # Returns
+    /// * The value (if any)
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Writes the entries as a flat JSON object of strings.
+    ///
+    /// # Returns
+    /// * The JSON object
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(key, value)| format!("  {}: {}", json_string(key), json_string(value)))
+            .collect();
+
+        format!("{{\n{}\n}}\n", entries.join(",\n"))
+    }
+
+    /// Reads the entries of a flat JSON object of strings, as written by
+    /// [to_json](Metadata::to_json), ordered by their keys.
+    ///
+    /// # Arguments
+    /// * `json` - The JSON object
+    ///
+    /// # Returns
+    /// * The metadata, or an error message
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        // a JSON object of strings is a valid RON map
+        let entries: BTreeMap<String, String> =
+            ron::from_str(json).map_err(|err| err.to_string())?;
+
+        Ok(Self {
+            entries: entries.into_iter().collect(),
+        })
+    }
+
+    /// Saves the given image as PNG with a `tEXt` chunk per entry.
+    ///
+    /// # Arguments
+    /// * `image` - The image
+    /// * `path` - The path of the `.png` file
+    ///
+    /// # Returns
+    /// * Whether the file could be written
+    pub fn save_png<P: AsRef<Path>>(&self, image: &DynamicImage, path: P) -> ImageResult<()> {
+        let mut png = Vec::new();
+        image.write_to(&mut png, ImageOutputFormat::Png)?;
+
+        // the text chunks follow the signature and the header chunk
+        let (head, tail) = png.split_at(PNG_SIGNATURE + PNG_HEADER_CHUNK);
+
+        let mut file = File::create(path)?;
+        file.write_all(head)?;
+        for (key, value) in &self.entries {
+            let mut data = latin1(key);
+            data.push(0);
+            data.extend(latin1(value));
+
+            write_png_chunk(&mut file, b"tEXt", &data)?;
+        }
+        file.write_all(tail)?;
+
+        Ok(())
+    }
+}
+
+/// The length of the PNG signature.
+const PNG_SIGNATURE: usize = 8;
+/// The length of the `IHDR` chunk, i.e. the length, type, 13 bytes of data and the CRC.
+const PNG_HEADER_CHUNK: usize = 4 + 4 + 13 + 4;
+
+/// Quotes the given text as JSON string, escaping quotes, backslashes and control characters.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push(' '),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+/// Encodes the given text in Latin-1 as required by `tEXt` chunks, replacing other characters by
+/// `?`.
+fn latin1(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+        .collect()
+}
+
+fn write_png_chunk<W: Write>(writer: &mut W, typ: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(typ)?;
+    writer.write_all(data)?;
+
+    let crc = crc32(typ.iter().chain(data));
+    writer.write_all(&crc.to_be_bytes())
+}
+
+/// Computes the CRC-32 (ISO 3309) used by PNG chunks.
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+/// Hashes the given content by the 64-bit FNV-1a hash, which stays stable across platforms and
+/// compiler versions.
+///
+/// # Arguments
+/// * `content` - The content to hash, e.g. a scene file
+///
+/// # Returns
+/// * The hash
+pub fn fnv1a(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[test]
+fn crc32_check_value() {
+    assert_eq!(0xCBF4_3926, crc32(b"123456789".iter()));
+}
+
+#[test]
+fn fnv1a_check_value() {
+    assert_eq!(0xcbf2_9ce4_8422_2325, fnv1a(b""));
+    assert_eq!(0xaf63_dc4c_8601_ec8c, fnv1a(b"a"));
+}
+
+#[test]
+fn with_replaces_keys() {
+    let metadata = Metadata::new().with("a", 1).with("b", 2).with("a", 3);
+
+    assert_eq!(
+        &[
+            ("b".to_string(), "2".to_string()),
+            ("a".to_string(), "3".to_string())
+        ],
+        metadata.entries()
+    );
+}
+
+#[test]
+fn png_text_chunks() {
+    use image::{GenericImageView, Rgb, RgbImage};
+
+    let image = DynamicImage::ImageRgb8(RgbImage::from_fn(3, 2, |x, y| {
+        Rgb([x as u8 * 80, y as u8 * 200, 7])
+    }));
+    let metadata = Metadata::new()
+        .with("Software", "Rust-V")
+        .with("Integrator", "Path(max_depth: 4)");
+
+    let path = std::env::temp_dir().join(format!("rust-v-metadata-{}.png", std::process::id()));
+    metadata.save_png(&image, &path).unwrap();
+    let png = std::fs::read(&path).unwrap();
+    let decoded = image::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // still a valid image
+    assert_eq!(decoded.dimensions(), (3, 2));
+    assert_eq!(decoded.to_rgb8(), image.to_rgb8());
+
+    // walk the chunks, validating their CRCs
+    let mut texts = Vec::new();
+    let mut offset = PNG_SIGNATURE;
+    while offset < png.len() {
+        let length = u32::from_be_bytes([
+            png[offset],
+            png[offset + 1],
+            png[offset + 2],
+            png[offset + 3],
+        ]) as usize;
+        let typ = &png[offset + 4..offset + 8];
+        let data = &png[offset + 8..offset + 8 + length];
+        let crc = &png[offset + 8 + length..offset + 12 + length];
+
+        assert_eq!(crc, crc32(typ.iter().chain(data)).to_be_bytes());
+        if typ == b"tEXt" {
+            texts.push(String::from_utf8(data.to_vec()).unwrap());
+        }
+
+        offset += 12 + length;
+    }
+
+    assert_eq!(
+        texts,
+        vec!["Software\0Rust-V", "Integrator\0Path(max_depth: 4)"]
+    );
+}
+
+#[test]
+fn json_round_trip() {
+    let metadata = Metadata::new()
+        .with("Scene", "scenes/\"quoted\" \\ path.ron")
+        .with("Passes", 16);
+
+    let json = metadata.to_json();
+    assert!(json.starts_with("{\n  \"Scene\": \"scenes/\\\"quoted\\\" \\\\ path.ron\","));
+
+    let read = Metadata::from_json(&json).unwrap();
+    assert_eq!(Some("16"), read.get("Passes"));
+    assert_eq!(metadata.get("Scene"), read.get("Scene"));
+}
@@ -0,0 +1,391 @@
+//! Loading of sparse [NanoVDB](https://developer.nvidia.com/nanovdb) float grids, e.g. smoke or
+//! clouds exported from a simulation.
+//!
+//! NanoVDB stores a grid as a flat buffer of a tree: a hash map of root tiles, upper internal
+//! nodes of `32³` children, lower internal nodes of `16³` children and leaves of `8³` voxels.
+//! Since media sample their density by trilinear interpolation of a dense voxel grid, the tree
+//! gets rasterized into the box of its active voxels.
+//!
+//! Only uncompressed files holding float grids are supported, compressed ones get rejected when
+//! loading. OpenVDB files can be converted by `nanovdb_convert` of the OpenVDB tools.
+
+use crate::Float;
+
+/// The first seven bytes of the magic number of NanoVDB files, `"NanoVDB"`.
+const MAGIC: &[u8] = b"NanoVDB";
+/// The major version of the supported NanoVDB layout.
+const MAJOR_VERSION: u32 = 32;
+
+const FILE_HEADER_SIZE: usize = 16;
+const FILE_META_DATA_SIZE: usize = 176;
+const GRID_DATA_SIZE: usize = 672;
+/// The `GridType` of float grids
+const GRID_TYPE_FLOAT: u32 = 1;
+/// The `Codec` of uncompressed grids
+const CODEC_NONE: u16 = 0;
+
+const LEAF_SIZE: usize = 2144;
+const LEAF_VALUES: usize = 96;
+const LOWER_SIZE: usize = 1088 + 8 * (1 << 12);
+const UPPER_SIZE: usize = 8256 + 8 * (1 << 15);
+
+/// The offset of the tile table inside an internal node.
+fn table_offset(log2_dim: u32) -> usize {
+    match log2_dim {
+        4 => 1088,
+        _ => 8256,
+    }
+}
+
+/// The maximum number of voxels a rasterized grid may have.
+const MAX_VOXELS: usize = 1 << 30;
+
+/// A little-endian reader of a byte buffer, failing on out of bounds accesses.
+struct Buffer<'a>(&'a [u8]);
+
+impl Buffer<'_> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&[u8], String> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.0.get(offset..end))
+            .ok_or_else(|| "NanoVDB file truncated".to_string())
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, String> {
+        let b = self.bytes(offset, 2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, String> {
+        let b = self.bytes(offset, 4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i32(&self, offset: usize) -> Result<i32, String> {
+        Ok(self.u32(offset)? as i32)
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64, String> {
+        let b = self.bytes(offset, 8)?;
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(b);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn i64(&self, offset: usize) -> Result<i64, String> {
+        Ok(self.u64(offset)? as i64)
+    }
+
+    fn f32(&self, offset: usize) -> Result<f32, String> {
+        Ok(f32::from_bits(self.u32(offset)?))
+    }
+
+    fn coord(&self, offset: usize) -> Result<[i32; 3], String> {
+        Ok([
+            self.i32(offset)?,
+            self.i32(offset + 4)?,
+            self.i32(offset + 8)?,
+        ])
+    }
+
+    /// Returns whether bit `n` of the mask at the given offset is set.
+    fn bit(&self, offset: usize, n: usize) -> Result<bool, String> {
+        Ok((self.u64(offset + 8 * (n >> 6))? >> (n & 63)) & 1 == 1)
+    }
+
+    /// Returns the offset of `base + delta`.
+    fn relative(&self, base: usize, delta: i64) -> Result<usize, String> {
+        let offset = base as i64 + delta;
+        if offset < 0 || offset as usize >= self.0.len() {
+            return Err("NanoVDB node offset out of bounds".to_string());
+        }
+        Ok(offset as usize)
+    }
+}
+
+/// A dense grid being rasterized from the sparse tree.
+struct Raster {
+    min: [i32; 3],
+    resolution: [usize; 3],
+    densities: Vec<Float>,
+}
+
+impl Raster {
+    /// Fills the cube of the given origin and size with a constant value.
+    fn fill(&mut self, origin: [i32; 3], size: i32, value: Float) {
+        let mut range = [(0, 0); 3];
+        for axis in 0..3 {
+            let start = (origin[axis] - self.min[axis]).max(0);
+            let end = (origin[axis] + size - self.min[axis]).min(self.resolution[axis] as i32);
+            if start >= end {
+                return;
+            }
+            range[axis] = (start as usize, end as usize);
+        }
+
+        let [rx, ry, _] = self.resolution;
+        for z in range[2].0..range[2].1 {
+            for y in range[1].0..range[1].1 {
+                let row = rx * (y + ry * z);
+                self.densities[row + range[0].0..row + range[0].1]
+                    .iter_mut()
+                    .for_each(|d| *d = value);
+            }
+        }
+    }
+
+    fn set(&mut self, coord: [i32; 3], value: Float) {
+        let mut index = [0; 3];
+        for axis in 0..3 {
+            let i = coord[axis] - self.min[axis];
+            if i < 0 || i as usize >= self.resolution[axis] {
+                return;
+            }
+            index[axis] = i as usize;
+        }
+
+        let [rx, ry, _] = self.resolution;
+        self.densities[index[0] + rx * (index[1] + ry * index[2])] = value;
+    }
+}
+
+/// Parses the first float grid of a NanoVDB file and rasterizes it into the box of its active
+/// voxels.
+///
+/// # Arguments
+/// * `bytes` - The content of the NanoVDB file
+///
+/// # Returns
+/// * The resolution of the rasterized grid
+/// * Its densities, `x` varying the fastest
+/// * Or an error message
+pub(crate) fn load_float_grid(bytes: &[u8]) -> Result<([usize; 3], Vec<Float>), String> {
+    let buffer = Buffer(bytes);
+
+    if buffer.bytes(0, MAGIC.len())? != MAGIC {
+        return Err("Not a NanoVDB file".to_string());
+    }
+    let grid_count = buffer.u16(12)?;
+    check_codec(buffer.u16(14)?)?;
+
+    let mut offset = FILE_HEADER_SIZE;
+    for _ in 0..grid_count {
+        let grid_size = buffer.u64(offset + 8)? as usize;
+        let grid_type = buffer.u32(offset + 32)?;
+        let name_size = buffer.u32(offset + 136)? as usize;
+        let codec = buffer.u16(offset + 168)?;
+        let version = buffer.u32(offset + 172)?;
+
+        let grid = offset + FILE_META_DATA_SIZE + name_size;
+        if grid_type == GRID_TYPE_FLOAT {
+            // the grids may be compressed individually
+            check_codec(codec)?;
+            if version >> 21 != MAJOR_VERSION {
+                return Err(format!(
+                    "Unsupported NanoVDB version {}, expected {}",
+                    version >> 21,
+                    MAJOR_VERSION
+                ));
+            }
+
+            return rasterize(&Buffer(buffer.bytes(grid, grid_size)?));
+        }
+
+        offset = grid + grid_size;
+    }
+
+    Err("NanoVDB file contains no float grid".to_string())
+}
+
+/// Rejects compressed buffers, which would otherwise get misread as a tree.
+///
+/// # Arguments
+/// * `codec` - The `Codec` of the file or a grid
+///
+/// # Returns
+/// * Nothing, or an error message naming the codec
+fn check_codec(codec: u16) -> Result<(), String> {
+    match codec {
+        CODEC_NONE => Ok(()),
+        1 => Err("Compressed NanoVDB files (ZIP) are not supported".to_string()),
+        2 => Err("Compressed NanoVDB files (BLOSC) are not supported".to_string()),
+        _ => Err(format!("Unknown NanoVDB codec {}", codec)),
+    }
+}
+
+/// Rasterizes the tree of a float grid buffer.
+fn rasterize(grid: &Buffer) -> Result<([usize; 3], Vec<Float>), String> {
+    let tree = GRID_DATA_SIZE;
+    let leaves = grid.relative(tree, grid.i64(tree)?)?;
+    let lowers = grid.relative(tree, grid.i64(tree + 8)?)?;
+    let uppers = grid.relative(tree, grid.i64(tree + 16)?)?;
+    let root = grid.relative(tree, grid.i64(tree + 24)?)?;
+    let counts = [
+        grid.u32(tree + 32)? as usize,
+        grid.u32(tree + 36)? as usize,
+        grid.u32(tree + 40)? as usize,
+    ];
+
+    // the root data begins with the index bounding box of all active values
+    let min = grid.coord(root)?;
+    let max = grid.coord(root + 12)?;
+    let background = grid.f32(root + 28)? as Float;
+
+    let mut resolution = [0; 3];
+    for axis in 0..3 {
+        if max[axis] < min[axis] {
+            return Err("NanoVDB grid is empty".to_string());
+        }
+        resolution[axis] = (max[axis] as i64 - min[axis] as i64 + 1) as usize;
+    }
+    let voxels = resolution
+        .iter()
+        .try_fold(1usize, |n, r| n.checked_mul(*r))
+        .filter(|n| *n <= MAX_VOXELS)
+        .ok_or_else(|| format!("NanoVDB grid of resolution {:?} is too large", resolution))?;
+
+    let mut raster = Raster {
+        min,
+        resolution,
+        densities: vec![background; voxels],
+    };
+
+    // coarse tiles first, so finer nodes overwrite them
+    for (nodes, count, size, log2_dim, child_size) in [
+        (uppers, counts[2], UPPER_SIZE, 5, 128),
+        (lowers, counts[1], LOWER_SIZE, 4, 8),
+    ] {
+        for i in 0..count {
+            let node = nodes + i * size;
+            fill_tiles(grid, &mut raster, node, log2_dim, child_size)?;
+        }
+    }
+
+    for i in 0..counts[0] {
+        let leaf = leaves + i * LEAF_SIZE;
+        let origin = grid.coord(leaf)?.map(|c| c & !7);
+
+        for n in 0..512 {
+            let value = grid.f32(leaf + LEAF_VALUES + 4 * n)? as Float;
+            let coord = [
+                origin[0] + (n >> 6) as i32,
+                origin[1] + ((n >> 3) & 7) as i32,
+                origin[2] + (n & 7) as i32,
+            ];
+            raster.set(coord, value);
+        }
+    }
+
+    if raster.densities.iter().any(|d| !d.is_finite()) {
+        return Err("NanoVDB grid contains non-finite values".to_string());
+    }
+    raster.densities.iter_mut().for_each(|d| *d = d.max(0.0));
+
+    Ok((resolution, raster.densities))
+}
+
+/// Fills the active value tiles of an internal node.
+fn fill_tiles(
+    grid: &Buffer,
+    raster: &mut Raster,
+    node: usize,
+    log2_dim: u32,
+    child_size: i32,
+) -> Result<(), String> {
+    let dim = 1 << log2_dim;
+    let origin = grid.coord(node)?.map(|c| c & !(dim * child_size - 1));
+    let value_mask = node + 32;
+    let child_mask = value_mask + (1 << (3 * log2_dim)) / 8;
+    let table = node + table_offset(log2_dim);
+
+    for n in 0..1usize << (3 * log2_dim) {
+        if grid.bit(child_mask, n)? || !grid.bit(value_mask, n)? {
+            continue;
+        }
+
+        let value = grid.f32(table + 8 * n)? as Float;
+        let d = dim as usize;
+        let tile = [
+            origin[0] + (n / (d * d)) as i32 * child_size,
+            origin[1] + (n / d % d) as i32 * child_size,
+            origin[2] + (n % d) as i32 * child_size,
+        ];
+        raster.fill(tile, child_size, value);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn load_single_leaf() {
+    // a grid of one upper, one lower and one leaf node at the origin
+    let tree = GRID_DATA_SIZE;
+    let root = tree + 64;
+    let upper = root + 64;
+    let lower = upper + UPPER_SIZE;
+    let leaf = lower + LOWER_SIZE;
+    let mut grid = vec![0u8; leaf + LEAF_SIZE];
+
+    let mut put = |offset: usize, bytes: &[u8]| {
+        grid[offset..offset + bytes.len()].copy_from_slice(bytes);
+    };
+    for (i, node) in [leaf, lower, upper, root].iter().enumerate() {
+        put(tree + 8 * i, &((node - tree) as i64).to_le_bytes());
+    }
+    for i in 0..3 {
+        put(tree + 32 + 4 * i, &1u32.to_le_bytes());
+    }
+    // root bounding box from (0, 0, 0) to (1, 0, 7)
+    put(root + 12, &1i32.to_le_bytes());
+    put(root + 20, &7i32.to_le_bytes());
+    // the voxel (1, 0, 2)
+    put(leaf + LEAF_VALUES + 4 * (64 + 2), &2.5f32.to_le_bytes());
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"NanoVDB0");
+    bytes.extend_from_slice(&(MAJOR_VERSION << 21).to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&CODEC_NONE.to_le_bytes());
+    let mut meta = [0u8; FILE_META_DATA_SIZE];
+    meta[8..16].copy_from_slice(&(grid.len() as u64).to_le_bytes());
+    meta[32..36].copy_from_slice(&GRID_TYPE_FLOAT.to_le_bytes());
+    meta[172..176].copy_from_slice(&(MAJOR_VERSION << 21).to_le_bytes());
+    bytes.extend_from_slice(&meta);
+    bytes.extend_from_slice(&grid);
+
+    let (resolution, densities) = load_float_grid(&bytes).unwrap();
+    assert_eq!([2, 1, 8], resolution);
+    assert_eq!(2.5, densities[1 + 2 * 2]);
+    assert_eq!(2.5, densities.iter().sum::<Float>());
+
+    assert!(load_float_grid(&bytes[..bytes.len() - 1]).is_err());
+}
+
+#[test]
+fn reject_compressed_grids() {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"NanoVDB0");
+    bytes.extend_from_slice(&(MAJOR_VERSION << 21).to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&CODEC_NONE.to_le_bytes());
+    let mut meta = [0u8; FILE_META_DATA_SIZE];
+    meta[8..16].copy_from_slice(&(GRID_DATA_SIZE as u64).to_le_bytes());
+    meta[32..36].copy_from_slice(&GRID_TYPE_FLOAT.to_le_bytes());
+    meta[172..176].copy_from_slice(&(MAJOR_VERSION << 21).to_le_bytes());
+    bytes.extend_from_slice(&meta);
+    bytes.extend_from_slice(&[0u8; GRID_DATA_SIZE]);
+
+    let load_with_codec = |offset: usize, codec: u16| {
+        let mut bytes = bytes.clone();
+        bytes[offset..offset + 2].copy_from_slice(&codec.to_le_bytes());
+        load_float_grid(&bytes).unwrap_err()
+    };
+
+    // compressed files
+    assert!(load_with_codec(14, 1).contains("ZIP"));
+    assert!(load_with_codec(14, 7).contains("codec 7"));
+    // compressed grids of an uncompressed file
+    let grid_codec = FILE_HEADER_SIZE + 168;
+    assert!(load_with_codec(grid_codec, 2).contains("BLOSC"));
+    assert!(load_with_codec(grid_codec, 3).contains("codec 3"));
+}
@@ -0,0 +1,199 @@
+//! Exports debug geometry like the camera frustum, light samples and BVH bounding boxes into a
+//! lightweight Wavefront OBJ file for inspection in DCC tools.
+
+use crate::camera::Camera;
+use crate::samplers::Sampler;
+use crate::scene::Scene;
+use crate::{Float, Vector3};
+use geometry::Aabb;
+use std::fmt::Write;
+use std::path::Path;
+use ultraviolet::UVec2;
+
+/// A builder of an OBJ file consisting of named objects of points and lines.
+#[derive(Default)]
+pub struct ObjExport {
+    content: String,
+    vertices: usize,
+}
+
+impl ObjExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the vertex and returns its (1-based) OBJ index.
+    fn vertex(&mut self, v: Vector3) -> usize {
+        writeln!(self.content, "v {} {} {}", v.x, v.y, v.z).unwrap();
+        self.vertices += 1;
+
+        self.vertices
+    }
+
+    fn object(&mut self, name: &str) {
+        writeln!(self.content, "o {}", name).unwrap();
+    }
+
+    fn line(&mut self, a: usize, b: usize) {
+        writeln!(self.content, "l {} {}", a, b).unwrap();
+    }
+
+    /// Adds the frustum of the camera, spanned by the primary rays of the corner pixels.
+    ///
+    /// # Arguments
+    /// * `camera` - The camera
+    /// * `distance` - The length of the frustum edges
+    ///
+    /// # Returns
+    /// * Self for chaining
+    pub fn add_frustum(&mut self, camera: &dyn Camera, distance: Float) -> &mut Self {
+        let res = camera.resolution();
+        let corners = [
+            UVec2::new(0, 0),
+            UVec2::new(res.x - 1, 0),
+            UVec2::new(res.x - 1, res.y - 1),
+            UVec2::new(0, res.y - 1),
+        ];
+
+        self.object("camera_frustum");
+
+        let rays: Vec<_> = corners.iter().map(|&c| camera.primary_ray(c)).collect();
+        let apex = self.vertex(rays[0].origin);
+        let ends: Vec<_> = rays.iter().map(|r| self.vertex(r.at(distance))).collect();
+
+        for i in 0..ends.len() {
+            self.line(apex, ends[i]);
+            self.line(ends[i], ends[(i + 1) % ends.len()]);
+        }
+
+        self
+    }
+
+    /// Adds random samples on each emitter, as seen from the given point, connected to the point.
+    ///
+    /// # Arguments
+    /// * `scene` - The scene containing the emitters
+    /// * `point` - The point to sample the emitters from
+    /// * `samples` - The number of samples per emitter
+    ///
+    /// # Returns
+    /// * Self for chaining
+    pub fn add_light_samples(
+        &mut self,
+        scene: &Scene,
+        point: Vector3,
+        samples: usize,
+    ) -> &mut Self {
+        let sampler = Sampler::Random;
+
+        for (i, emitter) in scene.emitters.iter().enumerate() {
+            self.object(&format!("light_samples_{}", i));
+
+            let origin = self.vertex(point);
+            for _ in 0..samples {
+                let sample = emitter.sample(point, sampler.get_2d());
+                let ray = sample.occlusion_tester.ray();
+                let target = self.vertex(ray.at(ray.t_end));
+
+                self.line(origin, target);
+            }
+        }
+
+        self
+    }
+
+    /// Adds the edges of the given bounding boxes.
+    ///
+    /// # Arguments
+    /// * `name` - The object name
+    /// * `boxes` - The bounding boxes
+    ///
+    /// # Returns
+    /// * Self for chaining
+    pub fn add_aabbs(&mut self, name: &str, boxes: &[Aabb]) -> &mut Self {
+        self.object(name);
+
+        for aabb in boxes {
+            let (min, max) = (aabb.min, aabb.max);
+            let corner = |i: usize| {
+                Vector3::new(
+                    if i & 1 == 0 { min.x } else { max.x },
+                    if i & 2 == 0 { min.y } else { max.y },
+                    if i & 4 == 0 { min.z } else { max.z },
+                )
+            };
+
+            let first = self.vertex(corner(0));
+            (1..8).for_each(|i| {
+                self.vertex(corner(i));
+            });
+
+            // connect all corners differing in exactly one axis
+            for i in 0..8 {
+                for axis in &[1, 2, 4] {
+                    if i & axis == 0 {
+                        self.line(first + i, first + (i | axis));
+                    }
+                }
+            }
+        }
+
+        self
+    }
+
+    /// Writes the OBJ file to the given path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, &self.content)
+    }
+}
+
+#[test]
+fn exported_boxes_load_back() {
+    use geometry::obj_file::ObjFile;
+
+    let boxes = [
+        Aabb::new(Vector3::new(-1.0, -2.0, -3.0), Vector3::new(1.0, 2.0, 3.0)),
+        Aabb::new(Vector3::new(0.5, 0.5, 0.5), Vector3::new(0.75, 1.0, 1.5)),
+    ];
+
+    let mut export = ObjExport::new();
+    export.add_aabbs("bvh", &boxes);
+
+    let path = std::env::temp_dir().join(format!("rust-v-export-{}.obj", std::process::id()));
+    export.save(&path).unwrap();
+    let obj = ObjFile::from(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    // 8 corners and 12 edges per box
+    assert_eq!(obj.vertices.len(), 16);
+    assert_eq!(
+        export
+            .content
+            .lines()
+            .filter(|l| l.starts_with("l "))
+            .count(),
+        24
+    );
+
+    for (aabb, corners) in boxes.iter().zip(obj.vertices.chunks(8)) {
+        for v in corners {
+            let p = v.position;
+            assert!(p == aabb.min.max_by_component(p) && p == aabb.max.min_by_component(p));
+        }
+        assert_eq!(corners[0].position, aabb.min);
+        assert_eq!(corners[7].position, aabb.max);
+    }
+
+    // every edge connects two corners of the same box differing in one axis
+    for line in export.content.lines().filter(|l| l.starts_with("l ")) {
+        let mut indices = line[2..]
+            .split(' ')
+            .map(|i| i.parse::<usize>().unwrap() - 1);
+        let (a, b) = (indices.next().unwrap(), indices.next().unwrap());
+        assert_eq!(a / 8, b / 8);
+
+        let d = obj.vertices[a].position - obj.vertices[b].position;
+        let zeros = [d.x, d.y, d.z].iter().filter(|&&c| c == 0.0).count();
+        assert_eq!(zeros, 2);
+    }
+}
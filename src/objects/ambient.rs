@@ -0,0 +1,23 @@
+use crate::Spectrum;
+use color::Color;
+use serde::{Deserialize, Serialize};
+
+/// An ambient light contributes a constant radiance to all rays escaping the scene.
+///
+/// It is a cheap replacement of a surrounding sky sphere to quickly light debug scenes.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct AmbientLight(pub Spectrum);
+
+impl AmbientLight {
+    /// Returns whether this ambient light does not contribute anything.
+    #[inline]
+    pub fn is_black(&self) -> bool {
+        self.0.is_black()
+    }
+}
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        Self(Spectrum::broadcast(0.0))
+    }
+}
@@ -1,14 +1,27 @@
 use crate::bxdf::BSDF;
 
 use crate::debug_utils::{is_finite, is_normalized, within_01};
+use crate::mc::{sample_unit_hemisphere, sample_unit_sphere};
+use crate::objects::{all_light_channels, SceneObject};
+use crate::samplers::Sampler;
 use crate::scene::{Scene, SceneIntersection};
 use crate::*;
 use color::Color;
 
-use geometry::{Aabb, Boundable, Geometry, Intersectable, Intersection, Ray};
+use geometry::{Aabb, Boundable, CoordinateSystem, Geometry, Intersectable, Intersection, Ray};
 use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::{PI, TAU};
+#[cfg(feature = "f64")]
+use std::f64::consts::{PI, TAU};
+use std::sync::atomic::{AtomicU64, Ordering};
 use utility::floats::FloatExt;
 
+/// The default multiplier of emitters, not scaling their emission.
+fn unit_multiplier() -> AtomicU64 {
+    AtomicU64::new(1f64.to_bits())
+}
+
 /// An emitter is similar to a receiver, consisting of a geometry and a BSDF. Additionally, the
 /// emitter also has an emission.
 #[derive(Serialize, Deserialize)]
@@ -16,7 +29,30 @@ pub struct Emitter {
     geometry: Box<dyn Sampleable>,
     #[serde(default)]
     pub bsdf: BSDF,
-    pub emission: Spectrum,
+    /// The name of a BSDF of the scene's material library, replacing `bsdf` when loading the
+    /// scene
+    #[serde(default, skip_serializing)]
+    pub material: Option<String>,
+    emission: Spectrum,
+    /// The multiplier of the light group (as bits of a `f64`), adjustable while rendering.
+    #[serde(skip, default = "unit_multiplier")]
+    multiplier: AtomicU64,
+    /// The light group, allowing to scale the emission of multiple emitters collectively.
+    #[serde(default)]
+    pub light_group: Option<String>,
+    /// The light-linking mask. Only objects with an overlapping mask get illuminated directly.
+    #[serde(default = "all_light_channels")]
+    pub light_mask: u32,
+    /// The optional name, identifying this emitter in [scene queries](crate::scene::Scene::find)
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The tags, selecting this emitter in [scene queries](crate::scene::Scene::tagged)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The label of this emitter in the segmentation [AOV](crate::sensor::aov::Aov), `0` being
+    /// reserved for the background
+    #[serde(default)]
+    pub label: u32,
 }
 
 impl Emitter {
@@ -33,13 +69,113 @@ impl Emitter {
         Self {
             geometry,
             bsdf,
+            material: None,
             emission,
+            multiplier: unit_multiplier(),
+            light_group: None,
+            light_mask: all_light_channels(),
+            name: None,
+            tags: Vec::new(),
+            label: 0,
         }
     }
 
+    #[inline]
+    pub fn geometry(&self) -> &dyn Sampleable {
+        self.geometry.as_ref()
+    }
+
+    /// Returns whether this emitter is linked to (may directly illuminate) the given object.
+    ///
+    /// # Arguments
+    /// * `object` - The object to illuminate
+    ///
+    /// # Returns
+    /// * Whether the light-linking masks overlap
+    #[inline]
+    pub fn is_linked(&self, object: &SceneObject) -> bool {
+        self.light_mask & object.light_mask() != 0
+    }
+
+    /// Returns the emission scaled by the multiplier of the light group.
+    ///
+    /// # Returns
+    /// * The emitted radiance
+    #[inline]
+    pub fn emission(&self) -> Spectrum {
+        self.emission * self.multiplier()
+    }
+
+    /// Returns the multiplier of the light group, `1` by default.
+    ///
+    /// # Returns
+    /// * The multiplier
+    #[inline]
+    pub fn multiplier(&self) -> Float {
+        f64::from_bits(self.multiplier.load(Ordering::Relaxed)) as Float
+    }
+
+    /// Sets the multiplier scaling the emission, e.g. of the light group. As it only takes a
+    /// shared reference, it may be adjusted while the emitter is in use.
+    ///
+    /// # Arguments
+    /// * `multiplier` - The multiplier
+    pub fn set_multiplier(&self, multiplier: Float) {
+        self.multiplier
+            .store((multiplier as f64).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Returns a heuristic of the power of this emitter, ignoring the multiplier of its light
+    /// group.
+    ///
+    /// # Returns
+    /// * The maximum emission times the surface area (or the intensity of points)
+    pub fn unscaled_power(&self) -> Float {
+        let area = self.geometry.surface_area();
+        // points have no area and emit their intensity
+        if area > 0.0 {
+            self.emission.component_max() * area
+        } else {
+            self.emission.component_max()
+        }
+    }
+
+    /// Returns a heuristic of how strongly this emitter illuminates the given point, dividing its
+    /// power by the squared distance to its bounds.
+    ///
+    /// # Arguments
+    /// * `point` - The point to illuminate
+    ///
+    /// # Returns
+    /// * The influence (infinite if the point lies within the bounds)
+    pub fn influence(&self, point: Vector3) -> Float {
+        self.unscaled_power() * self.multiplier() / self.bounds().distance_squared(point)
+    }
+
+    /// Returns whether this emitter is described by a delta distribution (e.g. a point light),
+    /// which cannot be hit by rays.
+    #[inline]
+    pub fn is_delta(&self) -> bool {
+        self.geometry.surface_area() == 0.0
+    }
+
+    /// Returns the pdf in the "solid angle" form that [sample](Emitter::sample) samples the given
+    /// incident direction from the given point.
+    ///
+    /// # Arguments
+    /// * `point` - The point from which we sample the emitter
+    /// * `incident` - The direction towards the emitter
+    ///
+    /// # Returns
+    /// * The pdf
+    #[inline]
+    pub fn pdf(&self, point: Vector3, incident: Vector3) -> Float {
+        self.geometry.pdf(point, incident)
+    }
+
     #[inline]
     pub fn emission_wavelength(&self, wavelength_index: usize) -> Float {
-        self.emission[wavelength_index]
+        self.emission[wavelength_index] * self.multiplier()
     }
 
     /// Returns the radiance of this emitter, comparing the incident and normal vector.
@@ -66,27 +202,49 @@ impl Emitter {
         let dot = incident.dot(normal);
 
         if dot > 0.0 {
-            self.emission
+            self.emission()
         } else {
             Spectrum::broadcast(0.0)
         }
     }
 
+    /// Writes the radiance of this emitter at the given wavelength indices into the buffer,
+    /// comparing the incident and normal vector.
+    ///
+    /// # Constraints
+    /// * `incident` - All values should be finite (neither infinite nor `NaN`).
+    ///   Should be normalized.
+    /// * `normal` - All values should be finite.
+    ///   Should be normalized.
+    /// * `buf` - Should have the same length as `indices`.
+    ///
+    /// # Arguments
+    /// * `incident` - The incident on the surface of an object
+    /// * `normal` - The normal on the surface of an object
+    /// * `indices` - The wavelength indices
+    /// * `buf` - The buffer to write the radiances into
+    #[inline]
     pub fn radiance_buf(
         &self,
         incident: Vector3,
         normal: Vector3,
         indices: &[usize],
-    ) -> Vec<Float> {
-        let mut buf = vec![0.0; indices.len()];
+        buf: &mut [Float],
+    ) {
+        debug_assert!(is_finite(incident));
+        debug_assert!(is_normalized(incident));
+        debug_assert!(is_finite(normal));
+        debug_assert!(is_normalized(normal));
+        debug_assert_eq!(indices.len(), buf.len());
 
         if incident.dot(normal) > 0.0 {
-            for i in 0..indices.len() {
-                buf[i] = self.emission[indices[i]];
+            let multiplier = self.multiplier();
+            for (b, &index) in buf.iter_mut().zip(indices) {
+                *b = self.emission[index] * multiplier;
             }
+        } else {
+            buf.iter_mut().for_each(|b| *b = 0.0);
         }
-
-        buf
     }
 
     #[inline]
@@ -135,12 +293,29 @@ impl Emitter {
         EmitterSample::new(radiance, incident, surface_sample.pdf, occlusion_tester)
     }
 
-    pub fn sample_buf(
+    /// Samples the emitter from a given point in space, writing the radiances at the given
+    /// wavelength indices into the buffer.
+    ///
+    /// # Constraints
+    /// * `point` - ALl values should be finite (neither infinite nor `NaN`).
+    /// * `sample` - All values should be within `[0, 1)`.
+    /// * `buf` - Should have the same length as `indices`.
+    ///
+    /// # Arguments
+    /// * `point` - The point from which we sample the emitter
+    /// * `sample` - A random sample
+    /// * `indices` - The wavelength indices
+    /// * `buf` - The buffer to write the radiances into
+    ///
+    /// # Returns
+    /// * An emitter sample borrowing the buffer
+    pub fn sample_buf<'a>(
         &self,
         point: Vector3,
         sample: Vector2,
         indices: &[usize],
-    ) -> EmitterSample<Vec<Float>> {
+        buf: &'a mut [Float],
+    ) -> EmitterSample<&'a [Float]> {
         debug_assert!(is_finite(point));
         debug_assert!(within_01(sample));
 
@@ -149,9 +324,9 @@ impl Emitter {
         let occlusion_tester = OcclusionTester::between(point, surface_sample.point);
         let incident = occlusion_tester.ray.direction;
 
-        let radiances = self.radiance_buf(-incident, surface_sample.normal, indices);
+        self.radiance_buf(-incident, surface_sample.normal, indices, buf);
 
-        EmitterSample::new(radiances, incident, surface_sample.pdf, occlusion_tester)
+        EmitterSample::new(buf, incident, surface_sample.pdf, occlusion_tester)
     }
 
     pub fn sample_wavelength(
@@ -172,6 +347,48 @@ impl Emitter {
 
         EmitterSample::new(radiance, incident, surface_sample.pdf, occlusion_tester)
     }
+
+    /// Samples a ray leaving this emitter, e.g. to trace photons.
+    /// Area emitters emit cosine weighted from a point sampled uniformly by area, while points
+    /// emit uniformly into all directions.
+    ///
+    /// # Constraints
+    /// * `sample_point` - All values should be within `[0, 1)`.
+    /// * `sample_direction` - All values should be within `[0, 1)`.
+    ///
+    /// # Arguments
+    /// * `sample_point` - A random sample for the starting point
+    /// * `sample_direction` - A random sample for the direction
+    ///
+    /// # Returns
+    /// * The starting point, the direction and the flux of the ray, i.e. the emission divided by
+    ///   the pdfs of sampling the ray
+    pub fn sample_ray(
+        &self,
+        sample_point: Vector2,
+        sample_direction: Vector2,
+    ) -> (SurfaceSample, Vector3, Spectrum) {
+        debug_assert!(within_01(sample_point));
+        debug_assert!(within_01(sample_direction));
+
+        let surface_sample = self.geometry.sample_area(sample_point);
+
+        if self.is_delta() {
+            let direction = sample_unit_sphere(sample_direction);
+            let flux = self.emission() * (2.0 * TAU);
+
+            return (surface_sample, direction, flux);
+        }
+
+        let frame = CoordinateSystem::from_y(surface_sample.normal);
+        let local = sample_unit_hemisphere(sample_direction);
+        let direction = frame.x_axis * local.x + frame.y_axis * local.y + frame.z_axis * local.z;
+
+        // the cosine of the emitted radiance cancels with the cosine weighted pdf up to pi
+        let flux = self.emission() * (PI / surface_sample.pdf);
+
+        (surface_sample, direction, flux)
+    }
 }
 
 impl Boundable for Emitter {
@@ -238,12 +455,13 @@ impl<T> EmitterSample<T> {
 /// A simple occlusion tester to test a ray against a scene.
 pub struct OcclusionTester {
     ray: Ray,
+    distance: Float,
 }
 
 impl OcclusionTester {
     /// Creates a new occlusion tester between the two given points.
-    /// The created ray partition will be clamped to `[e, distance - e]`, with `e` denoting an epsilon
-    /// and `distance` the distance between the points.
+    /// The created ray partition will be clamped to `[e, distance - e]`, with `e` denoting the
+    /// [epsilon](Scene::epsilon) of the tested scene and `distance` the distance between the points.
     /// This is to work around floating point imprecision that might occur in the intersection code.
     ///
     /// # Constraints
@@ -264,8 +482,15 @@ impl OcclusionTester {
         let direction = target - origin;
         let distance = direction.mag();
 
-        let mut t_start = Float::big_epsilon();
-        let mut t_end = distance - Float::big_epsilon();
+        let ray = Self::clamped_ray(origin, direction / distance, distance, Float::big_epsilon());
+
+        Self { ray, distance }
+    }
+
+    /// Creates the ray partition `[e, distance - e]` between the points.
+    fn clamped_ray(origin: Vector3, direction: Vector3, distance: Float, epsilon: Float) -> Ray {
+        let mut t_start = epsilon;
+        let mut t_end = distance - epsilon;
 
         if t_end < t_start {
             // edge case when distance very small
@@ -273,9 +498,25 @@ impl OcclusionTester {
             t_end = distance;
         }
 
-        let ray = Ray::new(origin, direction / distance, t_start, t_end);
+        Ray::new(origin, direction, t_start, t_end)
+    }
 
-        Self { ray }
+    /// Returns the contained ray, clamped by the [epsilon](Scene::epsilon) of the given scene.
+    pub fn ray_in(&self, scene: &Scene) -> Ray {
+        Self::clamped_ray(
+            self.ray.origin,
+            self.ray.direction,
+            self.distance,
+            scene.epsilon(),
+        )
+    }
+
+    /// Returns the contained ray.
+    ///
+    /// # Returns
+    /// * The ray to test
+    pub fn ray(&self) -> &Ray {
+        &self.ray
     }
 
     /// Tests the contained ray against the scene, whether it is unoccluded.
@@ -287,7 +528,30 @@ impl OcclusionTester {
     /// * `true` - If unoccluded
     /// * `false` - Otherwise
     pub fn unoccluded(&self, scene: &Scene) -> bool {
-        !scene.is_occluded(&self.ray)
+        !scene.is_occluded(&self.ray_in(scene))
+    }
+
+    /// Tests the contained ray against the scene, returning the transmittance through its
+    /// [media](Scene::media) and [atmosphere](Scene::atmosphere).
+    ///
+    /// # Arguments
+    /// * `scene` - The scene to test against
+    /// * `sampler` - The sampler to estimate the transmittance
+    ///
+    /// # Returns
+    /// * `0` - If occluded
+    /// * `1` - If unoccluded without media in between
+    /// * The transmittance - Otherwise
+    pub fn transmittance(&self, scene: &Scene, sampler: Sampler) -> Spectrum {
+        let ray = self.ray_in(scene);
+
+        if scene.is_occluded(&ray) {
+            Spectrum::broadcast(0.0)
+        } else if !scene.has_media() {
+            Spectrum::broadcast(1.0)
+        } else {
+            scene.transmittance(&ray, ray.t_end, sampler)
+        }
     }
 
     /// Tests the contained ray against the scene.
@@ -298,7 +562,7 @@ impl OcclusionTester {
     /// # Returns
     /// * The scene intersection (if any)
     pub fn test_get(&self, scene: &Scene) -> Option<SceneIntersection> {
-        scene.intersect(&self.ray)
+        scene.intersect(&self.ray_in(scene))
     }
 }
 
@@ -345,6 +609,12 @@ pub trait Sampleable: Geometry + Send + Sync {
     /// * The surface area
     fn surface_area(&self) -> Float;
 
+    /// Returns the name of the kind of this sampleable, e.g. for statistics.
+    ///
+    /// # Returns
+    /// * The kind name
+    fn kind(&self) -> &'static str;
+
     /// Samples the surface from the given point in the "solid angle" form.
     ///
     /// # Constraints
@@ -358,4 +628,113 @@ pub trait Sampleable: Geometry + Send + Sync {
     /// # Returns
     /// * A surface sample
     fn sample_surface(&self, origin: Vector3, sample: Vector2) -> SurfaceSample;
+
+    /// Samples the surface uniformly by area, e.g. to emit photons.
+    ///
+    /// # Constraints
+    /// * `sample` - ALl values should be inside `[0, 1)`.
+    ///
+    /// # Arguments
+    /// * `sample` - A random sample
+    ///
+    /// # Returns
+    /// * A surface sample with the pdf in the "area" form
+    fn sample_area(&self, sample: Vector2) -> SurfaceSample;
+
+    /// Returns the pdf in the "solid angle" form that [sample_surface](Sampleable::sample_surface)
+    /// samples the given incident direction.
+    ///
+    /// # Constraints
+    /// * `origin` - All values should be finite (neither infinite nor `NaN`).
+    /// * `incident` - All values should be finite.
+    ///                Should be normalized.
+    ///
+    /// # Arguments
+    /// * `origin` - The point at which we look at the object
+    /// * `incident` - The direction towards the object
+    ///
+    /// # Returns
+    /// * The pdf (`0` if the direction cannot be sampled)
+    fn pdf(&self, origin: Vector3, incident: Vector3) -> Float;
+}
+
+#[test]
+fn buffered_samples_match_the_spectrum() {
+    use geometry::Sphere;
+
+    let mut emission = Spectrum::broadcast(0.0);
+    for i in 0..Spectrum::size() {
+        emission[i] = i as Float;
+    }
+    let emitter = Emitter::new(
+        Box::new(Sphere::new(Vector3::zero(), 1.0)),
+        BSDF::empty(),
+        emission,
+    );
+
+    let point = Vector3::new(0.0, 0.0, 3.0);
+    let sample = Vector2::new(0.3, 0.7);
+    let indices = [5, 0, 5, 2];
+
+    let spectral = emitter.sample(point, sample);
+    // stale values from a previous use of the buffer
+    let mut buf = [Float::NAN; 4];
+    let buffered = emitter.sample_buf(point, sample, &indices, &mut buf);
+
+    assert_eq!(buffered.incident, spectral.incident);
+    assert_eq!(buffered.pdf, spectral.pdf);
+    for (&radiance, &index) in buffered.radiance.iter().zip(&indices) {
+        assert_eq!(radiance, spectral.radiance[index]);
+    }
+    assert_eq!(buf, [5.0, 0.0, 5.0, 2.0]);
+
+    // facing away from the normal emits nothing
+    emitter.radiance_buf(-Vector3::unit_z(), Vector3::unit_z(), &indices, &mut buf);
+    assert_eq!(buf, [0.0; 4]);
+}
+
+#[test]
+fn disks_and_tubes_emit_on_both_sides() {
+    use geometry::{Cylinder, Disk};
+
+    let emission = Spectrum::broadcast(2.0);
+    let disk = Emitter::new(
+        Box::new(Disk::new(Vector3::zero(), Vector3::unit_y(), 1.0)),
+        BSDF::empty(),
+        emission,
+    );
+
+    // the solid angle of the disk seen head-on from a distance of `1`
+    let expected = TAU * (1.0 - 1.0 / Float::sqrt(2.0));
+
+    for &point in &[Vector3::unit_y(), -Vector3::unit_y()] {
+        let n = 64;
+        let mut solid_angle = 0.0;
+        for x in 0..n {
+            for y in 0..n {
+                let sample = Vector2::new(x as Float + 0.5, y as Float + 0.5) / n as Float;
+                let sample = disk.sample(point, sample);
+
+                assert_eq!(sample.radiance, emission);
+                solid_angle += 1.0 / sample.pdf;
+            }
+        }
+        solid_angle /= (n * n) as Float;
+
+        assert!((solid_angle - expected).abs() < 0.01 * expected);
+    }
+
+    let tube = Emitter::new(
+        Box::new(Cylinder::new((-Vector3::unit_x(), Vector3::unit_x()), 0.5)),
+        BSDF::empty(),
+        emission,
+    );
+
+    // inside and outside the tube
+    for &point in &[Vector3::zero(), Vector3::new(0.0, 2.0, 0.0)] {
+        let sample = tube.sample(point, Vector2::new(0.5, 0.25));
+
+        assert_eq!(sample.radiance, emission);
+        assert!(sample.pdf > 0.0);
+    }
 }
@@ -0,0 +1,297 @@
+use crate::objects::Emitter;
+use crate::{Float, Vector3};
+use geometry::{Aabb, Boundable};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The importance of nodes, whose power gets divided by a vanishing distance.
+const MAX_IMPORTANCE: Float = 1e16;
+
+/// A node of the [light tree](LightTree). The left child of an inner node directly follows it.
+#[derive(Clone)]
+struct LightNode {
+    bounds: Aabb,
+    /// The summed (unscaled) power of the emitters below
+    power: Float,
+    /// The first position of the emitters below in the order of the tree
+    start: usize,
+    /// The position after the last emitter below in the order of the tree
+    end: usize,
+    /// The index of the right child (unused in leaves)
+    right: usize,
+}
+
+impl LightNode {
+    #[inline]
+    fn is_leaf(&self) -> bool {
+        self.end - self.start == 1
+    }
+
+    /// Returns a heuristic of how strongly the emitters below illuminate the given point,
+    /// dividing their power by the squared distance to the bounds, but at least by the squared
+    /// radius of the bounds.
+    fn importance(&self, point: Vector3) -> Float {
+        if self.power <= 0.0 {
+            return 0.0;
+        }
+
+        let radius_sq = self.bounds.size().mag_sq() / 4.0;
+        let distance_sq = self.bounds.distance_squared(point).max(radius_sq);
+
+        if distance_sq > 0.0 {
+            (self.power / distance_sq).min(MAX_IMPORTANCE)
+        } else {
+            MAX_IMPORTANCE
+        }
+    }
+}
+
+/// A light tree is a bounding volume hierarchy over the emitters of a scene, selecting an emitter
+/// for a shading point in logarithmic time. Descending from the root, each inner node picks a
+/// child proportionally to its [importance](LightNode::importance) at the shading point, such
+/// that near and powerful emitters get selected more often.
+///
+/// The tree captures the power of the emitters without the multipliers of their light groups,
+/// which may change while rendering.
+#[derive(Clone, Default)]
+pub struct LightTree {
+    nodes: Vec<LightNode>,
+    /// The indices of the emitters in the order of the leaves
+    order: Vec<usize>,
+    /// The positions of the emitters in `order`, keyed by their address
+    positions: HashMap<usize, usize>,
+}
+
+/// Returns the key of the given emitter in the positions of a light tree.
+#[inline]
+fn key(emitter: &Emitter) -> usize {
+    emitter as *const Emitter as usize
+}
+
+impl LightTree {
+    /// Creates a new light tree over the given emitters, splitting them at the median of their
+    /// centers along the largest extent.
+    ///
+    /// # Arguments
+    /// * `emitters` - The emitters of the scene
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(emitters: &[Arc<Emitter>]) -> Self {
+        let mut tree = Self {
+            nodes: Vec::with_capacity(2 * emitters.len()),
+            order: (0..emitters.len()).collect(),
+            positions: HashMap::with_capacity(emitters.len()),
+        };
+
+        if !emitters.is_empty() {
+            tree.build(emitters, 0, emitters.len());
+        }
+
+        for (position, &i) in tree.order.iter().enumerate() {
+            tree.positions.insert(key(&emitters[i]), position);
+        }
+
+        tree
+    }
+
+    /// Builds the subtree of the emitters at the given range of the order.
+    ///
+    /// # Returns
+    /// * The index of the subtree's root
+    fn build(&mut self, emitters: &[Arc<Emitter>], start: usize, end: usize) -> usize {
+        let below = &mut self.order[start..end];
+        let bounds = Aabb::union_all(below.iter().map(|&i| emitters[i].bounds()));
+        let power = below.iter().map(|&i| emitters[i].unscaled_power()).sum();
+
+        let index = self.nodes.len();
+        self.nodes.push(LightNode {
+            bounds,
+            power,
+            start,
+            end,
+            right: 0,
+        });
+
+        if end - start > 1 {
+            let center = |i: usize| emitters[i].bounds().center();
+            let centers = Aabb::union_all(below.iter().map(|&i| Aabb::new(center(i), center(i))));
+            let size = centers.size();
+            let axis = if size.x >= size.y && size.x >= size.z {
+                0
+            } else if size.y >= size.z {
+                1
+            } else {
+                2
+            };
+
+            let mid = (start + end) / 2;
+            below.select_nth_unstable_by(mid - start, |&a, &b| {
+                center(a)[axis]
+                    .partial_cmp(&center(b)[axis])
+                    .unwrap_or(Ordering::Equal)
+            });
+
+            self.build(emitters, start, mid);
+            let right = self.build(emitters, mid, end);
+            self.nodes[index].right = right;
+        }
+
+        index
+    }
+
+    /// Returns a heuristic of how strongly all emitters illuminate the given point, comparable to
+    /// their [influence](Emitter::influence).
+    ///
+    /// # Arguments
+    /// * `point` - The shading point
+    ///
+    /// # Returns
+    /// * The importance of the root (`0` without emitters)
+    pub fn importance(&self, point: Vector3) -> Float {
+        self.nodes
+            .first()
+            .map_or(0.0, |root| root.importance(point))
+    }
+
+    /// Returns the probabilities of descending into the left and right child of the given inner
+    /// node.
+    fn child_probabilities(&self, index: usize, point: Vector3) -> Option<(Float, Float)> {
+        let left = self.nodes[index + 1].importance(point);
+        let right = self.nodes[self.nodes[index].right].importance(point);
+        let total = left + right;
+
+        if total > 0.0 {
+            Some((left / total, right / total))
+        } else {
+            None
+        }
+    }
+
+    /// Selects an emitter for the given shading point.
+    ///
+    /// # Constraints
+    /// * `sample` - Should be within `[0, 1)`.
+    ///
+    /// # Arguments
+    /// * `point` - The shading point
+    /// * `sample` - A random sample
+    ///
+    /// # Returns
+    /// * The index of the selected emitter (`None` if no emitter illuminates the point)
+    pub fn sample(&self, point: Vector3, mut sample: Float) -> Option<usize> {
+        if self.importance(point) <= 0.0 {
+            return None;
+        }
+
+        let mut index = 0;
+        while !self.nodes[index].is_leaf() {
+            let (left, _) = self.child_probabilities(index, point)?;
+
+            // reuse the sample for the next decision
+            if sample < left {
+                index += 1;
+                sample /= left;
+            } else {
+                index = self.nodes[index].right;
+                sample = (sample - left) / (1.0 - left);
+            }
+            sample = sample.min(1.0 - Float::EPSILON);
+        }
+
+        Some(self.order[self.nodes[index].start])
+    }
+
+    /// Returns the probability that [sample](LightTree::sample) selects the given emitter for the
+    /// given shading point.
+    ///
+    /// # Arguments
+    /// * `point` - The shading point
+    /// * `emitter` - The emitter
+    ///
+    /// # Returns
+    /// * The selection probability (`0` if the emitter is not part of the tree)
+    pub fn pdf(&self, point: Vector3, emitter: &Emitter) -> Float {
+        let position = match self.positions.get(&key(emitter)) {
+            Some(&position) => position,
+            None => return 0.0,
+        };
+
+        if self.importance(point) <= 0.0 {
+            return 0.0;
+        }
+
+        let mut index = 0;
+        let mut pdf = 1.0;
+        while !self.nodes[index].is_leaf() {
+            let (left, right) = match self.child_probabilities(index, point) {
+                Some(probabilities) => probabilities,
+                None => return 0.0,
+            };
+
+            let right_index = self.nodes[index].right;
+            if position < self.nodes[right_index].start {
+                index += 1;
+                pdf *= left;
+            } else {
+                index = right_index;
+                pdf *= right;
+            }
+        }
+
+        pdf
+    }
+}
+
+#[test]
+fn sampling_matches_pdf() {
+    use crate::bxdf::BSDF;
+    use crate::Spectrum;
+    use color::Color;
+    use geometry::{Point, Sphere};
+
+    let emitters: Vec<Arc<Emitter>> = (0..7)
+        .map(|i| {
+            let center = Vector3::new(i as Float, (i % 3) as Float, 0.0);
+            let emission = Spectrum::broadcast(1.0 + i as Float);
+            let geometry: Box<dyn crate::objects::Sampleable> = if i % 2 == 0 {
+                Box::new(Sphere::new(center, 0.25))
+            } else {
+                Box::new(Point(center))
+            };
+
+            Arc::new(Emitter::new(geometry, BSDF::empty(), emission))
+        })
+        .collect();
+    let tree = LightTree::new(&emitters);
+
+    let point = Vector3::new(1.2, 3.0, 0.5);
+    let pdfs: Vec<Float> = emitters.iter().map(|e| tree.pdf(point, e)).collect();
+    assert!((pdfs.iter().sum::<Float>() - 1.0).abs() < 1e-5);
+    assert!(pdfs.iter().all(|&pdf| pdf > 0.0));
+
+    let n = 70000;
+    let mut counts = vec![0; emitters.len()];
+    for i in 0..n {
+        let sample = (i as Float + 0.5) / n as Float;
+        counts[tree.sample(point, sample).unwrap()] += 1;
+    }
+    for (&count, &pdf) in counts.iter().zip(&pdfs) {
+        assert!((count as Float / n as Float - pdf).abs() < 1e-3);
+    }
+
+    // near emitters are more likely than distant ones
+    let near = Vector3::new(6.0, 0.0, 0.5);
+    assert!(tree.pdf(near, &emitters[6]) > 0.5);
+    assert!(tree.pdf(near, &emitters[0]) < 0.01);
+
+    // unknown emitters never get selected
+    let other = Emitter::new(
+        Box::new(Point(point)),
+        BSDF::empty(),
+        Spectrum::broadcast(1.0),
+    );
+    assert_eq!(0.0, tree.pdf(point, &other));
+    assert_eq!(None, LightTree::default().sample(point, 0.5));
+}
@@ -1,15 +1,30 @@
+#[cfg(not(feature = "static-dispatch"))]
+use geometry::Geometry;
 use geometry::{Aabb, Boundable, Intersectable, Intersection, Ray};
 
+mod ambient;
 mod emitter;
+mod light_tree;
+mod model;
 mod receiver;
 mod sampleables;
 
 use crate::bxdf::BSDF;
+use crate::{Vector2, Vector3};
+pub use ambient::*;
 pub use emitter::*;
+pub use light_tree::LightTree;
+pub use model::*;
 pub use receiver::*;
+pub use sampleables::Transformed;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+/// The default light-linking mask, linking to all channels.
+pub(crate) fn all_light_channels() -> u32 {
+    u32::MAX
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub enum SceneObject {
     Emitter(Arc<Emitter>),
@@ -24,6 +39,79 @@ impl SceneObject {
             SceneObject::Receiver(r) => &r.bsdf,
         }
     }
+
+    /// Returns the number of triangles of the object's geometry.
+    #[inline]
+    pub fn triangle_count(&self) -> usize {
+        match self {
+            SceneObject::Emitter(e) => e.geometry().triangle_count(),
+            SceneObject::Receiver(r) => r.geometry().triangle_count(),
+        }
+    }
+
+    /// Returns the light-linking mask of this object.
+    #[inline]
+    pub fn light_mask(&self) -> u32 {
+        match self {
+            SceneObject::Emitter(e) => e.light_mask,
+            SceneObject::Receiver(r) => r.light_mask,
+        }
+    }
+
+    /// Returns the name of this object (if any).
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            SceneObject::Emitter(e) => e.name.as_deref(),
+            SceneObject::Receiver(r) => r.name.as_deref(),
+        }
+    }
+
+    /// Returns the tags of this object.
+    #[inline]
+    pub fn tags(&self) -> &[String] {
+        match self {
+            SceneObject::Emitter(e) => &e.tags,
+            SceneObject::Receiver(r) => &r.tags,
+        }
+    }
+
+    /// Generates the texture coordinates of the given surface point by the
+    /// [uv mapping](Receiver::uv_mapping) of this object.
+    ///
+    /// # Returns
+    /// * The texture coordinates, or `None` if no mapping is set
+    #[inline]
+    pub fn uv(&self, point: Vector3, normal: Vector3) -> Option<Vector2> {
+        match self {
+            SceneObject::Emitter(_) => None,
+            SceneObject::Receiver(r) => r.uv(point, normal),
+        }
+    }
+
+    /// Returns the segmentation label of this object.
+    #[inline]
+    pub fn label(&self) -> u32 {
+        match self {
+            SceneObject::Emitter(e) => e.label,
+            SceneObject::Receiver(r) => r.label,
+        }
+    }
+
+    /// Returns whether this object carries the given tag.
+    #[inline]
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().iter().any(|t| t == tag)
+    }
+
+    /// Returns whether this object is a [matte](Receiver::matte) holdout.
+    #[inline]
+    pub fn is_matte(&self) -> bool {
+        match self {
+            SceneObject::Emitter(_) => false,
+            SceneObject::Receiver(r) => r.matte,
+        }
+    }
 }
 
 impl Boundable for SceneObject {
@@ -0,0 +1,235 @@
+use crate::bxdf::{
+    BeckmannDistribution, BxDFItem, FresnelSpecular, FresnelType, LambertianReflection,
+    MicrofacetReflection, BSDF,
+};
+use crate::objects::{all_light_channels, Receiver, ReceiverGeometry};
+use crate::refractive_index::RefractiveType;
+use crate::texture::{Texture, TextureValue};
+use crate::{Float, Spectrum};
+use color::Color;
+use geometry::mtl_file::{MtlFile, MtlMaterial};
+use geometry::obj_file::ObjFile;
+use geometry::{FromObjFile, Mesh, ShadingMode};
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::path::Path;
+
+/// The minimum roughness of specular highlights, avoiding degenerate microfacet distributions.
+const MIN_ALPHA: Float = 1e-3;
+
+/// The textual representation of a [Model] in scene files.
+#[derive(Deserialize)]
+struct ModelSource {
+    obj: FromObjFile,
+    shading_mode: ShadingMode,
+    /// The mtl file, relative to the scene file. Defaults to the `mtllib` of the obj file.
+    #[serde(default)]
+    mtl: Option<String>,
+    #[serde(default = "all_light_channels")]
+    light_mask: u32,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    label: u32,
+}
+
+/// An obj file with multiple groups and materials, deserializing into one [Receiver] per group
+/// and material. Their BSDFs get derived from the materials of the mtl file.
+///
+/// Each receiver keeps its part of the obj file, such that the scene serializes into ordinary
+/// receivers with [meshes](Mesh) selecting their group and material.
+#[derive(Deserialize)]
+#[serde(try_from = "ModelSource")]
+pub struct Model {
+    receivers: Vec<Receiver>,
+}
+
+impl Model {
+    /// Returns the receivers of the parts of the model.
+    #[inline]
+    pub fn into_receivers(self) -> Vec<Receiver> {
+        self.receivers
+    }
+}
+
+impl TryFrom<ModelSource> for Model {
+    type Error = String;
+
+    fn try_from(source: ModelSource) -> Result<Self, Self::Error> {
+        let obj_path = source.obj.path().to_string();
+        let obj_file = ObjFile::from(geometry::resolve_dependency(&obj_path));
+
+        let mtl = source.mtl.clone().or_else(|| {
+            obj_file
+                .material_libraries
+                .first()
+                .map(|lib| sibling(&obj_path, lib))
+        });
+        let mtl_file = match &mtl {
+            Some(mtl) => MtlFile::load(geometry::resolve_dependency(mtl))?,
+            None => MtlFile::default(),
+        };
+
+        let mut receivers = Vec::new();
+        for (group, material) in obj_file.parts() {
+            let mtl_material = material
+                .as_deref()
+                .and_then(|m| mtl_file.get(m))
+                .cloned()
+                .unwrap_or_else(|| MtlMaterial::new(String::new()));
+            let mtl = mtl.as_deref().unwrap_or(&obj_path);
+
+            let obj = source.obj.clone().with_part(Some(group.clone()), material);
+            let mesh = Mesh::from_obj(obj, source.shading_mode, false);
+
+            let mut receiver = Receiver::new(receiver_geometry(mesh), bsdf(&mtl_material, mtl)?);
+            receiver.alpha = alpha(&mtl_material, mtl)?;
+            receiver.light_mask = source.light_mask;
+            receiver.name = Some(match &source.name {
+                Some(name) => format!("{}/{}", name, group),
+                None => group,
+            });
+            receiver.tags = source.tags.clone();
+            receiver.label = source.label;
+
+            receivers.push(receiver);
+        }
+
+        Ok(Self { receivers })
+    }
+}
+
+/// Returns the path of a file relative to another file, e.g. of a texture relative to its mtl
+/// file.
+fn sibling(file: &str, relative: &str) -> String {
+    let (file, relative) = (file.replace('\\', "/"), relative.replace('\\', "/"));
+    if Path::new(&relative).is_absolute() {
+        return relative;
+    }
+
+    match file.rfind('/') {
+        Some(i) => format!("{}/{}", &file[..i], relative),
+        None => relative,
+    }
+}
+
+/// Converts a color of an mtl file.
+#[inline]
+fn spectrum(rgb: [Float; 3]) -> Spectrum {
+    <Spectrum as TextureValue>::from_rgb(rgb)
+}
+
+/// Derives the BSDF of an mtl material: refractive illumination models become glass, others a
+/// diffuse base (`Kd`, `map_Kd`) with glossy highlights (`Ks`, `Ns`).
+///
+/// # Arguments
+/// * `material` - The mtl material
+/// * `mtl` - The path of the mtl file as written, to resolve its maps against
+///
+/// # Returns
+/// * The BSDF, or an error message
+fn bsdf(material: &MtlMaterial, mtl: &str) -> Result<BSDF, String> {
+    let mut bxdfs = Vec::new();
+
+    if material.is_refractive() {
+        let eta = material
+            .ior
+            .map_or(RefractiveType::Glass, |n| RefractiveType::Linear(n, n));
+        let t = material
+            .transmission
+            .map_or(Spectrum::broadcast(1.0), spectrum);
+
+        let glass = FresnelSpecular::new(Spectrum::broadcast(1.0), t, RefractiveType::Air, eta);
+        bxdfs.push(bxdf_item(glass));
+
+        return Ok(BSDF::new(bxdfs));
+    }
+
+    let diffuse = match &material.diffuse_map {
+        Some(map) => {
+            let file = sibling(mtl, map);
+            let path = geometry::resolve_dependency(&file);
+
+            Some(Texture::image(file, path, 1.0)?)
+        }
+        None if material.diffuse.iter().any(|c| *c > 0.0) => {
+            Some(Texture::Constant(spectrum(material.diffuse)))
+        }
+        None => None,
+    };
+    if let Some(diffuse) = diffuse {
+        bxdfs.push(bxdf_item(LambertianReflection::new(diffuse)));
+    }
+
+    // illumination models 0 and 1 have no highlights
+    if material.illumination >= 2 && material.specular.iter().any(|c| *c > 0.0) {
+        // the Beckmann roughness matching a Phong exponent
+        let alpha = (2.0 / (material.shininess.max(0.0) + 2.0))
+            .sqrt()
+            .max(MIN_ALPHA);
+        let distribution = Box::new(BeckmannDistribution::new(alpha, alpha, true));
+
+        bxdfs.push(bxdf_item(MicrofacetReflection::new(
+            spectrum(material.specular),
+            distribution,
+            FresnelType::NoOp,
+        )));
+    }
+
+    Ok(BSDF::new(bxdfs))
+}
+
+/// Derives the alpha mask of an mtl material from its opacity (`d`, `map_d`). Refractive
+/// materials stay opaque, as their transparency comes from the refraction.
+///
+/// # Arguments
+/// * `material` - The mtl material
+/// * `mtl` - The path of the mtl file as written, to resolve its maps against
+///
+/// # Returns
+/// * The alpha mask (if any), or an error message
+fn alpha(material: &MtlMaterial, mtl: &str) -> Result<Option<Texture<Float>>, String> {
+    if material.is_refractive() {
+        return Ok(None);
+    }
+
+    match &material.dissolve_map {
+        Some(map) => {
+            let file = sibling(mtl, map);
+            let path = geometry::resolve_dependency(&file);
+
+            Texture::image(file, path, material.dissolve).map(Some)
+        }
+        None if material.dissolve < 1.0 => Ok(Some(Texture::Constant(material.dissolve.max(0.0)))),
+        None => Ok(None),
+    }
+}
+
+#[cfg(not(feature = "static-dispatch"))]
+fn receiver_geometry(mesh: Mesh) -> ReceiverGeometry {
+    Box::new(mesh)
+}
+
+#[cfg(feature = "static-dispatch")]
+fn receiver_geometry(mesh: Mesh) -> ReceiverGeometry {
+    mesh.into()
+}
+
+#[cfg(not(feature = "static-dispatch"))]
+fn bxdf_item<B: crate::bxdf::BxDF + 'static>(bxdf: B) -> BxDFItem {
+    Box::new(bxdf)
+}
+
+#[cfg(feature = "static-dispatch")]
+fn bxdf_item<B: Into<BxDFItem>>(bxdf: B) -> BxDFItem {
+    bxdf.into()
+}
+
+#[test]
+fn resolve_siblings() {
+    assert_eq!("meshes/room.mtl", sibling("meshes/room.obj", "room.mtl"));
+    assert_eq!("../tex/wood.png", sibling("..\\tex\\room.mtl", "wood.png"));
+    assert_eq!("room.mtl", sibling("room.obj", "room.mtl"));
+}
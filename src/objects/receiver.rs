@@ -1,12 +1,84 @@
 use crate::bxdf::BSDF;
-use geometry::{Aabb, Boundable, Geometry, Intersectable, Intersection, Ray};
+use crate::objects::all_light_channels;
+use crate::texture::{Texture, TexturePoint};
+use crate::{Float, Vector2, Vector3};
+#[cfg(not(feature = "static-dispatch"))]
+use geometry::Geometry;
+use geometry::{Aabb, Boundable, Intersectable, Intersection, Ray, UvMapping};
 use serde::{Deserialize, Serialize};
+use utility::floats::FloatExt;
+
+/// The maximum number of cut out surface points skipped by a single ray.
+const MAX_CUTOUTS: usize = 64;
+
+/// The geometry of a [Receiver], dispatched dynamically by default or statically with the
+/// `static-dispatch` feature.
+#[cfg(not(feature = "static-dispatch"))]
+pub type ReceiverGeometry = Box<dyn Geometry>;
+/// The geometry of a [Receiver], dispatched dynamically by default or statically with the
+/// `static-dispatch` feature.
+#[cfg(feature = "static-dispatch")]
+pub type ReceiverGeometry = geometry::Geometries;
+
+/// Wraps the given geometry into a [ReceiverGeometry], independently of the dispatch.
+///
+/// # Arguments
+/// * `geometry` - The geometry
+///
+/// # Returns
+/// * The receiver geometry
+#[cfg(not(feature = "static-dispatch"))]
+pub fn receiver_geometry<G: Geometry + 'static>(geometry: G) -> ReceiverGeometry {
+    Box::new(geometry)
+}
+
+/// Wraps the given geometry into a [ReceiverGeometry], independently of the dispatch.
+///
+/// # Arguments
+/// * `geometry` - The geometry
+///
+/// # Returns
+/// * The receiver geometry
+#[cfg(feature = "static-dispatch")]
+pub fn receiver_geometry<G: Into<ReceiverGeometry>>(geometry: G) -> ReceiverGeometry {
+    geometry.into()
+}
 
 /// A receiver consists of a geometry and a BSDF.
 #[derive(Serialize, Deserialize)]
 pub struct Receiver {
-    geometry: Box<dyn Geometry>,
+    geometry: ReceiverGeometry,
+    #[serde(default)]
     pub bsdf: BSDF,
+    /// The name of a BSDF of the scene's material library, replacing `bsdf` when loading the
+    /// scene
+    #[serde(default, skip_serializing)]
+    pub material: Option<String>,
+    /// The light-linking mask. Only emitters with an overlapping mask illuminate this receiver.
+    #[serde(default = "all_light_channels")]
+    pub light_mask: u32,
+    /// The optional generation of texture coordinates
+    #[serde(default)]
+    pub uv_mapping: Option<UvMapping>,
+    /// The optional alpha mask cutting out parts of the surface, e.g. of leaves or fences.
+    /// Intersections skip surface points with a probability of `1 - alpha`, such that they cast
+    /// no shadows either.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<Texture<Float>>,
+    /// Whether this receiver is a holdout. It still takes part in the light transport, but its
+    /// pixels get a zero alpha, such that it can be composited with a photographed foreground.
+    #[serde(default)]
+    pub matte: bool,
+    /// The optional name, identifying this receiver in [scene queries](crate::scene::Scene::find)
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The tags, selecting this receiver in [scene queries](crate::scene::Scene::tagged)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The label of this receiver in the segmentation [AOV](crate::sensor::aov::Aov), `0` being
+    /// reserved for the background
+    #[serde(default)]
+    pub label: u32,
 }
 
 impl Receiver {
@@ -18,11 +90,69 @@ impl Receiver {
     ///
     /// # Returns
     /// * Self
-    pub fn new(geometry: Box<dyn Geometry>, bsdf: BSDF) -> Self {
-        Self { geometry, bsdf }
+    pub fn new(geometry: ReceiverGeometry, bsdf: BSDF) -> Self {
+        Self {
+            geometry,
+            bsdf,
+            material: None,
+            light_mask: all_light_channels(),
+            uv_mapping: None,
+            alpha: None,
+            matte: false,
+            name: None,
+            tags: Vec::new(),
+            label: 0,
+        }
+    }
+
+    #[inline]
+    pub fn geometry(&self) -> &ReceiverGeometry {
+        &self.geometry
+    }
+
+    /// Generates the texture coordinates of the given surface point by the
+    /// [uv mapping](Receiver::uv_mapping) of this receiver.
+    ///
+    /// # Arguments
+    /// * `point` - The surface point
+    /// * `normal` - The surface normal
+    ///
+    /// # Returns
+    /// * The texture coordinates, or `None` if no mapping is set
+    pub fn uv(&self, point: Vector3, normal: Vector3) -> Option<Vector2> {
+        let mapping = self.uv_mapping?;
+
+        Some(mapping.map(point, normal, &self.bounds()))
+    }
+
+    /// Returns whether the given intersection hits the surface, or got cut out by the
+    /// [alpha mask](Receiver::alpha).
+    ///
+    /// Fractional alphas get resolved by a threshold hashed from the intersection point, such
+    /// that [intersect](Intersectable::intersect) and [intersects](Intersectable::intersects)
+    /// agree for the same ray.
+    fn is_opaque_at(&self, alpha: &Texture<Float>, intersection: &Intersection) -> bool {
+        let uv = intersection
+            .uv
+            .or_else(|| self.uv(intersection.point, intersection.normal));
+        let alpha = alpha.evaluate(TexturePoint::new(uv, Some(intersection.point)));
+
+        alpha >= 1.0 || (alpha > 0.0 && alpha > threshold(intersection.point))
     }
 }
 
+/// Hashes the given point into a threshold in `[0, 1)`.
+fn threshold(point: Vector3) -> Float {
+    let mut h = (point.x.to_bits() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (point.y.to_bits() as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ (point.z.to_bits() as u64).wrapping_mul(0x1656_67b1_9e37_79f9);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+
+    (h >> 40) as Float / (1u64 << 24) as Float
+}
+
 impl Boundable for Receiver {
     fn bounds(&self) -> Aabb {
         self.geometry.bounds()
@@ -31,10 +161,81 @@ impl Boundable for Receiver {
 
 impl Intersectable for Receiver {
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
-        self.geometry.intersect(ray)
+        let alpha = match &self.alpha {
+            Some(alpha) => alpha,
+            None => return self.geometry.intersect(ray),
+        };
+
+        // continue behind cut out points
+        let mut ray = *ray;
+        for _ in 0..MAX_CUTOUTS {
+            let intersection = self.geometry.intersect(&ray)?;
+            if self.is_opaque_at(alpha, &intersection) {
+                return Some(intersection);
+            }
+
+            ray.t_start = intersection.t + Float::big_epsilon();
+            if ray.t_start >= ray.t_end {
+                return None;
+            }
+        }
+
+        None
     }
 
     fn intersects(&self, ray: &Ray) -> bool {
-        self.geometry.intersects(ray)
+        if self.alpha.is_some() {
+            self.intersect(ray).is_some()
+        } else {
+            self.geometry.intersects(ray)
+        }
     }
 }
+
+#[test]
+fn alpha_masks_cut_out_hits() {
+    use crate::texture::Texture;
+    use geometry::Sphere;
+
+    let mut receiver = Receiver::new(
+        receiver_geometry(Sphere::new(Vector3::zero(), 1.0)),
+        BSDF::empty(),
+    );
+    receiver.uv_mapping = Some(UvMapping::Planar);
+    // opaque for `z < 0`, cut out for `z > 0` (at `x < 0`)
+    receiver.alpha = Some(Texture::Checkerboard {
+        even: 1.0,
+        odd: 0.0,
+        scale: 2.0,
+    });
+
+    let entry = (1.0 - 0.25 as Float).sqrt();
+
+    // hits the opaque front
+    let ray = Ray::new_fast(Vector3::new(-0.5, 0.0, -5.0), Vector3::unit_z());
+    let hit = receiver.intersect(&ray).unwrap();
+    assert!((hit.point.z + entry).abs() < 1e-4);
+    assert!(receiver.intersects(&ray));
+
+    // skips the cut out front, hitting the opaque back
+    let ray = Ray::new_fast(Vector3::new(-0.5, 0.0, 5.0), -Vector3::unit_z());
+    let hit = receiver.intersect(&ray).unwrap();
+    assert!((hit.point.z + entry).abs() < 1e-4);
+    assert!((hit.t - (5.0 + entry)).abs() < 1e-4);
+    assert!(receiver.intersects(&ray));
+
+    // nothing left behind the cut out back
+    let mut ray = Ray::new_fast(Vector3::new(-0.5, 0.0, 0.0), Vector3::unit_z());
+    ray.t_start = 0.1;
+    assert!(receiver.intersect(&ray).is_none());
+    assert!(!receiver.intersects(&ray));
+
+    receiver.alpha = Some(Texture::Constant(0.0));
+    let ray = Ray::new_fast(Vector3::new(0.0, 0.0, -5.0), Vector3::unit_z());
+    assert!(receiver.intersect(&ray).is_none());
+    assert!(!receiver.intersects(&ray));
+
+    receiver.alpha = None;
+    assert!(receiver.intersect(&ray).is_some());
+    assert!(receiver.intersects(&ray));
+}
@@ -0,0 +1,196 @@
+use crate::debug_utils::{is_finite, within_01};
+use crate::objects::emitter::{Sampleable, SurfaceSample};
+use crate::objects::sampleables::area_to_solid_angle;
+use crate::*;
+
+use geometry::{Aabb, Intersectable, Ray};
+
+/// A face of a box, given by the axis of its normal and whether it lies on the `max` side.
+#[derive(Copy, Clone)]
+struct Face {
+    axis: usize,
+    positive: bool,
+}
+
+impl Face {
+    const ALL: [Face; 6] = [
+        Face::new(0, false),
+        Face::new(0, true),
+        Face::new(1, false),
+        Face::new(1, true),
+        Face::new(2, false),
+        Face::new(2, true),
+    ];
+
+    const fn new(axis: usize, positive: bool) -> Self {
+        Self { axis, positive }
+    }
+
+    /// Returns the area of this face of the given box.
+    fn area(&self, aabb: &Aabb) -> Float {
+        let size: [Float; 3] = aabb.size().into();
+
+        size[(self.axis + 1) % 3] * size[(self.axis + 2) % 3]
+    }
+
+    /// Returns the outward normal of this face.
+    fn normal(&self) -> Vector3 {
+        let mut normal = [0.0; 3];
+        normal[self.axis] = if self.positive { 1.0 } else { -1.0 };
+
+        normal.into()
+    }
+
+    /// Returns whether this face of the given box faces the given point.
+    fn is_facing(&self, aabb: &Aabb, point: Vector3) -> bool {
+        let (point, min, max): ([Float; 3], [Float; 3], [Float; 3]) =
+            (point.into(), aabb.min.into(), aabb.max.into());
+
+        if self.positive {
+            point[self.axis] > max[self.axis]
+        } else {
+            point[self.axis] < min[self.axis]
+        }
+    }
+
+    /// Samples a point on this face of the given box uniformly.
+    fn sample(&self, aabb: &Aabb, sample: Vector2) -> Vector3 {
+        let (min, max): ([Float; 3], [Float; 3]) = (aabb.min.into(), aabb.max.into());
+        let (u, v) = ((self.axis + 1) % 3, (self.axis + 2) % 3);
+
+        let mut point = [0.0; 3];
+        point[self.axis] = if self.positive {
+            max[self.axis]
+        } else {
+            min[self.axis]
+        };
+        point[u] = min[u] + sample.x * (max[u] - min[u]);
+        point[v] = min[v] + sample.y * (max[v] - min[v]);
+
+        point.into()
+    }
+}
+
+/// Returns the faces of the box visible from the given point with their total area.
+/// From inside the box, all faces are visible.
+fn visible_faces(aabb: &Aabb, origin: Vector3) -> ([Face; 6], usize, Float) {
+    let mut faces = Face::ALL;
+    let mut count = 0;
+    let mut area = 0.0;
+
+    for face in &Face::ALL {
+        if face.is_facing(aabb, origin) && face.area(aabb) > 0.0 {
+            faces[count] = *face;
+            count += 1;
+            area += face.area(aabb);
+        }
+    }
+
+    if count == 0 {
+        // inside the box
+        return (Face::ALL, 6, aabb.surface_area());
+    }
+
+    (faces, count, area)
+}
+
+/// Selects one of the given faces proportionally to its area, reusing the first dimension of
+/// the sample.
+///
+/// # Arguments
+/// * `aabb` - The box
+/// * `faces` - The faces to select from
+/// * `area` - The total area of the faces
+/// * `sample` - A random sample
+///
+/// # Returns
+/// * The selected face
+/// * The sample, rescaled to sample the face
+fn select_face(aabb: &Aabb, faces: &[Face], area: Float, sample: Vector2) -> (Face, Vector2) {
+    let mut target = sample.x * area;
+
+    for face in faces {
+        let face_area = face.area(aabb);
+
+        if target < face_area {
+            return (*face, Vector2::new(target / face_area, sample.y));
+        }
+        target -= face_area;
+    }
+
+    // rounding errors of the sum
+    (faces[faces.len() - 1], Vector2::new(1.0, sample.y))
+}
+
+/// Boxes get sampled uniformly by area on their faces visible from the origin, e.g. the flat
+/// panel lights of Cornell boxes.
+#[typetag::serde]
+impl Sampleable for Aabb {
+    fn surface_area(&self) -> Float {
+        Aabb::surface_area(self)
+    }
+
+    fn kind(&self) -> &'static str {
+        "Aabb"
+    }
+
+    fn sample_surface(&self, origin: Vector3, sample: Vector2) -> SurfaceSample {
+        debug_assert!(is_finite(origin));
+        debug_assert!(within_01(sample));
+
+        let (faces, count, area) = visible_faces(self, origin);
+        let (face, sample) = select_face(self, &faces[..count], area, sample);
+
+        let point = face.sample(self, sample);
+        let normal = face.normal();
+
+        SurfaceSample::new(
+            point,
+            normal,
+            area_to_solid_angle(area, origin, point, normal),
+        )
+    }
+
+    fn sample_area(&self, sample: Vector2) -> SurfaceSample {
+        debug_assert!(within_01(sample));
+
+        let area = Aabb::surface_area(self);
+        let (face, sample) = select_face(self, &Face::ALL, area, sample);
+
+        SurfaceSample::new(face.sample(self, sample), face.normal(), 1.0 / area)
+    }
+
+    fn pdf(&self, origin: Vector3, incident: Vector3) -> Float {
+        debug_assert!(is_finite(origin));
+        debug_assert!(is_finite(incident));
+
+        match self.intersect(&Ray::new_fast(origin, incident)) {
+            Some(i) => {
+                let (_, _, area) = visible_faces(self, origin);
+
+                area_to_solid_angle(area, origin, i.point, i.normal)
+            }
+            None => 0.0,
+        }
+    }
+}
+
+#[test]
+fn sampled_pdf_matches_pdf() {
+    let panel = Aabb::new(Vector3::new(-0.5, 1.9, -0.5), Vector3::new(0.5, 2.0, 0.5));
+    let origin = Vector3::new(0.3, 0.0, -0.2);
+
+    for &(x, y) in &[(0.1, 0.2), (0.5, 0.5), (0.9, 0.7), (0.999, 0.1)] {
+        let sample = panel.sample_surface(origin, Vector2::new(x, y));
+        let incident = (sample.point - origin).normalized();
+        let pdf = Sampleable::pdf(&panel, origin, incident);
+
+        assert!(sample.pdf > 0.0);
+        assert!(
+            (sample.pdf - pdf).abs() < 1e-3 * pdf,
+            "{} != {}",
+            sample.pdf,
+            pdf
+        );
+    }
+}
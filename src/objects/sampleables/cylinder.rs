@@ -0,0 +1,95 @@
+use crate::debug_utils::{is_finite, within_01};
+use crate::objects::emitter::{Sampleable, SurfaceSample};
+use crate::objects::sampleables::area_to_solid_angle;
+use crate::*;
+
+use geometry::{CoordinateSystem, Cylinder, Intersectable, Ray};
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::TAU;
+#[cfg(feature = "f64")]
+use std::f64::consts::TAU;
+
+/// Cylinders are open tubes emitting on both sides, e.g. of fluorescent lamps.
+#[typetag::serde]
+impl Sampleable for Cylinder {
+    fn surface_area(&self) -> Float {
+        TAU * self.radius() * self.height()
+    }
+
+    fn kind(&self) -> &'static str {
+        "Cylinder"
+    }
+
+    /// Samples the tube uniformly by area, with the normal facing the origin.
+    /// Points on the far side of the tube get occluded by its near side.
+    fn sample_surface(&self, origin: Vector3, sample: Vector2) -> SurfaceSample {
+        debug_assert!(is_finite(origin));
+        debug_assert!(within_01(sample));
+
+        let mut surface_sample = self.sample_area(sample);
+        if surface_sample.normal.dot(origin - surface_sample.point) < 0.0 {
+            surface_sample.normal = -surface_sample.normal;
+        }
+
+        surface_sample.pdf = area_to_solid_angle(
+            self.surface_area(),
+            origin,
+            surface_sample.point,
+            surface_sample.normal,
+        );
+
+        surface_sample
+    }
+
+    fn sample_area(&self, sample: Vector2) -> SurfaceSample {
+        debug_assert!(within_01(sample));
+
+        let axis = self.axis();
+        let frame = CoordinateSystem::from_y(axis);
+        let (sin_phi, cos_phi) = Float::sin_cos(sample.y * TAU);
+
+        let normal = cos_phi * frame.x_axis + sin_phi * frame.z_axis;
+        let height = (sample.x - 0.5) * self.height();
+        let point = self.center() + height * axis + self.radius() * normal;
+
+        SurfaceSample::new(point, normal, 1.0 / self.surface_area())
+    }
+
+    /// Returns the pdf of the first intersection of the tube, as further ones are occluded.
+    fn pdf(&self, origin: Vector3, incident: Vector3) -> Float {
+        debug_assert!(is_finite(origin));
+        debug_assert!(is_finite(incident));
+
+        match self.intersect(&Ray::new_fast(origin, incident)) {
+            Some(i) => area_to_solid_angle(self.surface_area(), origin, i.point, i.normal),
+            None => 0.0,
+        }
+    }
+}
+
+#[test]
+fn sampled_pdf_matches_pdf() {
+    let tube = Cylinder::new(
+        (Vector3::new(-1.0, 2.0, 0.0), Vector3::new(1.0, 2.0, 0.0)),
+        0.1,
+    );
+    let origin = Vector3::new(0.3, 0.0, -0.2);
+    let mut visible = 0;
+
+    for x in &[0.1, 0.5, 0.9] {
+        for y in &[0.0, 0.2, 0.4, 0.6, 0.8] {
+            let sample = tube.sample_surface(origin, Vector2::new(*x, *y));
+            let incident = (sample.point - origin).normalized();
+
+            // points on the far side are occluded by the near side
+            let hit = tube.intersect(&Ray::new_fast(origin, incident)).unwrap();
+            if (hit.point - sample.point).mag() < 1e-3 {
+                let pdf = Sampleable::pdf(&tube, origin, incident);
+                assert!((sample.pdf - pdf).abs() < 1e-2 * pdf);
+                visible += 1;
+            }
+        }
+    }
+
+    assert!(visible > 0);
+}
@@ -0,0 +1,63 @@
+use crate::debug_utils::{is_finite, within_01};
+use crate::mc::sample_unit_disk_concentric;
+use crate::objects::emitter::{Sampleable, SurfaceSample};
+use crate::objects::sampleables::area_to_solid_angle;
+use crate::*;
+
+use geometry::{CoordinateSystem, Disk, Intersectable, Ray};
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::PI;
+#[cfg(feature = "f64")]
+use std::f64::consts::PI;
+
+/// Disks emit on both sides, like their intersections do.
+#[typetag::serde]
+impl Sampleable for Disk {
+    fn surface_area(&self) -> Float {
+        PI * self.radius * self.radius
+    }
+
+    fn kind(&self) -> &'static str {
+        "Disk"
+    }
+
+    /// Samples the disk uniformly by area, with the normal facing the origin.
+    fn sample_surface(&self, origin: Vector3, sample: Vector2) -> SurfaceSample {
+        debug_assert!(is_finite(origin));
+        debug_assert!(within_01(sample));
+
+        let mut surface_sample = self.sample_area(sample);
+        if surface_sample.normal.dot(origin - surface_sample.point) < 0.0 {
+            surface_sample.normal = -surface_sample.normal;
+        }
+
+        surface_sample.pdf = area_to_solid_angle(
+            self.surface_area(),
+            origin,
+            surface_sample.point,
+            surface_sample.normal,
+        );
+
+        surface_sample
+    }
+
+    fn sample_area(&self, sample: Vector2) -> SurfaceSample {
+        debug_assert!(within_01(sample));
+
+        let frame = CoordinateSystem::from_y(self.normal);
+        let offset = self.radius * sample_unit_disk_concentric(sample);
+        let point = self.center + offset.x * frame.x_axis + offset.y * frame.z_axis;
+
+        SurfaceSample::new(point, self.normal, 1.0 / self.surface_area())
+    }
+
+    fn pdf(&self, origin: Vector3, incident: Vector3) -> Float {
+        debug_assert!(is_finite(origin));
+        debug_assert!(is_finite(incident));
+
+        match self.intersect(&Ray::new_fast(origin, incident)) {
+            Some(i) => area_to_solid_angle(self.surface_area(), origin, i.point, i.normal),
+            None => 0.0,
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::debug_utils::{is_finite, within_01};
+use crate::objects::emitter::{Sampleable, SurfaceSample};
+use crate::objects::sampleables::area_to_solid_angle;
+use crate::*;
+
+use geometry::{Intersectable, Mesh, Ray, ShadingMode};
+
+/// Samples a point on the mesh uniformly by area: a face proportionally to its area, then a point
+/// on the face uniformly.
+///
+/// Like the intersections, the normals follow the shading mode. Meshes therefore emit on the side
+/// their faces are wound towards, e.g. outwards of light bulbs loaded from obj files.
+///
+/// # Returns
+/// * The point and its normal
+fn sample_point(mesh: &Mesh, sample: Vector2) -> (Vector3, Vector3) {
+    let (face, u) = mesh.sample_face(sample.x);
+    let (v0, v1, v2) = face.get_vertices(mesh.vertices());
+
+    // uniform barycentric coordinates
+    let su = u.sqrt();
+    let (beta, gamma) = (su * (1.0 - sample.y), su * sample.y);
+    let alpha = 1.0 - beta - gamma;
+
+    let point = alpha * v0.position + beta * v1.position + gamma * v2.position;
+    let normal = match mesh.shading_mode() {
+        ShadingMode::Flat => face.geometric_normal(mesh.vertices()),
+        ShadingMode::Phong => {
+            (alpha * v0.normal + beta * v1.normal + gamma * v2.normal).normalized()
+        }
+    };
+
+    (point, normal)
+}
+
+/// Meshes get sampled uniformly by area over all faces, allowing arbitrary light shapes like neon
+/// signs.
+#[typetag::serde]
+impl Sampleable for Mesh {
+    fn surface_area(&self) -> Float {
+        Mesh::surface_area(self)
+    }
+
+    fn kind(&self) -> &'static str {
+        "Mesh"
+    }
+
+    fn sample_surface(&self, origin: Vector3, sample: Vector2) -> SurfaceSample {
+        debug_assert!(is_finite(origin));
+        debug_assert!(within_01(sample));
+
+        let (point, normal) = sample_point(self, sample);
+        let pdf = area_to_solid_angle(Mesh::surface_area(self), origin, point, normal);
+
+        SurfaceSample::new(point, normal, pdf)
+    }
+
+    fn sample_area(&self, sample: Vector2) -> SurfaceSample {
+        debug_assert!(within_01(sample));
+
+        let (point, normal) = sample_point(self, sample);
+
+        SurfaceSample::new(point, normal, 1.0 / Mesh::surface_area(self))
+    }
+
+    fn pdf(&self, origin: Vector3, incident: Vector3) -> Float {
+        debug_assert!(is_finite(origin));
+        debug_assert!(is_finite(incident));
+
+        match self.intersect(&Ray::new_fast(origin, incident)) {
+            Some(i) => area_to_solid_angle(Mesh::surface_area(self), origin, i.point, i.normal),
+            None => 0.0,
+        }
+    }
+}
+
+#[test]
+fn sampled_pdf_matches_pdf() {
+    use geometry::{Aabb, Face, Vertex};
+
+    let vertex = |x: Float, z: Float| Vertex {
+        position: Vector3::new(x, 2.0, z),
+        normal: -Vector3::unit_y(),
+        uv: Vector2::zero(),
+    };
+    // a panel of two differently sized triangles, facing down
+    let vertices = vec![
+        vertex(-0.5, -0.5),
+        vertex(0.5, -0.5),
+        vertex(0.5, 0.5),
+        vertex(-0.5, 1.5),
+    ];
+    let faces = vec![
+        Face::new((0, 1, 2), -Vector3::unit_y()),
+        Face::new((0, 2, 3), -Vector3::unit_y()),
+    ];
+
+    let mut panel = Mesh::new(vertices, faces, Aabb::empty(), ShadingMode::Flat, None);
+    panel.update_bounds().build_bvh();
+    assert!((Mesh::surface_area(&panel) - 1.5).abs() < 1e-5);
+
+    let origin = Vector3::new(0.3, 0.0, -0.2);
+    // strictly inside the faces, away from the shared edge
+    for &(x, y) in &[(0.1, 0.4), (0.25, 0.3), (0.6, 0.5), (0.85, 0.7)] {
+        let sample = panel.sample_surface(origin, Vector2::new(x, y));
+        let incident = (sample.point - origin).normalized();
+        let pdf = Sampleable::pdf(&panel, origin, incident);
+
+        assert!(sample.normal.dot(origin - sample.point) > 0.0);
+        assert!(sample.pdf > 0.0);
+        assert!(
+            (sample.pdf - pdf).abs() < 1e-3 * pdf,
+            "{} != {}",
+            sample.pdf,
+            pdf
+        );
+
+        let area_sample = panel.sample_area(Vector2::new(x, y));
+        assert_eq!(area_sample.pdf, 1.0 / Mesh::surface_area(&panel));
+    }
+}
@@ -1,10 +1,40 @@
-use crate::objects::SurfaceSample;
-use crate::*;
+use crate::objects::Sampleable;
+use crate::{Float, Vector3};
 
 use geometry::{Aabb, Boundable, Geometry, Intersectable, Intersection, Ray};
 
+mod aabb;
+mod cylinder;
+mod disk;
+mod mesh;
 mod point;
 mod sphere;
+mod transformed;
+
+pub use transformed::Transformed;
+
+/// Converts the pdf of sampling a surface point uniformly by the given area into the
+/// "solid angle" form.
+///
+/// # Arguments
+/// * `area` - The sampled area
+/// * `origin` - The point at which we look at the surface
+/// * `point` - The sampled surface point
+/// * `normal` - The surface normal
+///
+/// # Returns
+/// * The pdf (`0` for degenerate configurations)
+fn area_to_solid_angle(area: Float, origin: Vector3, point: Vector3, normal: Vector3) -> Float {
+    let to_origin = origin - point;
+    let dist_sq = to_origin.mag_sq();
+    let cos = normal.dot(to_origin).abs() / dist_sq.sqrt();
+
+    if dist_sq == 0.0 || cos == 0.0 || area == 0.0 {
+        0.0
+    } else {
+        dist_sq / (cos * area)
+    }
+}
 
 impl Boundable for Box<dyn Sampleable> {
     fn bounds(&self) -> Aabb {
@@ -23,28 +53,8 @@ impl Intersectable for Box<dyn Sampleable> {
 }
 
 #[typetag::serde]
-impl Geometry for Box<dyn Sampleable> {}
-
-/// Allows geometries to be sampled for a surface point.
-#[typetag::serde]
-pub trait Sampleable: Geometry + Send + Sync {
-    /// Returns the surface area of this object.
-    ///
-    /// # Returns
-    /// * The surface area
-    fn surface_area(&self) -> Float;
-
-    /// Samples the surface from the given point in the "solid angle" form.
-    ///
-    /// # Constraints
-    /// * `point` - All values should be finite (neither infinite nor `NaN`).
-    /// * `sample` - ALl values should be inside `[0, 1)`.
-    ///
-    /// # Arguments
-    /// * `point` - The point at which we look at the object
-    /// * `sample` - A random sample
-    ///
-    /// # Returns
-    /// * A surface sample
-    fn sample_surface(&self, origin: Vector3, sample: Vector2) -> SurfaceSample;
+impl Geometry for Box<dyn Sampleable> {
+    fn triangle_count(&self) -> usize {
+        self.as_ref().triangle_count()
+    }
 }
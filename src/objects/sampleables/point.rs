@@ -13,6 +13,10 @@ impl Sampleable for Point {
         0.0
     }
 
+    fn kind(&self) -> &'static str {
+        "Point"
+    }
+
     /// A point cannot be sampled plausibly.
     ///
     /// # Constraints
@@ -29,4 +33,23 @@ impl Sampleable for Point {
 
         SurfaceSample::new(self.0, normal.normalized(), 1.0)
     }
+
+    /// A point has no area to sample.
+    ///
+    /// # Arguments
+    /// * `_` - Ignored
+    ///
+    /// # Returns
+    /// * The point with an arbitrary normal and a pdf of `1`
+    fn sample_area(&self, _: Vector2) -> SurfaceSample {
+        SurfaceSample::new(self.0, Vector3::unit_y(), 1.0)
+    }
+
+    /// A point cannot be hit by sampling directions.
+    ///
+    /// # Returns
+    /// * `0.0`
+    fn pdf(&self, _: Vector3, _: Vector3) -> Float {
+        0.0
+    }
 }
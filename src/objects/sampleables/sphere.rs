@@ -4,25 +4,59 @@ use crate::objects::emitter::SurfaceSample;
 use crate::objects::Sampleable;
 use crate::*;
 
-use geometry::{spherical_to_cartesian_frame_trig, CoordinateSystem, Sphere};
+use geometry::{
+    spherical_to_cartesian_frame_trig, CoordinateSystem, Intersectable, Ray, Sphere, SphereClip,
+};
 #[cfg(not(feature = "f64"))]
 use std::f32::consts::TAU;
 #[cfg(feature = "f64")]
 use std::f64::consts::TAU;
 use utility::floats::FloatExt;
 
-fn sample_surface_inside(sphere: &Sphere, sample: Vector2) -> SurfaceSample {
+/// Converts the pdf of sampling a surface point by area into the "solid angle" form.
+fn area_to_solid_angle(sphere: &Sphere, origin: Vector3, point: Vector3, normal: Vector3) -> Float {
+    let to_origin = origin - point;
+    let dist_sq = to_origin.mag_sq();
+    let cos = normal.dot(to_origin).abs() / dist_sq.sqrt();
+
+    if dist_sq == 0.0 || cos == 0.0 {
+        0.0
+    } else {
+        dist_sq / (cos * sphere.surface_area())
+    }
+}
+
+fn sample_surface_inside(sphere: &Sphere, origin: Vector3, sample: Vector2) -> SurfaceSample {
     let normal = sample_unit_sphere(sample);
     let point = sphere.center + sphere.radius * normal;
-    let pdf = 1.0 / sphere.surface_area();
+    let pdf = area_to_solid_angle(sphere, origin, point, normal);
 
     SurfaceSample::new(point, normal, pdf)
 }
 
+/// Samples the clipped surface uniformly by area.
+fn sample_clipped(sphere: &Sphere, clip: &SphereClip, sample: Vector2) -> SurfaceSample {
+    let y = clip.y_min.lerp(clip.y_max, sample.x) / sphere.radius;
+    let sin_theta = Float::fast_max(0.0, 1.0 - y * y).sqrt();
+    let (sin_phi, cos_phi) = Float::sin_cos(sample.y * clip.phi_max);
+
+    let normal = Vector3::new(sin_theta * sin_phi, y, sin_theta * cos_phi);
+    let point = sphere.center + sphere.radius * normal;
+
+    SurfaceSample::new(point, normal, 1.0 / sphere.surface_area())
+}
+
 #[typetag::serde]
 impl Sampleable for Sphere {
     fn surface_area(&self) -> Float {
-        2.0 * TAU as Float * self.radius * self.radius
+        match self.clip {
+            Some(clip) => clip.phi_max * self.radius * (clip.y_max - clip.y_min),
+            None => 2.0 * TAU as Float * self.radius * self.radius,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        "Sphere"
     }
 
     // Copyright: https://github.com/mmp/pbrt-v3/blob/master/src/shapes/sphere.cpp
@@ -30,13 +64,22 @@ impl Sampleable for Sphere {
         debug_assert!(is_finite(origin));
         debug_assert!(within_01(sample));
 
+        if let Some(clip) = &self.clip {
+            // the cone towards the sphere may contain clipped-away parts
+            let mut surface_sample = sample_clipped(self, clip, sample);
+            surface_sample.pdf =
+                area_to_solid_angle(self, origin, surface_sample.point, surface_sample.normal);
+
+            return surface_sample;
+        }
+
         let origin_to_center = self.center - origin;
         let dist_sq = origin_to_center.mag_sq();
         let r2 = self.radius * self.radius;
 
         if dist_sq <= r2 {
             // inside the sphere (may happen)
-            sample_surface_inside(&self, sample)
+            sample_surface_inside(self, origin, sample)
         } else {
             let distance = dist_sq.sqrt();
             let axis = origin_to_center / -distance;
@@ -78,4 +121,37 @@ impl Sampleable for Sphere {
             SurfaceSample::new(point, normal, pdf)
         }
     }
+
+    fn sample_area(&self, sample: Vector2) -> SurfaceSample {
+        debug_assert!(within_01(sample));
+
+        if let Some(clip) = &self.clip {
+            return sample_clipped(self, clip, sample);
+        }
+
+        let normal = sample_unit_sphere(sample);
+        let point = self.center + self.radius * normal;
+
+        SurfaceSample::new(point, normal, 1.0 / self.surface_area())
+    }
+
+    fn pdf(&self, origin: Vector3, incident: Vector3) -> Float {
+        debug_assert!(is_finite(origin));
+        debug_assert!(is_finite(incident));
+
+        let dist_sq = (self.center - origin).mag_sq();
+        let r2 = self.radius * self.radius;
+
+        if dist_sq <= r2 || self.clip.is_some() {
+            // inside the sphere or clipped, the surface gets sampled uniformly by area
+            match self.intersect(&Ray::new_fast(origin, incident)) {
+                Some(i) => area_to_solid_angle(self, origin, i.point, i.normal),
+                None => 0.0,
+            }
+        } else {
+            let cos_theta_max = Float::fast_max(0.0, 1.0 - r2 / dist_sq).sqrt();
+
+            uniform_cone_pdf(cos_theta_max)
+        }
+    }
 }
@@ -0,0 +1,282 @@
+use crate::objects::emitter::{Sampleable, SurfaceSample};
+use crate::*;
+
+use geometry::{Aabb, Boundable, Geometry, Intersectable, Intersection, Ray};
+use serde::{Deserialize, Serialize};
+use utility::floats::FloatExt;
+
+/// A sampleable geometry placed by a similarity transform, i.e. a uniform scale, followed by a
+/// rotation and a translation.
+///
+/// As similarity transforms preserve angles, the solid angle pdfs of the transformed geometry are
+/// the same as the ones of the untransformed geometry.
+#[derive(Serialize, Deserialize)]
+#[serde(from = "TransformedSerde")]
+pub struct Transformed {
+    geometry: Box<dyn Sampleable>,
+    /// Optional uniform scaling (1st application)
+    scale: Option<Float>,
+    /// Optional rotation (2nd application)
+    /// - params: (axis, angle)
+    rotation: Option<(Vector3, Float)>,
+    /// Optional translation (3rd application)
+    translation: Option<Vector3>,
+    #[serde(skip)]
+    matrix: Matrix3,
+}
+
+/// The serialized form of a [`Transformed`](Transformed), without the rotation matrix.
+#[derive(Deserialize)]
+struct TransformedSerde {
+    geometry: Box<dyn Sampleable>,
+    #[serde(default)]
+    scale: Option<Float>,
+    #[serde(default)]
+    rotation: Option<(Vector3, Float)>,
+    #[serde(default)]
+    translation: Option<Vector3>,
+}
+
+impl Transformed {
+    /// Creates a new transformed geometry.
+    ///
+    /// # Constraints
+    /// * `scale` - Should be greater than `0`.
+    /// * `rotation` - The axis should be normalized.
+    ///
+    /// # Arguments
+    /// * `geometry` - The geometry in object space
+    /// * `scale` - The optional uniform scale
+    /// * `rotation` - The optional rotation `(axis, angle)`
+    /// * `translation` - The optional translation
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(
+        geometry: Box<dyn Sampleable>,
+        scale: Option<Float>,
+        rotation: Option<(Vector3, Float)>,
+        translation: Option<Vector3>,
+    ) -> Self {
+        debug_assert!(scale.is_none_or(|s| s > 0.0));
+
+        let matrix = match rotation {
+            Some((axis, angle)) => Matrix3::from_rotation_around(axis, angle),
+            None => Matrix3::identity(),
+        };
+
+        Self {
+            geometry,
+            scale,
+            rotation,
+            translation,
+            matrix,
+        }
+    }
+
+    #[inline]
+    fn scale(&self) -> Float {
+        self.scale.unwrap_or(1.0)
+    }
+
+    #[inline]
+    fn translation(&self) -> Vector3 {
+        self.translation.unwrap_or_else(Vector3::zero)
+    }
+
+    #[inline]
+    fn point_to_world(&self, point: Vector3) -> Vector3 {
+        self.matrix * (point * self.scale()) + self.translation()
+    }
+
+    #[inline]
+    fn point_to_local(&self, point: Vector3) -> Vector3 {
+        self.direction_to_local(point - self.translation()) / self.scale()
+    }
+
+    #[inline]
+    fn direction_to_world(&self, direction: Vector3) -> Vector3 {
+        self.matrix * direction
+    }
+
+    /// The inverse of a rotation matrix is its transposed.
+    #[inline]
+    fn direction_to_local(&self, direction: Vector3) -> Vector3 {
+        self.matrix.transposed() * direction
+    }
+
+    #[inline]
+    fn ray_to_local(&self, ray: &Ray) -> Ray {
+        let origin = self.point_to_local(ray.origin);
+        let direction = self.direction_to_local(ray.direction);
+        let scale = self.scale();
+
+        Ray::new(
+            origin,
+            direction.normalized(),
+            ray.t_start / scale,
+            ray.t_end / scale,
+        )
+    }
+}
+
+impl From<TransformedSerde> for Transformed {
+    fn from(serde: TransformedSerde) -> Self {
+        Self::new(
+            serde.geometry,
+            serde.scale,
+            serde.rotation,
+            serde.translation,
+        )
+    }
+}
+
+impl Boundable for Transformed {
+    fn bounds(&self) -> Aabb {
+        let local = self.geometry.bounds();
+
+        Aabb::union_all((0..8).map(|i| {
+            let corner = Vector3::new(
+                if i & 1 == 0 { local.min.x } else { local.max.x },
+                if i & 2 == 0 { local.min.y } else { local.max.y },
+                if i & 4 == 0 { local.min.z } else { local.max.z },
+            );
+            let corner = self.point_to_world(corner);
+
+            Aabb::new(corner, corner)
+        }))
+    }
+}
+
+impl Intersectable for Transformed {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let i = self.geometry.intersect(&self.ray_to_local(ray))?;
+
+        let point = self.point_to_world(i.point);
+        let normal = self.direction_to_world(i.normal).normalized();
+        let t = (i.t * self.scale()).fast_clamp(ray.t_start, ray.t_end);
+
+        let mut intersection = Intersection::new(point, normal, t, *ray);
+        intersection.uv = i.uv;
+
+        Some(intersection)
+    }
+
+    fn intersects(&self, ray: &Ray) -> bool {
+        self.geometry.intersects(&self.ray_to_local(ray))
+    }
+}
+
+#[typetag::serde]
+impl Geometry for Transformed {
+    fn triangle_count(&self) -> usize {
+        self.geometry.triangle_count()
+    }
+}
+
+#[typetag::serde]
+impl Sampleable for Transformed {
+    fn surface_area(&self) -> Float {
+        self.geometry.surface_area() * self.scale() * self.scale()
+    }
+
+    fn kind(&self) -> &'static str {
+        "Transformed"
+    }
+
+    /// Samples the geometry in object space and transforms the sample into world space.
+    ///
+    /// # Constraints
+    /// * `origin` - All values should be finite (neither infinite nor `NaN`).
+    /// * `sample` - ALl values should be inside `[0, 1)`.
+    ///
+    /// # Arguments
+    /// * `origin` - The point at which we look at the object
+    /// * `sample` - A random sample
+    ///
+    /// # Returns
+    /// * A surface sample
+    fn sample_surface(&self, origin: Vector3, sample: Vector2) -> SurfaceSample {
+        let local = self
+            .geometry
+            .sample_surface(self.point_to_local(origin), sample);
+
+        let point = self.point_to_world(local.point);
+        let normal = self.direction_to_world(local.normal).normalized();
+
+        SurfaceSample::new(point, normal, local.pdf)
+    }
+
+    /// Samples the geometry in object space by area and transforms the sample into world space.
+    ///
+    /// # Constraints
+    /// * `sample` - ALl values should be inside `[0, 1)`.
+    ///
+    /// # Arguments
+    /// * `sample` - A random sample
+    ///
+    /// # Returns
+    /// * A surface sample with the pdf scaled by the area change
+    fn sample_area(&self, sample: Vector2) -> SurfaceSample {
+        let local = self.geometry.sample_area(sample);
+
+        let point = self.point_to_world(local.point);
+        let normal = self.direction_to_world(local.normal).normalized();
+        let scale = self.scale();
+
+        SurfaceSample::new(point, normal, local.pdf / (scale * scale))
+    }
+
+    fn pdf(&self, origin: Vector3, incident: Vector3) -> Float {
+        let incident = self.direction_to_local(incident).normalized();
+
+        self.geometry.pdf(self.point_to_local(origin), incident)
+    }
+}
+
+#[test]
+fn pdf_accounts_for_the_area_change() {
+    use geometry::Sphere;
+
+    let center = Vector3::new(3.0, 0.0, 0.0);
+    let transformed = Transformed::new(
+        Box::new(Sphere::new(Vector3::zero(), 1.0)),
+        Some(2.0),
+        Some((Vector3::unit_y(), 0.7)),
+        Some(center),
+    );
+    // the same sphere, placed directly in world space
+    let world = Sphere::new(center, 2.0);
+
+    // the scale of 2 quadruples the area
+    let area = Sampleable::surface_area(&transformed);
+    let local_area = Sampleable::surface_area(&Sphere::new(Vector3::zero(), 1.0));
+    assert!((area - 4.0 * local_area).abs() < 1e-3);
+    assert!((area - Sampleable::surface_area(&world)).abs() < 1e-3);
+
+    // outside the sphere (cone sampling) and inside it (area sampling)
+    for &origin in &[Vector3::new(3.0, 1.0, 10.0), Vector3::new(3.5, 0.5, 0.0)] {
+        for &(x, y) in &[(0.1, 0.4), (0.25, 0.8), (0.6, 0.5), (0.85, 0.2)] {
+            let sample = transformed.sample_surface(origin, Vector2::new(x, y));
+            assert!(((sample.point - center).mag() - 2.0).abs() < 1e-4);
+
+            let incident = (sample.point - origin).normalized();
+            let pdf = Sampleable::pdf(&transformed, origin, incident);
+            let expected = Sampleable::pdf(&world, origin, incident);
+
+            assert!(pdf > 0.0);
+            assert!(
+                (pdf - expected).abs() < 1e-3 * expected,
+                "{} != {}",
+                pdf,
+                expected
+            );
+            assert!(
+                (sample.pdf - pdf).abs() < 1e-3 * pdf,
+                "{} != {}",
+                sample.pdf,
+                pdf
+            );
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Packs a scene file together with all assets it references (e.g. obj files or density grids)
+//! into a single directory or `.zip` archive, allowing to share reproducible scenes.
+//!
+//! The assets get copied into the [asset directory](ASSET_DIRECTORY) next to the scene file,
+//! whose paths get rewritten accordingly.
+
+use crate::serialization::Serialization;
+use geometry::{record_assets, AssetReference};
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// The directory inside the pack containing the assets.
+pub const ASSET_DIRECTORY: &str = "assets";
+
+pub(crate) fn invalid_data<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Parses the given scene file, collecting the assets it references.
+///
+/// # Arguments
+/// * `scene` - The path of the scene file
+///
+/// # Returns
+/// * The content of the scene file
+/// * The references to the assets in loading order
+pub fn assets<P: AsRef<Path>>(scene: P) -> io::Result<(String, Vec<AssetReference>)> {
+    let scene = scene.as_ref();
+    let content = fs::read_to_string(scene)?;
+
+    let (result, references) = record_assets(|| Serialization::parse(&content, Some(scene)));
+    result.map_err(|err| invalid_data(err.to_string()))?;
+
+    Ok((content, references))
+}
+
+/// Copies the given scene file and all assets it references into the output, which is either a
+/// directory or a `.zip` archive.
+///
+/// # Arguments
+/// * `scene` - The path of the scene file
+/// * `output` - The path of the output directory or `.zip` archive
+///
+/// # Returns
+/// * The number of packed assets
+pub fn pack<P, Q>(scene: P, output: Q) -> io::Result<usize>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let scene = scene.as_ref();
+    let output = output.as_ref();
+    let (mut content, references) = assets(scene)?;
+
+    // the files to pack by their name inside the asset directory
+    let mut files: Vec<(String, PathBuf)> = Vec::new();
+
+    // the paths as written, which got replaced already
+    let mut replaced: Vec<&str> = Vec::new();
+
+    for reference in &references {
+        // e.g. obj files loaded by several parts of a model
+        if replaced.contains(&reference.path.as_str()) {
+            continue;
+        }
+        replaced.push(&reference.path);
+
+        let name = match files.iter().find(|(_, f)| *f == reference.resolved) {
+            Some((name, _)) => name.clone(),
+            None => {
+                let name = unique_name(&reference.resolved, &files);
+                files.push((name.clone(), reference.resolved.clone()));
+                name
+            }
+        };
+
+        // RON strings share the escaping of debug formatted strings
+        let written = format!("{:?}", reference.path);
+        let packed = format!("{:?}", format!("{}/{}", ASSET_DIRECTORY, name));
+
+        if !content.contains(&written) {
+            return Err(invalid_data(format!(
+                "Could not find the path {} in the scene file",
+                written
+            )));
+        }
+        content = content.replace(&written, &packed);
+    }
+
+    let scene_name = scene
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| invalid_data("The scene path has no file name"))?;
+
+    let is_zip = output
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("zip"));
+
+    if is_zip {
+        let mut zip = ZipWriter::new(File::create(output)?);
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        zip.start_file(scene_name, options).map_err(invalid_data)?;
+        zip.write_all(content.as_bytes())?;
+
+        for (name, file) in &files {
+            zip.start_file(format!("{}/{}", ASSET_DIRECTORY, name), options)
+                .map_err(invalid_data)?;
+            zip.write_all(&fs::read(file)?)?;
+        }
+
+        zip.finish().map_err(invalid_data)?;
+    } else {
+        let assets = output.join(ASSET_DIRECTORY);
+        fs::create_dir_all(&assets)?;
+
+        fs::write(output.join(scene_name), content)?;
+        for (name, file) in &files {
+            fs::copy(file, assets.join(name))?;
+        }
+    }
+
+    Ok(files.len())
+}
+
+/// Returns the file name of the given path, suffixed by a number if already taken, e.g.
+/// `dragon_1.obj`.
+fn unique_name(path: &Path, files: &[(String, PathBuf)]) -> String {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("asset");
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("obj");
+
+    let mut name = format!("{}.{}", stem, extension);
+    let mut i = 0;
+    while files.iter().any(|(n, _)| *n == name) {
+        i += 1;
+        name = format!("{}_{}.{}", stem, i, extension);
+    }
+
+    name
+}
+
+#[test]
+fn unique_names() {
+    let files = vec![
+        ("dragon.obj".to_string(), PathBuf::from("a/dragon.obj")),
+        ("dragon_1.obj".to_string(), PathBuf::from("b/dragon.obj")),
+    ];
+
+    assert_eq!("bunny.obj", unique_name(Path::new("c/bunny.obj"), &files));
+    assert_eq!(
+        "dragon_2.obj",
+        unique_name(Path::new("c/dragon.obj"), &files)
+    );
+}
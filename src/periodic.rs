@@ -0,0 +1,163 @@
+//! Periodic boundaries, tiling a cell of the scene infinitely along chosen axes, e.g. to render
+//! foams, crystals or metamaterials from a single unit cell.
+//!
+//! Instead of instancing the geometry, rays get wrapped: whenever a ray leaves the cell through
+//! a periodic face, it continues from the opposite face, while the hit points get translated
+//! back into the copy of the cell the ray actually reached.
+
+use crate::{Float, Vector3};
+use geometry::{Aabb, Ray};
+use serde::{Deserialize, Serialize};
+
+fn default_max_cells() -> u32 {
+    256
+}
+
+/// A cell of the scene repeated infinitely along its periodic axes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PeriodicCell {
+    /// The box getting repeated
+    bounds: Aabb,
+    /// Whether the cell repeats along the `x`, `y` and `z` axis respectively
+    axes: (bool, bool, bool),
+    /// The maximal number of cells a ray traverses before being treated as escaped
+    #[serde(default = "default_max_cells")]
+    max_cells: u32,
+}
+
+impl PeriodicCell {
+    /// Creates a new periodic cell.
+    ///
+    /// # Constraints
+    /// * `bounds` - Should have a positive size along the periodic axes.
+    ///
+    /// # Arguments
+    /// * `bounds` - The box getting repeated
+    /// * `axes` - Whether the cell repeats along the `x`, `y` and `z` axis respectively
+    /// * `max_cells` - The maximal number of cells a ray traverses
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(bounds: Aabb, axes: (bool, bool, bool), max_cells: u32) -> Self {
+        Self {
+            bounds,
+            axes,
+            max_cells,
+        }
+    }
+
+    fn periodic(&self) -> [bool; 3] {
+        let size = self.bounds.size();
+
+        [
+            self.axes.0 && size.x > 0.0,
+            self.axes.1 && size.y > 0.0,
+            self.axes.2 && size.z > 0.0,
+        ]
+    }
+
+    /// Traverses the cells along the given ray, calling the given function with the part of the
+    /// ray inside each cell, wrapped into the original cell, until it returns a result.
+    ///
+    /// # Arguments
+    /// * `ray` - The ray to traverse
+    /// * `f` - The function testing a wrapped ray segment
+    ///
+    /// # Returns
+    /// * The first result and the translation from the original cell to the cell of the result
+    pub fn traverse<T, F>(&self, ray: &Ray, mut f: F) -> Option<(T, Vector3)>
+    where
+        F: FnMut(&Ray) -> Option<T>,
+    {
+        let periodic = self.periodic();
+        let min = to_array(self.bounds.min);
+        let max = to_array(self.bounds.max);
+        let size = to_array(self.bounds.size());
+        let direction = to_array(ray.direction);
+
+        // wrap the origin into the original cell
+        let mut origin = to_array(ray.origin);
+        let mut offset = [0.0; 3];
+        for i in 0..3 {
+            if periodic[i] {
+                let cells = ((origin[i] - min[i]) / size[i]).floor();
+                origin[i] -= cells * size[i];
+                offset[i] += cells * size[i];
+            }
+        }
+
+        let mut t_start = ray.t_start;
+
+        for _ in 0..self.max_cells {
+            // the nearest exit through a periodic face
+            let mut t_exit = Float::INFINITY;
+            let mut exit_axis = None;
+            for i in 0..3 {
+                if !periodic[i] || direction[i] == 0.0 {
+                    continue;
+                }
+
+                let face = if direction[i] > 0.0 { max[i] } else { min[i] };
+                let t = (face - origin[i]) / direction[i];
+                if t < t_exit {
+                    t_exit = t;
+                    exit_axis = Some(i);
+                }
+            }
+
+            let t_end = ray.t_end.min(t_exit);
+            if t_start < t_end {
+                let segment = Ray::new(from_array(origin), ray.direction, t_start, t_end);
+                if let Some(result) = f(&segment) {
+                    return Some((result, from_array(offset)));
+                }
+            }
+
+            match exit_axis {
+                Some(i) if t_exit < ray.t_end => {
+                    // continue from the opposite face
+                    let shift = size[i].copysign(direction[i]);
+                    origin[i] -= shift;
+                    offset[i] += shift;
+                    t_start = t_start.max(t_exit);
+                }
+                _ => return None,
+            }
+        }
+
+        None
+    }
+}
+
+fn to_array(v: Vector3) -> [Float; 3] {
+    [v.x, v.y, v.z]
+}
+
+fn from_array(a: [Float; 3]) -> Vector3 {
+    Vector3::new(a[0], a[1], a[2])
+}
+
+#[test]
+fn wraps_rays_into_the_cell() {
+    let cell = PeriodicCell::new(
+        Aabb::new(Vector3::zero(), Vector3::one()),
+        (true, false, false),
+        16,
+    );
+    let ray = Ray::new_fast(Vector3::new(0.5, 0.5, 0.5), Vector3::unit_x());
+
+    // misses the plane x = 0.25 behind the origin, but finds it in the next cell
+    let (t, offset) = cell
+        .traverse(&ray, |r| {
+            let t = (0.25 - r.origin.x) / r.direction.x;
+            if r.contains(t) {
+                Some(t)
+            } else {
+                None
+            }
+        })
+        .unwrap();
+
+    assert!((t - 0.75).abs() < 1e-5);
+    assert_eq!(offset, Vector3::new(1.0, 0.0, 0.0));
+}
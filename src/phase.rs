@@ -0,0 +1,280 @@
+//! Phase functions describing the angular distribution of light scattering inside a
+//! [medium](crate::medium::GridMedium).
+//!
+//! All directions are directions of travel, such that forward scattering keeps the direction.
+
+use crate::{Float, Spectrum, Vector2, Vector3};
+use color::Color;
+use geometry::{spherical_to_cartesian_frame_trig, CoordinateSystem};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+
+/// Evaluates the Henyey-Greenstein phase function.
+pub(crate) fn henyey_greenstein(cos_theta: Float, g: Float) -> Float {
+    let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+
+    (1.0 - g * g) / (2.0 * TAU as Float * denominator * denominator.sqrt())
+}
+
+/// Samples the cosine of the scattering angle proportionally to the Henyey-Greenstein phase
+/// function.
+pub(crate) fn sample_henyey_greenstein(g: Float, u: Float) -> Float {
+    let cos_theta = if g.abs() < 1e-3 {
+        1.0 - 2.0 * u
+    } else {
+        let square = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+        (1.0 + g * g - square * square) / (2.0 * g)
+    };
+
+    cos_theta.clamp(-1.0, 1.0)
+}
+
+/// Evaluates the Rayleigh phase function of scattering by particles much smaller than the
+/// wavelength, e.g. air molecules.
+pub(crate) fn rayleigh(cos_theta: Float) -> Float {
+    3.0 * (1.0 + cos_theta * cos_theta) / (8.0 * TAU as Float)
+}
+
+/// Samples the cosine of the scattering angle proportionally to the Rayleigh phase function, by
+/// solving the cubic of the inverted cdf.
+pub(crate) fn sample_rayleigh(u: Float) -> Float {
+    let a = 4.0 * u - 2.0;
+    let w = (a + (a * a + 1.0).sqrt()).cbrt();
+
+    (w - 1.0 / w).clamp(-1.0, 1.0)
+}
+
+/// Returns the direction of travel after scattering by the given angle.
+///
+/// # Arguments
+/// * `direction` - The direction of travel before scattering
+/// * `cos_theta` - The cosine of the scattering angle
+/// * `u` - A random sample for the azimuth
+///
+/// # Returns
+/// * The direction of travel after scattering
+pub(crate) fn scatter(direction: Vector3, cos_theta: Float, u: Float) -> Vector3 {
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let (sin_phi, cos_phi) = (u * TAU as Float).sin_cos();
+
+    let frame = CoordinateSystem::from_y(direction);
+
+    spherical_to_cartesian_frame_trig(sin_theta, cos_theta, sin_phi, cos_phi, &frame)
+}
+
+/// Evaluates the Draine phase function, generalizing Henyey-Greenstein by `alpha`.
+fn draine(cos_theta: Float, g: Float, alpha: Float) -> Float {
+    henyey_greenstein(cos_theta, g) * (1.0 + alpha * cos_theta * cos_theta)
+        / (1.0 + alpha * (1.0 + 2.0 * g * g) / 3.0)
+}
+
+/// The parameters of the approximated Mie phase function, blending a Henyey-Greenstein and a
+/// Draine lobe.
+struct MieLobes {
+    g_hg: Float,
+    g_draine: Float,
+    alpha: Float,
+    /// The weight of the Draine lobe
+    weight: Float,
+}
+
+impl MieLobes {
+    /// Fits the lobes to water droplets of the given diameter (in µm), as proposed by Jendersie
+    /// and d'Eon (2023). The fit is valid for diameters within `[5, 50]`.
+    fn new(diameter: Float) -> Self {
+        debug_assert!((5.0..=50.0).contains(&diameter));
+
+        let d = diameter;
+
+        Self {
+            g_hg: (-0.0990567 / (d - 1.67154)).exp(),
+            g_draine: (-2.20679 / (d + 3.91029) - 0.428934).exp(),
+            alpha: (3.62489 - 8.29288 / (d + 5.52825)).exp(),
+            weight: (-0.599085 / (d - 0.641583) - 0.665888).exp(),
+        }
+    }
+
+    fn evaluate(&self, cos_theta: Float) -> Float {
+        (1.0 - self.weight) * henyey_greenstein(cos_theta, self.g_hg)
+            + self.weight * draine(cos_theta, self.g_draine, self.alpha)
+    }
+
+    /// Returns the pdf of sampling both lobes as Henyey-Greenstein.
+    fn pdf(&self, cos_theta: Float) -> Float {
+        (1.0 - self.weight) * henyey_greenstein(cos_theta, self.g_hg)
+            + self.weight * henyey_greenstein(cos_theta, self.g_draine)
+    }
+
+    fn sample(&self, u: Float) -> Float {
+        if u < 1.0 - self.weight {
+            sample_henyey_greenstein(self.g_hg, u / (1.0 - self.weight))
+        } else {
+            sample_henyey_greenstein(self.g_draine, (u - 1.0 + self.weight) / self.weight)
+        }
+    }
+}
+
+/// A phase function of a medium.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub enum PhaseFunction {
+    /// Scatters uniformly into all directions.
+    #[default]
+    Isotropic,
+    /// The Henyey-Greenstein phase function with an asymmetry per wavelength, from backward
+    /// (`-1`) over isotropic (`0`) to forward scattering (`1`). Differing asymmetries scatter
+    /// wavelengths differently, e.g. for sunset skies.
+    HenyeyGreenstein(Spectrum),
+    /// Approximated Mie scattering of water droplets with the given diameter (in µm), e.g. for
+    /// fog and clouds.
+    Mie(Float),
+}
+
+impl PhaseFunction {
+    /// Evaluates the phase function.
+    ///
+    /// # Arguments
+    /// * `direction` - The direction of travel before scattering
+    /// * `incident` - The direction of travel after scattering
+    ///
+    /// # Returns
+    /// * The phase function value of each wavelength
+    pub fn evaluate(&self, direction: Vector3, incident: Vector3) -> Spectrum {
+        let cos_theta = direction.dot(incident);
+
+        match self {
+            PhaseFunction::Isotropic => Spectrum::broadcast(henyey_greenstein(cos_theta, 0.0)),
+            PhaseFunction::HenyeyGreenstein(g) => {
+                let mut phase = *g;
+                for i in 0..Spectrum::size() {
+                    phase[i] = henyey_greenstein(cos_theta, g[i]);
+                }
+
+                phase
+            }
+            PhaseFunction::Mie(diameter) => {
+                Spectrum::broadcast(MieLobes::new(*diameter).evaluate(cos_theta))
+            }
+        }
+    }
+
+    /// Evaluates the phase function for the given wavelength index.
+    ///
+    /// # Arguments
+    /// * `direction` - The direction of travel before scattering
+    /// * `incident` - The direction of travel after scattering
+    /// * `index` - The wavelength index
+    ///
+    /// # Returns
+    /// * The phase function value
+    pub fn evaluate_wavelength(
+        &self,
+        direction: Vector3,
+        incident: Vector3,
+        index: usize,
+    ) -> Float {
+        match self {
+            PhaseFunction::HenyeyGreenstein(g) => {
+                henyey_greenstein(direction.dot(incident), g[index])
+            }
+            _ => self.evaluate(direction, incident)[0],
+        }
+    }
+
+    /// Returns the pdf of [sampling](PhaseFunction::sample) the given direction.
+    ///
+    /// # Arguments
+    /// * `direction` - The direction of travel before scattering
+    /// * `incident` - The direction of travel after scattering
+    ///
+    /// # Returns
+    /// * The pdf
+    pub fn pdf(&self, direction: Vector3, incident: Vector3) -> Float {
+        let cos_theta = direction.dot(incident);
+
+        match self {
+            PhaseFunction::Isotropic => henyey_greenstein(cos_theta, 0.0),
+            // the asymmetry of a random wavelength gets sampled
+            PhaseFunction::HenyeyGreenstein(g) => {
+                let sum: Float = (0..Spectrum::size())
+                    .map(|i| henyey_greenstein(cos_theta, g[i]))
+                    .sum();
+
+                sum / Spectrum::size() as Float
+            }
+            PhaseFunction::Mie(diameter) => MieLobes::new(*diameter).pdf(cos_theta),
+        }
+    }
+
+    /// Samples a direction after scattering.
+    ///
+    /// # Constraints
+    /// * `sample` - All values should be within `[0, 1)`.
+    ///
+    /// # Arguments
+    /// * `direction` - The direction of travel before scattering
+    /// * `sample` - A random sample
+    ///
+    /// # Returns
+    /// * The direction of travel after scattering
+    /// * The phase function divided by the pdf, being `1` for wavelength independent
+    ///   Henyey-Greenstein phase functions
+    pub fn sample(&self, direction: Vector3, sample: Vector2) -> (Vector3, Spectrum) {
+        let cos_theta = match self {
+            PhaseFunction::Isotropic => sample_henyey_greenstein(0.0, sample.x),
+            PhaseFunction::HenyeyGreenstein(g) => {
+                let scaled = sample.x * Spectrum::size() as Float;
+                let index = (scaled as usize).min(Spectrum::size() - 1);
+
+                sample_henyey_greenstein(g[index], scaled - index as Float)
+            }
+            PhaseFunction::Mie(diameter) => MieLobes::new(*diameter).sample(sample.x),
+        };
+
+        let incident = scatter(direction, cos_theta, sample.y);
+
+        let weight = match self {
+            PhaseFunction::Isotropic => Spectrum::broadcast(1.0),
+            _ => {
+                let pdf = self.pdf(direction, incident);
+                if pdf > 0.0 {
+                    self.evaluate(direction, incident) / pdf
+                } else {
+                    Spectrum::broadcast(0.0)
+                }
+            }
+        };
+
+        (incident, weight)
+    }
+}
+
+#[test]
+fn mie_integrates_to_one() {
+    let phase = PhaseFunction::Mie(5.0);
+
+    // midpoint rule over the cosine, the azimuth contributing `2π`
+    let n = 200_000;
+    let d_cos = 2.0 / n as Float;
+    let integral: f64 = (0..n)
+        .map(|i| -1.0 + (i as Float + 0.5) * d_cos)
+        .map(|cos| {
+            let sin = (1.0 - cos * cos).sqrt();
+            let incident = Vector3::new(sin, cos, 0.0);
+            let value = phase.evaluate(Vector3::unit_y(), incident)[0];
+
+            (value * d_cos) as f64 * TAU
+        })
+        .sum();
+
+    assert!((integral - 1.0).abs() < 1e-2);
+}
+
+#[test]
+fn rayleigh_sampling_inverts_cdf() {
+    for &u in &[0.0, 0.1, 0.25, 0.5, 0.75, 0.9, 1.0] {
+        let cos = sample_rayleigh(u);
+        let cdf = (cos * cos * cos + 3.0 * cos + 4.0) / 8.0;
+
+        assert!((cdf - u).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::postprocessing::Frame;
+use crate::{Float, Spectrum};
+use color::Color;
+
+/// The number of gaussians (with doubling widths) summed up to approximate the glare kernel.
+const GLARE_OCTAVES: u32 = 3;
+
+/// Applies bloom/glare to the bright parts of the frame.
+///
+/// The intensity above `threshold` gets extracted and blurred with a sum of separable gaussians
+/// of increasing width, approximating the long-tailed glare kernel of the eye or a lens.
+/// The result gets scaled by `intensity` and added back to the frame.
+///
+/// # Constraints
+/// * `threshold` - Should be non-negative.
+/// * `radius` - Should be positive.
+///
+/// # Arguments
+/// * `frame` - The frame to process
+/// * `threshold` - The luminance above which pixels bloom
+/// * `intensity` - The strength of the added bloom
+/// * `radius` - The standard deviation (in pixels) of the narrowest gaussian
+pub fn bloom(frame: &mut Frame, threshold: Float, intensity: Float, radius: Float) {
+    debug_assert!(threshold >= 0.0);
+    debug_assert!(radius > 0.0);
+
+    let bright: Vec<Spectrum> = frame
+        .pixels
+        .iter()
+        .map(|s| {
+            let luminance = s.luminance();
+            if luminance > threshold {
+                *s * ((luminance - threshold) / luminance)
+            } else {
+                Spectrum::broadcast(0.0)
+            }
+        })
+        .collect();
+
+    if bright.iter().all(|s| s.is_black()) {
+        return;
+    }
+
+    let bright = Frame::new(frame.width, frame.height, bright);
+    let mut sigma = radius;
+    let mut weight = 1.0;
+    let mut weight_sum = 0.0;
+    let mut glare = vec![Spectrum::broadcast(0.0); frame.pixels.len()];
+
+    for _ in 0..GLARE_OCTAVES {
+        let kernel = gaussian_kernel(sigma);
+        let blurred = blur_vertical(&blur_horizontal(&bright, &kernel), &kernel);
+
+        glare
+            .iter_mut()
+            .zip(blurred.pixels.iter())
+            .for_each(|(g, b)| *g += *b * weight);
+
+        weight_sum += weight;
+        sigma *= 2.0;
+        weight /= 2.0;
+    }
+
+    let scale = intensity / weight_sum;
+    frame
+        .pixels
+        .iter_mut()
+        .zip(glare.iter())
+        .for_each(|(p, g)| *p += *g * scale);
+}
+
+/// Creates a normalized, discrete gaussian kernel spanning 3 standard deviations.
+fn gaussian_kernel(sigma: Float) -> Vec<Float> {
+    let half = (3.0 * sigma).ceil() as i64;
+    let mut kernel: Vec<Float> = (-half..=half)
+        .map(|i| {
+            let x = i as Float / sigma;
+            (-0.5 * x * x).exp()
+        })
+        .collect();
+
+    let sum: Float = kernel.iter().sum();
+    kernel.iter_mut().for_each(|k| *k /= sum);
+
+    kernel
+}
+
+fn blur_horizontal(frame: &Frame, kernel: &[Float]) -> Frame {
+    let half = (kernel.len() / 2) as i64;
+    let max = frame.width as i64 - 1;
+    let mut pixels = Vec::with_capacity(frame.pixels.len());
+
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            let mut sum = Spectrum::broadcast(0.0);
+            for (k, weight) in kernel.iter().enumerate() {
+                let sx = (x as i64 + k as i64 - half).max(0).min(max);
+                sum += *frame.get(sx as u32, y) * *weight;
+            }
+
+            pixels.push(sum);
+        }
+    }
+
+    Frame::new(frame.width, frame.height, pixels)
+}
+
+fn blur_vertical(frame: &Frame, kernel: &[Float]) -> Frame {
+    let half = (kernel.len() / 2) as i64;
+    let max = frame.height as i64 - 1;
+    let mut pixels = Vec::with_capacity(frame.pixels.len());
+
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            let mut sum = Spectrum::broadcast(0.0);
+            for (k, weight) in kernel.iter().enumerate() {
+                let sy = (y as i64 + k as i64 - half).max(0).min(max);
+                sum += *frame.get(x, sy as u32) * *weight;
+            }
+
+            pixels.push(sum);
+        }
+    }
+
+    Frame::new(frame.width, frame.height, pixels)
+}
+
+#[test]
+fn bloom_spreads_the_excess_energy() {
+    for &sigma in &[0.5, 1.0, 2.5] {
+        let kernel = gaussian_kernel(sigma);
+        assert_eq!(kernel.len() % 2, 1);
+        assert!((kernel.iter().sum::<Float>() - 1.0).abs() < 1e-5);
+        assert_eq!(kernel.first(), kernel.last());
+    }
+
+    let size = 41;
+    let center = size / 2;
+    let mut frame = Frame::new(
+        size,
+        size,
+        vec![Spectrum::broadcast(0.1); (size * size) as usize],
+    );
+    let dim = frame.clone();
+
+    // nothing exceeds the threshold
+    bloom(&mut frame, 1.0, 0.5, 1.0);
+    assert!(frame.pixels == dim.pixels);
+
+    let bright = Spectrum::broadcast(10.0);
+    *frame.get_mut(center, center) = bright;
+    let before: Float = frame.pixels.iter().map(|s| s.luminance()).sum();
+    bloom(&mut frame, 1.0, 0.5, 1.0);
+    let after: Float = frame.pixels.iter().map(|s| s.luminance()).sum();
+
+    // the kernel of the widest octave stays inside the frame
+    let excess = bright.luminance() - 1.0;
+    assert!((after - before - 0.5 * excess).abs() < 1e-3 * excess);
+
+    let near = frame.get(center + 1, center)[0];
+    let far = frame.get(center + 6, center)[0];
+    assert!(near > far && far > 0.1);
+    assert!((near - frame.get(center, center - 1)[0]).abs() < 1e-6);
+}
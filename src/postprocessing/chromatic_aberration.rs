@@ -0,0 +1,46 @@
+use crate::postprocessing::Frame;
+use crate::{Float, Spectrum};
+use color::color_data::{LAMBDA_END, LAMBDA_RANGE, LAMBDA_START};
+
+/// Applies lateral chromatic aberration.
+///
+/// Each wavelength gets magnified radially around the frame center, linearly depending on its
+/// distance to the center of the visible range. Longer wavelengths get magnified more.
+///
+/// # Arguments
+/// * `frame` - The frame to process
+/// * `strength` - The relative magnification difference between the shortest and longest
+///   wavelength
+pub fn chromatic_aberration(frame: &mut Frame, strength: Float) {
+    if strength == 0.0 {
+        return;
+    }
+
+    let (cx, cy) = frame.center();
+    let lambda_center = (LAMBDA_START + LAMBDA_END) / 2.0;
+
+    let mut pixels = Vec::with_capacity(frame.pixels.len());
+
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            let dx = x as Float + 0.5 - cx;
+            let dy = y as Float + 0.5 - cy;
+
+            let mut spectrum = Spectrum::default();
+            for i in 0..Spectrum::size() {
+                let lambda = Spectrum::lambda_of_index(i);
+                let magnification = 1.0 + strength * (lambda - lambda_center) / LAMBDA_RANGE;
+
+                // inverse mapping: look up where this wavelength came from
+                let sx = cx + dx / magnification;
+                let sy = cy + dy / magnification;
+
+                spectrum[i] = frame.sample_bilinear(sx, sy, i);
+            }
+
+            pixels.push(spectrum);
+        }
+    }
+
+    frame.pixels = pixels;
+}
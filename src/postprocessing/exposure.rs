@@ -0,0 +1,83 @@
+use crate::postprocessing::Frame;
+use crate::{Float, Spectrum};
+use color::Color;
+use serde::{Deserialize, Serialize};
+
+/// Avoids the logarithm of black pixels.
+const LUMINANCE_EPSILON: Float = 1e-4;
+
+/// The exposure of the LDR images, scaling the HDR image before its conversion.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Exposure {
+    /// A fixed scale, e.g. to override the automatic exposure.
+    Manual(Float),
+    /// Scales the log-average luminance of the image to the given key value, `0.18` mapping it
+    /// to a middle grey.
+    Auto { key: Float },
+}
+
+impl Exposure {
+    /// Computes the scale of the given frame.
+    ///
+    /// # Arguments
+    /// * `frame` - The HDR frame
+    ///
+    /// # Returns
+    /// * The scale
+    pub fn scale(&self, frame: &Frame) -> Float {
+        match *self {
+            Exposure::Manual(scale) => scale,
+            Exposure::Auto { key } => {
+                let average = log_average_luminance(frame);
+
+                if average > LUMINANCE_EPSILON {
+                    key / average
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+
+    /// Applies this exposure to the given frame.
+    ///
+    /// # Arguments
+    /// * `frame` - The frame to scale
+    pub fn apply(&self, frame: &mut Frame) {
+        let scale = self.scale(frame);
+
+        if scale != 1.0 {
+            frame.pixels.iter_mut().for_each(|px| *px *= scale);
+        }
+    }
+}
+
+/// Computes the log-average (geometric mean) luminance of the given frame, being less sensitive
+/// to few very bright pixels than the arithmetic mean.
+///
+/// # Arguments
+/// * `frame` - The frame
+///
+/// # Returns
+/// * The log-average luminance
+pub fn log_average_luminance(frame: &Frame) -> Float {
+    if frame.pixels.is_empty() {
+        return 0.0;
+    }
+
+    let sum: f64 = frame
+        .pixels
+        .iter()
+        .map(|px: &Spectrum| (LUMINANCE_EPSILON + px.luminance().max(0.0)).ln() as f64)
+        .sum();
+
+    (sum / frame.pixels.len() as f64).exp() as Float
+}
+
+#[test]
+fn auto_exposure_maps_uniform_frame_to_key() {
+    let mut frame = Frame::new(4, 4, vec![Spectrum::broadcast(5.0); 16]);
+    Exposure::Auto { key: 0.18 }.apply(&mut frame);
+
+    assert!((frame.pixels[0].luminance() - 0.18).abs() < 1e-3);
+}
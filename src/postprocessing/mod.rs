@@ -0,0 +1,204 @@
+//! Post-processing effects applied to the HDR sensor data before the conversion to LDR images.
+
+mod bloom;
+mod chromatic_aberration;
+mod exposure;
+mod sensor_bloom;
+mod sensor_noise;
+mod vignetting;
+
+pub use bloom::*;
+pub use chromatic_aberration::*;
+pub use exposure::*;
+pub use sensor_bloom::*;
+pub use sensor_noise::*;
+pub use vignetting::*;
+
+use crate::{Float, Spectrum};
+use serde::{Deserialize, Serialize};
+
+/// A frame of HDR spectra in row-major order.
+#[derive(Clone)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Spectrum>,
+}
+
+impl Frame {
+    /// Creates a new frame.
+    ///
+    /// # Constraints
+    /// * `pixels` - Should contain `width * height` spectra.
+    ///
+    /// # Arguments
+    /// * `width` - The width
+    /// * `height` - The height
+    /// * `pixels` - The spectra in row-major order
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(width: u32, height: u32, pixels: Vec<Spectrum>) -> Self {
+        debug_assert!(pixels.len() == (width * height) as usize);
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[inline]
+    pub fn index(&self, x: u32, y: u32) -> usize {
+        (x + y * self.width) as usize
+    }
+
+    #[inline]
+    pub fn get(&self, x: u32, y: u32) -> &Spectrum {
+        &self.pixels[self.index(x, y)]
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, x: u32, y: u32) -> &mut Spectrum {
+        let index = self.index(x, y);
+        &mut self.pixels[index]
+    }
+
+    /// Returns the center of the frame in continuous pixel coordinates.
+    #[inline]
+    pub fn center(&self) -> (Float, Float) {
+        (self.width as Float / 2.0, self.height as Float / 2.0)
+    }
+
+    /// Samples a single wavelength with bilinear interpolation, clamping at the borders.
+    ///
+    /// # Arguments
+    /// * `x` - The x coordinate in continuous pixel coordinates (pixel centers at `+0.5`)
+    /// * `y` - The y coordinate in continuous pixel coordinates
+    /// * `index` - The wavelength index
+    ///
+    /// # Returns
+    /// * The interpolated intensity
+    pub fn sample_bilinear(&self, x: Float, y: Float, index: usize) -> Float {
+        let max_x = (self.width - 1) as Float;
+        let max_y = (self.height - 1) as Float;
+
+        let x = (x - 0.5).max(0.0).min(max_x);
+        let y = (y - 0.5).max(0.0).min(max_y);
+
+        let x0 = x.floor() as u32;
+        let y0 = y.floor() as u32;
+        let x1 = (x0 + 1).min(self.width - 1);
+        let y1 = (y0 + 1).min(self.height - 1);
+
+        let tx = x - x0 as Float;
+        let ty = y - y0 as Float;
+
+        let top = self.get(x0, y0)[index] * (1.0 - tx) + self.get(x1, y0)[index] * tx;
+        let bottom = self.get(x0, y1)[index] * (1.0 - tx) + self.get(x1, y1)[index] * tx;
+
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+/// A post-processing effect on the HDR sensor data.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PostEffect {
+    /// Lateral chromatic aberration, magnifying the image depending on the wavelength.
+    ChromaticAberration {
+        /// The relative magnification difference between the shortest and longest wavelength.
+        strength: Float,
+    },
+    /// Natural `cos^4` vignetting of a lens with the given vertical field of view (in degrees).
+    Vignetting { fov_y: Float },
+    /// Sensor blooming, spilling the charge of saturated pixels vertically into their neighbours.
+    SensorBloom {
+        /// The intensity at which a pixel saturates.
+        saturation: Float,
+    },
+    /// Bloom/glare of bright pixels, blurring the intensity above a luminance threshold.
+    Bloom {
+        /// The luminance above which pixels bloom.
+        threshold: Float,
+        /// The strength of the added bloom.
+        intensity: Float,
+        /// The standard deviation (in pixels) of the narrowest blur.
+        radius: Float,
+    },
+    /// Shot and read noise of a camera sensor and film grain, e.g. for synthetic training data
+    /// resembling real photos.
+    SensorNoise {
+        /// The expected number of photoelectrons per unit intensity, lower values being noisier.
+        exposure: Float,
+        /// The standard deviation of the read noise (in electrons).
+        #[serde(default)]
+        read_noise: Float,
+        /// The standard deviation of the achromatic film grain factor.
+        #[serde(default)]
+        grain: Float,
+        /// The seed of the noise, making it reproducible.
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+impl PostEffect {
+    /// Applies this effect to the given frame.
+    ///
+    /// # Arguments
+    /// * `frame` - The frame to process
+    pub fn apply(&self, frame: &mut Frame) {
+        if frame.width == 0 || frame.height == 0 {
+            return;
+        }
+
+        match *self {
+            PostEffect::ChromaticAberration { strength } => chromatic_aberration(frame, strength),
+            PostEffect::Vignetting { fov_y } => vignetting(frame, fov_y),
+            PostEffect::SensorBloom { saturation } => sensor_bloom(frame, saturation),
+            PostEffect::Bloom {
+                threshold,
+                intensity,
+                radius,
+            } => bloom(frame, threshold, intensity, radius),
+            PostEffect::SensorNoise {
+                exposure,
+                read_noise,
+                grain,
+                seed,
+            } => sensor_noise(frame, exposure, read_noise, grain, seed),
+        }
+    }
+}
+
+#[test]
+fn lens_and_sensor_effects() {
+    use color::Color;
+
+    // vignetting darkens the corners, but not the center
+    let mut frame = Frame::new(5, 5, vec![Spectrum::broadcast(1.0); 25]);
+    PostEffect::Vignetting { fov_y: 60.0 }.apply(&mut frame);
+    assert_eq!(frame.get(2, 2)[0], 1.0);
+    assert!(frame.get(0, 0)[0] < frame.get(0, 2)[0]);
+    assert!(frame.get(0, 2)[0] < 1.0);
+    assert_eq!(frame.get(0, 0)[0], frame.get(4, 4)[0]);
+
+    // longer wavelengths get magnified more, looking up a ramp closer to the center
+    let ramp = (0..25)
+        .map(|i| Spectrum::broadcast((i % 5) as Float))
+        .collect();
+    let mut frame = Frame::new(5, 5, ramp);
+    PostEffect::ChromaticAberration { strength: 0.2 }.apply(&mut frame);
+    let right = frame.get(4, 2);
+    assert!(right[Spectrum::size() - 1] < 4.0);
+    assert!(right[0] > right[Spectrum::size() - 1]);
+    assert_eq!(frame.get(2, 2)[0], 2.0);
+
+    // saturated charge spills into the column, conserving energy
+    let mut frame = Frame::new(2, 5, vec![Spectrum::broadcast(0.0); 10]);
+    *frame.get_mut(0, 2) = Spectrum::broadcast(2.5);
+    PostEffect::SensorBloom { saturation: 1.0 }.apply(&mut frame);
+    let column: Vec<Float> = (0..5).map(|y| frame.get(0, y)[0]).collect();
+    assert_eq!(column, vec![0.0, 0.75, 1.0, 0.75, 0.0]);
+    assert!((0..5).all(|y| frame.get(1, y).is_black()));
+}
@@ -0,0 +1,60 @@
+use crate::postprocessing::Frame;
+use crate::{Float, Spectrum};
+
+/// Applies sensor blooming.
+///
+/// Like the charge of a CCD pixel, each wavelength intensity above `saturation` overflows and
+/// spills equally up and down the column, filling up neighbouring pixels until it is absorbed.
+///
+/// # Constraints
+/// * `saturation` - Should be positive.
+///
+/// # Arguments
+/// * `frame` - The frame to process
+/// * `saturation` - The intensity at which a pixel saturates
+pub fn sensor_bloom(frame: &mut Frame, saturation: Float) {
+    debug_assert!(saturation > 0.0);
+
+    let height = frame.height as usize;
+    let mut excess = vec![0.0; height];
+
+    for x in 0..frame.width {
+        for i in 0..Spectrum::size() {
+            // collect the overflowing charge
+            let mut any = false;
+            for (y, e) in excess.iter_mut().enumerate() {
+                let value = &mut frame.get_mut(x, y as u32)[i];
+                *e = (*value - saturation).max(0.0);
+
+                if *e > 0.0 {
+                    *value = saturation;
+                    any = true;
+                }
+            }
+
+            if !any {
+                continue;
+            }
+
+            // spill half downwards
+            let mut carry = 0.0;
+            for (y, e) in excess.iter().enumerate() {
+                carry += e / 2.0;
+                let value = &mut frame.get_mut(x, y as u32)[i];
+                let absorbed = carry.min(saturation - *value);
+                *value += absorbed;
+                carry -= absorbed;
+            }
+
+            // spill half upwards
+            carry = 0.0;
+            for (y, e) in excess.iter().enumerate().rev() {
+                carry += e / 2.0;
+                let value = &mut frame.get_mut(x, y as u32)[i];
+                let absorbed = carry.min(saturation - *value);
+                *value += absorbed;
+                carry -= absorbed;
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::postprocessing::Frame;
+use crate::{Float, Spectrum};
+use fastrand::Rng;
+use std::f64::consts::TAU;
+
+/// The mean above which Poisson samples get approximated by a normal distribution.
+const POISSON_NORMAL_THRESHOLD: f64 = 32.0;
+
+/// Samples the standard normal distribution by the Box-Muller transform.
+fn sample_normal(rng: &Rng) -> f64 {
+    let u1 = 1.0 - rng.f64();
+    let u2 = rng.f64();
+
+    (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+}
+
+/// Samples the Poisson distribution with the given mean, approximating large means normally.
+fn sample_poisson(rng: &Rng, mean: f64) -> f64 {
+    if mean <= 0.0 {
+        0.0
+    } else if mean < POISSON_NORMAL_THRESHOLD {
+        // Knuth's multiplication of uniform samples
+        let limit = (-mean).exp();
+        let mut count = 0;
+        let mut product = rng.f64();
+
+        while product > limit {
+            count += 1;
+            product *= rng.f64();
+        }
+
+        count as f64
+    } else {
+        (mean + mean.sqrt() * sample_normal(rng)).round().max(0.0)
+    }
+}
+
+/// Applies the noise of a camera sensor and film grain.
+///
+/// The intensities get exposed to a number of photoelectrons, counted with Poisson distributed
+/// shot noise and disturbed by normally distributed read noise, before getting scaled back. Low
+/// exposures therefore look like photos in dim light. Film grain additionally scales each pixel
+/// by an achromatic normally distributed factor.
+///
+/// The noise is reproducible by the seed, e.g. for generating training data.
+///
+/// # Constraints
+/// * `exposure` - Should be positive.
+/// * `read_noise` - Should be non-negative.
+/// * `grain` - Should be non-negative.
+///
+/// # Arguments
+/// * `frame` - The frame to process
+/// * `exposure` - The expected number of photoelectrons per unit intensity
+/// * `read_noise` - The standard deviation of the read noise (in electrons)
+/// * `grain` - The standard deviation of the film grain factor
+/// * `seed` - The seed of the noise
+pub fn sensor_noise(
+    frame: &mut Frame,
+    exposure: Float,
+    read_noise: Float,
+    grain: Float,
+    seed: u64,
+) {
+    debug_assert!(exposure > 0.0);
+    debug_assert!(read_noise >= 0.0);
+    debug_assert!(grain >= 0.0);
+
+    let rng = Rng::with_seed(seed);
+    let exposure = exposure as f64;
+
+    for pixel in frame.pixels.iter_mut() {
+        let grain_factor = if grain > 0.0 {
+            (1.0 + grain as f64 * sample_normal(&rng)).max(0.0)
+        } else {
+            1.0
+        };
+
+        for i in 0..Spectrum::size() {
+            let electrons = pixel[i].max(0.0) as f64 * grain_factor * exposure;
+
+            let mut count = sample_poisson(&rng, electrons);
+            if read_noise > 0.0 {
+                count += read_noise as f64 * sample_normal(&rng);
+            }
+
+            pixel[i] = (count / exposure) as Float;
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::postprocessing::Frame;
+use crate::Float;
+
+/// Applies natural `cos^4` vignetting.
+///
+/// # Constraints
+/// * `fov_y` - Should be within `(0, 180)`.
+///
+/// # Arguments
+/// * `frame` - The frame to process
+/// * `fov_y` - The vertical field of view (in degrees)
+pub fn vignetting(frame: &mut Frame, fov_y: Float) {
+    debug_assert!(fov_y > 0.0 && fov_y < 180.0);
+
+    let (cx, cy) = frame.center();
+    // the distance of the image plane in pixels
+    let focal = cy / (0.5 * fov_y).to_radians().tan();
+    let focal2 = focal * focal;
+
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            let dx = x as Float + 0.5 - cx;
+            let dy = y as Float + 0.5 - cy;
+
+            let cos2 = focal2 / (focal2 + dx * dx + dy * dy);
+            *frame.get_mut(x, y) *= cos2 * cos2;
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//! Aluminium coefficients, a conductor.
+//!
+//! # Resources
+//! Data of Rakić (1995) taken from
+//! [here](https://refractiveindex.info/?shelf=main&book=Al&page=Rakic),
+//! resampled to the visible range.
+
+use crate::Float;
+
+/// The wavelengths in **µm**
+pub static INDEX: [Float; 9] = [0.4, 0.45, 0.5, 0.55, 0.6, 0.65, 0.7, 0.75, 0.8];
+pub static N: [Float; 9] = [0.49, 0.62, 0.77, 0.96, 1.2, 1.49, 1.83, 2.29, 2.8];
+pub static K: [Float; 9] = [4.86, 5.47, 6.08, 6.69, 7.26, 7.79, 8.31, 8.75, 8.45];
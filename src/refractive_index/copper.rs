@@ -0,0 +1,13 @@
+//! Copper coefficients, a conductor.
+//!
+//! # Resources
+//! Data of Johnson and Christy (1972) taken from
+//! [here](https://refractiveindex.info/?shelf=main&book=Cu&page=Johnson),
+//! resampled to the visible range.
+
+use crate::Float;
+
+/// The wavelengths in **µm**
+pub static INDEX: [Float; 10] = [0.4, 0.45, 0.5, 0.55, 0.575, 0.6, 0.65, 0.7, 0.75, 0.8];
+pub static N: [Float; 10] = [1.18, 1.13, 1.12, 1.02, 0.5, 0.27, 0.21, 0.21, 0.22, 0.26];
+pub static K: [Float; 10] = [2.21, 2.47, 2.6, 2.58, 2.8, 3.4, 3.67, 4.2, 4.66, 5.1];
@@ -0,0 +1,13 @@
+//! Gold coefficients, a conductor.
+//!
+//! # Resources
+//! Data of Johnson and Christy (1972) taken from
+//! [here](https://refractiveindex.info/?shelf=main&book=Au&page=Johnson),
+//! resampled to the visible range.
+
+use crate::Float;
+
+/// The wavelengths in **µm**
+pub static INDEX: [Float; 9] = [0.4, 0.45, 0.5, 0.55, 0.6, 0.65, 0.7, 0.75, 0.8];
+pub static N: [Float; 9] = [1.66, 1.4, 0.97, 0.4, 0.24, 0.17, 0.16, 0.15, 0.16];
+pub static K: [Float; 9] = [1.96, 1.91, 1.87, 2.4, 2.98, 3.4, 3.86, 4.3, 4.75];
@@ -15,8 +15,12 @@ use utility::floats::FloatExt;
 ///! coefficient**. The extinction coefficient describes how strongly a material absorbs light at given
 ///! wavelength.
 pub mod air;
+pub mod aluminium;
+pub mod copper;
 pub mod glass;
+pub mod gold;
 pub mod sapphire;
+pub mod silver;
 pub mod water;
 
 #[derive(Copy, Clone, Serialize, Deserialize)]
@@ -27,6 +31,14 @@ pub enum RefractiveType {
     Glass,
     Sapphire,
     Linear(Float, Float),
+    /// A conductor, absorbing most transmitted light
+    Gold,
+    /// A conductor, absorbing most transmitted light
+    Silver,
+    /// A conductor, absorbing most transmitted light
+    Copper,
+    /// A conductor, absorbing most transmitted light
+    Aluminium,
 }
 
 impl RefractiveType {
@@ -43,6 +55,10 @@ impl RefractiveType {
             RefractiveType::Glass => 1.5168,
             RefractiveType::Sapphire => 1.7490,
             &RefractiveType::Linear(min, max) => 0.5 * (min + max),
+            RefractiveType::Gold => 0.40,
+            RefractiveType::Silver => 0.06,
+            RefractiveType::Copper => 1.02,
+            RefractiveType::Aluminium => 0.96,
         }
     }
 
@@ -57,6 +73,10 @@ impl RefractiveType {
             RefractiveType::Water => Some(7.2792e-9),
             RefractiveType::Glass => Some(9.7525e-9),
             RefractiveType::Sapphire => Some(0.020900),
+            RefractiveType::Gold => Some(2.40),
+            RefractiveType::Silver => Some(3.59),
+            RefractiveType::Copper => Some(2.58),
+            RefractiveType::Aluminium => Some(6.69),
             _ => None,
         }
     }
@@ -79,6 +99,10 @@ impl RefractiveType {
             RefractiveType::Linear(min, max) => {
                 Float::lerp_map(LAMBDA_START, LAMBDA_END, *max, *min, lambda)
             }
+            RefractiveType::Gold => interpolate(&gold::INDEX, &gold::N, lambda),
+            RefractiveType::Silver => interpolate(&silver::INDEX, &silver::N, lambda),
+            RefractiveType::Copper => interpolate(&copper::INDEX, &copper::N, lambda),
+            RefractiveType::Aluminium => interpolate(&aluminium::INDEX, &aluminium::N, lambda),
         }
     }
 
@@ -97,6 +121,12 @@ impl RefractiveType {
             RefractiveType::Sapphire => {
                 Some(search_and_lerp(&sapphire::INDEX_K, &sapphire::K, lambda))
             }
+            RefractiveType::Gold => Some(interpolate(&gold::INDEX, &gold::K, lambda)),
+            RefractiveType::Silver => Some(interpolate(&silver::INDEX, &silver::K, lambda)),
+            RefractiveType::Copper => Some(interpolate(&copper::INDEX, &copper::K, lambda)),
+            RefractiveType::Aluminium => {
+                Some(interpolate(&aluminium::INDEX, &aluminium::K, lambda))
+            }
             _ => None,
         }
     }
@@ -136,3 +166,41 @@ pub fn search_and_lerp(
         }
     }
 }
+
+/// Linearly interpolates tabulated values at the given wavelength, clamping to the first and last
+/// value outside the table.
+///
+/// # Constraints
+/// * `index_slice` - Should be sorted ascending and not be empty.
+/// * `value_slice` - Should have the same length as `index_slice`.
+///
+/// # Arguments
+/// * `index_slice` - The wavelengths of the values
+/// * `value_slice` - The values
+/// * `lambda` - The wavelength
+///
+/// # Returns
+/// * The interpolated value
+pub fn interpolate(index_slice: &[Float], value_slice: &[Float], lambda: Float) -> Float {
+    debug_assert!(!index_slice.is_empty());
+    debug_assert_eq!(index_slice.len(), value_slice.len());
+
+    let last = index_slice.len() - 1;
+    if lambda <= index_slice[0] {
+        return value_slice[0];
+    }
+    if lambda >= index_slice[last] {
+        return value_slice[last];
+    }
+
+    match search_index(index_slice, lambda) {
+        Ok(i) => value_slice[i],
+        Err((min, max)) => Float::lerp_map(
+            index_slice[min],
+            index_slice[max],
+            value_slice[min],
+            value_slice[max],
+            lambda,
+        ),
+    }
+}
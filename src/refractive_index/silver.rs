@@ -0,0 +1,13 @@
+//! Silver coefficients, a conductor.
+//!
+//! # Resources
+//! Data of Johnson and Christy (1972) taken from
+//! [here](https://refractiveindex.info/?shelf=main&book=Ag&page=Johnson),
+//! resampled to the visible range.
+
+use crate::Float;
+
+/// The wavelengths in **µm**
+pub static INDEX: [Float; 9] = [0.4, 0.45, 0.5, 0.55, 0.6, 0.65, 0.7, 0.75, 0.8];
+pub static N: [Float; 9] = [0.05, 0.04, 0.05, 0.06, 0.06, 0.05, 0.04, 0.03, 0.04];
+pub static K: [Float; 9] = [2.1, 2.66, 3.09, 3.59, 4.01, 4.41, 4.84, 5.24, 5.6];
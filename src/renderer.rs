@@ -1,28 +1,77 @@
 use crate::camera::Camera;
 use crate::config::Config;
 use crate::integrator::Integrator;
+use crate::merge;
+use crate::metadata::{fnv1a, Metadata};
+use crate::obj_export::ObjExport;
+use crate::postprocessing::Frame;
 use crate::samplers::Sampler;
 use crate::scene::Scene;
+use crate::sensor::aov::Aov;
 use crate::sensor::bounds::{Bounds2, UBounds2};
+#[cfg(feature = "sanitize")]
+use crate::sensor::pixel::Discarded;
+use crate::sensor::pixel::Pixel;
 use crate::sensor::sensor_tile::SensorTile;
 use crate::sensor::Sensor;
 use crate::serialization::Serialization;
-use crate::{Float, Vector2};
-use image::{ImageBuffer, Rgb};
+use crate::stats::SceneStats;
+use crate::{Float, Spectrum, Vector2, Vector3};
+use color::{Color, Srgb};
+use image::{ImageBuffer, Luma, Primitive, Rgb};
 use indicatif::{ProgressBar, ProgressStyle};
 use parking_lot::Mutex;
+use std::any::Any;
+use std::convert::TryInto;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use ultraviolet::UVec2;
 
+/// The number of primary rays per pixel estimating the coverage of the alpha channel.
+const ALPHA_SAMPLES: usize = 16;
+
+/// The default stack size of the render threads in MiB. The platform defaults (e.g. 1 MiB on
+/// Windows) are too small for deep BVHs and recursive integrators.
+const DEFAULT_STACK_SIZE: usize = 32;
+
+/// The number of times a tile gets retried within a pass after its integration panicked.
+const TILE_RETRIES: u32 = 2;
+
+/// The maximum relative difference of the depths of a surface seen by a pixel and its previous
+/// pixel, such that the previous samples get [reused](Renderer::move_camera).
+const REPROJECTION_DEPTH_TOLERANCE: Float = 0.02;
+
+/// The minimum cosine between the normals of a surface seen by a pixel and its previous pixel,
+/// such that the previous samples get [reused](Renderer::move_camera).
+const REPROJECTION_MIN_COS: Float = 0.9;
+
+/// A report of a tile whose integration panicked in a pass.
+#[derive(Clone, Debug)]
+pub struct TileError {
+    /// The index of the tile
+    pub tile: usize,
+    /// The pass
+    pub pass: usize,
+    /// The number of panics, each getting retried up to [TILE_RETRIES] times
+    pub panics: u32,
+    /// Whether a retry succeeded. Otherwise, the tile misses the pass.
+    pub recovered: bool,
+    /// The message of the last panic
+    pub message: String,
+}
+
 /// A render job consists of thread handles.
 /// It can be stopped or joined at the end of execution.
 pub struct RenderJob<T> {
     renderer: Renderer,
     should_stop: Arc<AtomicBool>,
     handles: Vec<JoinHandle<T>>,
+    started: Instant,
 }
 
 impl<T> RenderJob<T> {
@@ -44,6 +93,7 @@ impl<T> RenderJob<T> {
             renderer,
             should_stop,
             handles,
+            started: Instant::now(),
         }
     }
 
@@ -65,6 +115,7 @@ impl<T> RenderJob<T> {
             handle.join()?;
         }
 
+        *self.renderer.render_time.lock() = Some(self.started.elapsed());
         self.renderer.progress_bar.lock().finish();
 
         Ok(())
@@ -78,7 +129,15 @@ pub struct Renderer {
     integrator: Box<dyn Integrator>,
     sensor: Arc<Sensor>,
     config: Config,
+    /// The [content hash](Serialization::content_hash) of the scene
+    content_hash: u64,
     progress: Arc<AtomicUsize>,
+    /// The number of tiles that failed the energy check
+    energy_warnings: Arc<AtomicUsize>,
+    /// The tiles whose integration panicked
+    tile_errors: Arc<Mutex<Vec<TileError>>>,
+    /// The duration of the last finished render job
+    render_time: Arc<Mutex<Option<Duration>>>,
     pub progress_bar: Arc<Mutex<ProgressBar>>,
 }
 
@@ -91,7 +150,11 @@ impl Clone for Renderer {
             integrator: dyn_clone::clone_box(&*self.integrator),
             sensor: self.sensor.clone(),
             config: self.config.clone(),
+            content_hash: self.content_hash,
             progress: self.progress.clone(),
+            energy_warnings: self.energy_warnings.clone(),
+            tile_errors: self.tile_errors.clone(),
+            render_time: self.render_time.clone(),
             progress_bar: self.progress_bar.clone(),
         }
     }
@@ -108,13 +171,26 @@ impl Renderer {
             Arc::new(Mutex::new(bar))
         };
 
+        let content_hash = serialization.content_hash();
+
         let mut scene = serialization.scene;
+        scene.apply_light_groups(&serialization.config.light_groups);
+        scene.set_light_paths(
+            serialization
+                .config
+                .light_paths
+                .iter()
+                .map(|(_, expression)| expression.clone())
+                .collect(),
+        );
+        scene.set_aovs(serialization.config.aovs.clone());
         scene.init();
 
         let camera = serialization.camera;
 
         let sampler = serialization.sampler;
-        let integrator = serialization.integrator;
+        let mut integrator = serialization.integrator;
+        integrator.init(&*camera);
 
         let config = serialization.config.clone();
 
@@ -139,21 +215,36 @@ impl Renderer {
             ),
         );
 
+        let light_groups = if config.light_group_aovs {
+            scene.light_group_names().len()
+        } else {
+            0
+        };
+
         let sensor = Sensor::new(
             camera.resolution(),
             config.filename,
             sensor_bounds,
             config.block_size,
+            light_groups,
+            config.light_paths.len(),
+            camera.get_filter().radius(),
         );
 
+        integrator.preprocess(&scene, &sensor);
+
         Self {
             scene: Arc::new(scene),
             camera,
             sampler,
             integrator,
             config: serialization.config,
+            content_hash,
             sensor: Arc::new(sensor),
             progress,
+            energy_warnings: Arc::new(AtomicUsize::new(0)),
+            tile_errors: Arc::default(),
+            render_time: Arc::new(Mutex::new(None)),
             progress_bar,
         }
     }
@@ -180,48 +271,308 @@ impl Renderer {
         self.progress_out_of_range(self.get_progress())
     }
 
+    /// Returns the fraction of the progress in `[0, z]` for `z = render_blocks * passes`, e.g. to
+    /// report it when resuming a render or to clients.
+    ///
+    /// # Returns
+    /// * The fraction in `[0, 1]`
+    pub fn progress_fraction(&self) -> f64 {
+        let total = self.tile_range().len() * self.config.passes as usize;
+
+        if total == 0 {
+            1.0
+        } else {
+            self.get_progress().min(total) as f64 / total as f64
+        }
+    }
+
     /// Returns whether the given progress is at/over the limit of `[0, z]` for
     /// `z = render_blocks * passes`.
     ///
     /// # Returns
     /// * Whether the progress is at/over the limit
     fn progress_out_of_range(&self, progress: usize) -> bool {
-        progress >= self.sensor.num_tiles() * self.config.passes as usize
+        progress >= self.tile_range().len() * self.config.passes as usize
+    }
+
+    /// Returns the indices of the tiles to render, which are all tiles unless restricted by the
+    /// [tile range](Config::tile_range).
+    ///
+    /// # Returns
+    /// * The range of tile indices
+    pub fn tile_range(&self) -> Range<usize> {
+        let tiles = self.sensor.num_tiles();
+
+        match self.config.tile_range {
+            Some((start, end)) => {
+                let start = start.min(tiles);
+                start..end.clamp(start, tiles)
+            }
+            None => 0..tiles,
+        }
+    }
+
+    /// Returns whether only a part of the tiles gets rendered.
+    ///
+    /// # Returns
+    /// * Whether a [tile range](Config::tile_range) is set
+    pub fn is_partial(&self) -> bool {
+        self.config.tile_range.is_some()
     }
 
     fn get_progress_and_next_tile(&mut self) -> Option<(usize, &Mutex<SensorTile>)> {
         let index = self.progress.fetch_add(1, Ordering::Relaxed);
+        let range = self.tile_range();
 
-        if index < self.config.passes as usize * self.sensor.num_tiles() {
-            Some((
-                index,
-                &self.sensor.tiles[index as usize % self.sensor.num_tiles()],
-            ))
+        if index < self.config.passes as usize * range.len() {
+            Some((index, &self.sensor.tiles[range.start + index % range.len()]))
         } else {
             None
         }
     }
 
+    /// Saves the accumulated data and the progress to the given path, to
+    /// [resume](Renderer::load_checkpoint) the render later on.
+    ///
+    /// # Constraints
+    /// * Any render job should be stopped beforehand, such that all started tiles are done.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the checkpoint
+    ///
+    /// # Returns
+    /// * Whether writing succeeded
+    pub fn save_checkpoint(&self, path: &str) -> bincode::Result<()> {
+        let total = self.tile_range().len() * self.config.passes as usize;
+
+        self.sensor
+            .save_checkpoint(path, self.get_progress().min(total))
+    }
+
+    /// Restores the accumulated data and the progress of a checkpoint written by
+    /// [save_checkpoint](Renderer::save_checkpoint), such that rendering continues from there.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the checkpoint
+    ///
+    /// # Returns
+    /// * Whether reading succeeded and the checkpoint matches the sensor
+    pub fn load_checkpoint(&self, path: &str) -> bincode::Result<()> {
+        let progress = self.sensor.load_checkpoint(path)?;
+        self.progress.store(progress, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Returns the multipliers of all light groups of the scene, sorted by their names.
+    ///
+    /// # Returns
+    /// * The names and multipliers of the light groups
+    pub fn light_group_multipliers(&self) -> Vec<(&str, Float)> {
+        let mut groups: Vec<(&str, Float)> = self
+            .scene
+            .emitters
+            .iter()
+            .filter_map(|e| Some((e.light_group.as_deref()?, e.multiplier())))
+            .collect();
+        groups.sort_by(|a, b| a.0.cmp(b.0));
+        groups.dedup_by(|a, b| a.0 == b.0);
+
+        groups
+    }
+
+    /// Sets the multiplier of the given light group and discards the accumulated samples, which
+    /// got rendered with the previous multiplier.
+    ///
+    /// Any render job should be stopped beforehand, as the progress starts over.
+    ///
+    /// # Arguments
+    /// * `group` - The name of the light group
+    /// * `multiplier` - The multiplier of its emitters
+    ///
+    /// # Returns
+    /// * Nothing, or an error message if the scene has no such light group
+    pub fn set_light_group_multiplier(
+        &mut self,
+        group: &str,
+        multiplier: Float,
+    ) -> Result<(), String> {
+        if self.scene.light_group_multiplier(group).is_none() {
+            return Err(format!("Unknown light group: {}", group));
+        }
+
+        self.config
+            .light_groups
+            .insert(group.to_string(), multiplier);
+        self.scene.apply_light_groups(&self.config.light_groups);
+
+        // e.g. photon maps depend on the emission
+        self.integrator.preprocess(&self.scene, &self.sensor);
+        self.sensor.clear();
+        self.progress.store(0, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Saves the accumulated data of the [tile range](Renderer::tile_range) to the given path,
+    /// to be [merged](Renderer::load_partial) with the other parts of the render.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the sensor dump
+    ///
+    /// # Returns
+    /// * Whether writing succeeded
+    pub fn save_partial(&self, path: &str) -> bincode::Result<()> {
+        self.sensor.save_partial(path, self.tile_range())
+    }
+
+    /// Merges the tiles of a sensor dump written by [save_partial](Renderer::save_partial) into
+    /// this renderer.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the sensor dump
+    ///
+    /// # Returns
+    /// * Whether reading succeeded and the dump matches the sensor
+    pub fn load_partial(&self, path: &str) -> bincode::Result<()> {
+        self.sensor.load_partial(path)
+    }
+
+    /// Returns the number of tiles that failed the [energy check](Renderer::check_energy).
+    ///
+    /// # Returns
+    /// * The number of warnings
+    pub fn energy_warnings(&self) -> usize {
+        self.energy_warnings.load(Ordering::Relaxed)
+    }
+
+    /// Returns the reports of the tiles whose integration panicked, ordered by pass and tile.
+    ///
+    /// # Returns
+    /// * The tile errors
+    pub fn tile_errors(&self) -> Vec<TileError> {
+        let mut errors = self.tile_errors.lock().clone();
+        errors.sort_by_key(|e| (e.pass, e.tile));
+
+        errors
+    }
+
+    /// Integrates the given tile for one pass, catching panics of the integrator such that a
+    /// single bad path does not abort the whole render. A panicking tile gets restored and
+    /// retried up to [TILE_RETRIES] times, [reporting](Renderer::tile_errors) the panics.
+    ///
+    /// # Arguments
+    /// * `index` - The index of the tile
+    /// * `pass` - The current pass
+    /// * `tile` - The tile
+    fn integrate_tile(&self, index: usize, pass: usize, tile: &mut SensorTile) {
+        let mut panics = 0;
+        let mut message = String::new();
+
+        let recovered = loop {
+            let pixels = tile.pixels.clone();
+            let splats = tile.splats.clone();
+
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                self.integrator.integrate_tile(
+                    &mut tile.pixels,
+                    &self.scene,
+                    &*self.camera,
+                    self.sampler,
+                )
+            }));
+
+            match result {
+                Ok(()) => break true,
+                Err(payload) => {
+                    // discard the partially integrated pass
+                    tile.pixels = pixels;
+                    tile.splats = splats;
+                    panics += 1;
+                    message = panic_message(&*payload);
+
+                    if panics > TILE_RETRIES {
+                        break false;
+                    }
+                }
+            }
+        };
+
+        if panics > 0 {
+            self.tile_errors.lock().push(TileError {
+                tile: index,
+                pass,
+                panics,
+                recovered,
+                message,
+            });
+        }
+    }
+
+    /// Compares the mean radiance of the given tile against the maximal radiance of the scene,
+    /// warning if it exceeds it by more than the configured tolerance.
+    ///
+    /// # Arguments
+    /// * `tile` - The rendered tile
+    /// * `pass` - The current pass
+    fn check_energy(&self, tile: &SensorTile, pass: usize) {
+        let (tolerance, budget) = match (self.config.energy_check, self.scene.max_radiance()) {
+            (Some(tolerance), Some(budget)) => (tolerance, budget),
+            _ => return,
+        };
+
+        if tile.pixels.is_empty() {
+            return;
+        }
+
+        let sum: Spectrum = tile.pixels.iter().map(|px| px.average).sum();
+        let mean = (sum / tile.pixels.len() as Float).component_max();
+
+        if mean > budget * (1.0 + tolerance) {
+            let warnings = self.energy_warnings.fetch_add(1, Ordering::Relaxed);
+
+            // only report the first violation in detail
+            if warnings == 0 {
+                self.progress_bar.lock().println(format!(
+                    "Energy check failed in pass {} for tile {:?}: mean radiance {} exceeds maximal emitted radiance {}",
+                    pass, tile.bounds, mean, budget
+                ));
+            }
+        }
+    }
+
     #[inline]
     pub fn resolution(&self) -> UVec2 {
         self.camera.resolution()
     }
 
+    /// Returns the resolution of the rendered images, i.e. of the sensor bounds.
+    ///
+    /// # Returns
+    /// * The width and height
+    pub fn image_resolution(&self) -> UVec2 {
+        self.sensor.bounds.to_range()
+    }
+
     pub fn render(&mut self) -> RenderJob<()> {
         // reset progress bar
         {
             let bar = self.progress_bar.lock();
-            bar.set_length((self.sensor.num_tiles() * self.config.passes as usize) as u64);
+            let total = self.tile_range().len() * self.config.passes as usize;
+            bar.set_length(total as u64);
             bar.reset();
+            // continue from previous (or restored) progress
+            bar.set_position(self.get_progress().min(total) as u64);
         }
 
         let threads = self.config.threads.unwrap_or(num_cpus::get() as u32);
+        let stack_size = self.config.stack_size.unwrap_or(DEFAULT_STACK_SIZE) * 1024 * 1024;
 
         let mut handles = Vec::with_capacity(threads as usize);
         let should_stop = Arc::new(AtomicBool::new(false));
         let frames = Arc::new(AtomicIsize::new(0));
 
-        let tiles = self.sensor.num_tiles();
+        let tiles = self.tile_range().len();
 
         for i in 0..threads {
             let this = self.clone();
@@ -230,7 +581,7 @@ impl Renderer {
 
             let handle = thread::Builder::new()
                 .name(format!("Render thread {}", i))
-                .stack_size(32 * 1024 * 1024)
+                .stack_size(stack_size)
                 .spawn(move || loop {
                     if this_should_stop.load(Ordering::Relaxed) {
                         break;
@@ -244,11 +595,12 @@ impl Renderer {
                                 .set_message(format!("Frames rendered: {}", frame));
                         }
 
-                        for px in &mut sensor.lock().pixels {
-                            let primary_ray = this.camera.primary_ray(px.position);
-                            this.integrator
-                                .integrate(px, &this.scene, &primary_ray, this.sampler);
-                        }
+                        let mut tile = sensor.lock();
+                        let index = this.tile_range().start + progress % tiles;
+                        this.integrate_tile(index, progress / tiles, &mut tile);
+                        this.check_energy(&tile, progress / tiles);
+                        this.sensor.add_light_splats(&mut tile);
+                        drop(tile);
 
                         this.progress_bar.lock().inc(1);
                     } else {
@@ -263,37 +615,1227 @@ impl Renderer {
         RenderJob::new(self.clone(), should_stop, handles)
     }
 
-    //noinspection DuplicatedCode
+    /// Returns the names of the light groups that have a separate buffer.
+    ///
+    /// # Returns
+    /// * The light group names (empty if disabled)
+    pub fn light_group_names(&self) -> &[String] {
+        if self.sensor.light_groups == 0 {
+            &[]
+        } else {
+            self.scene.light_group_names()
+        }
+    }
+
+    /// Returns the names of the light path expressions with separate buffers.
+    ///
+    /// # Returns
+    /// * The light path names (empty if none are configured)
+    pub fn light_path_names(&self) -> Vec<&str> {
+        self.config
+            .light_paths
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
     pub fn get_image_u8(&self) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        Self::frame_to_image(&self.get_exposed_frame())
+    }
+
+    pub fn get_image_u16(&self) -> ImageBuffer<Rgb<u16>, Vec<u16>> {
+        Self::frame_to_image(&self.get_exposed_frame())
+    }
+
+    /// Returns the frame scaled by the configured [exposure](Config::exposure) for the LDR
+    /// images.
+    fn get_exposed_frame(&self) -> Frame {
+        let mut frame = self.get_frame();
+        if let Some(exposure) = &self.config.exposure {
+            exposure.apply(&mut frame);
+        }
+
+        frame
+    }
+
+    /// Returns the image of the light group at the given index.
+    ///
+    /// # Constraints
+    /// * `index` - Should be less than the number of [light groups](Renderer::light_group_names).
+    pub fn get_light_group_image_u8(&self, index: usize) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        Self::frame_to_image(&self.get_raw_frame(|px| px.light_groups[index]))
+    }
+
+    /// Returns the image of the light group at the given index.
+    ///
+    /// # Constraints
+    /// * `index` - Should be less than the number of [light groups](Renderer::light_group_names).
+    pub fn get_light_group_image_u16(&self, index: usize) -> ImageBuffer<Rgb<u16>, Vec<u16>> {
+        Self::frame_to_image(&self.get_raw_frame(|px| px.light_groups[index]))
+    }
+
+    /// Returns the image of the light path expression at the given index.
+    ///
+    /// # Constraints
+    /// * `index` - Should be less than the number of [light paths](Renderer::light_path_names).
+    pub fn get_light_path_image_u8(&self, index: usize) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        Self::frame_to_image(&self.get_raw_frame(|px| px.light_paths[index]))
+    }
+
+    /// Returns the image of the light path expression at the given index.
+    ///
+    /// # Constraints
+    /// * `index` - Should be less than the number of [light paths](Renderer::light_path_names).
+    pub fn get_light_path_image_u16(&self, index: usize) -> ImageBuffer<Rgb<u16>, Vec<u16>> {
+        Self::frame_to_image(&self.get_raw_frame(|px| px.light_paths[index]))
+    }
+
+    /// Sums up the contributions discarded by all pixels.
+    ///
+    /// # Returns
+    /// * The discarded contributions
+    #[cfg(feature = "sanitize")]
+    pub fn discarded(&self) -> Discarded {
+        let mut discarded = Discarded::default();
+
+        for lock in &self.sensor.tiles {
+            for px in &lock.lock().pixels {
+                discarded += px.discarded;
+            }
+        }
+
+        discarded
+    }
+
+    /// Collects statistics about the scene and the memory needed to render it.
+    ///
+    /// # Returns
+    /// * The scene statistics
+    pub fn stats(&self) -> SceneStats {
+        SceneStats::new(&self.scene, &self.sensor)
+    }
+
+    /// Exports the camera frustum, samples on the emitters (as seen from the camera) and the
+    /// bounding boxes of the scene's BVH into an OBJ file to inspect the scene setup.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the `.obj` file
+    /// * `bvh_depth` - The maximum depth of the exported BVH nodes
+    ///
+    /// # Returns
+    /// * Whether the file could be written
+    pub fn export_obj<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        bvh_depth: u32,
+    ) -> std::io::Result<()> {
+        let res = self.resolution();
+        let center = self.camera.primary_ray(UVec2::new(res.x / 2, res.y / 2));
+        let distance = self.scene.bvh_bounds(0)[0].size().mag();
+
+        ObjExport::new()
+            .add_frustum(self.camera.as_ref(), distance)
+            .add_light_samples(&self.scene, center.origin, 16)
+            .add_aabbs("bvh", &self.scene.bvh_bounds(bvh_depth))
+            .save(path)
+    }
+
+    /// Collects the settings of this render, to be embedded into the saved images.
+    ///
+    /// # Returns
+    /// * The crate version, passes, integrator and sampler settings and the render time (if
+    ///   finished)
+    pub fn metadata(&self) -> Metadata {
+        let integrator = ron::to_string(&self.integrator).unwrap_or_default();
+        let sampler = ron::to_string(&self.sampler).unwrap_or_default();
+
+        let mut metadata = Metadata::new()
+            .with("Software", format!("Rust-V {}", env!("CARGO_PKG_VERSION")))
+            .with("Passes", self.config.passes)
+            .with("Integrator", integrator)
+            .with("Sampler", sampler)
+            .with("Content hash", format!("{:016x}", self.content_hash));
+
+        if let Some(time) = *self.render_time.lock() {
+            metadata = metadata.with("Render time", format!("{:.3}s", time.as_secs_f64()));
+        }
+
+        metadata
+    }
+
+    /// Returns the [content hash](Serialization::content_hash) of the rendered scene.
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash
+    }
+
+    /// Hashes the bytes of the HDR frame, allowing to check whether a render reproduces another
+    /// one exactly.
+    ///
+    /// # Returns
+    /// * The [FNV-1a](fnv1a) hash
+    pub fn image_hash(&self) -> u64 {
+        let frame = self.get_frame();
+
+        let mut bytes = Vec::with_capacity(frame.pixels.len() * Spectrum::size() * 8);
+        for pixel in &frame.pixels {
+            for i in 0..Spectrum::size() {
+                bytes.extend_from_slice(&pixel[i].to_le_bytes());
+            }
+        }
+
+        fnv1a(&bytes)
+    }
+
+    /// Saves the HDR frame as linear RGB into an OpenEXR file.
+    ///
+    /// Unlike the integer images, the values are neither clamped nor gamma-encoded.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the `.exr` file
+    /// * `metadata` - The metadata to embed as text attributes of the header
+    ///
+    /// # Returns
+    /// * Whether the file could be written
+    #[allow(clippy::unnecessary_cast)]
+    pub fn save_exr<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        metadata: &Metadata,
+    ) -> exr::error::UnitResult {
+        use exr::prelude::*;
+
+        let frame = self.get_frame();
+        let size = (frame.width as usize, frame.height as usize);
+        // allows to merge independent renders weighted by their passes
+        let mut attributes = merge::layer_attributes(self.config.passes);
+        for (key, value) in metadata.entries() {
+            if let Some(value) = Text::new_or_none(value) {
+                attributes
+                    .other
+                    .insert(Text::from(key.as_str()), AttributeValue::Text(value));
+            }
+        }
+
+        if self.has_holdouts() {
+            let (width, _, alpha) = self.get_alpha_frame();
+
+            // premultiplied by alpha, cutting out the holdouts
+            let channels = SpecificChannels::rgba(|position: Vec2<usize>| {
+                let (x, y) = (position.x(), position.y());
+                let a = alpha[x + y * width as usize];
+                let rgb = Srgb::from(*frame.get(x as u32, y as u32)).to_linear() * a;
+                (rgb[0] as f32, rgb[1] as f32, rgb[2] as f32, a as f32)
+            });
+            let layer = Layer::new(size, attributes, Encoding::FAST_LOSSLESS, channels);
+
+            return Image::from_layer(layer).write().to_file(path);
+        }
+
+        let channels = SpecificChannels::rgb(|position: Vec2<usize>| {
+            let rgb = Srgb::from(*frame.get(position.x() as u32, position.y() as u32)).to_linear();
+            (rgb[0] as f32, rgb[1] as f32, rgb[2] as f32)
+        });
+        let layer = Layer::new(size, attributes, Encoding::FAST_LOSSLESS, channels);
+
+        Image::from_layer(layer).write().to_file(path)
+    }
+
+    /// Returns whether the scene contains [matte](crate::objects::Receiver::matte) holdouts, such
+    /// that an alpha channel should be saved.
+    ///
+    /// # Returns
+    /// * Whether the scene has holdouts
+    pub fn has_holdouts(&self) -> bool {
+        self.scene.has_matte()
+    }
+
+    /// Estimates the coverage of each pixel by casting [ALPHA_SAMPLES] jittered primary rays.
+    /// Rays hitting a regular object count as covered, while rays hitting a matte holdout or
+    /// escaping the scene do not.
+    ///
+    /// # Returns
+    /// * The width, height and the alpha values within `[0, 1]` in row-major order
+    fn get_alpha_frame(&self) -> (u32, u32, Vec<Float>) {
         let bounds = self.sensor.bounds;
         let res = bounds.to_range();
-        let mut buffer = ImageBuffer::new(res.x, res.y);
+
+        let mut values = Vec::with_capacity(bounds.area());
+        for y in 0..res.y {
+            for x in 0..res.x {
+                let position = UVec2::new(bounds.min.x + x, bounds.min.y + y);
+
+                let covered = (0..ALPHA_SAMPLES)
+                    .filter(|_| {
+                        let ray = self.camera.primary_ray(position);
+                        self.scene
+                            .intersect(&ray)
+                            .is_some_and(|i| !i.object.is_matte())
+                    })
+                    .count();
+                values.push(covered as Float / ALPHA_SAMPLES as Float);
+            }
+        }
+
+        (res.x, res.y, values)
+    }
+
+    /// Returns the linear greyscale alpha image of the [holdouts](Renderer::has_holdouts).
+    pub fn get_alpha_image_u8(&self) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+        let frame = self.get_alpha_frame();
+        ImageBuffer::from_fn(frame.0, frame.1, |x, y| {
+            let value = frame.2[(x + y * frame.0) as usize];
+            Luma([(value * u8::MAX as Float).round() as u8])
+        })
+    }
+
+    /// Returns the linear greyscale alpha image of the [holdouts](Renderer::has_holdouts).
+    pub fn get_alpha_image_u16(&self) -> ImageBuffer<Luma<u16>, Vec<u16>> {
+        let frame = self.get_alpha_frame();
+        ImageBuffer::from_fn(frame.0, frame.1, |x, y| {
+            let value = frame.2[(x + y * frame.0) as usize];
+            Luma([(value * u16::MAX as Float).round() as u16])
+        })
+    }
+
+    /// Casts a primary ray through each pixel and collects the world-space position and normal of
+    /// the first visible surface. Pixels seeing no surface get zero vectors.
+    ///
+    /// The geometries of the scene are placed in world space, such that the object-space normals
+    /// are the same as the world-space ones.
+    ///
+    /// # Returns
+    /// * The width, height and the positions and normals in row-major order
+    fn get_geometry_frame(&self) -> (u32, u32, Vec<(Vector3, Vector3)>) {
+        let res = self.sensor.bounds.to_range();
+        let values = self
+            .visible_surfaces()
+            .into_iter()
+            .map(|v| v.unwrap_or((Vector3::zero(), Vector3::zero())))
+            .collect();
+
+        (res.x, res.y, values)
+    }
+
+    /// Casts a primary ray through each pixel of the sensor bounds and collects the world-space
+    /// position and normal of the first visible surface.
+    ///
+    /// # Returns
+    /// * The positions and normals in row-major order, `None` for pixels seeing no surface
+    fn visible_surfaces(&self) -> Vec<Option<(Vector3, Vector3)>> {
+        let bounds = self.sensor.bounds;
+        let res = bounds.to_range();
+
+        let mut values = Vec::with_capacity(bounds.area());
+        for y in 0..res.y {
+            for x in 0..res.x {
+                let position = UVec2::new(bounds.min.x + x, bounds.min.y + y);
+                let ray = self.camera.primary_ray(position);
+
+                values.push(self.scene.intersect(&ray).map(|i| (i.point, i.normal)));
+            }
+        }
+
+        values
+    }
+
+    /// Moves the camera by the given offset, reusing the accumulated samples of the pixels that
+    /// still see the same surface instead of clearing the sensor, e.g. while navigating in the
+    /// live window.
+    ///
+    /// The visible surface of each pixel gets projected onto the previous camera. The pixel it
+    /// lands on gets reused if it saw a surface at a similar depth and with a similar normal.
+    /// Pixels seeing no surface reuse themselves if they did not see one before either, as the
+    /// directions of the primary rays stay the same.
+    ///
+    /// Any render job should be stopped beforehand, as the progress starts over.
+    ///
+    /// # Arguments
+    /// * `offset` - The translation along the right, up and viewing direction of the camera
+    ///
+    /// # Returns
+    /// * The number of reused pixels, or an error message if the camera cannot be moved
+    pub fn move_camera(&mut self, offset: Vector3) -> Result<usize, String> {
+        let camera = self
+            .camera
+            .translated(offset)
+            .ok_or_else(|| "The camera does not support moving".to_string())?;
+
+        let previous_surfaces = self.visible_surfaces();
+        let previous_camera = std::mem::replace(&mut self.camera, camera);
+        self.integrator.init(&*self.camera);
+        let surfaces = self.visible_surfaces();
+
+        let bounds = self.sensor.bounds;
+        let width = bounds.to_range().x;
+        let index = |p: UVec2| ((p.x - bounds.min.x) + (p.y - bounds.min.y) * width) as usize;
+
+        let reused = self.sensor.reproject(|position| {
+            let (point, normal) = match surfaces[index(position)] {
+                Some(surface) => surface,
+                None if previous_surfaces[index(position)].is_none() => return Some(position),
+                None => return None,
+            };
+
+            let connection = previous_camera.connect(point)?;
+            let (x, y) = (connection.raster.x as u32, connection.raster.y as u32);
+            if x < bounds.min.x || y < bounds.min.y || x >= bounds.max.x || y >= bounds.max.y {
+                return None;
+            }
+
+            let previous = UVec2::new(x, y);
+            let (previous_point, previous_normal) = previous_surfaces[index(previous)]?;
+
+            let depth = (point - connection.eye).mag();
+            let previous_depth = (previous_point - connection.eye).mag();
+
+            if (depth - previous_depth).abs() <= REPROJECTION_DEPTH_TOLERANCE * depth
+                && normal.dot(previous_normal) >= REPROJECTION_MIN_COS
+            {
+                Some(previous)
+            } else {
+                None
+            }
+        });
+
+        self.progress.store(0, Ordering::Relaxed);
+
+        Ok(reused)
+    }
+
+    /// Returns whether the [geometry AOVs](Renderer::save_geometry_exr) should be saved.
+    ///
+    /// # Returns
+    /// * Whether they are enabled in the config
+    pub fn geometry_aovs(&self) -> bool {
+        self.config.geometry_aovs
+    }
+
+    /// Saves the world-space positions and normals of the visible surfaces into two OpenEXR files,
+    /// e.g. to reproject textures in external tools.
+    ///
+    /// # Arguments
+    /// * `position_path` - The path of the `.exr` file of the positions
+    /// * `normal_path` - The path of the `.exr` file of the normals
+    ///
+    /// # Returns
+    /// * Whether the files could be written
+    #[allow(clippy::unnecessary_cast)]
+    pub fn save_geometry_exr<P: AsRef<std::path::Path>>(
+        &self,
+        position_path: P,
+        normal_path: P,
+    ) -> exr::error::UnitResult {
+        let (width, height, values) = self.get_geometry_frame();
+        let get = |x: usize, y: usize| values[x + y * width as usize];
+
+        exr::prelude::write_rgb_file(position_path, width as usize, height as usize, |x, y| {
+            let p = get(x, y).0;
+            (p.x as f32, p.y as f32, p.z as f32)
+        })?;
+
+        exr::prelude::write_rgb_file(normal_path, width as usize, height as usize, |x, y| {
+            let n = get(x, y).1;
+            (n.x as f32, n.y as f32, n.z as f32)
+        })
+    }
+
+    /// Returns the names of the configured [AOVs](Aov).
+    ///
+    /// # Returns
+    /// * The AOV names (empty if disabled)
+    pub fn aov_names(&self) -> Vec<&str> {
+        self.config.aovs.iter().map(Aov::name).collect()
+    }
+
+    /// Collects the AOV at the given index of each pixel as linear RGB. Normals get stored as
+    /// `xyz`, depths and labels in all three channels. Pixels without any AOV sample stay black.
+    ///
+    /// # Returns
+    /// * The width, height and the values in row-major order
+    fn get_aov_frame(&self, index: usize) -> (u32, u32, Vec<[Float; 3]>) {
+        let aov = self.config.aovs[index];
+        let offset: usize = self.config.aovs[..index].iter().map(Aov::channels).sum();
+
+        let bounds = self.sensor.bounds;
+        let res = bounds.to_range();
+        let mut values = vec![[0.0; 3]; bounds.area()];
 
         for lock in &self.sensor.tiles {
             for px in &lock.lock().pixels {
+                let channels = match px.aovs.get(offset..offset + aov.channels()) {
+                    Some(channels) => channels,
+                    None => continue,
+                };
+
+                let value = match aov {
+                    Aov::Normal => [channels[0], channels[1], channels[2]],
+                    Aov::Depth | Aov::Segmentation => [channels[0]; 3],
+                    Aov::Albedo | Aov::Direct | Aov::Indirect => {
+                        let spectrum = Spectrum::new(channels.try_into().unwrap());
+                        let rgb = Srgb::from(spectrum).to_linear();
+                        [rgb[0], rgb[1], rgb[2]]
+                    }
+                };
+
                 let (x, y) = (px.position.x - bounds.min.x, px.position.y - bounds.min.y);
+                values[(x + y * res.x) as usize] = value;
+            }
+        }
+
+        (res.x, res.y, values)
+    }
+
+    /// Saves the AOV at the given index into an OpenEXR file.
+    ///
+    /// # Constraints
+    /// * `index` - Should be less than the number of [AOVs](Renderer::aov_names).
+    ///
+    /// # Arguments
+    /// * `index` - The index of the AOV
+    /// * `path` - The path of the `.exr` file
+    ///
+    /// # Returns
+    /// * Whether the file could be written
+    #[allow(clippy::unnecessary_cast)]
+    pub fn save_aov_exr<P: AsRef<std::path::Path>>(
+        &self,
+        index: usize,
+        path: P,
+    ) -> exr::error::UnitResult {
+        let (width, height, values) = self.get_aov_frame(index);
+
+        exr::prelude::write_rgb_file(path, width as usize, height as usize, |x, y| {
+            let v = values[x + y * width as usize];
+            (v[0] as f32, v[1] as f32, v[2] as f32)
+        })
+    }
+
+    /// Returns the names of the configured spectral bands.
+    ///
+    /// # Returns
+    /// * The spectral band names
+    pub fn spectral_band_names(&self) -> Vec<&str> {
+        self.config
+            .spectral_bands
+            .iter()
+            .map(|b| b.name.as_str())
+            .collect()
+    }
+
+    /// Returns the linear greyscale image of the spectral band at the given index.
+    ///
+    /// # Constraints
+    /// * `index` - Should be less than the number of [bands](Renderer::spectral_band_names).
+    pub fn get_spectral_band_image_u8(&self, index: usize) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+        let frame = self.get_spectral_band_frame(index);
+        ImageBuffer::from_fn(frame.0, frame.1, |x, y| {
+            let value = frame.2[(x + y * frame.0) as usize];
+            Luma([(value * u8::MAX as Float).round() as u8])
+        })
+    }
+
+    /// Returns the linear greyscale image of the spectral band at the given index.
+    ///
+    /// # Constraints
+    /// * `index` - Should be less than the number of [bands](Renderer::spectral_band_names).
+    pub fn get_spectral_band_image_u16(&self, index: usize) -> ImageBuffer<Luma<u16>, Vec<u16>> {
+        let frame = self.get_spectral_band_frame(index);
+        ImageBuffer::from_fn(frame.0, frame.1, |x, y| {
+            let value = frame.2[(x + y * frame.0) as usize];
+            Luma([(value * u16::MAX as Float).round() as u16])
+        })
+    }
+
+    /// Integrates each pixel against the spectral band at the given index.
+    ///
+    /// # Returns
+    /// * The width, height and the band values clamped to `[0, 1]` in row-major order
+    fn get_spectral_band_frame(&self, index: usize) -> (u32, u32, Vec<Float>) {
+        let band = &self.config.spectral_bands[index];
+        let frame = self.get_raw_frame(|px| px.average);
+
+        let values = frame
+            .pixels
+            .iter()
+            .map(|s| band.integrate(s).clamp(0.0, 1.0))
+            .collect();
+
+        (frame.width, frame.height, values)
+    }
+
+    /// Returns the HDR frame of the sensor with all post-processing effects applied.
+    ///
+    /// # Returns
+    /// * The processed frame
+    pub fn get_frame(&self) -> Frame {
+        let mut frame = match self.sensor.resolve_splats(self.camera.get_filter()) {
+            Some(pixels) => {
+                let res = self.sensor.bounds.to_range();
+                Frame::new(res.x, res.y, pixels)
+            }
+            None => self.get_raw_frame(|px| px.average),
+        };
+
+        self.integrator.reconstruct(&mut frame, &self.sensor);
 
-                buffer.put_pixel(x, y, Rgb::from(px.average));
+        if let Some(light_splats) = self.sensor.resolve_light_splats() {
+            for (px, splat) in frame.pixels.iter_mut().zip(light_splats) {
+                *px += splat;
             }
         }
 
-        buffer
+        for effect in &self.config.post_effects {
+            effect.apply(&mut frame);
+        }
+
+        frame
     }
 
-    //noinspection DuplicatedCode
-    pub fn get_image_u16(&self) -> ImageBuffer<Rgb<u16>, Vec<u16>> {
+    fn get_raw_frame(&self, spectrum: impl Fn(&Pixel) -> Spectrum) -> Frame {
         let bounds = self.sensor.bounds;
         let res = bounds.to_range();
-        let mut buffer = ImageBuffer::new(res.x, res.y);
+        let mut frame = Frame::new(res.x, res.y, vec![Spectrum::default(); bounds.area()]);
 
         for lock in &self.sensor.tiles {
             for px in &lock.lock().pixels {
                 let (x, y) = (px.position.x - bounds.min.x, px.position.y - bounds.min.y);
 
-                buffer.put_pixel(x, y, Rgb::from(px.average));
+                *frame.get_mut(x, y) = spectrum(px);
+            }
+        }
+
+        frame
+    }
+
+    fn frame_to_image<T>(frame: &Frame) -> ImageBuffer<Rgb<T>, Vec<T>>
+    where
+        T: Primitive + 'static,
+        Rgb<T>: From<Spectrum>,
+    {
+        ImageBuffer::from_fn(frame.width, frame.height, |x, y| {
+            Rgb::from(*frame.get(x, y))
+        })
+    }
+}
+
+/// Returns the message of the given panic payload.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[test]
+fn checkpoints_resume_the_progress() {
+    let source = |passes: u32| {
+        format!(
+            r#"(
+                config: (
+                    filename: None,
+                    bounds: None,
+                    block_size: (x: 4, y: 4),
+                    passes: {},
+                    threads: Some(2),
+                ),
+                camera: {{
+                    "PerspectiveCamera": (
+                        Sampler: Random,
+                        Position: (x: 0, y: 0, z: 4),
+                        Target: (x: 0, y: 0, z: 0),
+                        Up: (x: 0, y: 1, z: 0),
+                        FovY: 40,
+                        Resolution: (x: 8, y: 6),
+                    ),
+                }},
+                integrator: {{"Path": (max_depth: 1)}},
+                sampler: Random,
+                scene: (
+                    bounding_box: (
+                        min: (x: -1, y: -1, z: -1),
+                        max: (x: 1, y: 1, z: 1),
+                    ),
+                    objects: [
+                        Emitter((
+                            geometry: {{"Sphere": (center: (x: 0, y: 0, z: 0), radius: 0.8)}},
+                            bsdf: (bxdfs: []),
+                            emission: Constant(1),
+                        )),
+                    ],
+                ),
+            )"#,
+            passes
+        )
+    };
+    let samples = |renderer: &Renderer| {
+        renderer
+            .sensor
+            .tiles
+            .iter()
+            .flat_map(|t| {
+                t.lock()
+                    .pixels
+                    .iter()
+                    .map(|px| px.samples()[0])
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let path = std::env::temp_dir().join(format!("rust-v-resume-{}.bin", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    let mut first = Renderer::new(ron::from_str::<Serialization>(&source(1)).unwrap());
+    first.render().join().unwrap();
+    first.save_checkpoint(path).unwrap();
+
+    // resume with more passes
+    let mut resumed = Renderer::new(ron::from_str::<Serialization>(&source(3)).unwrap());
+    let loaded = resumed.load_checkpoint(path);
+    std::fs::remove_file(path).unwrap();
+    loaded.unwrap();
+
+    assert!((resumed.progress_fraction() - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(samples(&first), samples(&resumed));
+
+    resumed.render().join().unwrap();
+    assert!(resumed.is_done());
+    assert!(samples(&resumed).iter().all(|&s| s == 3));
+}
+
+#[test]
+fn filter_splats_merge_across_tile_seams() {
+    use crate::filters::{Filter, TriangleFilter};
+    use color::Color;
+
+    // an emitting disk covering parts of several 4x4 tiles
+    let source = r#"(
+        config: (
+            filename: None,
+            bounds: None,
+            block_size: (x: 4, y: 4),
+            passes: 2,
+            threads: Some(2),
+        ),
+        camera: {
+            "PerspectiveCamera": (
+                Sampler: Random,
+                Filter: {"TriangleFilter": (radius: (x: 2, y: 2))},
+                Position: (x: 0, y: 0, z: 4),
+                Target: (x: 0, y: 0, z: 0),
+                Up: (x: 0, y: 1, z: 0),
+                FovY: 40,
+                Resolution: (x: 12, y: 12),
+            ),
+        },
+        integrator: {"Path": (max_depth: 1)},
+        sampler: Random,
+        scene: (
+            bounding_box: (
+                min: (x: -1, y: -1, z: -1),
+                max: (x: 1, y: 1, z: 1),
+            ),
+            objects: [
+                Emitter((
+                    geometry: {"Sphere": (center: (x: 0.3, y: 0.2, z: 0), radius: 0.8)},
+                    bsdf: (bxdfs: []),
+                    emission: Constant(1),
+                )),
+            ],
+        ),
+    )"#;
+
+    let mut renderer = Renderer::new(Serialization::parse(source, None).unwrap());
+    renderer.render().join().unwrap();
+
+    let mut raw = vec![Spectrum::broadcast(0.0); 12 * 12];
+    for tile in &renderer.sensor.tiles {
+        for px in &tile.lock().pixels {
+            raw[(px.position.x + px.position.y * 12) as usize] = px.average;
+        }
+    }
+    let frame = renderer
+        .sensor
+        .resolve_splats(renderer.camera.get_filter())
+        .unwrap();
+    let filter = TriangleFilter::new(Vector2::broadcast(2.0));
+
+    // every pixel took the same number of samples, so the splats equal a convolution
+    let mut filtered = false;
+    for y in 0..12i32 {
+        for x in 0..12i32 {
+            let mut sum = Spectrum::broadcast(0.0);
+            let mut weights = 0.0;
+            for ny in (y - 2).max(0)..(y + 3).min(12) {
+                for nx in (x - 2).max(0)..(x + 3).min(12) {
+                    let offset = Vector2::new((nx - x) as Float, (ny - y) as Float);
+                    let weight = filter.evaluate(offset);
+                    sum += raw[(nx + ny * 12) as usize] * weight;
+                    weights += weight;
+                }
             }
+
+            let expected = sum / weights;
+            let actual = frame[(x + y * 12) as usize];
+            for i in 0..Spectrum::size() {
+                assert!(
+                    (actual[i] - expected[i]).abs() < 1e-4,
+                    "pixel ({}, {}) is {} instead of {}",
+                    x,
+                    y,
+                    actual[i],
+                    expected[i]
+                );
+            }
+
+            filtered |= (actual[0] - raw[(x + y * 12) as usize][0]).abs() > 1e-2;
+        }
+    }
+
+    assert!(filtered);
+}
+
+#[test]
+fn energy_check_flags_brightening_materials() {
+    let render = |reflectance: Float, light: &str| {
+        let source = format!(
+            r#"(
+                config: (
+                    filename: None,
+                    bounds: None,
+                    block_size: (x: 4, y: 4),
+                    passes: 1,
+                    threads: Some(1),
+                    energy_check: Some(0.05),
+                ),
+                camera: {{
+                    "PerspectiveCamera": (
+                        Sampler: NoOp,
+                        Position: (x: 0, y: 0, z: 4),
+                        Target: (x: 0, y: 0, z: 0),
+                        Up: (x: 0, y: 1, z: 0),
+                        FovY: 40,
+                        Resolution: (x: 8, y: 8),
+                    ),
+                }},
+                integrator: {{"Path": (max_depth: 4)}},
+                sampler: NoOp,
+                scene: (
+                    bounding_box: (
+                        min: (x: -1, y: -1, z: -1),
+                        max: (x: 1, y: 1, z: 1),
+                    ),
+                    ambient_light: (Constant(1)),
+                    objects: [
+                        Receiver((
+                            geometry: {{"Sphere": (center: (x: 0, y: 0, z: 0), radius: 1)}},
+                            bsdf: (bxdfs: [{{"LambertianReflection": (r: Constant({}))}}]),
+                        )),
+                        {}
+                    ],
+                ),
+            )"#,
+            reflectance, light
+        );
+
+        let mut renderer = Renderer::new(ron::from_str(&source).unwrap());
+        renderer.render().join().unwrap();
+
+        (renderer.scene.max_radiance(), renderer.energy_warnings())
+    };
+
+    // a white furnace stays within the ambient light
+    assert_eq!(render(1.0, ""), (Some(1.0), 0));
+    assert_eq!(render(3.0, ""), (Some(1.0), 4));
+
+    // point lights have an unbounded radiance
+    let point = r#"Emitter((geometry: {"Point": ((x: 0, y: 2, z: 0))}, emission: Constant(1))),"#;
+    assert_eq!(render(3.0, point), (None, 0));
+}
+
+#[test]
+fn geometry_aovs_store_world_positions_and_normals() {
+    use exr::prelude::{read_first_rgba_layer_from_file, Vec2};
+
+    let source = r#"(
+        config: (
+            filename: None,
+            bounds: None,
+            block_size: (x: 4, y: 4),
+            passes: 1,
+            threads: Some(1),
+        ),
+        camera: {
+            "PerspectiveCamera": (
+                Sampler: NoOp,
+                Position: (x: 0, y: 0, z: 6),
+                Target: (x: 0, y: 0, z: 0),
+                Up: (x: 0, y: 1, z: 0),
+                FovY: 40,
+                Resolution: (x: 9, y: 7),
+            ),
+        },
+        integrator: {"Whitted": (max_depth: 1)},
+        sampler: NoOp,
+        scene: (
+            bounding_box: (
+                min: (x: 0, y: 0, z: -1),
+                max: (x: 2, y: 2, z: 1),
+            ),
+            objects: [
+                Receiver((
+                    geometry: {"Sphere": (center: (x: 1, y: 1, z: 0), radius: 1)},
+                    bsdf: (bxdfs: []),
+                )),
+            ],
+        ),
+    )"#;
+    let renderer = Renderer::new(ron::from_str(source).unwrap());
+
+    let dir = std::env::temp_dir();
+    let id = std::process::id();
+    let position_path = dir.join(format!("rust-v-position-{}.exr", id));
+    let normal_path = dir.join(format!("rust-v-normal-{}.exr", id));
+    renderer
+        .save_geometry_exr(&position_path, &normal_path)
+        .unwrap();
+
+    let read = |path: &std::path::Path| {
+        let image = read_first_rgba_layer_from_file(
+            path,
+            |resolution, _| (resolution, vec![Vector3::zero(); resolution.area()]),
+            |(resolution, pixels): &mut (Vec2<usize>, Vec<Vector3>),
+             position: Vec2<usize>,
+             (r, g, b, _): (f32, f32, f32, f32)| {
+                pixels[position.x() + position.y() * resolution.width()] =
+                    Vector3::new(r as Float, g as Float, b as Float);
+            },
+        )
+        .unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        image.layer_data.channel_data.pixels
+    };
+    let (resolution, positions) = read(&position_path);
+    let (_, normals) = read(&normal_path);
+
+    assert_eq!((resolution.width(), resolution.height()), (9, 7));
+
+    let center = Vector3::new(1.0, 1.0, 0.0);
+    let mut hits = 0;
+    for (p, n) in positions.iter().zip(&normals) {
+        if *n == Vector3::zero() {
+            assert_eq!(*p, Vector3::zero());
+            continue;
+        }
+
+        hits += 1;
+        assert!(((*p - center).mag() - 1.0).abs() < 1e-4);
+        assert!((*n - (*p - center)).mag() < 1e-4);
+        assert!(n.z > 0.0);
+    }
+
+    assert!(hits > 0 && hits < positions.len());
+}
+
+#[test]
+fn matte_receivers_hold_out_the_alpha() {
+    let source = |matte: bool| {
+        format!(
+            r#"(
+                config: (
+                    filename: None,
+                    bounds: None,
+                    block_size: (x: 4, y: 4),
+                    passes: 1,
+                    threads: Some(1),
+                ),
+                camera: {{
+                    "PerspectiveCamera": (
+                        Sampler: NoOp,
+                        Position: (x: 0, y: 0, z: 8),
+                        Target: (x: 0, y: 0, z: 0),
+                        Up: (x: 0, y: 1, z: 0),
+                        FovY: 20,
+                        Resolution: (x: 12, y: 4),
+                    ),
+                }},
+                integrator: {{"Whitted": (max_depth: 1)}},
+                sampler: NoOp,
+                scene: (
+                    bounding_box: (
+                        min: (x: -3, y: -1, z: -1),
+                        max: (x: 3, y: 1, z: 1),
+                    ),
+                    objects: [
+                        Receiver((
+                            geometry: {{"Sphere": (center: (x: -1.5, y: 0, z: 0), radius: 1)}},
+                            bsdf: (bxdfs: []),
+                        )),
+                        Receiver((
+                            geometry: {{"Sphere": (center: (x: 1.5, y: 0, z: 0), radius: 1)}},
+                            bsdf: (bxdfs: []),
+                            matte: {},
+                        )),
+                    ],
+                ),
+            )"#,
+            matte
+        )
+    };
+
+    assert!(!Renderer::new(ron::from_str(&source(false)).unwrap()).has_holdouts());
+
+    let renderer = Renderer::new(ron::from_str(&source(true)).unwrap());
+    assert!(renderer.has_holdouts());
+
+    let alpha = renderer.get_alpha_image_u8();
+    let row: Vec<u8> = (0..12).map(|x| alpha.get_pixel(x, 2)[0]).collect();
+
+    // the regular sphere on the left, the matte one and the background cut out
+    assert_eq!(row[3], u8::MAX);
+    assert_eq!(row[8], 0);
+    assert_eq!(row[0], 0);
+    assert!(row[6..].iter().all(|&a| a == 0));
+}
+
+#[test]
+fn exr_headers_embed_the_metadata() {
+    use exr::prelude::{read_all_data_from_file, AttributeValue, Text};
+
+    let source = r#"(
+        config: (
+            filename: None,
+            bounds: None,
+            block_size: (x: 4, y: 4),
+            passes: 3,
+            threads: Some(1),
+        ),
+        camera: {
+            "PerspectiveCamera": (
+                Sampler: NoOp,
+                Position: (x: 0, y: 0, z: 4),
+                Target: (x: 0, y: 0, z: 0),
+                Up: (x: 0, y: 1, z: 0),
+                FovY: 40,
+                Resolution: (x: 4, y: 4),
+            ),
+        },
+        integrator: {"Whitted": (max_depth: 2)},
+        sampler: NoOp,
+        scene: (
+            bounding_box: (
+                min: (x: -1, y: -1, z: -1),
+                max: (x: 1, y: 1, z: 1),
+            ),
+            objects: [
+                Receiver((
+                    geometry: {"Sphere": (center: (x: 0, y: 0, z: 0), radius: 1)},
+                    bsdf: (bxdfs: []),
+                )),
+            ],
+        ),
+    )"#;
+    let mut renderer = Renderer::new(ron::from_str(source).unwrap());
+    renderer.render().join().unwrap();
+
+    let metadata = renderer.metadata();
+    assert_eq!(metadata.get("Passes"), Some("3"));
+    assert!(metadata.get("Integrator").unwrap().contains("Whitted"));
+    assert!(metadata.get("Render time").is_some());
+
+    let path = std::env::temp_dir().join(format!("rust-v-metadata-{}.exr", std::process::id()));
+    renderer.save_exr(&path, &metadata).unwrap();
+    let image = read_all_data_from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let attributes = &image.layer_data[0].attributes.other;
+    for (key, value) in metadata.entries() {
+        assert_eq!(
+            attributes.get(&Text::from(key.as_str())),
+            Some(&AttributeValue::Text(Text::from(value.as_str())))
+        );
+    }
+    assert_eq!(
+        crate::merge::passes(&image.layer_data[0].attributes),
+        Some(3)
+    );
+}
+
+#[test]
+fn path_tracer_writes_the_aovs() {
+    let source = r#"(
+        config: (
+            filename: None,
+            bounds: None,
+            block_size: (x: 4, y: 4),
+            passes: 1,
+            threads: Some(1),
+            aovs: [Normal, Depth, Direct, Indirect],
+        ),
+        camera: {
+            "PerspectiveCamera": (
+                Sampler: NoOp,
+                Position: (x: 0, y: 0, z: 4),
+                Target: (x: 0, y: 0, z: 0),
+                Up: (x: 0, y: 1, z: 0),
+                FovY: 40,
+                Resolution: (x: 8, y: 8),
+            ),
+        },
+        integrator: {"Path": (max_depth: 4)},
+        sampler: NoOp,
+        scene: (
+            bounding_box: (
+                min: (x: -100, y: -201, z: -100),
+                max: (x: 100, y: 3, z: 100),
+            ),
+            ambient_light: (Constant(0.5)),
+            objects: [
+                Receiver((
+                    geometry: {"Sphere": (center: (x: 0, y: 0, z: 0), radius: 1)},
+                    bsdf: (bxdfs: [{"LambertianReflection": (r: Constant(0.8))}]),
+                )),
+                // a floor bouncing light onto the bottom of the sphere
+                Receiver((
+                    geometry: {"Sphere": (center: (x: 0, y: -101, z: 0), radius: 100)},
+                    bsdf: (bxdfs: [{"LambertianReflection": (r: Constant(0.8))}]),
+                )),
+                Emitter((
+                    geometry: {"Sphere": (center: (x: 0, y: 3, z: 3), radius: 0.25)},
+                    emission: Constant(50),
+                )),
+            ],
+        ),
+    )"#;
+    let mut renderer = Renderer::new(ron::from_str(source).unwrap());
+    renderer.render().join().unwrap();
+
+    assert_eq!(
+        renderer.aov_names(),
+        vec!["normal", "depth", "direct", "indirect"]
+    );
+
+    let frame = renderer.get_frame();
+    let (_, _, normals) = renderer.get_aov_frame(0);
+    let (_, _, depths) = renderer.get_aov_frame(1);
+    let (_, _, direct) = renderer.get_aov_frame(2);
+    let (width, _, indirect) = renderer.get_aov_frame(3);
+
+    // the center pixels see the front of the sphere
+    let center = (4 + 4 * width) as usize;
+    assert!(normals[center][2] > 0.9);
+    assert!((depths[center][0] - 3.0).abs() < 0.05);
+
+    // the background only sees the ambient light directly
+    assert_eq!(normals[0], [0.0; 3]);
+    assert_eq!(indirect[0], [0.0; 3]);
+    assert!(indirect.iter().any(|v| v.iter().any(|&c| c > 0.0)));
+
+    // the direct and indirect light add up to the image
+    for (i, (d, ind)) in direct.iter().zip(&indirect).enumerate() {
+        let px = frame.get(i as u32 % width, i as u32 / width);
+        let rgb = Srgb::from(*px).to_linear();
+        for c in 0..3 {
+            assert!((d[c] + ind[c] - rgb[c]).abs() < 1e-4 * rgb[c].max(1.0));
+        }
+    }
+}
+
+/// An integrator panicking on the tile at `x = 4` in every pass, and on the tile at the origin for
+/// the first `origin_panics` times.
+#[cfg(test)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct PanickingIntegrator {
+    origin_panics: usize,
+    #[serde(skip)]
+    panicked: Arc<AtomicUsize>,
+}
+
+#[cfg(test)]
+#[typetag::serde]
+impl Integrator for PanickingIntegrator {
+    fn integrate(&self, pixel: &mut Pixel, _: &Scene, _: &geometry::Ray, _: Sampler) {
+        if pixel.position.x == 4 {
+            panic!("bad path at {:?}", pixel.position);
+        }
+
+        if pixel.position == UVec2::zero()
+            && self.panicked.fetch_add(1, Ordering::Relaxed) < self.origin_panics
+        {
+            panic!("transient failure");
         }
 
-        buffer
+        pixel.add_black();
+    }
+}
+
+#[test]
+fn panicking_tiles_get_retried_and_reported() {
+    let source = r#"(
+        config: (
+            filename: None,
+            bounds: None,
+            block_size: (x: 4, y: 4),
+            passes: 1,
+            threads: Some(2),
+        ),
+        camera: {
+            "PerspectiveCamera": (
+                Sampler: Random,
+                Position: (x: 0, y: 0, z: 4),
+                Target: (x: 0, y: 0, z: 0),
+                Up: (x: 0, y: 1, z: 0),
+                FovY: 40,
+                Resolution: (x: 8, y: 8),
+            ),
+        },
+        integrator: {"Path": (max_depth: 1)},
+        sampler: Random,
+        scene: (
+            bounding_box: (
+                min: (x: -1, y: -1, z: -1),
+                max: (x: 1, y: 1, z: 1),
+            ),
+            objects: [],
+        ),
+    )"#;
+
+    let mut serialization = Serialization::parse(source, None).unwrap();
+    serialization.integrator = Box::new(PanickingIntegrator {
+        origin_panics: TILE_RETRIES as usize,
+        panicked: Arc::default(),
+    });
+
+    let mut renderer = Renderer::new(serialization);
+    renderer.render().join().unwrap();
+    assert!(renderer.is_done());
+
+    let errors = renderer.tile_errors();
+    assert_eq!(errors.len(), 3);
+
+    // recovered by the last retry
+    assert_eq!((errors[0].tile, errors[0].pass), (0, 0));
+    assert_eq!(errors[0].panics, TILE_RETRIES);
+    assert!(errors[0].recovered);
+    assert_eq!(errors[0].message, "transient failure");
+
+    // skipped after exhausting the retries
+    for error in &errors[1..] {
+        assert_eq!(error.panics, TILE_RETRIES + 1);
+        assert!(!error.recovered);
+        assert!(error.message.starts_with("bad path at"));
+    }
+
+    // the skipped tiles keep no partial samples
+    let frame = renderer.get_raw_frame(|px| Spectrum::broadcast(px.samples()[0] as Float));
+    for y in 0..8 {
+        for x in 0..8 {
+            let expected = if x < 4 { 1.0 } else { 0.0 };
+            assert_eq!(frame.get(x, y)[0], expected, "samples at ({}, {})", x, y);
+        }
     }
 }
@@ -1,9 +1,20 @@
-use crate::objects::{Emitter, SceneObject};
-use crate::{Float, Vector3};
-use geometry::bvh::Tree;
-use geometry::{Aabb, Boundable, Intersectable, Intersection, Ray};
+use crate::atmosphere::Atmosphere;
+use crate::bxdf::{world_to_bxdf, Frame, BSDF};
+use crate::integrator::LightPathExpression;
+use crate::medium::{GridMedium, Scatterer};
+use crate::objects::{AmbientLight, Emitter, LightTree, Model, SceneObject};
+use crate::periodic::PeriodicCell;
+use crate::samplers::Sampler;
+use crate::sensor::aov::Aov;
+use crate::{Float, Spectrum, Vector2, Vector3};
+use color::Color;
+use geometry::bvh::{Tree, TreeStats};
+use geometry::{offset_ray_towards_by, Aabb, Boundable, Intersectable, Intersection, Ray};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::sync::Arc;
+use utility::floats::FloatExt;
 
 /// A scene intersection is a more detailed `Intersection`, also containing a reference to the
 /// intersected object.
@@ -14,6 +25,11 @@ pub struct SceneIntersection {
     pub ray: Ray,
     pub t: Float,
     pub object: SceneObject,
+    /// The surface tangent orienting anisotropic materials (if any)
+    pub tangent: Option<Vector3>,
+    /// The texture coordinates of the geometry, or else of the object's
+    /// [uv mapping](crate::objects::Receiver::uv_mapping) (if any)
+    pub uv: Option<Vector2>,
 }
 
 impl SceneIntersection {
@@ -26,31 +42,177 @@ impl SceneIntersection {
     /// # Returns
     /// * Self
     pub fn new(intersection: Intersection, object: SceneObject) -> Self {
+        let uv = intersection
+            .uv
+            .or_else(|| object.uv(intersection.point, intersection.normal));
+
         Self {
             point: intersection.point,
             normal: intersection.normal,
             ray: intersection.ray,
             t: intersection.t,
             object,
+            tangent: intersection.tangent,
+            uv,
+        }
+    }
+
+    /// Returns the shading frame at the intersection, oriented by the tangent if available and
+    /// carrying the texture coordinates and the point.
+    ///
+    /// # Returns
+    /// * The shading frame
+    #[inline]
+    pub fn frame(&self) -> Frame {
+        let frame = match self.tangent {
+            Some(tangent) => Frame::from_tangent(self.normal, tangent),
+            None => world_to_bxdf(self.normal),
+        };
+
+        frame.with_uv(self.uv).with_point(Some(self.point))
+    }
+}
+
+/// The number of incremental BVH edits always allowed before rebuilding the BVH
+const MIN_EDITS_BEFORE_REBUILD: usize = 16;
+
+#[derive(Deserialize)]
+struct SerdeScene {
+    bounding_box: Aabb,
+    #[serde(default)]
+    ambient_light: AmbientLight,
+    #[serde(default)]
+    scale_hint: Option<Float>,
+    /// The named BSDFs, referenced by the `material` of objects instead of inlining them
+    #[serde(default)]
+    materials: BTreeMap<String, BSDF>,
+    objects: Vec<SceneObject>,
+    /// The obj files split into receivers by their groups and materials
+    #[serde(default)]
+    models: Vec<Model>,
+    #[serde(default)]
+    media: Vec<GridMedium>,
+    #[serde(default)]
+    atmosphere: Option<Atmosphere>,
+    #[serde(default)]
+    periodic: Option<PeriodicCell>,
+}
+
+impl TryFrom<SerdeScene> for Scene {
+    type Error = String;
+
+    fn try_from(mut serde: SerdeScene) -> Result<Self, Self::Error> {
+        resolve_materials(&mut serde.objects, &serde.materials)?;
+
+        let mut scene = Self {
+            bounding_box: serde.bounding_box,
+            ambient_light: serde.ambient_light,
+            scale_hint: serde.scale_hint,
+            objects: serde.objects,
+            media: serde.media,
+            atmosphere: serde.atmosphere,
+            periodic: serde.periodic,
+            ..Default::default()
+        };
+
+        // the receivers of models serialize like ordinary ones
+        for model in serde.models {
+            for receiver in model.into_receivers() {
+                scene.add(SceneObject::Receiver(Arc::new(receiver)));
+            }
+        }
+
+        Ok(scene)
+    }
+}
+
+/// Replaces the BSDFs of the objects referencing a named material by a copy of it.
+///
+/// # Arguments
+/// * `objects` - The freshly deserialized objects
+/// * `materials` - The material library of the scene
+///
+/// # Returns
+/// * Nothing, or an error message if an object references an unknown material
+fn resolve_materials(
+    objects: &mut [SceneObject],
+    materials: &BTreeMap<String, BSDF>,
+) -> Result<(), String> {
+    // BSDFs hold trait objects, such that each copy gets deserialized from the library anew
+    let mut serialized = BTreeMap::new();
+    for (name, bsdf) in materials {
+        let content = ron::to_string(bsdf).map_err(|e| format!("material {}: {}", name, e))?;
+        serialized.insert(name.as_str(), content);
+    }
+
+    for object in objects {
+        let (material, bsdf) = match object {
+            SceneObject::Emitter(e) => match Arc::get_mut(e) {
+                Some(e) => (&e.material, &mut e.bsdf),
+                None => continue,
+            },
+            SceneObject::Receiver(r) => match Arc::get_mut(r) {
+                Some(r) => (&r.material, &mut r.bsdf),
+                None => continue,
+            },
+        };
+
+        if let Some(name) = material {
+            let content = serialized
+                .get(name.as_str())
+                .ok_or_else(|| format!("unknown material: {}", name))?;
+            *bsdf = ron::from_str(content).map_err(|e| format!("material {}: {}", name, e))?;
         }
     }
+
+    Ok(())
 }
 
 /// A scene consists of scene objects and lights.
 #[derive(Serialize, Deserialize)]
+#[serde(try_from = "SerdeScene")]
 pub struct Scene {
     bounding_box: Aabb,
+    /// The constant radiance of rays escaping the scene
+    pub ambient_light: AmbientLight,
+    /// The magnitude of the scene coordinates, scaling the epsilon of ray offsets.
+    /// If not set, it gets derived from the bounding box.
+    pub scale_hint: Option<Float>,
+    #[serde(skip)]
+    epsilon: Float,
     #[serde(skip)]
     pub emitters: Vec<Arc<Emitter>>,
+    /// The hierarchy over the emitters, selecting them for direct illumination
+    #[serde(skip)]
+    light_tree: LightTree,
     objects: Vec<SceneObject>,
+    /// The participating media, only rendered by volumetric integrators
+    pub media: Vec<GridMedium>,
+    /// The atmosphere of a planet (if any), only rendered by volumetric integrators
+    pub atmosphere: Option<Atmosphere>,
+    /// The cell tiling the scene infinitely (if any)
+    pub periodic: Option<PeriodicCell>,
     #[serde(skip)]
     bvh: Tree<SceneObject>,
+    /// The BVH ids of the objects, if the BVH got built
+    #[serde(skip)]
+    bvh_ids: Option<Vec<u32>>,
+    /// The number of incremental BVH edits since the last rebuild
+    #[serde(skip)]
+    bvh_edits: usize,
+    #[serde(skip)]
+    light_groups: Vec<String>,
+    #[serde(skip)]
+    light_paths: Vec<LightPathExpression>,
+    #[serde(skip)]
+    aovs: Vec<Aov>,
 }
 
 impl Scene {
     /// Adds the given object to the scene.
     ///
     /// If the object is an emitter, it will also be added to the lights.
+    /// After [init](Scene::init), the object gets inserted into the BVH incrementally.
     ///
     /// # Arguments
     /// * `obj` - The scene object to add
@@ -61,14 +223,106 @@ impl Scene {
         self.objects.push(obj.clone());
 
         if let SceneObject::Emitter(ref e) = obj {
-            self.emitters.push(e.clone())
+            self.emitters.push(e.clone());
+            self.light_tree = LightTree::new(&self.emitters);
         }
 
         self.bounding_box = self.bounding_box.join(&obj.bounds());
 
+        if let Some(ids) = &mut self.bvh_ids {
+            let bounds = obj.bounds();
+            ids.push(self.bvh.insert(obj, bounds));
+            self.edited_bvh();
+        }
+
         self
     }
 
+    /// Removes the object at the given index (as in [objects](Scene::objects)) from the scene.
+    ///
+    /// If the object is an emitter, it will also be removed from the lights.
+    /// After [init](Scene::init), the object gets removed from the BVH incrementally.
+    /// The bounding box of the scene does not shrink.
+    ///
+    /// # Constraints
+    /// * `index` - Should be less than the number of objects.
+    ///
+    /// # Arguments
+    /// * `index` - The index of the object
+    ///
+    /// # Returns
+    /// * The removed object
+    pub fn remove(&mut self, index: usize) -> SceneObject {
+        let obj = self.objects.remove(index);
+
+        if let SceneObject::Emitter(ref e) = obj {
+            self.emitters.retain(|other| !Arc::ptr_eq(other, e));
+            self.light_tree = LightTree::new(&self.emitters);
+        }
+
+        if let Some(ids) = &mut self.bvh_ids {
+            let id = ids.remove(index);
+            self.bvh.remove(id, &obj.bounds());
+            self.edited_bvh();
+        }
+
+        obj
+    }
+
+    /// Counts an incremental BVH edit, rebuilding the BVH once the edits make up a quarter of the
+    /// objects, as incremental edits degrade its quality.
+    fn edited_bvh(&mut self) {
+        self.bvh_edits += 1;
+
+        if self.bvh_edits > MIN_EDITS_BEFORE_REBUILD + self.objects.len() / 4 {
+            self.rebuild_bvh();
+        }
+    }
+
+    /// Rebuilds the BVH from scratch.
+    fn rebuild_bvh(&mut self) {
+        self.bvh = Tree::new(self.objects.clone(), |s| s.bounds());
+        self.bvh_ids = Some((0..self.objects.len() as u32).collect());
+        self.bvh_edits = 0;
+    }
+
+    /// Scales the emission of all emitters belonging to a light group by the group's multiplier.
+    /// Emitters of groups without a multiplier keep their current one.
+    ///
+    /// The emitters read their multiplier when shading, such that this may be called at any time,
+    /// even while rendering.
+    ///
+    /// # Arguments
+    /// * `multipliers` - The multipliers of the light groups
+    pub fn apply_light_groups(&self, multipliers: &BTreeMap<String, Float>) {
+        for o in &self.objects {
+            if let SceneObject::Emitter(e) = o {
+                let multiplier = e
+                    .light_group
+                    .as_ref()
+                    .and_then(|group| multipliers.get(group));
+
+                if let Some(multiplier) = multiplier {
+                    e.set_multiplier(*multiplier);
+                }
+            }
+        }
+    }
+
+    /// Returns the multiplier of the given light group.
+    ///
+    /// # Arguments
+    /// * `group` - The name of the light group
+    ///
+    /// # Returns
+    /// * The multiplier, or `None` if no emitter belongs to the group
+    pub fn light_group_multiplier(&self, group: &str) -> Option<Float> {
+        self.emitters
+            .iter()
+            .find(|e| e.light_group.as_deref() == Some(group))
+            .map(|e| e.multiplier())
+    }
+
     /// Recollects all emitters into a cached list.
     pub fn init(&mut self) {
         // collect emitters
@@ -80,8 +334,134 @@ impl Scene {
             }
         }
         self.emitters.shrink_to_fit();
+        self.light_tree = LightTree::new(&self.emitters);
 
-        self.bvh = Tree::new(self.objects.clone(), |s| s.bounds());
+        // collect light groups
+        self.light_groups = self
+            .emitters
+            .iter()
+            .filter_map(|e| e.light_group.clone())
+            .collect();
+        self.light_groups.sort();
+        self.light_groups.dedup();
+
+        self.rebuild_bvh();
+
+        let (min, max) = (self.bounding_box.min, self.bounding_box.max);
+        let extent = min.abs().max_by_component(max.abs());
+        let scale = self.scale_hint.unwrap_or_else(|| extent.component_max());
+        self.epsilon = Self::epsilon_for(scale);
+    }
+
+    /// Returns the light tree over the emitters, built during [init](Scene::init).
+    ///
+    /// # Returns
+    /// * The light tree
+    #[inline]
+    pub fn light_tree(&self) -> &LightTree {
+        &self.light_tree
+    }
+
+    /// Returns the epsilon of ray offsets for the given scene scale.
+    /// Scenes up to a scale of `1` use the default epsilon, larger ones a proportionally larger one.
+    fn epsilon_for(scale: Float) -> Float {
+        if scale.is_finite() {
+            Float::big_epsilon() * scale.max(1.0)
+        } else {
+            Float::big_epsilon()
+        }
+    }
+
+    /// Returns the epsilon used to offset rays from surfaces and to shorten occlusion tests,
+    /// which scales with the scene.
+    ///
+    /// # Returns
+    /// * The epsilon
+    #[inline]
+    pub fn epsilon(&self) -> Float {
+        self.epsilon
+    }
+
+    /// Returns a distance after which rays from the given point surely left the bounding box,
+    /// e.g. to test the occlusion of the ambient light.
+    ///
+    /// # Arguments
+    /// * `point` - The origin of the rays
+    ///
+    /// # Returns
+    /// * Twice the distance to the farthest corner of the bounding box
+    pub fn escape_distance(&self, point: Vector3) -> Float {
+        let (min, max) = (self.bounding_box.min, self.bounding_box.max);
+        let farthest = (point - min).abs().max_by_component((point - max).abs());
+
+        2.0 * farthest.mag().max(self.epsilon)
+    }
+
+    /// Creates a ray from the given point into the given direction, offset by the
+    /// [epsilon](Scene::epsilon) of this scene.
+    ///
+    /// # Arguments
+    /// * `point` - The starting point
+    /// * `normal` - The normal vector to offset towards
+    /// * `direction` - The direction of the ray
+    ///
+    /// # Returns
+    /// * The offset ray
+    #[inline]
+    pub fn offset_ray_towards(&self, point: Vector3, normal: Vector3, direction: Vector3) -> Ray {
+        offset_ray_towards_by(point, normal, direction, self.epsilon)
+    }
+
+    /// Returns the sorted names of all light groups.
+    ///
+    /// # Returns
+    /// * The light group names
+    pub fn light_group_names(&self) -> &[String] {
+        &self.light_groups
+    }
+
+    /// Returns the index of the light group of the given emitter.
+    ///
+    /// # Arguments
+    /// * `emitter` - The emitter
+    ///
+    /// # Returns
+    /// * The index into [light_group_names](Scene::light_group_names) (if any)
+    pub fn light_group_index(&self, emitter: &Emitter) -> Option<usize> {
+        let group = emitter.light_group.as_ref()?;
+        self.light_groups.binary_search(group).ok()
+    }
+
+    /// Sets the light path expressions whose contributions get accumulated separately.
+    ///
+    /// # Arguments
+    /// * `light_paths` - The light path expressions
+    pub fn set_light_paths(&mut self, light_paths: Vec<LightPathExpression>) {
+        self.light_paths = light_paths;
+    }
+
+    /// Returns the light path expressions whose contributions get accumulated separately.
+    ///
+    /// # Returns
+    /// * The light path expressions
+    pub fn light_paths(&self) -> &[LightPathExpression] {
+        &self.light_paths
+    }
+
+    /// Sets the auxiliary outputs the integrators should write.
+    ///
+    /// # Arguments
+    /// * `aovs` - The AOVs
+    pub fn set_aovs(&mut self, aovs: Vec<Aov>) {
+        self.aovs = aovs;
+    }
+
+    /// Returns the auxiliary outputs the integrators should write.
+    ///
+    /// # Returns
+    /// * The AOVs
+    pub fn aovs(&self) -> &[Aov] {
+        &self.aovs
     }
 
     /// Intersects the scene with the given ray.
@@ -95,6 +475,21 @@ impl Scene {
     /// # Returns
     /// * A scene intersection (if any)
     pub fn intersect(&self, ray: &Ray) -> Option<SceneIntersection> {
+        let mut intersection = match &self.periodic {
+            Some(cell) => {
+                let (mut i, offset) = cell.traverse(ray, |r| self.intersect_cell(r))?;
+                i.point += offset;
+                i
+            }
+            None => self.intersect_cell(ray)?,
+        };
+
+        intersection.ray = *ray;
+        Some(intersection)
+    }
+
+    /// Intersects the objects of the scene, ignoring the [periodic cell](PeriodicCell).
+    fn intersect_cell(&self, ray: &Ray) -> Option<SceneIntersection> {
         let mut new_ray = *ray;
         let mut intersection = None;
 
@@ -106,11 +501,187 @@ impl Scene {
             }
         }
 
-        if let Some(mut i) = intersection {
-            i.ray = *ray;
-            Some(i)
-        } else {
-            None
+        intersection
+    }
+
+    /// Returns the emitters whose [influence](Emitter::influence) at the given point reaches the
+    /// threshold, allowing to cull distant or weak lights.
+    ///
+    /// # Arguments
+    /// * `point` - The shading point
+    /// * `threshold` - The minimum influence
+    ///
+    /// # Returns
+    /// * The influential emitters
+    pub fn emitters_in_bounds(
+        &self,
+        point: Vector3,
+        threshold: Float,
+    ) -> impl Iterator<Item = &Arc<Emitter>> {
+        self.emitters
+            .iter()
+            .filter(move |e| e.influence(point) >= threshold)
+    }
+
+    /// Returns the maximal radiance emitted by any emitter or the ambient light, which passive
+    /// surfaces cannot exceed.
+    ///
+    /// # Returns
+    /// * The maximal radiance, or `None` if unbounded (point lights)
+    pub fn max_radiance(&self) -> Option<Float> {
+        let mut max = self.ambient().component_max();
+
+        for e in &self.emitters {
+            if e.geometry().surface_area() == 0.0 {
+                return None;
+            }
+
+            max = max.max(e.emission().component_max());
+        }
+
+        Some(max)
+    }
+
+    /// Returns the objects of the scene.
+    #[inline]
+    pub fn objects(&self) -> &[SceneObject] {
+        &self.objects
+    }
+
+    /// Finds the object with the given name.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the object
+    ///
+    /// # Returns
+    /// * The first object with the name (if any)
+    pub fn find(&self, name: &str) -> Option<&SceneObject> {
+        self.position(name).map(|i| &self.objects[i])
+    }
+
+    /// Finds the index (as in [objects](Scene::objects)) of the object with the given name, e.g.
+    /// to [remove](Scene::remove) it.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the object
+    ///
+    /// # Returns
+    /// * The index of the first object with the name (if any)
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.objects.iter().position(|o| o.name() == Some(name))
+    }
+
+    /// Selects all objects carrying the given tag.
+    ///
+    /// # Arguments
+    /// * `tag` - The tag to select
+    ///
+    /// # Returns
+    /// * The tagged objects in scene order
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a SceneObject> + 'a {
+        self.objects.iter().filter(move |o| o.has_tag(tag))
+    }
+
+    /// Returns whether the scene contains any [matte](crate::objects::Receiver::matte) holdouts.
+    #[inline]
+    pub fn has_matte(&self) -> bool {
+        self.objects.iter().any(SceneObject::is_matte)
+    }
+
+    /// Returns statistics about the structure of the scene's BVH.
+    pub fn bvh_stats(&self) -> TreeStats {
+        self.bvh.stats()
+    }
+
+    /// Returns the bounding boxes of the scene's BVH nodes up to the given depth.
+    ///
+    /// # Arguments
+    /// * `max_depth` - The maximum depth of the BVH nodes
+    ///
+    /// # Returns
+    /// * The bounding boxes
+    pub fn bvh_bounds(&self, max_depth: u32) -> Vec<Aabb> {
+        self.bvh.node_bounds(max_depth)
+    }
+
+    /// Returns the radiance arriving along rays that escape the scene.
+    ///
+    /// # Returns
+    /// * The [ambient light](AmbientLight) radiance
+    #[inline]
+    pub fn ambient(&self) -> Spectrum {
+        self.ambient_light.0
+    }
+
+    /// Returns whether the scene contains any participating media.
+    #[inline]
+    pub fn has_media(&self) -> bool {
+        !self.media.is_empty() || self.atmosphere.is_some()
+    }
+
+    /// Samples the next real collision with any [medium](GridMedium) or the
+    /// [atmosphere](Atmosphere) along the given ray.
+    ///
+    /// # Arguments
+    /// * `ray` - The ray with a normalized direction
+    /// * `t_max` - The end of the ray segment, e.g. the next surface intersection
+    /// * `sampler` - The sampler
+    ///
+    /// # Returns
+    /// * The scattering and the ray parameter of the collision (if any)
+    /// * The weight of each wavelength to multiply the throughput with, e.g. the albedo
+    pub fn sample_medium(
+        &self,
+        ray: &Ray,
+        t_max: Float,
+        sampler: Sampler,
+    ) -> (Option<(Scatterer<'_>, Float)>, Spectrum) {
+        // the free flight through overlapping media is the nearest one of each medium
+        let mut collision = None;
+        let mut t_max = t_max;
+
+        for medium in &self.media {
+            if let Some(t) = medium.sample_collision(ray, t_max, sampler) {
+                t_max = t;
+                collision = Some((medium, t));
+            }
+        }
+
+        // the atmosphere weights its free flight, which must end at the nearest grid collision
+        let (atmosphere, weight) = match &self.atmosphere {
+            Some(atmosphere) => atmosphere.sample_collision(ray, t_max, sampler),
+            None => (None, Spectrum::broadcast(1.0)),
+        };
+
+        match (atmosphere, collision) {
+            (Some((t, scattering)), _) => (Some((Scatterer::Atmosphere(scattering), t)), weight),
+            (None, Some((medium, t))) => {
+                (Some((Scatterer::Grid(medium), t)), weight * medium.albedo())
+            }
+            (None, None) => (None, weight),
+        }
+    }
+
+    /// Estimates the transmittance through all [media](GridMedium) and the
+    /// [atmosphere](Atmosphere) along the given ray.
+    ///
+    /// # Arguments
+    /// * `ray` - The ray with a normalized direction
+    /// * `t_max` - The end of the ray segment
+    /// * `sampler` - The sampler
+    ///
+    /// # Returns
+    /// * The transmittance of each wavelength in `[0, 1]`
+    pub fn transmittance(&self, ray: &Ray, t_max: Float, sampler: Sampler) -> Spectrum {
+        let grids: Float = self
+            .media
+            .iter()
+            .map(|m| m.transmittance(ray, t_max, sampler))
+            .product();
+
+        match &self.atmosphere {
+            Some(atmosphere) => atmosphere.transmittance(ray, t_max, sampler) * grids,
+            None => Spectrum::broadcast(grids),
         }
     }
 
@@ -118,6 +689,42 @@ impl Scene {
         self.intersects(ray)
     }
 
+    /// Tests the given rays for occlusion as a batch, e.g. all shadow rays of a tile.
+    ///
+    /// The rays get traced sorted by the octant of their direction and the Morton code of their
+    /// origin, such that similar rays traverse the same BVH nodes one after another.
+    ///
+    /// # Arguments
+    /// * `rays` - The rays to test
+    ///
+    /// # Returns
+    /// * Whether each ray is occluded, in the order of the given rays
+    pub fn occluded_batch(&self, rays: &[Ray]) -> Vec<bool> {
+        let min = self.bounding_box.min;
+        let size = self
+            .bounding_box
+            .size()
+            .max_by_component(Vector3::broadcast(Float::EPSILON));
+
+        let key = |ray: &Ray| {
+            let d = ray.direction;
+            let octant =
+                (((d.x < 0.0) as u64) << 2) | (((d.y < 0.0) as u64) << 1) | (d.z < 0.0) as u64;
+
+            (octant << 30) | morton_code((ray.origin - min) / size)
+        };
+
+        let mut order: Vec<usize> = (0..rays.len()).collect();
+        order.sort_by_cached_key(|&i| key(&rays[i]));
+
+        let mut occluded = vec![false; rays.len()];
+        for i in order {
+            occluded[i] = self.is_occluded(&rays[i]);
+        }
+
+        occluded
+    }
+
     /// Intersects the scene with the given ray.
     ///
     /// # Performance
@@ -129,17 +736,306 @@ impl Scene {
     /// # Returns
     /// * Whether the ray intersects
     pub fn intersects(&self, ray: &Ray) -> bool {
+        match &self.periodic {
+            Some(cell) => cell
+                .traverse(ray, |r| {
+                    if self.intersects_cell(r) {
+                        Some(())
+                    } else {
+                        None
+                    }
+                })
+                .is_some(),
+            None => self.intersects_cell(ray),
+        }
+    }
+
+    /// Tests the objects of the scene for intersection, ignoring the
+    /// [periodic cell](PeriodicCell).
+    fn intersects_cell(&self, ray: &Ray) -> bool {
         self.bvh.intersect(ray).iter().any(|s| s.intersects(ray))
     }
 }
 
+/// Interleaves the bits of the coordinates quantized to 10 bits each.
+///
+/// # Constraints
+/// * `point` - All values should be within `[0, 1]`, others get clamped.
+fn morton_code(point: Vector3) -> u64 {
+    fn spread(v: Float) -> u64 {
+        let mut v = (v.fast_clamp(0.0, 1.0) * 1023.0) as u64;
+        v = (v | (v << 16)) & 0x0300_00ff;
+        v = (v | (v << 8)) & 0x0300_f00f;
+        v = (v | (v << 4)) & 0x030c_30c3;
+        (v | (v << 2)) & 0x0924_9249
+    }
+
+    (spread(point.x) << 2) | (spread(point.y) << 1) | spread(point.z)
+}
+
 impl Default for Scene {
     fn default() -> Self {
         Self {
             bounding_box: Aabb::empty(),
+            ambient_light: AmbientLight::default(),
+            scale_hint: None,
+            epsilon: Float::big_epsilon(),
             emitters: Vec::default(),
+            light_tree: LightTree::default(),
             objects: Vec::default(),
+            media: Vec::default(),
+            atmosphere: None,
+            periodic: None,
             bvh: Tree::default(),
+            bvh_ids: None,
+            bvh_edits: 0,
+            light_groups: Vec::default(),
+            light_paths: Vec::default(),
+            aovs: Vec::default(),
         }
     }
 }
+
+#[test]
+fn light_groups_scale_shared_emitters() {
+    use crate::Spectrum;
+    use color::Color;
+
+    let mut scene: Scene = ron::from_str(
+        r#"(
+            bounding_box: (
+                min: (x: -1, y: -1, z: -1),
+                max: (x: 4, y: 1, z: 1),
+            ),
+            objects: [
+                Emitter((
+                    geometry: {"Sphere": (center: (x: 0, y: 0, z: 0), radius: 1)},
+                    emission: Constant(1),
+                    light_group: Some("key"),
+                )),
+                Emitter((
+                    geometry: {"Sphere": (center: (x: 3, y: 0, z: 0), radius: 1)},
+                    emission: Constant(1),
+                    light_group: Some("fill"),
+                )),
+            ],
+        )"#,
+    )
+    .unwrap();
+    scene.init();
+
+    // the emitters are shared by the objects, the BVH and the cached list
+    let mut multipliers = BTreeMap::new();
+    multipliers.insert("key".to_string(), 0.5);
+    scene.apply_light_groups(&multipliers);
+
+    assert_eq!(Some(0.5), scene.light_group_multiplier("key"));
+    assert_eq!(Some(1.0), scene.light_group_multiplier("fill"));
+    assert_eq!(None, scene.light_group_multiplier("rim"));
+
+    let key = &scene.emitters[0];
+    assert_eq!(Spectrum::broadcast(0.5).data, key.emission().data);
+    assert_eq!(0.5, key.emission_wavelength(0));
+}
+
+#[test]
+fn epsilon_scales_with_the_scene() {
+    let parse = |scale_hint: &str| -> Scene {
+        let mut scene: Scene = ron::from_str(&format!(
+            r#"(
+                bounding_box: (
+                    min: (x: -1, y: -1, z: -1),
+                    max: (x: 2000, y: 10, z: 10),
+                ),
+                {}
+                objects: [
+                    Receiver((
+                        geometry: {{"Sphere": (center: (x: 1000, y: 0, z: 0), radius: 10)}},
+                        bsdf: (bxdfs: []),
+                    )),
+                ],
+            )"#,
+            scale_hint
+        ))
+        .unwrap();
+        scene.init();
+
+        scene
+    };
+
+    let derived = parse("");
+    assert_eq!(derived.epsilon(), Float::big_epsilon() * 2000.0);
+
+    // small scenes keep the default epsilon
+    let hinted = parse("scale_hint: Some(0.5),");
+    assert_eq!(hinted.epsilon(), Float::big_epsilon());
+
+    let point = Vector3::new(1010.0, 0.0, 0.0);
+    let ray = derived.offset_ray_towards(point, Vector3::unit_x(), -Vector3::unit_x());
+    let offset = (ray.origin - point).mag();
+    assert!((offset - derived.epsilon()).abs() <= 1e-3 * derived.epsilon());
+    assert!(ray.origin.x < point.x);
+}
+
+#[test]
+fn add_and_remove_objects_after_init() {
+    let parse = |objects: &str| -> Scene {
+        ron::from_str(&format!(
+            r#"(
+                bounding_box: (
+                    min: (x: -1, y: -1, z: -1),
+                    max: (x: 1, y: 1, z: 1),
+                ),
+                objects: [{}],
+            )"#,
+            objects
+        ))
+        .unwrap()
+    };
+    let sphere = |x: i32| {
+        format!(
+            r#"Receiver((
+                geometry: {{"Sphere": (center: (x: {}, y: 0, z: 0), radius: 1)}},
+                bsdf: (bxdfs: []),
+            )),"#,
+            x
+        )
+    };
+    let emitter = r#"Emitter((
+        geometry: {"Sphere": (center: (x: 3, y: 0, z: 0), radius: 1)},
+        emission: Constant(1),
+    )),"#;
+
+    let mut scene = parse(&(sphere(0) + emitter));
+    scene.init();
+    let spheres = parse(&(6..30).map(sphere).collect::<String>());
+
+    let ray = |x| Ray::new_fast(Vector3::new(x as Float, 0.0, -5.0), Vector3::unit_z());
+    assert!(scene.intersect(&ray(6)).is_none());
+
+    // enough edits to trigger rebuilds of the BVH in between
+    for object in spheres.objects() {
+        scene.add(object.clone());
+    }
+    for x in [0, 3, 6, 29] {
+        let hit = scene.intersect(&ray(x)).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-3);
+    }
+
+    scene.remove(0);
+    assert!(scene.intersect(&ray(0)).is_none());
+    assert_eq!(1, scene.emitters.len());
+
+    scene.remove(0);
+    assert!(scene.intersect(&ray(3)).is_none());
+    assert!(scene.emitters.is_empty());
+    assert!(scene.intersect(&ray(29)).is_some());
+}
+
+#[test]
+fn find_objects_by_name_and_tag() {
+    let scene: Scene = ron::from_str(
+        r#"(
+            bounding_box: (
+                min: (x: -1, y: -1, z: -1),
+                max: (x: 1, y: 1, z: 1),
+            ),
+            objects: [
+                Receiver((
+                    geometry: {"Sphere": (center: (x: 0, y: 0, z: 0), radius: 1)},
+                    bsdf: (bxdfs: []),
+                    name: Some("ball"),
+                    tags: ["round"],
+                )),
+                Receiver((
+                    geometry: {"Aabb": (min: (x: 2, y: 0, z: 0), max: (x: 3, y: 1, z: 1))},
+                    bsdf: (bxdfs: []),
+                    tags: ["boxy", "round"],
+                )),
+                Emitter((
+                    geometry: {"Sphere": (center: (x: 0, y: 3, z: 0), radius: 1)},
+                    emission: Constant(1),
+                    name: Some("ball"),
+                    tags: ["light", "round"],
+                )),
+            ],
+        )"#,
+    )
+    .unwrap();
+
+    // the first object wins if names are not unique
+    assert_eq!(Some(0), scene.position("ball"));
+    assert!(matches!(scene.find("ball"), Some(SceneObject::Receiver(_))));
+    assert_eq!(None, scene.position("lamp"));
+    assert!(scene.find("lamp").is_none());
+
+    let round: Vec<_> = scene.tagged("round").map(SceneObject::name).collect();
+    assert_eq!(vec![Some("ball"), None, Some("ball")], round);
+    assert!(matches!(
+        scene.tagged("light").collect::<Vec<_>>()[..],
+        [SceneObject::Emitter(_)]
+    ));
+    assert_eq!(0, scene.tagged("missing").count());
+}
+
+#[test]
+fn objects_reference_named_materials() {
+    use crate::bxdf::Type;
+
+    let parse = |material: &str| -> Result<Scene, ron::Error> {
+        ron::from_str(&format!(
+            r#"(
+                bounding_box: (
+                    min: (x: -1, y: -1, z: -1),
+                    max: (x: 4, y: 1, z: 1),
+                ),
+                materials: {{
+                    "white": (bxdfs: [{{"LambertianReflection": (r: Constant(0.8))}}]),
+                    "mirror": (bxdfs: [{{"SpecularReflection": (r: Constant(1), fresnel: NoOp)}}]),
+                }},
+                objects: [
+                    Receiver((
+                        geometry: {{"Sphere": (center: (x: 0, y: 0, z: 0), radius: 1)}},
+                        material: Some("{}"),
+                    )),
+                    Receiver((
+                        geometry: {{"Sphere": (center: (x: 3, y: 0, z: 0), radius: 1)}},
+                        bsdf: (bxdfs: []),
+                    )),
+                    Emitter((
+                        geometry: {{"Sphere": (center: (x: 0, y: 3, z: 0), radius: 1)}},
+                        material: Some("white"),
+                        emission: Constant(1),
+                    )),
+                ],
+            )"#,
+            material
+        ))
+    };
+    let bsdf = |object: &SceneObject| match object {
+        SceneObject::Receiver(r) => r.bsdf.types(),
+        SceneObject::Emitter(e) => e.bsdf.types(),
+    };
+
+    let scene = parse("mirror").unwrap();
+    let objects = scene.objects();
+    assert_eq!(bsdf(&objects[0]), Type::REFLECTION | Type::SPECULAR);
+    assert_eq!(bsdf(&objects[1]), Type::empty());
+    assert_eq!(bsdf(&objects[2]), Type::REFLECTION | Type::DIFFUSE);
+
+    // serialized scenes contain the BSDFs inline
+    let serialized = ron::to_string(&scene).unwrap();
+    assert!(!serialized.contains("material:"));
+    let reparsed: Scene = ron::from_str(&serialized).unwrap();
+    assert_eq!(
+        bsdf(&reparsed.objects()[0]),
+        Type::REFLECTION | Type::SPECULAR
+    );
+
+    let error = parse("glass").err().unwrap();
+    assert!(
+        error.to_string().contains("unknown material: glass"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,77 @@
+use crate::{Float, Spectrum, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// An auxiliary output variable, written by integrators in addition to the beauty spectrum, e.g.
+/// as guides for external denoisers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Aov {
+    /// The world-space normal of the first visible surface
+    Normal,
+    /// The distance to the first visible surface
+    Depth,
+    /// The reflectance of the first visible surface
+    Albedo,
+    /// The light reaching the eye after at most one bounce
+    Direct,
+    /// The light reaching the eye after more than one bounce
+    Indirect,
+    /// The [label](crate::objects::SceneObject::label) of the first visible object
+    Segmentation,
+}
+
+impl Aov {
+    /// Returns the name of this AOV, e.g. to suffix file names with.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Albedo => "albedo",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::Segmentation => "segmentation",
+        }
+    }
+
+    /// Returns the number of channels of this AOV.
+    pub fn channels(&self) -> usize {
+        match self {
+            Aov::Normal => 3,
+            Aov::Depth | Aov::Segmentation => 1,
+            Aov::Albedo | Aov::Direct | Aov::Indirect => Spectrum::size(),
+        }
+    }
+
+    /// Returns whether the samples of this AOV get averaged. Otherwise, the first sample of each
+    /// pixel is kept, as e.g. averaging labels at object edges would mix them up.
+    pub fn is_averaged(&self) -> bool {
+        !matches!(self, Aov::Segmentation)
+    }
+}
+
+/// The auxiliary values of a single sample, gathered by an integrator.
+#[derive(Copy, Clone, Default)]
+pub struct AovSample {
+    pub normal: Vector3,
+    pub depth: Float,
+    pub albedo: Spectrum,
+    pub direct: Spectrum,
+    pub indirect: Spectrum,
+    pub label: u32,
+}
+
+impl AovSample {
+    /// Returns the value of the given channel of the given AOV.
+    ///
+    /// # Constraints
+    /// * `channel` - Should be less than the [channels](Aov::channels) of the AOV.
+    pub fn value(&self, aov: Aov, channel: usize) -> Float {
+        match aov {
+            Aov::Normal => [self.normal.x, self.normal.y, self.normal.z][channel],
+            Aov::Depth => self.depth,
+            Aov::Albedo => self.albedo[channel],
+            Aov::Direct => self.direct[channel],
+            Aov::Indirect => self.indirect[channel],
+            Aov::Segmentation => self.label as Float,
+        }
+    }
+}
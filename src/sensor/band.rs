@@ -0,0 +1,90 @@
+use crate::{Float, Spectrum};
+use serde::{Deserialize, Serialize};
+use utility::floats::FloatExt;
+
+/// A narrow-band filter with a gaussian spectral response, as used in multispectral imaging.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpectralBand {
+    /// The name of the band, used for the output file.
+    pub name: String,
+    /// The center wavelength in `μm`.
+    pub center: Float,
+    /// The full width at half maximum in `μm`.
+    pub width: Float,
+}
+
+impl SpectralBand {
+    /// Returns the response of this band at the given wavelength.
+    ///
+    /// # Arguments
+    /// * `lambda` - The wavelength in `μm`
+    ///
+    /// # Returns
+    /// * The response in `[0, 1]`
+    pub fn response(&self, lambda: Float) -> Float {
+        // FWHM = 2 * sqrt(2 * ln(2)) * sigma
+        let sigma = self.width / 2.354_82;
+        let t = (lambda - self.center) / sigma;
+
+        (-0.5 * t * t).exp()
+    }
+
+    /// Integrates the given spectrum against the response of this band.
+    ///
+    /// # Arguments
+    /// * `spectrum` - The spectrum to integrate
+    ///
+    /// # Returns
+    /// * The response-weighted average intensity (`0` if the band lies outside the spectrum)
+    pub fn integrate(&self, spectrum: &Spectrum) -> Float {
+        let mut weighted = 0.0;
+        let mut responses = 0.0;
+
+        for i in 0..Spectrum::size() {
+            let response = self.response(Spectrum::lambda_of_index(i));
+            weighted += spectrum[i] * response;
+            responses += response;
+        }
+
+        if responses.is_approx_zero() {
+            0.0
+        } else {
+            weighted / responses
+        }
+    }
+}
+
+#[test]
+fn bands_average_their_response() {
+    let band = SpectralBand {
+        name: "green".to_string(),
+        center: 0.55,
+        width: 0.02,
+    };
+
+    assert_eq!(band.response(0.55), 1.0);
+    assert!((band.response(0.56) - 0.5).abs() < 1e-4);
+    assert!((band.response(0.54) - 0.5).abs() < 1e-4);
+
+    // a flat spectrum keeps its intensity
+    assert!((band.integrate(&Spectrum::new([0.3; 36])) - 0.3).abs() < 1e-5);
+
+    // only the wavelengths near the center contribute, 540nm and 560nm by half
+    let mut spectrum = Spectrum::new([0.0; 36]);
+    spectrum[17] = 1.0;
+    let center = band.integrate(&spectrum);
+    assert!(center > 0.4);
+    spectrum[17] = 0.0;
+    spectrum[16] = 1.0;
+    assert!((band.integrate(&spectrum) - 0.5 * center).abs() < 1e-3);
+    spectrum[16] = 0.0;
+    spectrum[30] = 1.0;
+    assert!(band.integrate(&spectrum) < 1e-6);
+
+    let outside = SpectralBand {
+        name: "infrared".to_string(),
+        center: 2.0,
+        width: 0.01,
+    };
+    assert_eq!(outside.integrate(&Spectrum::new([1.0; 36])), 0.0);
+}
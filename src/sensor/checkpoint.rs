@@ -0,0 +1,141 @@
+use crate::sensor::bounds::UBounds2;
+use crate::sensor::pixel::Pixel;
+use crate::sensor::sensor_tile::SensorTile;
+use crate::{Float, Spectrum};
+use color::IntSpectrum;
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use ultraviolet::UVec2;
+
+/// The accumulated data of a sensor tile, flattened for a compact binary representation.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TileCheckpoint {
+    pub bounds: UBounds2,
+    pub averages: Vec<Float>,
+    pub samples: Vec<u32>,
+    /// The light group averages of all pixels, flattened
+    pub light_groups: Vec<Float>,
+    /// The light path expression averages of all pixels, flattened
+    pub light_paths: Vec<Float>,
+    /// The auxiliary output averages of each pixel with their number of samples
+    pub aovs: Vec<(Vec<Float>, u32)>,
+    /// The gradient averages of each pixel, flattened, with their number of samples
+    pub gradients: Vec<(Vec<Float>, u32)>,
+}
+
+impl TileCheckpoint {
+    pub fn new(tile: &SensorTile) -> Self {
+        let len = tile.pixels.len() * Spectrum::size();
+        let mut averages = Vec::with_capacity(len);
+        let mut samples = Vec::with_capacity(len);
+        let mut light_groups = Vec::new();
+        let mut light_paths = Vec::new();
+
+        for px in &tile.pixels {
+            averages.extend_from_slice(&px.average.data);
+            samples.extend_from_slice(&px.samples()[..]);
+            light_groups.extend(px.light_groups.iter().flat_map(|s| s.data.iter()));
+            light_paths.extend(px.light_paths.iter().flat_map(|s| s.data.iter()));
+        }
+        let aovs = tile
+            .pixels
+            .iter()
+            .map(|px| (px.aovs.clone(), px.aov_samples()))
+            .collect();
+        let gradients = tile
+            .pixels
+            .iter()
+            .map(|px| {
+                let gradients = px.gradients.iter().flat_map(|s| s.data.iter()).copied();
+                (gradients.collect(), px.gradient_samples())
+            })
+            .collect();
+
+        Self {
+            bounds: tile.bounds,
+            averages,
+            samples,
+            light_groups,
+            light_paths,
+            aovs,
+            gradients,
+        }
+    }
+
+    /// Returns whether this checkpoint matches the given tile, i.e. has the same bounds and
+    /// buffer sizes.
+    ///
+    /// # Arguments
+    /// * `tile` - The tile
+    ///
+    /// # Returns
+    /// * Whether the checkpoint can restore the tile
+    pub fn matches(&self, tile: &SensorTile) -> bool {
+        let len = tile.pixels.len() * Spectrum::size();
+        let buffers = |buffer: fn(&Pixel) -> usize| {
+            tile.pixels.iter().map(buffer).sum::<usize>() * Spectrum::size()
+        };
+
+        self.bounds.min == tile.bounds.min
+            && self.bounds.max == tile.bounds.max
+            && self.averages.len() == len
+            && self.samples.len() == len
+            && self.light_groups.len() == buffers(|px| px.light_groups.len())
+            && self.light_paths.len() == buffers(|px| px.light_paths.len())
+            && self.aovs.len() == tile.pixels.len()
+            && self.gradients.len() == tile.pixels.len()
+            && self
+                .gradients
+                .iter()
+                .all(|(g, _)| g.is_empty() || g.len() == 2 * Spectrum::size())
+    }
+
+    /// Restores the given tile from this checkpoint.
+    ///
+    /// # Constraints
+    /// * The checkpoint should [match](TileCheckpoint::matches) the tile.
+    ///
+    /// # Arguments
+    /// * `tile` - The tile to restore
+    pub fn restore(&self, tile: &mut SensorTile) {
+        debug_assert!(self.matches(tile));
+
+        let size = Spectrum::size();
+        let mut light_groups = self.light_groups.chunks_exact(size);
+        let mut light_paths = self.light_paths.chunks_exact(size);
+
+        for (i, px) in tile.pixels.iter_mut().enumerate() {
+            let range = i * size..(i + 1) * size;
+            let average = Spectrum::new(self.averages[range.clone()].try_into().unwrap());
+            let samples = IntSpectrum::new(self.samples[range].try_into().unwrap());
+            px.restore(average, samples);
+
+            let (aovs, aov_samples) = &self.aovs[i];
+            px.restore_aovs(aovs.clone(), *aov_samples);
+
+            let (gradients, gradient_samples) = &self.gradients[i];
+            let gradients = gradients.chunks_exact(size);
+            let gradients = gradients.map(|g| Spectrum::new(g.try_into().unwrap()));
+            px.restore_gradients(gradients.collect(), *gradient_samples);
+
+            let buffers = px.light_groups.iter_mut().zip(&mut light_groups);
+            for (buffer, data) in buffers.chain(px.light_paths.iter_mut().zip(&mut light_paths)) {
+                *buffer = Spectrum::new(data.try_into().unwrap());
+            }
+        }
+    }
+}
+
+/// A checkpoint of the whole sensor, allowing to resume long renders.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SensorCheckpoint {
+    pub resolution: UVec2,
+    pub bounds: UBounds2,
+    pub block_size: UVec2,
+    /// The number of tile passes rendered so far (`0` for partial checkpoints)
+    pub progress: usize,
+    pub tiles: Vec<TileCheckpoint>,
+    /// The sums of the light splats of all pixels, flattened (empty if nothing got splatted).
+    /// Partial checkpoints contain the splats of their tiles onto any pixel.
+    pub light_splats: Vec<Float>,
+}
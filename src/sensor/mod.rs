@@ -1,6 +1,9 @@
 use ultraviolet::UVec2;
 
+pub mod aov;
+pub mod band;
 pub mod bounds;
+pub mod checkpoint;
 pub mod pixel;
 pub mod sensor_tile;
 
@@ -15,10 +18,18 @@ pub fn index_to_vec2(index: usize, height: usize) -> UVec2 {
     UVec2::new(x as u32, y as u32)
 }
 
+use crate::filters::Filter;
 use crate::sensor::bounds::UBounds2;
-use crate::sensor::sensor_tile::SensorTile;
-use crate::Float;
+use crate::sensor::checkpoint::{SensorCheckpoint, TileCheckpoint};
+use crate::sensor::pixel::Pixel;
+use crate::sensor::sensor_tile::{SensorTile, Splat};
+use crate::{Float, Spectrum, Vector2};
 use parking_lot::Mutex;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::mem::size_of;
+use std::ops::Range;
 
 pub struct Sensor {
     pub resolution: UVec2,
@@ -26,28 +37,74 @@ pub struct Sensor {
     pub filename: Option<String>,
     pub bounds: UBounds2,
     pub block_size: UVec2,
+    /// The number of light group buffers per pixel.
+    pub light_groups: usize,
+    /// The number of light path expression buffers per pixel.
+    pub light_paths: usize,
+    /// The number of pixels the tiles extend beyond their bounds to accumulate filter splats.
+    pub apron: UVec2,
     pub tiles: Vec<Mutex<SensorTile>>,
+    /// The sums of the spectra splatted onto arbitrary pixels, e.g. by light tracing (row-major,
+    /// relative to `bounds.min`). Allocated with the first splat.
+    light_splats: Mutex<Vec<Spectrum>>,
 }
 
 impl Sensor {
+    /// Estimates the memory used by the pixels and splats of all tiles and the light splats.
+    ///
+    /// # Returns
+    /// * The estimated number of bytes
+    pub fn memory_usage(&self) -> usize {
+        let pixel =
+            size_of::<Pixel>() + (self.light_groups + self.light_paths) * size_of::<Spectrum>();
+
+        self.tiles
+            .iter()
+            .map(|t| {
+                let t = t.lock();
+                t.pixels.len() * pixel + t.splats.len() * size_of::<Splat>()
+            })
+            .sum::<usize>()
+            + self.light_splats.lock().len() * size_of::<Spectrum>()
+    }
+
     pub fn new(
         resolution: UVec2,
         // filter: Box<dyn Filter>,
         filename: Option<String>,
         bounds: UBounds2,
         block_size: UVec2,
+        light_groups: usize,
+        light_paths: usize,
+        filter_radius: Vector2,
     ) -> Self {
+        // the pixels get splatted from their centers, reaching the pixels within the radius
+        let apron = UVec2::new(
+            filter_radius.x.max(0.0).floor() as u32,
+            filter_radius.y.max(0.0).floor() as u32,
+        );
+
         Self {
             resolution,
             // filter,
             filename,
             bounds,
             block_size,
-            tiles: Self::create_tiles(bounds, block_size),
+            light_groups,
+            light_paths,
+            apron,
+            tiles: Self::create_tiles(bounds, block_size, light_groups, light_paths, apron),
+            light_splats: Mutex::new(Vec::new()),
         }
     }
 
-    fn create_tiles(bounds: UBounds2, block_size: UVec2) -> Vec<Mutex<SensorTile>> {
+    fn create_tiles(
+        bounds: UBounds2,
+        block_size: UVec2,
+        light_groups: usize,
+        light_paths: usize,
+        apron: UVec2,
+    ) -> Vec<Mutex<SensorTile>> {
         let range = bounds.to_range();
         let width = range.x;
         let height = range.y;
@@ -64,8 +121,19 @@ impl Sensor {
                     width.min(min.x + block_size.x),
                     height.min(min.y + block_size.y),
                 );
-                let bounds = UBounds2::new(min + bounds.min, max + bounds.min);
-                let tile = SensorTile::new(bounds);
+                let tile_bounds = UBounds2::new(min + bounds.min, max + bounds.min);
+                let apron_bounds = if apron == UVec2::broadcast(0) {
+                    None
+                } else {
+                    let apron_min = UVec2::new(
+                        tile_bounds.min.x.saturating_sub(apron.x).max(bounds.min.x),
+                        tile_bounds.min.y.saturating_sub(apron.y).max(bounds.min.y),
+                    );
+                    let apron_max = (tile_bounds.max + apron).min_by_component(bounds.max);
+
+                    Some(UBounds2::new(apron_min, apron_max))
+                };
+                let tile = SensorTile::new(tile_bounds, apron_bounds, light_groups, light_paths);
 
                 sensor_tiles.push(Mutex::new(tile));
             }
@@ -77,4 +145,655 @@ impl Sensor {
     pub fn num_tiles(&self) -> usize {
         self.tiles.len()
     }
+
+    /// Discards the accumulated data of all pixels, e.g. after changing the scene.
+    pub fn clear(&self) {
+        for tile in &self.tiles {
+            let mut tile = tile.lock();
+            for px in &mut tile.pixels {
+                *px = Pixel::new(px.position, px.light_groups.len(), px.light_paths.len());
+            }
+            tile.splats.iter_mut().for_each(|s| *s = Splat::default());
+        }
+    }
+
+    /// [Splats](SensorTile::splat_pixels) the pixels of all tiles with the given filter and merges
+    /// the splats, including their overlapping aprons.
+    ///
+    /// # Arguments
+    /// * `filter` - The pixel filter
+    ///
+    /// # Returns
+    /// * The filtered spectra of all pixels inside the sensor bounds (row-major, relative to
+    ///   `bounds.min`), or `None` if filter splatting is disabled
+    pub fn resolve_splats(&self, filter: &dyn Filter) -> Option<Vec<Spectrum>> {
+        if self.apron == UVec2::broadcast(0) {
+            return None;
+        }
+
+        let width = self.bounds.to_range().x as usize;
+        let mut merged = vec![Splat::default(); self.bounds.area()];
+
+        for tile in &self.tiles {
+            let mut tile = tile.lock();
+            tile.splat_pixels(filter);
+
+            let apron = tile.apron_bounds;
+            let apron_width = apron.to_range().x as usize;
+
+            for y in apron.min.y..apron.max.y {
+                for x in apron.min.x..apron.max.x {
+                    let local =
+                        (x - apron.min.x) as usize + (y - apron.min.y) as usize * apron_width;
+                    let global =
+                        (x - self.bounds.min.x) as usize + (y - self.bounds.min.y) as usize * width;
+
+                    merged[global].merge(&tile.splats[local]);
+                }
+            }
+        }
+
+        Some(merged.iter().map(Splat::resolve).collect())
+    }
+
+    /// Moves the pending [splats](Pixel::splat) of the pixels of the given tile onto the sensor.
+    /// Splats outside the sensor bounds get discarded.
+    ///
+    /// # Arguments
+    /// * `tile` - The tile whose pixels got integrated
+    pub fn add_light_splats(&self, tile: &mut SensorTile) {
+        let width = self.bounds.to_range().x as usize;
+        let mut light_splats = None;
+
+        for px in &mut tile.pixels {
+            for (position, spectrum) in px.take_splats() {
+                if position.x < self.bounds.min.x as Float
+                    || position.y < self.bounds.min.y as Float
+                    || position.x >= self.bounds.max.x as Float
+                    || position.y >= self.bounds.max.y as Float
+                {
+                    continue;
+                }
+
+                let buffer = light_splats.get_or_insert_with(|| self.light_splats.lock());
+                if buffer.is_empty() {
+                    buffer.resize(self.bounds.area(), Spectrum::default());
+                }
+
+                let x = position.x as usize - self.bounds.min.x as usize;
+                let y = position.y as usize - self.bounds.min.y as usize;
+                buffer[x + y * width] += spectrum;
+            }
+        }
+    }
+
+    /// Normalizes the [light splats](Sensor::add_light_splats) by the number of samples taken
+    /// over all pixels, as each sample may splat onto any pixel.
+    ///
+    /// # Returns
+    /// * The light splats of all pixels inside the sensor bounds (row-major, relative to
+    ///   `bounds.min`), or `None` if nothing got splatted
+    pub fn resolve_light_splats(&self) -> Option<Vec<Spectrum>> {
+        // count before locking the splats, as renderers lock them while holding a tile
+        let samples: u64 = self
+            .tiles
+            .iter()
+            .map(|t| {
+                t.lock()
+                    .pixels
+                    .iter()
+                    .map(|px| px.samples()[0] as u64)
+                    .sum::<u64>()
+            })
+            .sum();
+        let samples = samples.max(1) as Float;
+
+        let light_splats = self.light_splats.lock();
+        if light_splats.is_empty() {
+            return None;
+        }
+
+        Some(light_splats.iter().map(|s| *s / samples).collect())
+    }
+
+    /// Reprojects the accumulated data onto a moved camera: pixels get replaced by the data of
+    /// their previous pixel (if any) and reset otherwise. Splats cannot be reprojected and get
+    /// cleared.
+    ///
+    /// # Constraints
+    /// * `previous` - Should return positions inside the sensor bounds.
+    ///
+    /// # Arguments
+    /// * `previous` - Returns the position of the previous pixel of a pixel, or `None` if it
+    ///   cannot be reused
+    ///
+    /// # Returns
+    /// * The number of reused pixels
+    pub fn reproject(&self, previous: impl Fn(UVec2) -> Option<UVec2>) -> usize {
+        let width = self.bounds.to_range().x as usize;
+        let index = |p: UVec2| {
+            (p.x - self.bounds.min.x) as usize + (p.y - self.bounds.min.y) as usize * width
+        };
+
+        let mut pixels = vec![None; self.bounds.area()];
+        for tile in &self.tiles {
+            for px in &tile.lock().pixels {
+                pixels[index(px.position)] = Some(px.clone());
+            }
+        }
+
+        let mut reused = 0;
+        for tile in &self.tiles {
+            let mut tile = tile.lock();
+
+            for px in &mut tile.pixels {
+                let position = px.position;
+                let pixel = previous(position).and_then(|p| {
+                    debug_assert!(p.x < self.bounds.max.x && p.y < self.bounds.max.y);
+                    pixels[index(p)].clone()
+                });
+
+                *px = match pixel {
+                    Some(mut pixel) => {
+                        reused += 1;
+                        pixel.position = position;
+                        pixel.take_splats();
+                        pixel
+                    }
+                    None => Pixel::new(position, self.light_groups, self.light_paths),
+                };
+            }
+
+            tile.splats.iter_mut().for_each(|s| *s = Splat::default());
+        }
+
+        self.light_splats.lock().clear();
+
+        reused
+    }
+
+    /// Creates a checkpoint of the currently accumulated data.
+    ///
+    /// # Arguments
+    /// * `progress` - The number of tile passes rendered so far
+    ///
+    /// # Returns
+    /// * The checkpoint
+    pub fn checkpoint(&self, progress: usize) -> SensorCheckpoint {
+        let tiles = self
+            .tiles
+            .iter()
+            .map(|t| TileCheckpoint::new(&t.lock()))
+            .collect();
+
+        SensorCheckpoint {
+            resolution: self.resolution,
+            bounds: self.bounds,
+            block_size: self.block_size,
+            progress,
+            tiles,
+            light_splats: self.flat_light_splats(),
+        }
+    }
+
+    /// Creates a checkpoint of the tiles in the given range only.
+    ///
+    /// # Arguments
+    /// * `range` - The indices of the tiles
+    ///
+    /// # Returns
+    /// * The partial checkpoint
+    pub fn partial_checkpoint(&self, range: Range<usize>) -> SensorCheckpoint {
+        let tiles = self.tiles[range]
+            .iter()
+            .map(|t| TileCheckpoint::new(&t.lock()))
+            .collect();
+
+        SensorCheckpoint {
+            resolution: self.resolution,
+            bounds: self.bounds,
+            block_size: self.block_size,
+            progress: 0,
+            tiles,
+            light_splats: self.flat_light_splats(),
+        }
+    }
+
+    fn flat_light_splats(&self) -> Vec<Float> {
+        self.light_splats
+            .lock()
+            .iter()
+            .flat_map(|s| s.data.iter().copied())
+            .collect()
+    }
+
+    /// Returns whether the given checkpoint was taken of a sensor with the same layout.
+    fn matches(&self, checkpoint: &SensorCheckpoint) -> bool {
+        let light_splats = checkpoint.light_splats.len();
+
+        checkpoint.resolution == self.resolution
+            && checkpoint.block_size == self.block_size
+            && checkpoint.bounds.min == self.bounds.min
+            && checkpoint.bounds.max == self.bounds.max
+            && (light_splats == 0 || light_splats == self.bounds.area() * Spectrum::size())
+    }
+
+    /// Adds the light splats of the given checkpoint, or replaces the current ones.
+    fn restore_light_splats(&self, checkpoint: &SensorCheckpoint, add: bool) {
+        let mut light_splats = self.light_splats.lock();
+        if !add {
+            light_splats.clear();
+        }
+        if checkpoint.light_splats.is_empty() {
+            return;
+        }
+
+        light_splats.resize(self.bounds.area(), Spectrum::default());
+        let splats = checkpoint.light_splats.chunks_exact(Spectrum::size());
+        for (splat, data) in light_splats.iter_mut().zip(splats) {
+            *splat += Spectrum::new(data.try_into().unwrap());
+        }
+    }
+
+    /// Restores the tiles contained in the given (partial) checkpoint, leaving the other tiles
+    /// untouched, and adds its light splats. The tiles get matched by their bounds. Nothing gets
+    /// restored unless all tiles of the checkpoint match.
+    ///
+    /// # Arguments
+    /// * `checkpoint` - The checkpoint
+    ///
+    /// # Returns
+    /// * Whether the checkpoint matches this sensor
+    pub fn merge(&self, checkpoint: &SensorCheckpoint) -> bool {
+        if !self.matches(checkpoint) {
+            return false;
+        }
+
+        let tiles: Option<Vec<_>> = checkpoint
+            .tiles
+            .iter()
+            .map(|c| self.tiles.iter().find(|t| c.matches(&t.lock())))
+            .collect();
+
+        match tiles {
+            Some(tiles) => {
+                for (c, t) in checkpoint.tiles.iter().zip(tiles) {
+                    c.restore(&mut t.lock());
+                }
+                self.restore_light_splats(checkpoint, true);
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Restores the accumulated data from the given checkpoint. Nothing gets restored unless all
+    /// tiles match.
+    ///
+    /// # Arguments
+    /// * `checkpoint` - The checkpoint
+    ///
+    /// # Returns
+    /// * Whether the checkpoint matches this sensor
+    pub fn restore(&self, checkpoint: &SensorCheckpoint) -> bool {
+        if !self.matches(checkpoint)
+            || checkpoint.tiles.len() != self.tiles.len()
+            || !checkpoint
+                .tiles
+                .iter()
+                .zip(&self.tiles)
+                .all(|(c, t)| c.matches(&t.lock()))
+        {
+            return false;
+        }
+
+        for (c, t) in checkpoint.tiles.iter().zip(&self.tiles) {
+            c.restore(&mut t.lock());
+        }
+        self.restore_light_splats(checkpoint, false);
+
+        true
+    }
+
+    /// Writes a checkpoint of the currently accumulated data to the given path in a compact binary
+    /// format.
+    ///
+    /// # Arguments
+    /// * `path` - The file path
+    /// * `progress` - The number of tile passes rendered so far
+    ///
+    /// # Returns
+    /// * Whether writing succeeded
+    pub fn save_checkpoint(&self, path: &str, progress: usize) -> bincode::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, &self.checkpoint(progress))
+    }
+
+    /// Restores the accumulated data from the checkpoint at the given path.
+    ///
+    /// # Arguments
+    /// * `path` - The file path
+    ///
+    /// # Returns
+    /// * The number of tile passes rendered before the checkpoint, or an error if reading failed
+    ///   or the checkpoint does not match this sensor
+    pub fn load_checkpoint(&self, path: &str) -> bincode::Result<usize> {
+        let checkpoint = Self::read_checkpoint(path)?;
+
+        if self.restore(&checkpoint) {
+            Ok(checkpoint.progress)
+        } else {
+            Err(Box::new(bincode::ErrorKind::Custom(
+                "Checkpoint does not match the sensor".to_string(),
+            )))
+        }
+    }
+
+    /// Writes a [partial checkpoint](Sensor::partial_checkpoint) of the tiles in the given range
+    /// to the given path.
+    ///
+    /// # Arguments
+    /// * `path` - The file path
+    /// * `range` - The indices of the tiles
+    ///
+    /// # Returns
+    /// * Whether writing succeeded
+    pub fn save_partial(&self, path: &str, range: Range<usize>) -> bincode::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, &self.partial_checkpoint(range))
+    }
+
+    /// [Merges](Sensor::merge) the partial checkpoint at the given path into this sensor.
+    ///
+    /// # Arguments
+    /// * `path` - The file path
+    ///
+    /// # Returns
+    /// * Whether reading succeeded and the checkpoint matches this sensor
+    pub fn load_partial(&self, path: &str) -> bincode::Result<()> {
+        let checkpoint = Self::read_checkpoint(path)?;
+
+        if self.merge(&checkpoint) {
+            Ok(())
+        } else {
+            Err(Box::new(bincode::ErrorKind::Custom(
+                "Partial checkpoint does not match the sensor".to_string(),
+            )))
+        }
+    }
+
+    fn read_checkpoint(path: &str) -> bincode::Result<SensorCheckpoint> {
+        let reader = BufReader::new(File::open(path)?);
+        bincode::deserialize_from(reader)
+    }
+}
+
+#[test]
+fn checkpoint_round_trip() {
+    use crate::Spectrum;
+    use color::Color;
+
+    let resolution = UVec2::new(8, 6);
+    let bounds = UBounds2::new(UVec2::zero(), resolution);
+    let sensor =
+        |block_size| Sensor::new(resolution, None, bounds, block_size, 0, 0, Vector2::zero());
+
+    let original = sensor(UVec2::new(4, 4));
+    for tile in &original.tiles {
+        for px in &mut tile.lock().pixels {
+            px.add(Spectrum::broadcast(
+                (px.position.x + 8 * px.position.y) as Float,
+            ));
+            px.add(Spectrum::broadcast(0.5));
+        }
+    }
+
+    let path = std::env::temp_dir().join(format!("rust-v-checkpoint-{}.bin", std::process::id()));
+    let path = path.to_str().unwrap();
+    original.save_checkpoint(path, 5).unwrap();
+
+    let restored = sensor(UVec2::new(4, 4));
+    let loaded = restored.load_checkpoint(path);
+    let mismatch = sensor(UVec2::new(8, 8)).load_checkpoint(path);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(5, loaded.unwrap());
+    assert!(mismatch.is_err());
+
+    for (a, b) in original.tiles.iter().zip(&restored.tiles) {
+        for (a, b) in a.lock().pixels.iter().zip(&b.lock().pixels) {
+            assert_eq!(a.average.data, b.average.data);
+            assert_eq!(a.samples(), b.samples());
+        }
+    }
+
+    // a single corrupt tile rejects the whole checkpoint
+    let mut corrupt = original.checkpoint(5);
+    corrupt.tiles.last_mut().unwrap().averages.pop();
+    let untouched = sensor(UVec2::new(4, 4));
+    assert!(!untouched.restore(&corrupt));
+    assert!(!untouched.merge(&corrupt));
+    assert!(untouched
+        .tiles
+        .iter()
+        .all(|t| t.lock().pixels.iter().all(|px| px.samples()[0] == 0)));
+}
+
+#[test]
+fn partial_checkpoints_merge_all_buffers() {
+    use color::Color;
+
+    let resolution = UVec2::new(8, 6);
+    let bounds = UBounds2::new(UVec2::zero(), resolution);
+    let sensor = || {
+        Sensor::new(
+            resolution,
+            None,
+            bounds,
+            UVec2::new(4, 4),
+            2,
+            1,
+            Vector2::zero(),
+        )
+    };
+
+    let original = sensor();
+    for tile in &original.tiles {
+        for px in &mut tile.lock().pixels {
+            let value = (px.position.x + 8 * px.position.y) as Float;
+            let groups = [Spectrum::broadcast(value), Spectrum::broadcast(0.5)];
+            let paths = [Spectrum::broadcast(2.0 * value)];
+
+            px.add_with_aovs(Spectrum::broadcast(value + 0.5), &groups, &paths);
+        }
+    }
+
+    // e.g. written by two processes
+    let parts = [0..2, 2..original.num_tiles()];
+    let merged = sensor();
+    for range in parts.iter().cloned() {
+        let bytes = bincode::serialize(&original.partial_checkpoint(range)).unwrap();
+        assert!(merged.merge(&bincode::deserialize(&bytes).unwrap()));
+    }
+
+    for (a, b) in original.tiles.iter().zip(&merged.tiles) {
+        for (a, b) in a.lock().pixels.iter().zip(&b.lock().pixels) {
+            assert_eq!(a.average.data, b.average.data);
+            assert_eq!(a.samples(), b.samples());
+            assert_eq!(a.light_groups[0].data, b.light_groups[0].data);
+            assert_eq!(a.light_groups[1].data, b.light_groups[1].data);
+            assert_eq!(a.light_paths[0].data, b.light_paths[0].data);
+        }
+    }
+
+    // the buffers have to match as well
+    let without_groups = Sensor::new(
+        resolution,
+        None,
+        bounds,
+        UVec2::new(4, 4),
+        0,
+        1,
+        Vector2::zero(),
+    );
+    assert!(!without_groups.merge(&original.partial_checkpoint(0..2)));
+}
+
+#[test]
+fn partial_checkpoints_sum_light_splats() {
+    use color::Color;
+
+    let resolution = UVec2::new(8, 6);
+    let bounds = UBounds2::new(UVec2::zero(), resolution);
+    let sensor = || {
+        Sensor::new(
+            resolution,
+            None,
+            bounds,
+            UVec2::new(4, 4),
+            0,
+            0,
+            Vector2::zero(),
+        )
+    };
+    // renders the given tiles, each pixel splatting onto its mirrored pixel
+    let render = |sensor: &Sensor, range: Range<usize>| {
+        for tile in &sensor.tiles[range] {
+            let mut tile = tile.lock();
+            for px in &mut tile.pixels {
+                let mirrored = Vector2::new(
+                    (7 - px.position.x) as Float + 0.5,
+                    (5 - px.position.y) as Float + 0.5,
+                );
+                px.add(Spectrum::broadcast(1.0));
+                px.splat(mirrored, Spectrum::broadcast(px.position.x as Float));
+            }
+            sensor.add_light_splats(&mut tile);
+        }
+    };
+
+    let full = sensor();
+    render(&full, 0..full.num_tiles());
+
+    let first = sensor();
+    render(&first, 0..2);
+    let second = sensor();
+    render(&second, 2..second.num_tiles());
+
+    let merged = sensor();
+    assert!(merged.merge(&first.partial_checkpoint(0..2)));
+    assert!(merged.merge(&second.partial_checkpoint(2..second.num_tiles())));
+
+    let expected = full.resolve_light_splats().unwrap();
+    let actual = merged.resolve_light_splats().unwrap();
+    for (e, a) in expected.iter().zip(&actual) {
+        assert_eq!(e.data, a.data);
+    }
+
+    // restoring a full checkpoint replaces the splats instead
+    assert!(merged.restore(&full.checkpoint(0)));
+    let restored = merged.resolve_light_splats().unwrap();
+    for (e, a) in expected.iter().zip(&restored) {
+        assert_eq!(e.data, a.data);
+    }
+}
+
+#[test]
+fn checkpoints_keep_aovs() {
+    use crate::sensor::aov::{Aov, AovSample};
+    use crate::Vector3;
+
+    let resolution = UVec2::new(8, 6);
+    let bounds = UBounds2::new(UVec2::zero(), resolution);
+    let sensor = || {
+        Sensor::new(
+            resolution,
+            None,
+            bounds,
+            UVec2::new(4, 4),
+            0,
+            0,
+            Vector2::zero(),
+        )
+    };
+    let aovs = [Aov::Normal, Aov::Depth];
+    let sample = |depth: Float| AovSample {
+        normal: Vector3::unit_y(),
+        depth,
+        ..Default::default()
+    };
+
+    let original = sensor();
+    for tile in &original.tiles {
+        for px in &mut tile.lock().pixels {
+            px.add_aovs(&aovs, &sample(px.position.x as Float));
+            px.add_aovs(&aovs, &sample(1.0));
+        }
+    }
+
+    let merged = sensor();
+    let bytes = bincode::serialize(&original.partial_checkpoint(0..original.num_tiles())).unwrap();
+    assert!(merged.merge(&bincode::deserialize(&bytes).unwrap()));
+
+    for (a, b) in original.tiles.iter().zip(&merged.tiles) {
+        let (mut a, mut b) = (a.lock(), b.lock());
+        for (a, b) in a.pixels.iter_mut().zip(&mut b.pixels) {
+            assert_eq!(a.aovs, b.aovs);
+
+            // the restored number of samples keeps weighting further samples
+            a.add_aovs(&aovs, &sample(4.0));
+            b.add_aovs(&aovs, &sample(4.0));
+            assert_eq!(a.aovs, b.aovs);
+        }
+    }
+}
+
+#[test]
+fn checkpoints_keep_gradients() {
+    use color::Color;
+
+    let resolution = UVec2::new(8, 6);
+    let bounds = UBounds2::new(UVec2::zero(), resolution);
+    let sensor = || {
+        Sensor::new(
+            resolution,
+            None,
+            bounds,
+            UVec2::new(4, 4),
+            0,
+            0,
+            Vector2::zero(),
+        )
+    };
+
+    let original = sensor();
+    for tile in &original.tiles {
+        for px in &mut tile.lock().pixels {
+            // the last row has no gradients
+            if px.position.y < 5 {
+                let dx = Spectrum::broadcast(px.position.x as Float);
+                px.add_gradients(dx, Spectrum::broadcast(1.0));
+                px.add_gradients(dx, Spectrum::broadcast(-2.0));
+            }
+        }
+    }
+
+    let restored = sensor();
+    let bytes = bincode::serialize(&original.checkpoint(0)).unwrap();
+    assert!(restored.restore(&bincode::deserialize(&bytes).unwrap()));
+
+    for (a, b) in original.tiles.iter().zip(&restored.tiles) {
+        let (mut a, mut b) = (a.lock(), b.lock());
+        for (a, b) in a.pixels.iter_mut().zip(&mut b.pixels) {
+            assert_eq!(a.gradients.len(), b.gradients.len());
+
+            // the restored number of samples keeps weighting further samples
+            a.add_gradients(Spectrum::broadcast(0.0), Spectrum::broadcast(3.0));
+            b.add_gradients(Spectrum::broadcast(0.0), Spectrum::broadcast(3.0));
+            for (a, b) in a.gradients.iter().zip(&b.gradients) {
+                assert_eq!(a.data, b.data);
+            }
+        }
+    }
 }
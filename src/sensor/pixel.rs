@@ -1,45 +1,314 @@
+use crate::sensor::aov::{Aov, AovSample};
 use crate::Float;
 use crate::Spectrum;
+use crate::Vector2;
 use color::{Color, IntSpectrum};
 use ultraviolet::UVec2;
 
+/// Counts the contributions discarded by the `sanitize` feature.
+#[cfg(feature = "sanitize")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Discarded {
+    /// The number of `NaN` or infinite contributions
+    pub non_finite: u64,
+    /// The number of negative contributions
+    pub negative: u64,
+}
+
+#[cfg(feature = "sanitize")]
+impl core::ops::AddAssign for Discarded {
+    fn add_assign(&mut self, rhs: Self) {
+        self.non_finite += rhs.non_finite;
+        self.negative += rhs.negative;
+    }
+}
+
+#[cfg(feature = "sanitize")]
+impl Discarded {
+    /// Returns the given value, or `0` if it is not finite or negative.
+    fn value(&mut self, value: Float) -> Float {
+        if !value.is_finite() {
+            self.non_finite += 1;
+            0.0
+        } else if value < 0.0 {
+            self.negative += 1;
+            0.0
+        } else {
+            value
+        }
+    }
+
+    /// Returns the given spectrum, black if any value is not finite or negative values clamped
+    /// to `0`.
+    fn spectrum(&mut self, spectrum: Spectrum) -> Spectrum {
+        if spectrum.data.iter().any(|v| !v.is_finite()) {
+            self.non_finite += 1;
+            Spectrum::broadcast(0.0)
+        } else if spectrum.data.iter().any(|v| *v < 0.0) {
+            self.negative += 1;
+            spectrum.clamp(0.0, Float::INFINITY)
+        } else {
+            spectrum
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct Pixel {
     pub position: UVec2,
     pub average: Spectrum,
+    /// The averages of the contributions of each light group (empty if disabled).
+    pub light_groups: Vec<Spectrum>,
+    /// The averages of the contributions of each light path expression (empty if disabled).
+    pub light_paths: Vec<Spectrum>,
     samples: IntSpectrum<{ Spectrum::size() }>,
+    /// The averages of the channels of all auxiliary outputs, concatenated (empty until added).
+    pub aovs: Vec<Float>,
+    /// The number of samples of the auxiliary outputs.
+    aov_samples: u32,
+    /// The averages of the finite differences towards the next pixel in `x` and `y` (empty
+    /// unless estimated).
+    pub gradients: Vec<Spectrum>,
+    /// The number of samples of the gradients.
+    gradient_samples: u32,
+    /// The contributions to arbitrary pixels not yet splatted onto the sensor.
+    splats: Vec<(Vector2, Spectrum)>,
+    /// The contributions discarded so far.
+    #[cfg(feature = "sanitize")]
+    pub discarded: Discarded,
 }
 
 impl Pixel {
-    pub fn new(position: UVec2) -> Self {
+    pub fn new(position: UVec2, light_groups: usize, light_paths: usize) -> Self {
         Self {
             position,
             average: Spectrum::broadcast(0.0),
+            light_groups: vec![Spectrum::broadcast(0.0); light_groups],
+            light_paths: vec![Spectrum::broadcast(0.0); light_paths],
             samples: IntSpectrum::broadcast(0),
+            aovs: Vec::new(),
+            aov_samples: 0,
+            gradients: Vec::new(),
+            gradient_samples: 0,
+            splats: Vec::new(),
+            #[cfg(feature = "sanitize")]
+            discarded: Discarded::default(),
         }
     }
 
+    /// Returns the number of samples per wavelength.
+    pub fn samples(&self) -> &IntSpectrum<{ Spectrum::size() }> {
+        &self.samples
+    }
+
+    /// Restores the pixel from previously accumulated data.
+    ///
+    /// # Arguments
+    /// * `average` - The average spectrum
+    /// * `samples` - The number of samples per wavelength
+    pub fn restore(&mut self, average: Spectrum, samples: IntSpectrum<{ Spectrum::size() }>) {
+        self.average = average;
+        self.samples = samples;
+    }
+
+    /// Returns the number of samples of the auxiliary outputs.
+    pub fn aov_samples(&self) -> u32 {
+        self.aov_samples
+    }
+
+    /// Restores the auxiliary outputs from previously accumulated data.
+    ///
+    /// # Arguments
+    /// * `aovs` - The averages of the channels
+    /// * `samples` - The number of samples
+    pub fn restore_aovs(&mut self, aovs: Vec<Float>, samples: u32) {
+        self.aovs = aovs;
+        self.aov_samples = samples;
+    }
+
+    /// Returns the number of samples of the gradients.
+    pub fn gradient_samples(&self) -> u32 {
+        self.gradient_samples
+    }
+
+    /// Restores the gradients from previously accumulated data.
+    ///
+    /// # Arguments
+    /// * `gradients` - The averages of the differences in `x` and `y` (empty if not estimated)
+    /// * `samples` - The number of samples
+    pub fn restore_gradients(&mut self, gradients: Vec<Spectrum>, samples: u32) {
+        self.gradients = gradients;
+        self.gradient_samples = samples;
+    }
+
     pub fn add(&mut self, spectrum: Spectrum) {
-        let mut avg = self.average * self.samples;
+        self.add_with_light_groups(spectrum, &[]);
+    }
+
+    /// Adds the given spectrum and the contributions of the light groups to it.
+    ///
+    /// # Arguments
+    /// * `spectrum` - The spectrum to add
+    /// * `light_groups` - The contributions of the light groups (missing ones count as black)
+    pub fn add_with_light_groups(&mut self, spectrum: Spectrum, light_groups: &[Spectrum]) {
+        self.add_with_aovs(spectrum, light_groups, &[]);
+    }
+
+    /// Adds the given spectrum and the contributions of the light groups and light path
+    /// expressions to it.
+    ///
+    /// # Arguments
+    /// * `spectrum` - The spectrum to add
+    /// * `light_groups` - The contributions of the light groups (missing ones count as black)
+    /// * `light_paths` - The contributions of the light paths (missing ones count as black)
+    pub fn add_with_aovs(
+        &mut self,
+        spectrum: Spectrum,
+        light_groups: &[Spectrum],
+        light_paths: &[Spectrum],
+    ) {
+        #[cfg(feature = "sanitize")]
+        let spectrum = self.discarded.spectrum(spectrum);
+
+        let before = self.samples;
+        let mut avg = self.average * before;
         avg += spectrum;
         self.samples.increment();
 
         self.average = avg / self.samples;
+
+        Self::accumulate(
+            &mut self.light_groups,
+            light_groups,
+            before,
+            self.samples,
+            #[cfg(feature = "sanitize")]
+            &mut self.discarded,
+        );
+        Self::accumulate(
+            &mut self.light_paths,
+            light_paths,
+            before,
+            self.samples,
+            #[cfg(feature = "sanitize")]
+            &mut self.discarded,
+        );
+    }
+
+    /// Adds the contributions to the averages of the buffers, with missing ones counting as black.
+    fn accumulate(
+        buffers: &mut [Spectrum],
+        contributions: &[Spectrum],
+        before: IntSpectrum<{ Spectrum::size() }>,
+        after: IntSpectrum<{ Spectrum::size() }>,
+        #[cfg(feature = "sanitize")] discarded: &mut Discarded,
+    ) {
+        for (i, buffer) in buffers.iter_mut().enumerate() {
+            let mut avg = *buffer * before;
+            if let Some(&contribution) = contributions.get(i) {
+                #[cfg(feature = "sanitize")]
+                let contribution = discarded.spectrum(contribution);
+
+                avg += contribution;
+            }
+
+            *buffer = avg / after;
+        }
+    }
+
+    /// Adds the auxiliary values of a sample to the averages of the given AOVs (or keeps the first
+    /// sample of AOVs that are not [averaged](Aov::is_averaged)).
+    ///
+    /// # Arguments
+    /// * `aovs` - The enabled AOVs, the same for all samples
+    /// * `sample` - The auxiliary values
+    pub fn add_aovs(&mut self, aovs: &[Aov], sample: &AovSample) {
+        let channels = aovs.iter().map(Aov::channels).sum();
+        if self.aovs.len() != channels {
+            self.aovs.resize(channels, 0.0);
+        }
+
+        let before = self.aov_samples as Float;
+        self.aov_samples += 1;
+        let after = self.aov_samples as Float;
+
+        let mut buffer = self.aovs.iter_mut();
+        for &aov in aovs {
+            for (channel, average) in (0..aov.channels()).zip(&mut buffer) {
+                if aov.is_averaged() {
+                    *average = (*average * before + sample.value(aov, channel)) / after;
+                } else if before == 0.0 {
+                    *average = sample.value(aov, channel);
+                }
+            }
+        }
+    }
+
+    /// Adds the finite differences of a sample towards the neighbouring pixels.
+    ///
+    /// # Arguments
+    /// * `dx` - The difference to the next pixel in `x`
+    /// * `dy` - The difference to the next pixel in `y`
+    pub fn add_gradients(&mut self, dx: Spectrum, dy: Spectrum) {
+        if self.gradients.is_empty() {
+            self.gradients = vec![Spectrum::broadcast(0.0); 2];
+        }
+
+        let before = self.gradient_samples as Float;
+        self.gradient_samples += 1;
+        let after = self.gradient_samples as Float;
+
+        for (average, difference) in self.gradients.iter_mut().zip([dx, dy].iter()) {
+            *average = (*average * before + *difference) / after;
+        }
+    }
+
+    /// Splats the given spectrum onto the pixel at the given raster position, which may be any
+    /// pixel of the sensor. The renderer moves the splats onto the sensor after integrating.
+    ///
+    /// # Arguments
+    /// * `position` - The position in raster space
+    /// * `spectrum` - The spectrum to splat
+    pub fn splat(&mut self, position: Vector2, spectrum: Spectrum) {
+        #[cfg(feature = "sanitize")]
+        let spectrum = self.discarded.spectrum(spectrum);
+
+        self.splats.push((position, spectrum));
+    }
+
+    /// Takes the pending splats of this pixel.
+    ///
+    /// # Returns
+    /// * The raster positions and spectra splatted since
+    pub fn take_splats(&mut self) -> Vec<(Vector2, Spectrum)> {
+        std::mem::take(&mut self.splats)
     }
 
     pub fn add_black(&mut self) {
-        let avg = self.average * self.samples;
+        let before = self.samples;
+        let avg = self.average * before;
         self.samples.increment();
         self.average = avg / self.samples;
+
+        for buffer in self.light_groups.iter_mut().chain(&mut self.light_paths) {
+            *buffer = *buffer * before / self.samples;
+        }
     }
 
     pub fn add_light_wave(&mut self, lambda: Float, light_wave_index: usize) {
+        #[cfg(feature = "sanitize")]
+        let lambda = self.discarded.value(lambda);
+
         let before = self.samples[light_wave_index];
         let after = self.samples[light_wave_index] + 1;
 
         self.average[light_wave_index] =
             (self.average[light_wave_index] * before as Float + lambda) / after as Float;
         self.samples[light_wave_index] = after;
+
+        for buffer in self.light_groups.iter_mut().chain(&mut self.light_paths) {
+            buffer[light_wave_index] *= before as Float / after as Float;
+        }
     }
 
     pub fn add_black_light_wave(&mut self, light_wave_index: usize) {
@@ -48,5 +317,38 @@ impl Pixel {
 
         self.average[light_wave_index] *= before as Float / after as Float;
         self.samples[light_wave_index] = after;
+
+        for buffer in self.light_groups.iter_mut().chain(&mut self.light_paths) {
+            buffer[light_wave_index] *= before as Float / after as Float;
+        }
     }
 }
+
+#[cfg(feature = "sanitize")]
+#[test]
+fn sanitize_discards_invalid_contributions() {
+    let mut pixel = Pixel::default();
+    pixel.add(Spectrum::broadcast(3.0));
+
+    // counts as a black sample
+    let mut nan = Spectrum::broadcast(1.0);
+    nan[0] = Float::NAN;
+    pixel.add(nan);
+
+    // clamped to zero
+    let mut negative = Spectrum::broadcast(3.0);
+    negative[1] = -1.0;
+    pixel.add(negative);
+
+    pixel.add_light_wave(Float::INFINITY, 2);
+    pixel.add_light_wave(-2.0, 3);
+
+    assert_eq!(pixel.discarded.non_finite, 2);
+    assert_eq!(pixel.discarded.negative, 2);
+
+    assert_eq!(pixel.average[0], 2.0);
+    assert_eq!(pixel.average[1], 1.0);
+    assert_eq!(pixel.average[2], 1.5);
+    assert_eq!(pixel.average[3], 1.5);
+    assert_eq!(pixel.average[4], 2.0);
+}
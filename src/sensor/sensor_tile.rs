@@ -1,22 +1,153 @@
+use crate::filters::Filter;
 use crate::sensor::bounds::UBounds2;
 use crate::sensor::pixel::Pixel;
+use crate::{Float, Spectrum, Vector2};
+use color::Color;
 use ultraviolet::UVec2;
 
+/// A filtered accumulation of samples, consisting of the weighted sum of all splatted spectra and
+/// the sum of their weights per wavelength.
+#[derive(Copy, Clone, Default)]
+pub struct Splat {
+    pub weighted_sum: Spectrum,
+    pub weight_sum: Spectrum,
+}
+
+impl Splat {
+    /// Adds the contribution of the other splat.
+    ///
+    /// # Arguments
+    /// * `other` - The splat to add
+    pub fn merge(&mut self, other: &Splat) {
+        self.weighted_sum += other.weighted_sum;
+        self.weight_sum += other.weight_sum;
+    }
+
+    /// Returns the filtered spectrum.
+    ///
+    /// # Returns
+    /// * The weighted sum normalized by the weights (black where no weights)
+    pub fn resolve(&self) -> Spectrum {
+        let mut spectrum = Spectrum::broadcast(0.0);
+        for i in 0..Spectrum::size() {
+            if self.weight_sum[i] != 0.0 {
+                spectrum[i] = self.weighted_sum[i] / self.weight_sum[i];
+            }
+        }
+
+        spectrum
+    }
+}
+
+/// A sensor tile owns the pixels inside its `bounds`.
+///
+/// If filter splatting is enabled, the pixels inside `bounds` contribute to pixels up to the
+/// filter radius away. The tile therefore accumulates splats over the larger `apron_bounds`,
+/// which overlap with neighbouring tiles and get merged by the sensor afterwards.
 pub struct SensorTile {
     pub bounds: UBounds2,
+    pub apron_bounds: UBounds2,
     pub pixels: Vec<Pixel>,
+    pub splats: Vec<Splat>,
 }
 
 impl SensorTile {
-    pub fn new(bounds: UBounds2) -> Self {
+    pub fn new(
+        bounds: UBounds2,
+        apron_bounds: Option<UBounds2>,
+        light_groups: usize,
+        light_paths: usize,
+    ) -> Self {
         let mut pixels = Vec::with_capacity(bounds.area());
         for y in bounds.min.y..bounds.max.y {
             for x in bounds.min.x..bounds.max.x {
                 let position = UVec2::new(x, y);
-                pixels.push(Pixel::new(position));
+                pixels.push(Pixel::new(position, light_groups, light_paths));
             }
         }
 
-        Self { bounds, pixels }
+        let (apron_bounds, splats) = match apron_bounds {
+            Some(apron) => (apron, vec![Splat::default(); apron.area()]),
+            None => (bounds, Vec::new()),
+        };
+
+        Self {
+            bounds,
+            apron_bounds,
+            pixels,
+            splats,
+        }
+    }
+
+    /// Replaces the splats by the estimates of the pixels splatted from their centers, weighted by
+    /// their number of samples per wavelength.
+    ///
+    /// # Arguments
+    /// * `filter` - The filter to weight the estimates with
+    pub fn splat_pixels(&mut self, filter: &dyn Filter) {
+        if self.splats.is_empty() {
+            return;
+        }
+
+        self.splats.iter_mut().for_each(|s| *s = Splat::default());
+
+        for i in 0..self.pixels.len() {
+            let px = &self.pixels[i];
+            let position = Vector2::new(px.position.x as Float, px.position.y as Float)
+                + Vector2::broadcast(0.5);
+            let samples = Spectrum::broadcast(1.0) * *px.samples();
+
+            self.splat(position, px.average * samples, samples, filter);
+        }
+    }
+
+    /// Splats the given spectrum onto all pixels within the filter radius.
+    ///
+    /// # Constraints
+    /// * `position` - Should be inside `bounds` (in continuous raster space, pixel centers at `+0.5`).
+    ///
+    /// # Arguments
+    /// * `position` - The position of the sample in raster space
+    /// * `spectrum` - The sampled spectrum
+    /// * `weight` - The weight of the spectrum per wavelength, e.g. its number of samples
+    /// * `filter` - The filter to weight the sample with
+    pub fn splat(
+        &mut self,
+        position: Vector2,
+        spectrum: Spectrum,
+        weight: Spectrum,
+        filter: &dyn Filter,
+    ) {
+        if self.splats.is_empty() {
+            return;
+        }
+
+        let radius = filter.radius();
+        let min = self.apron_bounds.min;
+        let max = self.apron_bounds.max;
+
+        // discrete pixel range whose centers lie within the radius, clamped to the apron
+        let x_start = ((position.x - 0.5 - radius.x).ceil().max(min.x as Float)) as u32;
+        let y_start = ((position.y - 0.5 - radius.y).ceil().max(min.y as Float)) as u32;
+        let x_end = ((position.x - 0.5 + radius.x).floor() as i64 + 1).min(max.x as i64);
+        let y_end = ((position.y - 0.5 + radius.y).floor() as i64 + 1).min(max.y as i64);
+
+        let width = (max.x - min.x) as usize;
+
+        for y in y_start as i64..y_end {
+            for x in x_start as i64..x_end {
+                let offset =
+                    Vector2::new(x as Float + 0.5 - position.x, y as Float + 0.5 - position.y);
+                let filtered = filter.evaluate(offset);
+
+                if filtered != 0.0 {
+                    let index =
+                        (x as usize - min.x as usize) + (y as usize - min.y as usize) * width;
+                    let splat = &mut self.splats[index];
+                    splat.weighted_sum += spectrum * filtered;
+                    splat.weight_sum += weight * filtered;
+                }
+            }
+        }
     }
 }
@@ -1,9 +1,11 @@
 use crate::camera::Camera;
 use crate::config::Config;
 use crate::integrator::Integrator;
+use crate::metadata::fnv1a;
 use crate::samplers::Sampler;
 use crate::scene::Scene;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Serialize};
+use std::path::Path;
 
 #[derive(Serialize, Deserialize)]
 pub struct Serialization {
@@ -13,3 +15,100 @@ pub struct Serialization {
     pub sampler: Sampler,
     pub scene: Scene,
 }
+
+impl Serialization {
+    /// Parses the given content of a scene file. Relative paths inside (e.g. of obj files) get
+    /// resolved against the directory of the scene file, independent of the working directory.
+    ///
+    /// # Arguments
+    /// * `content` - The content of the scene file
+    /// * `path` - The path of the scene file (`None` to resolve against the working directory)
+    ///
+    /// # Returns
+    /// * The parsed serialization, or an error if the integrator does not support the config
+    pub fn parse(content: &str, path: Option<&Path>) -> ron::Result<Self> {
+        let directory = path.and_then(Path::parent);
+
+        let serialization: Self =
+            geometry::with_base_directory(directory, || ron::from_str(content))?;
+        if serialization.config.light_group_aovs
+            && !serialization.integrator.supports_light_groups()
+        {
+            return Err(de::Error::custom(
+                "light_group_aovs are not supported by the integrator",
+            ));
+        }
+
+        Ok(serialization)
+    }
+
+    /// Hashes the deserialized content together with the crate version, identifying renders that
+    /// reproduce each other. The output file and the thread settings do not change the image and
+    /// get ignored.
+    ///
+    /// # Returns
+    /// * The [FNV-1a](fnv1a) hash
+    pub fn content_hash(&self) -> u64 {
+        let mut config = self.config.clone();
+        config.filename = None;
+        config.threads = None;
+        config.stack_size = None;
+
+        let parts = [
+            ron::to_string(&config),
+            ron::to_string(&self.camera),
+            ron::to_string(&self.integrator),
+            ron::to_string(&self.sampler),
+            ron::to_string(&self.scene),
+        ];
+
+        let mut content = env!("CARGO_PKG_VERSION").to_string();
+        for part in &parts {
+            content.push('\n');
+            content.push_str(part.as_deref().unwrap_or_default());
+        }
+
+        fnv1a(content.as_bytes())
+    }
+}
+
+#[test]
+fn light_group_aovs_require_support() {
+    let scene = |integrator: &str| {
+        format!(
+            r#"(
+                config: (
+                    filename: None,
+                    bounds: None,
+                    block_size: (x: 8, y: 8),
+                    passes: 1,
+                    threads: None,
+                    light_group_aovs: true,
+                ),
+                camera: {{
+                    "PerspectiveCamera": (
+                        Sampler: Random,
+                        Position: (x: 0, y: 0, z: 4),
+                        Target: (x: 0, y: 0, z: 0),
+                        Up: (x: 0, y: 1, z: 0),
+                        FovY: 40,
+                        Resolution: (x: 8, y: 8),
+                    ),
+                }},
+                integrator: {},
+                sampler: Random,
+                scene: (
+                    bounding_box: (
+                        min: (x: -1, y: -1, z: -1),
+                        max: (x: 1, y: 1, z: 1),
+                    ),
+                    objects: [],
+                ),
+            )"#,
+            integrator
+        )
+    };
+
+    assert!(Serialization::parse(&scene(r#"{"Path": (max_depth: 4)}"#), None).is_ok());
+    assert!(Serialization::parse(&scene(r#"{"Whitted": (max_depth: 4)}"#), None).is_err());
+}
@@ -0,0 +1,207 @@
+//! Statistics about a scene and the memory needed to render it.
+
+use crate::objects::SceneObject;
+use crate::scene::Scene;
+use crate::sensor::Sensor;
+use crate::Spectrum;
+use geometry::bvh::TreeStats;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::mem::size_of;
+
+/// Statistics about a scene, reported before rendering begins.
+#[derive(Clone, Debug)]
+pub struct SceneStats {
+    pub receivers: usize,
+    pub triangles: usize,
+    /// The number of emitters of each geometry kind
+    pub emitters: BTreeMap<&'static str, usize>,
+    pub bvh: TreeStats,
+    /// The number of spectra stored in the scene (emissions and ambient light)
+    pub spectra: usize,
+    pub sensor_pixels: usize,
+    pub sensor_bytes: usize,
+}
+
+impl SceneStats {
+    /// Collects the statistics of the given scene and sensor.
+    ///
+    /// # Arguments
+    /// * `scene` - The initialized scene
+    /// * `sensor` - The sensor to render into
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(scene: &Scene, sensor: &Sensor) -> Self {
+        let mut receivers = 0;
+        let mut emitters = BTreeMap::new();
+
+        for o in scene.objects() {
+            match o {
+                SceneObject::Emitter(e) => *emitters.entry(e.geometry().kind()).or_insert(0) += 1,
+                SceneObject::Receiver(_) => receivers += 1,
+            }
+        }
+
+        Self {
+            receivers,
+            triangles: scene.objects().iter().map(|o| o.triangle_count()).sum(),
+            emitters,
+            bvh: scene.bvh_stats(),
+            spectra: scene.emitters.len() + 1,
+            sensor_pixels: sensor.bounds.area(),
+            sensor_bytes: sensor.memory_usage(),
+        }
+    }
+}
+
+/// Formats a number of bytes with a binary unit prefix.
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+impl fmt::Display for SceneStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Scene")?;
+        writeln!(f, "  receivers:  {}", self.receivers)?;
+        writeln!(f, "  triangles:  {}", self.triangles)?;
+        writeln!(f, "  emitters:   {}", self.emitters.values().sum::<usize>())?;
+        for (kind, count) in &self.emitters {
+            writeln!(f, "    {:<10}{}", kind, count)?;
+        }
+
+        writeln!(f, "BVH")?;
+        writeln!(f, "  nodes:      {}", self.bvh.nodes)?;
+        writeln!(f, "  leaves:     {}", self.bvh.leaves)?;
+        writeln!(f, "  max depth:  {}", self.bvh.max_depth())?;
+        writeln!(f, "  leaves per depth:")?;
+        for (depth, count) in self.bvh.leaf_depths.iter().enumerate() {
+            if *count > 0 {
+                writeln!(f, "    {:>4}: {}", depth, count)?;
+            }
+        }
+        writeln!(f, "  leaves per number of objects:")?;
+        for (objects, count) in self.bvh.leaf_occupancy.iter().enumerate() {
+            if *count > 0 {
+                writeln!(f, "    {:>4}: {}", objects, count)?;
+            }
+        }
+
+        writeln!(f, "Memory")?;
+        writeln!(
+            f,
+            "  spectrum:   {} ({} in the scene emissions and ambient light)",
+            format_bytes(size_of::<Spectrum>()),
+            format_bytes(self.spectra * size_of::<Spectrum>())
+        )?;
+        write!(
+            f,
+            "  sensor:     {} ({} pixels)",
+            format_bytes(self.sensor_bytes),
+            self.sensor_pixels
+        )
+    }
+}
+
+#[test]
+fn count_objects_and_bvh_leaves() {
+    use crate::renderer::Renderer;
+
+    let source = r#"(
+        config: (
+            filename: None,
+            bounds: None,
+            block_size: (x: 4, y: 4),
+            passes: 1,
+            threads: Some(1),
+        ),
+        camera: {
+            "PerspectiveCamera": (
+                Sampler: NoOp,
+                Position: (x: 0, y: 0, z: 8),
+                Target: (x: 0, y: 0, z: 0),
+                Up: (x: 0, y: 1, z: 0),
+                FovY: 40,
+                Resolution: (x: 6, y: 4),
+            ),
+        },
+        integrator: {"Whitted": (max_depth: 1)},
+        sampler: NoOp,
+        scene: (
+            bounding_box: (
+                min: (x: -4, y: -4, z: -4),
+                max: (x: 4, y: 4, z: 4),
+            ),
+            objects: [
+                Receiver((
+                    geometry: {"Sphere": (center: (x: -2, y: 0, z: 0), radius: 1)},
+                    bsdf: (bxdfs: []),
+                )),
+                Receiver((
+                    geometry: {"Sphere": (center: (x: 2, y: 0, z: 0), radius: 1)},
+                    bsdf: (bxdfs: []),
+                )),
+                Emitter((
+                    geometry: {"Sphere": (center: (x: 0, y: 3, z: 0), radius: 0.5)},
+                    emission: Constant(1),
+                )),
+                Emitter((
+                    geometry: {"Point": ((x: 0, y: -3, z: 0))},
+                    emission: Constant(1),
+                )),
+                Emitter((
+                    geometry: {"Point": ((x: 0, y: 0, z: 3))},
+                    emission: Constant(1),
+                )),
+            ],
+        ),
+    )"#;
+
+    let stats = Renderer::new(ron::from_str(source).unwrap()).stats();
+
+    assert_eq!(stats.receivers, 2);
+    assert_eq!(stats.triangles, 0);
+    assert_eq!(stats.emitters.get("Point"), Some(&2));
+    assert_eq!(stats.emitters.values().sum::<usize>(), 3);
+    assert_eq!(stats.spectra, 4);
+    assert_eq!(stats.sensor_pixels, 24);
+
+    // every object ends up in exactly one leaf
+    assert_eq!(
+        stats.bvh.leaf_depths.iter().sum::<usize>(),
+        stats.bvh.leaves
+    );
+    assert_eq!(
+        stats.bvh.leaf_occupancy.iter().sum::<usize>(),
+        stats.bvh.leaves
+    );
+    let objects: usize = stats
+        .bvh
+        .leaf_occupancy
+        .iter()
+        .enumerate()
+        .map(|(n, c)| n * c)
+        .sum();
+    assert_eq!(objects, 5);
+
+    let report = stats.to_string();
+    assert!(report.contains("receivers:  2"));
+    assert!(report.contains("(24 pixels)"));
+}
+
+#[test]
+fn format_bytes_with_binary_prefixes() {
+    assert_eq!(format_bytes(512), "512.0 B");
+    assert_eq!(format_bytes(1536), "1.5 KiB");
+    assert_eq!(format_bytes(3 << 20), "3.0 MiB");
+    assert_eq!(format_bytes(5 << 40), "5120.0 GiB");
+}
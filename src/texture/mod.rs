@@ -0,0 +1,529 @@
+//! Textures varying material parameters over a surface by its texture coordinates.
+//!
+//! In scene files, a [Texture] is either written like a plain value (e.g. `Srgb([0.8, 0.1, 0.1])`
+//! for a constant spectrum), or as an `Image(file: "...")`,
+//! `Checkerboard(even: ..., odd: ..., scale: ...)` or procedural
+//! `Noise(pattern: ..., low: ..., high: ..., scale: ...)`.
+
+pub mod noise;
+
+use crate::texture::noise::Pattern;
+use crate::{Float, Spectrum, Vector2, Vector3};
+use color::{Color, Srgb};
+use serde::de::value::StringDeserializer;
+use serde::de::{self, DeserializeSeed, EnumAccess, IntoDeserializer, VariantAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Add, Mul};
+use std::path::Path;
+
+/// Returns the weights of the red, green and blue channel at the given wavelength (in µm),
+/// upsampling by smooth bands of the visible spectrum. The weights sum up to `1` at every
+/// wavelength, such that white stays white.
+pub(crate) fn rgb_weights(lambda: Float) -> [Float; 3] {
+    let blue_to_green = ((lambda - 0.48) / 0.02).clamp(0.0, 1.0);
+    let green_to_red = ((lambda - 0.57) / 0.02).clamp(0.0, 1.0);
+
+    [
+        green_to_red,
+        blue_to_green * (1.0 - green_to_red),
+        1.0 - blue_to_green,
+    ]
+}
+
+/// A value a [Texture] can vary.
+pub trait TextureValue: Copy + Add<Output = Self> + Mul<Float, Output = Self> {
+    /// Converts the given linear RGB color of an image.
+    fn from_rgb(rgb: [Float; 3]) -> Self;
+}
+
+impl TextureValue for Spectrum {
+    fn from_rgb(rgb: [Float; 3]) -> Self {
+        let mut spectrum = Spectrum::broadcast(0.0);
+        for i in 0..Spectrum::size() {
+            let [r, g, b] = rgb_weights(Spectrum::lambda_of_index(i));
+            spectrum[i] = r * rgb[0] + g * rgb[1] + b * rgb[2];
+        }
+
+        spectrum
+    }
+}
+
+impl TextureValue for Float {
+    /// Uses the luminance of the color.
+    fn from_rgb(rgb: [Float; 3]) -> Self {
+        0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2]
+    }
+}
+
+/// An image of linear RGB texels, looked up with bilinear interpolation and repeating outside of
+/// `[0, 1]`.
+#[derive(Clone)]
+pub struct ImageTexture {
+    file: String,
+    width: u32,
+    height: u32,
+    texels: Vec<[Float; 3]>,
+    /// The mean texel, used on surfaces without texture coordinates
+    average: [Float; 3],
+}
+
+impl ImageTexture {
+    /// Loads the given (gamma-encoded sRGB) image.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the image
+    ///
+    /// # Returns
+    /// * The image texture, or an error message
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let image = image::open(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?
+            .to_rgb8();
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return Err(format!("{}: empty image", path.display()));
+        }
+
+        let texels: Vec<[Float; 3]> = image
+            .pixels()
+            .map(|p| {
+                let srgb = Srgb::new([
+                    p[0] as Float / 255.0,
+                    p[1] as Float / 255.0,
+                    p[2] as Float / 255.0,
+                ]);
+                let linear = srgb.to_linear();
+
+                [linear[0], linear[1], linear[2]]
+            })
+            .collect();
+
+        let mut average = [0.0; 3];
+        for texel in &texels {
+            (0..3).for_each(|c| average[c] += texel[c]);
+        }
+        average.iter_mut().for_each(|c| *c /= texels.len() as Float);
+
+        Ok(Self {
+            file: path.display().to_string(),
+            width,
+            height,
+            texels,
+            average,
+        })
+    }
+
+    #[inline]
+    fn texel(&self, x: i64, y: i64) -> [Float; 3] {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+
+        self.texels[x + y * self.width as usize]
+    }
+
+    /// Looks up the given texture coordinates, with `v = 0` at the bottom of the image.
+    ///
+    /// # Arguments
+    /// * `uv` - The texture coordinates
+    ///
+    /// # Returns
+    /// * The bilinearly interpolated linear RGB color
+    pub fn lookup(&self, uv: Vector2) -> [Float; 3] {
+        let x = uv.x * self.width as Float - 0.5;
+        let y = (1.0 - uv.y) * self.height as Float - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let (a, b) = (self.texel(x0, y0), self.texel(x0 + 1, y0));
+        let (c, d) = (self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1));
+
+        let mut rgb = [0.0; 3];
+        for i in 0..3 {
+            let top = a[i] * (1.0 - tx) + b[i] * tx;
+            let bottom = c[i] * (1.0 - tx) + d[i] * tx;
+            rgb[i] = top * (1.0 - ty) + bottom * ty;
+        }
+
+        rgb
+    }
+}
+
+fn one() -> Float {
+    1.0
+}
+
+/// A surface point to evaluate textures at.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TexturePoint {
+    /// The texture coordinates (if any)
+    pub uv: Option<Vector2>,
+    /// The point in world space (if any)
+    pub point: Option<Vector3>,
+}
+
+impl TexturePoint {
+    /// Creates a new texture point.
+    ///
+    /// # Arguments
+    /// * `uv` - The texture coordinates (if any)
+    /// * `point` - The point in world space (if any)
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(uv: Option<Vector2>, point: Option<Vector3>) -> Self {
+        Self { uv, point }
+    }
+}
+
+/// A value varying over a surface by its texture coordinates.
+#[derive(Clone)]
+pub enum Texture<T> {
+    /// The same value everywhere
+    Constant(T),
+    /// An image, scaled by a factor
+    Image(ImageTexture, Float),
+    /// Alternating squares of two values, `scale` squares per unit of the texture coordinates
+    Checkerboard { even: T, odd: T, scale: Float },
+    /// A procedural pattern in world space, blending between `low` and `high` with features of
+    /// size `1 / scale`
+    Noise {
+        pattern: Pattern,
+        low: T,
+        high: T,
+        scale: Float,
+    },
+}
+
+impl<T: TextureValue> Texture<T> {
+    /// Returns whether this texture has the same value everywhere.
+    #[inline]
+    pub fn is_constant(&self) -> bool {
+        matches!(self, Texture::Constant(_))
+    }
+
+    /// Evaluates this texture at the given surface point.
+    ///
+    /// # Arguments
+    /// * `at` - The surface point. Without texture coordinates (or a point for
+    ///   [noise](Texture::Noise)), this results in the average value.
+    ///
+    /// # Returns
+    /// * The value
+    pub fn evaluate(&self, at: TexturePoint) -> T {
+        let uv = at.uv;
+
+        match self {
+            Texture::Constant(value) => *value,
+            Texture::Image(image, scale) => {
+                let rgb = match uv {
+                    Some(uv) => image.lookup(uv),
+                    None => image.average,
+                };
+
+                T::from_rgb(rgb) * *scale
+            }
+            Texture::Checkerboard { even, odd, scale } => match uv {
+                Some(uv) => {
+                    let checks = (uv.x * *scale).floor() as i64 + (uv.y * *scale).floor() as i64;
+
+                    if checks.rem_euclid(2) == 0 {
+                        *even
+                    } else {
+                        *odd
+                    }
+                }
+                None => (*even + *odd) * 0.5,
+            },
+            Texture::Noise {
+                pattern,
+                low,
+                high,
+                scale,
+            } => match at.point {
+                Some(point) => {
+                    let t = pattern.evaluate(point * *scale);
+
+                    *low * (1.0 - t) + *high * t
+                }
+                None => (*low + *high) * 0.5,
+            },
+        }
+    }
+}
+
+impl<T> Texture<T> {
+    /// Loads an image texture.
+    ///
+    /// # Arguments
+    /// * `file` - The image file as written, relative to the scene file
+    /// * `path` - The resolved path of the image file
+    /// * `scale` - The factor to scale the image by
+    ///
+    /// # Returns
+    /// * The texture, or an error message
+    pub fn image<P: AsRef<Path>>(file: String, path: P, scale: Float) -> Result<Self, String> {
+        let mut image = ImageTexture::load(path)?;
+        image.file = file;
+
+        Ok(Texture::Image(image, scale))
+    }
+}
+
+impl<T> From<T> for Texture<T> {
+    fn from(value: T) -> Self {
+        Texture::Constant(value)
+    }
+}
+
+/// The textual representation of the non-constant textures.
+#[derive(Serialize, Deserialize)]
+enum TextureSource<T> {
+    Image {
+        /// The image file, relative to the scene file
+        file: String,
+        #[serde(default = "one")]
+        scale: Float,
+    },
+    Checkerboard {
+        even: T,
+        odd: T,
+        #[serde(default = "one")]
+        scale: Float,
+    },
+    Noise {
+        pattern: Pattern,
+        low: T,
+        high: T,
+        #[serde(default = "one")]
+        scale: Float,
+    },
+}
+
+impl<T> TryFrom<TextureSource<T>> for Texture<T> {
+    type Error = String;
+
+    fn try_from(source: TextureSource<T>) -> Result<Self, Self::Error> {
+        let texture = match source {
+            TextureSource::Image { file, scale } => {
+                let path = geometry::resolve_asset(&file);
+
+                Texture::image(file, path, scale)?
+            }
+            TextureSource::Checkerboard { even, odd, scale } => {
+                Texture::Checkerboard { even, odd, scale }
+            }
+            TextureSource::Noise {
+                pattern,
+                low,
+                high,
+                scale,
+            } => Texture::Noise {
+                pattern,
+                low,
+                high,
+                scale,
+            },
+        };
+
+        Ok(texture)
+    }
+}
+
+impl<T: Serialize> Serialize for Texture<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Texture::Constant(value) => value.serialize(serializer),
+            Texture::Image(image, scale) => TextureSource::<&T>::Image {
+                file: image.file.clone(),
+                scale: *scale,
+            }
+            .serialize(serializer),
+            Texture::Checkerboard { even, odd, scale } => TextureSource::Checkerboard {
+                even,
+                odd,
+                scale: *scale,
+            }
+            .serialize(serializer),
+            Texture::Noise {
+                pattern,
+                low,
+                high,
+                scale,
+            } => TextureSource::Noise {
+                pattern: *pattern,
+                low,
+                high,
+                scale: *scale,
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+/// Deserializes a single, already identified enum variant, allowing to decide between the
+/// [texture sources](TextureSource) and the (enum) representation of the constant value by the
+/// variant name.
+struct VariantDeserializer<A> {
+    name: String,
+    variant: A,
+}
+
+impl<'de, A: VariantAccess<'de>> Deserializer<'de> for VariantDeserializer<A> {
+    type Error = A::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> EnumAccess<'de> for VariantDeserializer<A> {
+    type Error = A::Error;
+    type Variant = A;
+
+    fn variant_seed<S>(self, seed: S) -> Result<(S::Value, Self::Variant), Self::Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        let name: StringDeserializer<A::Error> = self.name.into_deserializer();
+
+        Ok((seed.deserialize(name)?, self.variant))
+    }
+}
+
+/// The name of an enum variant, deserialized as an identifier (as opposed to a string).
+struct VariantName(String);
+
+impl<'de> Deserialize<'de> for VariantName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct VariantNameVisitor;
+
+        impl<'de> Visitor<'de> for VariantNameVisitor {
+            type Value = VariantName;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a variant name")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(VariantName(v.to_string()))
+            }
+        }
+
+        deserializer.deserialize_identifier(VariantNameVisitor)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Texture<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TextureVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for TextureVisitor<T> {
+            type Value = Texture<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a value, an Image, a Checkerboard or a Noise texture")
+            }
+
+            fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+            where
+                A: EnumAccess<'de>,
+            {
+                let (VariantName(name), variant) = data.variant()?;
+                let is_source = matches!(name.as_str(), "Image" | "Checkerboard" | "Noise");
+                let deserializer = VariantDeserializer { name, variant };
+
+                if is_source {
+                    let source = TextureSource::deserialize(deserializer)?;
+                    Texture::try_from(source).map_err(de::Error::custom)
+                } else {
+                    T::deserialize(deserializer).map(Texture::Constant)
+                }
+            }
+        }
+
+        const VARIANTS: &[&str] = &[
+            "Image",
+            "Checkerboard",
+            "Noise",
+            "Srgb",
+            "Xyz",
+            "Spectrum",
+            "Color",
+            "MulColor",
+            "Constant",
+        ];
+
+        deserializer.deserialize_enum("Texture", VARIANTS, TextureVisitor(PhantomData))
+    }
+}
+
+#[test]
+fn checkerboard_alternates() {
+    let texture: Texture<Float> = Texture::Checkerboard {
+        even: 0.0,
+        odd: 1.0,
+        scale: 2.0,
+    };
+
+    let at = |u, v| TexturePoint::new(Some(Vector2::new(u, v)), None);
+
+    assert_eq!(texture.evaluate(at(0.1, 0.1)), 0.0);
+    assert_eq!(texture.evaluate(at(0.6, 0.1)), 1.0);
+    assert_eq!(texture.evaluate(at(0.6, 0.6)), 0.0);
+    assert_eq!(texture.evaluate(TexturePoint::default()), 0.5);
+}
+
+#[test]
+fn deserialize_constant_and_checkerboard() {
+    let constant: Texture<Spectrum> = ron::from_str("Constant(0.5)").unwrap();
+    assert_eq!(constant.evaluate(TexturePoint::default())[0], 0.5);
+
+    let checkerboard: Texture<Spectrum> =
+        ron::from_str("Checkerboard(even: Constant(0.0), odd: Constant(1.0))").unwrap();
+    let at = TexturePoint::new(Some(Vector2::new(1.5, 0.5)), None);
+    assert_eq!(checkerboard.evaluate(at)[0], 1.0);
+}
+
+#[test]
+fn deserialize_noise() {
+    let noise: Texture<Float> =
+        ron::from_str("Noise(pattern: Marble(octaves: 3), low: 0.2, high: 0.6, scale: 4.0)")
+            .unwrap();
+
+    assert!((noise.evaluate(TexturePoint::default()) - 0.4).abs() < 1e-6);
+    let value = noise.evaluate(TexturePoint::new(None, Some(Vector3::new(0.3, 0.1, 0.7))));
+    assert!((0.2..=0.6).contains(&value));
+}
+
+#[test]
+fn deserialize_scene_with_constant_reflectances() {
+    let content = include_str!("../../scenes/cornell_oren_nayar.ron");
+
+    crate::serialization::Serialization::parse(content, None).unwrap();
+}
@@ -0,0 +1,255 @@
+//! Procedural noise, evaluated at points in space instead of texture coordinates.
+
+use crate::{Float, Vector3};
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "f64"))]
+use std::f32::consts::PI;
+#[cfg(feature = "f64")]
+use std::f64::consts::PI;
+
+fn default_octaves() -> u32 {
+    4
+}
+
+/// A procedural pattern, mapping points to values in `[0, 1]`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Pattern {
+    /// Fractal Brownian motion, i.e. octaves of Perlin noise with halving amplitudes
+    Perlin {
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+    },
+    /// The distance to the nearest of randomly scattered feature points, resembling cells
+    Worley,
+    /// Veins of a sine wave along the x-axis, perturbed by turbulence
+    Marble {
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+    },
+    /// Concentric rings around the y-axis, perturbed by noise
+    Wood,
+}
+
+impl Pattern {
+    /// Evaluates this pattern at the given point.
+    ///
+    /// # Arguments
+    /// * `p` - The point, with features roughly of unit size
+    ///
+    /// # Returns
+    /// * The value in `[0, 1]`
+    pub fn evaluate(&self, p: Vector3) -> Float {
+        let value = match *self {
+            Pattern::Perlin { octaves } => 0.5 + 0.5 * fbm(p, octaves),
+            Pattern::Worley => worley(p),
+            Pattern::Marble { octaves } => {
+                0.5 + 0.5 * (PI * (p.x + 4.0 * turbulence(p, octaves))).sin()
+            }
+            Pattern::Wood => {
+                let rings = (p.x * p.x + p.z * p.z).sqrt() + 0.5 * fbm(p, 2);
+
+                rings - rings.floor()
+            }
+        };
+
+        value.clamp(0.0, 1.0)
+    }
+}
+
+/// Hashes the given lattice point.
+#[inline]
+fn hash(x: i32, y: i32, z: i32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^ (h >> 16)
+}
+
+/// Returns a random value in `[0, 1)` of the given hash and channel.
+#[inline]
+fn hash_to_unit(h: u32, channel: u32) -> Float {
+    let h = hash(h as i32, channel as i32, 0x5bd1_e995);
+
+    (h >> 8) as Float / (1u32 << 24) as Float
+}
+
+/// The dot product of the pseudo-random gradient of the hash (one of the 12 edge directions of a
+/// cube) with the given offset, like improved Perlin noise.
+#[inline]
+fn gradient(h: u32, x: Float, y: Float, z: Float) -> Float {
+    match h % 12 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
+
+#[inline]
+fn fade(t: Float) -> Float {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+#[inline]
+fn lerp(t: Float, a: Float, b: Float) -> Float {
+    a + t * (b - a)
+}
+
+/// Returns Perlin (gradient) noise at the given point.
+///
+/// # Arguments
+/// * `p` - The point
+///
+/// # Returns
+/// * The noise, roughly in `[-1, 1]` and `0` at the integer lattice points
+pub fn perlin(p: Vector3) -> Float {
+    let (fx, fy, fz) = (p.x.floor(), p.y.floor(), p.z.floor());
+    let (x, y, z) = (fx as i32, fy as i32, fz as i32);
+    let (dx, dy, dz) = (p.x - fx, p.y - fy, p.z - fz);
+    let (u, v, w) = (fade(dx), fade(dy), fade(dz));
+
+    let corner = |i: i32, j: i32, k: i32| {
+        gradient(
+            hash(x + i, y + j, z + k),
+            dx - i as Float,
+            dy - j as Float,
+            dz - k as Float,
+        )
+    };
+
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+            lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+        ),
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+            lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+        ),
+    )
+}
+
+/// Returns fractal Brownian motion, summing octaves of [Perlin noise](perlin) with doubling
+/// frequency and halving amplitude.
+///
+/// # Arguments
+/// * `p` - The point
+/// * `octaves` - The number of octaves
+///
+/// # Returns
+/// * The noise, roughly in `[-1, 1]`
+pub fn fbm(p: Vector3, octaves: u32) -> Float {
+    let mut sum = 0.0;
+    let mut norm = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+
+    for _ in 0..octaves.max(1) {
+        sum += amplitude * perlin(p * frequency);
+        norm += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    sum / norm
+}
+
+/// Returns turbulence, i.e. [fractal Brownian motion](fbm) of the absolute noise.
+///
+/// # Arguments
+/// * `p` - The point
+/// * `octaves` - The number of octaves
+///
+/// # Returns
+/// * The turbulence, roughly in `[0, 1]`
+pub fn turbulence(p: Vector3, octaves: u32) -> Float {
+    let mut sum = 0.0;
+    let mut norm = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+
+    for _ in 0..octaves.max(1) {
+        sum += amplitude * perlin(p * frequency).abs();
+        norm += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    sum / norm
+}
+
+/// Returns Worley (cellular) noise, the distance to the nearest feature point, with one feature
+/// point randomly placed in each unit cell.
+///
+/// # Arguments
+/// * `p` - The point
+///
+/// # Returns
+/// * The distance, clamped to `[0, 1]`
+pub fn worley(p: Vector3) -> Float {
+    let (x, y, z) = (p.x.floor() as i32, p.y.floor() as i32, p.z.floor() as i32);
+    let mut nearest = Float::INFINITY;
+
+    for i in -1..=1 {
+        for j in -1..=1 {
+            for k in -1..=1 {
+                let h = hash(x + i, y + j, z + k);
+                let feature = Vector3::new(
+                    (x + i) as Float + hash_to_unit(h, 0),
+                    (y + j) as Float + hash_to_unit(h, 1),
+                    (z + k) as Float + hash_to_unit(h, 2),
+                );
+
+                nearest = nearest.min((feature - p).mag_sq());
+            }
+        }
+    }
+
+    nearest.sqrt().min(1.0)
+}
+
+#[test]
+fn perlin_vanishes_on_lattice() {
+    for p in &[
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(3.0, -2.0, 7.0),
+        Vector3::new(-5.0, 1.0, -1.0),
+    ] {
+        assert_eq!(perlin(*p), 0.0);
+    }
+}
+
+#[test]
+fn patterns_stay_in_unit_range() {
+    let patterns = [
+        Pattern::Perlin { octaves: 4 },
+        Pattern::Worley,
+        Pattern::Marble { octaves: 4 },
+        Pattern::Wood,
+    ];
+
+    for i in 0..1000 {
+        let t = i as Float * 0.173;
+        let p = Vector3::new(t.sin() * 10.0, t * 0.37, t.cos() * 5.0 - t * 0.11);
+
+        for pattern in &patterns {
+            let value = pattern.evaluate(p);
+            assert!((0.0..=1.0).contains(&value), "{:?}: {}", pattern, value);
+        }
+    }
+}
@@ -1,13 +1,53 @@
 use crate::renderer::Renderer;
+use crate::{Float, Vector3};
 use bitflags::_core::time::Duration;
 use show_image::error::{CreateWindowError, InvalidWindowId};
 use show_image::event::VirtualKeyCode;
 use show_image::{create_window, event, WindowOptions, WindowProxy};
 use std::thread;
 
+/// The distance the camera moves per key press.
+const MOVE_STEP: Float = 0.1;
+
+/// Returns the offset of the camera along its right, up and viewing direction moved by the given
+/// key: `W`/`S` move forward/backward, `A`/`D` left/right and `Q`/`E` down/up.
+fn movement(key: VirtualKeyCode) -> Option<Vector3> {
+    let direction = match key {
+        VirtualKeyCode::W => Vector3::unit_z(),
+        VirtualKeyCode::S => -Vector3::unit_z(),
+        VirtualKeyCode::D => Vector3::unit_x(),
+        VirtualKeyCode::A => -Vector3::unit_x(),
+        VirtualKeyCode::E => Vector3::unit_y(),
+        VirtualKeyCode::Q => -Vector3::unit_y(),
+        _ => return None,
+    };
+
+    Some(direction * MOVE_STEP)
+}
+
+/// The factor the multiplier of the selected light group changes by per key press.
+const LIGHT_GROUP_STEP: Float = 2.0;
+
+/// Returns the factor the multiplier of the selected light group gets scaled by with the given
+/// key: `Up` brightens and `Down` dims the group.
+fn light_group_scale(key: VirtualKeyCode) -> Option<Float> {
+    match key {
+        VirtualKeyCode::Up => Some(LIGHT_GROUP_STEP),
+        VirtualKeyCode::Down => Some(1.0 / LIGHT_GROUP_STEP),
+        _ => None,
+    }
+}
+
+/// A window showing the progress of a render. While rendering, the camera can be
+/// [moved](movement), reusing the samples of pixels that still see the same surface.
+///
+/// `Tab` selects the next light group of the scene, whose multiplier can then be
+/// [scaled](light_group_scale), starting the render over.
 pub struct RenderWindow<'a> {
     window: WindowProxy,
     renderer: &'a mut Renderer,
+    /// The index of the selected light group
+    light_group: usize,
 }
 
 impl<'a> RenderWindow<'a> {
@@ -26,11 +66,15 @@ impl<'a> RenderWindow<'a> {
 
         let window = create_window(name, options)?;
 
-        Ok(Self { window, renderer })
+        Ok(Self {
+            window,
+            renderer,
+            light_group: 0,
+        })
     }
 
     pub fn render(&mut self) -> Result<(), InvalidWindowId> {
-        let render_job = self.renderer.render();
+        let mut render_job = self.renderer.render();
 
         let mut early_stop = false;
 
@@ -51,7 +95,54 @@ impl<'a> RenderWindow<'a> {
                                     }
                                     break;
                                 }
-                                _ => {}
+                                VirtualKeyCode::Tab => {
+                                    let groups = self.renderer.light_group_multipliers();
+                                    if !groups.is_empty() {
+                                        self.light_group = (self.light_group + 1) % groups.len();
+                                        let (group, multiplier) = groups[self.light_group];
+                                        println!("Light group {}: {}", group, multiplier);
+                                    }
+                                }
+                                key => {
+                                    if let Some(scale) = light_group_scale(key) {
+                                        let (group, multiplier) = match self
+                                            .renderer
+                                            .light_group_multipliers()
+                                            .get(self.light_group)
+                                        {
+                                            Some(&(group, multiplier)) => {
+                                                (group.to_string(), multiplier * scale)
+                                            }
+                                            None => continue,
+                                        };
+
+                                        render_job.stop().expect("Could not stop render threads");
+                                        if let Err(err) = self
+                                            .renderer
+                                            .set_light_group_multiplier(&group, multiplier)
+                                        {
+                                            eprintln!("{}", err);
+                                        }
+                                        println!("Light group {}: {}", group, multiplier);
+                                        render_job = self.renderer.render();
+                                        break;
+                                    }
+
+                                    if let Some(offset) = movement(key) {
+                                        render_job.stop().expect("Could not stop render threads");
+                                        if let Err(err) = self.renderer.move_camera(offset) {
+                                            eprintln!("{}", err);
+                                        }
+                                        render_job = self.renderer.render();
+
+                                        let image = self.renderer.get_image_u8();
+                                        if let Err(err) = self.window.set_image("Rendering", image)
+                                        {
+                                            eprintln!("{}\nSkipping this image!", err);
+                                        }
+                                        break;
+                                    }
+                                }
                             }
                         }
                     }
@@ -0,0 +1,432 @@
+//! Piecewise-constant distributions with precomputed CDFs, allowing to importance sample tabulated
+//! functions like environment maps, textures or triangle areas, and alias tables sampling discrete
+//! weights in constant time.
+
+use crate::*;
+
+/// A piecewise-constant distribution over `[0, 1)`, defined by a tabulated (non-negative)
+/// function.
+#[derive(Clone, Debug)]
+pub struct Distribution1D {
+    func: Vec<Float>,
+    cdf: Vec<Float>,
+    integral: Float,
+}
+
+impl Distribution1D {
+    /// Creates a new distribution proportional to the given function values.
+    ///
+    /// If the function integrates to `0`, the distribution falls back to a uniform one.
+    ///
+    /// # Constraints
+    /// * `func` - Should not be empty. All values should be finite and non-negative.
+    ///
+    /// # Arguments
+    /// * `func` - The function values of equally sized segments
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(func: &[Float]) -> Self {
+        debug_assert!(!func.is_empty());
+        debug_assert!(func.iter().all(|f| f.is_finite() && *f >= 0.0));
+
+        let n = func.len();
+        let mut cdf = Vec::with_capacity(n + 1);
+        cdf.push(0.0);
+        for (i, f) in func.iter().enumerate() {
+            cdf.push(cdf[i] + f / n as Float);
+        }
+
+        let integral = cdf[n];
+        if integral > 0.0 {
+            cdf.iter_mut().for_each(|c| *c /= integral);
+        } else {
+            cdf.iter_mut()
+                .enumerate()
+                .for_each(|(i, c)| *c = i as Float / n as Float);
+        }
+
+        Self {
+            func: func.to_vec(),
+            cdf,
+            integral,
+        }
+    }
+
+    /// Returns the number of segments.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Returns the integral of the function over `[0, 1)`.
+    #[inline]
+    pub fn integral(&self) -> Float {
+        self.integral
+    }
+
+    /// Returns the index of the segment whose CDF range contains the given value.
+    fn find_segment(&self, u: Float) -> usize {
+        // the first cdf entry greater than `u`, minus one
+        let index = self.cdf.partition_point(|c| *c <= u);
+
+        index.saturating_sub(1).min(self.count() - 1)
+    }
+
+    /// Samples a continuous value.
+    ///
+    /// # Constraints
+    /// * `u` - Should be within `[0, 1)`.
+    ///
+    /// # Arguments
+    /// * `u` - The uniform random number
+    ///
+    /// # Returns
+    /// * The sampled value within `[0, 1)`
+    /// * The pdf of the value
+    /// * The index of the segment containing the value
+    pub fn sample_continuous(&self, u: Float) -> (Float, Float, usize) {
+        let offset = self.find_segment(u);
+
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0.0 {
+            (u - self.cdf[offset]) / width
+        } else {
+            0.0
+        };
+
+        let x = (offset as Float + du) / self.count() as Float;
+
+        (
+            x.min(1.0 - Float::EPSILON),
+            self.segment_pdf(offset),
+            offset,
+        )
+    }
+
+    /// Samples a discrete segment.
+    ///
+    /// # Constraints
+    /// * `u` - Should be within `[0, 1)`.
+    ///
+    /// # Arguments
+    /// * `u` - The uniform random number
+    ///
+    /// # Returns
+    /// * The index of the sampled segment
+    /// * The probability of the segment
+    pub fn sample_discrete(&self, u: Float) -> (usize, Float) {
+        let offset = self.find_segment(u);
+
+        (offset, self.pmf(offset))
+    }
+
+    fn segment_pdf(&self, index: usize) -> Float {
+        if self.integral > 0.0 {
+            self.func[index] / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns the pdf of [sampling](Distribution1D::sample_continuous) the given value.
+    ///
+    /// # Arguments
+    /// * `x` - The value within `[0, 1)`
+    ///
+    /// # Returns
+    /// * The pdf
+    pub fn pdf(&self, x: Float) -> Float {
+        if !(0.0..1.0).contains(&x) {
+            return 0.0;
+        }
+
+        let index = ((x * self.count() as Float) as usize).min(self.count() - 1);
+
+        self.segment_pdf(index)
+    }
+
+    /// Returns the probability of [sampling](Distribution1D::sample_discrete) the given segment.
+    ///
+    /// # Arguments
+    /// * `index` - The index of the segment
+    ///
+    /// # Returns
+    /// * The probability
+    pub fn pmf(&self, index: usize) -> Float {
+        self.cdf
+            .get(index + 1)
+            .map_or(0.0, |next| next - self.cdf[index])
+    }
+
+    /// Inverts [sample_continuous](Distribution1D::sample_continuous), returning the random number
+    /// sampling the given value, e.g. to reuse samples across strategies.
+    ///
+    /// # Arguments
+    /// * `x` - The value within `[0, 1)`
+    ///
+    /// # Returns
+    /// * The random number within `[0, 1)`
+    pub fn invert(&self, x: Float) -> Float {
+        let scaled = x.max(0.0) * self.count() as Float;
+        let index = (scaled as usize).min(self.count() - 1);
+        let du = scaled - index as Float;
+
+        let u = self.cdf[index] + du * (self.cdf[index + 1] - self.cdf[index]);
+
+        u.min(1.0 - Float::EPSILON)
+    }
+}
+
+/// A piecewise-constant distribution over `[0, 1)²`, defined by a tabulated (non-negative)
+/// function in row-major order. It samples a row by the marginal distribution and a column
+/// within the row by its conditional distribution.
+#[derive(Clone, Debug)]
+pub struct Distribution2D {
+    conditionals: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// Creates a new distribution proportional to the given function values.
+    ///
+    /// # Constraints
+    /// * `func` - Should contain `width * height` values, all finite and non-negative.
+    /// * `width` - Should be greater than `0`.
+    /// * `height` - Should be greater than `0`.
+    ///
+    /// # Arguments
+    /// * `func` - The function values in row-major order
+    /// * `width` - The number of columns
+    /// * `height` - The number of rows
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(func: &[Float], width: usize, height: usize) -> Self {
+        debug_assert!(width > 0 && height > 0);
+        debug_assert_eq!(func.len(), width * height);
+
+        let conditionals: Vec<_> = func.chunks_exact(width).map(Distribution1D::new).collect();
+        let integrals: Vec<_> = conditionals.iter().map(Distribution1D::integral).collect();
+        let marginal = Distribution1D::new(&integrals);
+
+        Self {
+            conditionals,
+            marginal,
+        }
+    }
+
+    /// Returns the integral of the function over `[0, 1)²`.
+    #[inline]
+    pub fn integral(&self) -> Float {
+        self.marginal.integral()
+    }
+
+    /// Samples a continuous point.
+    ///
+    /// # Constraints
+    /// * `u` - Should be within `[0, 1)²`.
+    ///
+    /// # Arguments
+    /// * `u` - The uniform random numbers
+    ///
+    /// # Returns
+    /// * The sampled point `(x, y)` within `[0, 1)²`
+    /// * The pdf of the point
+    pub fn sample_continuous(&self, u: (Float, Float)) -> ((Float, Float), Float) {
+        let (y, pdf_y, row) = self.marginal.sample_continuous(u.1);
+        let (x, pdf_x, _) = self.conditionals[row].sample_continuous(u.0);
+
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    /// Returns the pdf of [sampling](Distribution2D::sample_continuous) the given point.
+    ///
+    /// # Arguments
+    /// * `p` - The point `(x, y)` within `[0, 1)²`
+    ///
+    /// # Returns
+    /// * The pdf
+    pub fn pdf(&self, p: (Float, Float)) -> Float {
+        if !(0.0..1.0).contains(&p.1) {
+            return 0.0;
+        }
+
+        let row = self.row(p.1);
+
+        self.marginal.pdf(p.1) * self.conditionals[row].pdf(p.0)
+    }
+
+    /// Inverts [sample_continuous](Distribution2D::sample_continuous), returning the random
+    /// numbers sampling the given point.
+    ///
+    /// # Arguments
+    /// * `p` - The point `(x, y)` within `[0, 1)²`
+    ///
+    /// # Returns
+    /// * The random numbers within `[0, 1)²`
+    pub fn invert(&self, p: (Float, Float)) -> (Float, Float) {
+        let row = self.row(p.1);
+
+        (
+            self.conditionals[row].invert(p.0),
+            self.marginal.invert(p.1),
+        )
+    }
+
+    fn row(&self, y: Float) -> usize {
+        let height = self.conditionals.len();
+
+        ((y.max(0.0) * height as Float) as usize).min(height - 1)
+    }
+}
+
+/// An alias table over discrete weights, sampling an index in constant time instead of searching
+/// a CDF, e.g. the faces of a mesh by their area.
+///
+/// Each of the `n` equally likely bins keeps its own index with some probability and otherwise
+/// takes its alias, which holds the excess of another index.
+#[derive(Clone, Debug, Default)]
+pub struct AliasTable {
+    /// The probability of each bin to keep its own index
+    probabilities: Vec<Float>,
+    /// The index each bin takes otherwise
+    aliases: Vec<u32>,
+    /// The probability of sampling each index
+    pmfs: Vec<Float>,
+}
+
+impl AliasTable {
+    /// Creates a new alias table sampling indices proportionally to the given weights.
+    ///
+    /// If the weights sum up to `0`, the table falls back to sampling uniformly.
+    ///
+    /// # Constraints
+    /// * `weights` - All values should be finite and non-negative.
+    ///
+    /// # Arguments
+    /// * `weights` - The weights of the indices
+    ///
+    /// # Returns
+    /// * Self
+    pub fn new(weights: &[Float]) -> Self {
+        debug_assert!(weights.iter().all(|w| w.is_finite() && *w >= 0.0));
+
+        let n = weights.len();
+        let sum: Float = weights.iter().sum();
+        let pmfs: Vec<Float> = if sum > 0.0 {
+            weights.iter().map(|w| w / sum).collect()
+        } else {
+            vec![1.0 / n as Float; n]
+        };
+
+        // Vose's method: fill the underfull bins with the excess of the overfull ones
+        let mut scaled: Vec<Float> = pmfs.iter().map(|p| p * n as Float).collect();
+        let mut probabilities = vec![1.0; n];
+        let mut aliases: Vec<u32> = (0..n as u32).collect();
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..n).partition(|&i| scaled[i] < 1.0);
+
+        while let (Some(s), Some(&l)) = (small.pop(), large.last()) {
+            probabilities[s] = scaled[s];
+            aliases[s] = l as u32;
+
+            scaled[l] -= 1.0 - scaled[s];
+            if scaled[l] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+        // the remaining bins are full up to rounding errors
+
+        Self {
+            probabilities,
+            aliases,
+            pmfs,
+        }
+    }
+
+    /// Returns the number of indices.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.pmfs.len()
+    }
+
+    /// Samples an index.
+    ///
+    /// # Constraints
+    /// * `u` - Should be within `[0, 1)`.
+    /// * `self` - Should not be empty.
+    ///
+    /// # Arguments
+    /// * `u` - The uniform random number
+    ///
+    /// # Returns
+    /// * The sampled index
+    /// * The probability of the index
+    /// * The random number, rescaled to `[0, 1)` to be reused
+    pub fn sample(&self, u: Float) -> (usize, Float, Float) {
+        debug_assert!(self.count() > 0);
+
+        let scaled = u * self.count() as Float;
+        let bin = (scaled as usize).min(self.count() - 1);
+        let u = scaled - bin as Float;
+
+        let probability = self.probabilities[bin];
+        let (index, u) = if u < probability {
+            (bin, u / probability)
+        } else {
+            (
+                self.aliases[bin] as usize,
+                (u - probability) / (1.0 - probability),
+            )
+        };
+
+        (index, self.pmfs[index], u.min(1.0 - Float::EPSILON))
+    }
+
+    /// Returns the probability of [sampling](AliasTable::sample) the given index.
+    ///
+    /// # Arguments
+    /// * `index` - The index
+    ///
+    /// # Returns
+    /// * The probability
+    pub fn pmf(&self, index: usize) -> Float {
+        self.pmfs.get(index).copied().unwrap_or(0.0)
+    }
+}
+
+#[test]
+fn alias_table_matches_weights() {
+    let weights = [1.0, 3.0, 0.0, 2.0, 4.0];
+    let table = AliasTable::new(&weights);
+
+    let n = 10_000;
+    let mut counts = [0; 5];
+    for i in 0..n {
+        let (index, pmf, u) = table.sample((i as Float + 0.5) / n as Float);
+
+        assert_eq!(pmf, table.pmf(index));
+        assert!((0.0..1.0).contains(&u));
+        counts[index] += 1;
+    }
+
+    for (count, weight) in counts.iter().zip(&weights) {
+        assert!((*count as Float / n as Float - weight / 10.0).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn inverts_samples() {
+    let distribution = Distribution2D::new(&[1.0, 3.0, 0.0, 2.0, 4.0, 6.0], 3, 2);
+
+    for &u in &[(0.1, 0.2), (0.5, 0.5), (0.9, 0.7)] {
+        let (p, pdf) = distribution.sample_continuous(u);
+        let inverted = distribution.invert(p);
+
+        assert!((pdf - distribution.pdf(p)).abs() < 1e-4);
+        assert!((inverted.0 - u.0).abs() < 1e-4);
+        assert!((inverted.1 - u.1).abs() < 1e-4);
+    }
+}
@@ -1,3 +1,4 @@
+pub mod distribution;
 pub mod floats;
 pub mod math;
 